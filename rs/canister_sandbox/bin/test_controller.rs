@@ -17,6 +17,20 @@ impl ControllerService for DummyControllerService {
         unimplemented!();
     }
 
+    fn execution_output_chunk(
+        &self,
+        _req: ctlsvc::ExecutionOutputChunkRequest,
+    ) -> rpc::Call<ctlsvc::ExecutionOutputChunkReply> {
+        unimplemented!();
+    }
+
+    fn trap_backtrace(
+        &self,
+        _req: ctlsvc::TrapBacktraceRequest,
+    ) -> rpc::Call<ctlsvc::TrapBacktraceReply> {
+        unimplemented!();
+    }
+
     fn execution_paused(
         &self,
         _req: ctlsvc::ExecutionPausedRequest,
@@ -27,6 +41,10 @@ impl ControllerService for DummyControllerService {
     fn log_via_replica(&self, _req: LogRequest) -> rpc::Call<()> {
         unimplemented!();
     }
+
+    fn heartbeat(&self, _req: ctlsvc::HeartbeatRequest) -> rpc::Call<ctlsvc::HeartbeatReply> {
+        rpc::Call::new_resolved(Ok(ctlsvc::HeartbeatReply {}))
+    }
 }
 
 fn main() {
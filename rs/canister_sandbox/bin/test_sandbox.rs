@@ -22,6 +22,15 @@ struct DummySandboxService {}
 
 /// RPC interface exposed by sandbox process.
 impl sandbox_service::SandboxService for DummySandboxService {
+    fn handshake(&self, req: sbxsvc::HandshakeRequest) -> rpc::Call<sbxsvc::HandshakeReply> {
+        println!("Sandbox: Received 'handshake' request");
+        let supported = protocol::version::ProtocolVersionRange::supported();
+        rpc::Call::new_resolved(Ok(sbxsvc::HandshakeReply {
+            supported,
+            version: supported.negotiate(&req.supported),
+        }))
+    }
+
     fn terminate(&self, _req: sbxsvc::TerminateRequest) -> rpc::Call<sbxsvc::TerminateReply> {
         println!("Sandbox: Received 'terminate' request");
         rpc::Call::new_resolved(Ok(sbxsvc::TerminateReply {}))
@@ -1,51 +1,318 @@
 use crate::controller_service::ControllerService;
 use crate::protocol::ctlsvc::*;
 use crate::protocol::logging::LogRequest;
-use crate::rpc::{Call, Channel, Error};
+use crate::rpc::{Call, Channel, Error, RPCResult};
 
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+// Log records are buffered until the next request that ends an execution
+// slice or the next heartbeat, if any, and are sent in one batch with that
+// request.
+// Once the buffer exceeds either of these limits, its records are sent on
+// their own.
+const MAX_BUFFERED_LOG_RECORDS: usize = 100;
+const MAX_BUFFERED_LOG_BYTES: usize = 64 * 1024;
+// The records still buffered after this delay are sent on their own, so that
+// records logged outside of executions, e.g. right before a crash, are not
+// held back until the next execution.
+const LOG_FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Default)]
+struct LogBuffer {
+    records: Vec<LogRequest>,
+    // The total length of the buffered messages.
+    bytes: usize,
+}
+
+impl LogBuffer {
+    fn push(&mut self, record: LogRequest) {
+        self.bytes += record.message.len();
+        self.records.push(record);
+    }
+
+    fn is_full(&self) -> bool {
+        self.records.len() >= MAX_BUFFERED_LOG_RECORDS || self.bytes >= MAX_BUFFERED_LOG_BYTES
+    }
+
+    fn take(&mut self) -> Vec<LogRequest> {
+        self.bytes = 0;
+        std::mem::take(&mut self.records)
+    }
+}
 
 /// Client stub for controller RPC interface -- this is instantiated in
 /// sandbox and allows to call the correspnoding functions inside
-/// the controller service.
+/// the controller service. Log records are not sent right away but buffered
+/// and sent along with the next execution boundary or heartbeat, which saves
+/// one IPC message per record. The thread started by `spawn_log_flusher()`
+/// sends the records that wait for longer.
 pub struct ControllerClientStub {
     channel: Arc<Channel<Request, Reply>>,
+    logs: Mutex<LogBuffer>,
 }
 
 impl ControllerClientStub {
     pub fn new(channel: Arc<Channel<Request, Reply>>) -> Self {
-        Self { channel }
+        Self {
+            channel,
+            logs: Mutex::new(LogBuffer::default()),
+        }
     }
-}
 
-impl ControllerService for ControllerClientStub {
-    fn execution_finished(&self, req: ExecutionFinishedRequest) -> Call<ExecutionFinishedReply> {
+    // Sends the request together with the buffered log records, which the
+    // controller handles first. Each record carries the execution during
+    // which it was logged, so it stays attributed to that execution. The
+    // reply of the request is the last one of the batch, see `unbatch()`.
+    fn call_with_logs<ExpectedReply: Sync + Send + 'static>(
+        &self,
+        req: Request,
+        xform: fn(Reply) -> RPCResult<ExpectedReply>,
+    ) -> Call<ExpectedReply> {
+        // The lock is held while sending to keep the records in order.
+        let mut logs = self.logs.lock().unwrap();
+        let records = logs.take();
+        let req = if records.is_empty() {
+            req
+        } else {
+            let mut requests: Vec<_> = records.into_iter().map(Request::LogViaReplica).collect();
+            requests.push(req);
+            Request::Batch(BatchRequest { requests })
+        };
+        Call::new(self.channel.call(req, xform))
+    }
+
+    /// Starts a thread that sends the buffered log records every
+    /// `LOG_FLUSH_INTERVAL` until the stub is dropped.
+    pub fn spawn_log_flusher(self: &Arc<Self>) {
+        let stub: Weak<Self> = Arc::downgrade(self);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(LOG_FLUSH_INTERVAL);
+            match stub.upgrade() {
+                Some(stub) => {
+                    stub.flush_logs();
+                }
+                None => return,
+            }
+        });
+    }
+
+    // Sends the buffered log records on their own, if there are any.
+    fn flush_logs(&self) -> Call<()> {
+        let mut logs = self.logs.lock().unwrap();
+        if logs.records.is_empty() {
+            return Call::new_resolved(Ok(()));
+        }
+        self.send_logs(logs.take())
+    }
+
+    fn send_logs(&self, records: Vec<LogRequest>) -> Call<()> {
+        let requests = records.into_iter().map(Request::LogViaReplica).collect();
         let cell = self
             .channel
-            .call(Request::ExecutionFinished(req), |rep| match rep {
-                Reply::ExecutionFinished(rep) => Ok(rep),
+            .call(Request::Batch(BatchRequest { requests }), |rep| match rep {
+                Reply::Batch(_) => Ok(()),
+                Reply::Rejected(code) => Err(Error::Rejected(code)),
                 _ => Err(Error::ServerError),
             });
         Call::new(cell)
     }
+}
 
-    fn execution_paused(&self, req: ExecutionPausedRequest) -> Call<ExecutionPausedReply> {
-        let cell = self
-            .channel
-            .call(Request::ExecutionPaused(req), |rep| match rep {
-                Reply::ExecutionPaused(rep) => Ok(rep),
+impl ControllerService for ControllerClientStub {
+    fn execution_finished(&self, req: ExecutionFinishedRequest) -> Call<ExecutionFinishedReply> {
+        self.call_with_logs(Request::ExecutionFinished(req), |rep| match unbatch(rep)? {
+            Reply::ExecutionFinished(rep) => Ok(rep),
+            Reply::Rejected(code) => Err(Error::Rejected(code)),
+            _ => Err(Error::ServerError),
+        })
+    }
+
+    fn execution_output_chunk(
+        &self,
+        req: ExecutionOutputChunkRequest,
+    ) -> Call<ExecutionOutputChunkReply> {
+        self.call_with_logs(Request::ExecutionOutputChunk(req), |rep| {
+            match unbatch(rep)? {
+                Reply::ExecutionOutputChunk(rep) => Ok(rep),
+                Reply::Rejected(code) => Err(Error::Rejected(code)),
                 _ => Err(Error::ServerError),
-            });
-        Call::new(cell)
+            }
+        })
+    }
+
+    fn trap_backtrace(&self, req: TrapBacktraceRequest) -> Call<TrapBacktraceReply> {
+        self.call_with_logs(Request::TrapBacktrace(req), |rep| match unbatch(rep)? {
+            Reply::TrapBacktrace(rep) => Ok(rep),
+            Reply::Rejected(code) => Err(Error::Rejected(code)),
+            _ => Err(Error::ServerError),
+        })
+    }
+
+    fn execution_paused(&self, req: ExecutionPausedRequest) -> Call<ExecutionPausedReply> {
+        self.call_with_logs(Request::ExecutionPaused(req), |rep| match unbatch(rep)? {
+            Reply::ExecutionPaused(rep) => Ok(rep),
+            Reply::Rejected(code) => Err(Error::Rejected(code)),
+            _ => Err(Error::ServerError),
+        })
     }
 
     fn log_via_replica(&self, req: LogRequest) -> Call<()> {
-        let cell = self
-            .channel
-            .call(Request::LogViaReplica(req), |rep| match rep {
-                Reply::LogViaReplica(_) => Ok(()),
-                _ => Err(Error::ServerError),
-            });
-        Call::new(cell)
+        let mut logs = self.logs.lock().unwrap();
+        logs.push(req);
+        if !logs.is_full() {
+            return Call::new_resolved(Ok(()));
+        }
+        // The lock is held while sending to keep the records in order.
+        self.send_logs(logs.take())
+    }
+
+    fn heartbeat(&self, req: HeartbeatRequest) -> Call<HeartbeatReply> {
+        self.call_with_logs(Request::Heartbeat(req), |rep| match unbatch(rep)? {
+            Reply::Heartbeat(rep) => Ok(rep),
+            Reply::Rejected(code) => Err(Error::Rejected(code)),
+            _ => Err(Error::ServerError),
+        })
+    }
+}
+
+// Returns the reply to the last request of a batch sent by `call_with_logs()`,
+// or the reply itself if the request was sent without log records.
+fn unbatch(rep: Reply) -> RPCResult<Reply> {
+    match rep {
+        Reply::Batch(BatchReply { mut replies }) => {
+            replies.pop().flatten().ok_or(Error::ServerError)
+        }
+        rep => Ok(rep),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::logging::LogLevel;
+    use crate::rpc::{MessageSink, ReplyManager};
+
+    // Records the sent requests without replying to them.
+    #[derive(Default)]
+    struct RecordingSink {
+        requests: Mutex<Vec<(u64, Request)>>,
+    }
+
+    impl MessageSink<Request> for RecordingSink {
+        fn handle(&self, cookie: u64, msg: Request) {
+            self.requests.lock().unwrap().push((cookie, msg));
+        }
+    }
+
+    fn stub() -> (
+        ControllerClientStub,
+        Arc<RecordingSink>,
+        Arc<ReplyManager<Reply>>,
+    ) {
+        let sink = Arc::new(RecordingSink::default());
+        let reply_manager = Arc::new(ReplyManager::new());
+        let channel = Channel::new(sink.clone(), reply_manager.clone());
+        (
+            ControllerClientStub::new(Arc::new(channel)),
+            sink,
+            reply_manager,
+        )
+    }
+
+    fn log(message: &str) -> LogRequest {
+        LogRequest {
+            level: LogLevel::Info,
+            message: message.to_string(),
+            canister_id: None,
+            exec_id: None,
+            trace_id: None,
+        }
+    }
+
+    fn logged_message(request: &Request) -> &str {
+        match request {
+            Request::LogViaReplica(req) => &req.message,
+            _ => panic!("Expected a log record"),
+        }
+    }
+
+    #[test]
+    fn logs_are_sent_with_the_next_heartbeat() {
+        let (stub, sink, reply_manager) = stub();
+        stub.log_via_replica(log("first")).sync().unwrap();
+        stub.log_via_replica(log("second")).sync().unwrap();
+        assert!(sink.requests.lock().unwrap().is_empty());
+
+        let heartbeat = stub.heartbeat(HeartbeatRequest {});
+        let (cookie, request) = sink.requests.lock().unwrap().pop().unwrap();
+        let requests = match request {
+            Request::Batch(BatchRequest { requests }) => requests,
+            _ => panic!("Expected a batch"),
+        };
+        assert_eq!(requests.len(), 3);
+        assert_eq!(logged_message(&requests[0]), "first");
+        assert_eq!(logged_message(&requests[1]), "second");
+        assert!(matches!(requests[2], Request::Heartbeat(_)));
+
+        reply_manager.handle(
+            cookie,
+            Reply::Batch(BatchReply {
+                replies: vec![
+                    Some(Reply::LogViaReplica(())),
+                    Some(Reply::LogViaReplica(())),
+                    Some(Reply::Heartbeat(HeartbeatReply {})),
+                ],
+            }),
+        );
+        assert!(heartbeat.sync().is_ok());
+
+        // The buffer is empty again, so the next heartbeat is sent alone.
+        stub.heartbeat(HeartbeatRequest {});
+        assert!(matches!(
+            sink.requests.lock().unwrap().pop(),
+            Some((_, Request::Heartbeat(_)))
+        ));
+    }
+
+    #[test]
+    fn flushed_logs_are_sent_on_their_own() {
+        let (stub, sink, _reply_manager) = stub();
+        stub.flush_logs();
+        assert!(sink.requests.lock().unwrap().is_empty());
+
+        stub.log_via_replica(log("first"));
+        stub.log_via_replica(log("second"));
+        stub.flush_logs();
+        let requests = sink.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        match &requests[0].1 {
+            Request::Batch(BatchRequest { requests }) => {
+                assert_eq!(requests.len(), 2);
+                assert_eq!(logged_message(&requests[0]), "first");
+                assert_eq!(logged_message(&requests[1]), "second");
+            }
+            _ => panic!("Expected a batch"),
+        }
+    }
+
+    #[test]
+    fn full_log_buffer_is_sent_on_its_own() {
+        let (stub, sink, _reply_manager) = stub();
+        for _ in 0..MAX_BUFFERED_LOG_RECORDS - 1 {
+            stub.log_via_replica(log("message"));
+        }
+        assert!(sink.requests.lock().unwrap().is_empty());
+
+        stub.log_via_replica(log("last"));
+        let requests = sink.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        match &requests[0].1 {
+            Request::Batch(BatchRequest { requests }) => {
+                assert_eq!(requests.len(), MAX_BUFFERED_LOG_RECORDS);
+                assert_eq!(logged_message(requests.last().unwrap()), "last");
+            }
+            _ => panic!("Expected a batch"),
+        }
     }
 }
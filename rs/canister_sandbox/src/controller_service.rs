@@ -1,6 +1,6 @@
 use crate::protocol::ctlsvc::*;
 use crate::protocol::logging::LogRequest;
-use crate::rpc::{Call, DemuxServer};
+use crate::rpc::{Call, DemuxServer, Error};
 
 /// RPC interface exposed by sandbox process.
 pub trait ControllerService: Send + Sync {
@@ -8,6 +8,18 @@ pub trait ControllerService: Send + Sync {
     /// (if successful) are transferred through this call.
     fn execution_finished(&self, req: ExecutionFinishedRequest) -> Call<ExecutionFinishedReply>;
 
+    /// Transfers a chunk of an execution output that is too large for a
+    /// single `execution_finished` call. The last chunk finishes the
+    /// execution.
+    fn execution_output_chunk(
+        &self,
+        req: ExecutionOutputChunkRequest,
+    ) -> Call<ExecutionOutputChunkReply>;
+
+    /// Transfers the Wasm backtrace of a trapping execution before the
+    /// execution is finished.
+    fn trap_backtrace(&self, req: TrapBacktraceRequest) -> Call<TrapBacktraceReply>;
+
     /// Triggered when wasm code execution is paused.
     fn execution_paused(&self, req: ExecutionPausedRequest) -> Call<ExecutionPausedReply>;
 
@@ -17,13 +29,18 @@ pub trait ControllerService: Send + Sync {
     /// single writer to the pipe -- otherwise we have to synchronize
     /// buffered and unbuffered writers.
     fn log_via_replica(&self, log: LogRequest) -> Call<()>;
+
+    /// Sent by the execution threads of the sandbox process while they make
+    /// progress, to tell the controller that the process is still responsive.
+    /// Only sent if the controller enforces a heartbeat timeout.
+    fn heartbeat(&self, req: HeartbeatRequest) -> Call<HeartbeatReply>;
 }
 
 impl<Svc: ControllerService + Send + Sync> DemuxServer<Request, Reply> for Svc {
     /// Dispatch generic RPC message to target function and produce
     /// matched reply (sync or async)
     fn dispatch(&self, req: Request) -> Call<Reply> {
-        match req {
+        let reply = match req {
             Request::ExecutionFinished(req) => {
                 Call::new_wrap(self.execution_finished(req), Reply::ExecutionFinished)
             }
@@ -33,6 +50,140 @@ impl<Svc: ControllerService + Send + Sync> DemuxServer<Request, Reply> for Svc {
             Request::LogViaReplica(req) => {
                 Call::new_wrap(self.log_via_replica(req), Reply::LogViaReplica)
             }
+            Request::Heartbeat(req) => Call::new_wrap(self.heartbeat(req), Reply::Heartbeat),
+            Request::ExecutionOutputChunk(req) => Call::new_wrap(
+                self.execution_output_chunk(req),
+                Reply::ExecutionOutputChunk,
+            ),
+            Request::TrapBacktrace(req) => {
+                Call::new_wrap(self.trap_backtrace(req), Reply::TrapBacktrace)
+            }
+            Request::Batch(BatchRequest { requests }) => {
+                // The requests are handled one after another, like requests
+                // sent on their own are by the `ServerStub`. The controller
+                // resolves all of them right away, so waiting for each one
+                // does not block.
+                let replies = requests
+                    .into_iter()
+                    .map(|req| match req {
+                        Request::Batch(_) => None,
+                        req => self.dispatch(req).sync().ok(),
+                    })
+                    .collect();
+                Call::new_resolved(Ok(Reply::Batch(BatchReply { replies })))
+            }
+        };
+        // A rejection is sent back as a reply, whereas other errors leave the
+        // request without a reply.
+        match reply.sync() {
+            Err(Error::Rejected(code)) => Call::new_resolved(Ok(Reply::Rejected(code))),
+            result => Call::new_resolved(result),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::id::{ExecId, TraceId};
+    use crate::protocol::logging::LogLevel;
+    use crate::rpc::RejectCode;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingService {
+        logged: Mutex<Vec<String>>,
+    }
+
+    impl ControllerService for RecordingService {
+        fn execution_finished(
+            &self,
+            _req: ExecutionFinishedRequest,
+        ) -> Call<ExecutionFinishedReply> {
+            Call::new_resolved(Err(Error::ServerError))
+        }
+
+        fn execution_output_chunk(
+            &self,
+            _req: ExecutionOutputChunkRequest,
+        ) -> Call<ExecutionOutputChunkReply> {
+            Call::new_resolved(Err(Error::Rejected(RejectCode::UnknownExecId)))
+        }
+
+        fn trap_backtrace(&self, _req: TrapBacktraceRequest) -> Call<TrapBacktraceReply> {
+            Call::new_resolved(Err(Error::ServerError))
+        }
+
+        fn execution_paused(&self, _req: ExecutionPausedRequest) -> Call<ExecutionPausedReply> {
+            Call::new_resolved(Err(Error::ServerError))
+        }
+
+        fn log_via_replica(&self, req: LogRequest) -> Call<()> {
+            self.logged.lock().unwrap().push(req.message);
+            Call::new_resolved(Ok(()))
+        }
+
+        fn heartbeat(&self, _req: HeartbeatRequest) -> Call<HeartbeatReply> {
+            Call::new_resolved(Ok(HeartbeatReply {}))
+        }
+    }
+
+    #[test]
+    fn batch_requests_are_handled_in_order() {
+        let service = RecordingService::default();
+        let log = |message: &str| {
+            Request::LogViaReplica(LogRequest {
+                level: LogLevel::Info,
+                message: message.to_string(),
+                canister_id: None,
+                exec_id: None,
+                trace_id: None,
+            })
+        };
+        let requests = vec![
+            log("first"),
+            Request::Batch(BatchRequest {
+                requests: vec![log("nested")],
+            }),
+            log("second"),
+        ];
+
+        let reply = service
+            .dispatch(Request::Batch(BatchRequest { requests }))
+            .sync();
+
+        let replies = match reply {
+            Ok(Reply::Batch(BatchReply { replies })) => replies,
+            _ => panic!("Expected a batch reply"),
+        };
+        assert!(matches!(
+            replies.as_slice(),
+            [
+                Some(Reply::LogViaReplica(())),
+                None,
+                Some(Reply::LogViaReplica(()))
+            ]
+        ));
+        assert_eq!(*service.logged.lock().unwrap(), vec!["first", "second"]);
+    }
+
+    #[test]
+    fn rejections_are_replied() {
+        let service = RecordingService::default();
+        let chunk = Request::ExecutionOutputChunk(ExecutionOutputChunkRequest {
+            exec_id: ExecId::new(),
+            trace_id: TraceId::new(),
+            chunk: vec![],
+            last: true,
+        });
+
+        assert!(matches!(
+            service.dispatch(chunk).sync(),
+            Ok(Reply::Rejected(RejectCode::UnknownExecId))
+        ));
+        assert!(service
+            .dispatch(Request::Heartbeat(HeartbeatRequest {}))
+            .sync()
+            .is_ok());
+    }
+}
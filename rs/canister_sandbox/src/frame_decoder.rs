@@ -2,6 +2,20 @@ use bytes::{Buf, BytesMut};
 use serde::de::DeserializeOwned;
 use std::marker::PhantomData;
 
+/// The bit of the length tag that marks a frame whose message is stored in
+/// shared memory. The payload of such a frame is the u64 length of the
+/// message, and the shared memory file is the first file descriptor passed
+/// along with the frame.
+pub const SHARED_MEMORY_FRAME_FLAG: u64 = 1 << 63;
+
+/// A frame extracted by the [`FrameDecoder`].
+pub enum Frame<Message> {
+    /// The message was sent inline in the stream.
+    Inline(Message),
+    /// The message of the given length was sent in shared memory.
+    Shared { len: usize },
+}
+
 /// Incremental decoder for stream of data. Splits frames preceded by
 /// u32 length tag and deserialized them using cbor.
 pub struct FrameDecoder<Message: DeserializeOwned + Clone> {
@@ -29,7 +43,7 @@ impl<Message: DeserializeOwned + Clone> FrameDecoder<Message> {
     /// from given buffer.
     /// This is to be called repeatedly, interleaved with filling the
     /// buffer with more data as needed.
-    pub fn decode(&mut self, data: &mut BytesMut) -> Option<Message> {
+    pub fn decode(&mut self, data: &mut BytesMut) -> Option<Frame<Message>> {
         loop {
             match &self.state {
                 FrameDecoderState::NoLength => {
@@ -41,16 +55,21 @@ impl<Message: DeserializeOwned + Clone> FrameDecoder<Message> {
                         self.state = FrameDecoderState::Length(size);
                     }
                 }
-                FrameDecoderState::Length(size) => {
-                    let size: usize = *size as usize;
+                FrameDecoderState::Length(tag) => {
+                    let shared = tag & SHARED_MEMORY_FRAME_FLAG != 0;
+                    let size: usize = (tag & !SHARED_MEMORY_FRAME_FLAG) as usize;
                     if data.len() < size {
                         data.reserve(size);
                         return None;
                     } else {
-                        let frame = data.split_to(size);
+                        let mut frame = data.split_to(size);
                         self.state = FrameDecoderState::NoLength;
+                        if shared {
+                            let len = frame.get_u64() as usize;
+                            return Some(Frame::Shared { len });
+                        }
                         let value = bincode::deserialize(&frame).unwrap();
-                        return Some(value);
+                        return Some(Frame::Inline(value));
                     }
                 }
             }
@@ -10,19 +10,21 @@ use crate::{
     controller_launcher_client_stub::{self, ControllerLauncherClientStub},
     controller_launcher_service::ControllerLauncherService,
     launcher_service::LauncherService,
-    process::spawn_socketed_process,
+    process::{spawn_socketed_process, spawn_socketed_process_with_memory_limit},
     protocol::{
         self,
         ctllaunchersvc::SandboxExitedRequest,
         launchersvc::{
-            LaunchCompilerReply, LaunchCompilerRequest, LaunchSandboxReply, LaunchSandboxRequest,
-            TerminateReply, TerminateRequest,
+            AssignCanisterReply, AssignCanisterRequest, LaunchCompilerReply, LaunchCompilerRequest,
+            LaunchSandboxReply, LaunchSandboxRequest, TerminateReply, TerminateRequest,
         },
     },
     rpc,
     transport::{self, SocketReaderConfig},
+    EmbeddersConfig,
 };
-use ic_types::CanisterId;
+use ic_config::flag_status::FlagStatus;
+use ic_types::{CanisterId, NumBytes};
 use nix::{
     errno::Errno,
     sys::wait::{wait, WaitStatus},
@@ -96,10 +98,21 @@ pub struct LauncherServer {
     pid_to_process_info: Arc<Mutex<HashMap<Pid, ProcessInfo>>>,
     has_children: Arc<Condvar>,
     embedder_config_arg: String,
+    // Whether the replica controller recovers from sandbox crashes. Otherwise,
+    // the launcher panics if a sandbox process exits unexpectedly, so that the
+    // replica never produces divergent state due to a node-local failure.
+    recover_from_sandbox_crashes: bool,
+    // The data memory limit of each sandbox process, enforced by the kernel.
+    sandbox_memory_limit: Option<NumBytes>,
 }
 
 impl LauncherServer {
     fn new(controller: ControllerLauncherClientStub, embedder_config_arg: String) -> Self {
+        let embedder_config: EmbeddersConfig = serde_json::from_str(&embedder_config_arg)
+            .expect("Could not parse the argument, invalid embedder config value.");
+        let recover_from_sandbox_crashes =
+            embedder_config.retry_on_sandbox_crash == FlagStatus::Enabled;
+        let sandbox_memory_limit = embedder_config.sandbox_resource_limits.max_memory;
         let pid_to_process_info = Arc::new(Mutex::new(HashMap::<Pid, ProcessInfo>::new()));
         let has_children = Arc::new(Condvar::new());
         let watcher_process_info_map = Arc::clone(&pid_to_process_info);
@@ -138,18 +151,23 @@ impl LauncherServer {
                             pid, process_info, status
                         );
 
+                        // If we have a canister id, tell the replica process so that it
+                        // can print the history and fail the executions of the sandbox.
+                        if let Some(canister_id) = process_info.as_ref().and_then(|x| x.canister_id)
+                        {
+                            controller
+                                .sandbox_exited(SandboxExitedRequest {
+                                    canister_id,
+                                    pid: pid.as_raw() as u32,
+                                })
+                                .sync()
+                                .unwrap();
+                        }
                         let should_panic = process_info
                             .as_ref()
                             .map(|x| x.panic_on_failure)
                             .unwrap_or(true);
                         if should_panic {
-                            // If we have a canister id, tell the replica process to print its history.
-                            if let Some(canister_id) = process_info.and_then(|x| x.canister_id) {
-                                controller
-                                    .sandbox_exited(SandboxExitedRequest { canister_id })
-                                    .sync()
-                                    .unwrap();
-                            }
                             panic!("Launcher detected sandbox exit");
                         }
                     }
@@ -160,6 +178,8 @@ impl LauncherServer {
             pid_to_process_info,
             has_children,
             embedder_config_arg,
+            recover_from_sandbox_crashes,
+            sandbox_memory_limit,
         }
     }
 }
@@ -174,7 +194,12 @@ impl LauncherService for LauncherServer {
             socket,
         }: LaunchSandboxRequest,
     ) -> rpc::Call<LaunchSandboxReply> {
-        match spawn_socketed_process(&sandbox_exec_path, &argv, socket) {
+        match spawn_socketed_process_with_memory_limit(
+            &sandbox_exec_path,
+            &argv,
+            socket,
+            self.sandbox_memory_limit,
+        ) {
             Ok(child_handle) => {
                 // Ensure the launcher closes its end of the socket.
                 drop(unsafe { UnixStream::from_raw_fd(socket) });
@@ -192,8 +217,8 @@ impl LauncherService for LauncherServer {
                 info_map.insert(
                     Pid::from_raw(pid as i32),
                     ProcessInfo {
-                        canister_id: Some(canister_id),
-                        panic_on_failure: true,
+                        canister_id,
+                        panic_on_failure: !self.recover_from_sandbox_crashes,
                     },
                 );
 
@@ -250,6 +275,19 @@ impl LauncherService for LauncherServer {
         }
     }
 
+    fn assign_canister(
+        &self,
+        AssignCanisterRequest { pid, canister_id }: AssignCanisterRequest,
+    ) -> rpc::Call<AssignCanisterReply> {
+        let mut info_map = self.pid_to_process_info.lock().unwrap();
+        // The process may have exited already, in which case the controller
+        // finds out once it fails to use it.
+        if let Some(process_info) = info_map.get_mut(&Pid::from_raw(pid as i32)) {
+            process_info.canister_id = Some(canister_id);
+        }
+        rpc::Call::new_resolved(Ok(AssignCanisterReply {}))
+    }
+
     fn terminate(&self, _req: TerminateRequest) -> rpc::Call<TerminateReply> {
         std::process::exit(0);
     }
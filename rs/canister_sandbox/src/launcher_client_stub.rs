@@ -33,6 +33,16 @@ impl LauncherService for LauncherClientStub {
         Call::new(cell)
     }
 
+    fn assign_canister(&self, req: AssignCanisterRequest) -> Call<AssignCanisterReply> {
+        let cell = self
+            .channel
+            .call(Request::AssignCanister(req), |rep| match rep {
+                Reply::AssignCanister(rep) => Ok(rep),
+                _ => Err(Error::ServerError),
+            });
+        Call::new(cell)
+    }
+
     fn terminate(&self, req: TerminateRequest) -> Call<TerminateReply> {
         let cell = self.channel.call(Request::Terminate(req), |rep| match rep {
             Reply::Terminate(rep) => Ok(rep),
@@ -8,6 +8,9 @@ pub trait LauncherService: Send + Sync {
     /// Launch a new compiler process.
     fn launch_compiler(&self, req: LaunchCompilerRequest) -> Call<LaunchCompilerReply>;
 
+    /// Assign a pooled sandbox process to a canister.
+    fn assign_canister(&self, req: AssignCanisterRequest) -> Call<AssignCanisterReply>;
+
     /// Terinate the Sandbox Launcher process.
     fn terminate(&self, req: TerminateRequest) -> Call<TerminateReply>;
 }
@@ -21,6 +24,9 @@ impl<Svc: LauncherService + Send + Sync> DemuxServer<Request, Reply> for Svc {
             Request::LaunchCompiler(req) => {
                 Call::new_wrap(self.launch_compiler(req), Reply::LaunchCompiler)
             }
+            Request::AssignCanister(req) => {
+                Call::new_wrap(self.assign_canister(req), Reply::AssignCanister)
+            }
             Request::Terminate(req) => Call::new_wrap(self.terminate(req), Reply::Terminate),
         }
     }
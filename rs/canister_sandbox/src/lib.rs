@@ -16,6 +16,7 @@ pub mod sandbox_client_stub;
 pub mod sandbox_manager;
 pub mod sandbox_server;
 pub mod sandbox_service;
+mod syscall_filter;
 pub mod transport;
 pub mod protocol {
     pub mod ctllaunchersvc;
@@ -26,9 +27,11 @@ pub mod protocol {
     pub mod sbxsvc;
     pub mod structs;
     pub mod transport;
+    pub mod version;
 }
 pub mod fdenum;
 
+use controller_service::ControllerService;
 use protocol::{
     ctllaunchersvc, ctlsvc, launchersvc, sbxsvc,
     transport::{
@@ -168,9 +171,14 @@ pub fn canister_sandbox_main() {
             )
         }
     }
-    let embedder_config = embedder_config_arg
+    let embedder_config: EmbeddersConfig = embedder_config_arg
         .expect("Error from the sandbox process due to unknown embedder config.");
 
+    // The filter must be installed before any thread is spawned, so that it
+    // applies to all threads of the process.
+    syscall_filter::install(embedder_config.sandbox_syscall_filter)
+        .expect("Failed to install the system call filter of the sandbox process.");
+
     // Currently Wasmtime uses the default rayon thread-pool with a thread per core.
     // In production this results in 64 threads. This MR reduces the default
     // thread pool size to 10 in the sandbox process because
@@ -216,6 +224,7 @@ pub fn run_canister_sandbox(
     let controller = Arc::new(controller_client_stub::ControllerClientStub::new(Arc::new(
         rpc::Channel::new(request_out_stream, reply_handler.clone()),
     )));
+    controller.spawn_log_flusher();
 
     // Construct RPC server for the  service offered by this binary,
     // namely access to the sandboxed canister runner functions.
@@ -1,9 +1,11 @@
+use ic_types::NumBytes;
 use nix::unistd::Pid;
 use std::os::unix::io::AsRawFd;
 use std::os::unix::prelude::{CommandExt, RawFd};
 use std::process::{Child, Command};
 use std::sync::atomic::{AtomicBool, Ordering};
 
+use crate::replica_controller::resource_limits::set_memory_limit;
 use crate::transport::SocketReaderConfig;
 use crate::{
     protocol, protocol::ctlsvc, rpc, sandbox_client_stub::SandboxClientStub,
@@ -19,6 +21,17 @@ pub fn spawn_socketed_process(
     exec_path: &str,
     argv: &[String],
     socket: RawFd,
+) -> std::io::Result<Child> {
+    spawn_socketed_process_with_memory_limit(exec_path, argv, socket, None)
+}
+
+/// Like [`spawn_socketed_process`], but additionally limits the data memory of
+/// the subprocess to `max_memory` bytes, if given.
+pub fn spawn_socketed_process_with_memory_limit(
+    exec_path: &str,
+    argv: &[String],
+    socket: RawFd,
+    max_memory: Option<NumBytes>,
 ) -> std::io::Result<Child> {
     let mut cmd = Command::new(exec_path);
     cmd.args(argv);
@@ -37,6 +50,9 @@ pub fn spawn_socketed_process(
             if fd != 3 {
                 return Err(std::io::Error::last_os_error());
             }
+            if let Some(max_memory) = max_memory {
+                set_memory_limit(max_memory)?;
+            }
             Ok(())
         })
     };
@@ -8,6 +8,7 @@ use crate::fdenum::EnumerateInnerFileDescriptors;
 #[derive(Serialize, Deserialize, Clone)]
 pub struct SandboxExitedRequest {
     pub canister_id: CanisterId,
+    pub pid: u32,
 }
 
 impl EnumerateInnerFileDescriptors for SandboxExitedRequest {
@@ -1,8 +1,32 @@
-use crate::{fdenum::EnumerateInnerFileDescriptors, protocol::logging::LogRequest};
+use crate::{
+    fdenum::EnumerateInnerFileDescriptors, protocol::logging::LogRequest, rpc::RejectCode,
+};
 use ic_embedders::wasm_executor::SliceExecutionOutput;
 use serde::{Deserialize, Serialize};
 
-use super::{id::ExecId, structs::SandboxExecOutput};
+use super::{
+    id::{ExecId, TraceId},
+    structs::SandboxExecOutput,
+};
+
+use std::time::Duration;
+
+/// The minimum interval between two heartbeats that the execution threads of
+/// a sandbox process send to the controller.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The size of the chunks in which a sandbox process streams an execution
+/// output. Outputs that serialize to at most this size are sent in a single
+/// `ExecutionFinishedRequest`.
+pub const EXECUTION_OUTPUT_CHUNK_SIZE: usize = 1 << 20;
+
+/// The maximum total size of the chunks of a streamed execution output. A
+/// sandbox process streaming a larger output is killed.
+pub const MAX_EXECUTION_OUTPUT_SIZE: usize = 512 << 20;
+
+/// The maximum size of a trap backtrace kept by the controller. Longer
+/// backtraces are truncated.
+pub const MAX_TRAP_BACKTRACE_SIZE: usize = 4 * 1024;
 
 // This defines the RPC service methods offered by the controller process
 // (used by the sandbox) as well as the expected replies.
@@ -13,22 +37,74 @@ pub struct ExecutionFinishedRequest {
     // Id for this run, as set up by controller.
     pub exec_id: ExecId,
 
+    // Trace id of the execution, as set up by controller.
+    pub trace_id: TraceId,
+
     pub exec_output: SandboxExecOutput,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct ExecutionFinishedReply {}
 
+// A chunk of the serialized `SandboxExecOutput` of a finished execution whose
+// output is too large for a single message. The chunks are sent in order, and
+// the last one completes the execution like an `ExecutionFinishedRequest`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ExecutionOutputChunkRequest {
+    pub exec_id: ExecId,
+    pub trace_id: TraceId,
+    #[serde(with = "serde_bytes")]
+    pub chunk: Vec<u8>,
+    pub last: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ExecutionOutputChunkReply {}
+
 // Notify controller that a canister run is paused.
 #[derive(Serialize, Deserialize, Clone)]
 pub struct ExecutionPausedRequest {
     pub exec_id: ExecId,
+    pub trace_id: TraceId,
     pub slice: SliceExecutionOutput,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct ExecutionPausedReply {}
 
+// The Wasm backtrace of an execution that trapped, sent right before the
+// request that finishes the execution.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TrapBacktraceRequest {
+    pub exec_id: ExecId,
+    pub trace_id: TraceId,
+    pub backtrace: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TrapBacktraceReply {}
+
+// Tells the controller that the sandbox process is still responsive.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HeartbeatRequest {}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HeartbeatReply {}
+
+// Several independent requests that the controller handles in order, saving
+// IPC round trips. A batch must not contain another batch.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BatchRequest {
+    pub requests: Vec<Request>,
+}
+
+// The replies to the requests of a batch in the same order. A request that
+// failed has no reply.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BatchReply {
+    pub replies: Vec<Option<Reply>>,
+}
+
 /// We reply to the replica controller that either the execution was
 /// finished or the request failed, or request a system call or a log
 /// to be applied.
@@ -38,6 +114,11 @@ pub enum Request {
     ExecutionFinished(ExecutionFinishedRequest),
     ExecutionPaused(ExecutionPausedRequest),
     LogViaReplica(LogRequest),
+    Heartbeat(HeartbeatRequest),
+    Batch(BatchRequest),
+    ExecutionOutputChunk(ExecutionOutputChunkRequest),
+    // Only sent to controllers that speak `TRAP_BACKTRACE_VERSION` or later.
+    TrapBacktrace(TrapBacktraceRequest),
 }
 
 impl EnumerateInnerFileDescriptors for Request {
@@ -52,6 +133,13 @@ pub enum Reply {
     ExecutionFinished(ExecutionFinishedReply),
     ExecutionPaused(ExecutionPausedReply),
     LogViaReplica(()),
+    Heartbeat(HeartbeatReply),
+    Batch(BatchReply),
+    ExecutionOutputChunk(ExecutionOutputChunkReply),
+    TrapBacktrace(TrapBacktraceReply),
+    // The request was rejected. Only sent to sandbox processes that speak
+    // `REJECTED_REPLIES_VERSION` or later.
+    Rejected(RejectCode),
 }
 
 impl EnumerateInnerFileDescriptors for Reply {
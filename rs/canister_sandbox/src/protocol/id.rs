@@ -97,3 +97,31 @@ impl Display for WasmId {
         write!(f, "wasm-id-{}", self.0)
     }
 }
+
+/// The identifier of a message execution for tracing. Unlike `ExecId`, it
+/// stays the same if the execution is started over on another sandbox
+/// process, so that all phases of the execution on both sides of the IPC
+/// boundary can be stitched together.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceId(usize);
+
+impl TraceId {
+    /// Only the replica process is supposed to create new `TraceId`.
+    pub fn new() -> Self {
+        static MONOTONICALLY_INCREASING_COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let id = MONOTONICALLY_INCREASING_COUNTER.fetch_add(1, Ordering::SeqCst);
+        Self(id)
+    }
+}
+
+impl Default for TraceId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for TraceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "trace-id-{}", self.0)
+    }
+}
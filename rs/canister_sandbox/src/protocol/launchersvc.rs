@@ -13,7 +13,9 @@ use crate::fdenum::EnumerateInnerFileDescriptors;
 pub struct LaunchSandboxRequest {
     pub sandbox_exec_path: String,
     pub argv: Vec<String>,
-    pub canister_id: CanisterId,
+    /// The canister that runs in the sandbox process, or `None` if the
+    /// process is spawned for the sandbox process pool.
+    pub canister_id: Option<CanisterId>,
     pub socket: RawFd,
 }
 
@@ -46,6 +48,18 @@ pub struct LaunchCompilerReply {
     pub pid: u32,
 }
 
+/// Records that a sandbox process that was spawned for the sandbox process
+/// pool now runs the given canister, so that the controller is notified with
+/// that canister when the process exits.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AssignCanisterRequest {
+    pub pid: u32,
+    pub canister_id: CanisterId,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AssignCanisterReply {}
+
 /// Instruct the Sandbox Launcher process to terminate.
 #[derive(Serialize, Deserialize, Clone)]
 pub struct TerminateRequest {}
@@ -58,6 +72,7 @@ pub struct TerminateReply {}
 pub enum Request {
     LaunchSandbox(LaunchSandboxRequest),
     LaunchCompiler(LaunchCompilerRequest),
+    AssignCanister(AssignCanisterRequest),
     Terminate(TerminateRequest),
 }
 
@@ -66,6 +81,7 @@ impl EnumerateInnerFileDescriptors for Request {
         match self {
             Request::LaunchSandbox(req) => req.enumerate_fds(fds),
             Request::LaunchCompiler(req) => req.enumerate_fds(fds),
+            Request::AssignCanister(_req) => {}
             Request::Terminate(_req) => {}
         }
     }
@@ -76,6 +92,7 @@ impl EnumerateInnerFileDescriptors for Request {
 pub enum Reply {
     LaunchSandbox(LaunchSandboxReply),
     LaunchCompiler(LaunchCompilerReply),
+    AssignCanister(AssignCanisterReply),
     Terminate(TerminateReply),
 }
 
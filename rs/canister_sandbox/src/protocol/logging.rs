@@ -1,9 +1,22 @@
+use ic_types::CanisterId;
 use serde::{Deserialize, Serialize};
 
+use super::id::{ExecId, TraceId};
+
 /// Describes a request for logging to the replica. We provide a log
-/// level and the description.
+/// level and the description, as well as the context in which the
+/// message was logged so that the replica can attribute it.
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct LogRequest(pub LogLevel, pub String);
+pub struct LogRequest {
+    pub level: LogLevel,
+    pub message: String,
+    /// The canister served by the sandbox process, if known.
+    pub canister_id: Option<CanisterId>,
+    /// The execution during which the message was logged, if any.
+    pub exec_id: Option<ExecId>,
+    /// The trace id of that execution, if any.
+    pub trace_id: Option<TraceId>,
+}
 
 /// We can inform the replica that we have one of the following debug
 /// levels.
@@ -1,29 +1,46 @@
 //! This defines the RPC service methods offered by the sandbox process
 //! (used by the controller) as well as the expected replies.
 
-use std::{sync::Arc, time::Duration};
+use std::time::Duration;
 
 use crate::fdenum::EnumerateInnerFileDescriptors;
 use crate::protocol::structs;
-use ic_embedders::{CompilationResult, SerializedModule, SerializedModuleBytes};
+use ic_embedders::{wasm_utils::Segments, CompilationResult, SerializedModule};
 use ic_interfaces::execution_environment::HypervisorResult;
 use ic_replicated_state::{
     page_map::{
-        BaseFileSerialization, CheckpointSerialization, MappingSerialization,
+        BaseFileSerialization, CheckpointSerialization, FileDescriptor, MappingSerialization,
         OverlayFileSerialization, PageAllocatorSerialization, PageMapSerialization,
         StorageSerialization,
     },
     Global, NumWasmPages,
 };
 use ic_types::CanisterId;
-use ic_utils;
 use serde::{Deserialize, Serialize};
 
 use super::{
     id::{ExecId, MemoryId, WasmId},
     structs::{MemoryModifications, SandboxExecInput},
+    version::{ProtocolVersion, ProtocolVersionRange},
 };
 
+/// The first request sent to a sandbox process to agree on the protocol
+/// version. See the `version` module.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HandshakeRequest {
+    /// The protocol versions supported by the controller.
+    pub supported: ProtocolVersionRange,
+}
+
+/// Reply to a `HandshakeRequest`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HandshakeReply {
+    /// The protocol versions supported by the sandbox process.
+    pub supported: ProtocolVersionRange,
+    /// The highest version supported by both sides, if any.
+    pub version: Option<ProtocolVersion>,
+}
+
 /// Instruct sandbox process to terminate: Sandbox process should take
 /// all necessary steps for graceful termination (sync all files etc.)
 /// and quit voluntarily. It is still expected to generate a reply to
@@ -60,6 +77,36 @@ pub struct OpenWasmRequest {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct OpenWasmReply(pub HypervisorResult<(CompilationResult, SerializedModule)>);
 
+/// A file holding the serialization of a previously compiled
+/// `wasmtime::Module`, i.e. the bytes of a `SerializedModuleBytes`.
+///
+/// The file is created and owned by the controller, which never modifies it
+/// after creation. Instead of receiving a copy of the bytes over the socket,
+/// the sandbox memory-maps the file.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SerializedModuleFile {
+    pub fd: FileDescriptor,
+}
+
+impl SerializedModuleFile {
+    /// Refers to the given file, which must stay open until the request has
+    /// been sent.
+    pub fn new(file: &std::fs::File) -> Self {
+        use std::os::unix::io::AsRawFd;
+        Self {
+            fd: FileDescriptor {
+                fd: file.as_raw_fd(),
+            },
+        }
+    }
+}
+
+impl EnumerateInnerFileDescriptors for SerializedModuleFile {
+    fn enumerate_fds<'a>(&'a mut self, fds: &mut Vec<&'a mut std::os::unix::io::RawFd>) {
+        fds.push(&mut self.fd.fd);
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct OpenWasmSerializedRequest {
     /// Id used to later refer to this canister runner. Must be unique
@@ -67,12 +114,13 @@ pub struct OpenWasmSerializedRequest {
     pub wasm_id: WasmId,
 
     /// The serialization of a previously compiled `wasmtime::Module`.
-    /// This types in just an `Arc` reference to a vector of bytes and the only
-    /// reason it is `Arc` is so that we can cheaply create the
-    /// `OpenWasmSerializedRequest` before sending it to the sandbox.
-    #[serde(serialize_with = "ic_utils::serde_arc::serialize_arc")]
-    #[serde(deserialize_with = "ic_utils::serde_arc::deserialize_arc")]
-    pub serialized_module: Arc<SerializedModuleBytes>,
+    pub serialized_module: SerializedModuleFile,
+}
+
+impl EnumerateInnerFileDescriptors for OpenWasmSerializedRequest {
+    fn enumerate_fds<'a>(&'a mut self, fds: &mut Vec<&'a mut std::os::unix::io::RawFd>) {
+        self.serialized_module.enumerate_fds(fds);
+    }
 }
 
 /// Reply to an `OpenWasmRequest`.
@@ -282,12 +330,9 @@ pub struct CreateExecutionStateReply(pub HypervisorResult<CreateExecutionStateSu
 pub struct CreateExecutionStateSerializedRequest {
     pub wasm_id: WasmId,
     /// The serialization of a previously compiled `wasmtime::Module`.
-    /// This types in just an `Arc` reference to a vector of bytes and the only
-    /// reason it is `Arc` is so that we can cheaply create the
-    /// `CreateExecutionStateSerializedRequest` before sending it to the sandbox.
-    #[serde(serialize_with = "ic_utils::serde_arc::serialize_arc")]
-    #[serde(deserialize_with = "ic_utils::serde_arc::deserialize_arc")]
-    pub serialized_module: Arc<SerializedModule>,
+    pub serialized_module: SerializedModuleFile,
+    /// The initial state of the Wasm heap of the module.
+    pub data_segments: Segments,
     pub wasm_page_map: PageMapSerialization,
     pub next_wasm_memory_id: MemoryId,
     pub canister_id: CanisterId,
@@ -296,6 +341,7 @@ pub struct CreateExecutionStateSerializedRequest {
 
 impl EnumerateInnerFileDescriptors for CreateExecutionStateSerializedRequest {
     fn enumerate_fds<'a>(&'a mut self, fds: &mut Vec<&'a mut std::os::unix::io::RawFd>) {
+        self.serialized_module.enumerate_fds(fds);
         self.wasm_page_map.enumerate_fds(fds);
         self.stable_memory_page_map.enumerate_fds(fds);
     }
@@ -329,6 +375,9 @@ pub enum Request {
     AbortExecution(AbortExecutionRequest),
     CreateExecutionState(CreateExecutionStateRequest),
     CreateExecutionStateSerialized(CreateExecutionStateSerializedRequest),
+    // The handshake is the first request to a new sandbox process. A sandbox
+    // binary that predates it fails to decode it, so spawning it fails.
+    Handshake(HandshakeRequest),
 }
 
 impl EnumerateInnerFileDescriptors for Request {
@@ -337,14 +386,15 @@ impl EnumerateInnerFileDescriptors for Request {
             Request::OpenMemory(request) => request.enumerate_fds(fds),
             Request::CreateExecutionState(request) => request.enumerate_fds(fds),
             Request::CreateExecutionStateSerialized(request) => request.enumerate_fds(fds),
+            Request::OpenWasmSerialized(request) => request.enumerate_fds(fds),
             Request::Terminate(_)
             | Request::OpenWasm(_)
-            | Request::OpenWasmSerialized(_)
             | Request::CloseWasm(_)
             | Request::CloseMemory(_)
             | Request::StartExecution(_)
             | Request::ResumeExecution(_)
-            | Request::AbortExecution(_) => {}
+            | Request::AbortExecution(_)
+            | Request::Handshake(_) => {}
         }
     }
 }
@@ -364,6 +414,7 @@ pub enum Reply {
     AbortExecution(AbortExecutionReply),
     CreateExecutionState(CreateExecutionStateReply),
     CreateExecutionStateSerialized(CreateExecutionStateSerializedReply),
+    Handshake(HandshakeReply),
 }
 
 impl EnumerateInnerFileDescriptors for Reply {
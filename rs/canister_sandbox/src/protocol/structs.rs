@@ -10,7 +10,7 @@ use ic_system_api::{
 use ic_types::{methods::FuncRef, NumBytes};
 use serde::{Deserialize, Serialize};
 
-use super::id::MemoryId;
+use super::id::{MemoryId, TraceId};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Round(pub u64);
@@ -30,6 +30,9 @@ pub struct SandboxExecInput {
     // access.
     pub sandbox_safe_system_state: SandboxSafeSystemState,
     pub wasm_reserved_pages: NumWasmPages,
+    // Passed back in all requests of the sandbox process that relate to the
+    // execution.
+    pub trace_id: TraceId,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -0,0 +1,107 @@
+//! Versioning of the IPC protocol between the replica controller and the
+//! sandbox processes.
+//!
+//! Right after spawning a sandbox process, the controller sends a
+//! `HandshakeRequest` with the range of protocol versions that it supports.
+//! The sandbox process replies with its own range and the highest version
+//! supported by both sides, which is used from then on. If the ranges do not
+//! overlap, the sandbox process is terminated.
+//!
+//! The layout of the requests and replies is not branched on the negotiated
+//! version, so both sides must agree on it exactly. Any change to it must
+//! increase `PROTOCOL_VERSION`, and `MIN_SUPPORTED_PROTOCOL_VERSION` must
+//! follow unless the old layout is still handled. A sandbox binary that
+//! predates the handshake cannot decode it, so spawning it fails.
+//!
+//! The handshake messages themselves must never change, so that both sides
+//! can detect a peer of any version that supports the handshake.
+
+use serde::{Deserialize, Serialize};
+
+pub type ProtocolVersion = u32;
+
+/// The protocol version spoken by this binary.
+///
+/// - 1: The initial version.
+/// - 2: Sandbox processes may stream large execution outputs in
+///   `ExecutionOutputChunkRequest`s.
+/// - 3: The controller replies to rejected requests with the reason.
+/// - 4: Sandbox processes may send the Wasm backtrace of a trapping execution
+///   in a `TrapBacktraceRequest`.
+pub const PROTOCOL_VERSION: ProtocolVersion = 4;
+
+/// The oldest protocol version this binary can still speak. Equal to
+/// `PROTOCOL_VERSION` because the message layouts of the older versions are
+/// not handled.
+pub const MIN_SUPPORTED_PROTOCOL_VERSION: ProtocolVersion = PROTOCOL_VERSION;
+
+/// The first protocol version in which execution outputs may be streamed.
+pub const STREAMED_EXECUTION_OUTPUT_VERSION: ProtocolVersion = 2;
+
+/// The first protocol version in which the controller replies to rejected
+/// requests.
+pub const REJECTED_REPLIES_VERSION: ProtocolVersion = 3;
+
+/// The first protocol version in which sandbox processes may send trap
+/// backtraces.
+pub const TRAP_BACKTRACE_VERSION: ProtocolVersion = 4;
+
+/// An inclusive range of protocol versions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolVersionRange {
+    pub min: ProtocolVersion,
+    pub max: ProtocolVersion,
+}
+
+impl ProtocolVersionRange {
+    /// The protocol versions supported by this binary.
+    pub const fn supported() -> Self {
+        Self {
+            min: MIN_SUPPORTED_PROTOCOL_VERSION,
+            max: PROTOCOL_VERSION,
+        }
+    }
+
+    /// Returns the highest version within both ranges, or `None` if the
+    /// ranges do not overlap.
+    pub fn negotiate(&self, other: &Self) -> Option<ProtocolVersion> {
+        let version = self.max.min(other.max);
+        (version >= self.min.max(other.min)).then_some(version)
+    }
+}
+
+impl std::fmt::Display for ProtocolVersionRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..={}", self.min, self.max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(min: ProtocolVersion, max: ProtocolVersion) -> ProtocolVersionRange {
+        ProtocolVersionRange { min, max }
+    }
+
+    #[test]
+    fn negotiates_highest_common_version() {
+        assert_eq!(range(1, 3).negotiate(&range(2, 5)), Some(3));
+        assert_eq!(range(2, 5).negotiate(&range(1, 3)), Some(3));
+        assert_eq!(range(1, 1).negotiate(&range(1, 1)), Some(1));
+        let supported = ProtocolVersionRange::supported();
+        assert_eq!(supported.negotiate(&supported), Some(PROTOCOL_VERSION));
+    }
+
+    #[test]
+    fn rejects_older_versions() {
+        let supported = ProtocolVersionRange::supported();
+        assert_eq!(supported.negotiate(&range(1, PROTOCOL_VERSION - 1)), None);
+    }
+
+    #[test]
+    fn rejects_disjoint_ranges() {
+        assert_eq!(range(1, 2).negotiate(&range(3, 4)), None);
+        assert_eq!(range(3, 4).negotiate(&range(1, 2)), None);
+    }
+}
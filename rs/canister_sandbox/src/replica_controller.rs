@@ -1,7 +1,16 @@
 pub mod active_execution_state_registry;
+mod compilation_coordinator;
 pub mod controller_service_impl;
+mod cpu_affinity;
+mod execution_slots;
+mod invalid_exec_id_limiter;
 pub mod launch_as_process;
+mod log_rate_limiter;
 mod process_exe_and_args;
 pub mod process_os_metrics;
+pub mod request_audit_log;
+pub mod resource_limits;
 mod sandbox_process_eviction;
+mod sandbox_process_pool;
 pub mod sandboxed_execution_controller;
+mod serialized_module_files;
@@ -1,6 +1,8 @@
+use super::resource_limits::ResourceLimitViolation;
 use crate::protocol::id::ExecId;
 use crate::protocol::structs::SandboxExecOutput;
 use ic_embedders::wasm_executor::SliceExecutionOutput;
+use ic_types::CanisterId;
 /// Execution state registry for sandbox processes.
 ///
 /// This tracks the "active" executions on a sandbox process and
@@ -19,18 +21,75 @@ use ic_embedders::wasm_executor::SliceExecutionOutput;
 /// to ensure multiple executions on a single sandbox process can be
 /// told apart and addressed individually.
 ///
+/// Completions are dispatched by execution ID, so the sandbox process may
+/// complete executions in any order, e.g. when it runs several of them
+/// concurrently. Each caller blocks on the completion of its own execution,
+/// so no ordering across executions is needed: executions that upper layers
+/// order relative to each other are never active at the same time.
+///
 /// There is one "ActiveExecutionStateRegistry" object per sandbox process,
 /// and one "ActiveExecutionState" object per ongoing execution in a specific
 /// sandbox process.
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 #[allow(clippy::large_enum_variant)]
 pub enum CompletionResult {
     Paused(SliceExecutionOutput),
-    Finished(SandboxExecOutput),
+    /// The execution finished. Carries the Wasm backtrace reported by the
+    /// sandbox process if the execution trapped.
+    Finished(SandboxExecOutput, Option<String>),
+    /// The sandbox process stopped before the execution completed.
+    Terminated(SandboxTermination),
+}
+
+/// The reason for which a sandbox process stopped serving its executions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SandboxTermination {
+    /// The replica controller terminated the process for exceeding its
+    /// resource limits.
+    ResourceLimitExceeded(ResourceLimitViolation),
+    /// The process exited unexpectedly, e.g. because it crashed.
+    Crashed,
+    /// The replica controller killed the process because one of its
+    /// executions did not complete within the given wall-clock time.
+    ExecutionTimedOut(Duration),
+    /// The replica controller killed the process because it had not sent a
+    /// heartbeat for the given duration while executions were active.
+    Unresponsive(Duration),
+    /// The replica controller killed the process because it kept sending
+    /// requests for non-existent executions.
+    Misbehaved,
+}
+
+/// The phase of an execution on a sandbox process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExecutionPhase {
+    /// The execution is running a slice or about to start one.
+    Running,
+    /// The execution is paused between slices and waits to be resumed or
+    /// aborted.
+    Paused,
 }
 
+/// A snapshot of an execution on a sandbox process, for diagnosing stuck
+/// executions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecutionInfo {
+    pub exec_id: ExecId,
+    /// The canister served by the sandbox process, if known.
+    pub canister_id: Option<CanisterId>,
+    pub phase: ExecutionPhase,
+    /// How long the execution has been in its current phase.
+    pub age: Duration,
+}
+
+/// The number of completed executions remembered per sandbox process to tell
+/// duplicate completions apart from completions of unknown executions.
+const COMPLETED_EXECUTIONS_TO_REMEMBER: usize = 100;
+
 type CompletionFunction = Box<dyn FnOnce(ExecId, CompletionResult) + Sync + Send + 'static>;
 
 /// Represents an execution in progress on the sandbox process.
@@ -46,12 +105,43 @@ pub(crate) struct ActiveExecutionState {
     /// execution has been called (it is not legal to receive two
     /// completions for the same execution).
     completion: Option<CompletionFunction>,
+    /// The time at which the execution was registered.
+    started_at: Instant,
+    /// The CPU time of the sandbox process when the execution was first seen
+    /// running by the monitoring thread.
+    process_cpu_time_at_start: Option<Duration>,
+}
+
+impl ActiveExecutionState {
+    pub(crate) fn into_completion(self) -> Option<CompletionFunction> {
+        self.completion
+    }
 }
 
 /// Multiple execution states, keyed by the unique ID used to identify
 /// it across processes.
 pub struct ActiveExecutionStateRegistry {
     states: Mutex<HashMap<ExecId, ActiveExecutionState>>,
+    /// The executions that are paused on the sandbox process and the times at
+    /// which they got paused. They are not in `states` until they are resumed.
+    /// Always locked after `states`.
+    paused_executions: Mutex<HashMap<ExecId, Instant>>,
+    /// The most recently completed or paused executions, oldest first. An
+    /// execution is removed once it gets resumed. Always locked after
+    /// `states`.
+    completed_executions: Mutex<VecDeque<ExecId>>,
+    /// The time at which the sandbox process last sent a heartbeat.
+    last_heartbeat: Mutex<Instant>,
+    /// Set once the sandbox process is being drained, e.g. after it was
+    /// recycled. No new executions are started on it, but the ones in progress
+    /// or paused may continue.
+    draining: AtomicBool,
+    /// Set once the sandbox process is being terminated or has exited.
+    /// Executions registered afterwards fail right away.
+    termination: Mutex<Option<SandboxTermination>>,
+    /// The canister served by the sandbox process. `None` while the process
+    /// waits in the sandbox process pool.
+    canister_id: Mutex<Option<CanisterId>>,
 }
 
 /// All active executions on a sandbox process.
@@ -59,6 +149,12 @@ impl ActiveExecutionStateRegistry {
     pub fn new() -> Self {
         Self {
             states: Mutex::new(HashMap::new()),
+            paused_executions: Mutex::new(HashMap::new()),
+            completed_executions: Mutex::new(VecDeque::new()),
+            last_heartbeat: Mutex::new(Instant::now()),
+            draining: AtomicBool::new(false),
+            termination: Mutex::new(None),
+            canister_id: Mutex::new(None),
         }
     }
 
@@ -85,19 +181,169 @@ impl ActiveExecutionStateRegistry {
         let completion = Box::new(completion);
         let state = ActiveExecutionState {
             completion: Some(Box::new(completion)),
+            started_at: Instant::now(),
+            process_cpu_time_at_start: None,
         };
         let mut mut_states = self.states.lock().unwrap();
         mut_states.insert(exec_id, state);
+        self.completed_executions
+            .lock()
+            .unwrap()
+            .retain(|completed| *completed != exec_id);
+        drop(mut_states);
+
+        // The sandbox process may have been terminated before the execution
+        // got registered, in which case nobody else would complete it.
+        if let Some(termination) = self.termination() {
+            if let Some(completion) = self.take(exec_id) {
+                completion(exec_id, CompletionResult::Terminated(termination));
+            }
+        }
     }
 
     /// Removes the given [`ExecId`] and returns its [`CompletionFunction`].
+    /// The execution is then considered completed until it gets registered
+    /// again.
     pub fn take(&self, exec_id: ExecId) -> Option<CompletionFunction> {
         let mut mut_states = self.states.lock().unwrap();
-        if let Some(entry) = mut_states.remove(&exec_id) {
-            entry.completion
-        } else {
-            None
+        let entry = mut_states.remove(&exec_id);
+        if entry.is_some() {
+            let mut completed_executions = self.completed_executions.lock().unwrap();
+            if completed_executions.len() == COMPLETED_EXECUTIONS_TO_REMEMBER {
+                completed_executions.pop_front();
+            }
+            completed_executions.push_back(exec_id);
         }
+        entry.and_then(|entry| entry.completion)
+    }
+
+    /// Returns true if the given execution is active, i.e. neither completed
+    /// nor paused.
+    pub fn is_active(&self, exec_id: ExecId) -> bool {
+        self.states.lock().unwrap().contains_key(&exec_id)
+    }
+
+    /// Returns true if the given execution was recently completed or paused
+    /// and has not been resumed since.
+    pub fn is_completed(&self, exec_id: ExecId) -> bool {
+        let _guard = self.states.lock().unwrap();
+        self.completed_executions.lock().unwrap().contains(&exec_id)
+    }
+
+    /// Records that an execution got paused. It is expected to be resumed
+    /// with [`Self::register_execution_with_id`] or aborted, after which
+    /// [`Self::paused_execution_ended`] must be called.
+    pub fn execution_paused(&self, exec_id: ExecId) {
+        let _guard = self.states.lock().unwrap();
+        self.paused_executions
+            .lock()
+            .unwrap()
+            .insert(exec_id, Instant::now());
+    }
+
+    /// Records that a paused execution got resumed or aborted.
+    pub fn paused_execution_ended(&self, exec_id: ExecId) {
+        let _guard = self.states.lock().unwrap();
+        self.paused_executions.lock().unwrap().remove(&exec_id);
+    }
+
+    /// Records that the sandbox process is being drained.
+    pub fn start_draining(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns true if the sandbox process is being drained.
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// Returns the active and paused executions at the given time.
+    pub fn executions(&self, now: Instant) -> Vec<ExecutionInfo> {
+        let canister_id = self.canister_id();
+        let states = self.states.lock().unwrap();
+        let paused_executions = self.paused_executions.lock().unwrap();
+        let running = states
+            .iter()
+            .map(|(exec_id, state)| (*exec_id, ExecutionPhase::Running, state.started_at));
+        let paused = paused_executions
+            .iter()
+            .map(|(exec_id, paused_at)| (*exec_id, ExecutionPhase::Paused, *paused_at));
+        running
+            .chain(paused)
+            .map(|(exec_id, phase, since)| ExecutionInfo {
+                exec_id,
+                canister_id,
+                phase,
+                age: now.saturating_duration_since(since),
+            })
+            .collect()
+    }
+
+    /// Returns for how long the oldest active execution has been running at
+    /// the given time, if there is any active execution.
+    pub fn longest_running_execution(&self, now: Instant) -> Option<Duration> {
+        let states = self.states.lock().unwrap();
+        states
+            .values()
+            .map(|state| now.saturating_duration_since(state.started_at))
+            .max()
+    }
+
+    /// Returns how much CPU time the sandbox process consumed since its oldest
+    /// running execution started, given the current CPU time of the process,
+    /// if there is any running execution. An execution is considered started
+    /// when this function first sees it, so the CPU time consumed until then
+    /// is not counted.
+    pub fn longest_execution_cpu_time(&self, process_cpu_time: Duration) -> Option<Duration> {
+        let mut states = self.states.lock().unwrap();
+        states
+            .values_mut()
+            .map(|state| {
+                let at_start = *state
+                    .process_cpu_time_at_start
+                    .get_or_insert(process_cpu_time);
+                process_cpu_time.saturating_sub(at_start)
+            })
+            .max()
+    }
+
+    /// Records that the sandbox process sent a heartbeat at the given time.
+    pub fn record_heartbeat(&self, now: Instant) {
+        *self.last_heartbeat.lock().unwrap() = now;
+    }
+
+    /// Returns for how long the sandbox process has not sent a heartbeat at
+    /// the given time while it was running executions, if there is any running
+    /// execution. Sandbox processes send heartbeats from their execution
+    /// threads, so the time is counted from the start of the oldest running
+    /// execution at the earliest.
+    pub fn time_without_heartbeat(&self, now: Instant) -> Option<Duration> {
+        let states = self.states.lock().unwrap();
+        let oldest_start = states.values().map(|state| state.started_at).min()?;
+        let last_heartbeat = *self.last_heartbeat.lock().unwrap();
+        Some(now.saturating_duration_since(oldest_start.max(last_heartbeat)))
+    }
+
+    /// Records that the sandbox process is being terminated or has exited.
+    /// Only the first reason is kept, and it is returned.
+    pub fn record_termination(&self, termination: SandboxTermination) -> SandboxTermination {
+        *self.termination.lock().unwrap().get_or_insert(termination)
+    }
+
+    /// Returns the reason for which the sandbox process is being terminated or
+    /// has exited, if any.
+    pub fn termination(&self) -> Option<SandboxTermination> {
+        *self.termination.lock().unwrap()
+    }
+
+    /// Records that the sandbox process serves the given canister.
+    pub fn assign_canister(&self, canister_id: CanisterId) {
+        *self.canister_id.lock().unwrap() = Some(canister_id);
+    }
+
+    /// Returns the canister served by the sandbox process, if any.
+    pub fn canister_id(&self) -> Option<CanisterId> {
+        *self.canister_id.lock().unwrap()
     }
 
     pub(crate) fn take_all(&self) -> HashMap<ExecId, ActiveExecutionState> {
@@ -111,3 +357,166 @@ impl Default for ActiveExecutionStateRegistry {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn execution_registered_after_termination_fails_right_away() {
+        let registry = ActiveExecutionStateRegistry::new();
+        registry.record_termination(SandboxTermination::Crashed);
+
+        let (tx, rx) = std::sync::mpsc::sync_channel(1);
+        registry.register_execution(move |_exec_id, result| tx.send(result).unwrap());
+
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(CompletionResult::Terminated(SandboxTermination::Crashed))
+        ));
+        assert!(registry.take_all().is_empty());
+    }
+
+    #[test]
+    fn longest_running_execution_is_measured_from_registration() {
+        let registry = ActiveExecutionStateRegistry::new();
+        assert_eq!(registry.longest_running_execution(Instant::now()), None);
+
+        let exec_id = registry.register_execution(|_exec_id, _result| {});
+        let later = Instant::now() + Duration::from_secs(10);
+        assert!(registry.longest_running_execution(later).unwrap() >= Duration::from_secs(10));
+
+        registry.take(exec_id);
+        assert_eq!(registry.longest_running_execution(later), None);
+    }
+
+    #[test]
+    fn execution_cpu_time_is_measured_from_first_observation() {
+        let registry = ActiveExecutionStateRegistry::new();
+        assert_eq!(
+            registry.longest_execution_cpu_time(Duration::from_secs(5)),
+            None
+        );
+
+        let first = registry.register_execution(|_exec_id, _result| {});
+        assert_eq!(
+            registry.longest_execution_cpu_time(Duration::from_secs(5)),
+            Some(Duration::ZERO)
+        );
+        registry.register_execution(|_exec_id, _result| {});
+        assert_eq!(
+            registry.longest_execution_cpu_time(Duration::from_secs(8)),
+            Some(Duration::from_secs(3))
+        );
+
+        // A resumed execution is measured from its resumption.
+        registry.take(first);
+        registry.register_execution_with_id(first, |_exec_id, _result| {});
+        assert_eq!(
+            registry.longest_execution_cpu_time(Duration::from_secs(10)),
+            Some(Duration::from_secs(2))
+        );
+    }
+
+    #[test]
+    fn time_without_heartbeat_is_counted_while_executions_run() {
+        let registry = ActiveExecutionStateRegistry::new();
+        let later = Instant::now() + Duration::from_secs(10);
+        assert_eq!(registry.time_without_heartbeat(later), None);
+
+        let exec_id = registry.register_execution(|_exec_id, _result| {});
+        assert!(registry.time_without_heartbeat(later).unwrap() <= Duration::from_secs(10));
+
+        registry.record_heartbeat(later - Duration::from_secs(2));
+        assert_eq!(
+            registry.time_without_heartbeat(later),
+            Some(Duration::from_secs(2))
+        );
+
+        registry.take(exec_id);
+        assert_eq!(registry.time_without_heartbeat(later), None);
+    }
+
+    #[test]
+    fn executions_are_completed_until_resumed() {
+        let registry = ActiveExecutionStateRegistry::new();
+        let exec_id = registry.register_execution(|_exec_id, _result| {});
+        assert!(!registry.is_completed(exec_id));
+
+        assert!(registry.take(exec_id).is_some());
+        assert!(registry.is_completed(exec_id));
+        assert!(registry.take(exec_id).is_none());
+
+        registry.register_execution_with_id(exec_id, |_exec_id, _result| {});
+        assert!(!registry.is_completed(exec_id));
+
+        let unknown = ExecId::new();
+        assert!(registry.take(unknown).is_none());
+        assert!(!registry.is_completed(unknown));
+    }
+
+    #[test]
+    fn only_the_most_recently_completed_executions_are_remembered() {
+        let registry = ActiveExecutionStateRegistry::new();
+        let exec_ids: Vec<_> = (0..=COMPLETED_EXECUTIONS_TO_REMEMBER)
+            .map(|_| registry.register_execution(|_exec_id, _result| {}))
+            .collect();
+        for exec_id in &exec_ids {
+            registry.take(*exec_id);
+        }
+        assert!(!registry.is_completed(exec_ids[0]));
+        assert!(registry.is_completed(exec_ids[1]));
+        assert!(registry.is_completed(*exec_ids.last().unwrap()));
+    }
+
+    #[test]
+    fn completions_can_arrive_out_of_order() {
+        let registry = ActiveExecutionStateRegistry::new();
+        let completed = Arc::new(Mutex::new(vec![]));
+        let exec_ids: Vec<_> = (0..3)
+            .map(|_| {
+                let completed = Arc::clone(&completed);
+                registry.register_execution(move |exec_id, _result| {
+                    completed.lock().unwrap().push(exec_id)
+                })
+            })
+            .collect();
+        for exec_id in [exec_ids[2], exec_ids[0], exec_ids[1]] {
+            let completion = registry.take(exec_id).unwrap();
+            completion(
+                exec_id,
+                CompletionResult::Terminated(SandboxTermination::Crashed),
+            );
+        }
+        assert_eq!(
+            *completed.lock().unwrap(),
+            vec![exec_ids[2], exec_ids[0], exec_ids[1]]
+        );
+        assert!(registry.executions(Instant::now()).is_empty());
+    }
+
+    #[test]
+    fn executions_are_reported_with_their_phase() {
+        let registry = ActiveExecutionStateRegistry::new();
+        let canister_id = CanisterId::from_u64(42);
+        registry.assign_canister(canister_id);
+        let running = registry.register_execution(|_exec_id, _result| {});
+        let paused = registry.register_execution(|_exec_id, _result| {});
+        registry.take(paused);
+        registry.execution_paused(paused);
+
+        let later = Instant::now() + Duration::from_secs(10);
+        let mut executions = registry.executions(later);
+        executions.sort_by_key(|execution| execution.phase != ExecutionPhase::Running);
+        assert_eq!(executions.len(), 2);
+        assert_eq!(executions[0].exec_id, running);
+        assert_eq!(executions[0].phase, ExecutionPhase::Running);
+        assert_eq!(executions[1].exec_id, paused);
+        assert_eq!(executions[1].phase, ExecutionPhase::Paused);
+        for execution in executions {
+            assert_eq!(execution.canister_id, Some(canister_id));
+            assert!(execution.age >= Duration::from_secs(10));
+        }
+    }
+}
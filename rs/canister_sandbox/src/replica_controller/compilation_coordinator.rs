@@ -0,0 +1,100 @@
+//! Coordination of Wasm compilations across sandbox processes.
+//!
+//! Canisters that run the same Wasm module have separate sandbox processes. If
+//! they miss the compilation cache at the same time, e.g. right after many of
+//! them were installed with the same module, each of their processes would
+//! compile the module. Instead, the first caller compiles the module while the
+//! other callers wait until the compilation finished and then find the result
+//! in the compilation cache.
+
+use ic_wasm_types::WasmHash;
+use std::collections::HashSet;
+use std::sync::{Condvar, Mutex};
+
+#[derive(Default)]
+pub(crate) struct CompilationCoordinator {
+    // The hashes of the modules that are being compiled.
+    in_progress: Mutex<HashSet<WasmHash>>,
+    // Signalled whenever a compilation finished.
+    finished: Condvar,
+}
+
+/// Marks a module as being compiled until the guard is dropped.
+pub(crate) struct CompilationGuard<'a> {
+    coordinator: &'a CompilationCoordinator,
+    wasm_hash: WasmHash,
+}
+
+impl CompilationCoordinator {
+    /// Returns a guard that makes the caller responsible for compiling the
+    /// given module, unless another caller is compiling it already. In that
+    /// case, blocks until the other compilation finished and returns `None`.
+    ///
+    /// The caller should store the result of its compilation in the
+    /// compilation cache before dropping the guard.
+    pub fn start_or_wait(&self, wasm_hash: WasmHash) -> Option<CompilationGuard<'_>> {
+        let mut in_progress = self.in_progress.lock().unwrap();
+        if in_progress.insert(wasm_hash.clone()) {
+            return Some(CompilationGuard {
+                coordinator: self,
+                wasm_hash,
+            });
+        }
+        let _ = self
+            .finished
+            .wait_while(in_progress, |in_progress| in_progress.contains(&wasm_hash))
+            .unwrap();
+        None
+    }
+}
+
+impl Drop for CompilationGuard<'_> {
+    fn drop(&mut self) {
+        self.coordinator
+            .in_progress
+            .lock()
+            .unwrap()
+            .remove(&self.wasm_hash);
+        self.coordinator.finished.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn same_module_is_not_compiled_concurrently() {
+        let coordinator = Arc::new(CompilationCoordinator::default());
+        let compiling = Arc::new(AtomicBool::new(false));
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let coordinator = Arc::clone(&coordinator);
+                let compiling = Arc::clone(&compiling);
+                std::thread::spawn(move || {
+                    if let Some(_guard) = coordinator.start_or_wait(WasmHash::from([1; 32])) {
+                        assert!(!compiling.swap(true, Ordering::SeqCst));
+                        std::thread::sleep(Duration::from_millis(1));
+                        compiling.store(false, Ordering::SeqCst);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn different_modules_are_compiled_concurrently() {
+        let coordinator = CompilationCoordinator::default();
+        let guard = coordinator.start_or_wait(WasmHash::from([1; 32]));
+        assert!(guard.is_some());
+        assert!(coordinator.start_or_wait(WasmHash::from([2; 32])).is_some());
+        drop(guard);
+        assert!(coordinator.start_or_wait(WasmHash::from([1; 32])).is_some());
+    }
+}
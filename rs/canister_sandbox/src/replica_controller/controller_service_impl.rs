@@ -12,87 +12,542 @@
 /// completion closure).
 use crate::controller_service::ControllerService;
 use crate::protocol;
+use crate::protocol::id::{ExecId, TraceId};
+use crate::protocol::structs::SandboxExecOutput;
+use crate::protocol::version::{ProtocolVersion, REJECTED_REPLIES_VERSION};
 use crate::rpc;
+use crate::rpc::RejectCode;
+use ic_config::embedders::{
+    Config as EmbeddersConfig, SandboxInvalidExecIdLimit, SandboxLogRateLimit,
+};
+use ic_config::flag_status::FlagStatus;
 use ic_logger::{debug, error, info, trace, ReplicaLogger};
+use ic_metrics::buckets::decimal_buckets_with_zero;
+use ic_metrics::MetricsRegistry;
+use prometheus::{HistogramVec, IntCounter, IntCounterVec};
 
 use super::active_execution_state_registry::ActiveExecutionStateRegistry;
-use super::active_execution_state_registry::CompletionResult;
+use super::active_execution_state_registry::{CompletionResult, SandboxTermination};
+use super::invalid_exec_id_limiter::InvalidExecIdLimiter;
+use super::log_rate_limiter::LogRateLimiter;
+use super::request_audit_log::{AuditRecord, RequestAuditLog};
 
-use std::sync::Arc;
+use once_cell::sync::OnceCell;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+const EXECUTION_FINISHED: &str = "execution_finished";
+const EXECUTION_OUTPUT_CHUNK: &str = "execution_output_chunk";
+const EXECUTION_PAUSED: &str = "execution_paused";
+const LOG_VIA_REPLICA: &str = "log_via_replica";
+const HEARTBEAT: &str = "heartbeat";
+const TRAP_BACKTRACE: &str = "trap_backtrace";
+
+const SANDBOXED_EXECUTION_MISBEHAVING_SANDBOX: &str = "sandboxed_execution_misbehaving_sandbox";
+const SANDBOXED_EXECUTION_DOUBLE_COMPLETION: &str = "sandboxed_execution_double_completion";
+const SANDBOXED_EXECUTION_REQUEST_AFTER_COMPLETION: &str =
+    "sandboxed_execution_request_after_completion";
+const SANDBOXED_EXECUTION_MALFORMED_REQUEST: &str = "sandboxed_execution_malformed_request";
+
+/// Metrics of the requests issued by sandbox processes, shared by the
+/// controller services of all sandbox processes.
+pub struct ControllerServiceMetrics {
+    // Time spent handling the requests, by request type. The count of the
+    // histogram is the number of handled requests.
+    request_duration: HistogramVec,
+    // Requests referring to an execution that is not active, by request type.
+    invalid_exec_id: IntCounterVec,
+    // Log messages dropped because a sandbox process exceeded its log rate
+    // limit.
+    dropped_log_messages: IntCounter,
+    // Bytes of execution outputs streamed in chunks.
+    streamed_output_bytes: IntCounter,
+    // Critical error for sandbox processes killed for exceeding the limit of
+    // requests referring to non-existent executions.
+    critical_error_misbehaving_sandbox: IntCounter,
+    // Critical error for completions or pauses of an execution that was
+    // already completed or paused.
+    critical_error_double_completion: IntCounter,
+    // Critical error for requests referring to an execution that was already
+    // completed or paused.
+    critical_error_request_after_completion: IntCounter,
+    // Critical error for requests referring to an execution or a canister
+    // that the sandbox process never served.
+    critical_error_malformed_request: IntCounter,
+    // Spawned sandbox processes, by the protocol version agreed on with them.
+    sandbox_protocol_version: IntCounterVec,
+}
+
+impl ControllerServiceMetrics {
+    pub fn new(metrics_registry: &MetricsRegistry) -> Self {
+        Self {
+            request_duration: metrics_registry.histogram_vec(
+                "sandboxed_execution_controller_request_duration_seconds",
+                "The time to handle a request from a sandbox process in the replica controller",
+                decimal_buckets_with_zero(-6, 0),
+                &["request"],
+            ),
+            invalid_exec_id: metrics_registry.int_counter_vec(
+                "sandboxed_execution_controller_invalid_exec_id_total",
+                "The number of requests from sandbox processes referring to a non-existent execution",
+                &["request"],
+            ),
+            dropped_log_messages: metrics_registry.int_counter(
+                "sandboxed_execution_controller_dropped_log_messages_total",
+                "The number of log messages from sandbox processes dropped due to rate limiting",
+            ),
+            streamed_output_bytes: metrics_registry.int_counter(
+                "sandboxed_execution_controller_streamed_output_bytes_total",
+                "The number of bytes of execution outputs that sandbox processes streamed in chunks",
+            ),
+            critical_error_misbehaving_sandbox: metrics_registry
+                .error_counter(SANDBOXED_EXECUTION_MISBEHAVING_SANDBOX),
+            critical_error_double_completion: metrics_registry
+                .error_counter(SANDBOXED_EXECUTION_DOUBLE_COMPLETION),
+            critical_error_request_after_completion: metrics_registry
+                .error_counter(SANDBOXED_EXECUTION_REQUEST_AFTER_COMPLETION),
+            critical_error_malformed_request: metrics_registry
+                .error_counter(SANDBOXED_EXECUTION_MALFORMED_REQUEST),
+            sandbox_protocol_version: metrics_registry.int_counter_vec(
+                "sandboxed_execution_controller_sandbox_protocol_version_total",
+                "The number of spawned sandbox processes by the IPC protocol version agreed on with them",
+                &["version"],
+            ),
+        }
+    }
+
+    pub(crate) fn observe_sandbox_protocol_version(&self, version: ProtocolVersion) {
+        self.sandbox_protocol_version
+            .with_label_values(&[&version.to_string()])
+            .inc();
+    }
+}
+
+/// The configuration of the controller services of all sandbox processes.
+#[derive(Clone, Copy, Debug)]
+pub struct ControllerServiceConfig {
+    pub audit_requests: FlagStatus,
+    pub log_rate_limit: SandboxLogRateLimit,
+    pub invalid_exec_id_limit: Option<SandboxInvalidExecIdLimit>,
+}
+
+impl From<&EmbeddersConfig> for ControllerServiceConfig {
+    fn from(config: &EmbeddersConfig) -> Self {
+        Self {
+            audit_requests: config.audit_sandbox_requests,
+            log_rate_limit: config.sandbox_log_rate_limit,
+            invalid_exec_id_limit: config.sandbox_invalid_exec_id_limit,
+        }
+    }
+}
 
 pub struct ControllerServiceImpl {
     registry: Arc<ActiveExecutionStateRegistry>,
+    metrics: Arc<ControllerServiceMetrics>,
+    audit_log: Option<RequestAuditLog>,
+    log_rate_limiter: Mutex<LogRateLimiter>,
+    // `None` if sandbox processes are not killed for invalid execution ids.
+    invalid_exec_id_limiter: Option<Mutex<InvalidExecIdLimiter>>,
+    // The chunks of the execution outputs that are being streamed, by
+    // execution.
+    output_chunks: Mutex<HashMap<ExecId, Vec<u8>>>,
+    // The maximum total size of the chunks of an execution output.
+    max_output_size: usize,
+    // The backtraces of trapping executions that have not finished yet, by
+    // execution. An entry is removed when its execution finishes or pauses,
+    // or when the sandbox process terminates. Aborted executions are paused,
+    // so they have no entry.
+    trap_backtraces: Mutex<HashMap<ExecId, String>>,
+    // The pid of the sandbox process, set once the process has been spawned.
+    sandbox_pid: OnceCell<u32>,
+    // The protocol version agreed on with the sandbox process, set once the
+    // process has been spawned.
+    protocol_version: OnceCell<ProtocolVersion>,
     log: ReplicaLogger,
 }
 
 impl ControllerServiceImpl {
     /// Create new instance of controller service.
-    pub fn new(registry: Arc<ActiveExecutionStateRegistry>, log: ReplicaLogger) -> Arc<Self> {
-        Arc::new(ControllerServiceImpl { registry, log })
+    pub fn new(
+        registry: Arc<ActiveExecutionStateRegistry>,
+        metrics: Arc<ControllerServiceMetrics>,
+        config: ControllerServiceConfig,
+        log: ReplicaLogger,
+    ) -> Arc<Self> {
+        let audit_log = match config.audit_requests {
+            FlagStatus::Enabled => Some(RequestAuditLog::new(log.clone())),
+            FlagStatus::Disabled => None,
+        };
+        Arc::new(ControllerServiceImpl {
+            registry,
+            metrics,
+            audit_log,
+            log_rate_limiter: Mutex::new(LogRateLimiter::new(
+                config.log_rate_limit,
+                Instant::now(),
+            )),
+            invalid_exec_id_limiter: config
+                .invalid_exec_id_limit
+                .map(|limit| Mutex::new(InvalidExecIdLimiter::new(limit, Instant::now()))),
+            output_chunks: Mutex::new(HashMap::new()),
+            max_output_size: protocol::ctlsvc::MAX_EXECUTION_OUTPUT_SIZE,
+            trap_backtraces: Mutex::new(HashMap::new()),
+            sandbox_pid: OnceCell::new(),
+            protocol_version: OnceCell::new(),
+            log,
+        })
     }
 
-    pub fn flush_with_errors(&self) {
-        let execs = self.registry.take_all();
-        for (_exec_id, entry) in execs {
-            // here we could do something like
-            // entry.completion(exec_id, CompletionResult::Finished(exec_failed_result));
-            // to pass the error to whoever is waiting, but since in the current setup we want to panic
-            // there anyway, it is sufficient to just drop the closure
-            // so the channel on which sandboxed_execution_controller is waiting will get disconnected
-            drop(entry)
+    /// Sets the pid of the sandbox process served by this controller service.
+    pub fn set_sandbox_pid(&self, pid: u32) {
+        let _ = self.sandbox_pid.set(pid);
+    }
+
+    /// Sets the protocol version agreed on with the sandbox process.
+    pub fn set_protocol_version(&self, version: ProtocolVersion) {
+        let _ = self.protocol_version.set(version);
+    }
+
+    /// Returns the error for a request rejected with the given code. The code
+    /// is only sent back to sandbox processes that understand it, the others
+    /// get no reply as before.
+    fn reject(&self, code: RejectCode) -> rpc::Error {
+        match self.protocol_version.get() {
+            Some(version) if *version >= REJECTED_REPLIES_VERSION => rpc::Error::Rejected(code),
+            _ => rpc::Error::ServerError,
         }
     }
-}
 
-impl ControllerService for ControllerServiceImpl {
-    fn execution_finished(
+    /// Accounts for a request of the given type that referred to a
+    /// non-existent execution. If the limit of such requests is enabled, a
+    /// sandbox process exceeding it is killed: its active executions fail, and
+    /// the next execution of the canister starts a new sandbox process.
+    fn on_invalid_exec_id(&self, request: &str) {
+        self.metrics
+            .invalid_exec_id
+            .with_label_values(&[request])
+            .inc();
+        let limit_exceeded = self
+            .invalid_exec_id_limiter
+            .as_ref()
+            .is_some_and(|limiter| limiter.lock().unwrap().record(Instant::now()));
+        if limit_exceeded {
+            self.kill_misbehaving_sandbox("sent too many requests for non-existent executions");
+        }
+    }
+
+    /// Kills the sandbox process for violating the protocol in the given way.
+    fn kill_misbehaving_sandbox(&self, violation: &str) {
+        if self.registry.termination().is_some() {
+            return;
+        }
+        let pid = match self.sandbox_pid.get() {
+            Some(pid) => *pid,
+            None => return,
+        };
+        error!(
+            self.log,
+            "{}: Killing sandbox process with pid {} of canister {} that {}",
+            SANDBOXED_EXECUTION_MISBEHAVING_SANDBOX,
+            pid,
+            self.canister(),
+            violation
+        );
+        self.metrics.critical_error_misbehaving_sandbox.inc();
+        self.registry
+            .record_termination(SandboxTermination::Misbehaved);
+        let _ = nix::sys::signal::kill(
+            nix::unistd::Pid::from_raw(pid as i32),
+            nix::sys::signal::Signal::SIGKILL,
+        );
+    }
+
+    /// Reports a completion or pause of the given execution that is not
+    /// active. While the sandbox process is alive, this is a protocol
+    /// violation: either the execution was already completed or paused, or it
+    /// never existed. After the process was terminated, a late request is
+    /// expected and only logged. Returns the error to reply with.
+    fn on_unknown_completion(
         &self,
-        req: protocol::ctlsvc::ExecutionFinishedRequest,
-    ) -> rpc::Call<protocol::ctlsvc::ExecutionFinishedReply> {
-        let exec_id = req.exec_id;
-        let exec_output = req.exec_output;
+        request: &str,
+        exec_id: ExecId,
+        trace_id: TraceId,
+    ) -> rpc::Error {
+        if self.registry.termination().is_some() {
+            error!(
+                self.log,
+                "Terminated sandbox process sent {} for non-existent execution {} ({})",
+                request,
+                exec_id,
+                trace_id
+            );
+            return self.reject(RejectCode::ShuttingDown);
+        } else if self.registry.is_completed(exec_id) {
+            error!(
+                self.log,
+                "{}: Sandbox process of canister {} sent {} for already completed execution {} ({})",
+                SANDBOXED_EXECUTION_DOUBLE_COMPLETION,
+                self.canister(),
+                request,
+                exec_id,
+                trace_id
+            );
+            self.metrics.critical_error_double_completion.inc();
+        } else {
+            error!(
+                self.log,
+                "{}: Sandbox process of canister {} sent {} for non-existent execution {} ({})",
+                SANDBOXED_EXECUTION_MALFORMED_REQUEST,
+                self.canister(),
+                request,
+                exec_id,
+                trace_id
+            );
+            self.metrics.critical_error_malformed_request.inc();
+        }
+        self.on_invalid_exec_id(request);
+        self.reject(RejectCode::UnknownExecId)
+    }
+
+    /// Completes the given finished execution with its output.
+    fn finish_execution(
+        &self,
+        request: &str,
+        exec_id: ExecId,
+        trace_id: TraceId,
+        exec_output: SandboxExecOutput,
+    ) -> rpc::RPCResult<()> {
+        self.audit(AuditRecord::ExecutionFinished {
+            exec_id,
+            executed_instructions: exec_output.slice.executed_instructions,
+            removed_cycles: exec_output
+                .state
+                .as_ref()
+                .map(|state| state.system_state_changes.removed_cycles()),
+        });
         // Sandbox is telling us that execution has finished for this
         // ID. We will validate this ID by looking up the execution
         // state for this ID and extracting its closure. If the closure
         // is not there, then the sandbox is "buggy" (or worse) and
         // trying to either issue "double-completions" or completions
         // for non-existent executions. Deal with this by ignoring
-        // such calls (but log them).
-        // Maybe we also want to deal with this in more radical ways
-        // (e.g. forcibly terminate the sandbox process).
-        let reply = self.registry.take(exec_id).map_or_else(
+        // such calls (but report them), and by killing the sandbox process
+        // if it keeps doing so.
+        let trap_backtrace = self.trap_backtraces.lock().unwrap().remove(&exec_id);
+        self.registry.take(exec_id).map_or_else(
             || {
                 // Should we log the entire erroneous request? It
                 // could both be large and hold canister-sensitive
                 // data, so maybe this is not advisable.
-                error!(
-                    self.log,
-                    "Wasm sandbox process sent completion for non-existent execution {}", &exec_id
-                );
-                Err(rpc::Error::ServerError)
+                Err(self.on_unknown_completion(request, exec_id, trace_id))
             },
             |completion| {
-                completion(exec_id, CompletionResult::Finished(exec_output));
-                Ok(protocol::ctlsvc::ExecutionFinishedReply {})
+                completion(
+                    exec_id,
+                    CompletionResult::Finished(exec_output, trap_backtrace),
+                );
+                Ok(())
             },
-        );
+        )
+    }
+
+    fn canister(&self) -> String {
+        self.registry
+            .canister_id()
+            .map_or_else(|| "unassigned".to_string(), |id| id.to_string())
+    }
+
+    fn audit(&self, record: AuditRecord) {
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.record(self.registry.canister_id(), record);
+        }
+    }
+
+    /// Fails all active executions after the connection to the sandbox
+    /// process was closed. Unless the controller terminated the process on
+    /// purpose, the process is considered to have crashed.
+    pub fn flush_with_errors(&self) {
+        let termination = self
+            .registry
+            .record_termination(SandboxTermination::Crashed);
+        let execs = self.registry.take_all();
+        self.output_chunks.lock().unwrap().clear();
+        self.trap_backtraces.lock().unwrap().clear();
+        for (exec_id, entry) in execs {
+            if let Some(completion) = entry.into_completion() {
+                match termination {
+                    SandboxTermination::ResourceLimitExceeded(violation) => error!(
+                        self.log,
+                        "Execution {} failed because the sandbox process exceeded its {}",
+                        exec_id,
+                        violation
+                    ),
+                    SandboxTermination::Crashed => error!(
+                        self.log,
+                        "Execution {} failed because the sandbox process exited unexpectedly",
+                        exec_id
+                    ),
+                    SandboxTermination::ExecutionTimedOut(limit) => error!(
+                        self.log,
+                        "Execution {} failed because the sandbox process was killed after an execution exceeded {:?}",
+                        exec_id,
+                        limit
+                    ),
+                    SandboxTermination::Unresponsive(elapsed) => error!(
+                        self.log,
+                        "Execution {} failed because the sandbox process was killed after not sending a heartbeat for {:?}",
+                        exec_id,
+                        elapsed
+                    ),
+                    SandboxTermination::Misbehaved => error!(
+                        self.log,
+                        "Execution {} failed because the sandbox process was killed for misbehaving",
+                        exec_id
+                    ),
+                }
+                completion(exec_id, CompletionResult::Terminated(termination));
+            }
+        }
+    }
+}
+
+impl ControllerService for ControllerServiceImpl {
+    fn execution_finished(
+        &self,
+        req: protocol::ctlsvc::ExecutionFinishedRequest,
+    ) -> rpc::Call<protocol::ctlsvc::ExecutionFinishedReply> {
+        let _timer = self
+            .metrics
+            .request_duration
+            .with_label_values(&[EXECUTION_FINISHED])
+            .start_timer();
+        let reply = self
+            .finish_execution(
+                EXECUTION_FINISHED,
+                req.exec_id,
+                req.trace_id,
+                req.exec_output,
+            )
+            .map(|()| protocol::ctlsvc::ExecutionFinishedReply {});
         rpc::Call::new_resolved(reply)
     }
 
+    fn execution_output_chunk(
+        &self,
+        req: protocol::ctlsvc::ExecutionOutputChunkRequest,
+    ) -> rpc::Call<protocol::ctlsvc::ExecutionOutputChunkReply> {
+        let _timer = self
+            .metrics
+            .request_duration
+            .with_label_values(&[EXECUTION_OUTPUT_CHUNK])
+            .start_timer();
+        let protocol::ctlsvc::ExecutionOutputChunkRequest {
+            exec_id,
+            trace_id,
+            chunk,
+            last,
+        } = req;
+        let mut output_chunks = self.output_chunks.lock().unwrap();
+        if !self.registry.is_active(exec_id) {
+            output_chunks.remove(&exec_id);
+            drop(output_chunks);
+            let err = self.on_unknown_completion(EXECUTION_OUTPUT_CHUNK, exec_id, trace_id);
+            return rpc::Call::new_resolved(Err(err));
+        }
+        self.metrics
+            .streamed_output_bytes
+            .inc_by(chunk.len() as u64);
+        let output = output_chunks.entry(exec_id).or_default();
+        if output.len().saturating_add(chunk.len()) > self.max_output_size {
+            output_chunks.remove(&exec_id);
+            drop(output_chunks);
+            self.kill_misbehaving_sandbox(&format!(
+                "streamed an output of execution {} larger than {} bytes",
+                exec_id, self.max_output_size
+            ));
+            return rpc::Call::new_resolved(Err(self.reject(RejectCode::Internal)));
+        }
+        output.extend_from_slice(&chunk);
+        if !last {
+            return rpc::Call::new_resolved(Ok(protocol::ctlsvc::ExecutionOutputChunkReply {}));
+        }
+        let output = output_chunks.remove(&exec_id).unwrap_or_default();
+        drop(output_chunks);
+        let reply = match bincode::deserialize::<SandboxExecOutput>(&output) {
+            Ok(exec_output) => {
+                self.finish_execution(EXECUTION_OUTPUT_CHUNK, exec_id, trace_id, exec_output)
+            }
+            Err(err) => {
+                error!(
+                    self.log,
+                    "{}: Sandbox process of canister {} streamed a malformed output of execution {} ({}): {}",
+                    SANDBOXED_EXECUTION_MALFORMED_REQUEST,
+                    self.canister(),
+                    exec_id,
+                    trace_id,
+                    err
+                );
+                self.metrics.critical_error_malformed_request.inc();
+                self.kill_misbehaving_sandbox("streamed a malformed execution output");
+                Err(self.reject(RejectCode::Internal))
+            }
+        };
+        rpc::Call::new_resolved(reply.map(|()| protocol::ctlsvc::ExecutionOutputChunkReply {}))
+    }
+
+    fn trap_backtrace(
+        &self,
+        req: protocol::ctlsvc::TrapBacktraceRequest,
+    ) -> rpc::Call<protocol::ctlsvc::TrapBacktraceReply> {
+        let _timer = self
+            .metrics
+            .request_duration
+            .with_label_values(&[TRAP_BACKTRACE])
+            .start_timer();
+        let protocol::ctlsvc::TrapBacktraceRequest {
+            exec_id,
+            trace_id,
+            mut backtrace,
+        } = req;
+        if !self.registry.is_active(exec_id) {
+            let err = self.on_unknown_completion(TRAP_BACKTRACE, exec_id, trace_id);
+            return rpc::Call::new_resolved(Err(err));
+        }
+        if backtrace.len() > protocol::ctlsvc::MAX_TRAP_BACKTRACE_SIZE {
+            let mut end = protocol::ctlsvc::MAX_TRAP_BACKTRACE_SIZE;
+            while !backtrace.is_char_boundary(end) {
+                end -= 1;
+            }
+            backtrace.truncate(end);
+        }
+        self.trap_backtraces
+            .lock()
+            .unwrap()
+            .insert(exec_id, backtrace);
+        rpc::Call::new_resolved(Ok(protocol::ctlsvc::TrapBacktraceReply {}))
+    }
+
     fn execution_paused(
         &self,
         req: protocol::ctlsvc::ExecutionPausedRequest,
     ) -> rpc::Call<protocol::ctlsvc::ExecutionPausedReply> {
+        let _timer = self
+            .metrics
+            .request_duration
+            .with_label_values(&[EXECUTION_PAUSED])
+            .start_timer();
         let exec_id = req.exec_id;
+        let trace_id = req.trace_id;
         let slice = req.slice;
+        self.audit(AuditRecord::ExecutionPaused {
+            exec_id,
+            executed_instructions: slice.executed_instructions,
+        });
+        // Only finished executions have a backtrace.
+        self.trap_backtraces.lock().unwrap().remove(&exec_id);
         let reply = self.registry.take(exec_id).map_or_else(
-            || {
-                error!(
-                    self.log,
-                    "Wasm sandbox process paused non-existent execution {}", &exec_id
-                );
-                Err(rpc::Error::ServerError)
-            },
+            || Err(self.on_unknown_completion(EXECUTION_PAUSED, exec_id, trace_id)),
             |completion| {
                 completion(exec_id, CompletionResult::Paused(slice));
                 Ok(protocol::ctlsvc::ExecutionPausedReply {})
@@ -102,13 +557,255 @@ impl ControllerService for ControllerServiceImpl {
     }
 
     fn log_via_replica(&self, req: protocol::logging::LogRequest) -> rpc::Call<()> {
-        let protocol::logging::LogRequest(level, message) = req;
+        let _timer = self
+            .metrics
+            .request_duration
+            .with_label_values(&[LOG_VIA_REPLICA])
+            .start_timer();
+        let protocol::logging::LogRequest {
+            level,
+            message,
+            canister_id,
+            exec_id,
+            trace_id,
+        } = req;
+        self.audit(AuditRecord::LogViaReplica {
+            exec_id,
+            message_len: message.len(),
+        });
+        if self.registry.termination().is_none() {
+            if let Some(exec_id) = exec_id {
+                if self.registry.is_completed(exec_id) {
+                    error!(
+                        self.log,
+                        "{}: Sandbox process of canister {} sent a log message for already completed execution {}",
+                        SANDBOXED_EXECUTION_REQUEST_AFTER_COMPLETION,
+                        self.canister(),
+                        exec_id
+                    );
+                    self.metrics.critical_error_request_after_completion.inc();
+                }
+            }
+            if let (Some(assigned), Some(requested)) = (self.registry.canister_id(), canister_id) {
+                if assigned != requested {
+                    error!(
+                        self.log,
+                        "{}: Sandbox process of canister {} sent a log message for canister {}",
+                        SANDBOXED_EXECUTION_MALFORMED_REQUEST,
+                        assigned,
+                        requested
+                    );
+                    self.metrics.critical_error_malformed_request.inc();
+                }
+            }
+        }
+        let admitted = self.log_rate_limiter.lock().unwrap().admit(Instant::now());
+        let suppressed = match admitted {
+            Some(suppressed) => suppressed,
+            None => {
+                self.metrics.dropped_log_messages.inc();
+                return rpc::Call::new_resolved(Ok(()));
+            }
+        };
+        // The sandbox process is not trusted, so the canister known to the
+        // controller takes precedence over the one in the request.
+        let canister = self
+            .registry
+            .canister_id()
+            .or(canister_id)
+            .map_or_else(|| "unknown".to_string(), |id| id.to_string());
+        let mut context = format!("canister_id={}", canister);
+        if let Some(exec_id) = exec_id {
+            context.push_str(&format!(" {}", exec_id));
+        }
+        if let Some(trace_id) = trace_id {
+            context.push_str(&format!(" {}", trace_id));
+        }
+        if suppressed > 0 {
+            info!(
+                self.log,
+                "CANISTER_SANDBOX [{}]: {} messages suppressed", context, suppressed
+            );
+        }
         match level {
-            protocol::logging::LogLevel::Info => info!(self.log, "CANISTER_SANDBOX: {}", message),
-            protocol::logging::LogLevel::Debug => debug!(self.log, "CANISTER_SANDBOX: {}", message),
-            protocol::logging::LogLevel::Trace => trace!(self.log, "CANISTER_SANDBOX: {}", message),
+            protocol::logging::LogLevel::Info => {
+                info!(self.log, "CANISTER_SANDBOX [{}]: {}", context, message)
+            }
+            protocol::logging::LogLevel::Debug => {
+                debug!(self.log, "CANISTER_SANDBOX [{}]: {}", context, message)
+            }
+            protocol::logging::LogLevel::Trace => {
+                trace!(self.log, "CANISTER_SANDBOX [{}]: {}", context, message)
+            }
         }
 
         rpc::Call::new_resolved(Ok(()))
     }
+
+    fn heartbeat(
+        &self,
+        _req: protocol::ctlsvc::HeartbeatRequest,
+    ) -> rpc::Call<protocol::ctlsvc::HeartbeatReply> {
+        let _timer = self
+            .metrics
+            .request_duration
+            .with_label_values(&[HEARTBEAT])
+            .start_timer();
+        self.registry.record_heartbeat(Instant::now());
+        rpc::Call::new_resolved(Ok(protocol::ctlsvc::HeartbeatReply {}))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::version::PROTOCOL_VERSION;
+    use ic_embedders::wasm_executor::SliceExecutionOutput;
+    use ic_logger::replica_logger::no_op_logger;
+    use ic_types::NumInstructions;
+
+    fn service(protocol_version: ProtocolVersion) -> Arc<ControllerServiceImpl> {
+        let service = ControllerServiceImpl::new(
+            Arc::new(ActiveExecutionStateRegistry::new()),
+            Arc::new(ControllerServiceMetrics::new(&MetricsRegistry::new())),
+            ControllerServiceConfig::from(&EmbeddersConfig::default()),
+            no_op_logger(),
+        );
+        service.set_protocol_version(protocol_version);
+        service
+    }
+
+    fn output_chunk(exec_id: ExecId) -> protocol::ctlsvc::ExecutionOutputChunkRequest {
+        protocol::ctlsvc::ExecutionOutputChunkRequest {
+            exec_id,
+            trace_id: TraceId::new(),
+            chunk: vec![],
+            last: true,
+        }
+    }
+
+    #[test]
+    fn request_for_unknown_execution_is_rejected() {
+        let service = service(PROTOCOL_VERSION);
+        assert!(matches!(
+            service
+                .execution_output_chunk(output_chunk(ExecId::new()))
+                .sync(),
+            Err(rpc::Error::Rejected(RejectCode::UnknownExecId))
+        ));
+    }
+
+    #[test]
+    fn request_after_termination_is_rejected() {
+        let service = service(PROTOCOL_VERSION);
+        service
+            .registry
+            .record_termination(SandboxTermination::Crashed);
+        assert!(matches!(
+            service
+                .execution_output_chunk(output_chunk(ExecId::new()))
+                .sync(),
+            Err(rpc::Error::Rejected(RejectCode::ShuttingDown))
+        ));
+    }
+
+    #[test]
+    fn malformed_output_is_rejected() {
+        let service = service(PROTOCOL_VERSION);
+        let exec_id = service.registry.register_execution(|_exec_id, _result| {});
+        assert!(matches!(
+            service.execution_output_chunk(output_chunk(exec_id)).sync(),
+            Err(rpc::Error::Rejected(RejectCode::Internal))
+        ));
+    }
+
+    #[test]
+    fn oversized_output_is_rejected() {
+        let mut service = service(PROTOCOL_VERSION);
+        Arc::get_mut(&mut service).unwrap().max_output_size = 4;
+        let exec_id = service.registry.register_execution(|_exec_id, _result| {});
+        let chunk = |chunk: Vec<u8>| protocol::ctlsvc::ExecutionOutputChunkRequest {
+            chunk,
+            last: false,
+            ..output_chunk(exec_id)
+        };
+        service
+            .execution_output_chunk(chunk(vec![0; 3]))
+            .sync()
+            .unwrap();
+        assert!(matches!(
+            service.execution_output_chunk(chunk(vec![0; 2])).sync(),
+            Err(rpc::Error::Rejected(RejectCode::Internal))
+        ));
+        assert!(service.output_chunks.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn long_trap_backtrace_is_truncated() {
+        let service = service(PROTOCOL_VERSION);
+        let exec_id = service.registry.register_execution(|_exec_id, _result| {});
+        service
+            .trap_backtrace(protocol::ctlsvc::TrapBacktraceRequest {
+                exec_id,
+                trace_id: TraceId::new(),
+                // A multi-byte character, so that the limit is not on a
+                // character boundary.
+                backtrace: "€".repeat(protocol::ctlsvc::MAX_TRAP_BACKTRACE_SIZE),
+            })
+            .sync()
+            .unwrap();
+        let backtrace = service.trap_backtraces.lock().unwrap().remove(&exec_id);
+        let len = backtrace.unwrap().len();
+        assert!(len <= protocol::ctlsvc::MAX_TRAP_BACKTRACE_SIZE);
+        assert!(len > protocol::ctlsvc::MAX_TRAP_BACKTRACE_SIZE - "€".len());
+    }
+
+    fn send_trap_backtrace(service: &ControllerServiceImpl, exec_id: ExecId) {
+        service
+            .trap_backtrace(protocol::ctlsvc::TrapBacktraceRequest {
+                exec_id,
+                trace_id: TraceId::new(),
+                backtrace: "backtrace".to_string(),
+            })
+            .sync()
+            .unwrap();
+    }
+
+    #[test]
+    fn trap_backtrace_is_dropped_when_execution_pauses() {
+        let service = service(PROTOCOL_VERSION);
+        let exec_id = service.registry.register_execution(|_exec_id, _result| {});
+        send_trap_backtrace(&service, exec_id);
+        service
+            .execution_paused(protocol::ctlsvc::ExecutionPausedRequest {
+                exec_id,
+                trace_id: TraceId::new(),
+                slice: SliceExecutionOutput {
+                    executed_instructions: NumInstructions::from(0),
+                },
+            })
+            .sync()
+            .unwrap();
+        assert!(service.trap_backtraces.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn trap_backtrace_is_dropped_when_sandbox_process_terminates() {
+        let service = service(PROTOCOL_VERSION);
+        let exec_id = service.registry.register_execution(|_exec_id, _result| {});
+        send_trap_backtrace(&service, exec_id);
+        service.flush_with_errors();
+        assert!(service.trap_backtraces.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn older_sandbox_processes_get_no_reject_code() {
+        let service = service(REJECTED_REPLIES_VERSION - 1);
+        assert!(matches!(
+            service
+                .execution_output_chunk(output_chunk(ExecId::new()))
+                .sync(),
+            Err(rpc::Error::ServerError)
+        ));
+    }
 }
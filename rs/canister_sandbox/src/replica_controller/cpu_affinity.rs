@@ -0,0 +1,119 @@
+//! Pinning of sandbox processes to CPUs.
+//!
+//! The replica config may pin the sandbox processes of specific canisters to
+//! sets of CPUs. The affinity is applied by the replica controller when a
+//! sandbox process is assigned to such a canister, i.e. after the process has
+//! spawned its threads, so it is set for each thread of the process.
+
+use ic_config::embedders::SandboxCpuAffinity;
+use ic_types::CanisterId;
+use std::collections::HashMap;
+
+/// The configured CPUs of each pinned canister.
+pub(crate) struct CpuAffinities(HashMap<CanisterId, Vec<usize>>);
+
+impl CpuAffinities {
+    pub fn new(config: &[SandboxCpuAffinity]) -> Self {
+        let mut affinities = HashMap::new();
+        for affinity in config {
+            for canister_id in &affinity.canisters {
+                affinities.insert(*canister_id, affinity.cpus.clone());
+            }
+        }
+        Self(affinities)
+    }
+
+    /// Returns the CPUs to which the sandbox process of the given canister is
+    /// pinned, if any.
+    pub fn cpus(&self, canister_id: &CanisterId) -> Option<&[usize]> {
+        self.0.get(canister_id).map(|cpus| cpus.as_slice())
+    }
+}
+
+/// Restricts all threads of the given process to the given CPUs.
+#[cfg(target_os = "linux")]
+pub(crate) fn set_process_affinity(pid: u32, cpus: &[usize]) -> std::io::Result<()> {
+    // SAFETY: `cpu_set_t` is a plain bit set for which all zeros is valid.
+    let mut cpu_set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    for cpu in cpus.iter().filter(|cpu| **cpu < libc::CPU_SETSIZE as usize) {
+        // SAFETY: `cpu_set` is a valid `cpu_set_t` and `cpu` is within the set.
+        unsafe { libc::CPU_SET(*cpu, &mut cpu_set) };
+    }
+    let tasks = std::path::Path::new("/proc")
+        .join(pid.to_string())
+        .join("task");
+    for task in std::fs::read_dir(tasks)? {
+        let tid = match task?.file_name().to_str().and_then(|tid| tid.parse().ok()) {
+            Some(tid) => tid,
+            None => continue,
+        };
+        // SAFETY: `cpu_set` is a valid, initialized `cpu_set_t`.
+        let result = unsafe {
+            libc::sched_setaffinity(tid, std::mem::size_of::<libc::cpu_set_t>(), &cpu_set)
+        };
+        if result != 0 {
+            let err = std::io::Error::last_os_error();
+            // The thread may have exited in the meantime.
+            if err.raw_os_error() != Some(libc::ESRCH) {
+                return Err(err);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the CPUs on which the main thread of the given process may run.
+#[cfg(target_os = "linux")]
+pub(crate) fn get_process_affinity(pid: u32) -> std::io::Result<Vec<usize>> {
+    // SAFETY: `cpu_set_t` is a plain bit set for which all zeros is valid.
+    let mut cpu_set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    // SAFETY: `cpu_set` is a valid, writable `cpu_set_t`.
+    let result = unsafe {
+        libc::sched_getaffinity(
+            pid as libc::pid_t,
+            std::mem::size_of::<libc::cpu_set_t>(),
+            &mut cpu_set,
+        )
+    };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok((0..libc::CPU_SETSIZE as usize)
+        // SAFETY: `cpu_set` is initialized and `cpu` is within the set.
+        .filter(|cpu| unsafe { libc::CPU_ISSET(*cpu, &cpu_set) })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn later_entries_override_earlier_ones() {
+        let canister_1 = CanisterId::from_u64(1);
+        let canister_2 = CanisterId::from_u64(2);
+        let affinities = CpuAffinities::new(&[
+            SandboxCpuAffinity {
+                canisters: vec![canister_1, canister_2],
+                cpus: vec![0, 1],
+            },
+            SandboxCpuAffinity {
+                canisters: vec![canister_2],
+                cpus: vec![2],
+            },
+        ]);
+        assert_eq!(affinities.cpus(&canister_1), Some(&[0, 1][..]));
+        assert_eq!(affinities.cpus(&canister_2), Some(&[2][..]));
+        assert_eq!(affinities.cpus(&CanisterId::from_u64(3)), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn affinity_of_own_process_can_be_set_to_itself() {
+        let pid = std::process::id();
+        let cpus = get_process_affinity(pid).unwrap();
+        assert!(!cpus.is_empty());
+        set_process_affinity(pid, &cpus).unwrap();
+        assert_eq!(get_process_affinity(pid).unwrap(), cpus);
+    }
+}
@@ -0,0 +1,116 @@
+//! Limits the number of executions that run concurrently on a sandbox
+//! process.
+//!
+//! Every running execution holds its Wasm and stable memory deltas in the
+//! sandbox process, so the memory usage of a process grows with the number of
+//! executions it runs at the same time, e.g. concurrent queries. An execution
+//! takes a slot before it is started or resumed on the process and releases
+//! it once its slice completes. Executions beyond the limit wait in the
+//! replica until a slot becomes free. Paused executions do not hold a slot.
+
+use std::sync::{Condvar, Mutex};
+
+pub(crate) struct ExecutionSlots {
+    // The maximum number of slots, or `None` if unlimited.
+    limit: Option<usize>,
+    // The number of taken slots.
+    taken: Mutex<usize>,
+    // Signalled whenever a slot is released.
+    released: Condvar,
+}
+
+impl ExecutionSlots {
+    /// Creates slots for at most `limit` concurrent executions. A limit of
+    /// zero is treated as one so that executions always make progress.
+    pub fn new(limit: Option<usize>) -> Self {
+        Self {
+            limit: limit.map(|limit| limit.max(1)),
+            taken: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Takes a slot if one is free.
+    pub fn try_acquire(&self) -> Option<ExecutionSlot<'_>> {
+        let mut taken = self.taken.lock().unwrap();
+        if self.limit.map_or(false, |limit| *taken >= limit) {
+            return None;
+        }
+        *taken += 1;
+        Some(ExecutionSlot { slots: self })
+    }
+
+    /// Blocks until a slot is free and takes it.
+    pub fn acquire(&self) -> ExecutionSlot<'_> {
+        let taken = self.taken.lock().unwrap();
+        let mut taken = self
+            .released
+            .wait_while(taken, |taken| {
+                self.limit.map_or(false, |limit| *taken >= limit)
+            })
+            .unwrap();
+        *taken += 1;
+        ExecutionSlot { slots: self }
+    }
+
+    #[cfg(test)]
+    fn taken(&self) -> usize {
+        *self.taken.lock().unwrap()
+    }
+}
+
+/// A slot of a running execution, released on drop.
+pub(crate) struct ExecutionSlot<'a> {
+    slots: &'a ExecutionSlots,
+}
+
+impl Drop for ExecutionSlot<'_> {
+    fn drop(&mut self) {
+        *self.slots.taken.lock().unwrap() -= 1;
+        self.slots.released.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn slots_are_limited_until_released() {
+        let slots = ExecutionSlots::new(Some(2));
+        let first = slots.try_acquire().unwrap();
+        let _second = slots.try_acquire().unwrap();
+        assert!(slots.try_acquire().is_none());
+
+        drop(first);
+        assert!(slots.try_acquire().is_some());
+        assert_eq!(slots.taken(), 1);
+    }
+
+    #[test]
+    fn unlimited_slots_are_always_free() {
+        let slots = ExecutionSlots::new(None);
+        let taken: Vec<_> = (0..100).map(|_| slots.try_acquire().unwrap()).collect();
+        assert_eq!(slots.taken(), taken.len());
+    }
+
+    #[test]
+    fn waiting_execution_takes_released_slot() {
+        let slots = Arc::new(ExecutionSlots::new(Some(1)));
+        let slot = slots.acquire();
+        let waiter = {
+            let slots = Arc::clone(&slots);
+            std::thread::spawn(move || {
+                let _slot = slots.acquire();
+            })
+        };
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(!waiter.is_finished());
+
+        drop(slot);
+        waiter.join().unwrap();
+        assert_eq!(slots.taken(), 0);
+    }
+}
@@ -0,0 +1,71 @@
+//! Detection of sandbox processes that keep referring to non-existent
+//! executions.
+//!
+//! A correct sandbox process only completes executions that the replica
+//! controller started, so an occasional invalid execution id is a bug, while
+//! many of them indicate a compromised process. The limiter counts invalid
+//! execution ids within a fixed time window and reports when the configured
+//! limit is exceeded.
+
+use ic_config::embedders::SandboxInvalidExecIdLimit;
+use std::time::Instant;
+
+pub(crate) struct InvalidExecIdLimiter {
+    limit: SandboxInvalidExecIdLimit,
+    window_start: Instant,
+    count: u32,
+}
+
+impl InvalidExecIdLimiter {
+    pub fn new(limit: SandboxInvalidExecIdLimit, now: Instant) -> Self {
+        Self {
+            limit,
+            window_start: now,
+            count: 0,
+        }
+    }
+
+    /// Records an invalid execution id and returns true if the limit is
+    /// exceeded within the current window.
+    pub fn record(&mut self, now: Instant) -> bool {
+        if now.saturating_duration_since(self.window_start) >= self.limit.window {
+            self.window_start = now;
+            self.count = 0;
+        }
+        self.count = self.count.saturating_add(1);
+        self.count > self.limit.max_invalid_exec_ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn limit_is_exceeded_within_window() {
+        let limit = SandboxInvalidExecIdLimit {
+            max_invalid_exec_ids: 3,
+            window: Duration::from_secs(60),
+        };
+        let start = Instant::now();
+        let mut limiter = InvalidExecIdLimiter::new(limit, start);
+        for _ in 0..3 {
+            assert!(!limiter.record(start));
+        }
+        assert!(limiter.record(start + Duration::from_secs(59)));
+    }
+
+    #[test]
+    fn count_is_reset_in_next_window() {
+        let limit = SandboxInvalidExecIdLimit {
+            max_invalid_exec_ids: 1,
+            window: Duration::from_secs(60),
+        };
+        let start = Instant::now();
+        let mut limiter = InvalidExecIdLimiter::new(limit, start);
+        assert!(!limiter.record(start));
+        assert!(!limiter.record(start + Duration::from_secs(60)));
+        assert!(limiter.record(start + Duration::from_secs(61)));
+    }
+}
@@ -11,6 +11,8 @@ use crate::{
     protocol::{
         self,
         launchersvc::{LaunchSandboxReply, LaunchSandboxRequest},
+        sbxsvc::{HandshakeReply, HandshakeRequest, TerminateRequest},
+        version::{ProtocolVersion, ProtocolVersionRange},
     },
     rpc,
     sandbox_client_stub::SandboxClientStub,
@@ -77,7 +79,7 @@ pub fn spawn_launcher_process(
 pub fn spawn_canister_sandbox_process(
     exec_path: &str,
     argv: &[String],
-    canister_id: CanisterId,
+    canister_id: Option<CanisterId>,
     controller_service: Arc<super::controller_service_impl::ControllerServiceImpl>,
     launcher: &dyn LauncherService,
 ) -> std::io::Result<(Arc<dyn SandboxService>, u32, std::thread::JoinHandle<()>)> {
@@ -131,15 +133,20 @@ pub fn spawn_canister_sandbox_process(
     Ok((svc, pid, thread_handle))
 }
 
-/// Spawns a sandbox process for the given canister.
+/// Spawns a sandbox process for the given canister, or an idle sandbox
+/// process for the sandbox process pool if no canister is given. Returns the
+/// protocol version agreed on with the sandbox process along with its handle
+/// and pid.
 pub fn create_sandbox_process(
     controller_service: Arc<super::controller_service_impl::ControllerServiceImpl>,
     launcher_service: &dyn LauncherService,
-    canister_id: CanisterId,
+    canister_id: Option<CanisterId>,
     mut argv: Vec<String>,
-) -> std::io::Result<(Arc<dyn SandboxService>, u32)> {
+) -> std::io::Result<(Arc<dyn SandboxService>, u32, ProtocolVersion)> {
     assert!(!argv.is_empty());
-    argv.push(canister_id.to_string());
+    if let Some(canister_id) = canister_id {
+        argv.push(canister_id.to_string());
+    }
 
     let (sandbox_handle, pid, _recv_thread_handle) = spawn_canister_sandbox_process(
         &argv[0],
@@ -147,7 +154,34 @@ pub fn create_sandbox_process(
         canister_id,
         controller_service,
         launcher_service,
-    )
-    .expect("Failed to start sandbox process");
-    Ok((sandbox_handle, pid))
+    )?;
+    let protocol_version = handshake(&*sandbox_handle)?;
+    Ok((sandbox_handle, pid, protocol_version))
+}
+
+/// Agrees on the protocol version with a freshly spawned sandbox process.
+/// Terminates the process if the two sides have no version in common.
+fn handshake(sandbox_handle: &dyn SandboxService) -> std::io::Result<ProtocolVersion> {
+    let supported = ProtocolVersionRange::supported();
+    let HandshakeReply {
+        supported: sandbox_supported,
+        version,
+    } = sandbox_handle
+        .handshake(HandshakeRequest { supported })
+        .sync()?;
+    match version {
+        Some(version) if supported.negotiate(&sandbox_supported) == Some(version) => Ok(version),
+        _ => {
+            sandbox_handle
+                .terminate(TerminateRequest {})
+                .on_completion(|_| ());
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!(
+                    "Incompatible sandbox process: it supports protocol versions {}, the replica {}",
+                    sandbox_supported, supported
+                ),
+            ))
+        }
+    }
 }
@@ -0,0 +1,82 @@
+//! Rate limiting of the messages that a sandbox process logs via the replica.
+//!
+//! A token bucket allows each sandbox process to log bursts of messages while
+//! bounding its average rate, so that a misbehaving canister cannot saturate
+//! the replica log. Dropped messages are counted, and their number is reported
+//! when the next message is admitted.
+
+use ic_config::embedders::SandboxLogRateLimit;
+use std::time::Instant;
+
+pub(crate) struct LogRateLimiter {
+    limit: SandboxLogRateLimit,
+    tokens: f64,
+    last_refill: Instant,
+    suppressed: u64,
+}
+
+impl LogRateLimiter {
+    pub fn new(limit: SandboxLogRateLimit, now: Instant) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst as f64,
+            last_refill: now,
+            suppressed: 0,
+        }
+    }
+
+    /// Returns the number of messages dropped since the previous admitted
+    /// message if the message is admitted, or `None` if it is dropped.
+    pub fn admit(&mut self, now: Instant) -> Option<u64> {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.last_refill = now;
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.limit.messages_per_second as f64)
+            .min(self.limit.burst as f64);
+        if self.tokens < 1.0 {
+            self.suppressed += 1;
+            return None;
+        }
+        self.tokens -= 1.0;
+        Some(std::mem::take(&mut self.suppressed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn admits_burst_and_then_average_rate() {
+        let limit = SandboxLogRateLimit {
+            messages_per_second: 10,
+            burst: 5,
+        };
+        let start = Instant::now();
+        let mut limiter = LogRateLimiter::new(limit, start);
+        for _ in 0..5 {
+            assert_eq!(limiter.admit(start), Some(0));
+        }
+        assert_eq!(limiter.admit(start), None);
+        assert_eq!(limiter.admit(start), None);
+
+        // One token is refilled every 100ms.
+        let later = start + Duration::from_millis(100);
+        assert_eq!(limiter.admit(later), Some(2));
+        assert_eq!(limiter.admit(later), None);
+    }
+
+    #[test]
+    fn refill_does_not_exceed_burst() {
+        let limit = SandboxLogRateLimit {
+            messages_per_second: 10,
+            burst: 2,
+        };
+        let start = Instant::now();
+        let mut limiter = LogRateLimiter::new(limit, start);
+        let later = start + Duration::from_secs(60);
+        assert_eq!(limiter.admit(later), Some(0));
+        assert_eq!(limiter.admit(later), Some(0));
+        assert_eq!(limiter.admit(later), None);
+    }
+}
@@ -20,6 +20,33 @@ pub fn get_anon_rss(pid: u32) -> std::io::Result<u64> {
     Ok(rss_anon)
 }
 
+// Returns the resident set size of the given process, including memory that
+// is shared with other processes (= "VmRSS").
+//
+// The size is returned in Kib units.
+pub fn get_rss(pid: u32) -> std::io::Result<u64> {
+    let path = std::path::Path::new("/proc")
+        .join(pid.to_string())
+        .join("status");
+    let data = std::fs::read(path)?;
+    let fields = parse_proc_status(&data);
+
+    get_named_field_kb(&fields, "VmRSS")
+}
+
+// Returns the number of file descriptors that the given process has open.
+pub fn get_open_fd_count(pid: u32) -> std::io::Result<u64> {
+    let path = std::path::Path::new("/proc")
+        .join(pid.to_string())
+        .join("fd");
+    let mut count = 0;
+    for entry in std::fs::read_dir(path)? {
+        entry?;
+        count += 1;
+    }
+    Ok(count)
+}
+
 // Helpers for parsing contents of /proc files below.
 
 // Parse the contents of /proc/<pid>/status as key/value pairs. Note that
@@ -254,6 +281,44 @@ pub fn get_page_allocator_rss(pid: u32) -> std::io::Result<u64> {
     Ok(compute_memory_allocator_rss_total(&vma_infos))
 }
 
+// Returns the CPU time (user and system) consumed so far by the given process.
+pub fn get_cpu_time(pid: u32) -> std::io::Result<std::time::Duration> {
+    let path = std::path::Path::new("/proc")
+        .join(pid.to_string())
+        .join("stat");
+    let data = std::fs::read(path)?;
+    // The number of clock ticks per second is a kernel constant.
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks_per_second <= 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let ticks = parse_proc_stat_cpu_ticks(&data).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Parsing CPU time information from process",
+        )
+    })?;
+
+    Ok(std::time::Duration::from_secs_f64(
+        ticks as f64 / ticks_per_second as f64,
+    ))
+}
+
+// Parse the contents of /proc/<pid>/stat and return the sum of the `utime`
+// and `stime` fields in clock ticks. The second field is the executable name
+// in parentheses, which may itself contain spaces and parentheses, so the
+// remaining fields are located after the last closing parenthesis.
+fn parse_proc_stat_cpu_ticks(data: &[u8]) -> Option<u64> {
+    let end_of_name = data.iter().rposition(|c| *c == b')')?;
+    let fields = std::str::from_utf8(&data[end_of_name + 1..]).ok()?;
+    // Fields after the name start with `state` (field 3); `utime` and `stime`
+    // are fields 14 and 15.
+    let mut fields = fields.split_ascii_whitespace().skip(11);
+    let utime = fields.next()?.parse::<u64>().ok()?;
+    let stime = fields.next()?.parse::<u64>().ok()?;
+    Some(utime + stime)
+}
+
 lazy_static! {
     static ref MEMORY_KIB_PARSE_RE: regex::Regex = regex::Regex::new(r"([0-9]+)[ \t]+kB").unwrap();
 }
@@ -903,6 +968,21 @@ VmFlags: rd wr sh mr mw me ms sd
     fn test_parse_proc_status() {
         let fields = parse_proc_status(PROC_STATUS_TESTCASE.as_bytes());
         assert_eq!(get_named_field_kb(&fields, "RssAnon").unwrap(), 72);
+        assert_eq!(get_named_field_kb(&fields, "VmRSS").unwrap(), 716);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_get_open_fd_count() {
+        // The test process has at least stdin, stdout and stderr open.
+        assert!(get_open_fd_count(std::process::id()).unwrap() >= 3);
+    }
+
+    #[test]
+    fn test_parse_proc_stat() {
+        let stat = b"44572 (cat (1) x) R 44546 44572 44546 34817 44572 4194304 94 0 0 0 17 25 0 0 20 0 1 0 2457 8794112 179 18446744073709551615";
+        assert_eq!(parse_proc_stat_cpu_ticks(stat), Some(42));
+        assert_eq!(parse_proc_stat_cpu_ticks(b"44572 (cat) R 44546"), None);
     }
 
     #[test]
@@ -0,0 +1,174 @@
+//! Audit log of the requests that a sandbox process sends to the replica
+//! controller.
+//!
+//! Each record is a single line with the type of the request, its execution
+//! id and the executed instructions and removed cycles where applicable. The
+//! payloads of the requests, e.g. log messages or memory changes, are never
+//! logged. The number of records is limited per sandbox process, and thus per
+//! canister, so that a busy canister cannot flood the replica log.
+
+use crate::protocol::id::ExecId;
+use ic_logger::{info, ReplicaLogger};
+use ic_types::{CanisterId, Cycles, NumInstructions};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The maximum number of records logged per sandbox process in each
+/// [`AUDIT_LOG_WINDOW`].
+const MAX_AUDIT_RECORDS_PER_WINDOW: usize = 100;
+
+const AUDIT_LOG_WINDOW: Duration = Duration::from_secs(60);
+
+/// A compact description of a request from a sandbox process.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuditRecord {
+    ExecutionFinished {
+        exec_id: ExecId,
+        executed_instructions: NumInstructions,
+        removed_cycles: Option<Cycles>,
+    },
+    ExecutionPaused {
+        exec_id: ExecId,
+        executed_instructions: NumInstructions,
+    },
+    LogViaReplica {
+        exec_id: Option<ExecId>,
+        message_len: usize,
+    },
+}
+
+impl std::fmt::Display for AuditRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ExecutionFinished {
+                exec_id,
+                executed_instructions,
+                removed_cycles,
+            } => {
+                write!(
+                    f,
+                    "request=execution_finished {} instructions={}",
+                    exec_id, executed_instructions
+                )?;
+                match removed_cycles {
+                    Some(cycles) => write!(f, " removed_cycles={}", cycles),
+                    None => Ok(()),
+                }
+            }
+            Self::ExecutionPaused {
+                exec_id,
+                executed_instructions,
+            } => write!(
+                f,
+                "request=execution_paused {} instructions={}",
+                exec_id, executed_instructions
+            ),
+            Self::LogViaReplica {
+                exec_id,
+                message_len,
+            } => {
+                write!(f, "request=log_via_replica")?;
+                if let Some(exec_id) = exec_id {
+                    write!(f, " {}", exec_id)?;
+                }
+                write!(f, " message_len={}", message_len)
+            }
+        }
+    }
+}
+
+/// Decides which records are logged within a fixed time window.
+#[derive(Debug)]
+struct RateLimiter {
+    window_start: Instant,
+    recorded: usize,
+    suppressed: usize,
+}
+
+impl RateLimiter {
+    fn new(now: Instant) -> Self {
+        Self {
+            window_start: now,
+            recorded: 0,
+            suppressed: 0,
+        }
+    }
+
+    /// Returns the number of records suppressed since the previous admitted
+    /// record if the record is admitted, or `None` if it is suppressed.
+    fn admit(&mut self, now: Instant) -> Option<usize> {
+        if now.saturating_duration_since(self.window_start) >= AUDIT_LOG_WINDOW {
+            self.window_start = now;
+            self.recorded = 0;
+        }
+        if self.recorded >= MAX_AUDIT_RECORDS_PER_WINDOW {
+            self.suppressed += 1;
+            return None;
+        }
+        self.recorded += 1;
+        Some(std::mem::take(&mut self.suppressed))
+    }
+}
+
+pub struct RequestAuditLog {
+    rate_limiter: Mutex<RateLimiter>,
+    log: ReplicaLogger,
+}
+
+impl RequestAuditLog {
+    pub fn new(log: ReplicaLogger) -> Self {
+        Self {
+            rate_limiter: Mutex::new(RateLimiter::new(Instant::now())),
+            log,
+        }
+    }
+
+    /// Logs the given record of a request from the sandbox process of the
+    /// given canister, unless the rate limit is exhausted.
+    pub fn record(&self, canister_id: Option<CanisterId>, record: AuditRecord) {
+        let admitted = self.rate_limiter.lock().unwrap().admit(Instant::now());
+        let suppressed = match admitted {
+            Some(suppressed) => suppressed,
+            None => return,
+        };
+        let canister = canister_id.map_or_else(|| "unassigned".to_string(), |id| id.to_string());
+        if suppressed > 0 {
+            info!(
+                self.log,
+                "SANDBOX_AUDIT: canister={} {} ({} records suppressed)",
+                canister,
+                record,
+                suppressed
+            );
+        } else {
+            info!(self.log, "SANDBOX_AUDIT: canister={} {}", canister, record);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limiter_suppresses_records_until_next_window() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new(start);
+        for _ in 0..MAX_AUDIT_RECORDS_PER_WINDOW {
+            assert_eq!(limiter.admit(start), Some(0));
+        }
+        assert_eq!(limiter.admit(start), None);
+        assert_eq!(limiter.admit(start + AUDIT_LOG_WINDOW / 2), None);
+        assert_eq!(limiter.admit(start + AUDIT_LOG_WINDOW), Some(2));
+        assert_eq!(limiter.admit(start + AUDIT_LOG_WINDOW), Some(0));
+    }
+
+    #[test]
+    fn audit_record_does_not_contain_payload() {
+        let record = AuditRecord::LogViaReplica {
+            exec_id: None,
+            message_len: 42,
+        };
+        assert_eq!(record.to_string(), "request=log_via_replica message_len=42");
+    }
+}
@@ -0,0 +1,103 @@
+//! Enforcement of the resource limits of sandbox processes.
+//!
+//! The memory limit is enforced by the kernel through `RLIMIT_DATA`, which the
+//! launcher sets on each sandbox process it spawns. A process that runs out of
+//! memory crashes and is handled like any other crashed sandbox process.
+//!
+//! The CPU time limit applies to each execution slice rather than to the
+//! lifetime of the process, so that long-lived processes are not eventually
+//! terminated. The replica controller periodically measures the CPU time that
+//! each sandbox process consumed since its running executions started and
+//! terminates the processes that exceed [`SandboxResourceLimits`].
+
+use ic_config::embedders::SandboxResourceLimits;
+use ic_types::NumBytes;
+use std::time::Duration;
+
+/// A resource limit that a sandbox process exceeded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResourceLimitViolation {
+    ExecutionCpuTime { usage: Duration, limit: Duration },
+}
+
+impl ResourceLimitViolation {
+    /// Returns a name of the violated limit for use e.g. as a metric label.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ExecutionCpuTime { .. } => "execution_cpu_time",
+        }
+    }
+}
+
+impl std::fmt::Display for ResourceLimitViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ExecutionCpuTime { usage, limit } => {
+                write!(
+                    f,
+                    "execution CPU time limit of {:?} (used {:?})",
+                    limit, usage
+                )
+            }
+        }
+    }
+}
+
+/// Returns the first limit exceeded by the given usage, if any. The
+/// `execution_cpu_time` is the CPU time consumed by the sandbox process since
+/// its longest running execution started, or `None` if it could not be
+/// measured or no execution is running.
+pub fn find_violation(
+    limits: &SandboxResourceLimits,
+    execution_cpu_time: Option<Duration>,
+) -> Option<ResourceLimitViolation> {
+    if let (Some(limit), Some(usage)) = (limits.max_execution_cpu_time, execution_cpu_time) {
+        if usage > limit {
+            return Some(ResourceLimitViolation::ExecutionCpuTime { usage, limit });
+        }
+    }
+    None
+}
+
+/// Limits the data memory of the calling process to the given number of
+/// bytes. Meant to be called in a freshly forked sandbox process before it
+/// executes the sandbox binary, so it must be async-signal-safe.
+pub fn set_memory_limit(max_memory: NumBytes) -> std::io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: max_memory.get() as libc::rlim_t,
+        rlim_max: max_memory.get() as libc::rlim_t,
+    };
+    // SAFETY: `setrlimit` only reads the given struct.
+    if unsafe { libc::setrlimit(libc::RLIMIT_DATA, &limit) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_violation_without_limits() {
+        let limits = SandboxResourceLimits::unlimited();
+        assert_eq!(find_violation(&limits, Some(Duration::MAX)), None);
+    }
+
+    #[test]
+    fn detects_execution_cpu_time_violation() {
+        let limits = SandboxResourceLimits {
+            max_memory: Some(NumBytes::new(1 << 30)),
+            max_execution_cpu_time: Some(Duration::from_secs(60)),
+        };
+        assert_eq!(find_violation(&limits, None), None);
+        assert_eq!(find_violation(&limits, Some(Duration::from_secs(60))), None);
+        assert_eq!(
+            find_violation(&limits, Some(Duration::from_secs(61))),
+            Some(ResourceLimitViolation::ExecutionCpuTime {
+                usage: Duration::from_secs(61),
+                limit: Duration::from_secs(60),
+            })
+        );
+    }
+}
@@ -6,6 +6,8 @@ use ic_types::CanisterId;
 pub(crate) struct EvictionCandidate {
     pub id: CanisterId,
     pub last_used: Instant,
+    /// The resident memory of the sandbox process in KiB, as last measured.
+    pub rss_kib: u64,
 }
 
 /// Evicts the least recently used candidates in order to bring the number of
@@ -23,7 +25,10 @@ pub(crate) struct EvictionCandidate {
 ///    - `min_count_threshold <= N-K <= max_count_threshold`.
 ///    - if there multiple possible values for `K`, then choose the one that
 ///      evicts the most candidates with `last_used < last_used_threshold`.
-/// 4. Return the evicted candidates.
+/// 4. If only some of the candidates with `last_used < last_used_threshold`
+///    are evicted, then evict the ones using the most memory instead of the
+///    least recently used ones.
+/// 5. Return the evicted candidates.
 pub(crate) fn evict(
     mut candidates: Vec<EvictionCandidate>,
     min_count_threshold: usize,
@@ -37,7 +42,7 @@ pub(crate) fn evict(
 
     let mut evicted = vec![];
 
-    for candidate in candidates.into_iter() {
+    for candidate in candidates.iter() {
         if evicted.len() >= evict_at_most {
             // Cannot evict anymore because at least `min_count_threshold`
             // should remain not evicted.
@@ -49,7 +54,22 @@ pub(crate) fn evict(
             // `last_used_threshold` time window. No need to evict more.
             break;
         }
-        evicted.push(candidate)
+        evicted.push(candidate.clone())
+    }
+
+    let idle = candidates
+        .iter()
+        .take_while(|x| x.last_used < last_used_threshold)
+        .count();
+    if evicted.len() < idle {
+        // Not all idle candidates can be evicted, so prefer the ones that
+        // free the most memory. The sort is stable, so ties are still broken
+        // by `last_used`.
+        let mut idle_candidates = candidates;
+        idle_candidates.truncate(idle);
+        idle_candidates.sort_by_key(|x| std::cmp::Reverse(x.rss_kib));
+        idle_candidates.truncate(evicted.len());
+        evicted = idle_candidates;
     }
 
     evicted
@@ -76,6 +96,7 @@ mod tests {
             candidates.push(EvictionCandidate {
                 id: canister_test_id(i),
                 last_used: now,
+                rss_kib: 0,
             });
         }
         assert_eq!(evict(candidates, 0, 10, now,), vec![],);
@@ -89,6 +110,7 @@ mod tests {
             candidates.push(EvictionCandidate {
                 id: canister_test_id(i),
                 last_used: now + Duration::from_secs(100 - i),
+                rss_kib: 0,
             });
         }
         assert_eq!(
@@ -105,6 +127,7 @@ mod tests {
             candidates.push(EvictionCandidate {
                 id: canister_test_id(i),
                 last_used: now - Duration::from_secs(i),
+                rss_kib: 0,
             });
         }
         assert_eq!(
@@ -121,6 +144,7 @@ mod tests {
             candidates.push(EvictionCandidate {
                 id: canister_test_id(i),
                 last_used: now - Duration::from_secs(i + 1),
+                rss_kib: 0,
             });
         }
         assert_eq!(
@@ -137,8 +161,33 @@ mod tests {
             candidates.push(EvictionCandidate {
                 id: canister_test_id(i),
                 last_used: now - Duration::from_secs(i + 1),
+                rss_kib: 0,
             });
         }
         assert_eq!(evict(candidates.clone(), 0, 100, now).len(), 100);
     }
+
+    #[test]
+    fn evict_idle_candidates_using_the_most_memory() {
+        let mut candidates = vec![];
+        let now = Instant::now();
+        for i in 0..10 {
+            candidates.push(EvictionCandidate {
+                id: canister_test_id(i),
+                last_used: now - Duration::from_secs(i + 1),
+                rss_kib: if i % 2 == 0 { 1024 } else { 1 },
+            });
+        }
+        let evicted: Vec<_> = evict(candidates, 5, 10, now)
+            .into_iter()
+            .map(|x| x.id)
+            .collect();
+        assert_eq!(
+            evicted,
+            [8, 6, 4, 2, 0]
+                .into_iter()
+                .map(canister_test_id)
+                .collect::<Vec<_>>()
+        );
+    }
 }
@@ -0,0 +1,127 @@
+//! A pool of idle sandbox processes that are spawned ahead of time.
+//!
+//! Spawning a sandbox process is slow compared to executing a message. A
+//! canister that does not have a sandbox process takes over an idle process
+//! from the pool instead, and the pool is refilled in the background. Idle
+//! processes are replaced by new ones once they have been in the pool for
+//! longer than the configured idle time.
+
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+struct IdleProcess<P> {
+    process: P,
+    spawned_at: Instant,
+}
+
+pub(crate) struct SandboxProcessPool<P> {
+    idle: Mutex<VecDeque<IdleProcess<P>>>,
+    // Signalled whenever the pool has fewer idle processes than its size.
+    refill_needed: Condvar,
+    size: usize,
+    max_idle_time: Duration,
+}
+
+impl<P> SandboxProcessPool<P> {
+    pub fn new(size: usize, max_idle_time: Duration) -> Self {
+        Self {
+            idle: Mutex::new(VecDeque::with_capacity(size)),
+            refill_needed: Condvar::new(),
+            size,
+            max_idle_time,
+        }
+    }
+
+    /// Takes the oldest idle process for which `is_usable` holds. Idle
+    /// processes that are not usable are discarded on the way.
+    pub fn take(&self, is_usable: impl Fn(&P) -> bool) -> Option<P> {
+        let mut idle = self.idle.lock().unwrap();
+        let mut taken = None;
+        while let Some(entry) = idle.pop_front() {
+            if is_usable(&entry.process) {
+                taken = Some(entry.process);
+                break;
+            }
+        }
+        self.refill_needed.notify_one();
+        taken
+    }
+
+    /// Discards the idle processes that are expired at `now` or for which
+    /// `is_usable` does not hold, and returns how many processes are missing
+    /// to fill the pool.
+    pub fn refresh(&self, now: Instant, is_usable: impl Fn(&P) -> bool) -> usize {
+        let mut idle = self.idle.lock().unwrap();
+        idle.retain(|entry| {
+            now.saturating_duration_since(entry.spawned_at) < self.max_idle_time
+                && is_usable(&entry.process)
+        });
+        self.size.saturating_sub(idle.len())
+    }
+
+    /// Adds a process that was spawned at the given time to the pool.
+    pub fn add(&self, process: P, spawned_at: Instant) {
+        let mut idle = self.idle.lock().unwrap();
+        if idle.len() < self.size {
+            idle.push_back(IdleProcess {
+                process,
+                spawned_at,
+            });
+        }
+    }
+
+    /// Discards all idle processes.
+    pub fn clear(&self) {
+        self.idle.lock().unwrap().clear();
+    }
+
+    /// Blocks until the pool needs to be refilled or the timeout elapsed.
+    pub fn wait_for_refill(&self, timeout: Duration) {
+        let idle = self.idle.lock().unwrap();
+        let _ = self
+            .refill_needed
+            .wait_timeout_while(idle, timeout, |idle| idle.len() >= self.size)
+            .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn take_returns_oldest_usable_process() {
+        let pool = SandboxProcessPool::new(3, Duration::from_secs(60));
+        let now = Instant::now();
+        pool.add(1, now);
+        pool.add(2, now);
+        pool.add(3, now);
+        assert_eq!(pool.take(|p| *p != 1), Some(2));
+        assert_eq!(pool.take(|_| true), Some(3));
+        assert_eq!(pool.take(|_| true), None);
+    }
+
+    #[test]
+    fn refresh_discards_expired_and_unusable_processes() {
+        let pool = SandboxProcessPool::new(4, Duration::from_secs(60));
+        let now = Instant::now();
+        pool.add(1, now);
+        pool.add(2, now + Duration::from_secs(30));
+        pool.add(3, now + Duration::from_secs(30));
+        assert_eq!(pool.refresh(now + Duration::from_secs(30), |_| true), 1);
+        assert_eq!(pool.refresh(now + Duration::from_secs(60), |p| *p != 3), 3);
+        assert_eq!(pool.take(|_| true), Some(2));
+    }
+
+    #[test]
+    fn add_does_not_exceed_pool_size() {
+        let pool = SandboxProcessPool::new(1, Duration::from_secs(60));
+        let now = Instant::now();
+        pool.add(1, now);
+        pool.add(2, now);
+        assert_eq!(pool.refresh(now, |_| true), 0);
+        assert_eq!(pool.take(|_| true), Some(1));
+        assert_eq!(pool.take(|_| true), None);
+    }
+}
@@ -1,24 +1,26 @@
 use crate::compiler_sandbox::WasmCompilerProxy;
 use crate::controller_launcher_service::ControllerLauncherService;
 use crate::launcher_service::LauncherService;
-use crate::protocol::id::{ExecId, MemoryId, WasmId};
-use crate::protocol::sbxsvc::MemorySerialization;
+use crate::protocol::id::{ExecId, MemoryId, TraceId, WasmId};
+use crate::protocol::sbxsvc::{MemorySerialization, SerializedModuleFile};
 use crate::protocol::structs::{SandboxExecInput, SandboxExecOutput};
+use crate::protocol::version::ProtocolVersion;
 use crate::sandbox_service::SandboxService;
 use crate::{protocol, rpc};
-use ic_config::embedders::Config as EmbeddersConfig;
+use ic_config::embedders::{
+    Config as EmbeddersConfig, SandboxRecyclePolicy, SandboxResourceLimits,
+};
 use ic_config::flag_status::FlagStatus;
 use ic_embedders::wasm_executor::{
     get_wasm_reserved_pages, wasm_execution_error, CanisterStateChanges, PausedWasmExecution,
     SliceExecutionOutput, WasmExecutionResult, WasmExecutor,
 };
 use ic_embedders::{
-    wasm_utils::WasmImportsDetails, CompilationCache, CompilationResult, WasmExecutionInput,
+    wasm_utils::WasmImportsDetails, CompilationCache, CompilationResult, SerializedModule,
+    WasmExecutionInput,
 };
-use ic_interfaces::execution_environment::{HypervisorError, HypervisorResult};
-#[cfg(target_os = "linux")]
-use ic_logger::warn;
-use ic_logger::{error, info, ReplicaLogger};
+use ic_interfaces::execution_environment::{ExecutionMode, HypervisorError, HypervisorResult};
+use ic_logger::{error, info, warn, ReplicaLogger};
 use ic_metrics::buckets::decimal_buckets_with_zero;
 use ic_metrics::MetricsRegistry;
 use ic_replicated_state::canister_state::execution_state::{
@@ -28,33 +30,47 @@ use ic_replicated_state::{EmbedderCache, ExecutionState, ExportedFunctions, Memo
 use ic_types::ingress::WasmResult;
 use ic_types::methods::{FuncRef, WasmMethod};
 use ic_types::{CanisterId, NumInstructions};
-use ic_wasm_types::CanisterModule;
-#[cfg(target_os = "linux")]
-use prometheus::IntGauge;
-use prometheus::{Histogram, HistogramVec, IntCounter, IntCounterVec};
+use ic_wasm_types::{CanisterModule, WasmHash};
+use prometheus::{Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec};
 use std::collections::{HashMap, VecDeque};
 #[cfg(target_os = "linux")]
 use std::convert::TryInto;
 use std::path::PathBuf;
 use std::process::ExitStatus;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Weak;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 
-use super::active_execution_state_registry::{ActiveExecutionStateRegistry, CompletionResult};
-use super::controller_service_impl::ControllerServiceImpl;
+use super::active_execution_state_registry::{
+    ActiveExecutionStateRegistry, CompletionResult, ExecutionInfo, SandboxTermination,
+};
+use super::compilation_coordinator::{CompilationCoordinator, CompilationGuard};
+use super::controller_service_impl::{
+    ControllerServiceConfig, ControllerServiceImpl, ControllerServiceMetrics,
+};
+use super::cpu_affinity::CpuAffinities;
+use super::execution_slots::{ExecutionSlot, ExecutionSlots};
 use super::launch_as_process::{create_sandbox_process, spawn_launcher_process};
 use super::process_exe_and_args::{
     create_compiler_sandbox_argv, create_launcher_argv, create_sandbox_argv,
 };
 #[cfg(target_os = "linux")]
 use super::process_os_metrics;
+#[cfg(target_os = "linux")]
+use super::resource_limits;
 use super::sandbox_process_eviction::{self, EvictionCandidate};
+use super::sandbox_process_pool::SandboxProcessPool;
+use super::serialized_module_files::SerializedModuleFiles;
 use ic_replicated_state::page_map::PageAllocatorFileDescriptor;
 
 const SANDBOX_PROCESS_UPDATE_INTERVAL: Duration = Duration::from_secs(10);
 
+// How often the watchdog checks for executions that exceeded the wall-clock
+// time limit.
+const SANDBOX_EXECUTION_WATCHDOG_INTERVAL: Duration = Duration::from_secs(1);
+
 // The percentage of sandbox processes to evict in one go in order to amortize
 // for the eviction cost.
 const SANDBOX_PROCESS_EVICTION_PERCENT: usize = 20;
@@ -69,8 +85,68 @@ const EMBEDDER_CACHE_HIT_SANDBOX_EVICTED: &str = "embedder_cache_hit_sandbox_evi
 const EMBEDDER_CACHE_HIT_COMPILATION_ERROR: &str = "embedder_cache_hit_compilation_error";
 const COMPILATION_CACHE_HIT: &str = "compilation_cache_hit";
 const COMPILATION_CACHE_HIT_COMPILATION_ERROR: &str = "compilation_cache_hit_compilation_error";
+// The module was compiled by a concurrent request for another sandbox process,
+// so its compilation was saved.
+const COMPILATION_CACHE_HIT_AFTER_WAIT: &str = "compilation_cache_hit_after_wait";
 const CACHE_MISS: &str = "cache_miss";
 
+// Metric labels for the outcomes of taking a process from the sandbox process
+// pool. Stored in the metric
+// [`SandboxedExecutionMetrics::sandboxed_execution_sandbox_process_pool_lookups`].
+const SANDBOX_PROCESS_POOL_HIT: &str = "hit";
+const SANDBOX_PROCESS_POOL_MISS: &str = "miss";
+
+// Metric labels for the reasons of retiring a sandbox process under the
+// recycle policy. Stored in the metric
+// [`SandboxedExecutionMetrics::sandboxed_execution_sandbox_process_recycles`].
+const SANDBOX_PROCESS_RECYCLED_AFTER_EXECUTIONS: &str = "executions";
+const SANDBOX_PROCESS_RECYCLED_AFTER_UPTIME: &str = "uptime";
+
+// The worker pools of a sandbox process, which run replicated and
+// non-replicated executions separately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ExecutionPool {
+    Replicated,
+    NonReplicated,
+}
+
+impl ExecutionPool {
+    fn of(execution_mode: &ExecutionMode) -> Self {
+        match execution_mode {
+            ExecutionMode::Replicated => Self::Replicated,
+            ExecutionMode::NonReplicated => Self::NonReplicated,
+        }
+    }
+
+    // Metric label stored in the metric
+    // [`SandboxedExecutionMetrics::sandboxed_execution_running_slices`].
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Replicated => "replicated",
+            Self::NonReplicated => "non_replicated",
+        }
+    }
+}
+
+// Limits of the number of executions that run concurrently on a sandbox
+// process, in total and in each of its worker pools. `None` is unlimited.
+#[derive(Clone, Copy, Debug)]
+struct SandboxExecutionLimits {
+    total: Option<usize>,
+    replicated: Option<usize>,
+    non_replicated: Option<usize>,
+}
+
+impl From<&EmbeddersConfig> for SandboxExecutionLimits {
+    fn from(config: &EmbeddersConfig) -> Self {
+        Self {
+            total: config.max_concurrent_executions_per_sandbox,
+            replicated: config.max_concurrent_replicated_executions_per_sandbox,
+            non_replicated: config.max_concurrent_non_replicated_executions_per_sandbox,
+        }
+    }
+}
+
 struct SandboxedExecutionMetrics {
     sandboxed_execution_replica_execute_duration: HistogramVec,
     sandboxed_execution_replica_execute_prepare_duration: HistogramVec,
@@ -89,6 +165,18 @@ struct SandboxedExecutionMetrics {
     sandboxed_execution_subprocess_memfd_rss: Histogram,
     #[cfg(target_os = "linux")]
     sandboxed_execution_subprocess_rss: Histogram,
+    // The resident memory of the sandbox process of each canister with an
+    // active sandbox process.
+    #[cfg(target_os = "linux")]
+    sandboxed_execution_canister_subprocess_rss: IntGaugeVec,
+    // The resource usage of the sandbox processes, sampled by the sandbox
+    // process stats collector.
+    #[cfg(target_os = "linux")]
+    sandboxed_execution_subprocess_vm_rss: Histogram,
+    #[cfg(target_os = "linux")]
+    sandboxed_execution_subprocess_cpu_time: Histogram,
+    #[cfg(target_os = "linux")]
+    sandboxed_execution_subprocess_open_fds: Histogram,
     sandboxed_execution_subprocess_active_last_used: Histogram,
     sandboxed_execution_subprocess_evicted_last_used: Histogram,
     sandboxed_execution_critical_error_invalid_memory_size: IntCounter,
@@ -99,6 +187,8 @@ struct SandboxedExecutionMetrics {
     sandboxed_execution_sandbox_create_exe_state_deserialize_duration: Histogram,
     sandboxed_execution_sandbox_create_exe_state_deserialize_total_duration: Histogram,
     sandboxed_execution_replica_cache_lookups: IntCounterVec,
+    // Time spent waiting for a concurrent compilation of the same module.
+    sandboxed_execution_replica_compilation_wait_duration: Histogram,
     // Executed message slices by type and status.
     sandboxed_execution_executed_message_slices: IntCounterVec,
     // TODO(EXC-376): Remove these metrics once we confirm that no module imports these IC0 methods
@@ -111,6 +201,34 @@ struct SandboxedExecutionMetrics {
     sandboxed_execution_wasm_imports_mint_cycles: IntCounter,
     // Critical error for left execution instructions above the maximum limit allowed.
     sandboxed_execution_instructions_left_error: IntCounter,
+    // Sandbox processes terminated for exceeding their resource limits, by limit.
+    sandboxed_execution_resource_limit_violations: IntCounterVec,
+    // Executions started over after their sandbox process crashed.
+    sandboxed_execution_crash_retries: IntCounter,
+    // Sandbox processes killed because an execution exceeded the wall-clock
+    // time limit.
+    sandboxed_execution_timeouts: IntCounter,
+    // Sandbox processes killed because they stopped sending heartbeats while
+    // executions were active.
+    sandboxed_execution_unresponsive_processes: IntCounter,
+    // Attempts to take a process from the sandbox process pool, by outcome.
+    sandboxed_execution_sandbox_process_pool_lookups: IntCounterVec,
+    // Sandbox processes retired under the recycle policy, by reason.
+    sandboxed_execution_sandbox_process_recycles: IntCounterVec,
+    // Execution slices currently running in sandbox processes, by worker pool.
+    sandboxed_execution_running_slices: IntGaugeVec,
+    // The seccomp profile of the sandbox processes. Set to 1 for the active
+    // profile.
+    sandboxed_execution_syscall_filter_profile: IntGaugeVec,
+    // The number of CPUs on which the sandbox process of a pinned canister may
+    // run, as reported by the OS.
+    sandboxed_execution_sandbox_cpu_affinity: IntGaugeVec,
+    // Executions waiting for a sandbox process that runs the maximum number
+    // of concurrent executions.
+    sandboxed_execution_queued_executions: IntGauge,
+    // Time executions spent waiting for a free execution slot of their sandbox
+    // process.
+    sandboxed_execution_queue_wait_duration: Histogram,
 }
 
 impl SandboxedExecutionMetrics {
@@ -186,6 +304,30 @@ impl SandboxedExecutionMetrics {
                 "The resident memory of a canister sandbox process in KiB",
                 decimal_buckets_with_zero(1, 7), // 10KiB - 50GiB.
             ),
+            #[cfg(target_os = "linux")]
+            sandboxed_execution_canister_subprocess_rss: metrics_registry.int_gauge_vec(
+                "sandboxed_execution_canister_subprocess_rss_kib",
+                "The resident memory in KiB of the active sandbox process of a canister",
+                &["canister_id"],
+            ),
+            #[cfg(target_os = "linux")]
+            sandboxed_execution_subprocess_vm_rss: metrics_registry.histogram(
+                "sandboxed_execution_subprocess_vm_rss_kib",
+                "The resident memory in KiB, including shared memory, of a canister sandbox process",
+                decimal_buckets_with_zero(1, 7), // 10KiB - 50GiB.
+            ),
+            #[cfg(target_os = "linux")]
+            sandboxed_execution_subprocess_cpu_time: metrics_registry.histogram(
+                "sandboxed_execution_subprocess_cpu_time_seconds",
+                "The CPU time consumed so far by a canister sandbox process",
+                decimal_buckets_with_zero(-2, 4), // 10ms - 14h.
+            ),
+            #[cfg(target_os = "linux")]
+            sandboxed_execution_subprocess_open_fds: metrics_registry.histogram(
+                "sandboxed_execution_subprocess_open_fds",
+                "The number of open file descriptors of a canister sandbox process",
+                decimal_buckets_with_zero(0, 4), // 1 - 50000.
+            ),
             sandboxed_execution_subprocess_active_last_used: metrics_registry.histogram(
                 "sandboxed_execution_subprocess_active_last_used_duration_seconds",
                 "Time since the last usage of an active sandbox process in seconds",
@@ -232,6 +374,11 @@ impl SandboxedExecutionMetrics {
                 "sandboxed_execution_replica_cache_lookups",
                 "Results from looking up a wasm module in the embedder cache or compilation cache",
                 &["lookup_result"]),
+            sandboxed_execution_replica_compilation_wait_duration: metrics_registry.histogram(
+                "sandboxed_execution_replica_compilation_wait_duration_seconds",
+                "Time spent waiting for a concurrent compilation of the same Wasm module for another sandbox process",
+                decimal_buckets_with_zero(-4, 1),
+            ),
             sandboxed_execution_wasm_imports_call_cycles_add: metrics_registry.int_counter(
                 "sandboxed_execution_wasm_imports_call_cycles_add",
                 "The number of Wasm modules that import ic0.call_cycles_add",
@@ -262,6 +409,99 @@ impl SandboxedExecutionMetrics {
                 &["api_type", "status"],
             ),
             sandboxed_execution_instructions_left_error: metrics_registry.error_counter("sandboxed_execution_invalid_instructions_left"),
+            sandboxed_execution_resource_limit_violations: metrics_registry.int_counter_vec(
+                "sandboxed_execution_resource_limit_violations_total",
+                "Number of sandbox processes terminated for exceeding their resource limits, by limit.",
+                &["limit"],
+            ),
+            sandboxed_execution_crash_retries: metrics_registry.int_counter(
+                "sandboxed_execution_crash_retries_total",
+                "Number of executions started over after their sandbox process crashed.",
+            ),
+            sandboxed_execution_timeouts: metrics_registry.int_counter(
+                "sandboxed_execution_timeouts_total",
+                "Number of sandbox processes killed because an execution exceeded the wall-clock time limit.",
+            ),
+            sandboxed_execution_unresponsive_processes: metrics_registry.int_counter(
+                "sandboxed_execution_unresponsive_processes_total",
+                "Number of sandbox processes killed because they stopped sending heartbeats during executions.",
+            ),
+            sandboxed_execution_sandbox_process_pool_lookups: metrics_registry.int_counter_vec(
+                "sandboxed_execution_sandbox_process_pool_lookups_total",
+                "Number of attempts to take an idle process from the sandbox process pool, by outcome.",
+                &["status"],
+            ),
+            sandboxed_execution_sandbox_process_recycles: metrics_registry.int_counter_vec(
+                "sandboxed_execution_sandbox_process_recycles_total",
+                "Number of sandbox processes retired under the recycle policy, by reason.",
+                &["reason"],
+            ),
+            sandboxed_execution_running_slices: metrics_registry.int_gauge_vec(
+                "sandboxed_execution_running_slices",
+                "Number of execution slices currently running in sandbox processes, by worker pool.",
+                &["pool"],
+            ),
+            sandboxed_execution_syscall_filter_profile: metrics_registry.int_gauge_vec(
+                "sandboxed_execution_syscall_filter_profile",
+                "The seccomp profile that restricts the system calls of sandbox processes, set to 1 for the active profile.",
+                &["profile"],
+            ),
+            sandboxed_execution_sandbox_cpu_affinity: metrics_registry.int_gauge_vec(
+                "sandboxed_execution_sandbox_cpu_affinity",
+                "The number of CPUs on which the sandbox process of a canister pinned in the replica config may run.",
+                &["canister_id"],
+            ),
+            sandboxed_execution_queued_executions: metrics_registry.int_gauge(
+                "sandboxed_execution_queued_executions",
+                "Number of executions waiting for their sandbox process to finish one of its concurrent executions.",
+            ),
+            sandboxed_execution_queue_wait_duration: metrics_registry.histogram(
+                "sandboxed_execution_queue_wait_duration_seconds",
+                "Time executions spent waiting for their sandbox process to finish one of its concurrent executions.",
+                decimal_buckets_with_zero(-4, 1),
+            ),
+        }
+    }
+
+    // Counts a slice as running in the given worker pool until the returned
+    // guard is dropped.
+    fn start_slice(&self, pool: ExecutionPool) -> RunningSliceGuard {
+        let gauge = self
+            .sandboxed_execution_running_slices
+            .with_label_values(&[pool.as_str()]);
+        gauge.inc();
+        RunningSliceGuard(gauge)
+    }
+
+    // Takes an execution slot of the given worker pool and one of the given
+    // sandbox process, waiting for them to be released if the pool or the
+    // process runs the maximum number of executions.
+    fn acquire_execution_slots<'a>(
+        &self,
+        sandbox_process: &'a SandboxProcess,
+        pool: ExecutionPool,
+    ) -> RunningExecutionSlots<'a> {
+        let pool_slots = sandbox_process.pool_execution_slots(pool);
+        if let Some(pool_slot) = pool_slots.try_acquire() {
+            if let Some(process_slot) = sandbox_process.execution_slots.try_acquire() {
+                self.sandboxed_execution_queue_wait_duration.observe(0.0);
+                return RunningExecutionSlots {
+                    _pool: pool_slot,
+                    _process: process_slot,
+                };
+            }
+        }
+        self.sandboxed_execution_queued_executions.inc();
+        let timer = self.sandboxed_execution_queue_wait_duration.start_timer();
+        // The pool slot is always taken first, so that waiting executions
+        // cannot block each other.
+        let pool_slot = pool_slots.acquire();
+        let process_slot = sandbox_process.execution_slots.acquire();
+        drop(timer);
+        self.sandboxed_execution_queued_executions.dec();
+        RunningExecutionSlots {
+            _pool: pool_slot,
+            _process: process_slot,
         }
     }
 
@@ -308,12 +548,22 @@ impl SandboxProcessRequestHistory {
 
     /// Replays the last actions recorded for this sandbox process to
     /// the given logger.
-    fn replay(&self, logger: &ReplicaLogger, canister_id: CanisterId, pid: u32) {
+    fn replay(
+        &self,
+        logger: &ReplicaLogger,
+        canister_id: CanisterId,
+        pid: u32,
+        protocol_version: ProtocolVersion,
+    ) {
         let guard = self.entries.lock().unwrap();
         for entry in &*guard {
             error!(
                 logger,
-                "History for canister {} with pid {}: {}", canister_id, pid, entry
+                "History for canister {} with pid {} and protocol version {}: {}",
+                canister_id,
+                pid,
+                protocol_version,
+                entry
             );
         }
     }
@@ -330,11 +580,129 @@ pub struct SandboxProcess {
     /// Process id of the backend process.
     pid: u32,
 
+    /// The IPC protocol version agreed on with the backend process.
+    protocol_version: ProtocolVersion,
+
+    /// Limits the number of executions running concurrently on the backend
+    /// process.
+    execution_slots: ExecutionSlots,
+
+    /// Limit the number of replicated and non-replicated executions running
+    /// concurrently on the backend process, so that one kind of executions
+    /// cannot take all slots of the process.
+    replicated_execution_slots: ExecutionSlots,
+    non_replicated_execution_slots: ExecutionSlots,
+
+    /// The resident memory of the backend process in KiB, as last measured
+    /// by the monitoring thread.
+    rss_kib: AtomicU64,
+
+    /// When the backend process was spawned.
+    spawned_at: Instant,
+
+    /// The number of executions started on the backend process.
+    started_executions: AtomicU64,
+
     /// History of operations sent to sandbox process (for crash
     /// diagnostics).
     history: SandboxProcessRequestHistory,
 }
 
+impl SandboxProcess {
+    fn pool_execution_slots(&self, pool: ExecutionPool) -> &ExecutionSlots {
+        match pool {
+            ExecutionPool::Replicated => &self.replicated_execution_slots,
+            ExecutionPool::NonReplicated => &self.non_replicated_execution_slots,
+        }
+    }
+
+    /// Terminates the sandbox process because it exceeded its resource limits.
+    /// Once the connection to the process is closed, its active executions are
+    /// handled like the executions of a crashed process.
+    #[cfg(target_os = "linux")]
+    fn terminate_due_to(&self, violation: ResourceLimitViolation) {
+        self.execution_states
+            .record_termination(SandboxTermination::ResourceLimitExceeded(violation));
+        self.history
+            .record(format!("Terminate(limit_violation={})", violation));
+        self.sandbox_service
+            .terminate(protocol::sbxsvc::TerminateRequest {})
+            .on_completion(|_| {});
+    }
+
+    /// Kills the sandbox process because one of its executions did not
+    /// complete within the given wall-clock time. Unlike a termination
+    /// request, this does not rely on the process still handling requests.
+    fn kill_due_to_timeout(&self, limit: Duration) {
+        self.execution_states
+            .record_termination(SandboxTermination::ExecutionTimedOut(limit));
+        self.history
+            .record(format!("Kill(execution_timeout={:?})", limit));
+        self.kill();
+    }
+
+    /// Kills the sandbox process because it has not sent a heartbeat for the
+    /// given duration while executions were active.
+    fn kill_due_to_missing_heartbeat(&self, elapsed: Duration) {
+        self.execution_states
+            .record_termination(SandboxTermination::Unresponsive(elapsed));
+        self.history
+            .record(format!("Kill(no_heartbeat_for={:?})", elapsed));
+        self.kill();
+    }
+
+    fn kill(&self) {
+        let _ = nix::sys::signal::kill(
+            nix::unistd::Pid::from_raw(self.pid as i32),
+            nix::sys::signal::Signal::SIGKILL,
+        );
+    }
+
+    /// Stops starting new executions on the sandbox process. The executions in
+    /// progress or paused on it continue, and the process terminates once the
+    /// last reference to it is dropped.
+    fn start_draining(&self) {
+        self.execution_states.start_draining();
+        self.history.record("Drain()".to_string());
+    }
+
+    /// Returns true if the sandbox process is being terminated or has exited,
+    /// in which case it must not be used anymore.
+    fn is_terminated(&self) -> bool {
+        self.execution_states.termination().is_some()
+    }
+
+    /// Returns true if the sandbox process is being drained, in which case no
+    /// new executions may start on it.
+    fn is_draining(&self) -> bool {
+        self.execution_states.is_draining()
+    }
+
+    /// Returns true if no new executions may start on the sandbox process,
+    /// either because it is terminated or because it is being drained, e.g.
+    /// after it was recycled. Its Wasm modules and memories must then be
+    /// opened again in the sandbox process that replaces it.
+    fn is_retired(&self) -> bool {
+        self.is_terminated() || self.is_draining()
+    }
+
+    /// Returns the reason for retiring the sandbox process under the given
+    /// recycle policy, or `None` if it may keep serving new executions.
+    fn recycle_reason(&self, policy: &SandboxRecyclePolicy, now: Instant) -> Option<&'static str> {
+        if let Some(max_executions) = policy.max_executions {
+            if self.started_executions.load(Ordering::Relaxed) >= max_executions {
+                return Some(SANDBOX_PROCESS_RECYCLED_AFTER_EXECUTIONS);
+            }
+        }
+        if let Some(max_uptime) = policy.max_uptime {
+            if now.saturating_duration_since(self.spawned_at) >= max_uptime {
+                return Some(SANDBOX_PROCESS_RECYCLED_AFTER_UPTIME);
+            }
+        }
+        None
+    }
+}
+
 impl Drop for SandboxProcess {
     fn drop(&mut self) {
         self.history.record("Terminate()".to_string());
@@ -407,7 +775,12 @@ impl SandboxMemoryOwner for OpenedMemory {
     }
 
     fn get_sandbox_process_id(&self) -> Option<usize> {
-        self.sandbox_process.upgrade().map(|sp| sp.pid as usize)
+        // A terminated or recycled process may still be referenced, but its
+        // memories need to be synchronized with the new sandbox process.
+        self.sandbox_process
+            .upgrade()
+            .filter(|sp| !sp.is_retired())
+            .map(|sp| sp.pid as usize)
     }
 }
 
@@ -453,6 +826,20 @@ enum Backend {
     Empty,
 }
 
+struct RunningSliceGuard(IntGauge);
+
+// The execution slots that an execution holds while its slice runs.
+struct RunningExecutionSlots<'a> {
+    _pool: ExecutionSlot<'a>,
+    _process: ExecutionSlot<'a>,
+}
+
+impl Drop for RunningSliceGuard {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
 #[derive(Clone)]
 struct SandboxProcessStats {
     last_used: std::time::Instant,
@@ -466,8 +853,10 @@ enum SandboxProcessStatus {
 // This is a helper struct that is used for tracing execution of method when the
 // `ic_config::execution_environment::Config::trace_execution` flag is enabled.
 // The struct keeps track of the number of executed slices, instructions and
-// the total duration of all executed slices.
+// the total duration of all executed slices. The trace id allows matching the
+// trace with the log messages of the sandbox process.
 struct ExecutionTracingState {
+    trace_id: TraceId,
     canister_id: CanisterId,
     function: FuncRef,
     slices: usize,
@@ -486,10 +875,13 @@ impl ExecutionTracingState {
         self.observe_slice(&result.slice, duration);
         let canister_id = self.canister_id;
         let function_name = self.format_function_name();
+        let trace_id = self.trace_id;
         let instructions = self.instructions;
         let duration_ms = duration.as_millis();
-        info!(log, "Executed {canister_id}::{function_name}: instructions = {instructions}, duration = {duration_ms}ms.");
-        eprintln!("Executed {canister_id}::{function_name}: instructions = {instructions}, duration = {duration_ms}ms.");
+        let sandbox_total_ms = result.execute_total_duration.as_millis();
+        let sandbox_run_ms = result.execute_run_duration.as_millis();
+        info!(log, "Executed {canister_id}::{function_name} ({trace_id}): instructions = {instructions}, duration = {duration_ms}ms, sandbox total = {sandbox_total_ms}ms, sandbox run = {sandbox_run_ms}ms.");
+        eprintln!("Executed {canister_id}::{function_name} ({trace_id}): instructions = {instructions}, duration = {duration_ms}ms, sandbox total = {sandbox_total_ms}ms, sandbox run = {sandbox_run_ms}ms.");
     }
 
     fn format_function_name(&self) -> String {
@@ -535,6 +927,7 @@ struct PausedSandboxExecution {
     next_stable_memory_id: MemoryId,
     message_instruction_limit: NumInstructions,
     api_type_label: &'static str,
+    pool: ExecutionPool,
     controller: Arc<SandboxedExecutionController>,
     execution_tracing: ExecutionTracing,
 }
@@ -555,6 +948,10 @@ impl PausedWasmExecution for PausedSandboxExecution {
         // output from closure (running by IPC thread at end of
         // execution).
         let timer = std::time::Instant::now();
+        let execution_slots = self
+            .controller
+            .metrics
+            .acquire_execution_slots(&self.sandbox_process, self.pool);
         let (tx, rx) = std::sync::mpsc::sync_channel(1);
         let sandbox_process = Arc::clone(&self.sandbox_process);
         self.sandbox_process
@@ -565,6 +962,9 @@ impl PausedWasmExecution for PausedSandboxExecution {
                     .record(format!("Completion(exec_id={})", exec_id));
                 tx.send(result).unwrap();
             });
+        self.sandbox_process
+            .execution_states
+            .paused_execution_ended(self.exec_id);
 
         self.sandbox_process
             .history
@@ -576,7 +976,10 @@ impl PausedWasmExecution for PausedSandboxExecution {
             })
             .on_completion(|_| {});
         // Wait for completion.
+        let running_slice = self.controller.metrics.start_slice(self.pool);
         let result = rx.recv().unwrap();
+        drop(running_slice);
+        drop(execution_slots);
         SandboxedExecutionController::process_completion(
             self.controller,
             self.exec_id,
@@ -587,6 +990,7 @@ impl PausedWasmExecution for PausedSandboxExecution {
             self.next_stable_memory_id,
             self.message_instruction_limit,
             self.api_type_label,
+            self.pool,
             self.sandbox_process,
             self.execution_tracing,
             timer,
@@ -603,6 +1007,9 @@ impl PausedWasmExecution for PausedSandboxExecution {
                 exec_id: self.exec_id,
             })
             .on_completion(|_| {});
+        self.sandbox_process
+            .execution_states
+            .paused_execution_ended(self.exec_id);
     }
 }
 
@@ -637,12 +1044,29 @@ pub struct SandboxedExecutionController {
     max_sandbox_count: usize,
     max_sandbox_idle_time: Duration,
     trace_execution: FlagStatus,
+    controller_service_config: ControllerServiceConfig,
+    retry_on_sandbox_crash: FlagStatus,
+    sandbox_execution_limits: SandboxExecutionLimits,
+    sandbox_recycle_policy: SandboxRecyclePolicy,
     logger: ReplicaLogger,
     /// Executable and arguments to be passed to `canister_sandbox` which are
     /// the same for all canisters.
     sandbox_exec_argv: Vec<String>,
     metrics: Arc<SandboxedExecutionMetrics>,
-    launcher_service: Box<dyn LauncherService>,
+    controller_service_metrics: Arc<ControllerServiceMetrics>,
+    launcher_service: Arc<dyn LauncherService>,
+    /// Idle sandbox processes that are taken over by canisters without a
+    /// sandbox process. `None` if the pool is disabled.
+    sandbox_process_pool: Option<Arc<SandboxProcessPool<Arc<SandboxProcess>>>>,
+    /// Ensures that sandbox processes of different canisters do not compile
+    /// the same Wasm module concurrently.
+    compilation_coordinator: CompilationCoordinator,
+    /// The CPUs to which the sandbox processes of specific canisters are
+    /// pinned.
+    cpu_affinities: CpuAffinities,
+    /// The files through which serialized modules are passed to sandbox
+    /// processes.
+    serialized_module_files: SerializedModuleFiles,
     fd_factory: Arc<dyn PageAllocatorFileDescriptor>,
 }
 
@@ -651,6 +1075,9 @@ impl Drop for SandboxedExecutionController {
         // Evict all the sandbox processes.
         let mut guard = self.backends.lock().unwrap();
         evict_sandbox_processes(&mut guard, 0, 0, Duration::default());
+        if let Some(pool) = &self.sandbox_process_pool {
+            pool.clear();
+        }
 
         // Terminate the Sandbox Launcher process.
         self.launcher_service
@@ -676,6 +1103,7 @@ impl WasmExecutor for SandboxedExecutionController {
     ) -> (Option<CompilationResult>, WasmExecutionResult) {
         let message_instruction_limit = execution_parameters.instruction_limits.message();
         let api_type_label = api_type.as_str();
+        let pool = ExecutionPool::of(&execution_parameters.execution_mode);
         let execution_start = std::time::Instant::now();
         let _execute_timer = self
             .metrics
@@ -688,8 +1116,12 @@ impl WasmExecutor for SandboxedExecutionController {
             .with_label_values(&[api_type_label])
             .start_timer();
 
+        // The trace id stays the same if the execution is retried on another
+        // sandbox process.
+        let trace_id = TraceId::new();
         let execution_tracing = match self.trace_execution {
             FlagStatus::Enabled => ExecutionTracing::Enabled(ExecutionTracingState {
+                trace_id,
                 canister_id: sandbox_safe_system_state.canister_id(),
                 function: func_ref.clone(),
                 slices: 0,
@@ -699,114 +1131,159 @@ impl WasmExecutor for SandboxedExecutionController {
             FlagStatus::Disabled => ExecutionTracing::Disabled,
         };
 
-        // Determine which process we want to run this on.
-        let sandbox_process = self.get_sandbox_process(sandbox_safe_system_state.canister_id());
-
-        // Ensure that Wasm is compiled.
-        let (wasm_id, compilation_result) = match open_wasm(
-            &sandbox_process,
-            &execution_state.wasm_binary,
-            compilation_cache,
-            &self.metrics,
-        ) {
-            Ok((wasm_id, compilation_result)) => (wasm_id, compilation_result),
-            Err(err) => {
-                self.metrics
-                    .observe_executed_message_slice(api_type_label, err.as_str());
-                return (None, wasm_execution_error(err, message_instruction_limit));
-            }
+        let canister_id = sandbox_safe_system_state.canister_id();
+        let next_wasm_memory_id = MemoryId::new();
+        let next_stable_memory_id = MemoryId::new();
+        let exec_input = SandboxExecInput {
+            func_ref,
+            api_type,
+            globals: execution_state.exported_globals.clone(),
+            canister_current_memory_usage,
+            canister_current_message_memory_usage,
+            execution_parameters,
+            subnet_available_memory,
+            next_wasm_memory_id,
+            next_stable_memory_id,
+            sandbox_safe_system_state,
+            wasm_reserved_pages: get_wasm_reserved_pages(execution_state),
+            trace_id,
         };
 
-        // Create channel through which we will receive the execution
-        // output from closure (running by IPC thread at end of
-        // execution).
-        let (tx, rx) = std::sync::mpsc::sync_channel(1);
-
-        // Generate an ID for this execution, register it. We need to
-        // pass the system state accessor as well as the completion
-        // function that gets our result back in the end.
-        let sandbox_process_weakref = Arc::downgrade(&sandbox_process);
-        let exec_id =
+        // A non-replicated execution that has not produced any slice yet can be
+        // started over from the same input if its sandbox process crashed.
+        let mut retry_on_crash = pool == ExecutionPool::NonReplicated
+            && self.retry_on_sandbox_crash == FlagStatus::Enabled;
+        let mut compilation_result = None;
+        let mut prepare_timer = Some(prepare_timer);
+        loop {
+            // Determine which process we want to run this on.
+            let sandbox_process = self.get_sandbox_process(canister_id);
             sandbox_process
-                .execution_states
-                .register_execution(move |exec_id, result| {
-                    if let Some(sandbox_process) = sandbox_process_weakref.upgrade() {
-                        sandbox_process
-                            .history
-                            .record(format!("Completion(exec_id={})", exec_id));
-                    }
-                    tx.send(result).unwrap();
-                });
+                .started_executions
+                .fetch_add(1, Ordering::Relaxed);
+
+            // Ensure that Wasm is compiled.
+            let wasm_id = match open_wasm(
+                &sandbox_process,
+                &execution_state.wasm_binary,
+                Arc::clone(&compilation_cache),
+                &self.compilation_coordinator,
+                &self.serialized_module_files,
+                &self.metrics,
+            ) {
+                Ok((wasm_id, result)) => {
+                    // Keep the result of the first compilation if the
+                    // execution is retried.
+                    compilation_result = compilation_result.or(result);
+                    wasm_id
+                }
+                Err(err) => {
+                    self.metrics
+                        .observe_executed_message_slice(api_type_label, err.as_str());
+                    return (
+                        compilation_result,
+                        wasm_execution_error(err, message_instruction_limit),
+                    );
+                }
+            };
 
-        // Now set up resources on the sandbox to drive the execution.
-        let wasm_memory_handle = open_remote_memory(&sandbox_process, &execution_state.wasm_memory);
-        let canister_id = sandbox_safe_system_state.canister_id();
-        let wasm_memory_id = MemoryId::from(wasm_memory_handle.get_sandbox_memory_id());
-        let next_wasm_memory_id = MemoryId::new();
+            let execution_slots = self.metrics.acquire_execution_slots(&sandbox_process, pool);
 
-        let stable_memory_handle =
-            open_remote_memory(&sandbox_process, &execution_state.stable_memory);
-        let stable_memory_id = MemoryId::from(stable_memory_handle.get_sandbox_memory_id());
-        let next_stable_memory_id = MemoryId::new();
+            // Create channel through which we will receive the execution
+            // output from closure (running by IPC thread at end of
+            // execution).
+            let (tx, rx) = std::sync::mpsc::sync_channel(1);
 
-        sandbox_process.history.record(
-            format!("StartExecution(exec_id={} wasm_id={} wasm_memory_id={} stable_member_id={} api_type={}, next_wasm_memory_id={} next_stable_memory_id={}",
-                exec_id, wasm_id, wasm_memory_id, stable_memory_id, api_type.as_str(), next_wasm_memory_id, next_stable_memory_id));
+            // Generate an ID for this execution, register it. We need to
+            // pass the system state accessor as well as the completion
+            // function that gets our result back in the end.
+            let sandbox_process_weakref = Arc::downgrade(&sandbox_process);
+            let exec_id =
+                sandbox_process
+                    .execution_states
+                    .register_execution(move |exec_id, result| {
+                        if let Some(sandbox_process) = sandbox_process_weakref.upgrade() {
+                            sandbox_process
+                                .history
+                                .record(format!("Completion(exec_id={})", exec_id));
+                        }
+                        tx.send(result).unwrap();
+                    });
 
-        sandbox_process
-            .sandbox_service
-            .start_execution(protocol::sbxsvc::StartExecutionRequest {
-                exec_id,
-                wasm_id,
-                wasm_memory_id,
-                stable_memory_id,
-                exec_input: SandboxExecInput {
-                    func_ref,
-                    api_type,
-                    globals: execution_state.exported_globals.clone(),
-                    canister_current_memory_usage,
-                    canister_current_message_memory_usage,
-                    execution_parameters,
-                    subnet_available_memory,
-                    next_wasm_memory_id,
-                    next_stable_memory_id,
-                    sandbox_safe_system_state,
-                    wasm_reserved_pages: get_wasm_reserved_pages(execution_state),
-                },
-            })
-            .on_completion(|_| {});
-        drop(prepare_timer);
+            // Now set up resources on the sandbox to drive the execution.
+            let wasm_memory_handle =
+                open_remote_memory(&sandbox_process, &execution_state.wasm_memory);
+            let wasm_memory_id = MemoryId::from(wasm_memory_handle.get_sandbox_memory_id());
 
-        let wait_timer = self
-            .metrics
-            .sandboxed_execution_replica_execute_wait_duration
-            .with_label_values(&[api_type_label])
-            .start_timer();
-        // Wait for completion.
-        let result = rx
-            .recv()
-            .expect("Sandboxed_execution_controller reply channel closed unexpectedly");
-        drop(wait_timer);
-        let _finish_timer = self
-            .metrics
-            .sandboxed_execution_replica_execute_finish_duration
-            .with_label_values(&[api_type_label])
-            .start_timer();
-        let execution_result = Self::process_completion(
-            self,
-            exec_id,
-            canister_id,
-            execution_state,
-            result,
-            next_wasm_memory_id,
-            next_stable_memory_id,
-            message_instruction_limit,
-            api_type_label,
-            sandbox_process,
-            execution_tracing,
-            execution_start,
-        );
-        (compilation_result, execution_result)
+            let stable_memory_handle =
+                open_remote_memory(&sandbox_process, &execution_state.stable_memory);
+            let stable_memory_id = MemoryId::from(stable_memory_handle.get_sandbox_memory_id());
+
+            sandbox_process.history.record(
+                format!("StartExecution(exec_id={} trace_id={} wasm_id={} wasm_memory_id={} stable_member_id={} api_type={}, next_wasm_memory_id={} next_stable_memory_id={}",
+                    exec_id, trace_id, wasm_id, wasm_memory_id, stable_memory_id, api_type_label, next_wasm_memory_id, next_stable_memory_id));
+
+            sandbox_process
+                .sandbox_service
+                .start_execution(protocol::sbxsvc::StartExecutionRequest {
+                    exec_id,
+                    wasm_id,
+                    wasm_memory_id,
+                    stable_memory_id,
+                    exec_input: exec_input.clone(),
+                })
+                .on_completion(|_| {});
+            drop(prepare_timer.take());
+
+            let wait_timer = self
+                .metrics
+                .sandboxed_execution_replica_execute_wait_duration
+                .with_label_values(&[api_type_label])
+                .start_timer();
+            // Wait for completion.
+            let running_slice = self.metrics.start_slice(pool);
+            let result = rx
+                .recv()
+                .expect("Sandboxed_execution_controller reply channel closed unexpectedly");
+            drop(running_slice);
+            drop(wait_timer);
+            drop(execution_slots);
+
+            if let CompletionResult::Terminated(SandboxTermination::Crashed) = result {
+                if retry_on_crash {
+                    retry_on_crash = false;
+                    warn!(
+                        self.logger,
+                        "Retrying execution of canister {} after its sandbox process crashed",
+                        canister_id
+                    );
+                    self.metrics.sandboxed_execution_crash_retries.inc();
+                    continue;
+                }
+            }
+
+            let _finish_timer = self
+                .metrics
+                .sandboxed_execution_replica_execute_finish_duration
+                .with_label_values(&[api_type_label])
+                .start_timer();
+            let execution_result = Self::process_completion(
+                self,
+                exec_id,
+                canister_id,
+                execution_state,
+                result,
+                next_wasm_memory_id,
+                next_stable_memory_id,
+                message_instruction_limit,
+                api_type_label,
+                pool,
+                sandbox_process,
+                execution_tracing,
+                execution_start,
+            );
+            return (compilation_result, execution_result);
+        }
     }
 
     fn create_execution_state(
@@ -834,9 +1311,13 @@ impl WasmExecutor for SandboxedExecutionController {
         let stable_memory_page_map = PageMap::new(Arc::clone(&self.fd_factory));
 
         let (memory_modifications, exported_globals, serialized_module, compilation_result) =
-            match compilation_cache.get(&wasm_binary.binary) {
-                None => {
-                    self.metrics.inc_cache_lookup(CACHE_MISS);
+            match lookup_compilation_cache(
+                &compilation_cache,
+                &self.compilation_coordinator,
+                &wasm_binary.binary,
+                &self.metrics,
+            ) {
+                CompilationCacheLookup::Miss(_compilation_guard) => {
                     let _compilation_timer = self
                         .metrics
                         .sandboxed_execution_replica_create_exe_state_wait_compile_duration
@@ -876,13 +1357,8 @@ impl WasmExecutor for SandboxedExecutionController {
                         }
                     }
                 }
-                Some(Err(err)) => {
-                    self.metrics
-                        .inc_cache_lookup(COMPILATION_CACHE_HIT_COMPILATION_ERROR);
-                    return Err(err);
-                }
-                Some(Ok(serialized_module)) => {
-                    self.metrics.inc_cache_lookup(COMPILATION_CACHE_HIT);
+                CompilationCacheLookup::Hit(Err(err)) => return Err(err),
+                CompilationCacheLookup::Hit(Ok(serialized_module)) => {
                     let _deserialization_timer = self
                         .metrics
                         .sandboxed_execution_replica_create_exe_state_wait_deserialize_duration
@@ -891,12 +1367,18 @@ impl WasmExecutor for SandboxedExecutionController {
                         "CreateExecutionStateSerialized(wasm_id={}, next_wasm_memory_id={})",
                         wasm_id, next_wasm_memory_id
                     ));
+                    let serialized_module_file = self
+                        .serialized_module_files
+                        .get_or_create(&serialized_module.bytes);
                     let sandbox_result = sandbox_process
                         .sandbox_service
                         .create_execution_state_serialized(
                             protocol::sbxsvc::CreateExecutionStateSerializedRequest {
                                 wasm_id,
-                                serialized_module: Arc::clone(&serialized_module),
+                                serialized_module: SerializedModuleFile::new(
+                                    &serialized_module_file,
+                                ),
+                                data_segments: serialized_module.data_segments.clone(),
                                 wasm_page_map: wasm_page_map.serialize(),
                                 next_wasm_memory_id,
                                 canister_id,
@@ -1016,13 +1498,28 @@ impl SandboxedExecutionController {
         let launcher_exec_argv =
             create_launcher_argv(embedder_config).expect("No sandbox_launcher binary found");
         let min_sandbox_count = embedder_config.min_sandbox_count;
-        let max_sandbox_count = embedder_config.max_sandbox_count;
+        // The idle processes in the sandbox process pool count against the
+        // maximum number of sandbox processes.
+        let sandbox_process_pool_size = embedder_config
+            .sandbox_process_pool_size
+            .min(embedder_config.max_sandbox_count);
+        let max_sandbox_count = embedder_config.max_sandbox_count - sandbox_process_pool_size;
         let max_sandbox_idle_time = embedder_config.max_sandbox_idle_time;
+        let sandbox_resource_limits = embedder_config.sandbox_resource_limits;
         let trace_execution = embedder_config.trace_execution;
+        let controller_service_config = ControllerServiceConfig::from(embedder_config);
+        let retry_on_sandbox_crash = embedder_config.retry_on_sandbox_crash;
+        let sandbox_execution_limits = SandboxExecutionLimits::from(embedder_config);
+        let sandbox_recycle_policy = embedder_config.sandbox_recycle_policy;
         let sandbox_exec_argv =
             create_sandbox_argv(embedder_config).expect("No canister_sandbox binary found");
         let backends = Arc::new(Mutex::new(HashMap::new()));
         let metrics = Arc::new(SandboxedExecutionMetrics::new(metrics_registry));
+        metrics
+            .sandboxed_execution_syscall_filter_profile
+            .with_label_values(&[embedder_config.sandbox_syscall_filter.as_str()])
+            .set(1);
+        let controller_service_metrics = Arc::new(ControllerServiceMetrics::new(metrics_registry));
 
         let backends_copy = Arc::clone(&backends);
         let metrics_copy = Arc::clone(&metrics);
@@ -1036,12 +1533,57 @@ impl SandboxedExecutionController {
                 min_sandbox_count,
                 max_sandbox_count,
                 max_sandbox_idle_time,
+                sandbox_resource_limits,
             );
         });
 
+        if let Some(heartbeat_timeout) = embedder_config.sandbox_heartbeat_timeout {
+            let backends_copy = Arc::clone(&backends);
+            let metrics_copy = Arc::clone(&metrics);
+            let logger_copy = logger.clone();
+            std::thread::spawn(move || {
+                SandboxedExecutionController::watch_sandbox_heartbeats(
+                    logger_copy,
+                    backends_copy,
+                    metrics_copy,
+                    heartbeat_timeout,
+                );
+            });
+        }
+
+        if let Some(execution_timeout) = embedder_config.sandbox_execution_timeout {
+            let backends_copy = Arc::clone(&backends);
+            let metrics_copy = Arc::clone(&metrics);
+            let logger_copy = logger.clone();
+            std::thread::spawn(move || {
+                SandboxedExecutionController::watch_execution_timeouts(
+                    logger_copy,
+                    backends_copy,
+                    metrics_copy,
+                    execution_timeout,
+                );
+            });
+        }
+
+        #[cfg(target_os = "linux")]
+        if let Some(stats_interval) = embedder_config.sandbox_process_stats_interval {
+            let backends_copy = Arc::clone(&backends);
+            let metrics_copy = Arc::clone(&metrics);
+            let logger_copy = logger.clone();
+            std::thread::spawn(move || {
+                SandboxedExecutionController::collect_sandbox_process_stats(
+                    logger_copy,
+                    backends_copy,
+                    metrics_copy,
+                    stats_interval,
+                );
+            });
+        }
+
         let exit_watcher = Arc::new(ExitWatcher {
             logger: logger.clone(),
             backends: Arc::clone(&backends),
+            retry_on_sandbox_crash,
         });
 
         let (launcher_service, mut child) = spawn_launcher_process(
@@ -1049,6 +1591,7 @@ impl SandboxedExecutionController {
             &launcher_exec_argv[1..],
             exit_watcher,
         )?;
+        let launcher_service: Arc<dyn LauncherService> = Arc::from(launcher_service);
 
         // We spawn a thread to wait for the exit notification of the launcher
         // process.
@@ -1059,23 +1602,58 @@ impl SandboxedExecutionController {
             panic_due_to_exit(output, pid);
         });
 
+        let sandbox_process_pool = (sandbox_process_pool_size > 0).then(|| {
+            let pool = Arc::new(SandboxProcessPool::new(
+                sandbox_process_pool_size,
+                embedder_config.max_pooled_sandbox_idle_time,
+            ));
+            let pool_copy = Arc::downgrade(&pool);
+            let logger_copy = logger.clone();
+            let launcher_service_copy = Arc::clone(&launcher_service);
+            let sandbox_exec_argv_copy = sandbox_exec_argv.clone();
+            let controller_service_metrics_copy = Arc::clone(&controller_service_metrics);
+            std::thread::spawn(move || {
+                SandboxedExecutionController::refill_sandbox_process_pool(
+                    logger_copy,
+                    pool_copy,
+                    launcher_service_copy,
+                    sandbox_exec_argv_copy,
+                    controller_service_metrics_copy,
+                    controller_service_config,
+                    sandbox_execution_limits,
+                );
+            });
+            pool
+        });
+
         Ok(Self {
             backends,
             min_sandbox_count,
             max_sandbox_count,
             max_sandbox_idle_time,
             trace_execution,
+            controller_service_config,
+            retry_on_sandbox_crash,
+            sandbox_execution_limits,
+            sandbox_recycle_policy,
             logger,
             sandbox_exec_argv,
             metrics,
+            controller_service_metrics,
             launcher_service,
+            sandbox_process_pool,
+            compilation_coordinator: CompilationCoordinator::default(),
+            cpu_affinities: CpuAffinities::new(&embedder_config.sandbox_cpu_affinity),
+            serialized_module_files: SerializedModuleFiles::new(Arc::clone(&fd_factory)),
             fd_factory: Arc::clone(&fd_factory),
         })
     }
 
     // Periodically walk through all the backend processes and:
     // - evict inactive processes,
-    // - update memory usage metrics.
+    // - update memory usage metrics,
+    // - terminate processes exceeding their resource limits.
+    #[allow(clippy::too_many_arguments)]
     fn monitor_and_evict_sandbox_processes(
         // `logger` isn't used on MacOS.
         #[allow(unused_variables)] logger: ReplicaLogger,
@@ -1084,12 +1662,19 @@ impl SandboxedExecutionController {
         min_sandbox_count: usize,
         max_sandbox_count: usize,
         max_sandbox_idle_time: Duration,
+        // `sandbox_resource_limits` isn't used on MacOS.
+        #[allow(unused_variables)] sandbox_resource_limits: SandboxResourceLimits,
     ) {
+        // The canisters for which the resident memory of their sandbox process
+        // is currently exported.
+        #[cfg(target_os = "linux")]
+        let mut canisters_with_rss = std::collections::HashSet::new();
         loop {
             let sandbox_processes = get_sandbox_process_stats(&backends);
 
             #[cfg(target_os = "linux")]
             {
+                let mut reported_canisters = std::collections::HashSet::new();
                 let mut total_anon_rss: u64 = 0;
                 let mut total_memfd_rss: u64 = 0;
                 let now = std::time::Instant::now();
@@ -1120,6 +1705,44 @@ impl SandboxedExecutionController {
                     metrics
                         .sandboxed_execution_subprocess_rss
                         .observe(process_rss as f64);
+                    sandbox_process
+                        .rss_kib
+                        .store(process_rss, Ordering::Relaxed);
+                    if let (SandboxProcessStatus::Active, Some(canister_id)) =
+                        (status, sandbox_process.execution_states.canister_id())
+                    {
+                        metrics
+                            .sandboxed_execution_canister_subprocess_rss
+                            .with_label_values(&[&canister_id.to_string()])
+                            .set(process_rss.try_into().unwrap_or(i64::MAX));
+                        reported_canisters.insert(canister_id);
+                    }
+                    let execution_cpu_time =
+                        process_os_metrics::get_cpu_time(pid)
+                            .ok()
+                            .and_then(|cpu_time| {
+                                sandbox_process
+                                    .execution_states
+                                    .longest_execution_cpu_time(cpu_time)
+                            });
+                    if let Some(violation) = resource_limits::find_violation(
+                        &sandbox_resource_limits,
+                        execution_cpu_time,
+                    ) {
+                        if !sandbox_process.is_terminated() {
+                            warn!(
+                                logger,
+                                "Terminating sandbox process with pid {} that exceeded its {}",
+                                pid,
+                                violation
+                            );
+                            metrics
+                                .sandboxed_execution_resource_limit_violations
+                                .with_label_values(&[violation.as_str()])
+                                .inc();
+                            sandbox_process.terminate_due_to(violation);
+                        }
+                    }
                     let time_since_last_usage = now
                         .checked_duration_since(stats.last_used)
                         .unwrap_or_else(|| std::time::Duration::from_secs(0));
@@ -1144,6 +1767,13 @@ impl SandboxedExecutionController {
                 metrics
                     .sandboxed_execution_subprocess_memfd_rss_total
                     .set(total_memfd_rss.try_into().unwrap());
+
+                for canister_id in canisters_with_rss.difference(&reported_canisters) {
+                    let _ = metrics
+                        .sandboxed_execution_canister_subprocess_rss
+                        .remove_label_values(&[&canister_id.to_string()]);
+                }
+                canisters_with_rss = reported_canisters;
             }
 
             // We don't need to record memory metrics on non-linux systems.  And
@@ -1175,6 +1805,14 @@ impl SandboxedExecutionController {
 
             {
                 let mut guard = backends.lock().unwrap();
+                // Drop the strong references to terminated processes so that
+                // they are not used for new executions.
+                guard.retain(|_id, backend| match backend {
+                    Backend::Active {
+                        sandbox_process, ..
+                    } => !sandbox_process.is_terminated(),
+                    Backend::Evicted { .. } | Backend::Empty => true,
+                });
                 evict_sandbox_processes(
                     &mut guard,
                     min_sandbox_count,
@@ -1192,6 +1830,177 @@ impl SandboxedExecutionController {
         }
     }
 
+    // Periodically kills the sandbox processes that have an execution running
+    // for longer than `execution_timeout`.
+    fn watch_execution_timeouts(
+        logger: ReplicaLogger,
+        backends: Arc<Mutex<HashMap<CanisterId, Backend>>>,
+        metrics: Arc<SandboxedExecutionMetrics>,
+        execution_timeout: Duration,
+    ) {
+        loop {
+            let now = Instant::now();
+            for (canister_id, sandbox_process) in get_canister_sandbox_processes(&backends) {
+                if sandbox_process.is_terminated() {
+                    continue;
+                }
+                let elapsed = sandbox_process
+                    .execution_states
+                    .longest_running_execution(now);
+                if elapsed.map_or(false, |elapsed| elapsed > execution_timeout) {
+                    error!(
+                        logger,
+                        "Killing sandbox process with pid {} of canister {} because an execution exceeded {:?}",
+                        sandbox_process.pid,
+                        canister_id,
+                        execution_timeout
+                    );
+                    metrics.sandboxed_execution_timeouts.inc();
+                    sandbox_process.kill_due_to_timeout(execution_timeout);
+                }
+            }
+
+            std::thread::sleep(SANDBOX_EXECUTION_WATCHDOG_INTERVAL);
+        }
+    }
+
+    // Periodically kills the sandbox processes that have active executions but
+    // have not sent a heartbeat for longer than `heartbeat_timeout`.
+    fn watch_sandbox_heartbeats(
+        logger: ReplicaLogger,
+        backends: Arc<Mutex<HashMap<CanisterId, Backend>>>,
+        metrics: Arc<SandboxedExecutionMetrics>,
+        heartbeat_timeout: Duration,
+    ) {
+        loop {
+            let now = Instant::now();
+            for (canister_id, sandbox_process) in get_canister_sandbox_processes(&backends) {
+                if sandbox_process.is_terminated() {
+                    continue;
+                }
+                let elapsed = match sandbox_process.execution_states.time_without_heartbeat(now) {
+                    Some(elapsed) => elapsed,
+                    None => continue,
+                };
+                if elapsed > heartbeat_timeout {
+                    error!(
+                        logger,
+                        "Killing sandbox process with pid {} of canister {} that sent no heartbeat for {:?}",
+                        sandbox_process.pid,
+                        canister_id,
+                        elapsed
+                    );
+                    metrics.sandboxed_execution_unresponsive_processes.inc();
+                    sandbox_process.kill_due_to_missing_heartbeat(elapsed);
+                }
+            }
+
+            std::thread::sleep(SANDBOX_EXECUTION_WATCHDOG_INTERVAL);
+        }
+    }
+
+    // Periodically samples the resident memory, CPU time and open file
+    // descriptors of the sandbox process of each canister and records them in
+    // histograms, so that the number of exported series doesn't grow with the
+    // number of canisters.
+    #[cfg(target_os = "linux")]
+    fn collect_sandbox_process_stats(
+        logger: ReplicaLogger,
+        backends: Arc<Mutex<HashMap<CanisterId, Backend>>>,
+        metrics: Arc<SandboxedExecutionMetrics>,
+        stats_interval: Duration,
+    ) {
+        loop {
+            for (_canister_id, sandbox_process) in get_canister_sandbox_processes(&backends) {
+                if sandbox_process.is_terminated() {
+                    continue;
+                }
+                let pid = sandbox_process.pid;
+                let stats = process_os_metrics::get_rss(pid).and_then(|rss_kib| {
+                    let cpu_time = process_os_metrics::get_cpu_time(pid)?;
+                    let open_fds = process_os_metrics::get_open_fd_count(pid)?;
+                    Ok((rss_kib, cpu_time, open_fds))
+                });
+                match stats {
+                    Ok((rss_kib, cpu_time, open_fds)) => {
+                        metrics
+                            .sandboxed_execution_subprocess_vm_rss
+                            .observe(rss_kib as f64);
+                        metrics
+                            .sandboxed_execution_subprocess_cpu_time
+                            .observe(cpu_time.as_secs_f64());
+                        metrics
+                            .sandboxed_execution_subprocess_open_fds
+                            .observe(open_fds as f64);
+                    }
+                    // The process may have exited after it was looked up.
+                    Err(err) => {
+                        warn!(
+                            logger,
+                            "Unable to get stats of sandbox process with pid {}: {}", pid, err
+                        );
+                    }
+                }
+            }
+
+            std::thread::sleep(stats_interval);
+        }
+    }
+
+    // Keeps the sandbox process pool filled with idle sandbox processes and
+    // replaces the expired ones until the controller is dropped.
+    fn refill_sandbox_process_pool(
+        logger: ReplicaLogger,
+        pool: Weak<SandboxProcessPool<Arc<SandboxProcess>>>,
+        launcher_service: Arc<dyn LauncherService>,
+        sandbox_exec_argv: Vec<String>,
+        controller_service_metrics: Arc<ControllerServiceMetrics>,
+        controller_service_config: ControllerServiceConfig,
+        execution_limits: SandboxExecutionLimits,
+    ) {
+        while let Some(pool) = pool.upgrade() {
+            let missing = pool.refresh(Instant::now(), |sandbox_process| {
+                !sandbox_process.is_terminated()
+            });
+            for _ in 0..missing {
+                match spawn_sandbox_process(
+                    None,
+                    &*launcher_service,
+                    sandbox_exec_argv.clone(),
+                    &controller_service_metrics,
+                    controller_service_config,
+                    execution_limits,
+                    &logger,
+                ) {
+                    Ok(sandbox_process) => pool.add(sandbox_process, Instant::now()),
+                    Err(err) => {
+                        warn!(
+                            logger,
+                            "Failed to spawn a sandbox process for the pool: {}", err
+                        );
+                        break;
+                    }
+                }
+            }
+            pool.wait_for_refill(SANDBOX_PROCESS_UPDATE_INTERVAL);
+        }
+    }
+
+    /// Returns the active and paused executions on all sandbox processes, the
+    /// longest in its current phase first, so that operators can diagnose
+    /// stuck executions.
+    pub fn active_executions(&self) -> Vec<ExecutionInfo> {
+        let now = Instant::now();
+        let mut executions: Vec<_> = get_canister_sandbox_processes(&self.backends)
+            .into_iter()
+            .flat_map(|(_canister_id, sandbox_process)| {
+                sandbox_process.execution_states.executions(now)
+            })
+            .collect();
+        executions.sort_by(|a, b| b.age.cmp(&a.age));
+        executions
+    }
+
     fn get_sandbox_process(&self, canister_id: CanisterId) -> Arc<SandboxProcess> {
         let mut guard = self.backends.lock().unwrap();
 
@@ -1207,9 +2016,32 @@ impl SandboxedExecutionController {
                     stats,
                 } => sandbox_process.upgrade().map(|p| (p, stats)),
                 Backend::Empty => None,
-            };
+            }
+            .filter(|(sandbox_process, _stats)| !sandbox_process.is_retired());
+            let now = std::time::Instant::now();
+            // A process that is due for recycling is drained and dropped from
+            // the registry so that a new one replaces it. Its executions in
+            // progress and paused executions hold their own references and
+            // keep running on it; the process terminates once the last of
+            // them completes.
+            let sandbox_process_and_stats =
+                sandbox_process_and_stats.filter(|(sandbox_process, _stats)| match sandbox_process
+                    .recycle_reason(&self.sandbox_recycle_policy, now)
+                {
+                    None => true,
+                    Some(reason) => {
+                        sandbox_process
+                            .history
+                            .record(format!("Retire(reason={})", reason));
+                        sandbox_process.start_draining();
+                        self.metrics
+                            .sandboxed_execution_sandbox_process_recycles
+                            .with_label_values(&[reason])
+                            .inc();
+                        false
+                    }
+                });
             if let Some((sandbox_process, _stats)) = sandbox_process_and_stats {
-                let now = std::time::Instant::now();
                 if self.max_sandbox_count > 0 {
                     *backend = Backend::Active {
                         sandbox_process: Arc::clone(&sandbox_process),
@@ -1225,7 +2057,6 @@ impl SandboxedExecutionController {
             }
         }
 
-        let _timer = self.metrics.sandboxed_execution_spawn_process.start_timer();
         if guard.len() > self.max_sandbox_count {
             let to_evict = self.max_sandbox_count * SANDBOX_PROCESS_EVICTION_PERCENT / 100;
             let max_active_sandboxes = self.max_sandbox_count.saturating_sub(to_evict);
@@ -1237,24 +2068,63 @@ impl SandboxedExecutionController {
             );
         }
 
-        // No sandbox process found for this canister. Start a new one and register it.
-        let reg = Arc::new(ActiveExecutionStateRegistry::new());
-        let controller_service = ControllerServiceImpl::new(Arc::clone(&reg), self.logger.clone());
-
-        let (sandbox_service, pid) = create_sandbox_process(
-            controller_service,
-            &*self.launcher_service,
-            canister_id,
-            self.sandbox_exec_argv.clone(),
-        )
-        .unwrap();
-
-        let sandbox_process = Arc::new(SandboxProcess {
-            execution_states: reg,
-            sandbox_service,
-            pid,
-            history: SandboxProcessRequestHistory::new(),
+        // No sandbox process found for this canister. Take over an idle one
+        // from the pool or start a new one, and register it.
+        let pooled_sandbox_process = self.sandbox_process_pool.as_ref().and_then(|pool| {
+            let sandbox_process = pool.take(|sandbox_process| !sandbox_process.is_terminated());
+            let status = match sandbox_process {
+                Some(_) => SANDBOX_PROCESS_POOL_HIT,
+                None => SANDBOX_PROCESS_POOL_MISS,
+            };
+            self.metrics
+                .sandboxed_execution_sandbox_process_pool_lookups
+                .with_label_values(&[status])
+                .inc();
+            sandbox_process
         });
+        let sandbox_process = match pooled_sandbox_process {
+            Some(sandbox_process) => {
+                sandbox_process
+                    .execution_states
+                    .assign_canister(canister_id);
+                // The launcher reports the exit of the process with the
+                // canister, so that the `ExitWatcher` can find it.
+                if let Err(err) = self
+                    .launcher_service
+                    .assign_canister(protocol::launchersvc::AssignCanisterRequest {
+                        pid: sandbox_process.pid,
+                        canister_id,
+                    })
+                    .sync()
+                {
+                    warn!(
+                        self.logger,
+                        "Failed to assign sandbox process with pid {} to canister {} in the launcher: {:?}",
+                        sandbox_process.pid,
+                        canister_id,
+                        err
+                    );
+                }
+                sandbox_process
+                    .history
+                    .record(format!("AssignToCanister(canister_id={})", canister_id));
+                sandbox_process
+            }
+            None => {
+                let _timer = self.metrics.sandboxed_execution_spawn_process.start_timer();
+                spawn_sandbox_process(
+                    Some(canister_id),
+                    &*self.launcher_service,
+                    self.sandbox_exec_argv.clone(),
+                    &self.controller_service_metrics,
+                    self.controller_service_config,
+                    self.sandbox_execution_limits,
+                    &self.logger,
+                )
+                .expect("Failed to start sandbox process")
+            }
+        };
+        self.pin_sandbox_process(canister_id, &sandbox_process);
 
         let now = std::time::Instant::now();
         let backend = Backend::Active {
@@ -1266,6 +2136,41 @@ impl SandboxedExecutionController {
         sandbox_process
     }
 
+    // Pins the sandbox process to the CPUs configured for the given canister,
+    // if any. Failing to do so only affects performance, so it is logged.
+    #[cfg(target_os = "linux")]
+    fn pin_sandbox_process(&self, canister_id: CanisterId, sandbox_process: &SandboxProcess) {
+        let cpus = match self.cpu_affinities.cpus(&canister_id) {
+            Some(cpus) => cpus,
+            None => return,
+        };
+        let effective_cpus = super::cpu_affinity::set_process_affinity(sandbox_process.pid, cpus)
+            .and_then(|()| super::cpu_affinity::get_process_affinity(sandbox_process.pid));
+        match effective_cpus {
+            Ok(effective_cpus) => {
+                sandbox_process.history.record(format!(
+                    "PinToCpus(cpus={:?}, effective_cpus={:?})",
+                    cpus, effective_cpus
+                ));
+                self.metrics
+                    .sandboxed_execution_sandbox_cpu_affinity
+                    .with_label_values(&[&canister_id.to_string()])
+                    .set(effective_cpus.len() as i64);
+            }
+            Err(err) => warn!(
+                self.logger,
+                "Failed to pin the sandbox process with pid {} of canister {} to CPUs {:?}: {}",
+                sandbox_process.pid,
+                canister_id,
+                cpus,
+                err
+            ),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn pin_sandbox_process(&self, _canister_id: CanisterId, _sandbox_process: &SandboxProcess) {}
+
     #[allow(clippy::too_many_arguments)]
     fn process_completion(
         self: Arc<Self>,
@@ -1277,6 +2182,7 @@ impl SandboxedExecutionController {
         next_stable_memory_id: MemoryId,
         message_instruction_limit: NumInstructions,
         api_type_label: &'static str,
+        pool: ExecutionPool,
         sandbox_process: Arc<SandboxProcess>,
         mut execution_tracing: ExecutionTracing,
         execution_start: std::time::Instant,
@@ -1286,6 +2192,7 @@ impl SandboxedExecutionController {
                 execution_tracing.observe_slice(&slice, execution_start.elapsed());
                 self.metrics
                     .observe_executed_message_slice(api_type_label, "Paused");
+                sandbox_process.execution_states.execution_paused(exec_id);
                 let paused = Box::new(PausedSandboxExecution {
                     canister_id,
                     sandbox_process,
@@ -1294,18 +2201,67 @@ impl SandboxedExecutionController {
                     next_stable_memory_id,
                     message_instruction_limit,
                     api_type_label,
+                    pool,
                     controller: self,
                     execution_tracing,
                 });
                 return WasmExecutionResult::Paused(slice, paused);
             }
-            CompletionResult::Finished(exec_output) => {
+            CompletionResult::Terminated(termination) => {
+                // A failure of the sandbox process is local to this node, so a
+                // replicated execution must not turn it into an error that
+                // ends up in the replicated state. Like before crashes were
+                // recovered from, the replica panics instead. This also holds
+                // for sandbox processes killed by the replica for exceeding a
+                // resource limit, the execution timeout or the heartbeat
+                // timeout, which is why all of them are disabled by default.
+                if pool == ExecutionPool::Replicated
+                    || self.retry_on_sandbox_crash == FlagStatus::Disabled
+                {
+                    sandbox_process.history.replay(
+                        &self.logger,
+                        canister_id,
+                        sandbox_process.pid,
+                        sandbox_process.protocol_version,
+                    );
+                    panic!(
+                        "Sandbox process with pid {} of canister {} stopped during a {} execution: {:?}",
+                        sandbox_process.pid,
+                        canister_id,
+                        pool.as_str(),
+                        termination
+                    );
+                }
+                let err = match termination {
+                    SandboxTermination::ResourceLimitExceeded(_)
+                    | SandboxTermination::Crashed
+                    | SandboxTermination::Unresponsive(_)
+                    | SandboxTermination::Misbehaved => HypervisorError::SandboxCrashed,
+                    SandboxTermination::ExecutionTimedOut(limit) => {
+                        HypervisorError::SandboxExecutionTimedOut(limit)
+                    }
+                };
+                self.metrics
+                    .observe_executed_message_slice(api_type_label, err.as_str());
+                return wasm_execution_error(err, message_instruction_limit);
+            }
+            CompletionResult::Finished(exec_output, trap_backtrace) => {
                 let execution_status = match exec_output.wasm.wasm_result.clone() {
                     Ok(Some(WasmResult::Reply(_))) => "Success",
                     Ok(Some(WasmResult::Reject(_))) => "Reject",
                     Ok(None) => "NoResponse",
                     Err(e) => e.as_str(),
                 };
+                if let (Err(err), Some(backtrace)) = (&exec_output.wasm.wasm_result, trap_backtrace)
+                {
+                    info!(
+                        self.logger,
+                        "Canister {} failed with {} at Wasm backtrace:\n{}",
+                        canister_id,
+                        err,
+                        backtrace
+                    );
+                }
                 self.metrics
                     .observe_executed_message_slice(api_type_label, execution_status);
                 exec_output
@@ -1486,12 +2442,18 @@ impl SandboxedExecutionController {
                                 "CreateExecutionStateSerialized(wasm_id={}, next_wasm_memory_id={})",
                                 wasm_id, next_wasm_memory_id
                             ));
+                            let serialized_module_file = self
+                                .serialized_module_files
+                                .get_or_create(&serialized_module.bytes);
                             let sandbox_result = sandbox_process
                                 .sandbox_service
                                 .create_execution_state_serialized(
                                     protocol::sbxsvc::CreateExecutionStateSerializedRequest {
                                         wasm_id,
-                                        serialized_module: Arc::clone(&serialized_module),
+                                        serialized_module: SerializedModuleFile::new(
+                                            &serialized_module_file,
+                                        ),
+                                        data_segments: serialized_module.data_segments.clone(),
                                         wasm_page_map: wasm_page_map.serialize(),
                                         next_wasm_memory_id,
                                         canister_id,
@@ -1531,12 +2493,18 @@ impl SandboxedExecutionController {
                         "CreateExecutionStateSerialized(wasm_id={}, next_wasm_memory_id={})",
                         wasm_id, next_wasm_memory_id
                     ));
+                    let serialized_module_file = self
+                        .serialized_module_files
+                        .get_or_create(&serialized_module.bytes);
                     let sandbox_result = sandbox_process
                         .sandbox_service
                         .create_execution_state_serialized(
                             protocol::sbxsvc::CreateExecutionStateSerializedRequest {
                                 wasm_id,
-                                serialized_module: Arc::clone(&serialized_module),
+                                serialized_module: SerializedModuleFile::new(
+                                    &serialized_module_file,
+                                ),
+                                data_segments: serialized_module.data_segments.clone(),
                                 wasm_page_map: wasm_page_map.serialize(),
                                 next_wasm_memory_id,
                                 canister_id,
@@ -1634,10 +2602,57 @@ fn cache_errored_wasm(embedder_cache: &mut Option<EmbedderCache>, err: Hyperviso
 
 // Get compiled wasm object in sandbox. Ask cache first, upload + compile if
 // needed.
+// The outcome of looking up a Wasm module in the compilation cache.
+enum CompilationCacheLookup<'a> {
+    Hit(HypervisorResult<Arc<SerializedModule>>),
+    // The module is not cached. The caller is responsible for compiling it and
+    // for inserting the result into the compilation cache before dropping the
+    // guard.
+    Miss(CompilationGuard<'a>),
+}
+
+// Looks up the given module in the compilation cache. If the module is being
+// compiled for another sandbox process, waits for that compilation instead of
+// compiling the module again.
+fn lookup_compilation_cache<'a>(
+    compilation_cache: &CompilationCache,
+    compilation_coordinator: &'a CompilationCoordinator,
+    canister_module: &CanisterModule,
+    metrics: &SandboxedExecutionMetrics,
+) -> CompilationCacheLookup<'a> {
+    let mut waited = false;
+    loop {
+        if let Some(result) = compilation_cache.get(canister_module) {
+            let label = match (&result, waited) {
+                (_, true) => COMPILATION_CACHE_HIT_AFTER_WAIT,
+                (Ok(_), false) => COMPILATION_CACHE_HIT,
+                (Err(_), false) => COMPILATION_CACHE_HIT_COMPILATION_ERROR,
+            };
+            metrics.inc_cache_lookup(label);
+            return CompilationCacheLookup::Hit(result);
+        }
+        let wait_timer = metrics
+            .sandboxed_execution_replica_compilation_wait_duration
+            .start_timer();
+        match compilation_coordinator.start_or_wait(WasmHash::from(canister_module)) {
+            Some(guard) => {
+                wait_timer.stop_and_discard();
+                metrics.inc_cache_lookup(CACHE_MISS);
+                return CompilationCacheLookup::Miss(guard);
+            }
+            // The other compilation finished, so its result should be in the
+            // cache now unless it was evicted in the meantime.
+            None => waited = true,
+        }
+    }
+}
+
 fn open_wasm(
     sandbox_process: &Arc<SandboxProcess>,
     wasm_binary: &WasmBinary,
     compilation_cache: Arc<CompilationCache>,
+    compilation_coordinator: &CompilationCoordinator,
+    serialized_module_files: &SerializedModuleFiles,
     metrics: &SandboxedExecutionMetrics,
 ) -> HypervisorResult<(WasmId, Option<CompilationResult>)> {
     let mut embedder_cache = wasm_binary.embedder_cache.lock().unwrap();
@@ -1645,7 +2660,11 @@ fn open_wasm(
         if let Some(opened_wasm) = cache.downcast::<HypervisorResult<OpenedWasm>>() {
             match opened_wasm {
                 Ok(opened_wasm) => {
-                    if let Some(cached_sandbox_process) = opened_wasm.sandbox_process.upgrade() {
+                    if let Some(cached_sandbox_process) = opened_wasm
+                        .sandbox_process
+                        .upgrade()
+                        .filter(|sandbox_process| !sandbox_process.is_retired())
+                    {
                         metrics.inc_cache_lookup(EMBEDDER_CACHE_HIT_SUCCESS);
                         assert!(Arc::ptr_eq(&cached_sandbox_process, sandbox_process));
                         return Ok((opened_wasm.wasm_id, None));
@@ -1662,9 +2681,13 @@ fn open_wasm(
     }
 
     let wasm_id = WasmId::new();
-    match compilation_cache.get(&wasm_binary.binary) {
-        None => {
-            metrics.inc_cache_lookup(CACHE_MISS);
+    match lookup_compilation_cache(
+        &compilation_cache,
+        compilation_coordinator,
+        &wasm_binary.binary,
+        metrics,
+    ) {
+        CompilationCacheLookup::Miss(_compilation_guard) => {
             sandbox_process
                 .history
                 .record(format!("OpenWasm(wasm_id={})", wasm_id));
@@ -1691,24 +2714,25 @@ fn open_wasm(
                 }
             }
         }
-        Some(Err(err)) => {
-            metrics.inc_cache_lookup(COMPILATION_CACHE_HIT_COMPILATION_ERROR);
+        CompilationCacheLookup::Hit(Err(err)) => {
             cache_errored_wasm(&mut embedder_cache, err.clone());
             Err(err)
         }
-        Some(Ok(serialized_module)) => {
-            metrics.inc_cache_lookup(COMPILATION_CACHE_HIT);
+        CompilationCacheLookup::Hit(Ok(serialized_module)) => {
             observe_metrics(metrics, &serialized_module.imports_details);
             sandbox_process
                 .history
                 .record(format!("OpenWasmSerialized(wasm_id={})", wasm_id));
+            let serialized_module_file =
+                serialized_module_files.get_or_create(&serialized_module.bytes);
             sandbox_process
                 .sandbox_service
                 .open_wasm_serialized(protocol::sbxsvc::OpenWasmSerializedRequest {
                     wasm_id,
-                    serialized_module: Arc::clone(&serialized_module.bytes),
+                    serialized_module: SerializedModuleFile::new(&serialized_module_file),
                 })
-                .on_completion(|_| ());
+                // Keep the file open until the request has been handled.
+                .on_completion(move |_| drop(serialized_module_file));
             cache_opened_wasm(&mut embedder_cache, sandbox_process, wasm_id);
             Ok((wasm_id, None))
         }
@@ -1724,6 +2748,7 @@ fn open_wasm_with_compiler_sandbox(
     launcher: &dyn LauncherService,
     wasm_binary: &WasmBinary,
     compilation_cache: Arc<CompilationCache>,
+    serialized_module_files: &SerializedModuleFiles,
     metrics: &SandboxedExecutionMetrics,
     log: &ReplicaLogger,
 ) -> HypervisorResult<(WasmId, Option<CompilationResult>)> {
@@ -1732,7 +2757,11 @@ fn open_wasm_with_compiler_sandbox(
         if let Some(opened_wasm) = cache.downcast::<HypervisorResult<OpenedWasm>>() {
             match opened_wasm {
                 Ok(opened_wasm) => {
-                    if let Some(cached_sandbox_process) = opened_wasm.sandbox_process.upgrade() {
+                    if let Some(cached_sandbox_process) = opened_wasm
+                        .sandbox_process
+                        .upgrade()
+                        .filter(|sandbox_process| !sandbox_process.is_retired())
+                    {
                         metrics.inc_cache_lookup(EMBEDDER_CACHE_HIT_SUCCESS);
                         assert!(Arc::ptr_eq(&cached_sandbox_process, sandbox_process));
                         return Ok((opened_wasm.wasm_id, None));
@@ -1771,19 +2800,23 @@ fn open_wasm_with_compiler_sandbox(
 
             match result {
                 Ok((compilation_result, serialized_module)) => {
+                    let serialized_module = Arc::new(serialized_module);
                     sandbox_process
                         .history
                         .record(format!("OpenWasmSerialized(wasm_id={})", wasm_id));
+                    let serialized_module_file =
+                        serialized_module_files.get_or_create(&serialized_module.bytes);
                     sandbox_process
                         .sandbox_service
                         .open_wasm_serialized(protocol::sbxsvc::OpenWasmSerializedRequest {
                             wasm_id,
-                            serialized_module: Arc::clone(&serialized_module.bytes),
+                            serialized_module: SerializedModuleFile::new(&serialized_module_file),
                         })
-                        .on_completion(|_| ());
+                        // Keep the file open until the request has been handled.
+                        .on_completion(move |_| drop(serialized_module_file));
                     cache_opened_wasm(&mut embedder_cache, sandbox_process, wasm_id);
                     observe_metrics(metrics, &serialized_module.imports_details);
-                    compilation_cache.insert(&wasm_binary.binary, Ok(Arc::new(serialized_module)));
+                    compilation_cache.insert(&wasm_binary.binary, Ok(serialized_module));
                     Ok((wasm_id, Some(compilation_result)))
                 }
                 Err(err) => {
@@ -1804,13 +2837,16 @@ fn open_wasm_with_compiler_sandbox(
             sandbox_process
                 .history
                 .record(format!("OpenWasmSerialized(wasm_id={})", wasm_id));
+            let serialized_module_file =
+                serialized_module_files.get_or_create(&serialized_module.bytes);
             sandbox_process
                 .sandbox_service
                 .open_wasm_serialized(protocol::sbxsvc::OpenWasmSerializedRequest {
                     wasm_id,
-                    serialized_module: Arc::clone(&serialized_module.bytes),
+                    serialized_module: SerializedModuleFile::new(&serialized_module_file),
                 })
-                .on_completion(|_| ());
+                // Keep the file open until the request has been handled.
+                .on_completion(move |_| drop(serialized_module_file));
             cache_opened_wasm(&mut embedder_cache, sandbox_process, wasm_id);
             Ok((wasm_id, None))
         }
@@ -1868,6 +2904,53 @@ fn wrap_remote_memory(
     SandboxMemoryHandle::new(Arc::new(opened_memory))
 }
 
+// Spawns a sandbox process for the given canister, or an idle one for the
+// sandbox process pool if no canister is given.
+fn spawn_sandbox_process(
+    canister_id: Option<CanisterId>,
+    launcher_service: &dyn LauncherService,
+    sandbox_exec_argv: Vec<String>,
+    controller_service_metrics: &Arc<ControllerServiceMetrics>,
+    controller_service_config: ControllerServiceConfig,
+    execution_limits: SandboxExecutionLimits,
+    logger: &ReplicaLogger,
+) -> std::io::Result<Arc<SandboxProcess>> {
+    let reg = Arc::new(ActiveExecutionStateRegistry::new());
+    if let Some(canister_id) = canister_id {
+        reg.assign_canister(canister_id);
+    }
+    let controller_service = ControllerServiceImpl::new(
+        Arc::clone(&reg),
+        Arc::clone(controller_service_metrics),
+        controller_service_config,
+        logger.clone(),
+    );
+
+    let (sandbox_service, pid, protocol_version) = create_sandbox_process(
+        Arc::clone(&controller_service),
+        launcher_service,
+        canister_id,
+        sandbox_exec_argv,
+    )?;
+    controller_service.set_sandbox_pid(pid);
+    controller_service.set_protocol_version(protocol_version);
+    controller_service_metrics.observe_sandbox_protocol_version(protocol_version);
+
+    Ok(Arc::new(SandboxProcess {
+        execution_states: reg,
+        sandbox_service,
+        pid,
+        protocol_version,
+        execution_slots: ExecutionSlots::new(execution_limits.total),
+        replicated_execution_slots: ExecutionSlots::new(execution_limits.replicated),
+        non_replicated_execution_slots: ExecutionSlots::new(execution_limits.non_replicated),
+        rss_kib: AtomicU64::new(0),
+        spawned_at: Instant::now(),
+        started_executions: AtomicU64::new(0),
+        history: SandboxProcessRequestHistory::new(),
+    }))
+}
+
 // Evicts some sandbox process backends according to the heuristics of the
 // `sandbox_process_eviction::evict()` function. See the comments of that
 // function for the explanation of the threshold parameters.
@@ -1894,9 +2977,13 @@ fn evict_sandbox_processes(
     let candidates: Vec<_> = backends
         .iter()
         .filter_map(|(id, backend)| match backend {
-            Backend::Active { stats, .. } => Some(EvictionCandidate {
+            Backend::Active {
+                sandbox_process,
+                stats,
+            } => Some(EvictionCandidate {
                 id: *id,
                 last_used: stats.last_used,
+                rss_kib: sandbox_process.rss_kib.load(Ordering::Relaxed),
             }),
             Backend::Evicted { .. } | Backend::Empty => None,
         })
@@ -1981,6 +3068,25 @@ fn get_sandbox_process_stats(
     result
 }
 
+// Returns the sandbox processes of all canisters that are still alive.
+fn get_canister_sandbox_processes(
+    backends: &Arc<Mutex<HashMap<CanisterId, Backend>>>,
+) -> Vec<(CanisterId, Arc<SandboxProcess>)> {
+    let guard = backends.lock().unwrap();
+    guard
+        .iter()
+        .filter_map(|(canister_id, backend)| match backend {
+            Backend::Active {
+                sandbox_process, ..
+            } => Some((*canister_id, Arc::clone(sandbox_process))),
+            Backend::Evicted {
+                sandbox_process, ..
+            } => sandbox_process.upgrade().map(|p| (*canister_id, p)),
+            Backend::Empty => None,
+        })
+        .collect()
+}
+
 pub fn panic_due_to_exit(output: ExitStatus, pid: u32) {
     match output.code() {
         // Do nothing when the Sandbox Launcher process terminates normally.
@@ -1997,10 +3103,13 @@ pub fn panic_due_to_exit(output: ExitStatus, pid: u32) {
 }
 
 /// Service responsible for printing the history of a canister's activity when
-/// it unexpectedly exits.
+/// it unexpectedly exits. If the replica recovers from sandbox crashes, the
+/// non-replicated executions that were active on the process fail and a new
+/// process is spawned for the next execution of the canister.
 struct ExitWatcher {
     logger: ReplicaLogger,
     backends: Arc<Mutex<HashMap<CanisterId, Backend>>>,
+    retry_on_sandbox_crash: FlagStatus,
 }
 
 impl ControllerLauncherService for ExitWatcher {
@@ -2009,22 +3118,42 @@ impl ControllerLauncherService for ExitWatcher {
         req: protocol::ctllaunchersvc::SandboxExitedRequest,
     ) -> crate::rpc::Call<protocol::ctllaunchersvc::SandboxExitedReply> {
         let guard = self.backends.lock().unwrap();
-        let sandbox_process = match guard.get(&req.canister_id).unwrap_or_else(|| {
-            panic!(
-                "Sandbox exited for unrecognized canister id {}",
-                req.canister_id,
-            )
-        }) {
-            Backend::Active {
+        let sandbox_process = match guard.get(&req.canister_id) {
+            Some(Backend::Active {
                 sandbox_process, ..
-            } => sandbox_process,
-            Backend::Evicted { .. } | Backend::Empty => {
+            }) if sandbox_process.pid == req.pid => sandbox_process,
+            None if self.retry_on_sandbox_crash == FlagStatus::Disabled => {
+                panic!(
+                    "Sandbox exited for unrecognized canister id {}",
+                    req.canister_id,
+                )
+            }
+            // The exited process may have been replaced or removed already,
+            // e.g. when it crashed during an execution that was retried.
+            Some(Backend::Active { .. }) | None => {
+                warn!(
+                    self.logger,
+                    "Sandbox pid {} for canister {} exited, but it is no longer registered",
+                    req.pid,
+                    req.canister_id
+                );
+                return rpc::Call::new_resolved(Ok(protocol::ctllaunchersvc::SandboxExitedReply));
+            }
+            Some(Backend::Evicted { .. }) | Some(Backend::Empty) => {
                 return rpc::Call::new_resolved(Ok(protocol::ctllaunchersvc::SandboxExitedReply));
             }
         };
+        // Make sure that the process is not used for new executions, even
+        // before the connection to it is closed.
         sandbox_process
-            .history
-            .replay(&self.logger, req.canister_id, sandbox_process.pid);
+            .execution_states
+            .record_termination(SandboxTermination::Crashed);
+        sandbox_process.history.replay(
+            &self.logger,
+            req.canister_id,
+            sandbox_process.pid,
+            sandbox_process.protocol_version,
+        );
         rpc::Call::new_resolved(Ok(protocol::ctllaunchersvc::SandboxExitedReply))
     }
 }
@@ -2049,6 +3178,7 @@ mod tests {
         let exit_watcher = Arc::new(ExitWatcher {
             logger: no_op_logger(),
             backends: Arc::new(Mutex::new(HashMap::new())),
+            retry_on_sandbox_crash: FlagStatus::Disabled,
         });
 
         let (_launcher_service, mut child) = spawn_launcher_process(
@@ -2127,4 +3257,149 @@ mod tests {
             canister_id, sandbox_pid
         )));
     }
+
+    #[test]
+    fn sandbox_respawned_after_crash() {
+        use ic_replicated_state::page_map::TestPageAllocatorFileDescriptorImpl;
+        let config = EmbeddersConfig {
+            retry_on_sandbox_crash: FlagStatus::Enabled,
+            ..EmbeddersConfig::default()
+        };
+        let controller = SandboxedExecutionController::new(
+            no_op_logger(),
+            &MetricsRegistry::new(),
+            &config,
+            Arc::new(TestPageAllocatorFileDescriptorImpl::new()),
+        )
+        .unwrap();
+
+        let canister_id = canister_test_id(0);
+        let sandbox_process = controller.get_sandbox_process(canister_id);
+        unsafe {
+            kill(sandbox_process.pid.try_into().unwrap(), libc::SIGKILL);
+        }
+        while !sandbox_process.is_terminated() {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let respawned = controller.get_sandbox_process(canister_id);
+        assert_ne!(respawned.pid, sandbox_process.pid);
+        assert!(!respawned.is_terminated());
+    }
+
+    #[test]
+    fn sandbox_recycled_after_max_executions() {
+        use ic_replicated_state::page_map::TestPageAllocatorFileDescriptorImpl;
+        let config = EmbeddersConfig {
+            sandbox_recycle_policy: SandboxRecyclePolicy {
+                max_executions: Some(2),
+                max_uptime: None,
+            },
+            ..EmbeddersConfig::default()
+        };
+        let controller = SandboxedExecutionController::new(
+            no_op_logger(),
+            &MetricsRegistry::new(),
+            &config,
+            Arc::new(TestPageAllocatorFileDescriptorImpl::new()),
+        )
+        .unwrap();
+
+        let canister_id = canister_test_id(0);
+        let sandbox_process = controller.get_sandbox_process(canister_id);
+        sandbox_process
+            .started_executions
+            .store(1, Ordering::Relaxed);
+        assert_eq!(
+            controller.get_sandbox_process(canister_id).pid,
+            sandbox_process.pid
+        );
+
+        sandbox_process
+            .started_executions
+            .store(2, Ordering::Relaxed);
+        let recycled = controller.get_sandbox_process(canister_id);
+        assert_ne!(recycled.pid, sandbox_process.pid);
+        // The retired process keeps serving the executions that reference it.
+        assert!(!sandbox_process.is_terminated());
+    }
+
+    #[test]
+    fn wasm_opened_again_after_sandbox_recycled() {
+        use ic_replicated_state::page_map::TestPageAllocatorFileDescriptorImpl;
+        let config = EmbeddersConfig {
+            sandbox_recycle_policy: SandboxRecyclePolicy {
+                max_executions: Some(1),
+                max_uptime: None,
+            },
+            ..EmbeddersConfig::default()
+        };
+        let controller = SandboxedExecutionController::new(
+            no_op_logger(),
+            &MetricsRegistry::new(),
+            &config,
+            Arc::new(TestPageAllocatorFileDescriptorImpl::new()),
+        )
+        .unwrap();
+
+        let canister_id = canister_test_id(0);
+        let wasm_binary = WasmBinary::new(CanisterModule::new(wat::parse_str("(module)").unwrap()));
+        let compilation_cache = Arc::new(CompilationCache::new(MAX_COMPILATION_CACHE_SIZE));
+        let open = |sandbox_process: &Arc<SandboxProcess>| {
+            open_wasm(
+                sandbox_process,
+                &wasm_binary,
+                Arc::clone(&compilation_cache),
+                &controller.compilation_coordinator,
+                &controller.serialized_module_files,
+                &controller.metrics,
+            )
+            .unwrap()
+            .0
+        };
+
+        let sandbox_process = controller.get_sandbox_process(canister_id);
+        let wasm_id = open(&sandbox_process);
+        sandbox_process
+            .started_executions
+            .store(1, Ordering::Relaxed);
+
+        // The cached Wasm module belongs to the retired process, so it must be
+        // opened again in the new one.
+        let recycled = controller.get_sandbox_process(canister_id);
+        assert_ne!(recycled.pid, sandbox_process.pid);
+        assert!(sandbox_process.is_retired());
+        let reopened_wasm_id = open(&recycled);
+        assert_ne!(reopened_wasm_id, wasm_id);
+        assert_eq!(open(&recycled), reopened_wasm_id);
+    }
+
+    #[test]
+    fn executions_are_limited_per_pool() {
+        use ic_replicated_state::page_map::TestPageAllocatorFileDescriptorImpl;
+        let config = EmbeddersConfig {
+            max_concurrent_non_replicated_executions_per_sandbox: Some(1),
+            ..EmbeddersConfig::default()
+        };
+        let controller = SandboxedExecutionController::new(
+            no_op_logger(),
+            &MetricsRegistry::new(),
+            &config,
+            Arc::new(TestPageAllocatorFileDescriptorImpl::new()),
+        )
+        .unwrap();
+
+        let sandbox_process = controller.get_sandbox_process(canister_test_id(0));
+        let _query = controller
+            .metrics
+            .acquire_execution_slots(&sandbox_process, ExecutionPool::NonReplicated);
+        assert!(sandbox_process
+            .pool_execution_slots(ExecutionPool::NonReplicated)
+            .try_acquire()
+            .is_none());
+        // A running query does not hold up replicated executions.
+        let _update = controller
+            .metrics
+            .acquire_execution_slots(&sandbox_process, ExecutionPool::Replicated);
+    }
 }
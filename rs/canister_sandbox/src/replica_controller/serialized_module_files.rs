@@ -0,0 +1,97 @@
+//! Files holding serialized Wasm modules for sandbox processes.
+//!
+//! Instead of copying the serialized module over the IPC socket whenever a
+//! sandbox process opens it, the controller writes the module once into a
+//! file and passes the file descriptor. The sandbox process memory-maps the
+//! file, so that the bytes are neither copied nor deserialized from the
+//! message.
+//!
+//! A file lives as long as the `SerializedModuleBytes` it was created from,
+//! i.e. until the module is evicted from the compilation cache and no request
+//! that refers to the file is in flight.
+
+use ic_embedders::SerializedModuleBytes;
+use ic_replicated_state::page_map::PageAllocatorFileDescriptor;
+use std::collections::HashMap;
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::FromRawFd;
+use std::sync::{Arc, Mutex, Weak};
+
+pub(crate) struct SerializedModuleFiles {
+    fd_factory: Arc<dyn PageAllocatorFileDescriptor>,
+    // The files indexed by the address of the serialized module. The weak
+    // reference keeps the address from being reused by another module.
+    files: Mutex<HashMap<usize, (Weak<SerializedModuleBytes>, Arc<File>)>>,
+}
+
+impl SerializedModuleFiles {
+    pub fn new(fd_factory: Arc<dyn PageAllocatorFileDescriptor>) -> Self {
+        Self {
+            fd_factory,
+            files: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the file holding the given serialized module and creates it if
+    /// it doesn't exist yet. The caller must keep the returned file alive until
+    /// the sandbox process received the file descriptor.
+    pub fn get_or_create(&self, serialized_module: &Arc<SerializedModuleBytes>) -> Arc<File> {
+        let key = Arc::as_ptr(serialized_module) as usize;
+        let mut files = self.files.lock().unwrap();
+        if let Some((_, file)) = files.get(&key) {
+            return Arc::clone(file);
+        }
+        // Drop the files of the modules that are no longer alive.
+        files.retain(|_, (module, _)| module.strong_count() > 0);
+
+        // SAFETY: The file descriptor factory returns a new file descriptor
+        // that is not owned by anything else.
+        let file = unsafe { File::from_raw_fd(self.fd_factory.get_fd()) };
+        file.write_all_at(serialized_module.as_slice(), 0)
+            .expect("Failed to write a serialized module to a file");
+        let file = Arc::new(file);
+        files.insert(key, (Arc::downgrade(serialized_module), Arc::clone(&file)));
+        file
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.files.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_replicated_state::page_map::TestPageAllocatorFileDescriptorImpl;
+    use std::io::Read;
+
+    fn serialized_module() -> Arc<SerializedModuleBytes> {
+        let bytes = bincode::serialize(serde_bytes::Bytes::new(b"wasmtime-aot")).unwrap();
+        Arc::new(bincode::deserialize(&bytes).unwrap())
+    }
+
+    #[test]
+    fn file_is_created_once_per_module() {
+        let files = SerializedModuleFiles::new(Arc::new(TestPageAllocatorFileDescriptorImpl));
+        let module = serialized_module();
+        let file = files.get_or_create(&module);
+        assert!(Arc::ptr_eq(&file, &files.get_or_create(&module)));
+
+        let mut contents = vec![];
+        (&*file).read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, module.as_slice());
+    }
+
+    #[test]
+    fn files_of_dropped_modules_are_dropped() {
+        let files = SerializedModuleFiles::new(Arc::new(TestPageAllocatorFileDescriptorImpl));
+        let module = serialized_module();
+        files.get_or_create(&module);
+        drop(module);
+        let module = serialized_module();
+        files.get_or_create(&module);
+        assert_eq!(files.len(), 1);
+    }
+}
@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use std::pin::Pin;
 use std::result::Result;
 use std::sync::{Arc, Condvar, Mutex};
@@ -15,6 +16,23 @@ pub enum Error {
     ConnectionBroken,
     /// Failure at server endpoint
     ServerError,
+    /// The server rejected the request for the given reason. Unlike
+    /// `ServerError`, the reason is sent back to the client.
+    Rejected(RejectCode),
+}
+
+/// The reason for which a server rejected a request, so that the client can
+/// tell failures that it should react to apart from internal errors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RejectCode {
+    /// The request referred to an execution that is not active, e.g. because
+    /// it was aborted.
+    UnknownExecId,
+    /// The server is shutting down the connection and handles no more
+    /// requests.
+    ShuttingDown,
+    /// The request could not be handled for any other reason.
+    Internal,
 }
 pub type RPCResult<T> = Result<T, Error>;
 
@@ -16,6 +16,14 @@ impl SandboxClientStub {
 }
 
 impl SandboxService for SandboxClientStub {
+    fn handshake(&self, req: HandshakeRequest) -> Call<HandshakeReply> {
+        let cell = self.channel.call(Request::Handshake(req), |rep| match rep {
+            Reply::Handshake(rep) => Ok(rep),
+            _ => Err(Error::ServerError),
+        });
+        Call::new(cell)
+    }
+
     fn terminate(&self, req: TerminateRequest) -> Call<TerminateReply> {
         let cell = self.channel.call(Request::Terminate(req), |rep| match rep {
             Reply::Terminate(rep) => Ok(rep),
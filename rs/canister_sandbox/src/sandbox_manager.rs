@@ -13,34 +13,64 @@
 //! towards the controller are found in this module.
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::os::unix::io::FromRawFd;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
-use crate::protocol::id::{ExecId, MemoryId, WasmId};
+use crate::protocol::ctlsvc::{EXECUTION_OUTPUT_CHUNK_SIZE, HEARTBEAT_INTERVAL};
+use crate::protocol::id::{ExecId, MemoryId, TraceId, WasmId};
 use crate::protocol::sbxsvc::{
-    CreateExecutionStateSerializedSuccessReply, CreateExecutionStateSuccessReply, OpenMemoryRequest,
+    CreateExecutionStateSerializedSuccessReply, CreateExecutionStateSuccessReply,
+    OpenMemoryRequest, SerializedModuleFile,
 };
 use crate::protocol::structs::{
     MemoryModifications, SandboxExecInput, SandboxExecOutput, StateModifications,
 };
+use crate::protocol::version::{
+    ProtocolVersion, MIN_SUPPORTED_PROTOCOL_VERSION, STREAMED_EXECUTION_OUTPUT_VERSION,
+    TRAP_BACKTRACE_VERSION,
+};
+use crate::rpc::{self, RejectCode};
 use crate::{controller_service::ControllerService, protocol};
 use ic_config::embedders::Config as EmbeddersConfig;
 use ic_embedders::{
     wasm_executor::WasmStateChanges,
     wasm_utils::{compile, decoding::decode_wasm, Segments},
-    CompilationResult, SerializedModule, SerializedModuleBytes, WasmtimeEmbedder,
+    CompilationResult, SerializedModule, WasmtimeEmbedder,
 };
 use ic_interfaces::execution_environment::{
-    ExecutionMode, HypervisorError, HypervisorResult, WasmExecutionOutput,
+    ExecutionMode, HypervisorError, HypervisorResult, OutOfInstructionsHandler, WasmExecutionOutput,
 };
-use ic_logger::ReplicaLogger;
+use ic_logger::{error, ReplicaLogger};
 use ic_replicated_state::page_map::{PageAllocatorRegistry, PageMapSerialization};
 use ic_replicated_state::{EmbedderCache, Global, Memory, PageMap};
 use ic_types::CanisterId;
 
 use crate::dts::{DeterministicTimeSlicingHandler, PausedExecution};
 
+/// Sends a heartbeat whenever the wrapped handler lets the execution continue
+/// in a new slice.
+struct HeartbeatingHandler {
+    inner: DeterministicTimeSlicingHandler,
+    sandbox_manager: Arc<SandboxManager>,
+}
+
+impl OutOfInstructionsHandler for HeartbeatingHandler {
+    fn out_of_instructions(&self, instruction_counter: i64) -> HypervisorResult<i64> {
+        let result = self.inner.out_of_instructions(instruction_counter);
+        self.sandbox_manager.send_heartbeat();
+        result
+    }
+
+    fn yield_for_dirty_memory_copy(&self, instruction_counter: i64) -> HypervisorResult<i64> {
+        let result = self.inner.yield_for_dirty_memory_copy(instruction_counter);
+        self.sandbox_manager.send_heartbeat();
+        result
+    }
+}
+
 /// A canister execution currently in progress.
 struct Execution {
     /// Id of the execution. This is used in communicating back to
@@ -101,6 +131,8 @@ impl Execution {
         total_timer: std::time::Instant,
     ) {
         let run_timer = std::time::Instant::now();
+        let trace_id = exec_input.trace_id;
+        self.sandbox_manager.send_heartbeat();
 
         let message_instruction_limit =
             exec_input.execution_parameters.instruction_limits.message();
@@ -119,9 +151,13 @@ impl Execution {
                     let mut guard = sandbox_manager.repr.lock().unwrap();
                     guard.paused_executions.insert(exec_id, paused_execution);
                 }
-                sandbox_manager
-                    .controller
-                    .execution_paused(protocol::ctlsvc::ExecutionPausedRequest { exec_id, slice });
+                sandbox_manager.controller.execution_paused(
+                    protocol::ctlsvc::ExecutionPausedRequest {
+                        exec_id,
+                        trace_id,
+                        slice,
+                    },
+                );
             },
         );
 
@@ -137,7 +173,7 @@ impl Execution {
                 canister_log,
             },
             deltas,
-            instance_or_system_api,
+            mut instance_or_system_api,
         ) = ic_embedders::wasm_executor::process(
             exec_input.func_ref,
             exec_input.api_type,
@@ -153,7 +189,10 @@ impl Execution {
             &exec_input.globals,
             self.sandbox_manager.log.clone(),
             exec_input.wasm_reserved_pages,
-            Rc::new(out_of_instructions_handler),
+            Rc::new(HeartbeatingHandler {
+                inner: out_of_instructions_handler,
+                sandbox_manager: Arc::clone(&self.sandbox_manager),
+            }),
         );
 
         match wasm_result {
@@ -203,16 +242,14 @@ impl Execution {
                     system_api_call_counters,
                     canister_log,
                 };
-                self.sandbox_manager.controller.execution_finished(
-                    protocol::ctlsvc::ExecutionFinishedRequest {
-                        exec_id: self.exec_id,
-                        exec_output: SandboxExecOutput {
-                            slice,
-                            wasm: wasm_output,
-                            state: state_modifications,
-                            execute_total_duration: total_timer.elapsed(),
-                            execute_run_duration: run_timer.elapsed(),
-                        },
+                self.send_execution_finished(
+                    trace_id,
+                    SandboxExecOutput {
+                        slice,
+                        wasm: wasm_output,
+                        state: state_modifications,
+                        execute_total_duration: total_timer.elapsed(),
+                        execute_run_duration: run_timer.elapsed(),
                     },
                 );
             }
@@ -221,6 +258,11 @@ impl Execution {
                 // was aborted and the controller removed `exec_id` on its side.
             }
             Err(err) => {
+                if let Ok(instance) = &mut instance_or_system_api {
+                    if let Some(backtrace) = instance.take_trap_backtrace() {
+                        self.send_trap_backtrace(trace_id, backtrace);
+                    }
+                }
                 let wasm_output = WasmExecutionOutput {
                     wasm_result: Err(err),
                     num_instructions_left,
@@ -231,21 +273,87 @@ impl Execution {
                     canister_log,
                 };
 
-                self.sandbox_manager.controller.execution_finished(
-                    protocol::ctlsvc::ExecutionFinishedRequest {
-                        exec_id: self.exec_id,
-                        exec_output: SandboxExecOutput {
-                            slice,
-                            wasm: wasm_output,
-                            state: None,
-                            execute_total_duration: total_timer.elapsed(),
-                            execute_run_duration: run_timer.elapsed(),
-                        },
+                self.send_execution_finished(
+                    trace_id,
+                    SandboxExecOutput {
+                        slice,
+                        wasm: wasm_output,
+                        state: None,
+                        execute_total_duration: total_timer.elapsed(),
+                        execute_run_duration: run_timer.elapsed(),
                     },
                 );
             }
         }
     }
+
+    // Sends the Wasm backtrace of the trapping execution to the controller, if
+    // it supports backtraces. The controller receives it before the output.
+    fn send_trap_backtrace(&self, trace_id: TraceId, backtrace: String) {
+        if self.sandbox_manager.protocol_version() < TRAP_BACKTRACE_VERSION {
+            return;
+        }
+        self.sandbox_manager
+            .controller
+            .trap_backtrace(protocol::ctlsvc::TrapBacktraceRequest {
+                exec_id: self.exec_id,
+                trace_id,
+                backtrace,
+            });
+    }
+
+    // Sends the output of the finished execution to the controller. An output
+    // that is too large for a single message is streamed in chunks if the
+    // controller supports it, so that neither side has to buffer one giant
+    // message.
+    fn send_execution_finished(&self, trace_id: TraceId, exec_output: SandboxExecOutput) {
+        let controller = &self.sandbox_manager.controller;
+        let streaming_supported =
+            self.sandbox_manager.protocol_version() >= STREAMED_EXECUTION_OUTPUT_VERSION;
+        let output_size = bincode::serialized_size(&exec_output).unwrap_or(0);
+        if !streaming_supported || output_size <= EXECUTION_OUTPUT_CHUNK_SIZE as u64 {
+            controller.execution_finished(protocol::ctlsvc::ExecutionFinishedRequest {
+                exec_id: self.exec_id,
+                trace_id,
+                exec_output,
+            });
+            return;
+        }
+        let output =
+            bincode::serialize(&exec_output).expect("Failed to serialize an execution output");
+        // Release the original output before streaming its serialized form.
+        drop(exec_output);
+        let mut chunks = output.chunks(EXECUTION_OUTPUT_CHUNK_SIZE).peekable();
+        while let Some(chunk) = chunks.next() {
+            let last = chunks.peek().is_none();
+            let reply =
+                controller.execution_output_chunk(protocol::ctlsvc::ExecutionOutputChunkRequest {
+                    exec_id: self.exec_id,
+                    trace_id,
+                    chunk: chunk.to_vec(),
+                    last,
+                });
+            // Wait for the controller to take each chunk before sending the
+            // next one to bound the amount of buffered data.
+            if !last {
+                match reply.sync() {
+                    Ok(_) => {}
+                    // The controller no longer waits for the output, e.g.
+                    // because it is terminating this process.
+                    Err(rpc::Error::Rejected(
+                        RejectCode::UnknownExecId | RejectCode::ShuttingDown,
+                    )) => return,
+                    Err(err) => {
+                        error!(
+                            self.sandbox_manager.log,
+                            "Failed to stream the output of execution {}: {:?}", self.exec_id, err
+                        );
+                        return;
+                    }
+                }
+            }
+        }
+    }
 }
 
 /// Manages the entirety of the sandbox process. It provides the methods
@@ -256,6 +364,11 @@ pub struct SandboxManager {
     controller: Arc<dyn ControllerService>,
     embedder: Arc<WasmtimeEmbedder>,
     page_allocator_registry: Arc<PageAllocatorRegistry>,
+    // The IPC protocol version agreed on with the controller.
+    protocol_version: AtomicU32,
+    // The time at which the last heartbeat was sent, or `None` if the
+    // controller does not expect heartbeats.
+    last_heartbeat: Option<Mutex<Instant>>,
     log: ReplicaLogger,
 }
 struct SandboxManagerInt {
@@ -292,7 +405,42 @@ impl SandboxManager {
             embedder,
             log,
             page_allocator_registry: Arc::new(PageAllocatorRegistry::new()),
+            protocol_version: AtomicU32::new(MIN_SUPPORTED_PROTOCOL_VERSION),
+            last_heartbeat: config
+                .sandbox_heartbeat_timeout
+                .map(|_| Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Tells the controller that this process is responsive, unless it did so
+    /// recently. Called by the execution threads whenever they start or resume
+    /// an execution slice, so that a wedged execution thread stops the
+    /// heartbeats.
+    fn send_heartbeat(&self) {
+        let last_heartbeat = match &self.last_heartbeat {
+            Some(last_heartbeat) => last_heartbeat,
+            None => return,
+        };
+        let now = Instant::now();
+        {
+            let mut last_heartbeat = last_heartbeat.lock().unwrap();
+            if now.saturating_duration_since(*last_heartbeat) < HEARTBEAT_INTERVAL {
+                return;
+            }
+            *last_heartbeat = now;
         }
+        self.controller
+            .heartbeat(protocol::ctlsvc::HeartbeatRequest {})
+            .on_completion(|_| {});
+    }
+
+    /// Records the IPC protocol version agreed on with the controller.
+    pub fn set_protocol_version(&self, version: ProtocolVersion) {
+        self.protocol_version.store(version, Ordering::Relaxed);
+    }
+
+    fn protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version.load(Ordering::Relaxed)
     }
 
     /// Compiles the given Wasm binary and registers it under the given id.
@@ -327,7 +475,7 @@ impl SandboxManager {
     pub fn open_wasm_serialized(
         &self,
         wasm_id: WasmId,
-        serialized_module: &SerializedModuleBytes,
+        serialized_module: SerializedModuleFile,
     ) -> HypervisorResult<(Arc<EmbedderCache>, Duration)> {
         let mut guard = self.repr.lock().unwrap();
         assert!(
@@ -336,9 +484,14 @@ impl SandboxManager {
             wasm_id,
         );
         let deserialization_timer = Instant::now();
-        let instance_pre = self
-            .embedder
-            .deserialize_module_and_pre_instantiate(serialized_module);
+        // SAFETY: The file descriptor was received along with the request and
+        // is not owned by anything else. The controller created the file from
+        // a `SerializedModuleBytes` and never modifies it afterwards.
+        let instance_pre = unsafe {
+            let file = std::fs::File::from_raw_fd(serialized_module.fd.fd);
+            self.embedder
+                .deserialize_module_file_and_pre_instantiate(file)
+        };
         let cache = Arc::new(EmbedderCache::new(instance_pre.clone()));
         let deserialization_time = deserialization_timer.elapsed();
         guard.caches.insert(wasm_id, Arc::clone(&cache));
@@ -501,7 +654,8 @@ impl SandboxManager {
     pub fn create_execution_state_serialized(
         &self,
         wasm_id: WasmId,
-        serialized_module: Arc<SerializedModule>,
+        serialized_module: SerializedModuleFile,
+        data_segments: Segments,
         wasm_page_map: PageMapSerialization,
         next_wasm_memory_id: MemoryId,
         canister_id: CanisterId,
@@ -509,11 +663,11 @@ impl SandboxManager {
     ) -> HypervisorResult<CreateExecutionStateSerializedSuccessReply> {
         let timer = Instant::now();
         let (embedder_cache, deserialization_time) =
-            self.open_wasm_serialized(wasm_id, &serialized_module.bytes)?;
+            self.open_wasm_serialized(wasm_id, serialized_module)?;
         let (wasm_memory_modifications, exported_globals) = self
             .create_initial_memory_and_globals(
                 &embedder_cache,
-                &serialized_module.data_segments,
+                &data_segments,
                 wasm_page_map,
                 next_wasm_memory_id,
                 canister_id,
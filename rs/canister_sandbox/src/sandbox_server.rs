@@ -5,7 +5,11 @@ use std::sync::Arc;
 /// actual "logic" in this module, just bridging the interfaces.
 use crate::sandbox_manager::SandboxManager;
 
-use crate::{protocol::sbxsvc::*, rpc, sandbox_service::SandboxService};
+use crate::{
+    protocol::{sbxsvc::*, version::ProtocolVersionRange},
+    rpc,
+    sandbox_service::SandboxService,
+};
 
 /// This is the implementation of the RPC interface exposed by the
 /// sandbox process and "binds everything together": All RPCs pass
@@ -28,6 +32,15 @@ impl SandboxServer {
 }
 
 impl SandboxService for SandboxServer {
+    fn handshake(&self, req: HandshakeRequest) -> rpc::Call<HandshakeReply> {
+        let supported = ProtocolVersionRange::supported();
+        let version = supported.negotiate(&req.supported);
+        if let Some(version) = version {
+            self.manager.set_protocol_version(version);
+        }
+        rpc::Call::new_resolved(Ok(HandshakeReply { supported, version }))
+    }
+
     fn terminate(&self, _req: TerminateRequest) -> rpc::Call<TerminateReply> {
         std::process::exit(0);
     }
@@ -46,7 +59,7 @@ impl SandboxService for SandboxServer {
     ) -> rpc::Call<OpenWasmSerializedReply> {
         let result = self
             .manager
-            .open_wasm_serialized(req.wasm_id, &req.serialized_module)
+            .open_wasm_serialized(req.wasm_id, req.serialized_module)
             .map(|_| ());
         rpc::Call::new_resolved(Ok(OpenWasmSerializedReply(result)))
     }
@@ -123,6 +136,7 @@ impl SandboxService for SandboxServer {
         let result = self.manager.create_execution_state_serialized(
             req.wasm_id,
             req.serialized_module,
+            req.data_segments,
             req.wasm_page_map,
             req.next_wasm_memory_id,
             req.canister_id,
@@ -141,7 +155,7 @@ mod tests {
         fdenum::EnumerateInnerFileDescriptors,
         protocol::{
             self,
-            id::{ExecId, MemoryId, WasmId},
+            id::{ExecId, MemoryId, TraceId, WasmId},
             structs::SandboxExecInput,
         },
     };
@@ -276,6 +290,7 @@ mod tests {
             next_stable_memory_id,
             sandbox_safe_system_state: sandbox_safe_system_state(caller, call_context_id),
             wasm_reserved_pages: NumWasmPages::from(0),
+            trace_id: TraceId::new(),
         }
     }
 
@@ -307,6 +322,7 @@ mod tests {
             next_stable_memory_id: MemoryId::new(),
             sandbox_safe_system_state: sandbox_safe_system_state(caller, call_context_id),
             wasm_reserved_pages: NumWasmPages::from(0),
+            trace_id: TraceId::new(),
         }
     }
 
@@ -319,11 +335,23 @@ mod tests {
                 &self, req : protocol::ctlsvc::ExecutionFinishedRequest
             ) -> rpc::Call<protocol::ctlsvc::ExecutionFinishedReply>;
 
+            fn execution_output_chunk(
+                &self, req : protocol::ctlsvc::ExecutionOutputChunkRequest
+            ) -> rpc::Call<protocol::ctlsvc::ExecutionOutputChunkReply>;
+
+            fn trap_backtrace(
+                &self, req : protocol::ctlsvc::TrapBacktraceRequest
+            ) -> rpc::Call<protocol::ctlsvc::TrapBacktraceReply>;
+
             fn execution_paused(
                 &self, req : protocol::ctlsvc::ExecutionPausedRequest
             ) -> rpc::Call<protocol::ctlsvc::ExecutionPausedReply>;
 
             fn log_via_replica(&self, log: protocol::logging::LogRequest) -> rpc::Call<()>;
+
+            fn heartbeat(
+                &self, req : protocol::ctlsvc::HeartbeatRequest
+            ) -> rpc::Call<protocol::ctlsvc::HeartbeatReply>;
         }
     }
 
@@ -3,6 +3,9 @@ use crate::rpc::{Call, DemuxServer};
 
 /// RPC interface exposed by sandbox process.
 pub trait SandboxService: Send + Sync {
+    /// Agrees on the protocol version to use with the sandbox.
+    fn handshake(&self, req: HandshakeRequest) -> Call<HandshakeReply>;
+
     /// Terminate the sandbox.
     fn terminate(&self, req: TerminateRequest) -> Call<TerminateReply>;
 
@@ -59,6 +62,7 @@ impl<Svc: SandboxService + Send + Sync> DemuxServer<Request, Reply> for Svc {
     /// matched reply (sync or async)
     fn dispatch(&self, req: Request) -> Call<Reply> {
         match req {
+            Request::Handshake(req) => Call::new_wrap(self.handshake(req), Reply::Handshake),
             Request::Terminate(req) => Call::new_wrap(self.terminate(req), Reply::Terminate),
             Request::OpenWasm(req) => Call::new_wrap(self.open_wasm(req), Reply::OpenWasm),
             Request::OpenWasmSerialized(req) => {
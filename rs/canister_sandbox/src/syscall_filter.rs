@@ -0,0 +1,349 @@
+//! Seccomp filters that restrict the system calls of sandbox processes.
+//!
+//! A sandbox process installs the filter of the configured
+//! [`SandboxSyscallFilter`] profile before it spawns any thread, so that the
+//! filter applies to all threads of the process. The filters are classic BPF
+//! programs that compare the system call number against a fixed list:
+//! `Strict` and `Debug` allow only the listed system calls, while `Compat`
+//! denies only the listed ones. System calls of a foreign architecture or ABI
+//! always kill the process, since their numbers would bypass the lists.
+
+use ic_config::embedders::SandboxSyscallFilter;
+
+/// Installs the seccomp filter of the given profile in the calling process.
+#[cfg(target_os = "linux")]
+pub(crate) fn install(profile: SandboxSyscallFilter) -> std::io::Result<()> {
+    let program = match profile {
+        SandboxSyscallFilter::Disabled => return Ok(()),
+        SandboxSyscallFilter::Strict => linux::build_program(
+            linux::ALLOWED_SYSCALLS,
+            libc::SECCOMP_RET_ALLOW,
+            libc::SECCOMP_RET_KILL_PROCESS,
+        ),
+        SandboxSyscallFilter::Debug => linux::build_program(
+            linux::ALLOWED_SYSCALLS,
+            libc::SECCOMP_RET_ALLOW,
+            libc::SECCOMP_RET_LOG,
+        ),
+        SandboxSyscallFilter::Compat => linux::build_program(
+            linux::DENIED_SYSCALLS,
+            libc::SECCOMP_RET_ERRNO | libc::EPERM as u32,
+            libc::SECCOMP_RET_ALLOW,
+        ),
+    };
+    linux::install_program(&program)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn install(profile: SandboxSyscallFilter) -> std::io::Result<()> {
+    match profile {
+        SandboxSyscallFilter::Disabled => Ok(()),
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "seccomp filters are only supported on Linux",
+        )),
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use libc::{c_long, c_ulong, sock_filter, sock_fprog};
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xc000_003e;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+    // System calls of the x32 ABI have this bit set in their number.
+    #[cfg(target_arch = "x86_64")]
+    const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+    // Offsets of the fields of `struct seccomp_data`.
+    const SECCOMP_DATA_NR_OFFSET: u32 = 0;
+    const SECCOMP_DATA_ARCH_OFFSET: u32 = 4;
+
+    /// The system calls allowed by the `Strict` and `Debug` profiles: memory
+    /// management, I/O on the file descriptors passed by the replica, the IPC
+    /// socket, threads, signals and time.
+    pub(super) const ALLOWED_SYSCALLS: &[c_long] = &[
+        libc::SYS_read,
+        libc::SYS_write,
+        libc::SYS_readv,
+        libc::SYS_writev,
+        libc::SYS_pread64,
+        libc::SYS_pwrite64,
+        libc::SYS_preadv,
+        libc::SYS_pwritev,
+        libc::SYS_close,
+        libc::SYS_lseek,
+        libc::SYS_fstat,
+        libc::SYS_newfstatat,
+        libc::SYS_statx,
+        libc::SYS_openat,
+        libc::SYS_readlinkat,
+        libc::SYS_getdents64,
+        libc::SYS_fcntl,
+        libc::SYS_dup,
+        libc::SYS_dup3,
+        libc::SYS_ftruncate,
+        libc::SYS_fallocate,
+        libc::SYS_fsync,
+        libc::SYS_fdatasync,
+        libc::SYS_fchmod,
+        libc::SYS_copy_file_range,
+        libc::SYS_memfd_create,
+        libc::SYS_mmap,
+        libc::SYS_munmap,
+        libc::SYS_mprotect,
+        libc::SYS_mremap,
+        libc::SYS_madvise,
+        libc::SYS_mincore,
+        libc::SYS_msync,
+        libc::SYS_brk,
+        libc::SYS_recvmsg,
+        libc::SYS_sendmsg,
+        libc::SYS_recvfrom,
+        libc::SYS_sendto,
+        libc::SYS_shutdown,
+        libc::SYS_getsockopt,
+        libc::SYS_setsockopt,
+        libc::SYS_ppoll,
+        libc::SYS_epoll_create1,
+        libc::SYS_epoll_ctl,
+        libc::SYS_epoll_pwait,
+        libc::SYS_eventfd2,
+        libc::SYS_pipe2,
+        libc::SYS_ioctl,
+        libc::SYS_futex,
+        libc::SYS_clone,
+        libc::SYS_clone3,
+        libc::SYS_set_robust_list,
+        libc::SYS_get_robust_list,
+        libc::SYS_rseq,
+        libc::SYS_set_tid_address,
+        libc::SYS_exit,
+        libc::SYS_exit_group,
+        libc::SYS_getpid,
+        libc::SYS_getppid,
+        libc::SYS_gettid,
+        libc::SYS_tgkill,
+        libc::SYS_rt_sigaction,
+        libc::SYS_rt_sigprocmask,
+        libc::SYS_rt_sigreturn,
+        libc::SYS_sigaltstack,
+        libc::SYS_restart_syscall,
+        libc::SYS_clock_gettime,
+        libc::SYS_clock_getres,
+        libc::SYS_clock_nanosleep,
+        libc::SYS_nanosleep,
+        libc::SYS_gettimeofday,
+        libc::SYS_sched_yield,
+        libc::SYS_sched_getaffinity,
+        libc::SYS_getrandom,
+        libc::SYS_getrusage,
+        libc::SYS_prlimit64,
+        libc::SYS_prctl,
+        libc::SYS_membarrier,
+        libc::SYS_uname,
+        libc::SYS_sysinfo,
+        libc::SYS_getcwd,
+        libc::SYS_getuid,
+        libc::SYS_geteuid,
+        libc::SYS_getgid,
+        libc::SYS_getegid,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_open,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_stat,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_lstat,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_poll,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_access,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_readlink,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_pipe,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_dup2,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_epoll_wait,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_arch_prctl,
+    ];
+
+    /// The system calls denied by the `Compat` profile. A sandbox process
+    /// never needs them, but a compromised one could use them to escape.
+    pub(super) const DENIED_SYSCALLS: &[c_long] = &[
+        libc::SYS_execve,
+        libc::SYS_execveat,
+        libc::SYS_ptrace,
+        libc::SYS_process_vm_readv,
+        libc::SYS_process_vm_writev,
+        libc::SYS_socket,
+        libc::SYS_socketpair,
+        libc::SYS_connect,
+        libc::SYS_bind,
+        libc::SYS_listen,
+        libc::SYS_accept,
+        libc::SYS_accept4,
+        libc::SYS_mount,
+        libc::SYS_umount2,
+        libc::SYS_pivot_root,
+        libc::SYS_chroot,
+        libc::SYS_setns,
+        libc::SYS_unshare,
+        libc::SYS_kexec_load,
+        libc::SYS_init_module,
+        libc::SYS_finit_module,
+        libc::SYS_delete_module,
+        libc::SYS_bpf,
+        libc::SYS_perf_event_open,
+        libc::SYS_userfaultfd,
+        libc::SYS_keyctl,
+        libc::SYS_add_key,
+        libc::SYS_request_key,
+        libc::SYS_open_by_handle_at,
+        libc::SYS_name_to_handle_at,
+        libc::SYS_setuid,
+        libc::SYS_setgid,
+        libc::SYS_setreuid,
+        libc::SYS_setregid,
+        libc::SYS_setresuid,
+        libc::SYS_setresgid,
+        libc::SYS_setgroups,
+        libc::SYS_personality,
+        libc::SYS_reboot,
+        libc::SYS_swapon,
+        libc::SYS_swapoff,
+        libc::SYS_syslog,
+        libc::SYS_acct,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_fork,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_vfork,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_iopl,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_ioperm,
+    ];
+
+    fn statement(code: u32, k: u32) -> sock_filter {
+        sock_filter {
+            code: code as u16,
+            jt: 0,
+            jf: 0,
+            k,
+        }
+    }
+
+    fn jump(code: u32, k: u32, jt: u8, jf: u8) -> sock_filter {
+        sock_filter {
+            code: code as u16,
+            jt,
+            jf,
+            k,
+        }
+    }
+
+    /// Builds a program that returns `listed_action` for the given system
+    /// calls and `default_action` for all other system calls of the native
+    /// architecture.
+    pub(super) fn build_program(
+        syscalls: &[c_long],
+        listed_action: u32,
+        default_action: u32,
+    ) -> Vec<sock_filter> {
+        let mut program = vec![
+            statement(
+                libc::BPF_LD | libc::BPF_W | libc::BPF_ABS,
+                SECCOMP_DATA_ARCH_OFFSET,
+            ),
+            jump(
+                libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K,
+                AUDIT_ARCH,
+                1,
+                0,
+            ),
+            statement(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_KILL_PROCESS),
+            statement(
+                libc::BPF_LD | libc::BPF_W | libc::BPF_ABS,
+                SECCOMP_DATA_NR_OFFSET,
+            ),
+        ];
+        #[cfg(target_arch = "x86_64")]
+        program.extend([
+            jump(
+                libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K,
+                X32_SYSCALL_BIT,
+                0,
+                1,
+            ),
+            statement(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_KILL_PROCESS),
+        ]);
+        for syscall in syscalls {
+            program.push(jump(
+                libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K,
+                *syscall as u32,
+                0,
+                1,
+            ));
+            program.push(statement(libc::BPF_RET | libc::BPF_K, listed_action));
+        }
+        program.push(statement(libc::BPF_RET | libc::BPF_K, default_action));
+        program
+    }
+
+    pub(super) fn install_program(program: &[sock_filter]) -> std::io::Result<()> {
+        let fprog = sock_fprog {
+            len: program.len() as u16,
+            filter: program.as_ptr() as *mut sock_filter,
+        };
+        // SAFETY: `fprog` points to a valid BPF program that outlives the
+        // calls. The kernel copies the program when installing the filter.
+        unsafe {
+            if libc::prctl(
+                libc::PR_SET_NO_NEW_PRIVS,
+                1 as c_ulong,
+                0 as c_ulong,
+                0 as c_ulong,
+                0 as c_ulong,
+            ) != 0
+            {
+                return Err(std::io::Error::last_os_error());
+            }
+            if libc::prctl(
+                libc::PR_SET_SECCOMP,
+                libc::SECCOMP_MODE_FILTER as c_ulong,
+                &fprog as *const sock_fprog,
+            ) != 0
+            {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::linux::*;
+
+    #[test]
+    fn program_length_fits_the_kernel_limit() {
+        // The kernel rejects programs with more than `BPF_MAXINSNS`
+        // instructions.
+        for syscalls in [ALLOWED_SYSCALLS, DENIED_SYSCALLS] {
+            let program = build_program(syscalls, libc::SECCOMP_RET_ALLOW, libc::SECCOMP_RET_LOG);
+            assert!(program.len() <= libc::BPF_MAXINSNS as usize);
+        }
+    }
+
+    #[test]
+    fn allowed_and_denied_syscalls_are_disjoint() {
+        for syscall in DENIED_SYSCALLS {
+            assert!(!ALLOWED_SYSCALLS.contains(syscall), "{}", syscall);
+        }
+    }
+}
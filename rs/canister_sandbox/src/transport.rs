@@ -1,5 +1,5 @@
 use crate::fdenum::EnumerateInnerFileDescriptors;
-use crate::frame_decoder::FrameDecoder;
+use crate::frame_decoder::{Frame, FrameDecoder, SHARED_MEMORY_FRAME_FLAG};
 use crate::rpc::MessageSink;
 
 use bytes::{
@@ -9,8 +9,10 @@ use bytes::{
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+use std::fs::File;
 use std::marker::PhantomData;
-use std::os::unix::{io::AsRawFd, io::RawFd, net::UnixStream};
+use std::os::unix::fs::FileExt;
+use std::os::unix::{io::AsRawFd, io::FromRawFd, io::RawFd, net::UnixStream};
 use std::sync::{Arc, Condvar, Mutex};
 use std::{convert::TryInto, time::Duration};
 
@@ -24,6 +26,12 @@ const INITIAL_BUFFER_CAPACITY: usize = 65536;
 // The minimum buffer capacity for reading in `recv_msg()`.
 const MIN_READ_BUFFER_CAPACITY: usize = 16384;
 
+// Messages of at least this size are passed in shared memory instead of
+// being copied through the socket.
+const MIN_SHARED_MEMORY_MESSAGE_SIZE: usize = 1 << 20;
+// The payload of a shared memory frame is the u64 length of the message.
+const SHARED_MEMORY_FRAME_PAYLOAD_SIZE: usize = std::mem::size_of::<u64>();
+
 // The timeout after which the IPC buffers are trimmed.
 const IDLE_TIMEOUT_TO_TRIM_BUFFER: Duration = Duration::from_secs(50);
 
@@ -109,6 +117,9 @@ pub struct UnixStreamMessageWriter<Message: 'static + Serialize + Send> {
 struct UnixStreamMessageWriterInt<Message: 'static + Send> {
     buf: BytesMut,
     fds: Vec<RawFd>,
+    // The shared memory files of the frames in `buf`. They are kept open
+    // until their file descriptors have been sent.
+    shared_memory: Vec<File>,
     sending_in_background: bool,
     quit_requested: bool,
     // This is needed only for testing.
@@ -128,6 +139,7 @@ impl<Message: 'static + Serialize + Send + EnumerateInnerFileDescriptors>
             state: Mutex::new(UnixStreamMessageWriterInt::<Message> {
                 buf: BytesMut::new(),
                 fds: vec![],
+                shared_memory: vec![],
                 sending_in_background: false,
                 quit_requested: false,
                 number_of_timeouts: 0,
@@ -151,7 +163,7 @@ impl<Message: 'static + Serialize + Send + EnumerateInnerFileDescriptors>
         };
 
         loop {
-            let (mut buf, mut fds) = {
+            let (mut buf, mut fds, shared_memory) = {
                 let mut guard = self.state.lock().unwrap();
                 if awaiting_input(&mut guard) {
                     guard.sending_in_background = false;
@@ -174,11 +186,16 @@ impl<Message: 'static + Serialize + Send + EnumerateInnerFileDescriptors>
                 if guard.quit_requested {
                     return;
                 }
-                (guard.buf.split(), std::mem::take(&mut guard.fds))
+                (
+                    guard.buf.split(),
+                    std::mem::take(&mut guard.fds),
+                    std::mem::take(&mut guard.shared_memory),
+                )
             };
             while !buf.is_empty() {
                 send_message(&self.socket, &mut buf, &mut fds, 0);
             }
+            drop(shared_memory);
         }
     }
 
@@ -198,11 +215,34 @@ impl<Message: 'static + Serialize + Send + EnumerateInnerFileDescriptors>
             return;
         }
 
+        self.enqueue_frame(data.len() as u64, data, fds, None);
+    }
+
+    // Writes a frame that refers to the given shared memory file holding a
+    // message of the given length. The file descriptor of the shared memory
+    // is passed ahead of the file descriptors of the message itself.
+    fn write_shared_memory_frame(&self, shared_memory: File, len: usize, fds: &[RawFd]) {
+        // See `write_frame()` for the invariant.
+        assert!(SHARED_MEMORY_FRAME_PAYLOAD_SIZE > fds.len());
+
+        let mut all_fds = Vec::with_capacity(fds.len() + 1);
+        all_fds.push(shared_memory.as_raw_fd());
+        all_fds.extend_from_slice(fds);
+        self.enqueue_frame(
+            SHARED_MEMORY_FRAME_FLAG | SHARED_MEMORY_FRAME_PAYLOAD_SIZE as u64,
+            &(len as u64).to_be_bytes(),
+            &all_fds,
+            Some(shared_memory),
+        );
+    }
+
+    fn enqueue_frame(&self, tag: u64, data: &[u8], fds: &[RawFd], shared_memory: Option<File>) {
         let mut guard = self.state.lock().unwrap();
         let state = &mut *guard;
-        state.buf.put_u64(data.len() as u64);
+        state.buf.put_u64(tag);
         state.buf.extend_from_slice(data);
         state.fds.extend_from_slice(fds);
+        state.shared_memory.extend(shared_memory);
         if !state.sending_in_background {
             send_message(
                 &self.socket,
@@ -210,7 +250,9 @@ impl<Message: 'static + Serialize + Send + EnumerateInnerFileDescriptors>
                 &mut state.fds,
                 libc::MSG_DONTWAIT,
             );
-            if !state.buf.is_empty() {
+            if state.buf.is_empty() {
+                state.shared_memory.clear();
+            } else {
                 state.sending_in_background = true;
                 self.trigger_background_sending.notify_one();
             }
@@ -246,6 +288,17 @@ impl<
         // Serialize the message.
         let serialized_msg = bincode::serialize(&msg).expect("Failed to serialize message");
 
+        // Pass large messages in shared memory, so that they are neither
+        // copied through the socket nor held in the IPC buffers.
+        if serialized_msg.len() >= MIN_SHARED_MEMORY_MESSAGE_SIZE
+            && fds.len() < SHARED_MEMORY_FRAME_PAYLOAD_SIZE
+        {
+            if let Some(shared_memory) = create_shared_memory(&serialized_msg) {
+                self.write_shared_memory_frame(shared_memory, serialized_msg.len(), &fds);
+                return;
+            }
+        }
+
         // Send message data + file descriptors down.
         // There must be a field in the struct for every file descriptor
         // that we send, so the amount of data in the message is
@@ -429,9 +482,21 @@ pub fn socket_read_messages<
     let mut fds = Vec::<RawFd>::new();
     let mut reader = SocketReaderWithTimeout::new(socket);
     loop {
-        while let Some(mut frame) = decoder.decode(&mut buf) {
-            install_file_descriptors(&mut frame, &mut fds);
-            handler(frame);
+        while let Some(frame) = decoder.decode(&mut buf) {
+            let mut message = match frame {
+                Frame::Inline(message) => message,
+                Frame::Shared { len } => match read_shared_memory_message(len, &mut fds) {
+                    Ok(message) => message,
+                    Err(err) => {
+                        // The peer cannot be trusted anymore, so stop reading
+                        // from it, which closes the connection.
+                        eprintln!("Failed to read a shared memory frame: {}", err);
+                        return;
+                    }
+                },
+            };
+            install_file_descriptors(&mut message, &mut fds);
+            handler(message);
         }
 
         let num_bytes_received =
@@ -468,6 +533,63 @@ pub fn socket_read_messages<
     }
 }
 
+/// Creates an in-memory file holding the given data. Returns `None` if such
+/// a file cannot be created, in which case the data is sent inline.
+#[cfg(target_os = "linux")]
+fn create_shared_memory(data: &[u8]) -> Option<File> {
+    if *ic_sys::IS_WSL {
+        return None;
+    }
+    let fd = nix::sys::memfd::memfd_create(
+        &std::ffi::CString::default(),
+        nix::sys::memfd::MemFdCreateFlag::MFD_CLOEXEC,
+    )
+    .ok()?;
+    // SAFETY: The file descriptor was just created and is not owned by
+    // anything else.
+    let file = unsafe { File::from_raw_fd(fd) };
+    file.write_all_at(data, 0).ok()?;
+    Some(file)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn create_shared_memory(_data: &[u8]) -> Option<File> {
+    None
+}
+
+/// A helper that reads a message of the given length from the shared memory
+/// file whose descriptor is the first one of the given file descriptors. The
+/// length comes from the peer, so it is checked against the size of the file
+/// before anything is allocated.
+fn read_shared_memory_message<Message: DeserializeOwned>(
+    len: usize,
+    fds: &mut Vec<RawFd>,
+) -> std::io::Result<Message> {
+    if fds.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Missing file descriptor of a shared memory frame",
+        ));
+    }
+    // SAFETY: The file descriptor was received along with the frame and
+    // is owned by the reader.
+    let file = unsafe { File::from_raw_fd(fds.remove(0)) };
+    let size = file.metadata()?.len();
+    if len as u64 > size {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "Shared memory frame of {} bytes exceeds its file of {} bytes",
+                len, size
+            ),
+        ));
+    }
+    let mut data = vec![0; len];
+    file.read_exact_at(&mut data, 0)?;
+    bincode::deserialize(&data)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+}
+
 /// A helper that write the given file descriptors into the file descriptor
 /// slots of the given message in the same order defined by `enumerate_fds()`.
 fn install_file_descriptors<Message: EnumerateInnerFileDescriptors>(
@@ -685,6 +807,7 @@ mod tests {
     use std::io::SeekFrom;
     use std::io::Write;
     use std::os::unix::io::FromRawFd;
+    use std::os::unix::io::IntoRawFd;
     use std::sync::mpsc::sync_channel;
 
     #[derive(Serialize, Deserialize, Clone)]
@@ -928,6 +1051,84 @@ mod tests {
         );
     }
 
+    #[test]
+    fn large_message_with_file_descriptor() {
+        let (comm_send, comm_recv) = std::os::unix::net::UnixStream::pair().unwrap();
+        let comm_send = Arc::new(comm_send);
+        let comm_recv = Arc::new(comm_recv);
+
+        let sender =
+            UnixStreamMessageWriter::<LargeMessage>::new(comm_send, IDLE_TIMEOUT_TO_TRIM_BUFFER);
+
+        let (ch_sender, ch_receiver) = sync_channel::<LargeMessage>(2);
+        std::thread::spawn(move || {
+            socket_read_messages(
+                |message: LargeMessage| {
+                    ch_sender.send(message).unwrap();
+                },
+                comm_recv,
+                SocketReaderConfig::for_testing(),
+            );
+        });
+
+        let mut file = File::options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(temp_dir().join("large-message-file"))
+            .unwrap();
+        file.write_all(b"Hello").unwrap();
+
+        let payload = vec![7; 4 * MIN_SHARED_MEMORY_MESSAGE_SIZE];
+        sender.handle(
+            0,
+            LargeMessage {
+                payload: payload.clone(),
+                fd: file.as_raw_fd(),
+            },
+        );
+        // A small message following the large one must not be affected.
+        sender.handle(
+            0,
+            LargeMessage {
+                payload: vec![8; 10],
+                fd: file.as_raw_fd(),
+            },
+        );
+        let message1 = ch_receiver.recv().unwrap();
+        let message2 = ch_receiver.recv().unwrap();
+        sender.stop();
+
+        assert_eq!(message1.payload, payload);
+        assert_eq!(message2.payload, vec![8; 10]);
+        for message in [message1, message2] {
+            assert_ne!(message.fd, file.as_raw_fd());
+            let mut file = unsafe { File::from_raw_fd(message.fd) };
+            file.seek(SeekFrom::Start(0)).unwrap();
+            let mut s = String::new();
+            file.read_to_string(&mut s).unwrap();
+            assert_eq!(s, "Hello");
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Clone)]
+    struct LargeMessage {
+        payload: Vec<u8>,
+        fd: RawFd,
+    }
+
+    impl MuxInto<LargeMessage> for LargeMessage {
+        fn wrap(self, _cookie: u64) -> LargeMessage {
+            self
+        }
+    }
+
+    impl EnumerateInnerFileDescriptors for LargeMessage {
+        fn enumerate_fds<'a>(&'a mut self, fds: &mut Vec<&'a mut RawFd>) {
+            fds.push(&mut self.fd);
+        }
+    }
+
     #[test]
     fn reader_timeout() {
         // Create a socketpair through which we will communicate.
@@ -968,4 +1169,21 @@ mod tests {
         );
         assert_eq!(bytes, None);
     }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn shared_memory_frame_longer_than_its_file_is_rejected() {
+        let data = bincode::serialize(&vec![42_u8; 100]).unwrap();
+        let file = create_shared_memory(&data).unwrap();
+        let mut fds = vec![file.into_raw_fd()];
+        assert!(read_shared_memory_message::<Vec<u8>>(data.len() + 1, &mut fds).is_err());
+        assert!(fds.is_empty());
+
+        let file = create_shared_memory(&data).unwrap();
+        let mut fds = vec![file.into_raw_fd()];
+        let message: Vec<u8> = read_shared_memory_message(data.len(), &mut fds).unwrap();
+        assert_eq!(message, vec![42; 100]);
+
+        assert!(read_shared_memory_message::<Vec<u8>>(data.len(), &mut vec![]).is_err());
+    }
 }
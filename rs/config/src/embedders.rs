@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use ic_base_types::NumBytes;
+use ic_base_types::{CanisterId, NumBytes};
 use ic_registry_subnet_type::SubnetType;
 use ic_sys::PAGE_SIZE;
 use ic_types::{NumInstructions, NumOsPages};
@@ -51,6 +51,17 @@ pub(crate) const DEFAULT_MAX_SANDBOX_COUNT: usize = 2_000;
 /// duration and sandbox process eviction is activated.
 pub(crate) const DEFAULT_MAX_SANDBOX_IDLE_TIME: Duration = Duration::from_secs(30 * 60);
 
+/// Idle sandbox processes are replaced by new ones after they have been in the
+/// sandbox process pool for this duration.
+pub(crate) const DEFAULT_MAX_POOLED_SANDBOX_IDLE_TIME: Duration = Duration::from_secs(10 * 60);
+
+/// The default rate limit of the messages that a sandbox process logs via the
+/// replica.
+pub(crate) const DEFAULT_SANDBOX_LOG_RATE_LIMIT: SandboxLogRateLimit = SandboxLogRateLimit {
+    messages_per_second: 100,
+    burst: 1_000,
+};
+
 /// The maximum number of pages that a message dirties without optimizing dirty
 /// page copying by triggering a new execution slice for copying pages.
 /// This default is 1 GiB.
@@ -108,6 +119,10 @@ pub struct FeatureFlags {
     // TODO(IC-1674): remove this flag once the feature is enabled by default.
     /// Indicates whether the best-effort responses feature is enabled.
     pub best_effort_responses: FlagStatus,
+    /// Indicates whether the Wasm backtraces of trapping executions are
+    /// recorded and reported to the replica. Recording them makes traps
+    /// slower.
+    pub canister_backtrace: FlagStatus,
 }
 
 impl FeatureFlags {
@@ -119,6 +134,7 @@ impl FeatureFlags {
             canister_logging: FlagStatus::Enabled,
             wasm64: FlagStatus::Disabled,
             best_effort_responses: FlagStatus::Disabled,
+            canister_backtrace: FlagStatus::Disabled,
         }
     }
 }
@@ -146,6 +162,124 @@ pub struct StableMemoryPageLimit {
     pub query: NumOsPages,
 }
 
+/// Resource limits of a single sandbox process. A sandbox process that
+/// exceeds any of them stops, which is handled like a crash of the process.
+/// In particular, a sandbox process stopping during a replicated execution
+/// panics the replica, so the limits must be far above what any legitimate
+/// execution uses.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SandboxResourceLimits {
+    /// Maximum data memory (private writable mappings, e.g. the heap) of the
+    /// sandbox process, enforced by the kernel with `RLIMIT_DATA` when the
+    /// process is launched. Allocations beyond it fail. The memory of the page
+    /// allocator is shared and not counted. `None` means unlimited.
+    pub max_memory: Option<NumBytes>,
+    /// Maximum CPU time (user and system) the sandbox process may consume
+    /// while running a single execution slice. The replica controller
+    /// terminates the process once the limit is exceeded. `None` means
+    /// unlimited.
+    pub max_execution_cpu_time: Option<Duration>,
+}
+
+impl SandboxResourceLimits {
+    pub const fn unlimited() -> Self {
+        Self {
+            max_memory: None,
+            max_execution_cpu_time: None,
+        }
+    }
+}
+
+/// Sandbox processes are retired after serving many executions or running for
+/// a long time, to bound memory fragmentation and leaked resources. A retired
+/// process is not used for new executions and terminates once its executions
+/// in progress and paused executions completed.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SandboxRecyclePolicy {
+    /// The number of executions after which a sandbox process is retired.
+    /// `None` means unlimited.
+    pub max_executions: Option<u64>,
+    /// The uptime after which a sandbox process is retired. `None` means
+    /// unlimited.
+    pub max_uptime: Option<Duration>,
+}
+
+impl SandboxRecyclePolicy {
+    pub const fn disabled() -> Self {
+        Self {
+            max_executions: None,
+            max_uptime: None,
+        }
+    }
+}
+
+/// Limits the rate at which a sandbox process may log via the replica. The
+/// messages beyond the limit are dropped and only their number is logged.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SandboxLogRateLimit {
+    /// The number of messages per second a sandbox process may log on average.
+    pub messages_per_second: u32,
+    /// The number of messages a sandbox process may log in a burst.
+    pub burst: u32,
+}
+
+impl Default for SandboxLogRateLimit {
+    fn default() -> Self {
+        DEFAULT_SANDBOX_LOG_RATE_LIMIT
+    }
+}
+
+/// Limits the number of requests referring to non-existent executions that a
+/// sandbox process may send. Such requests indicate a buggy or compromised
+/// sandbox process, which is terminated once it exceeds the limit.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SandboxInvalidExecIdLimit {
+    /// The number of invalid requests tolerated within `window`.
+    pub max_invalid_exec_ids: u32,
+    pub window: Duration,
+}
+
+/// Pins the sandbox processes of the given canisters to the given CPUs, e.g. to
+/// keep latency-critical system canisters apart from compute-heavy canisters.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SandboxCpuAffinity {
+    pub canisters: Vec<CanisterId>,
+    /// The indices of the CPUs on which the sandbox processes may run.
+    pub cpus: Vec<usize>,
+}
+
+/// A predefined seccomp profile that restricts the system calls of sandbox
+/// processes. Each sandbox process installs the filter of the profile when it
+/// starts, before it serves any request.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SandboxSyscallFilter {
+    /// System calls are not filtered.
+    #[default]
+    Disabled,
+    /// Only the system calls needed for executing canisters are allowed. Any
+    /// other system call kills the sandbox process.
+    Strict,
+    /// System calls that a sandbox process never needs, e.g. for running
+    /// programs, opening network sockets or tracing other processes, fail with
+    /// `EPERM`. All other system calls are allowed.
+    Compat,
+    /// Like `Strict`, but system calls outside of the profile are only logged
+    /// by the kernel. This is meant for tuning the `Strict` profile.
+    Debug,
+}
+
+impl SandboxSyscallFilter {
+    /// Returns the name of the profile for use e.g. as a metric label.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Disabled => "disabled",
+            Self::Strict => "strict",
+            Self::Compat => "compat",
+            Self::Debug => "debug",
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Config {
     /// The number of threads to use for query execution per canister.
@@ -204,6 +338,79 @@ pub struct Config {
     /// duration and sandbox process eviction is activated.
     pub max_sandbox_idle_time: Duration,
 
+    /// The number of idle sandbox processes that are spawned ahead of time,
+    /// so that a canister without a sandbox process does not have to wait for
+    /// a new process to start. The pool is disabled if this is zero. The idle
+    /// processes count against `max_sandbox_count`.
+    pub sandbox_process_pool_size: usize,
+
+    /// Idle sandbox processes are replaced by new ones after they have been in
+    /// the sandbox process pool for this duration.
+    pub max_pooled_sandbox_idle_time: Duration,
+
+    /// Resource limits under which each sandbox process runs.
+    pub sandbox_resource_limits: SandboxResourceLimits,
+
+    /// If this flag is enabled, then the replica recovers from sandbox process
+    /// crashes during non-replicated executions: such an execution is started
+    /// over once in a new sandbox process if it had not produced any slice yet,
+    /// and fails with `SandboxCrashed` otherwise. A crash during a replicated
+    /// execution is local to this node and always panics the replica, as does
+    /// any crash if this flag is disabled. This includes sandbox processes
+    /// killed for exceeding `sandbox_resource_limits`,
+    /// `sandbox_execution_timeout`, `sandbox_heartbeat_timeout` or
+    /// `sandbox_invalid_exec_id_limit`.
+    pub retry_on_sandbox_crash: FlagStatus,
+
+    /// If set, the sandbox process of an execution that has been running for
+    /// longer than this wall-clock time is killed, and the execution fails.
+    /// Wall-clock time differs between replicas, so this is only a safeguard
+    /// against wedged sandbox processes and must be far above the time any
+    /// legitimate execution takes: killing a sandbox process during a
+    /// replicated execution panics the replica.
+    pub sandbox_execution_timeout: Option<Duration>,
+
+    /// If set, sandbox processes send heartbeats from their execution threads
+    /// when those start or resume an execution slice, and a sandbox process
+    /// that has not sent a heartbeat for this duration while it has active
+    /// executions is killed, and its executions fail. This turns silent hangs
+    /// of sandbox processes into failures. The duration must exceed the
+    /// longest time a legitimate execution slice takes: killing a sandbox
+    /// process during a replicated execution panics the replica.
+    pub sandbox_heartbeat_timeout: Option<Duration>,
+
+    /// If set, the resident memory, CPU time and number of open file
+    /// descriptors of each sandbox process are sampled at this interval and
+    /// exported as histograms over all sandbox processes.
+    pub sandbox_process_stats_interval: Option<Duration>,
+
+    /// The seccomp profile that restricts the system calls of sandbox
+    /// processes.
+    pub sandbox_syscall_filter: SandboxSyscallFilter,
+
+    /// The CPUs to which the sandbox processes of specific canisters are
+    /// pinned. The sandbox processes of other canisters may run on any CPU.
+    pub sandbox_cpu_affinity: Vec<SandboxCpuAffinity>,
+
+    /// If set, at most this many executions run concurrently on a single
+    /// sandbox process, which bounds the memory usage of the process. Further
+    /// executions wait in the replica until one of the running executions
+    /// completes its slice.
+    pub max_concurrent_executions_per_sandbox: Option<usize>,
+
+    /// If set, at most this many replicated executions run concurrently on a
+    /// single sandbox process.
+    pub max_concurrent_replicated_executions_per_sandbox: Option<usize>,
+
+    /// If set, at most this many non-replicated executions, e.g. queries, run
+    /// concurrently on a single sandbox process, so that they cannot take all
+    /// slots of `max_concurrent_executions_per_sandbox` and hold up replicated
+    /// executions.
+    pub max_concurrent_non_replicated_executions_per_sandbox: Option<usize>,
+
+    /// When sandbox processes are retired and replaced by new ones.
+    pub sandbox_recycle_policy: SandboxRecyclePolicy,
+
     /// The type of the local subnet. The default value here should be replaced
     /// with the correct value at runtime when the hypervisor is created.
     pub subnet_type: SubnetType,
@@ -218,6 +425,20 @@ pub struct Config {
     /// entry with the number of executed instructions and the duration.
     pub trace_execution: FlagStatus,
 
+    /// If this flag is enabled, then each request that a sandbox process
+    /// sends to the replica produces a rate-limited audit log entry without
+    /// the payload of the request.
+    pub audit_sandbox_requests: FlagStatus,
+
+    /// Limits the rate at which each sandbox process may log via the replica.
+    pub sandbox_log_rate_limit: SandboxLogRateLimit,
+
+    /// If set, limits the number of requests referring to non-existent
+    /// executions that each sandbox process may send before it is killed, and
+    /// its executions fail. Killing a sandbox process during a replicated
+    /// execution panics the replica, see `retry_on_sandbox_crash`.
+    pub sandbox_invalid_exec_id_limit: Option<SandboxInvalidExecIdLimit>,
+
     /// The maximum number of pages that a message dirties without optimizing dirty
     /// page copying by triggering a new execution slice for copying and using prefaulting.
     pub max_dirty_pages_without_optimization: usize,
@@ -256,9 +477,25 @@ impl Config {
             min_sandbox_count: DEFAULT_MIN_SANDBOX_COUNT,
             max_sandbox_count: DEFAULT_MAX_SANDBOX_COUNT,
             max_sandbox_idle_time: DEFAULT_MAX_SANDBOX_IDLE_TIME,
+            sandbox_process_pool_size: 0,
+            max_pooled_sandbox_idle_time: DEFAULT_MAX_POOLED_SANDBOX_IDLE_TIME,
+            sandbox_resource_limits: SandboxResourceLimits::unlimited(),
+            retry_on_sandbox_crash: FlagStatus::Disabled,
+            sandbox_execution_timeout: None,
+            sandbox_heartbeat_timeout: None,
+            sandbox_process_stats_interval: None,
+            sandbox_syscall_filter: SandboxSyscallFilter::Disabled,
+            sandbox_cpu_affinity: Vec::new(),
+            max_concurrent_executions_per_sandbox: None,
+            max_concurrent_replicated_executions_per_sandbox: None,
+            max_concurrent_non_replicated_executions_per_sandbox: None,
+            sandbox_recycle_policy: SandboxRecyclePolicy::disabled(),
             subnet_type: SubnetType::Application,
             dirty_page_overhead: NumInstructions::new(0),
             trace_execution: FlagStatus::Disabled,
+            audit_sandbox_requests: FlagStatus::Disabled,
+            sandbox_log_rate_limit: DEFAULT_SANDBOX_LOG_RATE_LIMIT,
+            sandbox_invalid_exec_id_limit: None,
             max_dirty_pages_without_optimization: DEFAULT_MAX_DIRTY_PAGES_WITHOUT_OPTIMIZATION,
             dirty_page_copy_overhead: DIRTY_PAGE_COPY_OVERHEAD,
             wasm_max_size: WASM_MAX_SIZE,
@@ -14,6 +14,12 @@ pub struct TransportConfig {
     /// Transport creates 'max_streams' logical streams/channels between two peers.
     /// Channel ids should be within [0..max_streams).
     pub max_streams: usize,
+
+    /// Tunables of the consensus manager.
+    pub consensus_manager: ConsensusManagerTunables,
+
+    /// Tunables of the state sync manager.
+    pub state_sync: StateSyncTunables,
 }
 
 impl Default for TransportConfig {
@@ -23,6 +29,64 @@ impl Default for TransportConfig {
             node_ip: String::default(),
             listening_port: u16::default(),
             max_streams: 1,
+            consensus_manager: ConsensusManagerTunables::default(),
+            state_sync: StateSyncTunables::default(),
         }
     }
 }
+
+/// Tunables of the consensus manager as specified in the ic.json. The consensus manager
+/// keeps its own default for every field that is not set.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConsensusManagerTunables {
+    /// Capacity of the channel between the handler of slot updates and the receiver.
+    pub adverts_from_peers_channel_capacity: Option<usize>,
+
+    /// Maximum number of artifact downloads in flight at the same time. Unlimited if zero.
+    pub max_concurrent_downloads: Option<usize>,
+
+    /// Maximum number of locally produced adverts the sender takes at once.
+    pub advert_batch_size: Option<usize>,
+
+    /// Initial waiting time between attempts to push a slot update to a peer.
+    pub push_retry_initial_interval_ms: Option<u64>,
+
+    /// Maximum waiting time between attempts to push a slot update to a peer.
+    pub push_retry_max_interval_ms: Option<u64>,
+
+    /// Timeout of the first attempt to download an artifact.
+    pub download_retry_initial_interval_ms: Option<u64>,
+
+    /// Maximum timeout of an attempt to download an artifact.
+    pub download_retry_max_interval_ms: Option<u64>,
+
+    /// Number of failed download attempts after which the artifact is also requested from
+    /// peers that committed the same slot. Disabled if zero.
+    pub download_fallback_after_failures: Option<usize>,
+
+    /// Bytes per second pushed to a single peer. Pushes are only rate limited if this and
+    /// `send_rate_limit_aggregate_bytes_per_second` are set.
+    pub send_rate_limit_per_peer_bytes_per_second: Option<u64>,
+
+    /// Bytes per second pushed to all peers together.
+    pub send_rate_limit_aggregate_bytes_per_second: Option<u64>,
+
+    /// Traffic at the full rate that can be pushed at once after being idle.
+    pub send_rate_limit_burst_ms: Option<u64>,
+}
+
+/// Tunables of the state sync manager as specified in the ic.json. The state sync manager
+/// keeps its own default for every field that is not set.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StateSyncTunables {
+    /// Number of chunks that are downloaded in parallel from a single peer.
+    pub parallel_chunk_downloads_per_peer: Option<usize>,
+
+    /// Upper bound on the number of chunks that are downloaded in parallel from all peers.
+    pub max_parallel_chunk_downloads: Option<usize>,
+
+    /// Bandwidth in bytes per second that chunk downloads may use.
+    pub max_download_bandwidth: Option<u64>,
+}
@@ -40,7 +40,7 @@ fn execute_instance(
                             .get_priority(&msg)
                         {
                             Priority::Drop => return Some(timestamp),
-                            Priority::Stash => {
+                            Priority::Stash | Priority::FetchAfter(_) => {
                                 instance
                                     .buffered
                                     .borrow_mut()
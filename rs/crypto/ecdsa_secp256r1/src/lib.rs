@@ -15,6 +15,9 @@ use p256::{
 use rand::{CryptoRng, RngCore};
 use zeroize::ZeroizeOnDrop;
 
+#[cfg(feature = "proptest")]
+pub mod proptest_support;
+
 /// An error indicating that decoding a key failed
 #[derive(Clone, Debug)]
 pub enum KeyDecodingError {
@@ -0,0 +1,177 @@
+//! Proptest strategies and round-trip properties for secp256r1 keys and signatures
+//!
+//! The properties return a [`TestCaseResult`] so that crates wrapping this
+//! crate can run the same property suites inside their own `proptest!` blocks,
+//! e.g. against values that went through their wrappers.
+
+use crate::{PrivateKey, PublicKey};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::test_runner::TestCaseResult;
+use rand::SeedableRng;
+
+/// A private key generated by [`arb_private_key`]
+///
+/// The `Debug` output shows the public key instead of the secret key.
+#[derive(Clone)]
+pub struct ArbitraryPrivateKey(pub PrivateKey);
+
+impl std::fmt::Debug for ArbitraryPrivateKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PrivateKey {{ public_key: {:?} }}", self.0.public_key())
+    }
+}
+
+/// Strategy for private keys, derived deterministically from a random seed
+pub fn arb_private_key() -> impl Strategy<Value = ArbitraryPrivateKey> {
+    any::<[u8; 32]>().prop_map(|seed| {
+        let mut rng = rand_chacha::ChaCha20Rng::from_seed(seed);
+        ArbitraryPrivateKey(PrivateKey::generate_using_rng(&mut rng))
+    })
+}
+
+/// Strategy for public keys
+pub fn arb_public_key() -> impl Strategy<Value = PublicKey> {
+    arb_private_key().prop_map(|sk| sk.0.public_key())
+}
+
+/// Strategy for messages of up to 1 KiB, including the empty message
+pub fn arb_message() -> impl Strategy<Value = Vec<u8>> {
+    vec(any::<u8>(), 0..1024)
+}
+
+/// Strategy for message digests of the size of a SHA-256 digest
+pub fn arb_digest() -> impl Strategy<Value = [u8; 32]> {
+    any::<[u8; 32]>()
+}
+
+/// Strategy for byte strings obtained by truncating `encoding` or by appending
+/// bytes to it
+///
+/// For fixed-size encodings such as SEC1 keys and signatures, none of the
+/// returned byte strings is a valid encoding.
+pub fn arb_truncated_or_extended(encoding: Vec<u8>) -> impl Strategy<Value = Vec<u8>> {
+    let len = encoding.len();
+    let truncated = {
+        let encoding = encoding.clone();
+        (0..len).prop_map(move |new_len| encoding[..new_len].to_vec())
+    };
+    let extended = vec(any::<u8>(), 1..32).prop_map(move |suffix| {
+        let mut extended = encoding.clone();
+        extended.extend(suffix);
+        extended
+    });
+    prop_oneof![truncated, extended]
+}
+
+/// Strategy for byte strings obtained by flipping a single bit of `encoding`
+pub fn arb_bit_flipped(encoding: Vec<u8>) -> impl Strategy<Value = Vec<u8>> {
+    let bits = encoding.len() * 8;
+    (0..bits).prop_map(move |bit| {
+        let mut flipped = encoding.clone();
+        flipped[bit / 8] ^= 1 << (bit % 8);
+        flipped
+    })
+}
+
+/// Strategy for malformed SEC1 public key encodings, both compressed and uncompressed
+pub fn arb_malformed_sec1_public_key() -> impl Strategy<Value = Vec<u8>> {
+    (arb_public_key(), any::<bool>())
+        .prop_flat_map(|(pk, compressed)| arb_truncated_or_extended(pk.serialize_sec1(compressed)))
+}
+
+/// Strategy for malformed SEC1 private key encodings
+pub fn arb_malformed_sec1_private_key() -> impl Strategy<Value = Vec<u8>> {
+    arb_private_key().prop_flat_map(|sk| arb_truncated_or_extended(sk.0.serialize_sec1()))
+}
+
+/// Strategy for a public key, a message, and a signature on the message by the
+/// corresponding private key that was modified in a single bit
+pub fn arb_message_with_corrupted_signature() -> impl Strategy<Value = (PublicKey, Vec<u8>, Vec<u8>)>
+{
+    (arb_private_key(), arb_message()).prop_flat_map(|(sk, message)| {
+        let signature = sk.0.sign_message(&message).to_vec();
+        (
+            Just(sk.0.public_key()),
+            Just(message),
+            arb_bit_flipped(signature),
+        )
+    })
+}
+
+/// Checks that the private key survives a round trip through all supported encodings
+pub fn prop_private_key_round_trips(sk: &PrivateKey) -> TestCaseResult {
+    let sec1 = sk.serialize_sec1();
+    let decoded = [
+        PrivateKey::deserialize_sec1(&sec1),
+        PrivateKey::deserialize_rfc5915_der(&sk.serialize_rfc5915_der()),
+        PrivateKey::deserialize_rfc5915_pem(&sk.serialize_rfc5915_pem()),
+        PrivateKey::deserialize_pkcs8_der(&sk.serialize_pkcs8_der()),
+        PrivateKey::deserialize_pkcs8_pem(&sk.serialize_pkcs8_pem()),
+    ];
+    for result in decoded {
+        match result {
+            Ok(decoded) => prop_assert_eq!(decoded.serialize_sec1(), sec1.clone()),
+            Err(e) => return Err(TestCaseError::fail(format!("decoding failed: {:?}", e))),
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the public key survives a round trip through all supported encodings
+pub fn prop_public_key_round_trips(pk: &PublicKey) -> TestCaseResult {
+    let decoded = [
+        PublicKey::deserialize_sec1(&pk.serialize_sec1(true)),
+        PublicKey::deserialize_sec1(&pk.serialize_sec1(false)),
+        PublicKey::deserialize_der(&pk.serialize_der()),
+        PublicKey::deserialize_pem(&pk.serialize_pem()),
+    ];
+    for result in decoded {
+        match result {
+            Ok(decoded) => prop_assert_eq!(&decoded, pk),
+            Err(e) => return Err(TestCaseError::fail(format!("decoding failed: {:?}", e))),
+        }
+    }
+    Ok(())
+}
+
+/// Checks that a signature on the message and a signature on the digest both
+/// verify with the public key of the signer
+pub fn prop_signature_round_trips(
+    sk: &PrivateKey,
+    message: &[u8],
+    digest: &[u8],
+) -> TestCaseResult {
+    let pk = sk.public_key();
+
+    let signature = sk.sign_message(message);
+    prop_assert!(pk.verify_signature(message, &signature));
+
+    let digest_signature = sk
+        .sign_digest(digest)
+        .ok_or_else(|| TestCaseError::reject("digest is too short to sign"))?;
+    prop_assert!(pk.verify_signature_prehashed(digest, &digest_signature));
+    Ok(())
+}
+
+/// Checks that the corrupted signature does not verify
+pub fn prop_rejects_corrupted_signature(
+    pk: &PublicKey,
+    message: &[u8],
+    corrupted_signature: &[u8],
+) -> TestCaseResult {
+    prop_assert!(!pk.verify_signature(message, corrupted_signature));
+    Ok(())
+}
+
+/// Checks that the malformed SEC1 public key encoding is rejected
+pub fn prop_rejects_malformed_sec1_public_key(encoding: &[u8]) -> TestCaseResult {
+    prop_assert!(PublicKey::deserialize_sec1(encoding).is_err());
+    Ok(())
+}
+
+/// Checks that the malformed SEC1 private key encoding is rejected
+pub fn prop_rejects_malformed_sec1_private_key(encoding: &[u8]) -> TestCaseResult {
+    prop_assert!(PrivateKey::deserialize_sec1(encoding).is_err());
+    Ok(())
+}
@@ -0,0 +1,36 @@
+#![cfg(feature = "proptest")]
+
+use ic_crypto_ecdsa_secp256r1::proptest_support::*;
+use proptest::prelude::*;
+
+proptest! {
+    #[test]
+    fn private_key_round_trips(sk in arb_private_key()) {
+        prop_private_key_round_trips(&sk.0)?;
+    }
+
+    #[test]
+    fn public_key_round_trips(pk in arb_public_key()) {
+        prop_public_key_round_trips(&pk)?;
+    }
+
+    #[test]
+    fn signature_round_trips(sk in arb_private_key(), message in arb_message(), digest in arb_digest()) {
+        prop_signature_round_trips(&sk.0, &message, &digest)?;
+    }
+
+    #[test]
+    fn corrupted_signature_is_rejected((pk, message, signature) in arb_message_with_corrupted_signature()) {
+        prop_rejects_corrupted_signature(&pk, &message, &signature)?;
+    }
+
+    #[test]
+    fn malformed_sec1_public_key_is_rejected(encoding in arb_malformed_sec1_public_key()) {
+        prop_rejects_malformed_sec1_public_key(&encoding)?;
+    }
+
+    #[test]
+    fn malformed_sec1_private_key_is_rejected(encoding in arb_malformed_sec1_private_key()) {
+        prop_rejects_malformed_sec1_private_key(&encoding)?;
+    }
+}
@@ -3,6 +3,7 @@
 //!
 //! In particular, the crate provides functionality to
 //! * generate TLS key material and wrap the public part in an X.509 certificate
+//! * configure mutual TLS between nodes that pin each other's certificates
 #![forbid(unsafe_code)]
 #![deny(clippy::unwrap_used)]
 #![warn(rust_2018_idioms)]
@@ -22,6 +23,8 @@ use std::fmt;
 use time::OffsetDateTime;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
+pub mod mutual_tls;
+
 /// A DER-encoded X.509 v3 certificate with an Ed25519 public key.
 #[derive(Debug)]
 pub struct TlsEd25519CertificateDerBytes {
@@ -0,0 +1,314 @@
+//! Mutual TLS between nodes that pin each other's certificates.
+//!
+//! A node is identified by the node ID in the subject CN of its certificate, as generated by
+//! [`generate_tls_key_pair_der`](crate::generate_tls_key_pair_der). A peer is authenticated
+//! if it presents exactly the certificate that was pinned for its node ID. Pinning only the
+//! node ID would not be enough, since anyone can create a certificate with any subject CN.
+//!
+//! The rustls configurations use the same settings as the crypto component: TLS 1.3,
+//! Ed25519, and the cipher suites TLS_AES_256_GCM_SHA384 and TLS_AES_128_GCM_SHA256.
+use crate::{TlsEd25519CertificateDerBytes, TlsEd25519SecretKeyDerBytes};
+use ic_crypto_utils_tls::node_id_from_certificate_der;
+use ic_types::NodeId;
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{
+        ring::cipher_suite::{TLS13_AES_128_GCM_SHA256, TLS13_AES_256_GCM_SHA384},
+        CryptoProvider,
+    },
+    pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime},
+    server::danger::{ClientCertVerified, ClientCertVerifier},
+    version::TLS13,
+    CertificateError, ClientConfig, DigitallySignedStruct, DistinguishedName, ServerConfig,
+    SignatureScheme,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MutualTlsError {
+    /// A certificate can't be parsed or its subject CN is not a node ID.
+    MalformedCertificate(String),
+    /// The secret key is not a DER-encoded Ed25519 key in PKCS#8 format,
+    /// or it does not match the certificate.
+    MalformedSecretKey(String),
+    /// The node ID of a pinned certificate is not the expected one.
+    UnexpectedNodeId { expected: NodeId, actual: NodeId },
+    /// The peer did not present a certificate, e.g. because the handshake is not complete.
+    MissingPeerCertificate,
+}
+
+impl fmt::Display for MutualTlsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MalformedCertificate(e) => write!(f, "Malformed certificate: {e}"),
+            Self::MalformedSecretKey(e) => write!(f, "Malformed secret key: {e}"),
+            Self::UnexpectedNodeId { expected, actual } => {
+                write!(f, "Expected certificate of node {expected}, got {actual}")
+            }
+            Self::MissingPeerCertificate => write!(f, "The peer did not present a certificate"),
+        }
+    }
+}
+
+impl std::error::Error for MutualTlsError {}
+
+/// The TLS key material of the local node.
+pub struct NodeTlsIdentity {
+    node_id: NodeId,
+    certificate: CertificateDer<'static>,
+    secret_key: PrivatePkcs8KeyDer<'static>,
+}
+
+impl NodeTlsIdentity {
+    /// Creates the identity from the output of
+    /// [`generate_tls_key_pair_der`](crate::generate_tls_key_pair_der).
+    pub fn new(
+        certificate: &TlsEd25519CertificateDerBytes,
+        secret_key: &TlsEd25519SecretKeyDerBytes,
+    ) -> Result<Self, MutualTlsError> {
+        let node_id = node_id_from_der(&certificate.bytes)?;
+        let secret_key = PrivatePkcs8KeyDer::from(secret_key.bytes.expose_secret().to_vec());
+        rustls::crypto::ring::sign::any_eddsa_type(&secret_key)
+            .map_err(|e| MutualTlsError::MalformedSecretKey(e.to_string()))?;
+        Ok(Self {
+            node_id,
+            certificate: CertificateDer::from(certificate.bytes.clone()),
+            secret_key,
+        })
+    }
+
+    /// The node ID from the subject CN of the node's certificate.
+    pub fn node_id(&self) -> NodeId {
+        self.node_id
+    }
+
+    /// Returns a server config that requires clients to authenticate with one of the
+    /// certificates pinned by `verifier`.
+    pub fn server_config(
+        &self,
+        verifier: Arc<PinnedNodeCertVerifier>,
+    ) -> Result<ServerConfig, MutualTlsError> {
+        ServerConfig::builder_with_provider(crypto_provider())
+            .with_protocol_versions(&[&TLS13])
+            .expect("Valid rustls server config.")
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(vec![self.certificate.clone()], self.secret_key())
+            .map_err(|e| MutualTlsError::MalformedSecretKey(e.to_string()))
+    }
+
+    /// Returns a server config that does not authenticate clients.
+    pub fn server_config_without_client_auth(&self) -> Result<ServerConfig, MutualTlsError> {
+        ServerConfig::builder_with_provider(crypto_provider())
+            .with_protocol_versions(&[&TLS13])
+            .expect("Valid rustls server config.")
+            .with_no_client_auth()
+            .with_single_cert(vec![self.certificate.clone()], self.secret_key())
+            .map_err(|e| MutualTlsError::MalformedSecretKey(e.to_string()))
+    }
+
+    /// Returns a client config that requires the server to authenticate with one of the
+    /// certificates pinned by `verifier`.
+    pub fn client_config(
+        &self,
+        verifier: Arc<PinnedNodeCertVerifier>,
+    ) -> Result<ClientConfig, MutualTlsError> {
+        ClientConfig::builder_with_provider(crypto_provider())
+            .with_protocol_versions(&[&TLS13])
+            .expect("Valid rustls client config.")
+            .dangerous()
+            .with_custom_certificate_verifier(verifier)
+            .with_client_auth_cert(vec![self.certificate.clone()], self.secret_key())
+            .map_err(|e| MutualTlsError::MalformedSecretKey(e.to_string()))
+    }
+
+    fn secret_key(&self) -> PrivateKeyDer<'static> {
+        PrivateKeyDer::Pkcs8(self.secret_key.clone_key())
+    }
+}
+
+/// Returns the server and client configs for a mutual TLS connection between the local
+/// node and `expected_peer`, which must authenticate with `peer_certificate`.
+///
+/// The node ID of the peer is returned by [`authenticated_peer_node_id`] once the
+/// handshake is complete.
+pub fn mutual_tls_configs(
+    identity: &NodeTlsIdentity,
+    expected_peer: NodeId,
+    peer_certificate: &TlsEd25519CertificateDerBytes,
+) -> Result<(ServerConfig, ClientConfig), MutualTlsError> {
+    let peer_node_id = node_id_from_der(&peer_certificate.bytes)?;
+    if peer_node_id != expected_peer {
+        return Err(MutualTlsError::UnexpectedNodeId {
+            expected: expected_peer,
+            actual: peer_node_id,
+        });
+    }
+    let verifier = Arc::new(PinnedNodeCertVerifier::new(BTreeMap::from([(
+        expected_peer,
+        CertificateDer::from(peer_certificate.bytes.clone()),
+    )])));
+    Ok((
+        identity.server_config(verifier.clone())?,
+        identity.client_config(verifier)?,
+    ))
+}
+
+/// Returns the node ID of the peer from the certificates it presented during a complete
+/// handshake, e.g. from `rustls::CommonState::peer_certificates`.
+///
+/// If the connection was configured with a [`PinnedNodeCertVerifier`], the peer
+/// presented the certificate pinned for the returned node ID.
+pub fn authenticated_peer_node_id(
+    peer_certificates: Option<&[CertificateDer<'_>]>,
+) -> Result<NodeId, MutualTlsError> {
+    match peer_certificates {
+        Some([end_entity]) => node_id_from_der(end_entity.as_ref()),
+        _ => Err(MutualTlsError::MissingPeerCertificate),
+    }
+}
+
+/// Verifies server and client certificates. A certificate is trusted if:
+/// * There are no intermediate certificates.
+/// * The node ID can be parsed from the subject CN.
+/// * It is equal to the certificate pinned for that node ID.
+#[derive(Debug)]
+pub struct PinnedNodeCertVerifier {
+    pinned_certificates: BTreeMap<NodeId, CertificateDer<'static>>,
+}
+
+impl PinnedNodeCertVerifier {
+    /// Creates a verifier that trusts only the given certificates of the given nodes.
+    pub fn new(pinned_certificates: BTreeMap<NodeId, CertificateDer<'static>>) -> Self {
+        Self {
+            pinned_certificates,
+        }
+    }
+
+    fn verify_node_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+    ) -> Result<(), rustls::Error> {
+        if !intermediates.is_empty() {
+            return Err(rustls::Error::General(format!(
+                "Expected no intermediate certificates, got {}",
+                intermediates.len()
+            )));
+        }
+        let node_id = node_id_from_certificate_der(end_entity.as_ref())
+            .map_err(|_| rustls::Error::InvalidCertificate(CertificateError::BadEncoding))?;
+        match self.pinned_certificates.get(&node_id) {
+            Some(pinned) if pinned == end_entity => Ok(()),
+            Some(_) => Err(rustls::Error::InvalidCertificate(
+                CertificateError::UnknownIssuer,
+            )),
+            None => Err(rustls::Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            )),
+        }
+    }
+}
+
+impl ServerCertVerifier for PinnedNodeCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        self.verify_node_cert(end_entity, intermediates)
+            .map(|()| ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Err(rustls::Error::PeerIncompatible(
+            rustls::PeerIncompatible::Tls12NotOffered,
+        ))
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        vec![SignatureScheme::ED25519]
+    }
+}
+
+impl ClientCertVerifier for PinnedNodeCertVerifier {
+    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+        &[]
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        _now: UnixTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        self.verify_node_cert(end_entity, intermediates)
+            .map(|()| ClientCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Err(rustls::Error::PeerIncompatible(
+            rustls::PeerIncompatible::Tls12NotOffered,
+        ))
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        vec![SignatureScheme::ED25519]
+    }
+}
+
+fn verify_tls13_signature(
+    message: &[u8],
+    cert: &CertificateDer<'_>,
+    dss: &DigitallySignedStruct,
+) -> Result<HandshakeSignatureValid, rustls::Error> {
+    rustls::crypto::verify_tls13_signature(
+        message,
+        cert,
+        dss,
+        &rustls::crypto::ring::default_provider().signature_verification_algorithms,
+    )
+}
+
+fn node_id_from_der(certificate_der: &[u8]) -> Result<NodeId, MutualTlsError> {
+    node_id_from_certificate_der(certificate_der)
+        .map_err(|e| MutualTlsError::MalformedCertificate(e.to_string()))
+}
+
+fn crypto_provider() -> Arc<CryptoProvider> {
+    let mut ring_crypto_provider = rustls::crypto::ring::default_provider();
+    ring_crypto_provider.cipher_suites = vec![TLS13_AES_256_GCM_SHA384, TLS13_AES_128_GCM_SHA256];
+    Arc::new(ring_crypto_provider)
+}
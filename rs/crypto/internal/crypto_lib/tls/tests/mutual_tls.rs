@@ -0,0 +1,170 @@
+#![allow(clippy::unwrap_used)]
+
+use std::sync::Arc;
+
+use assert_matches::assert_matches;
+use ic_crypto_internal_tls::mutual_tls::{
+    authenticated_peer_node_id, mutual_tls_configs, MutualTlsError, NodeTlsIdentity,
+};
+use ic_crypto_internal_tls::{
+    generate_tls_key_pair_der, TlsEd25519CertificateDerBytes, TlsEd25519SecretKeyDerBytes,
+};
+use ic_crypto_test_utils_reproducible_rng::reproducible_rng;
+use ic_types::time::GENESIS;
+use ic_types::{NodeId, PrincipalId};
+use rustls::pki_types::ServerName;
+use rustls::{ClientConnection, Connection, ServerConnection};
+
+#[test]
+fn should_authenticate_both_peers() {
+    let (client_cert, client_key) = key_pair(node_id(1));
+    let (server_cert, server_key) = key_pair(node_id(2));
+    let client = NodeTlsIdentity::new(&client_cert, &client_key).unwrap();
+    let server = NodeTlsIdentity::new(&server_cert, &server_key).unwrap();
+    let (_, client_config) = mutual_tls_configs(&client, node_id(2), &server_cert).unwrap();
+    let (server_config, _) = mutual_tls_configs(&server, node_id(1), &client_cert).unwrap();
+
+    let (client_conn, server_conn) = handshake(client_config, server_config).unwrap();
+
+    assert_eq!(
+        authenticated_peer_node_id(client_conn.peer_certificates()),
+        Ok(node_id(2))
+    );
+    assert_eq!(
+        authenticated_peer_node_id(server_conn.peer_certificates()),
+        Ok(node_id(1))
+    );
+}
+
+#[test]
+fn should_reject_client_with_certificate_that_is_not_pinned() {
+    let (client_cert, client_key) = key_pair(node_id(1));
+    let (other_client_cert, _) = key_pair(node_id(1));
+    let (server_cert, server_key) = key_pair(node_id(2));
+    let client = NodeTlsIdentity::new(&client_cert, &client_key).unwrap();
+    let server = NodeTlsIdentity::new(&server_cert, &server_key).unwrap();
+    let (_, client_config) = mutual_tls_configs(&client, node_id(2), &server_cert).unwrap();
+    let (server_config, _) = mutual_tls_configs(&server, node_id(1), &other_client_cert).unwrap();
+
+    assert_matches!(
+        handshake(client_config, server_config),
+        Err(rustls::Error::InvalidCertificate(_))
+    );
+}
+
+#[test]
+fn should_reject_server_with_certificate_that_is_not_pinned() {
+    let (client_cert, client_key) = key_pair(node_id(1));
+    let (server_cert, server_key) = key_pair(node_id(2));
+    let (other_server_cert, _) = key_pair(node_id(2));
+    let client = NodeTlsIdentity::new(&client_cert, &client_key).unwrap();
+    let server = NodeTlsIdentity::new(&server_cert, &server_key).unwrap();
+    let (_, client_config) = mutual_tls_configs(&client, node_id(2), &other_server_cert).unwrap();
+    let (server_config, _) = mutual_tls_configs(&server, node_id(1), &client_cert).unwrap();
+
+    assert_matches!(
+        handshake(client_config, server_config),
+        Err(rustls::Error::InvalidCertificate(_))
+    );
+}
+
+#[test]
+fn should_fail_if_peer_certificate_is_not_of_expected_node() {
+    let (cert, key) = key_pair(node_id(1));
+    let (peer_cert, _) = key_pair(node_id(3));
+    let identity = NodeTlsIdentity::new(&cert, &key).unwrap();
+
+    assert_matches!(
+        mutual_tls_configs(&identity, node_id(2), &peer_cert),
+        Err(MutualTlsError::UnexpectedNodeId { expected, actual })
+        if expected == node_id(2) && actual == node_id(3)
+    );
+}
+
+#[test]
+fn should_fail_if_common_name_is_not_a_node_id() {
+    let (cert, key) = generate_tls_key_pair_der(
+        &mut reproducible_rng(),
+        "common name",
+        not_before(),
+        not_after(),
+    )
+    .unwrap();
+
+    assert_matches!(
+        NodeTlsIdentity::new(&cert, &key),
+        Err(MutualTlsError::MalformedCertificate(_))
+    );
+}
+
+#[test]
+fn should_fail_if_secret_key_is_malformed() {
+    let (cert, _key) = key_pair(node_id(1));
+
+    assert_matches!(
+        NodeTlsIdentity::new(&cert, &TlsEd25519SecretKeyDerBytes::new(vec![1, 2, 3])),
+        Err(MutualTlsError::MalformedSecretKey(_))
+    );
+}
+
+#[test]
+fn should_not_return_peer_node_id_without_peer_certificate() {
+    assert_eq!(
+        authenticated_peer_node_id(None),
+        Err(MutualTlsError::MissingPeerCertificate)
+    );
+}
+
+/// Runs the handshake between a client and a server connection in memory.
+fn handshake(
+    client_config: rustls::ClientConfig,
+    server_config: rustls::ServerConfig,
+) -> Result<(Connection, Connection), rustls::Error> {
+    let mut client = Connection::from(ClientConnection::new(
+        Arc::new(client_config),
+        ServerName::try_from("node").unwrap(),
+    )?);
+    let mut server = Connection::from(ServerConnection::new(Arc::new(server_config))?);
+    while client.is_handshaking() || server.is_handshaking() {
+        let sent_by_client = transfer(&mut client, &mut server)?;
+        let sent_by_server = transfer(&mut server, &mut client)?;
+        assert!(sent_by_client || sent_by_server, "handshake is stuck");
+    }
+    Ok((client, server))
+}
+
+/// Delivers all pending TLS messages from `from` to `to`. Returns whether there were any.
+fn transfer(from: &mut Connection, to: &mut Connection) -> Result<bool, rustls::Error> {
+    let mut buf = Vec::new();
+    while from.wants_write() {
+        from.write_tls(&mut buf).unwrap();
+    }
+    let mut reader = buf.as_slice();
+    while !reader.is_empty() {
+        to.read_tls(&mut reader).unwrap();
+        to.process_new_packets()?;
+    }
+    Ok(!buf.is_empty())
+}
+
+fn key_pair(node_id: NodeId) -> (TlsEd25519CertificateDerBytes, TlsEd25519SecretKeyDerBytes) {
+    generate_tls_key_pair_der(
+        &mut reproducible_rng(),
+        node_id.get().to_string().as_str(),
+        not_before(),
+        not_after(),
+    )
+    .unwrap()
+}
+
+fn node_id(n: u64) -> NodeId {
+    NodeId::from(PrincipalId::new_node_test_id(n))
+}
+
+fn not_before() -> u64 {
+    GENESIS.as_secs_since_unix_epoch()
+}
+
+fn not_after() -> u64 {
+    (GENESIS + std::time::Duration::from_secs(1000)).as_secs_since_unix_epoch()
+}
@@ -1395,7 +1395,11 @@ pub fn wasmtime_validation_config(embedders_config: &EmbeddersConfig) -> wasmtim
     config.generate_address_map(false);
     // The signal handler uses Posix signals, not Mach ports on MacOS.
     config.macos_use_mach_ports(false);
-    config.wasm_backtrace(false);
+    // Backtraces are only needed to report them for trapping executions.
+    config.wasm_backtrace(
+        embedders_config.feature_flags.canister_backtrace
+            == ic_config::flag_status::FlagStatus::Enabled,
+    );
     config.wasm_backtrace_details(wasmtime::WasmBacktraceDetails::Disable);
     config.wasm_bulk_memory(true);
     config.wasm_function_references(false);
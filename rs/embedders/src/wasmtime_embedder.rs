@@ -95,6 +95,30 @@ fn wasmtime_error_to_hypervisor_error(err: anyhow::Error) -> HypervisorError {
     }
 }
 
+// The maximum size of a formatted Wasm backtrace. The frames beyond it are
+// omitted.
+const MAX_TRAP_BACKTRACE_BYTES: usize = 4 * 1024;
+
+// Formats the Wasm backtrace of a trap if the engine recorded one. The frames
+// are symbolized with the function names of the name section, if the module
+// has one, and with the function indices otherwise.
+fn trap_backtrace(err: &anyhow::Error) -> Option<String> {
+    let backtrace = err.downcast_ref::<wasmtime::WasmBacktrace>()?;
+    let mut output = String::new();
+    for (i, frame) in backtrace.frames().iter().enumerate() {
+        let line = match frame.func_name() {
+            Some(name) => format!("{}: {}\n", i, name),
+            None => format!("{}: <function {}>\n", i, frame.func_index()),
+        };
+        if output.len() + line.len() > MAX_TRAP_BACKTRACE_BYTES {
+            output.push_str("...\n");
+            break;
+        }
+        output.push_str(&line);
+    }
+    Some(output)
+}
+
 fn trap_code_to_hypervisor_error(trap: wasmtime::Trap) -> HypervisorError {
     match trap {
         wasmtime::Trap::StackOverflow => HypervisorError::Trapped(TrapCode::StackOverflow),
@@ -302,6 +326,26 @@ impl WasmtimeEmbedder {
         self.pre_instantiate(&module)
     }
 
+    /// Same as `deserialize_module_and_pre_instantiate`, but memory-maps the
+    /// serialized module from the given file instead of copying it.
+    ///
+    /// # Safety
+    ///
+    /// The file must contain the bytes of a `SerializedModuleBytes` and must
+    /// not be modified while the returned module is alive.
+    pub unsafe fn deserialize_module_file_and_pre_instantiate(
+        &self,
+        file: std::fs::File,
+    ) -> HypervisorResult<InstancePre<StoreData>> {
+        let module =
+            Module::deserialize_open_file(&self.create_engine()?, file).map_err(|err| {
+                HypervisorError::WasmEngineError(WasmEngineError::FailedToDeserializeModule(
+                    format!("{:?}", err),
+                ))
+            })?;
+        self.pre_instantiate(&module)
+    }
+
     fn list_memory_infos(
         &self,
         modification_tracking: ModificationTracking,
@@ -517,6 +561,7 @@ impl WasmtimeEmbedder {
             stable_memory_dirty_page_limit: current_dirty_page_limit,
             stable_memory_page_access_limit: current_accessed_limit,
             main_memory_type,
+            trap_backtrace: None,
         })
     }
 
@@ -765,6 +810,7 @@ pub struct WasmtimeInstance {
     stable_memory_dirty_page_limit: ic_types::NumOsPages,
     stable_memory_page_access_limit: ic_types::NumOsPages,
     main_memory_type: WasmMemoryType,
+    trap_backtrace: Option<String>,
 }
 
 impl WasmtimeInstance {
@@ -780,8 +826,19 @@ impl WasmtimeInstance {
         self.store.data()
     }
 
+    /// Returns the Wasm backtrace of the last trap, if backtraces are enabled.
+    pub fn take_trap_backtrace(&mut self) -> Option<String> {
+        self.trap_backtrace.take()
+    }
+
+    fn on_wasmtime_error(&mut self, err: anyhow::Error) -> HypervisorError {
+        self.trap_backtrace = trap_backtrace(&err);
+        wasmtime_error_to_hypervisor_error(err)
+    }
+
     fn invoke_export(&mut self, export: &str, args: &[Val]) -> HypervisorResult<()> {
-        self.instance
+        let result = self
+            .instance
             .get_export(&mut self.store, export)
             .ok_or_else(|| {
                 HypervisorError::MethodNotFound(WasmMethod::try_from(export.to_string()).unwrap())
@@ -790,8 +847,8 @@ impl WasmtimeInstance {
             .ok_or_else(|| HypervisorError::ToolchainContractViolation {
                 error: "export is not a function".to_string(),
             })?
-            .call(&mut self.store, args, &mut [])
-            .map_err(wasmtime_error_to_hypervisor_error)
+            .call(&mut self.store, args, &mut []);
+        result.map_err(|err| self.on_wasmtime_error(err))
     }
 
     fn page_accesses(&mut self) -> HypervisorResult<PageAccessResults> {
@@ -993,7 +1050,8 @@ impl WasmtimeInstance {
                     WasmMemoryType::Wasm64 => [Val::I64(closure.env as i64)],
                 };
 
-                self.instance
+                let result = self
+                    .instance
                     .get_export(&mut self.store, "table")
                     .ok_or_else(|| HypervisorError::ToolchainContractViolation {
                         error: "table not found".to_string(),
@@ -1011,8 +1069,8 @@ impl WasmtimeInstance {
                     .ok_or_else(|| HypervisorError::ToolchainContractViolation {
                         error: "unexpected null function reference".to_string(),
                     })?
-                    .call(&mut self.store, &call_args, &mut [])
-                    .map_err(wasmtime_error_to_hypervisor_error)
+                    .call(&mut self.store, &call_args, &mut []);
+                result.map_err(|err| self.on_wasmtime_error(err))
             }
         }
         .map_err(|e| {
@@ -1,6 +1,6 @@
 use crate::eth_logs::{report_transaction_error, ReceivedEvent, ReceivedEventError};
-use crate::eth_rpc::{BlockSpec, HttpOutcallError};
-use crate::eth_rpc_client::EthRpcClient;
+use crate::eth_rpc::{BlockSpec, Hash, HttpOutcallError};
+use crate::eth_rpc_client::{ChainBackend, EthRpcClient};
 use crate::guard::TimerGuard;
 use crate::logs::{DEBUG, INFO};
 use crate::numeric::{BlockNumber, LedgerMintIndex};
@@ -9,6 +9,7 @@ use crate::state::{
 };
 use hex_literal::hex;
 use ic_canister_log::log;
+use ic_crypto_sha3::Keccak256;
 use ic_ethereum_types::Address;
 use num_traits::ToPrimitive;
 use scopeguard::ScopeGuard;
@@ -314,7 +315,67 @@ async fn scrape_contract_logs<F>(
     }
 }
 
+/// Checks that the bytecode deployed at the helper smart contract address
+/// matches the expected code hash, if any, to avoid scraping logs from a wrong contract
+/// (e.g., due to a misconfigured address after an upgrade).
+/// The check is only done once per contract address since the last upgrade.
+async fn verify_helper_contract_code<F>(
+    token_name: &str,
+    helper_contract_address: Option<Address>,
+    expected_code_hash: Option<Hash>,
+    verified_contract_address: Option<Address>,
+    update_verified_contract_address: &F,
+) -> bool
+where
+    F: Fn(Address),
+{
+    let (contract_address, expected_code_hash) = match (helper_contract_address, expected_code_hash)
+    {
+        (Some(address), Some(code_hash)) => (address, code_hash),
+        _ => return true,
+    };
+    if verified_contract_address == Some(contract_address) {
+        return true;
+    }
+    let block_height = read_state(State::ethereum_block_height);
+    match read_state(EthRpcClient::from_state)
+        .eth_get_code(contract_address, BlockSpec::Tag(block_height))
+        .await
+    {
+        Ok(code) => {
+            let code_hash = Hash(Keccak256::hash(code.as_ref()));
+            if code_hash != expected_code_hash {
+                log!(
+                    INFO,
+                    "ERROR: bytecode of {token_name} helper smart contract {contract_address} has hash {code_hash} but expected {expected_code_hash}. Will not scrape logs."
+                );
+                return false;
+            }
+            update_verified_contract_address(contract_address);
+            true
+        }
+        Err(e) => {
+            log!(
+                INFO,
+                "Failed to get the bytecode of {token_name} helper smart contract {contract_address}: {e:?}. Will retry later."
+            );
+            false
+        }
+    }
+}
+
 async fn scrape_eth_logs(last_block_number: BlockNumber) {
+    if !verify_helper_contract_code(
+        "ETH",
+        read_state(|s| s.eth_helper_contract_address),
+        read_state(|s| s.eth_helper_contract_code_hash),
+        read_state(|s| s.verified_eth_helper_contract_address),
+        &|address| mutate_state(|s| s.verified_eth_helper_contract_address = Some(address)),
+    )
+    .await
+    {
+        return;
+    }
     scrape_contract_logs(
         &RECEIVED_ETH_EVENT_TOPIC,
         "ETH",
@@ -337,6 +398,17 @@ async fn scrape_erc20_logs(last_block_number: BlockNumber) {
         );
         return;
     }
+    if !verify_helper_contract_code(
+        "ERC-20",
+        read_state(|s| s.erc20_helper_contract_address),
+        read_state(|s| s.erc20_helper_contract_code_hash),
+        read_state(|s| s.verified_erc20_helper_contract_address),
+        &|address| mutate_state(|s| s.verified_erc20_helper_contract_address = Some(address)),
+    )
+    .await
+    {
+        return;
+    }
     scrape_contract_logs(
         &RECEIVED_ERC20_EVENT_TOPIC,
         "ERC-20",
@@ -372,8 +444,8 @@ pub async fn scrape_logs() {
 
 pub async fn update_last_observed_block_number() -> Option<BlockNumber> {
     let block_height = read_state(State::ethereum_block_height);
-    match read_state(EthRpcClient::from_state)
-        .eth_get_block_by_number(BlockSpec::Tag(block_height))
+    match read_state(ChainBackend::reader)
+        .get_block(BlockSpec::Tag(block_height))
         .await
     {
         Ok(latest_block) => {
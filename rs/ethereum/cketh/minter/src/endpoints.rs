@@ -278,7 +278,64 @@ pub struct AddCkErc20Token {
     pub ckerc20_ledger_id: Principal,
 }
 
+/// A JSON-RPC provider queried by the minter.
+/// Which providers are available depends on the Ethereum network of the minter.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Encode, Decode)]
+#[cbor(index_only)]
+pub enum EthRpcProvider {
+    #[n(0)]
+    Ankr,
+    #[n(1)]
+    PublicNode,
+    #[n(2)]
+    LlamaNodes,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct UpdateRpcProviderStatusArg {
+    pub provider: EthRpcProvider,
+    pub enabled: bool,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct EstimateEthRpcCallCyclesArg {
+    pub method: String,
+    pub params_size: u64,
+    pub response_size_estimate: u64,
+}
+
+/// Summary of the responses of all providers to the same JSON-RPC query.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct EthRpcCallReport {
+    pub method: String,
+    pub ok_count: u64,
+    pub error_breakdown: EthRpcErrorBreakdown,
+    pub providers: Vec<EthRpcProviderReport>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct EthRpcErrorBreakdown {
+    pub http_outcall_errors: u64,
+    pub json_rpc_errors: u64,
+    pub evm_rpc_errors: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct EthRpcProviderReport {
+    pub provider: String,
+    pub status: EthRpcProviderStatus,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum EthRpcProviderStatus {
+    Ok,
+    HttpOutcallError(String),
+    JsonRpcError { code: i64, message: String },
+    EvmRpcError(String),
+}
+
 pub mod events {
+    use crate::endpoints::EthRpcProvider;
     use crate::lifecycle::init::InitArg;
     use crate::lifecycle::upgrade::UpgradeArg;
     use candid::{CandidType, Deserialize, Nat, Principal};
@@ -355,6 +412,32 @@ pub mod events {
         pub transaction_hash: String,
     }
 
+    #[derive(CandidType, Deserialize, Debug, Clone, PartialEq, Eq)]
+    pub enum StuckTransactionReason {
+        BelowBaseFee {
+            max_fee_per_gas: Nat,
+            base_fee_per_gas: Nat,
+        },
+        NotFound,
+        Unknown,
+    }
+
+    #[derive(CandidType, Deserialize, Debug, Clone, PartialEq, Eq)]
+    pub enum ResubmissionOutcome {
+        Replaced {
+            max_fee_per_gas: Nat,
+            max_priority_fee_per_gas: Nat,
+        },
+        FeeCapExceeded {
+            max_fee_per_gas_cap: Nat,
+            max_fee_per_gas: Nat,
+        },
+        InsufficientTransactionFee {
+            allowed_max_transaction_fee: Nat,
+            max_transaction_fee: Nat,
+        },
+    }
+
     #[derive(CandidType, Deserialize, Debug, Clone, PartialEq, Eq)]
     pub enum EventPayload {
         Init(InitArg),
@@ -468,5 +551,15 @@ pub mod events {
         QuarantinedReimbursement {
             index: ReimbursementIndex,
         },
+        AttemptedTransactionResubmission {
+            withdrawal_id: Nat,
+            nonce: Nat,
+            reason: StuckTransactionReason,
+            outcome: ResubmissionOutcome,
+        },
+        UpdatedRpcProviderStatus {
+            provider: EthRpcProvider,
+            enabled: bool,
+        },
     }
 }
@@ -2,7 +2,7 @@
 mod tests;
 
 use crate::eth_rpc::{FixedSizeData, Hash, LogEntry};
-use crate::eth_rpc_client::{EthRpcClient, MultiCallError};
+use crate::eth_rpc_client::{ChainBackend, MultiCallError};
 use crate::logs::{DEBUG, INFO};
 use crate::numeric::{BlockNumber, Erc20Value, LogIndex, Wei};
 use crate::state::read_state;
@@ -203,8 +203,8 @@ pub async fn last_received_events(
         )
     }
 
-    let result = read_state(EthRpcClient::from_state)
-        .eth_get_logs(GetLogsParam {
+    let result = read_state(ChainBackend::reader)
+        .get_logs(GetLogsParam {
             from_block: from.into(),
             to_block: to.into(),
             address: vec![contract_address],
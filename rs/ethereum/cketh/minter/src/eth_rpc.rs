@@ -2,16 +2,17 @@
 //! interface.
 
 use crate::endpoints::CandidBlockTag;
-use crate::eth_rpc_client::responses::TransactionReceipt;
+use crate::eth_rpc_client::responses::{Transaction, TransactionReceipt};
+use crate::eth_rpc_client::{RetryPolicy, RpcTransport};
 use crate::eth_rpc_error::{sanitize_send_raw_transaction_result, Parser};
 use crate::logs::{DEBUG, TRACE_HTTP};
-use crate::numeric::{BlockNumber, LogIndex, TransactionCount, Wei, WeiPerGas};
-use crate::state::{mutate_state, State};
-use candid::{candid_method, CandidType, Principal};
+use crate::numeric::{BlockNumber, GasAmount, LogIndex, TransactionCount, Wei, WeiPerGas};
+use crate::state::{mutate_state, read_state, State};
+use candid::{candid_method, CandidType};
 use ethnum;
 use evm_rpc_client::types::candid::HttpOutcallError as EvmHttpOutcallError;
 use ic_canister_log::log;
-use ic_cdk::api::call::{call_with_payment128, RejectionCode};
+use ic_cdk::api::call::RejectionCode;
 use ic_cdk::api::management_canister::http_request::{
     CanisterHttpRequestArgument, HttpHeader, HttpMethod, HttpResponse, TransformArgs,
     TransformContext,
@@ -40,6 +41,15 @@ const HTTP_MAX_SIZE: u64 = 2_000_000;
 
 pub const MAX_PAYLOAD_SIZE: u64 = HTTP_MAX_SIZE - HEADER_SIZE_LIMIT;
 
+// Approximation of the size of an HTTP request besides the JSON-RPC method name and parameters,
+// which includes the URL, the request headers and the JSON-RPC envelope
+// (`{"jsonrpc":"2.0","method":"","id":18446744073709551615,"params":}`).
+const REQUEST_OVERHEAD_SIZE: u64 = 256;
+
+/// Number of nodes in the fiduciary subnet where the minter is deployed,
+/// unless configured otherwise upon upgrade.
+pub const DEFAULT_SUBNET_SIZE: u32 = 34;
+
 pub type Quantity = ethnum::u256;
 
 pub fn into_nat(quantity: Quantity) -> candid::Nat {
@@ -57,6 +67,8 @@ impl AsRef<[u8]> for Data {
     }
 }
 
+impl HttpResponsePayload for Data {}
+
 #[derive(Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
 #[serde(transparent)]
 pub struct FixedSizeData(#[serde(with = "ic_ethereum_types::serde_data")] pub [u8; 32]);
@@ -172,7 +184,7 @@ impl HttpResponsePayload for Hash {}
 
 /// Block tags.
 /// See <https://ethereum.org/en/developers/docs/apis/json-rpc/#default-block>
-#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "lowercase")]
 pub enum BlockTag {
     /// The latest mined block.
@@ -399,6 +411,11 @@ pub struct FeeHistory {
     pub base_fee_per_gas: Vec<WeiPerGas>,
     /// A two-dimensional array of effective priority fees per gas at the requested block percentiles.
     pub reward: Vec<Vec<WeiPerGas>>,
+    /// An array of block base fees per blob gas (EIP-4844).
+    /// This includes the next block after the newest of the returned range.
+    /// Empty for providers or blocks that do not support blobs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub base_fee_per_blob_gas: Vec<WeiPerGas>,
 }
 
 impl HttpResponsePayload for FeeHistory {
@@ -409,6 +426,8 @@ impl HttpResponsePayload for FeeHistory {
 
 impl HttpResponsePayload for Wei {}
 
+impl HttpResponsePayload for BlockNumber {}
+
 impl From<BlockNumber> for BlockSpec {
     fn from(value: BlockNumber) -> Self {
         BlockSpec::Number(value)
@@ -420,8 +439,26 @@ impl From<BlockNumber> for BlockSpec {
 pub struct Block {
     ///The block number. `None` when its pending block.
     pub number: BlockNumber,
+    /// Hash of the block.
+    pub hash: Hash,
     /// Base fee value of this block
     pub base_fee_per_gas: Wei,
+    /// Total amount of blob gas consumed by the transactions within the block (EIP-4844).
+    /// `None` for pre-Dencun blocks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob_gas_used: Option<GasAmount>,
+    /// Running total of blob gas consumed in excess of the target, prior to the block (EIP-4844).
+    /// `None` for pre-Dencun blocks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub excess_blob_gas: Option<GasAmount>,
+}
+
+impl Block {
+    /// The fields of a block that the minter relies upon and on which providers must agree.
+    /// The blob gas fields are left out, since some providers do not return them.
+    pub fn consensus_fields(&self) -> (BlockNumber, Hash, Wei) {
+        (self.number, self.hash, self.base_fee_per_gas)
+    }
 }
 
 impl HttpResponsePayload for Block {
@@ -471,6 +508,8 @@ pub enum ResponseTransform {
     FeeHistory,
     #[n(4)]
     SendRawTransaction,
+    #[n(5)]
+    Transaction,
 }
 
 impl ResponseTransform {
@@ -514,6 +553,7 @@ impl ResponseTransform {
             Self::SendRawTransaction => {
                 sanitize_send_raw_transaction_result(body_bytes, Parser::new())
             }
+            Self::Transaction => redact_response::<Transaction>(body_bytes),
         }
     }
 }
@@ -549,6 +589,10 @@ pub enum HttpOutcallError {
         code: RejectionCode,
         message: String,
     },
+    /// The minter does not have enough cycles to pay for the HTTP outcall.
+    InsufficientCycles { required: u128, available: u128 },
+    /// The EVM RPC canister expected more cycles than were attached to the call.
+    TooFewCycles { attached: u128, expected: u128 },
     /// Response is not a valid JSON-RPC response,
     /// which means that the response was not successful (status other than 2xx)
     /// or that the response body could not be deserialized into a JSON-RPC response.
@@ -583,6 +627,10 @@ impl HttpOutcallError {
             _ => false,
         }
     }
+
+    pub fn is_too_few_cycles(&self) -> bool {
+        matches!(self, Self::TooFewCycles { .. })
+    }
 }
 
 pub fn is_response_too_large(code: &RejectionCode, message: &str) -> bool {
@@ -591,6 +639,33 @@ pub fn is_response_too_large(code: &RejectionCode, message: &str) -> bool {
 
 pub type HttpOutcallResult<T> = Result<T, HttpOutcallError>;
 
+/// Estimates the number of cycles required to call the given JSON-RPC method,
+/// whose parameters are serialized into `params_size` bytes, on a single provider
+/// from a subnet with `subnet_size` nodes.
+pub fn estimate_call_cycles_cost(
+    method: &str,
+    params_size: u64,
+    response_size_estimate: ResponseSizeEstimate,
+    subnet_size: u32,
+) -> u128 {
+    let request_size = REQUEST_OVERHEAD_SIZE + method.len() as u64 + params_size;
+    http_request_cycles_cost(request_size, response_size_estimate, subnet_size)
+}
+
+fn http_request_cycles_cost(
+    request_size: u64,
+    response_size_estimate: ResponseSizeEstimate,
+    subnet_size: u32,
+) -> u128 {
+    // Details of the values used in the following lines can be found here:
+    // https://internetcomputer.org/docs/current/developer-docs/production/computation-and-storage-costs
+    const BASE_SUBNET_SIZE: u128 = 13;
+    let effective_size_estimate = response_size_estimate.get() + HEADER_SIZE_LIMIT;
+    let base_cycles = 400_000_000u128
+        + 100_000u128 * (request_size as u128 + 2 * effective_size_estimate as u128);
+    base_cycles * subnet_size as u128 / BASE_SUBNET_SIZE
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ResponseSizeEstimate(u64);
 
@@ -611,6 +686,14 @@ impl ResponseSizeEstimate {
     pub fn adjust(self) -> Self {
         Self(self.0.max(1024).saturating_mul(2).min(MAX_PAYLOAD_SIZE))
     }
+
+    /// Limits the estimate to the given number of bytes, if any.
+    pub fn capped(self, max_num_bytes: Option<u64>) -> Self {
+        match max_num_bytes {
+            Some(max_num_bytes) => Self::new(self.0.min(max_num_bytes)),
+            None => self,
+        }
+    }
 }
 
 impl fmt::Display for ResponseSizeEstimate {
@@ -630,8 +713,13 @@ impl<T: HttpResponsePayload> HttpResponsePayload for Option<T> {}
 impl HttpResponsePayload for TransactionCount {}
 
 /// Calls a JSON-RPC method on an Ethereum node at the specified URL.
+/// The given headers are sent in addition to the `Content-Type` header.
+/// The HTTP request is sent via the given transport and retried according to the given policy.
 pub async fn call<I, O>(
+    transport: &dyn RpcTransport,
+    retry_policy: &RetryPolicy,
     url: impl Into<String>,
+    headers: Vec<HttpHeader>,
     method: impl Into<String>,
     params: I,
     mut response_size_estimate: ResponseSizeEstimate,
@@ -641,6 +729,16 @@ where
     O: DeserializeOwned + HttpResponsePayload,
 {
     let eth_method = method.into();
+    let now = ic_cdk::api::time();
+    if let Some(learned_size) = read_state(|s| s.learned_response_size(&eth_method, now)) {
+        if learned_size > response_size_estimate.get() {
+            response_size_estimate = ResponseSizeEstimate::new(learned_size.min(MAX_PAYLOAD_SIZE));
+        }
+    }
+    let response_bytes_cap = read_state(|s| s.response_bytes_cap(&eth_method));
+    let subnet_size = read_state(|s| s.subnet_size);
+    response_size_estimate = response_size_estimate.capped(response_bytes_cap);
+    let initial_size_estimate = response_size_estimate;
     let mut rpc_request = JsonRpcRequest {
         jsonrpc: "2.0".to_string(),
         params,
@@ -649,6 +747,7 @@ where
     };
     let url = url.into();
     let mut retries = 0;
+    let mut min_cycles = 0;
 
     loop {
         rpc_request.id = mutate_state(State::next_request_id);
@@ -669,14 +768,18 @@ where
             })
             .unwrap_or_default();
 
+        let request_headers: Vec<HttpHeader> = std::iter::once(HttpHeader {
+            name: "Content-Type".to_string(),
+            value: "application/json".to_string(),
+        })
+        .chain(headers.iter().cloned())
+        .collect();
+
         let request = CanisterHttpRequestArgument {
             url: url.clone(),
             max_response_bytes: Some(effective_size_estimate),
             method: HttpMethod::POST,
-            headers: vec![HttpHeader {
-                name: "Content-Type".to_string(),
-                value: "application/json".to_string(),
-            }],
+            headers: request_headers,
             body: Some(payload.as_bytes().to_vec()),
             transform: Some(TransformContext::from_name(
                 "cleanup_response".to_owned(),
@@ -684,34 +787,39 @@ where
             )),
         };
 
-        // Details of the values used in the following lines can be found here:
-        // https://internetcomputer.org/docs/current/developer-docs/production/computation-and-storage-costs
-        let base_cycles = 400_000_000u128 + 100_000u128 * (2 * effective_size_estimate as u128);
-
-        const BASE_SUBNET_SIZE: u128 = 13;
-        const SUBNET_SIZE: u128 = 34;
-        let cycles = base_cycles * SUBNET_SIZE / BASE_SUBNET_SIZE;
+        let request_size = (url.len() + payload.len()) as u64;
+        let cycles = http_request_cycles_cost(request_size, response_size_estimate, subnet_size)
+            .max(min_cycles);
 
-        let response: HttpResponse = match call_with_payment128(
-            Principal::management_canister(),
-            "http_request",
-            (request,),
-            cycles,
-        )
-        .await
+        let response: HttpResponse = match transport
+            .http_request(eth_method.clone(), request, cycles)
+            .await
         {
-            Ok((response,)) => response,
-            Err((code, message)) if is_response_too_large(&code, &message) => {
-                let new_estimate = response_size_estimate.adjust();
-                if response_size_estimate == new_estimate {
-                    return Err(HttpOutcallError::IcError { code, message });
+            Ok(response) => response,
+            Err(error) if retry_policy.should_retry(retries as u32 + 1, &error) => {
+                if error.is_response_too_large() {
+                    let new_estimate = retry_policy
+                        .response_size_growth
+                        .next_estimate(response_size_estimate)
+                        .capped(response_bytes_cap);
+                    if response_size_estimate == new_estimate {
+                        return Err(error);
+                    }
+                    log!(DEBUG, "The {eth_method} response didn't fit into {response_size_estimate} bytes, retrying with {new_estimate}");
+                    response_size_estimate = new_estimate;
+                } else if let HttpOutcallError::TooFewCycles { expected, .. } = error {
+                    log!(DEBUG, "Calling {eth_method} with {cycles} cycles was not enough, retrying with {expected}");
+                    min_cycles = expected;
+                } else {
+                    log!(
+                        DEBUG,
+                        "Calling {eth_method} failed with {error:?}, retrying"
+                    );
                 }
-                log!(DEBUG, "The {eth_method} response didn't fit into {response_size_estimate} bytes, retrying with {new_estimate}");
-                response_size_estimate = new_estimate;
                 retries += 1;
                 continue;
             }
-            Err((code, message)) => return Err(HttpOutcallError::IcError { code, message }),
+            Err(error) => return Err(error),
         };
 
         log!(
@@ -737,6 +845,23 @@ where
             });
         }
 
+        // Remember responses that did not fit into the initial estimate,
+        // so that future calls do not need to retry with increasing estimates.
+        let observed_size = if retries > 0 {
+            response_size_estimate.get()
+        } else {
+            response.body.len() as u64
+        };
+        if observed_size > initial_size_estimate.get() {
+            mutate_state(|s| {
+                s.record_response_size(
+                    eth_method.clone(),
+                    observed_size.next_power_of_two().min(MAX_PAYLOAD_SIZE),
+                    ic_cdk::api::time(),
+                )
+            });
+        }
+
         let reply: JsonRpcReply<O> = serde_json::from_slice(&response.body).map_err(|e| {
             HttpOutcallError::InvalidHttpJsonRpcResponse {
                 status: http_status_code,
@@ -749,6 +874,16 @@ where
     }
 }
 
+/// Extracts the host from the given URL, e.g. `rpc.ankr.com` from `https://rpc.ankr.com/eth`.
+/// Metrics are labelled by host instead of URL to avoid leaking API keys contained in the path.
+pub(crate) fn url_host(url: &str) -> &str {
+    let without_scheme = url.split_once("://").map_or(url, |(_scheme, rest)| rest);
+    without_scheme
+        .split(['/', '?'])
+        .next()
+        .unwrap_or(without_scheme)
+}
+
 fn http_status_code(response: &HttpResponse) -> u16 {
     use num_traits::cast::ToPrimitive;
     // HTTP status code are always 3 decimal digits, hence at most 999.
@@ -807,10 +942,19 @@ pub(super) mod metrics {
         }
     }
 
+    #[derive(Default)]
+    struct CyclesConsumption {
+        attached: u128,
+        refunded: u128,
+    }
+
     #[derive(Default)]
     pub struct HttpMetrics {
         /// Retry counts histograms indexed by the ETH RCP method name.
         retry_histogram_per_method: BTreeMap<String, RetryHistogram>,
+        /// Cycles attached to and refunded from HTTP outcalls,
+        /// indexed by the provider host and the ETH RPC method name.
+        cycles_per_provider_and_method: BTreeMap<(String, String), CyclesConsumption>,
     }
 
     impl HttpMetrics {
@@ -821,6 +965,32 @@ pub(super) mod metrics {
                 .observe_retry_count(count);
         }
 
+        pub fn observe_cycles(
+            &mut self,
+            provider: String,
+            method: String,
+            attached: u128,
+            refunded: u128,
+        ) {
+            let consumption = self
+                .cycles_per_provider_and_method
+                .entry((provider, method))
+                .or_default();
+            consumption.attached = consumption.attached.saturating_add(attached);
+            consumption.refunded = consumption.refunded.saturating_add(refunded);
+        }
+
+        #[cfg(test)]
+        pub fn cycles_attached_and_refunded(&self, provider: &str, method: &str) -> (u128, u128) {
+            match self
+                .cycles_per_provider_and_method
+                .get(&(provider.to_string(), method.to_string()))
+            {
+                Some(consumption) => (consumption.attached, consumption.refunded),
+                None => (0, 0),
+            }
+        }
+
         #[cfg(test)]
         pub fn count_retries_in_bucket(&self, method: &str, count: usize) -> u64 {
             match self.retry_histogram_per_method.get(method) {
@@ -833,21 +1003,43 @@ pub(super) mod metrics {
             &self,
             encoder: &mut MetricsEncoder<W>,
         ) -> std::io::Result<()> {
-            if self.retry_histogram_per_method.is_empty() {
-                return Ok(());
+            if !self.retry_histogram_per_method.is_empty() {
+                let mut histogram_vec = encoder.histogram_vec(
+                    "cketh_eth_rpc_call_retry_count",
+                    "The number of ETH RPC call retries by method.",
+                )?;
+
+                for (method, histogram) in &self.retry_histogram_per_method {
+                    histogram_vec = histogram_vec.histogram(
+                        &[("method", method.as_str())],
+                        histogram.iter(),
+                        histogram.retry_count as f64,
+                    )?;
+                }
             }
 
-            let mut histogram_vec = encoder.histogram_vec(
-                "cketh_eth_rpc_call_retry_count",
-                "The number of ETH RPC call retries by method.",
-            )?;
+            if !self.cycles_per_provider_and_method.is_empty() {
+                let mut attached = encoder.counter_vec(
+                    "cketh_eth_rpc_cycles_attached_total",
+                    "The total number of cycles attached to HTTP outcalls by provider and ETH RPC method.",
+                )?;
+                for ((provider, method), consumption) in &self.cycles_per_provider_and_method {
+                    attached = attached.value(
+                        &[("provider", provider.as_str()), ("method", method.as_str())],
+                        consumption.attached as f64,
+                    )?;
+                }
 
-            for (method, histogram) in &self.retry_histogram_per_method {
-                histogram_vec = histogram_vec.histogram(
-                    &[("method", method.as_str())],
-                    histogram.iter(),
-                    histogram.retry_count as f64,
+                let mut refunded = encoder.counter_vec(
+                    "cketh_eth_rpc_cycles_refunded_total",
+                    "The total number of cycles refunded from HTTP outcalls by provider and ETH RPC method.",
                 )?;
+                for ((provider, method), consumption) in &self.cycles_per_provider_and_method {
+                    refunded = refunded.value(
+                        &[("provider", provider.as_str()), ("method", method.as_str())],
+                        consumption.refunded as f64,
+                    )?;
+                }
             }
 
             Ok(())
@@ -863,6 +1055,15 @@ pub(super) mod metrics {
         METRICS.with(|metrics| metrics.borrow_mut().observe_retry_count(method, count));
     }
 
+    /// Record the cycles attached to and refunded from an HTTP outcall to the specified provider.
+    pub fn observe_cycles(provider: String, method: String, attached: u128, refunded: u128) {
+        METRICS.with(|metrics| {
+            metrics
+                .borrow_mut()
+                .observe_cycles(provider, method, attached, refunded)
+        });
+    }
+
     /// Encodes the metrics related to ETH RPC method calls.
     pub fn encode<W: std::io::Write>(encoder: &mut MetricsEncoder<W>) -> std::io::Result<()> {
         METRICS.with(|metrics| metrics.borrow().encode(encoder))
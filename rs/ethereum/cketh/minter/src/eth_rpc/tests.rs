@@ -562,3 +562,106 @@ fn check_get_logs_param_multiple_topics_serialization() {
         })
     );
 }
+
+#[test]
+fn should_estimate_call_cycles_cost() {
+    use super::{estimate_call_cycles_cost, ResponseSizeEstimate, DEFAULT_SUBNET_SIZE};
+
+    let estimate = |params_size, response_size, subnet_size| {
+        estimate_call_cycles_cost(
+            "eth_getLogs",
+            params_size,
+            ResponseSizeEstimate::new(response_size),
+            subnet_size,
+        )
+    };
+
+    let cost = estimate(200, 100, DEFAULT_SUBNET_SIZE);
+    // (400M + 100K * (256 + 11 + 200 + 2 * (100 + 2048))) * 34 / 13
+    assert_eq!(cost, 2_291_861_538);
+
+    assert!(estimate(201, 100, DEFAULT_SUBNET_SIZE) > cost);
+    assert!(estimate(200, 101, DEFAULT_SUBNET_SIZE) > cost);
+    // (400M + 100K * (256 + 11 + 200 + 2 * (100 + 2048))) * 13 / 13
+    assert_eq!(estimate(200, 100, 13), 876_300_000);
+}
+
+#[test]
+fn should_cap_response_size_estimate() {
+    use super::ResponseSizeEstimate;
+
+    let estimate = ResponseSizeEstimate::new(2048);
+
+    assert_eq!(estimate.capped(None), estimate);
+    assert_eq!(estimate.capped(Some(4096)), estimate);
+    assert_eq!(estimate.capped(Some(1024)), ResponseSizeEstimate::new(1024));
+    assert_eq!(
+        estimate.adjust().adjust().capped(Some(5000)),
+        ResponseSizeEstimate::new(5000)
+    );
+}
+
+#[test]
+fn http_metrics_should_aggregate_cycles_per_provider_and_method() {
+    use super::metrics::HttpMetrics;
+
+    let mut metrics = HttpMetrics::default();
+
+    metrics.observe_cycles(
+        "rpc.ankr.com".to_string(),
+        "eth_getLogs".to_string(),
+        1_000,
+        100,
+    );
+    metrics.observe_cycles(
+        "rpc.ankr.com".to_string(),
+        "eth_getLogs".to_string(),
+        2_000,
+        300,
+    );
+    metrics.observe_cycles(
+        "rpc.ankr.com".to_string(),
+        "eth_feeHistory".to_string(),
+        500,
+        0,
+    );
+    metrics.observe_cycles(
+        "eth.llamarpc.com".to_string(),
+        "eth_getLogs".to_string(),
+        4_000,
+        1_000,
+    );
+
+    assert_eq!(
+        metrics.cycles_attached_and_refunded("rpc.ankr.com", "eth_getLogs"),
+        (3_000, 400)
+    );
+    assert_eq!(
+        metrics.cycles_attached_and_refunded("rpc.ankr.com", "eth_feeHistory"),
+        (500, 0)
+    );
+    assert_eq!(
+        metrics.cycles_attached_and_refunded("eth.llamarpc.com", "eth_getLogs"),
+        (4_000, 1_000)
+    );
+    assert_eq!(
+        metrics.cycles_attached_and_refunded("eth.llamarpc.com", "eth_feeHistory"),
+        (0, 0)
+    );
+}
+
+#[test]
+fn should_extract_host_from_url() {
+    use super::url_host;
+
+    assert_eq!(url_host("https://rpc.ankr.com/eth"), "rpc.ankr.com");
+    assert_eq!(
+        url_host("https://ethereum-rpc.publicnode.com"),
+        "ethereum-rpc.publicnode.com"
+    );
+    assert_eq!(
+        url_host("https://eth-mainnet.example.com/v2/API_KEY"),
+        "eth-mainnet.example.com"
+    );
+    assert_eq!(url_host("https://example.com?key=API_KEY"), "example.com");
+}
@@ -0,0 +1,47 @@
+//! Keeps track of the highest block number returned for each block tag,
+//! to detect providers serving stale snapshots of the chain.
+
+use crate::eth_rpc::BlockTag;
+use crate::numeric::BlockNumber;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+/// Maximum number of blocks by which the block number of a given tag
+/// may go backwards, e.g., due to a chain reorganization.
+pub const REORG_TOLERANCE: BlockNumber = BlockNumber::new(10);
+
+#[derive(Debug, Default)]
+pub struct HighestBlockNumbers(BTreeMap<BlockTag, BlockNumber>);
+
+impl HighestBlockNumbers {
+    /// Records the block number returned for the given tag.
+    ///
+    /// Fails with the highest block number seen so far for that tag if the given block number
+    /// is lower than it by more than [`REORG_TOLERANCE`], in which case nothing is recorded.
+    pub fn observe(&mut self, tag: BlockTag, block_number: BlockNumber) -> Result<(), BlockNumber> {
+        match self.0.get(&tag) {
+            Some(&highest) if highest >= block_number => {
+                let lowest_tolerated = highest
+                    .checked_sub(REORG_TOLERANCE)
+                    .unwrap_or(BlockNumber::ZERO);
+                if block_number < lowest_tolerated {
+                    return Err(highest);
+                }
+                Ok(())
+            }
+            _ => {
+                self.0.insert(tag, block_number);
+                Ok(())
+            }
+        }
+    }
+}
+
+thread_local! {
+    static HIGHEST_BLOCK_NUMBERS: RefCell<HighestBlockNumbers> = RefCell::default();
+}
+
+/// Records the block number returned for the given tag, see [`HighestBlockNumbers::observe`].
+pub fn observe_block_number(tag: BlockTag, block_number: BlockNumber) -> Result<(), BlockNumber> {
+    HIGHEST_BLOCK_NUMBERS.with(|highest| highest.borrow_mut().observe(tag, block_number))
+}
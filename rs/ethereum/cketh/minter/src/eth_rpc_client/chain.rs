@@ -0,0 +1,110 @@
+use crate::eth_rpc::{
+    Block, BlockSpec, GetLogsParam, Hash, HttpOutcallResult, JsonRpcResult, LogEntry,
+    SendRawTransactionResult,
+};
+use crate::eth_rpc_client::responses::Transaction;
+use crate::eth_rpc_client::{EthRpcClient, MultiCallError};
+use crate::state::State;
+use candid::{CandidType, Deserialize};
+use futures::future::LocalBoxFuture;
+use minicbor::{Decode, Encode};
+use std::fmt::Debug;
+
+/// Reads the data from the Ethereum blockchain that drives the minter's state machine.
+///
+/// Like [`RpcTransport`](super::RpcTransport), the trait is object-safe so that the backend
+/// can be chosen at runtime from the [`ChainBackend`] recorded in the state.
+pub trait ChainReader: Debug {
+    /// Returns the logs matching the given filter.
+    fn get_logs(
+        &self,
+        params: GetLogsParam,
+    ) -> LocalBoxFuture<'_, Result<Vec<LogEntry>, MultiCallError<Vec<LogEntry>>>>;
+
+    /// Returns the requested block, usually the latest block with the
+    /// [block tag](State::ethereum_block_height) observed by the minter.
+    fn get_block(
+        &self,
+        block: BlockSpec,
+    ) -> LocalBoxFuture<'_, Result<Block, MultiCallError<Block>>>;
+
+    /// Returns the transaction with the given hash, or `None` if the transaction is unknown.
+    fn get_transaction(
+        &self,
+        hash: Hash,
+    ) -> LocalBoxFuture<'_, Result<Option<Transaction>, MultiCallError<Option<Transaction>>>>;
+}
+
+/// Submits transactions signed by the minter to the Ethereum blockchain.
+pub trait ChainWriter: Debug {
+    /// Submits the given hex-encoded signed transaction.
+    fn submit_transaction(
+        &self,
+        raw_signed_transaction_hex: String,
+    ) -> LocalBoxFuture<'_, HttpOutcallResult<JsonRpcResult<SendRawTransactionResult>>>;
+}
+
+/// Backend used by the minter to read from and write to the Ethereum blockchain.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Encode, Decode)]
+#[cbor(index_only)]
+pub enum ChainBackend {
+    /// JSON-RPC calls to the providers, see [`EthRpcClient`].
+    #[default]
+    #[n(0)]
+    JsonRpc,
+    /// JSON-RPC calls to the providers sent via the EVM RPC canister instead of directly
+    /// from the minter, see [`EvmRpcTransport`](super::EvmRpcTransport).
+    /// Requires the ID of the EVM RPC canister to be set.
+    #[n(1)]
+    EvmRpc,
+}
+
+impl ChainBackend {
+    pub fn reader(state: &State) -> Box<dyn ChainReader> {
+        match state.chain_backend {
+            ChainBackend::JsonRpc | ChainBackend::EvmRpc => {
+                Box::new(EthRpcClient::from_state(state))
+            }
+        }
+    }
+
+    pub fn writer(state: &State) -> Box<dyn ChainWriter> {
+        match state.chain_backend {
+            ChainBackend::JsonRpc | ChainBackend::EvmRpc => {
+                Box::new(EthRpcClient::from_state(state))
+            }
+        }
+    }
+}
+
+impl ChainReader for EthRpcClient {
+    fn get_logs(
+        &self,
+        params: GetLogsParam,
+    ) -> LocalBoxFuture<'_, Result<Vec<LogEntry>, MultiCallError<Vec<LogEntry>>>> {
+        Box::pin(self.eth_get_logs(params))
+    }
+
+    fn get_block(
+        &self,
+        block: BlockSpec,
+    ) -> LocalBoxFuture<'_, Result<Block, MultiCallError<Block>>> {
+        Box::pin(self.eth_get_block_by_number(block))
+    }
+
+    fn get_transaction(
+        &self,
+        hash: Hash,
+    ) -> LocalBoxFuture<'_, Result<Option<Transaction>, MultiCallError<Option<Transaction>>>> {
+        Box::pin(self.eth_get_transaction_by_hash(hash))
+    }
+}
+
+impl ChainWriter for EthRpcClient {
+    fn submit_transaction(
+        &self,
+        raw_signed_transaction_hex: String,
+    ) -> LocalBoxFuture<'_, HttpOutcallResult<JsonRpcResult<SendRawTransactionResult>>> {
+        Box::pin(self.eth_send_raw_transaction(raw_signed_transaction_hex))
+    }
+}
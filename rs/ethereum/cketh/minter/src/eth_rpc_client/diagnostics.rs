@@ -0,0 +1,23 @@
+//! Keeps track of the last responses of the providers to each JSON-RPC method,
+//! to help diagnose provider issues without having to go through the logs.
+
+use crate::endpoints::EthRpcCallReport;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+thread_local! {
+    static LAST_REPORTS: RefCell<BTreeMap<String, EthRpcCallReport>> = RefCell::default();
+}
+
+/// Record the report of the last call to a JSON-RPC method,
+/// replacing any previous report for the same method.
+pub fn record_report(report: EthRpcCallReport) {
+    LAST_REPORTS.with(|reports| {
+        reports.borrow_mut().insert(report.method.clone(), report);
+    });
+}
+
+/// Returns the report of the last call to each JSON-RPC method, ordered by method name.
+pub fn last_reports() -> Vec<EthRpcCallReport> {
+    LAST_REPORTS.with(|reports| reports.borrow().values().cloned().collect())
+}
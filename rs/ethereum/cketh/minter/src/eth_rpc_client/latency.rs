@@ -0,0 +1,81 @@
+//! Keeps track of the latency of each provider, so that providers queried in sequence
+//! can be ordered from fastest to slowest.
+
+use crate::eth_rpc_client::providers::RpcNodeProvider;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// How often the providers are re-ranked according to their observed latency.
+pub const RERANK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Latency recorded for a call that failed at the HTTP outcall level,
+/// so that a provider that is down is moved towards the end of the ranking.
+pub const FAILED_CALL_LATENCY: Duration = Duration::from_secs(60);
+
+/// Weight (in percent) of a new observation in the moving average of a provider's latency.
+const NEW_OBSERVATION_WEIGHT_PERCENT: u64 = 20;
+
+#[derive(Debug, Default)]
+pub struct LatencyStats {
+    /// Exponential moving average of the latency of each provider, in nanoseconds.
+    average_latency_ns: BTreeMap<RpcNodeProvider, u64>,
+    /// Providers ordered from fastest to slowest at the time of the last ranking.
+    ranking: Vec<RpcNodeProvider>,
+    last_ranked_at_ns: Option<u64>,
+}
+
+impl LatencyStats {
+    pub fn observe(&mut self, provider: RpcNodeProvider, latency: Duration) {
+        let latency_ns = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX);
+        self.average_latency_ns
+            .entry(provider)
+            .and_modify(|average| {
+                *average = average
+                    .saturating_mul(100 - NEW_OBSERVATION_WEIGHT_PERCENT)
+                    .saturating_add(latency_ns.saturating_mul(NEW_OBSERVATION_WEIGHT_PERCENT))
+                    / 100
+            })
+            .or_insert(latency_ns);
+    }
+
+    /// Orders the given providers from fastest to slowest.
+    ///
+    /// The ranking is only recomputed once every [`RERANK_INTERVAL`], so that the order is stable
+    /// in between. Providers that were not ranked yet come first, in their given order,
+    /// so that their latency gets measured.
+    pub fn rank(&mut self, providers: &[RpcNodeProvider], now_ns: u64) -> Vec<RpcNodeProvider> {
+        let is_stale = match self.last_ranked_at_ns {
+            Some(last_ranked_at_ns) => {
+                now_ns.saturating_sub(last_ranked_at_ns) >= RERANK_INTERVAL.as_nanos() as u64
+            }
+            None => true,
+        };
+        if is_stale {
+            let mut ranking: Vec<_> = self.average_latency_ns.iter().collect();
+            ranking.sort_by_key(|(_provider, average)| **average);
+            self.ranking = ranking
+                .into_iter()
+                .map(|(provider, _average)| *provider)
+                .collect();
+            self.last_ranked_at_ns = Some(now_ns);
+        }
+        let mut ranked_providers = providers.to_vec();
+        ranked_providers.sort_by_key(|provider| self.ranking.iter().position(|p| p == provider));
+        ranked_providers
+    }
+}
+
+thread_local! {
+    static LATENCY_STATS: RefCell<LatencyStats> = RefCell::default();
+}
+
+/// Record the latency of a call to the given provider.
+pub fn observe_latency(provider: RpcNodeProvider, latency: Duration) {
+    LATENCY_STATS.with(|stats| stats.borrow_mut().observe(provider, latency));
+}
+
+/// Orders the given providers from fastest to slowest, see [`LatencyStats::rank`].
+pub fn rank_providers(providers: &[RpcNodeProvider], now_ns: u64) -> Vec<RpcNodeProvider> {
+    LATENCY_STATS.with(|stats| stats.borrow_mut().rank(providers, now_ns))
+}
@@ -1,17 +1,27 @@
+use crate::endpoints::{
+    EthRpcCallReport, EthRpcErrorBreakdown, EthRpcProviderReport, EthRpcProviderStatus,
+};
 use crate::eth_rpc::{
-    self, Block, BlockSpec, BlockTag, FeeHistory, FeeHistoryParams, GetLogsParam, Hash,
+    self, Block, BlockSpec, BlockTag, Data, FeeHistory, FeeHistoryParams, GetLogsParam, Hash,
     HttpOutcallError, HttpOutcallResult, HttpResponsePayload, JsonRpcResult, LogEntry,
     ResponseSizeEstimate, SendRawTransactionResult,
 };
 use crate::eth_rpc_client::providers::{
-    EthereumProvider, RpcNodeProvider, SepoliaProvider, MAINNET_PROVIDERS, SEPOLIA_PROVIDERS,
+    EthereumProvider, MethodCategory, ProviderSelector, RpcNodeProvider, SepoliaProvider,
 };
-use crate::eth_rpc_client::requests::GetTransactionCountParams;
-use crate::eth_rpc_client::responses::TransactionReceipt;
+use crate::eth_rpc_client::recording::RecordingTransport;
+#[cfg(feature = "debug_checks")]
+use crate::eth_rpc_client::requests::{DebugTraceTransactionParams, Tracer};
+use crate::eth_rpc_client::requests::{GetCodeParams, GetTransactionCountParams};
+#[cfg(feature = "debug_checks")]
+use crate::eth_rpc_client::responses::TransactionTrace;
+use crate::eth_rpc_client::responses::{Transaction, TransactionReceipt};
+use crate::eth_rpc_error::{ErrorParser, Parser, SendRawTransactionError};
 use crate::lifecycle::EthereumNetwork;
 use crate::logs::{PrintProxySink, DEBUG, INFO, TRACE_HTTP};
 use crate::numeric::{BlockNumber, TransactionCount, Wei};
 use crate::state::State;
+use candid::{CandidType, Deserialize};
 use evm_rpc_client::{
     types::candid::{
         Block as EvmBlock, BlockTag as EvmBlockTag, MultiRpcResult as EvmMultiRpcResult,
@@ -19,41 +29,128 @@ use evm_rpc_client::{
     },
     EvmRpcClient, IcRuntime,
 };
+use futures::StreamExt;
 use ic_canister_log::log;
+use ic_ethereum_types::Address;
+use minicbor::{Decode, Encode};
 use serde::{de::DeserializeOwned, Serialize};
 use std::collections::BTreeMap;
 use std::fmt::{Debug, Display};
+use std::sync::Arc;
+use std::time::Duration;
 
+mod block_number_tracker;
+mod chain;
+pub use chain::{ChainBackend, ChainReader, ChainWriter};
+pub mod diagnostics;
+mod latency;
 mod providers;
+pub use providers::RpcApi;
+pub mod recording;
+pub(crate) use providers::{RpcNodeProvider, RpcProviderHttpOptions};
 pub mod requests;
 pub mod responses;
+mod retry;
+pub use retry::{ResponseSizeGrowth, RetryPolicy, DEFAULT_MAX_ATTEMPTS};
+mod transport;
+pub use transport::{EvmRpcTransport, HttpsOutcallTransport, RpcTransport};
 
 #[cfg(test)]
 mod tests;
 
+/// Default maximum number of transaction receipts that are fetched concurrently.
+pub const DEFAULT_MAX_CONCURRENT_RECEIPT_REQUESTS: usize = 5;
+
+/// How `eth_sendRawTransaction` should be dispatched to the providers.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Encode, Decode)]
+#[cbor(index_only)]
+pub enum SendRawTransactionStrategy {
+    /// Query providers in sequence until one returns an ok result.
+    #[default]
+    #[n(0)]
+    SequentialUntilOk,
+    /// Submit the transaction to all providers in parallel.
+    /// The transaction is considered sent when several providers agree that they accepted it
+    /// or already knew it, even if other providers reported it as having a too low nonce.
+    #[n(1)]
+    Parallel,
+}
+
 #[derive(Debug)]
 pub struct EthRpcClient {
     evm_rpc_client: Option<EvmRpcClient<IcRuntime, PrintProxySink>>,
     chain: EthereumNetwork,
+    provider_selector: ProviderSelector,
+    provider_http_options: BTreeMap<RpcNodeProvider, RpcProviderHttpOptions>,
+    send_raw_transaction_strategy: SendRawTransactionStrategy,
+    transport: Arc<dyn RpcTransport>,
+    max_concurrent_receipt_requests: usize,
+    retry_policy: RetryPolicy,
 }
 
 impl EthRpcClient {
-    const fn new(chain: EthereumNetwork) -> Self {
+    fn new(
+        chain: EthereumNetwork,
+        provider_selector: ProviderSelector,
+        retry_policy: RetryPolicy,
+    ) -> Self {
         Self {
             evm_rpc_client: None,
             chain,
+            provider_selector,
+            provider_http_options: BTreeMap::new(),
+            send_raw_transaction_strategy: SendRawTransactionStrategy::SequentialUntilOk,
+            transport: Arc::new(HttpsOutcallTransport),
+            max_concurrent_receipt_requests: DEFAULT_MAX_CONCURRENT_RECEIPT_REQUESTS,
+            retry_policy,
         }
     }
 
+    /// Limits the number of transaction receipts fetched concurrently by
+    /// [`eth_get_transaction_receipts`](Self::eth_get_transaction_receipts).
+    pub fn with_max_concurrent_receipt_requests(mut self, max: usize) -> Self {
+        assert!(max > 0, "BUG: at least one receipt request must be allowed");
+        self.max_concurrent_receipt_requests = max;
+        self
+    }
+
+    /// Replaces the transport used to send HTTPS outcalls to the providers.
+    pub fn with_transport(mut self, transport: Arc<dyn RpcTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
     pub fn from_state(state: &State) -> Self {
-        let mut client = Self::new(state.ethereum_network());
+        let chain = state.ethereum_network();
+        let mut client = Self::new(
+            chain,
+            ProviderSelector::from_state(state),
+            RetryPolicy::from_state(state),
+        );
+        client.provider_http_options = state.rpc_provider_http_options.clone();
+        client.send_raw_transaction_strategy = state.send_raw_transaction_strategy;
+        if let (ChainBackend::EvmRpc, Some(evm_rpc_id)) = (state.chain_backend, state.evm_rpc_id) {
+            client.transport = Arc::new(EvmRpcTransport::new(evm_rpc_id));
+        }
+        if recording::is_recording_enabled() {
+            client.transport = Arc::new(RecordingTransport::new(client.transport));
+        }
         if let Some(evm_rpc_id) = state.evm_rpc_id {
+            let enabled_providers = client
+                .provider_selector
+                .providers(MethodCategory::ConsensusCritical);
             let providers = match client.chain {
-                EthereumNetwork::Mainnet => EthereumProvider::evm_rpc_node_providers(),
-                EthereumNetwork::Sepolia => SepoliaProvider::evm_rpc_node_providers(),
+                EthereumNetwork::Mainnet => EthereumProvider::evm_rpc_node_providers(
+                    enabled_providers,
+                    &client.provider_http_options,
+                ),
+                EthereumNetwork::Sepolia => {
+                    SepoliaProvider::evm_rpc_node_providers(enabled_providers)
+                }
             };
             client.evm_rpc_client = Some(
                 EvmRpcClient::builder_for_ic(TRACE_HTTP)
+                    .with_max_num_retries(client.retry_policy.max_attempts.saturating_sub(1))
                     .with_providers(providers)
                     .with_evm_canister_id(evm_rpc_id)
                     .build(),
@@ -62,14 +159,15 @@ impl EthRpcClient {
         client
     }
 
-    fn providers(&self) -> &[RpcNodeProvider] {
-        match self.chain {
-            EthereumNetwork::Mainnet => &MAINNET_PROVIDERS,
-            EthereumNetwork::Sepolia => &SEPOLIA_PROVIDERS,
-        }
+    fn providers(&self, method: &str) -> &[RpcNodeProvider] {
+        self.provider_selector.providers(MethodCategory::of(method))
     }
 
-    /// Query all providers in sequence until one returns an ok result
+    fn api(&self, provider: &RpcNodeProvider) -> RpcApi {
+        provider.api(self.provider_http_options.get(provider))
+    }
+
+    /// Query all providers in sequence, from fastest to slowest, until one returns an ok result
     /// (which could still be a JsonRpcResult::Error).
     /// If none of the providers return an ok result, return the last error.
     /// This method is useful in case a provider is temporarily down but should only be for
@@ -84,20 +182,34 @@ impl EthRpcClient {
         I: Serialize + Clone,
         O: DeserializeOwned + HttpResponsePayload + Debug,
     {
+        let method: String = method.into();
         let mut last_result: Option<HttpOutcallResult<JsonRpcResult<O>>> = None;
-        for provider in self.providers() {
+        let providers = latency::rank_providers(self.providers(&method), ic_cdk::api::time());
+        for provider in providers {
             log!(
                 DEBUG,
                 "[sequential_call_until_ok]: calling provider: {:?}",
                 provider
             );
+            let start_ns = ic_cdk::api::time();
+            let api = self.api(&provider);
             let result = eth_rpc::call(
-                provider.url().to_string(),
+                self.transport.as_ref(),
+                &self.retry_policy,
+                api.url().to_string(),
+                api.http_headers(),
                 method.clone(),
                 params.clone(),
                 response_size_estimate,
             )
             .await;
+            latency::observe_latency(
+                provider,
+                match result {
+                    Ok(_) => Duration::from_nanos(ic_cdk::api::time().saturating_sub(start_ns)),
+                    Err(_) => latency::FAILED_CALL_LATENCY,
+                },
+            );
             match result {
                 Ok(JsonRpcResult::Result(value)) => return Ok(JsonRpcResult::Result(value)),
                 Ok(json_rpc_error @ JsonRpcResult::Error { .. }) => {
@@ -131,13 +243,44 @@ impl EthRpcClient {
         I: Serialize + Clone,
         O: DeserializeOwned + HttpResponsePayload,
     {
-        let providers = self.providers();
+        let results = MultiCallResults::from_non_empty_iter(
+            self.call_all_providers(method.clone(), params, response_size_estimate)
+                .await,
+        );
+        let report = results.to_report(method.into());
+        log!(
+            DEBUG,
+            "[parallel_call]: {} returned {} ok result(s) and errors {:?}",
+            report.method,
+            report.ok_count,
+            report.error_breakdown
+        );
+        diagnostics::record_report(report);
+        results
+    }
+
+    async fn call_all_providers<I, O>(
+        &self,
+        method: impl Into<String> + Clone,
+        params: I,
+        response_size_estimate: ResponseSizeEstimate,
+    ) -> Vec<(RpcNodeProvider, HttpOutcallResult<JsonRpcResult<O>>)>
+    where
+        I: Serialize + Clone,
+        O: DeserializeOwned + HttpResponsePayload,
+    {
+        let method: String = method.into();
+        let providers = self.providers(&method);
         let results = {
             let mut fut = Vec::with_capacity(providers.len());
             for provider in providers {
                 log!(DEBUG, "[parallel_call]: will call provider: {:?}", provider);
+                let api = self.api(provider);
                 fut.push(eth_rpc::call(
-                    provider.url().to_string(),
+                    self.transport.as_ref(),
+                    &self.retry_policy,
+                    api.url().to_string(),
+                    api.http_headers(),
                     method.clone(),
                     params.clone(),
                     response_size_estimate,
@@ -145,7 +288,22 @@ impl EthRpcClient {
             }
             futures::future::join_all(fut).await
         };
-        MultiCallResults::from_non_empty_iter(providers.iter().cloned().zip(results.into_iter()))
+        providers.iter().cloned().zip(results).collect()
+    }
+
+    /// Returns the number of the most recent block.
+    /// Since providers may not all be at the same height, the lowest reported block number is returned,
+    /// which is a conservative estimate of the chain tip.
+    pub async fn eth_block_number(&self) -> Result<BlockNumber, MultiCallError<BlockNumber>> {
+        // A typical response is a JSON-RPC envelope around a single hex-encoded quantity.
+        let results: MultiCallResults<BlockNumber> = self
+            .parallel_call(
+                "eth_blockNumber",
+                Vec::<()>::new(),
+                ResponseSizeEstimate::new(128),
+            )
+            .await;
+        results.reduce_with_min_by_key(|block_number| *block_number)
     }
 
     pub async fn eth_get_logs(
@@ -159,9 +317,34 @@ impl EthRpcClient {
         results.reduce_with_equality()
     }
 
+    /// Returns the requested block.
+    /// When a block tag is requested, the returned block number is checked against the highest
+    /// block number previously returned for that tag, to guard against providers serving stale data.
     pub async fn eth_get_block_by_number(
         &self,
         block: BlockSpec,
+    ) -> Result<Block, MultiCallError<Block>> {
+        let result = self.eth_get_block_by_number_unchecked(block).await;
+        if let (BlockSpec::Tag(tag), Ok(block)) = (block, &result) {
+            if let Err(highest_seen) = block_number_tracker::observe_block_number(tag, block.number)
+            {
+                log!(
+                    INFO,
+                    "[eth_get_block_by_number]: providers returned stale block {} for tag {tag:?}, whereas block {highest_seen} was previously seen",
+                    block.number
+                );
+                return Err(MultiCallError::BlockNumberRegression {
+                    highest_seen,
+                    received: block.number,
+                });
+            }
+        }
+        result
+    }
+
+    async fn eth_get_block_by_number_unchecked(
+        &self,
+        block: BlockSpec,
     ) -> Result<Block, MultiCallError<Block>> {
         use crate::eth_rpc::GetBlockByNumberParams;
 
@@ -192,7 +375,7 @@ impl EthRpcClient {
                 ResponseSizeEstimate::new(expected_block_size),
             )
             .await;
-        results.reduce_with_equality()
+        results.reduce_with_equality_on(Block::consensus_fields)
     }
 
     pub async fn eth_get_transaction_receipt(
@@ -209,6 +392,42 @@ impl EthRpcClient {
         results.reduce_with_equality()
     }
 
+    /// Returns the transaction with the given hash, or `None` if the transaction is unknown.
+    /// A transaction that was not yet mined is returned with no block number,
+    /// see [`Transaction::is_pending`].
+    pub async fn eth_get_transaction_by_hash(
+        &self,
+        tx_hash: Hash,
+    ) -> Result<Option<Transaction>, MultiCallError<Option<Transaction>>> {
+        // Transactions issued by the minter have a small input,
+        // so that a typical response is below 1KiB.
+        let results: MultiCallResults<Option<Transaction>> = self
+            .parallel_call(
+                "eth_getTransactionByHash",
+                vec![tx_hash],
+                ResponseSizeEstimate::new(1024),
+            )
+            .await;
+        results.reduce_with_equality()
+    }
+
+    /// Fetches the receipts of the given transactions, with at most
+    /// [`max_concurrent_receipt_requests`](Self::with_max_concurrent_receipt_requests)
+    /// receipts being fetched at the same time.
+    pub async fn eth_get_transaction_receipts(
+        &self,
+        hashes: Vec<Hash>,
+    ) -> BTreeMap<
+        Hash,
+        Result<Option<TransactionReceipt>, MultiCallError<Option<TransactionReceipt>>>,
+    > {
+        futures::stream::iter(hashes)
+            .map(|hash| async move { (hash, self.eth_get_transaction_receipt(hash).await) })
+            .buffer_unordered(self.max_concurrent_receipt_requests)
+            .collect()
+            .await
+    }
+
     pub async fn eth_fee_history(
         &self,
         params: FeeHistoryParams,
@@ -226,12 +445,27 @@ impl EthRpcClient {
     ) -> HttpOutcallResult<JsonRpcResult<SendRawTransactionResult>> {
         // A successful reply is under 256 bytes, but we expect most calls to end with an error
         // since we submit the same transaction from multiple nodes.
-        self.sequential_call_until_ok(
-            "eth_sendRawTransaction",
-            vec![raw_signed_transaction_hex],
-            ResponseSizeEstimate::new(256),
-        )
-        .await
+        let response_size_estimate = ResponseSizeEstimate::new(256);
+        match self.send_raw_transaction_strategy {
+            SendRawTransactionStrategy::SequentialUntilOk => {
+                self.sequential_call_until_ok(
+                    "eth_sendRawTransaction",
+                    vec![raw_signed_transaction_hex],
+                    response_size_estimate,
+                )
+                .await
+            }
+            SendRawTransactionStrategy::Parallel => {
+                let results = self
+                    .call_all_providers(
+                        "eth_sendRawTransaction",
+                        vec![raw_signed_transaction_hex],
+                        response_size_estimate,
+                    )
+                    .await;
+                reduce_send_raw_transaction_results(results)
+            }
+        }
     }
 
     pub async fn eth_get_transaction_count(
@@ -245,6 +479,127 @@ impl EthRpcClient {
         )
         .await
     }
+
+    pub async fn eth_get_code(
+        &self,
+        address: Address,
+        block: BlockSpec,
+    ) -> Result<Data, MultiCallError<Data>> {
+        // The size of deployed contracts is limited to 24 KiB (EIP-170),
+        // which is twice as large once hex-encoded.
+        let results: MultiCallResults<Data> = self
+            .parallel_call(
+                "eth_getCode",
+                GetCodeParams { address, block },
+                ResponseSizeEstimate::new(50 * 1024),
+            )
+            .await;
+        results.reduce_with_equality()
+    }
+
+    /// Traces the execution of the given transaction to investigate, e.g., stuck withdrawals.
+    /// The trace comes from a single provider and should therefore only be used for debugging purposes.
+    /// Only supported on Sepolia, since tracing is expensive and not offered by all mainnet providers.
+    /// On other chains, a JSON-RPC "method not found" error is returned without making any call.
+    #[cfg(feature = "debug_checks")]
+    pub async fn debug_trace_transaction(
+        &self,
+        tx_hash: Hash,
+    ) -> HttpOutcallResult<JsonRpcResult<TransactionTrace>> {
+        const METHOD_NOT_FOUND: i64 = -32601;
+        if self.chain != EthereumNetwork::Sepolia {
+            return Ok(JsonRpcResult::Error {
+                code: METHOD_NOT_FOUND,
+                message: format!("debug_traceTransaction is not supported on {}", self.chain),
+            });
+        }
+        // Call traces of simple transactions are small,
+        // but traces of contract calls can easily be several hundreds of KiB.
+        self.sequential_call_until_ok(
+            "debug_traceTransaction",
+            DebugTraceTransactionParams {
+                transaction_hash: tx_hash,
+                tracer: Tracer::CallTracer,
+            },
+            ResponseSizeEstimate::new(256 * 1024),
+        )
+        .await
+    }
+}
+
+/// Reduces the results of submitting the same transaction to several providers.
+///
+/// Since `eth_sendRawTransaction` is not idempotent, a provider may answer that the transaction
+/// is already known or that its nonce is too low, because another provider already forwarded it.
+/// The transaction is therefore considered as sent as soon as one provider accepted it or already
+/// knew it, regardless of the other answers. Otherwise, the first (in provider order) other ok
+/// result is returned, and if there is none, the last error.
+fn reduce_send_raw_transaction_results<I>(
+    results: I,
+) -> HttpOutcallResult<JsonRpcResult<SendRawTransactionResult>>
+where
+    I: IntoIterator<
+        Item = (
+            RpcNodeProvider,
+            HttpOutcallResult<JsonRpcResult<SendRawTransactionResult>>,
+        ),
+    >,
+{
+    let parser = Parser::new();
+    let results: Vec<_> = results
+        .into_iter()
+        .map(|(provider, result)| {
+            let result = match result {
+                Ok(JsonRpcResult::Error { code, message }) => {
+                    // The response transform normally sanitizes the errors,
+                    // but we cannot rely on it if the response could not be parsed.
+                    match parser.try_parse_send_raw_transaction_error(code, message.clone()) {
+                        Some(SendRawTransactionError::AlreadyKnown) => {
+                            Ok(JsonRpcResult::Result(SendRawTransactionResult::Ok))
+                        }
+                        Some(SendRawTransactionError::NonceTooLow) => {
+                            Ok(JsonRpcResult::Result(SendRawTransactionResult::NonceTooLow))
+                        }
+                        _ => Ok(JsonRpcResult::Error { code, message }),
+                    }
+                }
+                other => other,
+            };
+            (provider, result)
+        })
+        .collect();
+    if results.iter().any(|(_provider, result)| {
+        matches!(
+            result,
+            Ok(JsonRpcResult::Result(SendRawTransactionResult::Ok))
+        )
+    }) {
+        return Ok(JsonRpcResult::Result(SendRawTransactionResult::Ok));
+    }
+    let mut first_ok_result: Option<SendRawTransactionResult> = None;
+    let mut last_error: Option<HttpOutcallResult<JsonRpcResult<SendRawTransactionResult>>> = None;
+    for (provider, result) in results {
+        match result {
+            Ok(JsonRpcResult::Result(tx_result)) => {
+                log!(
+                    INFO,
+                    "[reduce_send_raw_transaction_results]: provider {provider:?} returned {tx_result:?}"
+                );
+                first_ok_result.get_or_insert(tx_result);
+            }
+            error => {
+                log!(
+                    INFO,
+                    "[reduce_send_raw_transaction_results]: provider {provider:?} returned error {error:?}"
+                );
+                last_error = Some(error);
+            }
+        }
+    }
+    match first_ok_result {
+        Some(tx_result) => Ok(JsonRpcResult::Result(tx_result)),
+        None => last_error.expect("BUG: no results for eth_sendRawTransaction"),
+    }
 }
 
 /// Aggregates responses of different providers to the same query.
@@ -340,6 +695,65 @@ impl<T> MultiCallResults<T> {
     pub fn is_empty(&self) -> bool {
         self.ok_results.is_empty() && self.errors.is_empty()
     }
+
+    /// Number of providers that returned an ok result.
+    pub fn ok_count(&self) -> usize {
+        self.ok_results.len()
+    }
+
+    /// Number of providers that returned an error, by kind of error.
+    pub fn error_breakdown(&self) -> EthRpcErrorBreakdown {
+        let mut breakdown = EthRpcErrorBreakdown::default();
+        for error in self.errors.values() {
+            match error {
+                SingleCallError::HttpOutcallError(_) => breakdown.http_outcall_errors += 1,
+                SingleCallError::JsonRpcError { .. } => breakdown.json_rpc_errors += 1,
+                SingleCallError::EvmRpcError(_) => breakdown.evm_rpc_errors += 1,
+            }
+        }
+        breakdown
+    }
+
+    /// Summarizes the results of calling the given JSON-RPC method on each provider.
+    pub fn into_report(self, method: impl Into<String>) -> EthRpcCallReport {
+        self.to_report(method.into())
+    }
+
+    fn to_report(&self, method: String) -> EthRpcCallReport {
+        let ok_providers = self
+            .ok_results
+            .keys()
+            .map(|provider| (provider, EthRpcProviderStatus::Ok));
+        let error_providers = self.errors.iter().map(|(provider, error)| {
+            let status = match error {
+                SingleCallError::HttpOutcallError(e) => {
+                    EthRpcProviderStatus::HttpOutcallError(format!("{e:?}"))
+                }
+                SingleCallError::JsonRpcError { code, message } => {
+                    EthRpcProviderStatus::JsonRpcError {
+                        code: *code,
+                        message: message.clone(),
+                    }
+                }
+                SingleCallError::EvmRpcError(e) => EthRpcProviderStatus::EvmRpcError(e.clone()),
+            };
+            (provider, status)
+        });
+        let mut providers: Vec<_> = ok_providers
+            .chain(error_providers)
+            .map(|(provider, status)| EthRpcProviderReport {
+                provider: format!("{provider:?}"),
+                status,
+            })
+            .collect();
+        providers.sort_by(|left, right| left.provider.cmp(&right.provider));
+        EthRpcCallReport {
+            method,
+            ok_count: self.ok_count() as u64,
+            error_breakdown: self.error_breakdown(),
+            providers,
+        }
+    }
 }
 
 impl<T: PartialEq> MultiCallResults<T> {
@@ -403,9 +817,18 @@ pub enum SingleCallError {
 #[derive(Debug, PartialEq, Eq)]
 pub enum MultiCallError<T> {
     ConsistentHttpOutcallError(HttpOutcallError),
-    ConsistentJsonRpcError { code: i64, message: String },
+    ConsistentJsonRpcError {
+        code: i64,
+        message: String,
+    },
     ConsistentEvmRpcCanisterError(String),
     InconsistentResults(MultiCallResults<T>),
+    /// The block number agreed upon by the providers for a given block tag
+    /// is lower than a previously seen block number for that tag by more than the reorg tolerance.
+    BlockNumberRegression {
+        highest_seen: BlockNumber,
+        received: BlockNumber,
+    },
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -440,6 +863,13 @@ impl<T> ReducedResult<T> {
             Err(MultiCallError::ConsistentEvmRpcCanisterError(e)) => {
                 Err(MultiCallError::<U>::ConsistentEvmRpcCanisterError(e))
             }
+            Err(MultiCallError::BlockNumberRegression {
+                highest_seen,
+                received,
+            }) => Err(MultiCallError::<U>::BlockNumberRegression {
+                highest_seen,
+                received,
+            }),
             Err(MultiCallError::InconsistentResults(results)) => {
                 reduction(results.map(faillible_op, &|e| {
                     SingleCallError::EvmRpcError(e.to_string())
@@ -523,10 +953,15 @@ impl From<EvmMultiRpcResult<EvmBlock>> for ReducedResult<Block> {
             &|block: EvmBlock| {
                 Ok::<Block, String>(Block {
                     number: BlockNumber::try_from(block.number)?,
+                    hash: block.hash.parse()?,
                     base_fee_per_gas: Wei::try_from(block.base_fee_per_gas)?,
+                    // The EVM RPC canister does not return the blob gas fields,
+                    // which are not part of the block consensus fields.
+                    blob_gas_used: None,
+                    excess_blob_gas: None,
                 })
             },
-            MultiCallResults::reduce_with_equality,
+            |results| results.reduce_with_equality_on(Block::consensus_fields),
         )
     }
 }
@@ -553,7 +988,8 @@ impl<T> MultiCallError<T> {
                         }
                     })
             }
-            MultiCallError::ConsistentEvmRpcCanisterError(_) => false,
+            MultiCallError::ConsistentEvmRpcCanisterError(_)
+            | MultiCallError::BlockNumberRegression { .. } => false,
         }
     }
 }
@@ -583,6 +1019,37 @@ impl<T: Debug + PartialEq> MultiCallResults<T> {
         Ok(base_result)
     }
 
+    /// Expects all results to be ok and to agree on the fields extracted by `projection`.
+    /// This is useful when providers may legitimately differ on some fields that are not used by the minter,
+    /// e.g. provider-specific extra fields. In that case, the result of the first provider is returned.
+    pub fn reduce_with_equality_on<F: Fn(&T) -> P, P: PartialEq + Debug>(
+        self,
+        projection: F,
+    ) -> Result<T, MultiCallError<T>> {
+        let mut results = self.all_ok()?.into_iter();
+        let (base_node_provider, base_result) = results
+            .next()
+            .expect("BUG: MultiCallResults is guaranteed to be non-empty");
+        let base_projection = projection(&base_result);
+        let mut inconsistent_results: Vec<_> = results
+            .filter(|(_provider, result)| projection(result) != base_projection)
+            .collect();
+        if !inconsistent_results.is_empty() {
+            inconsistent_results.push((base_node_provider, base_result));
+            let error = MultiCallError::InconsistentResults(MultiCallResults::from_iter(
+                inconsistent_results
+                    .into_iter()
+                    .map(|(provider, result)| (provider, Ok(result))),
+            ));
+            log!(
+                INFO,
+                "[reduce_with_equality_on]: inconsistent results {error:?}"
+            );
+            return Err(error);
+        }
+        Ok(base_result)
+    }
+
     pub fn reduce_with_min_by_key<F: FnMut(&T) -> K, K: Ord>(
         self,
         extractor: F,
@@ -1,7 +1,12 @@
+use crate::endpoints::EthRpcProvider;
+use crate::lifecycle::EthereumNetwork;
+use crate::state::State;
 use evm_rpc_client::types::candid::{
     EthSepoliaService as EvmEthSepoliaService, RpcService as EvmRpcService,
     RpcServices as EvmRpcServices,
 };
+use ic_cdk::api::management_canister::http_request::HttpHeader;
+use std::collections::BTreeMap;
 
 pub(crate) const MAINNET_PROVIDERS: [RpcNodeProvider; 3] = [
     RpcNodeProvider::Ethereum(EthereumProvider::Ankr),
@@ -14,8 +19,86 @@ pub(crate) const SEPOLIA_PROVIDERS: [RpcNodeProvider; 2] = [
     RpcNodeProvider::Sepolia(SepoliaProvider::PublicNode),
 ];
 
-const EVM_RPC_SEPOLIA_PROVIDERS: [EvmEthSepoliaService; 2] =
-    [EvmEthSepoliaService::Ankr, EvmEthSepoliaService::PublicNode];
+/// Category of JSON-RPC methods, which may be served by different sets of providers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Ord, PartialOrd, Hash)]
+pub(crate) enum MethodCategory {
+    /// Methods whose results the minter relies upon to mint or to finalize withdrawals,
+    /// e.g., `eth_getLogs` or `eth_getTransactionReceipt`.
+    ConsensusCritical,
+    /// Methods that are not critical but latency-sensitive,
+    /// e.g., `eth_sendRawTransaction` or `eth_feeHistory`.
+    LatencySensitive,
+}
+
+impl MethodCategory {
+    pub(crate) fn of(method: &str) -> Self {
+        match method {
+            "eth_sendRawTransaction" | "eth_feeHistory" => Self::LatencySensitive,
+            _ => Self::ConsensusCritical,
+        }
+    }
+}
+
+/// Selects which providers should be queried for a given JSON-RPC method.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ProviderSelector {
+    consensus_critical: Vec<RpcNodeProvider>,
+    latency_sensitive: Vec<RpcNodeProvider>,
+}
+
+impl ProviderSelector {
+    pub(crate) fn new(
+        consensus_critical: Vec<RpcNodeProvider>,
+        latency_sensitive: Vec<RpcNodeProvider>,
+    ) -> Self {
+        assert!(
+            !consensus_critical.is_empty(),
+            "BUG: no providers for consensus-critical methods"
+        );
+        assert!(
+            !latency_sensitive.is_empty(),
+            "BUG: no providers for latency-sensitive methods"
+        );
+        Self {
+            consensus_critical,
+            latency_sensitive,
+        }
+    }
+
+    /// Use all known providers of the given network for all methods.
+    pub(crate) fn all(chain: EthereumNetwork) -> Self {
+        let providers = RpcNodeProvider::all(chain).to_vec();
+        Self::new(providers.clone(), providers)
+    }
+
+    /// Selects the providers configured in the state, leaving out the disabled ones.
+    /// Consensus-critical methods are sent to all enabled providers, while latency-sensitive
+    /// methods are only sent to the enabled providers among the configured ones,
+    /// or to all enabled providers if there are none.
+    pub(crate) fn from_state(state: &State) -> Self {
+        let enabled: Vec<_> = RpcNodeProvider::all(state.ethereum_network())
+            .iter()
+            .filter(|provider| !state.disabled_rpc_providers.contains(provider))
+            .copied()
+            .collect();
+        let latency_sensitive: Vec<_> = enabled
+            .iter()
+            .filter(|provider| state.latency_sensitive_rpc_providers.contains(provider))
+            .copied()
+            .collect();
+        if latency_sensitive.is_empty() {
+            return Self::new(enabled.clone(), enabled);
+        }
+        Self::new(enabled, latency_sensitive)
+    }
+
+    pub(crate) fn providers(&self, category: MethodCategory) -> &[RpcNodeProvider] {
+        match category {
+            MethodCategory::ConsensusCritical => &self.consensus_critical,
+            MethodCategory::LatencySensitive => &self.latency_sensitive,
+        }
+    }
+}
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Ord, PartialOrd, Hash)]
 pub(crate) enum RpcNodeProvider {
@@ -24,7 +107,121 @@ pub(crate) enum RpcNodeProvider {
     EvmRpc(EvmRpcService),
 }
 
+/// How to reach a JSON-RPC provider.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RpcApi {
+    url: String,
+    /// Additional HTTP headers, e.g., for providers requiring header-based authentication.
+    headers: Vec<HttpHeader>,
+    /// Value of the `Host` header, when it should differ from the host in the URL.
+    host: Option<String>,
+}
+
+impl RpcApi {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            headers: vec![],
+            host: None,
+        }
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push(HttpHeader {
+            name: name.into(),
+            value: value.into(),
+        });
+        self
+    }
+
+    pub fn with_host(mut self, host: impl Into<String>) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// All the HTTP headers specific to this provider, including the `Host` header if overridden.
+    pub fn http_headers(&self) -> Vec<HttpHeader> {
+        let mut headers = self.headers.clone();
+        if let Some(host) = &self.host {
+            headers.push(HttpHeader {
+                name: "Host".to_string(),
+                value: host.clone(),
+            });
+        }
+        headers
+    }
+}
+
+/// HTTP options configured for a provider, which are added to every request sent to it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct RpcProviderHttpOptions {
+    pub headers: Vec<HttpHeader>,
+    pub host: Option<String>,
+}
+
+impl RpcProviderHttpOptions {
+    fn apply(&self, api: RpcApi) -> RpcApi {
+        let api = self.headers.iter().fold(api, |api, header| {
+            api.with_header(header.name.clone(), header.value.clone())
+        });
+        match &self.host {
+            Some(host) => api.with_host(host.clone()),
+            None => api,
+        }
+    }
+}
+
 impl RpcNodeProvider {
+    /// All known providers of the given network.
+    pub(crate) fn all(chain: EthereumNetwork) -> &'static [RpcNodeProvider] {
+        match chain {
+            EthereumNetwork::Mainnet => &MAINNET_PROVIDERS,
+            EthereumNetwork::Sepolia => &SEPOLIA_PROVIDERS,
+        }
+    }
+
+    /// Returns the provider of the given network corresponding to the Candid provider,
+    /// if that provider is available on that network.
+    pub(crate) fn from_candid(chain: EthereumNetwork, provider: EthRpcProvider) -> Option<Self> {
+        match (chain, provider) {
+            (EthereumNetwork::Mainnet, EthRpcProvider::Ankr) => {
+                Some(Self::Ethereum(EthereumProvider::Ankr))
+            }
+            (EthereumNetwork::Mainnet, EthRpcProvider::PublicNode) => {
+                Some(Self::Ethereum(EthereumProvider::PublicNode))
+            }
+            (EthereumNetwork::Mainnet, EthRpcProvider::LlamaNodes) => {
+                Some(Self::Ethereum(EthereumProvider::LlamaNodes))
+            }
+            (EthereumNetwork::Sepolia, EthRpcProvider::Ankr) => {
+                Some(Self::Sepolia(SepoliaProvider::Ankr))
+            }
+            (EthereumNetwork::Sepolia, EthRpcProvider::PublicNode) => {
+                Some(Self::Sepolia(SepoliaProvider::PublicNode))
+            }
+            (EthereumNetwork::Sepolia, EthRpcProvider::LlamaNodes) => None,
+        }
+    }
+
+    /// Returns how to reach this provider, with the given HTTP options if any.
+    pub(crate) fn api(&self, http_options: Option<&RpcProviderHttpOptions>) -> RpcApi {
+        let api = match self {
+            Self::Ethereum(provider) => RpcApi::new(provider.ethereum_mainnet_endpoint_url()),
+            Self::Sepolia(provider) => RpcApi::new(provider.ethereum_sepolia_endpoint_url()),
+            RpcNodeProvider::EvmRpc(_) => {
+                panic!("BUG: should not need API of provider from EVM RPC canister")
+            }
+        };
+        match http_options {
+            Some(options) => options.apply(api),
+            None => api,
+        }
+    }
+
     //TODO XC-27: remove this method
     pub(crate) fn url(&self) -> &str {
         match self {
@@ -58,14 +255,24 @@ impl EthereumProvider {
 
     // TODO XC-131: Replace using Custom providers with EthMainnetService,
     // when LlamaNodes is supported as a provider.
-    pub(crate) fn evm_rpc_node_providers() -> EvmRpcServices {
+    /// Services of the EVM RPC canister for the given providers,
+    /// skipping the providers of other networks.
+    pub(crate) fn evm_rpc_node_providers(
+        providers: &[RpcNodeProvider],
+        http_options: &BTreeMap<RpcNodeProvider, RpcProviderHttpOptions>,
+    ) -> EvmRpcServices {
         use evm_rpc_client::types::candid::RpcApi as EvmRpcApi;
 
-        let services = MAINNET_PROVIDERS
+        let services = providers
             .iter()
-            .map(|provider| EvmRpcApi {
-                url: provider.url().to_string(),
-                headers: None,
+            .filter(|provider| matches!(provider, RpcNodeProvider::Ethereum(_)))
+            .map(|provider| {
+                let api = provider.api(http_options.get(provider));
+                let headers = api.http_headers();
+                EvmRpcApi {
+                    url: api.url().to_string(),
+                    headers: (!headers.is_empty()).then_some(headers),
+                }
             })
             .collect();
         EvmRpcServices::Custom {
@@ -91,7 +298,19 @@ impl SepoliaProvider {
         }
     }
 
-    pub(crate) fn evm_rpc_node_providers() -> EvmRpcServices {
-        EvmRpcServices::EthSepolia(Some(EVM_RPC_SEPOLIA_PROVIDERS.to_vec()))
+    /// Services of the EVM RPC canister for the given providers,
+    /// skipping the providers of other networks.
+    pub(crate) fn evm_rpc_node_providers(providers: &[RpcNodeProvider]) -> EvmRpcServices {
+        let services = providers
+            .iter()
+            .filter_map(|provider| match provider {
+                RpcNodeProvider::Sepolia(SepoliaProvider::Ankr) => Some(EvmEthSepoliaService::Ankr),
+                RpcNodeProvider::Sepolia(SepoliaProvider::PublicNode) => {
+                    Some(EvmEthSepoliaService::PublicNode)
+                }
+                _ => None,
+            })
+            .collect();
+        EvmRpcServices::EthSepolia(Some(services))
     }
 }
@@ -0,0 +1,189 @@
+//! Records the JSON-RPC interactions of the minter with its providers,
+//! so that they can be exported and replayed in deterministic tests.
+//!
+//! Requests and responses are normalized before being recorded: the JSON-RPC request ID is reset,
+//! since it changes with every call, and only the host of the provider's URL is kept,
+//! since the path may contain API keys.
+
+use crate::eth_rpc::{url_host, HttpOutcallError, HttpOutcallResult};
+use crate::eth_rpc_client::transport::RpcTransport;
+use candid::Nat;
+use futures::future::LocalBoxFuture;
+use ic_cdk::api::call::RejectionCode;
+use ic_cdk::api::management_canister::http_request::{CanisterHttpRequestArgument, HttpResponse};
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Maximum number of recorded calls kept in memory.
+/// Once reached, the oldest calls are discarded.
+pub const MAX_RECORDED_CALLS: usize = 1_000;
+
+/// JSON-RPC request ID used in normalized requests and responses.
+const NORMALIZED_REQUEST_ID: u64 = 0;
+
+/// A successful HTTP request/response pair sent to a provider.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedRpcCall {
+    /// Host of the provider, e.g. `rpc.ankr.com`.
+    pub provider: String,
+    pub eth_method: String,
+    /// Normalized JSON-RPC request body.
+    pub request: String,
+    pub status: u16,
+    /// Normalized response body, which may not be valid JSON if the provider misbehaved.
+    pub response: String,
+}
+
+#[derive(Debug, Default)]
+pub struct RpcRecorder {
+    enabled: Cell<bool>,
+    calls: RefCell<VecDeque<RecordedRpcCall>>,
+}
+
+impl RpcRecorder {
+    pub fn record(&self, call: RecordedRpcCall) {
+        if !self.enabled.get() {
+            return;
+        }
+        let mut calls = self.calls.borrow_mut();
+        if calls.len() >= MAX_RECORDED_CALLS {
+            calls.pop_front();
+        }
+        calls.push_back(call);
+    }
+}
+
+thread_local! {
+    static RPC_RECORDER: RpcRecorder = RpcRecorder::default();
+}
+
+/// Starts or stops recording the calls made to the providers.
+/// Stopping the recording does not discard the calls recorded so far.
+pub fn set_recording_enabled(enabled: bool) {
+    RPC_RECORDER.with(|recorder| recorder.enabled.set(enabled));
+}
+
+pub fn is_recording_enabled() -> bool {
+    RPC_RECORDER.with(|recorder| recorder.enabled.get())
+}
+
+/// Returns the recorded calls, from oldest to newest.
+pub fn recorded_calls() -> Vec<RecordedRpcCall> {
+    RPC_RECORDER.with(|recorder| recorder.calls.borrow().iter().cloned().collect())
+}
+
+/// Replaces the JSON-RPC request ID in the given body, if it's a JSON object.
+/// Other bodies are returned unchanged.
+fn normalize_body(body: &[u8]) -> String {
+    match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(serde_json::Value::Object(mut fields)) => {
+            if fields.contains_key("id") {
+                fields.insert("id".to_string(), NORMALIZED_REQUEST_ID.into());
+            }
+            serde_json::Value::Object(fields).to_string()
+        }
+        _ => String::from_utf8_lossy(body).to_string(),
+    }
+}
+
+fn normalize_request(request: &CanisterHttpRequestArgument) -> String {
+    normalize_body(request.body.as_deref().unwrap_or_default())
+}
+
+/// Forwards calls to the inner transport and records the successful ones
+/// while recording is [enabled](set_recording_enabled).
+#[derive(Debug)]
+pub struct RecordingTransport {
+    inner: Arc<dyn RpcTransport>,
+}
+
+impl RecordingTransport {
+    pub fn new(inner: Arc<dyn RpcTransport>) -> Self {
+        Self { inner }
+    }
+}
+
+impl RpcTransport for RecordingTransport {
+    fn http_request(
+        &self,
+        eth_method: String,
+        request: CanisterHttpRequestArgument,
+        cycles: u128,
+    ) -> LocalBoxFuture<'_, HttpOutcallResult<HttpResponse>> {
+        Box::pin(async move {
+            let provider = url_host(&request.url).to_string();
+            let normalized_request = normalize_request(&request);
+            let result = self
+                .inner
+                .http_request(eth_method.clone(), request, cycles)
+                .await;
+            if let Ok(response) = &result {
+                use num_traits::cast::ToPrimitive;
+                RPC_RECORDER.with(|recorder| {
+                    recorder.record(RecordedRpcCall {
+                        provider,
+                        eth_method,
+                        request: normalized_request,
+                        status: response.status.0.to_u16().unwrap_or(u16::MAX),
+                        response: normalize_body(&response.body),
+                    })
+                });
+            }
+            result
+        })
+    }
+}
+
+/// Serves previously recorded calls instead of sending HTTPS outcalls.
+///
+/// A request is answered with the response of the first recorded call to the same provider
+/// with the same method and normalized request body, and fails if there is no such call.
+#[derive(Debug)]
+pub struct ReplayTransport {
+    calls: Vec<RecordedRpcCall>,
+}
+
+impl ReplayTransport {
+    pub fn new(calls: Vec<RecordedRpcCall>) -> Self {
+        Self { calls }
+    }
+
+    /// Parses calls in the format returned by the `get_recorded_eth_rpc_calls` endpoint.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json).map(Self::new)
+    }
+}
+
+impl RpcTransport for ReplayTransport {
+    fn http_request(
+        &self,
+        eth_method: String,
+        request: CanisterHttpRequestArgument,
+        _cycles: u128,
+    ) -> LocalBoxFuture<'_, HttpOutcallResult<HttpResponse>> {
+        let provider = url_host(&request.url).to_string();
+        let normalized_request = normalize_request(&request);
+        let result = self
+            .calls
+            .iter()
+            .find(|call| {
+                call.provider == provider
+                    && call.eth_method == eth_method
+                    && call.request == normalized_request
+            })
+            .map(|call| HttpResponse {
+                status: Nat::from(call.status),
+                headers: vec![],
+                body: call.response.as_bytes().to_vec(),
+            })
+            .ok_or_else(|| HttpOutcallError::IcError {
+                code: RejectionCode::SysFatal,
+                message: format!(
+                    "no recorded response for {eth_method} on {provider} with request {normalized_request}"
+                ),
+            });
+        Box::pin(async move { result })
+    }
+}
@@ -1,4 +1,4 @@
-use crate::eth_rpc::BlockSpec;
+use crate::eth_rpc::{BlockSpec, Hash};
 use ic_ethereum_types::Address;
 use serde::Serialize;
 
@@ -17,3 +17,57 @@ impl From<GetTransactionCountParams> for (Address, BlockSpec) {
         (params.address, params.block)
     }
 }
+
+/// Parameters of the [`eth_getCode`](https://ethereum.org/en/developers/docs/apis/json-rpc/#eth_getcode) call.
+#[derive(Debug, Serialize, Clone)]
+#[serde(into = "(Address, BlockSpec)")]
+pub struct GetCodeParams {
+    /// The address of the smart contract.
+    pub address: Address,
+    /// Integer block number, or "latest" for the last mined block or "pending", "earliest" for not yet mined transactions.
+    pub block: BlockSpec,
+}
+
+impl From<GetCodeParams> for (Address, BlockSpec) {
+    fn from(params: GetCodeParams) -> Self {
+        (params.address, params.block)
+    }
+}
+
+/// Parameters of the [`debug_traceTransaction`](https://geth.ethereum.org/docs/interacting-with-geth/rpc/ns-debug#debugtracetransaction) call.
+#[derive(Debug, Serialize, Clone)]
+#[serde(into = "(Hash, TracerOptions)")]
+pub struct DebugTraceTransactionParams {
+    /// The hash of the transaction to trace.
+    pub transaction_hash: Hash,
+    /// The tracer used to produce the trace.
+    pub tracer: Tracer,
+}
+
+/// Built-in tracers.
+/// See <https://geth.ethereum.org/docs/developers/evm-tracing/built-in-tracers>
+#[derive(Debug, Default, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Tracer {
+    /// Tracks all the call frames executed during the transaction.
+    #[default]
+    CallTracer,
+    /// Returns the accounts necessary to execute the transaction.
+    PrestateTracer,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TracerOptions {
+    tracer: Tracer,
+}
+
+impl From<DebugTraceTransactionParams> for (Hash, TracerOptions) {
+    fn from(params: DebugTraceTransactionParams) -> Self {
+        (
+            params.transaction_hash,
+            TracerOptions {
+                tracer: params.tracer,
+            },
+        )
+    }
+}
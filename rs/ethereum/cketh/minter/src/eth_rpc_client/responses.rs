@@ -1,5 +1,6 @@
-use crate::eth_rpc::{Hash, HttpResponsePayload, ResponseTransform};
-use crate::numeric::{BlockNumber, GasAmount, Wei, WeiPerGas};
+use crate::eth_rpc::{Data, Hash, HttpResponsePayload, Quantity, ResponseTransform};
+use crate::numeric::{BlockNumber, GasAmount, TransactionNonce, Wei, WeiPerGas};
+use ic_ethereum_types::Address;
 use minicbor::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
@@ -91,3 +92,77 @@ impl Display for TransactionStatus {
         }
     }
 }
+
+/// Transaction as returned by the `eth_getTransactionByHash` call.
+///
+/// A transaction unknown to the queried node is returned as `null`,
+/// while a transaction still pending in the mempool has no block hash or block number.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Transaction {
+    /// The hash of the transaction.
+    pub hash: Hash,
+
+    /// The type of the transaction envelope, e.g. `0x2` for EIP-1559 transactions.
+    /// Absent for legacy transactions on some providers.
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub transaction_type: Option<Quantity>,
+
+    /// The number of transactions made by the sender prior to this one.
+    pub nonce: TransactionNonce,
+
+    /// The address of the sender.
+    pub from: Address,
+
+    /// The address of the receiver. None when it's a contract creation transaction.
+    pub to: Option<Address>,
+
+    /// The value transferred in Wei.
+    pub value: Wei,
+
+    /// The gas limit provided by the sender.
+    pub gas: GasAmount,
+
+    /// The gas price paid by the sender. For EIP-1559 transactions, this is the effective gas price
+    /// once the transaction is mined, and the maximum fee per gas while it is pending.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_price: Option<WeiPerGas>,
+
+    /// Maximum fee per gas the sender is willing to pay (EIP-1559 transactions only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_fee_per_gas: Option<WeiPerGas>,
+
+    /// Maximum priority fee per gas the sender is willing to pay (EIP-1559 transactions only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_priority_fee_per_gas: Option<WeiPerGas>,
+
+    /// The data sent along with the transaction.
+    pub input: Data,
+
+    /// The hash of the block containing the transaction. None when the transaction is pending.
+    pub block_hash: Option<Hash>,
+
+    /// The number of the block containing the transaction. None when the transaction is pending.
+    pub block_number: Option<BlockNumber>,
+}
+
+impl Transaction {
+    /// Returns true if the transaction is known but not yet included in a block.
+    pub fn is_pending(&self) -> bool {
+        self.block_number.is_none()
+    }
+}
+
+impl HttpResponsePayload for Transaction {
+    fn response_transform() -> Option<ResponseTransform> {
+        Some(ResponseTransform::Transaction)
+    }
+}
+
+/// Result of the `debug_traceTransaction` call.
+/// The format of the trace depends on the tracer used, so it is kept as raw JSON.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(transparent)]
+pub struct TransactionTrace(pub serde_json::Value);
+
+impl HttpResponsePayload for TransactionTrace {}
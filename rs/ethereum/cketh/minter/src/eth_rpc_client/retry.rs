@@ -0,0 +1,72 @@
+use crate::eth_rpc::{HttpOutcallError, ResponseSizeEstimate};
+use crate::state::State;
+
+/// Maximum number of attempts of a single call to a provider with the default policy.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 20;
+
+/// Describes when and how a single JSON-RPC call to a provider is retried.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts of a call to a provider, including the first one.
+    pub max_attempts: u32,
+    /// How the response size estimate evolves when a response did not fit into it.
+    pub response_size_growth: ResponseSizeGrowth,
+    /// Errors for which the call is retried.
+    pub retry_on: fn(&HttpOutcallError) -> bool,
+}
+
+impl Default for RetryPolicy {
+    /// Retries calls whose response was too large with a doubled response size estimate,
+    /// and calls to the EVM RPC canister with too few cycles attached with the expected amount.
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            response_size_growth: ResponseSizeGrowth::Exponential,
+            retry_on: |error| error.is_response_too_large() || error.is_too_few_cycles(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The default policy with the maximum number of attempts configured in the state.
+    pub fn from_state(state: &State) -> Self {
+        Self {
+            max_attempts: state.max_rpc_call_attempts,
+            ..Self::default()
+        }
+    }
+
+    /// A policy where each call is attempted exactly once.
+    pub fn no_retry() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Returns whether a call that failed with the given error after the given number of attempts
+    /// should be attempted again.
+    pub fn should_retry(&self, attempts: u32, error: &HttpOutcallError) -> bool {
+        attempts < self.max_attempts && (self.retry_on)(error)
+    }
+}
+
+/// How the response size estimate evolves between attempts.
+/// Attempts are made right after each other, without any delay between them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResponseSizeGrowth {
+    /// Keep the same response size estimate.
+    Constant,
+    /// Double the response size estimate, up to the maximum payload size.
+    #[default]
+    Exponential,
+}
+
+impl ResponseSizeGrowth {
+    pub fn next_estimate(&self, estimate: ResponseSizeEstimate) -> ResponseSizeEstimate {
+        match self {
+            ResponseSizeGrowth::Constant => estimate,
+            ResponseSizeGrowth::Exponential => estimate.adjust(),
+        }
+    }
+}
@@ -1,13 +1,28 @@
 mod eth_rpc_client {
-    use crate::eth_rpc_client::providers::{EthereumProvider, RpcNodeProvider, SepoliaProvider};
-    use crate::eth_rpc_client::EthRpcClient;
+    use crate::endpoints::EthRpcProvider;
+    use crate::eth_rpc_client::providers::{
+        EthereumProvider, MethodCategory, ProviderSelector, RpcNodeProvider, SepoliaProvider,
+    };
+    use crate::eth_rpc_client::{EthRpcClient, RetryPolicy};
+    use crate::lifecycle::init::InitArg;
     use crate::lifecycle::EthereumNetwork;
+    use crate::state::State;
+    use assert_matches::assert_matches;
+    use candid::{Nat, Principal};
+    use evm_rpc_client::types::candid::{
+        EthSepoliaService as EvmEthSepoliaService, RpcServices as EvmRpcServices,
+    };
+    use std::collections::{BTreeMap, BTreeSet};
 
     #[test]
     fn should_retrieve_sepolia_providers_in_stable_order() {
-        let client = EthRpcClient::new(EthereumNetwork::Sepolia);
+        let client = EthRpcClient::new(
+            EthereumNetwork::Sepolia,
+            ProviderSelector::all(EthereumNetwork::Sepolia),
+            RetryPolicy::default(),
+        );
 
-        let providers = client.providers();
+        let providers = client.providers("eth_getLogs");
 
         assert_eq!(
             providers,
@@ -20,9 +35,13 @@ mod eth_rpc_client {
 
     #[test]
     fn should_retrieve_mainnet_providers_in_stable_order() {
-        let client = EthRpcClient::new(EthereumNetwork::Mainnet);
+        let client = EthRpcClient::new(
+            EthereumNetwork::Mainnet,
+            ProviderSelector::all(EthereumNetwork::Mainnet),
+            RetryPolicy::default(),
+        );
 
-        let providers = client.providers();
+        let providers = client.providers("eth_getLogs");
 
         assert_eq!(
             providers,
@@ -33,6 +52,932 @@ mod eth_rpc_client {
             ]
         );
     }
+
+    #[test]
+    fn should_select_providers_by_method() {
+        let client = EthRpcClient::new(
+            EthereumNetwork::Mainnet,
+            ProviderSelector::new(
+                vec![
+                    RpcNodeProvider::Ethereum(EthereumProvider::Ankr),
+                    RpcNodeProvider::Ethereum(EthereumProvider::PublicNode),
+                ],
+                vec![RpcNodeProvider::Ethereum(EthereumProvider::LlamaNodes)],
+            ),
+            RetryPolicy::default(),
+        );
+
+        for method in [
+            "eth_getLogs",
+            "eth_getTransactionReceipt",
+            "eth_getTransactionByHash",
+            "eth_getBlockByNumber",
+            "eth_getTransactionCount",
+        ] {
+            assert_eq!(
+                MethodCategory::of(method),
+                MethodCategory::ConsensusCritical
+            );
+            assert_eq!(
+                client.providers(method),
+                &[
+                    RpcNodeProvider::Ethereum(EthereumProvider::Ankr),
+                    RpcNodeProvider::Ethereum(EthereumProvider::PublicNode),
+                ]
+            );
+        }
+        for method in ["eth_sendRawTransaction", "eth_feeHistory"] {
+            assert_eq!(MethodCategory::of(method), MethodCategory::LatencySensitive);
+            assert_eq!(
+                client.providers(method),
+                &[RpcNodeProvider::Ethereum(EthereumProvider::LlamaNodes)]
+            );
+        }
+    }
+
+    #[test]
+    fn should_skip_disabled_providers() {
+        let mut state = mainnet_state();
+        state
+            .update_rpc_provider_status(EthRpcProvider::PublicNode, false)
+            .unwrap();
+        let client = EthRpcClient::from_state(&state);
+
+        for method in ["eth_getLogs", "eth_sendRawTransaction"] {
+            assert_eq!(
+                client.providers(method),
+                &[
+                    RpcNodeProvider::Ethereum(EthereumProvider::Ankr),
+                    RpcNodeProvider::Ethereum(EthereumProvider::LlamaNodes)
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn should_skip_disabled_providers_of_evm_rpc_canister() {
+        let mut state = mainnet_state();
+        state
+            .update_rpc_provider_status(EthRpcProvider::PublicNode, false)
+            .unwrap();
+        let selector = ProviderSelector::from_state(&state);
+
+        let services = EthereumProvider::evm_rpc_node_providers(
+            selector.providers(MethodCategory::ConsensusCritical),
+            &BTreeMap::new(),
+        );
+
+        assert_matches!(
+            services,
+            EvmRpcServices::Custom { chain_id: 1, services }
+                if services.iter().map(|api| api.url.as_str()).collect::<Vec<_>>()
+                    == vec!["https://rpc.ankr.com/eth", "https://eth.llamarpc.com"]
+        );
+        assert_eq!(
+            SepoliaProvider::evm_rpc_node_providers(&[RpcNodeProvider::Sepolia(
+                SepoliaProvider::Ankr
+            )]),
+            EvmRpcServices::EthSepolia(Some(vec![EvmEthSepoliaService::Ankr]))
+        );
+    }
+
+    #[test]
+    fn should_select_latency_sensitive_providers_from_state() {
+        let mut state = mainnet_state();
+        state.latency_sensitive_rpc_providers = BTreeSet::from([
+            RpcNodeProvider::Ethereum(EthereumProvider::PublicNode),
+            RpcNodeProvider::Ethereum(EthereumProvider::LlamaNodes),
+        ]);
+
+        let selector = ProviderSelector::from_state(&state);
+        assert_eq!(
+            selector.providers(MethodCategory::ConsensusCritical),
+            &[
+                RpcNodeProvider::Ethereum(EthereumProvider::Ankr),
+                RpcNodeProvider::Ethereum(EthereumProvider::PublicNode),
+                RpcNodeProvider::Ethereum(EthereumProvider::LlamaNodes)
+            ]
+        );
+        assert_eq!(
+            selector.providers(MethodCategory::LatencySensitive),
+            &[
+                RpcNodeProvider::Ethereum(EthereumProvider::PublicNode),
+                RpcNodeProvider::Ethereum(EthereumProvider::LlamaNodes)
+            ]
+        );
+
+        state
+            .update_rpc_provider_status(EthRpcProvider::PublicNode, false)
+            .unwrap();
+        state
+            .update_rpc_provider_status(EthRpcProvider::LlamaNodes, false)
+            .unwrap();
+        let selector = ProviderSelector::from_state(&state);
+        assert_eq!(
+            selector.providers(MethodCategory::LatencySensitive),
+            &[RpcNodeProvider::Ethereum(EthereumProvider::Ankr)],
+            "should fall back to the enabled providers when all configured ones are disabled"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "no providers for latency-sensitive methods")]
+    fn should_panic_when_no_providers() {
+        let _panic = ProviderSelector::new(
+            vec![RpcNodeProvider::Ethereum(EthereumProvider::Ankr)],
+            vec![],
+        );
+    }
+
+    fn mainnet_state() -> State {
+        State::try_from(InitArg {
+            ethereum_network: EthereumNetwork::Mainnet,
+            ecdsa_key_name: "test_key_1".to_string(),
+            ethereum_contract_address: None,
+            ledger_id: Principal::from_text("apia6-jaaaa-aaaar-qabma-cai")
+                .expect("BUG: invalid principal"),
+            ethereum_block_height: Default::default(),
+            minimum_withdrawal_amount: Nat::from(10_000_000_000_000_000_u64),
+            next_transaction_nonce: Default::default(),
+            last_scraped_block_number: Default::default(),
+        })
+        .expect("init args should be valid")
+    }
+}
+
+mod latency_stats {
+    use crate::eth_rpc_client::latency::{LatencyStats, RERANK_INTERVAL};
+    use crate::eth_rpc_client::providers::{EthereumProvider, RpcNodeProvider};
+    use std::time::Duration;
+
+    const ANKR: RpcNodeProvider = RpcNodeProvider::Ethereum(EthereumProvider::Ankr);
+    const PUBLIC_NODE: RpcNodeProvider = RpcNodeProvider::Ethereum(EthereumProvider::PublicNode);
+    const LLAMA_NODES: RpcNodeProvider = RpcNodeProvider::Ethereum(EthereumProvider::LlamaNodes);
+    const PROVIDERS: [RpcNodeProvider; 3] = [ANKR, PUBLIC_NODE, LLAMA_NODES];
+
+    #[test]
+    fn should_keep_given_order_without_observations() {
+        let mut stats = LatencyStats::default();
+
+        assert_eq!(stats.rank(&PROVIDERS, 0), PROVIDERS.to_vec());
+    }
+
+    #[test]
+    fn should_rank_providers_from_fastest_to_slowest() {
+        let mut stats = LatencyStats::default();
+        stats.observe(ANKR, Duration::from_millis(900));
+        stats.observe(PUBLIC_NODE, Duration::from_millis(500));
+        stats.observe(LLAMA_NODES, Duration::from_millis(100));
+
+        assert_eq!(
+            stats.rank(&PROVIDERS, 0),
+            vec![LLAMA_NODES, PUBLIC_NODE, ANKR]
+        );
+    }
+
+    #[test]
+    fn should_rank_unmeasured_providers_first() {
+        let mut stats = LatencyStats::default();
+        stats.observe(ANKR, Duration::from_millis(100));
+
+        assert_eq!(
+            stats.rank(&PROVIDERS, 0),
+            vec![PUBLIC_NODE, LLAMA_NODES, ANKR]
+        );
+    }
+
+    #[test]
+    fn should_only_rerank_periodically() {
+        let mut stats = LatencyStats::default();
+        stats.observe(ANKR, Duration::from_millis(100));
+        stats.observe(PUBLIC_NODE, Duration::from_millis(200));
+        assert_eq!(stats.rank(&[ANKR, PUBLIC_NODE], 0), vec![ANKR, PUBLIC_NODE]);
+
+        for _ in 0..10 {
+            stats.observe(ANKR, Duration::from_secs(10));
+        }
+        let just_before_rerank = RERANK_INTERVAL.as_nanos() as u64 - 1;
+        assert_eq!(
+            stats.rank(&[ANKR, PUBLIC_NODE], just_before_rerank),
+            vec![ANKR, PUBLIC_NODE]
+        );
+
+        let rerank = RERANK_INTERVAL.as_nanos() as u64;
+        assert_eq!(
+            stats.rank(&[ANKR, PUBLIC_NODE], rerank),
+            vec![PUBLIC_NODE, ANKR]
+        );
+    }
+
+    #[test]
+    fn should_smooth_latency_observations() {
+        let mut stats = LatencyStats::default();
+        stats.observe(ANKR, Duration::from_millis(100));
+        stats.observe(PUBLIC_NODE, Duration::from_millis(150));
+        // A single slow observation should not outweigh the history of a provider.
+        stats.observe(ANKR, Duration::from_millis(300));
+
+        assert_eq!(stats.rank(&[ANKR, PUBLIC_NODE], 0), vec![ANKR, PUBLIC_NODE]);
+    }
+}
+
+mod block_number_tracker {
+    use crate::eth_rpc::BlockTag;
+    use crate::eth_rpc_client::block_number_tracker::{HighestBlockNumbers, REORG_TOLERANCE};
+    use crate::numeric::BlockNumber;
+
+    #[test]
+    fn should_accept_increasing_block_numbers() {
+        let mut tracker = HighestBlockNumbers::default();
+
+        for block_number in [100_u128, 101, 105, 200] {
+            assert_eq!(
+                tracker.observe(BlockTag::Finalized, BlockNumber::new(block_number)),
+                Ok(())
+            );
+        }
+    }
+
+    #[test]
+    fn should_tolerate_small_regressions() {
+        let mut tracker = HighestBlockNumbers::default();
+        let highest = BlockNumber::new(1_000);
+        assert_eq!(tracker.observe(BlockTag::Latest, highest), Ok(()));
+
+        assert_eq!(
+            tracker.observe(
+                BlockTag::Latest,
+                highest.checked_sub(REORG_TOLERANCE).unwrap()
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn should_reject_regressions_beyond_reorg_tolerance() {
+        let mut tracker = HighestBlockNumbers::default();
+        let highest = BlockNumber::new(1_000);
+        assert_eq!(tracker.observe(BlockTag::Finalized, highest), Ok(()));
+
+        let stale = highest
+            .checked_sub(REORG_TOLERANCE)
+            .unwrap()
+            .checked_decrement()
+            .unwrap();
+        assert_eq!(tracker.observe(BlockTag::Finalized, stale), Err(highest));
+        // The highest block number is kept.
+        assert_eq!(tracker.observe(BlockTag::Finalized, stale), Err(highest));
+    }
+
+    #[test]
+    fn should_track_block_tags_independently() {
+        let mut tracker = HighestBlockNumbers::default();
+        assert_eq!(
+            tracker.observe(BlockTag::Latest, BlockNumber::new(1_000)),
+            Ok(())
+        );
+
+        assert_eq!(
+            tracker.observe(BlockTag::Finalized, BlockNumber::new(900)),
+            Ok(())
+        );
+    }
+}
+
+mod transport {
+    use crate::eth_rpc::{
+        Block, BlockSpec, BlockTag, GetLogsParam, Hash, HttpOutcallError, HttpOutcallResult,
+    };
+    use crate::eth_rpc_client::providers::{
+        EthereumProvider, ProviderSelector, RpcNodeProvider, RpcProviderHttpOptions,
+    };
+    use crate::eth_rpc_client::{
+        EthRpcClient, MultiCallError, ResponseSizeGrowth, RetryPolicy, RpcTransport,
+    };
+    use crate::lifecycle::init::InitArg;
+    use crate::lifecycle::EthereumNetwork;
+    use crate::numeric::{BlockNumber, Wei};
+    use crate::state::{State, STATE};
+    use assert_matches::assert_matches;
+    use candid::{Nat, Principal};
+    use futures::future::LocalBoxFuture;
+    use ic_cdk::api::call::RejectionCode;
+    use ic_cdk::api::management_canister::http_request::{
+        CanisterHttpRequestArgument, HttpHeader, HttpResponse,
+    };
+    use std::cell::{Cell, RefCell};
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    #[derive(Debug)]
+    struct MockTransport {
+        response_body: &'static str,
+        requests: RefCell<Vec<(String, String)>>,
+        headers: RefCell<Vec<Vec<HttpHeader>>>,
+    }
+
+    impl MockTransport {
+        fn new(response_body: &'static str) -> Self {
+            Self {
+                response_body,
+                requests: RefCell::default(),
+                headers: RefCell::default(),
+            }
+        }
+    }
+
+    impl RpcTransport for MockTransport {
+        fn http_request(
+            &self,
+            eth_method: String,
+            request: CanisterHttpRequestArgument,
+            _cycles: u128,
+        ) -> LocalBoxFuture<'_, HttpOutcallResult<HttpResponse>> {
+            self.requests.borrow_mut().push((eth_method, request.url));
+            self.headers.borrow_mut().push(request.headers);
+            let body = self.response_body.as_bytes().to_vec();
+            Box::pin(async move {
+                Ok(HttpResponse {
+                    status: Nat::from(200_u8),
+                    headers: vec![],
+                    body,
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn should_send_requests_through_injected_transport() {
+        init_state();
+        let transport = Arc::new(MockTransport::new(
+            r#"{"jsonrpc":"2.0","id":1,"result":{"number":"0x12d687","hash":"0xb3b20624f8f0f86eb50dd04688409e5cea4bd02d700bf6e79e9384d47d6a5a35","baseFeePerGas":"0x3b9aca00"}}"#,
+        ));
+        let client = EthRpcClient::new(
+            EthereumNetwork::Mainnet,
+            ProviderSelector::new(
+                vec![
+                    RpcNodeProvider::Ethereum(EthereumProvider::Ankr),
+                    RpcNodeProvider::Ethereum(EthereumProvider::PublicNode),
+                ],
+                vec![RpcNodeProvider::Ethereum(EthereumProvider::Ankr)],
+            ),
+            RetryPolicy::default(),
+        )
+        .with_transport(transport.clone());
+
+        let block = client
+            .eth_get_block_by_number(BlockSpec::Tag(BlockTag::Latest))
+            .await;
+
+        assert_eq!(
+            block,
+            Ok(Block {
+                number: BlockNumber::new(0x12d687),
+                hash: "0xb3b20624f8f0f86eb50dd04688409e5cea4bd02d700bf6e79e9384d47d6a5a35"
+                    .parse()
+                    .unwrap(),
+                base_fee_per_gas: Wei::new(0x3b9aca00),
+                blob_gas_used: None,
+                excess_blob_gas: None,
+            })
+        );
+        let requests = transport.requests.borrow();
+        assert_eq!(requests.len(), 2);
+        for ((method, url), provider) in requests.iter().zip([
+            RpcNodeProvider::Ethereum(EthereumProvider::Ankr),
+            RpcNodeProvider::Ethereum(EthereumProvider::PublicNode),
+        ]) {
+            assert_eq!(method, "eth_getBlockByNumber");
+            assert_eq!(url, provider.api(None).url());
+        }
+    }
+
+    #[tokio::test]
+    async fn should_send_configured_http_headers() {
+        init_state();
+        let transport = Arc::new(MockTransport::new(
+            r#"{"jsonrpc":"2.0","id":1,"result":"0x12d687"}"#,
+        ));
+        let ankr = RpcNodeProvider::Ethereum(EthereumProvider::Ankr);
+        let mut client = EthRpcClient::new(
+            EthereumNetwork::Mainnet,
+            ProviderSelector::new(vec![ankr], vec![ankr]),
+            RetryPolicy::default(),
+        )
+        .with_transport(transport.clone());
+        let authorization = HttpHeader {
+            name: "Authorization".to_string(),
+            value: "Bearer token".to_string(),
+        };
+        client.provider_http_options = BTreeMap::from([(
+            ankr,
+            RpcProviderHttpOptions {
+                headers: vec![authorization.clone()],
+                host: None,
+            },
+        )]);
+
+        let _ = client.eth_block_number().await;
+
+        let headers = transport.headers.borrow();
+        assert_eq!(headers.len(), 1);
+        assert!(headers[0].contains(&authorization));
+    }
+
+    #[tokio::test]
+    async fn should_fetch_transaction_receipts_with_bounded_concurrency() {
+        init_state();
+        let transport = Arc::new(MockTransport::new(
+            r#"{"jsonrpc":"2.0","id":1,"result":null}"#,
+        ));
+        let client = EthRpcClient::new(
+            EthereumNetwork::Mainnet,
+            ProviderSelector::all(EthereumNetwork::Mainnet),
+            RetryPolicy::default(),
+        )
+        .with_transport(transport.clone())
+        .with_max_concurrent_receipt_requests(2);
+        let hashes: Vec<_> = (1..=5_u8).map(|i| Hash([i; 32])).collect();
+
+        let receipts = client.eth_get_transaction_receipts(hashes.clone()).await;
+
+        assert_eq!(receipts.keys().copied().collect::<Vec<_>>(), hashes);
+        for result in receipts.values() {
+            assert_eq!(result, &Ok(None));
+        }
+        assert_eq!(
+            transport.requests.borrow().len(),
+            hashes.len() * client.providers("eth_getTransactionReceipt").len()
+        );
+    }
+
+    /// Fails with a transient error a given number of times before returning a block.
+    #[derive(Debug)]
+    struct FlakyTransport {
+        failures_left: Cell<u32>,
+    }
+
+    impl RpcTransport for FlakyTransport {
+        fn http_request(
+            &self,
+            _eth_method: String,
+            _request: CanisterHttpRequestArgument,
+            _cycles: u128,
+        ) -> LocalBoxFuture<'_, HttpOutcallResult<HttpResponse>> {
+            let failures_left = self.failures_left.get();
+            Box::pin(async move {
+                if failures_left > 0 {
+                    self.failures_left.set(failures_left - 1);
+                    return Err(HttpOutcallError::IcError {
+                        code: RejectionCode::SysTransient,
+                        message: "transient error".to_string(),
+                    });
+                }
+                Ok(HttpResponse {
+                    status: Nat::from(200_u8),
+                    headers: vec![],
+                    body: br#"{"jsonrpc":"2.0","id":1,"result":{"number":"0x12d687","hash":"0xb3b20624f8f0f86eb50dd04688409e5cea4bd02d700bf6e79e9384d47d6a5a35","baseFeePerGas":"0x3b9aca00"}}"#.to_vec(),
+                })
+            })
+        }
+    }
+
+    fn retry_on_transient_errors(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            response_size_growth: ResponseSizeGrowth::Constant,
+            retry_on: |error| {
+                matches!(
+                    error,
+                    HttpOutcallError::IcError {
+                        code: RejectionCode::SysTransient,
+                        ..
+                    }
+                )
+            },
+        }
+    }
+
+    fn single_provider_client(retry_policy: RetryPolicy) -> EthRpcClient {
+        let provider = RpcNodeProvider::Ethereum(EthereumProvider::Ankr);
+        EthRpcClient::new(
+            EthereumNetwork::Mainnet,
+            ProviderSelector::new(vec![provider], vec![provider]),
+            retry_policy,
+        )
+    }
+
+    #[tokio::test]
+    async fn should_retry_errors_selected_by_policy() {
+        init_state();
+        let client = single_provider_client(retry_on_transient_errors(3)).with_transport(Arc::new(
+            FlakyTransport {
+                failures_left: Cell::new(2),
+            },
+        ));
+
+        let block = client
+            .eth_get_block_by_number(BlockSpec::Number(BlockNumber::new(0x12d687)))
+            .await;
+
+        assert_eq!(block.map(|b| b.number), Ok(BlockNumber::new(0x12d687)));
+    }
+
+    #[tokio::test]
+    async fn should_stop_retrying_after_max_attempts() {
+        init_state();
+        let client = single_provider_client(retry_on_transient_errors(2)).with_transport(Arc::new(
+            FlakyTransport {
+                failures_left: Cell::new(2),
+            },
+        ));
+
+        let block = client
+            .eth_get_block_by_number(BlockSpec::Number(BlockNumber::new(0x12d687)))
+            .await;
+
+        assert_eq!(
+            block,
+            Err(MultiCallError::ConsistentHttpOutcallError(
+                HttpOutcallError::IcError {
+                    code: RejectionCode::SysTransient,
+                    message: "transient error".to_string(),
+                }
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn should_not_retry_errors_not_selected_by_policy() {
+        init_state();
+        let client = single_provider_client(RetryPolicy::default()).with_transport(Arc::new(
+            FlakyTransport {
+                failures_left: Cell::new(1),
+            },
+        ));
+
+        let block = client
+            .eth_get_block_by_number(BlockSpec::Number(BlockNumber::new(0x12d687)))
+            .await;
+
+        assert!(block.is_err());
+    }
+
+    /// Expects more cycles than attached to the first call, like the EVM RPC canister.
+    #[derive(Debug, Default)]
+    struct TooFewCyclesTransport {
+        attached_cycles: RefCell<Vec<u128>>,
+    }
+
+    const EXTRA_CYCLES: u128 = 1_000_000;
+
+    impl RpcTransport for TooFewCyclesTransport {
+        fn http_request(
+            &self,
+            _eth_method: String,
+            _request: CanisterHttpRequestArgument,
+            cycles: u128,
+        ) -> LocalBoxFuture<'_, HttpOutcallResult<HttpResponse>> {
+            let is_first_call = self.attached_cycles.borrow().is_empty();
+            self.attached_cycles.borrow_mut().push(cycles);
+            Box::pin(async move {
+                if is_first_call {
+                    return Err(HttpOutcallError::TooFewCycles {
+                        attached: cycles,
+                        expected: cycles + EXTRA_CYCLES,
+                    });
+                }
+                Ok(HttpResponse {
+                    status: Nat::from(200_u8),
+                    headers: vec![],
+                    body: br#"{"jsonrpc":"2.0","id":1,"result":{"number":"0x12d687","hash":"0xb3b20624f8f0f86eb50dd04688409e5cea4bd02d700bf6e79e9384d47d6a5a35","baseFeePerGas":"0x3b9aca00"}}"#.to_vec(),
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn should_retry_with_expected_cycles() {
+        init_state();
+        let transport = Arc::new(TooFewCyclesTransport::default());
+        let client =
+            single_provider_client(RetryPolicy::default()).with_transport(transport.clone());
+
+        let block = client
+            .eth_get_block_by_number(BlockSpec::Number(BlockNumber::new(0x12d687)))
+            .await;
+
+        assert_eq!(block.map(|b| b.number), Ok(BlockNumber::new(0x12d687)));
+        let attached_cycles = transport.attached_cycles.borrow();
+        assert_eq!(attached_cycles.len(), 2);
+        assert_eq!(attached_cycles[1], attached_cycles[0] + EXTRA_CYCLES);
+    }
+
+    /// Always fails because the response does not fit into the requested number of bytes.
+    #[derive(Debug, Default)]
+    struct TooLargeResponseTransport {
+        max_response_bytes: RefCell<Vec<u64>>,
+    }
+
+    impl RpcTransport for TooLargeResponseTransport {
+        fn http_request(
+            &self,
+            _eth_method: String,
+            request: CanisterHttpRequestArgument,
+            _cycles: u128,
+        ) -> LocalBoxFuture<'_, HttpOutcallResult<HttpResponse>> {
+            self.max_response_bytes
+                .borrow_mut()
+                .push(request.max_response_bytes.unwrap());
+            Box::pin(async move {
+                Err(HttpOutcallError::IcError {
+                    code: RejectionCode::SysFatal,
+                    message: "Http body exceeds size limit".to_string(),
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn should_not_grow_response_size_estimate_beyond_cap() {
+        init_state();
+        STATE.with(|s| {
+            s.borrow_mut()
+                .as_mut()
+                .unwrap()
+                .response_bytes_caps
+                .insert("eth_getLogs".to_string(), 4096);
+        });
+        let transport = Arc::new(TooLargeResponseTransport::default());
+        let client =
+            single_provider_client(RetryPolicy::default()).with_transport(transport.clone());
+
+        let result = client
+            .eth_get_logs(GetLogsParam {
+                from_block: BlockSpec::Number(BlockNumber::new(0x12d687)),
+                to_block: BlockSpec::Number(BlockNumber::new(0x12d687)),
+                address: vec![],
+                topics: vec![],
+            })
+            .await;
+
+        assert_matches!(
+            result,
+            Err(MultiCallError::ConsistentHttpOutcallError(error)) if error.is_response_too_large()
+        );
+        // The requested number of bytes includes 2KiB for the response headers.
+        assert_eq!(
+            *transport.max_response_bytes.borrow(),
+            vec![100 + 2048, 2048 + 2048, 4096 + 2048]
+        );
+    }
+
+    mod recording {
+        use super::{init_state, single_provider_client, MockTransport};
+        use crate::eth_rpc::{BlockSpec, HttpOutcallError};
+        use crate::eth_rpc_client::recording::{
+            self, RecordedRpcCall, RecordingTransport, ReplayTransport,
+        };
+        use crate::eth_rpc_client::{MultiCallError, RetryPolicy};
+        use crate::numeric::BlockNumber;
+        use assert_matches::assert_matches;
+        use std::sync::Arc;
+
+        const BLOCK: &str = r#"{"jsonrpc":"2.0","id":1,"result":{"number":"0x12d687","hash":"0xb3b20624f8f0f86eb50dd04688409e5cea4bd02d700bf6e79e9384d47d6a5a35","baseFeePerGas":"0x3b9aca00"}}"#;
+
+        #[tokio::test]
+        async fn should_replay_recorded_calls() {
+            init_state();
+            recording::set_recording_enabled(true);
+            let recording_client = single_provider_client(RetryPolicy::default()).with_transport(
+                Arc::new(RecordingTransport::new(Arc::new(MockTransport::new(BLOCK)))),
+            );
+            let block = BlockSpec::Number(BlockNumber::new(0x12d687));
+
+            let recorded_block = recording_client.eth_get_block_by_number(block).await;
+            recording::set_recording_enabled(false);
+
+            let recorded_calls = recording::recorded_calls();
+            assert_matches!(
+                &recorded_calls[..],
+                [RecordedRpcCall { provider, eth_method, request, status: 200, .. }]
+                    if provider == "rpc.ankr.com"
+                    && eth_method == "eth_getBlockByNumber"
+                    && request.contains(r#""id":0"#)
+            );
+
+            let replay_client =
+                single_provider_client(RetryPolicy::default()).with_transport(Arc::new(
+                    ReplayTransport::from_json(&serde_json::to_string(&recorded_calls).unwrap())
+                        .unwrap(),
+                ));
+            let replayed_block = replay_client.eth_get_block_by_number(block).await;
+
+            assert!(recorded_block.is_ok());
+            assert_eq!(replayed_block, recorded_block);
+        }
+
+        #[tokio::test]
+        async fn should_not_record_calls_when_recording_is_disabled() {
+            init_state();
+            let client = single_provider_client(RetryPolicy::default()).with_transport(Arc::new(
+                RecordingTransport::new(Arc::new(MockTransport::new(BLOCK))),
+            ));
+
+            let _ = client
+                .eth_get_block_by_number(BlockSpec::Number(BlockNumber::new(0x12d687)))
+                .await;
+
+            assert_eq!(recording::recorded_calls(), vec![]);
+        }
+
+        #[tokio::test]
+        async fn should_fail_to_replay_unknown_request() {
+            init_state();
+            recording::set_recording_enabled(true);
+            let recording_client = single_provider_client(RetryPolicy::default()).with_transport(
+                Arc::new(RecordingTransport::new(Arc::new(MockTransport::new(BLOCK)))),
+            );
+            let _ = recording_client
+                .eth_get_block_by_number(BlockSpec::Number(BlockNumber::new(0x12d687)))
+                .await;
+            recording::set_recording_enabled(false);
+
+            let replay_client = single_provider_client(RetryPolicy::default())
+                .with_transport(Arc::new(ReplayTransport::new(recording::recorded_calls())));
+            let result = replay_client
+                .eth_get_block_by_number(BlockSpec::Number(BlockNumber::new(0x12d688)))
+                .await;
+
+            assert_matches!(
+                result,
+                Err(MultiCallError::ConsistentHttpOutcallError(HttpOutcallError::IcError { message, .. }))
+                    if message.contains("no recorded response for eth_getBlockByNumber")
+            );
+        }
+    }
+
+    fn init_state() {
+        let state = State::try_from(InitArg {
+            ethereum_network: EthereumNetwork::Mainnet,
+            ecdsa_key_name: "test_key_1".to_string(),
+            ethereum_contract_address: None,
+            ledger_id: Principal::from_text("apia6-jaaaa-aaaar-qabma-cai")
+                .expect("BUG: invalid principal"),
+            ethereum_block_height: Default::default(),
+            minimum_withdrawal_amount: Nat::from(10_000_000_000_000_000_u64),
+            next_transaction_nonce: Default::default(),
+            last_scraped_block_number: Default::default(),
+        })
+        .expect("init args should be valid");
+        STATE.with(|s| *s.borrow_mut() = Some(state));
+    }
+}
+
+mod evm_rpc_transport {
+    use crate::eth_rpc::HttpOutcallError;
+    use crate::eth_rpc_client::transport::{into_http_response, EvmRpcRequestResult};
+    use candid::Nat;
+    use evm_rpc_client::types::candid::{
+        HttpOutcallError as EvmHttpOutcallError, ProviderError as EvmProviderError,
+        RpcError as EvmRpcError,
+    };
+    use ic_cdk::api::call::RejectionCode;
+    use ic_cdk::api::management_canister::http_request::HttpResponse;
+
+    #[test]
+    fn should_convert_successful_request_into_http_response() {
+        let body = r#"{"jsonrpc":"2.0","id":1,"result":"0x12d687"}"#;
+
+        assert_eq!(
+            into_http_response(EvmRpcRequestResult::Ok(body.to_string())),
+            Ok(HttpResponse {
+                status: Nat::from(200_u16),
+                headers: vec![],
+                body: body.as_bytes().to_vec(),
+            })
+        );
+    }
+
+    #[test]
+    fn should_keep_status_and_body_of_invalid_json_rpc_response() {
+        let result = EvmRpcRequestResult::Err(EvmRpcError::HttpOutcallError(
+            EvmHttpOutcallError::InvalidHttpJsonRpcResponse {
+                status: 503,
+                body: "Service Unavailable".to_string(),
+                parsing_error: None,
+            },
+        ));
+
+        assert_eq!(
+            into_http_response(result),
+            Ok(HttpResponse {
+                status: Nat::from(503_u16),
+                headers: vec![],
+                body: b"Service Unavailable".to_vec(),
+            })
+        );
+    }
+
+    #[test]
+    fn should_convert_ic_error() {
+        let result = EvmRpcRequestResult::Err(EvmRpcError::HttpOutcallError(
+            EvmHttpOutcallError::IcError {
+                code: RejectionCode::SysTransient,
+                message: "timeout".to_string(),
+            },
+        ));
+
+        assert_eq!(
+            into_http_response(result),
+            Err(HttpOutcallError::IcError {
+                code: RejectionCode::SysTransient,
+                message: "timeout".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn should_convert_other_errors_into_canister_error() {
+        let result =
+            EvmRpcRequestResult::Err(EvmRpcError::ProviderError(EvmProviderError::NoPermission));
+
+        assert_eq!(
+            into_http_response(result),
+            Err(HttpOutcallError::IcError {
+                code: RejectionCode::CanisterError,
+                message:
+                    "EVM RPC canister error: Provider error: No permission to call this provider"
+                        .to_string(),
+            })
+        );
+    }
+}
+
+mod rpc_api {
+    use crate::eth_rpc_client::providers::{
+        EthereumProvider, RpcNodeProvider, RpcProviderHttpOptions,
+    };
+    use crate::eth_rpc_client::RpcApi;
+    use ic_cdk::api::management_canister::http_request::HttpHeader;
+
+    #[test]
+    fn should_not_add_headers_for_known_providers() {
+        let api = RpcNodeProvider::Ethereum(EthereumProvider::Ankr).api(None);
+
+        assert_eq!(api.url(), "https://rpc.ankr.com/eth");
+        assert_eq!(api.http_headers(), vec![]);
+    }
+
+    #[test]
+    fn should_add_custom_headers_and_host() {
+        let api = RpcApi::new("https://10.0.0.1/rpc")
+            .with_header("Authorization", "Bearer token")
+            .with_host("eth.example.com");
+
+        assert_eq!(api.url(), "https://10.0.0.1/rpc");
+        assert_eq!(
+            api.http_headers(),
+            vec![
+                HttpHeader {
+                    name: "Authorization".to_string(),
+                    value: "Bearer token".to_string(),
+                },
+                HttpHeader {
+                    name: "Host".to_string(),
+                    value: "eth.example.com".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn should_add_configured_http_options() {
+        let options = RpcProviderHttpOptions {
+            headers: vec![HttpHeader {
+                name: "x-api-key".to_string(),
+                value: "key".to_string(),
+            }],
+            host: Some("eth.example.com".to_string()),
+        };
+
+        let api = RpcNodeProvider::Ethereum(EthereumProvider::Ankr).api(Some(&options));
+
+        assert_eq!(api.url(), "https://rpc.ankr.com/eth");
+        assert_eq!(
+            api.http_headers(),
+            vec![
+                HttpHeader {
+                    name: "x-api-key".to_string(),
+                    value: "key".to_string(),
+                },
+                HttpHeader {
+                    name: "Host".to_string(),
+                    value: "eth.example.com".to_string(),
+                },
+            ]
+        );
+    }
 }
 
 mod multi_call_results {
@@ -177,15 +1122,138 @@ mod multi_call_results {
         }
 
         #[test]
-        fn should_be_consistent_ok_result() {
-            let results: MultiCallResults<String> = MultiCallResults::from_non_empty_iter(vec![
-                (ANKR, Ok(JsonRpcResult::Result("0x01".to_string()))),
-                (PUBLIC_NODE, Ok(JsonRpcResult::Result("0x01".to_string()))),
+        fn should_be_consistent_ok_result() {
+            let results: MultiCallResults<String> = MultiCallResults::from_non_empty_iter(vec![
+                (ANKR, Ok(JsonRpcResult::Result("0x01".to_string()))),
+                (PUBLIC_NODE, Ok(JsonRpcResult::Result("0x01".to_string()))),
+            ]);
+
+            let reduced = results.clone().reduce_with_equality();
+
+            assert_eq!(reduced, Ok("0x01".to_string()));
+        }
+    }
+
+    mod reduce_with_equality_on {
+        use crate::eth_rpc::{Block, JsonRpcResult};
+        use crate::eth_rpc_client::tests::multi_call_results::{ANKR, LLAMA_NODES, PUBLIC_NODE};
+        use crate::eth_rpc_client::{MultiCallError, MultiCallResults};
+        use crate::numeric::{BlockNumber, GasAmount, Wei};
+
+        #[test]
+        fn should_be_consistent_when_projections_are_equal() {
+            let results: MultiCallResults<(u64, String)> =
+                MultiCallResults::from_non_empty_iter(vec![
+                    (ANKR, Ok(JsonRpcResult::Result((1, "ankr".to_string())))),
+                    (
+                        PUBLIC_NODE,
+                        Ok(JsonRpcResult::Result((1, "public_node".to_string()))),
+                    ),
+                    (
+                        LLAMA_NODES,
+                        Ok(JsonRpcResult::Result((1, "llama_nodes".to_string()))),
+                    ),
+                ]);
+
+            let reduced = results.reduce_with_equality_on(|(number, _extra)| *number);
+
+            assert_eq!(reduced, Ok((1, "ankr".to_string())));
+        }
+
+        #[test]
+        fn should_be_inconsistent_when_projections_differ() {
+            let results: MultiCallResults<(u64, String)> =
+                MultiCallResults::from_non_empty_iter(vec![
+                    (ANKR, Ok(JsonRpcResult::Result((1, "ankr".to_string())))),
+                    (
+                        PUBLIC_NODE,
+                        Ok(JsonRpcResult::Result((2, "public_node".to_string()))),
+                    ),
+                ]);
+
+            let reduced = results
+                .clone()
+                .reduce_with_equality_on(|(number, _extra)| *number);
+
+            assert_eq!(reduced, Err(MultiCallError::InconsistentResults(results)));
+        }
+
+        #[test]
+        fn should_fail_when_some_results_are_errors() {
+            let results: MultiCallResults<(u64, String)> =
+                MultiCallResults::from_non_empty_iter(vec![
+                    (ANKR, Ok(JsonRpcResult::Result((1, "ankr".to_string())))),
+                    (
+                        PUBLIC_NODE,
+                        Ok(JsonRpcResult::Error {
+                            code: -32700,
+                            message: "error".to_string(),
+                        }),
+                    ),
+                ]);
+
+            let reduced = results.reduce_with_equality_on(|(number, _extra)| *number);
+
+            assert_eq!(
+                reduced,
+                Err(MultiCallError::ConsistentJsonRpcError {
+                    code: -32700,
+                    message: "error".to_string()
+                })
+            );
+        }
+
+        fn block() -> Block {
+            Block {
+                number: BlockNumber::new(0x12884e1),
+                hash: "0x8f1e1a6a1bd34b48a55b5e5b5be2b0d2b49cde0f2a3c6b3b9a8c5f94e2d7c1a0"
+                    .parse()
+                    .unwrap(),
+                base_fee_per_gas: Wei::new(0x2f3a6b7c1),
+                blob_gas_used: Some(GasAmount::new(0x60000)),
+                excess_blob_gas: Some(GasAmount::new(0x4b80000)),
+            }
+        }
+
+        #[test]
+        fn should_agree_on_block_when_some_providers_omit_blob_gas_fields() {
+            let results: MultiCallResults<Block> = MultiCallResults::from_non_empty_iter(vec![
+                (ANKR, Ok(JsonRpcResult::Result(block()))),
+                (
+                    PUBLIC_NODE,
+                    Ok(JsonRpcResult::Result(Block {
+                        blob_gas_used: None,
+                        excess_blob_gas: None,
+                        ..block()
+                    })),
+                ),
+            ]);
+
+            let reduced = results.reduce_with_equality_on(Block::consensus_fields);
+
+            assert_eq!(reduced, Ok(block()));
+        }
+
+        #[test]
+        fn should_not_agree_on_block_when_hashes_differ() {
+            let results: MultiCallResults<Block> = MultiCallResults::from_non_empty_iter(vec![
+                (ANKR, Ok(JsonRpcResult::Result(block()))),
+                (
+                    PUBLIC_NODE,
+                    Ok(JsonRpcResult::Result(Block {
+                        hash: "0xb3b20624f8f0f86eb50dd04688409e5cea4bd02d700bf6e79e9384d47d6a5a35"
+                            .parse()
+                            .unwrap(),
+                        ..block()
+                    })),
+                ),
             ]);
 
-            let reduced = results.clone().reduce_with_equality();
+            let reduced = results
+                .clone()
+                .reduce_with_equality_on(Block::consensus_fields);
 
-            assert_eq!(reduced, Ok("0x01".to_string()));
+            assert_eq!(reduced, Err(MultiCallError::InconsistentResults(results)));
         }
     }
 
@@ -202,14 +1270,24 @@ mod multi_call_results {
                     ANKR,
                     Ok(JsonRpcResult::Result(Block {
                         number: BlockNumber::new(0x411cda),
+                        hash: "0x2e9a7c1f0d4b6a58c3e2f1d0b9a8c7e6f5d4c3b2a1f0e9d8c7b6a5f4e3d2c1b0"
+                            .parse()
+                            .unwrap(),
                         base_fee_per_gas: Wei::new(0x10),
+                        blob_gas_used: None,
+                        excess_blob_gas: None,
                     })),
                 ),
                 (
                     PUBLIC_NODE,
                     Ok(JsonRpcResult::Result(Block {
                         number: BlockNumber::new(0x411cd9),
+                        hash: "0x7c5e4d5a3b3f9a0f5ac2f0b4f0d5f0b1b6a7cbe2cbdf2fb4f7f4cf3d2a1b0c9d"
+                            .parse()
+                            .unwrap(),
                         base_fee_per_gas: Wei::new(0x10),
+                        blob_gas_used: None,
+                        excess_blob_gas: None,
                     })),
                 ),
             ]);
@@ -220,7 +1298,12 @@ mod multi_call_results {
                 reduced,
                 Ok(Block {
                     number: BlockNumber::new(0x411cd9),
+                    hash: "0x7c5e4d5a3b3f9a0f5ac2f0b4f0d5f0b1b6a7cbe2cbdf2fb4f7f4cf3d2a1b0c9d"
+                        .parse()
+                        .unwrap(),
                     base_fee_per_gas: Wei::new(0x10),
+                    blob_gas_used: None,
+                    excess_blob_gas: None,
                 })
             );
         }
@@ -473,6 +1556,7 @@ mod multi_call_results {
                     vec![WeiPerGas::new(0x5f5e100)],
                     vec![WeiPerGas::new(0x5f5e100)],
                 ],
+                base_fee_per_blob_gas: vec![],
             }
         }
     }
@@ -546,6 +1630,82 @@ mod multi_call_results {
     }
 }
 
+mod multi_call_report {
+    use crate::endpoints::{
+        EthRpcCallReport, EthRpcErrorBreakdown, EthRpcProviderReport, EthRpcProviderStatus,
+    };
+    use crate::eth_rpc::{HttpOutcallError, JsonRpcResult};
+    use crate::eth_rpc_client::providers::{EthereumProvider, RpcNodeProvider};
+    use crate::eth_rpc_client::MultiCallResults;
+    use ic_cdk::api::call::RejectionCode;
+
+    const ANKR: RpcNodeProvider = RpcNodeProvider::Ethereum(EthereumProvider::Ankr);
+    const PUBLIC_NODE: RpcNodeProvider = RpcNodeProvider::Ethereum(EthereumProvider::PublicNode);
+    const LLAMA_NODES: RpcNodeProvider = RpcNodeProvider::Ethereum(EthereumProvider::LlamaNodes);
+
+    #[test]
+    fn should_summarize_results_per_provider() {
+        let results: MultiCallResults<String> = MultiCallResults::from_non_empty_iter(vec![
+            (ANKR, Ok(JsonRpcResult::Result("hello".to_string()))),
+            (
+                PUBLIC_NODE,
+                Ok(JsonRpcResult::Error {
+                    code: -32700,
+                    message: "error".to_string(),
+                }),
+            ),
+            (
+                LLAMA_NODES,
+                Err(HttpOutcallError::IcError {
+                    code: RejectionCode::SysTransient,
+                    message: "transient".to_string(),
+                }),
+            ),
+        ]);
+
+        assert_eq!(results.ok_count(), 1);
+        assert_eq!(
+            results.error_breakdown(),
+            EthRpcErrorBreakdown {
+                http_outcall_errors: 1,
+                json_rpc_errors: 1,
+                evm_rpc_errors: 0,
+            }
+        );
+        assert_eq!(
+            results.into_report("eth_getLogs"),
+            EthRpcCallReport {
+                method: "eth_getLogs".to_string(),
+                ok_count: 1,
+                error_breakdown: EthRpcErrorBreakdown {
+                    http_outcall_errors: 1,
+                    json_rpc_errors: 1,
+                    evm_rpc_errors: 0,
+                },
+                providers: vec![
+                    EthRpcProviderReport {
+                        provider: "Ethereum(Ankr)".to_string(),
+                        status: EthRpcProviderStatus::Ok,
+                    },
+                    EthRpcProviderReport {
+                        provider: "Ethereum(LlamaNodes)".to_string(),
+                        status: EthRpcProviderStatus::HttpOutcallError(
+                            "IcError { code: SysTransient, message: \"transient\" }".to_string()
+                        ),
+                    },
+                    EthRpcProviderReport {
+                        provider: "Ethereum(PublicNode)".to_string(),
+                        status: EthRpcProviderStatus::JsonRpcError {
+                            code: -32700,
+                            message: "error".to_string(),
+                        },
+                    },
+                ],
+            }
+        );
+    }
+}
+
 mod eth_get_transaction_receipt {
     use crate::eth_rpc::Hash;
     use crate::eth_rpc_client::responses::{TransactionReceipt, TransactionStatus};
@@ -633,6 +1793,126 @@ mod eth_get_transaction_receipt {
     }
 }
 
+mod eth_get_transaction_by_hash {
+    use crate::eth_rpc::{Data, Hash, JsonRpcReply, JsonRpcResult, Quantity};
+    use crate::eth_rpc_client::responses::Transaction;
+    use crate::numeric::{BlockNumber, GasAmount, TransactionNonce, Wei, WeiPerGas};
+    use ic_ethereum_types::Address;
+    use std::str::FromStr;
+
+    const MINED_TRANSACTION: &str = r#"{
+        "blockHash": "0x82005d2f17b251900968f01b0ed482cb49b7e1d797342bc504904d442b64dbe4",
+        "blockNumber": "0x4132ec",
+        "from": "0x1789f79e95324a47c5fd6693071188e82e9a3558",
+        "gas": "0x5208",
+        "gasPrice": "0xfefbee3e",
+        "maxFeePerGas": "0x1d4a4c2a4",
+        "maxPriorityFeePerGas": "0x59682f00",
+        "hash": "0x0e59bd032b9b22aca5e2784e4cf114783512db00988c716cf17a1cc755a0a93d",
+        "input": "0x",
+        "nonce": "0x2a",
+        "to": "0xdd2851cdd40ae6536831558dd46db62fac7a844d",
+        "transactionIndex": "0x32",
+        "value": "0x2386f26fc10000",
+        "type": "0x2",
+        "accessList": [],
+        "chainId": "0xaa36a7",
+        "v": "0x1",
+        "r": "0x6a0a7bbd9b4a2e80a3a7bf8dbc4d4ab7dd0b2b21d0a9f4c0a3b0d5e2f1c6a7b8",
+        "s": "0x1c9d0a6e4e1f0f1bdbbd2b1b8b7e9d6a52c2e4d6f3a1b9c8d7e6f5a4b3c2d1e0",
+        "yParity": "0x1"
+    }"#;
+
+    fn expected_mined_transaction() -> Transaction {
+        Transaction {
+            hash: Hash::from_str(
+                "0x0e59bd032b9b22aca5e2784e4cf114783512db00988c716cf17a1cc755a0a93d",
+            )
+            .unwrap(),
+            transaction_type: Some(Quantity::from(2_u8)),
+            nonce: TransactionNonce::from(0x2a_u8),
+            from: Address::from_str("0x1789f79e95324a47c5fd6693071188e82e9a3558").unwrap(),
+            to: Some(Address::from_str("0xdd2851cdd40ae6536831558dd46db62fac7a844d").unwrap()),
+            value: Wei::new(0x2386f26fc10000),
+            gas: GasAmount::new(0x5208),
+            gas_price: Some(WeiPerGas::new(0xfefbee3e)),
+            max_fee_per_gas: Some(WeiPerGas::new(0x1d4a4c2a4)),
+            max_priority_fee_per_gas: Some(WeiPerGas::new(0x59682f00)),
+            input: Data(vec![]),
+            block_hash: Some(
+                Hash::from_str(
+                    "0x82005d2f17b251900968f01b0ed482cb49b7e1d797342bc504904d442b64dbe4",
+                )
+                .unwrap(),
+            ),
+            block_number: Some(BlockNumber::new(0x4132ec)),
+        }
+    }
+
+    #[test]
+    fn should_deserialize_mined_transaction() {
+        let transaction: Transaction = serde_json::from_str(MINED_TRANSACTION).unwrap();
+
+        assert_eq!(transaction, expected_mined_transaction());
+        assert!(!transaction.is_pending());
+    }
+
+    #[test]
+    fn should_deserialize_pending_transaction() {
+        let pending = MINED_TRANSACTION
+            .replace(
+                r#""0x82005d2f17b251900968f01b0ed482cb49b7e1d797342bc504904d442b64dbe4""#,
+                "null",
+            )
+            .replace(r#""0x4132ec""#, "null")
+            .replace(r#""0x32""#, "null");
+
+        let transaction: Transaction = serde_json::from_str(&pending).unwrap();
+
+        assert_eq!(
+            transaction,
+            Transaction {
+                block_hash: None,
+                block_number: None,
+                ..expected_mined_transaction()
+            }
+        );
+        assert!(transaction.is_pending());
+    }
+
+    #[test]
+    fn should_deserialize_unknown_transaction_as_none() {
+        let reply: JsonRpcReply<Option<Transaction>> =
+            serde_json::from_str(r#"{"jsonrpc":"2.0","id":1,"result":null}"#).unwrap();
+
+        assert_eq!(reply.result, JsonRpcResult::Result(None));
+    }
+
+    #[test]
+    fn should_deserialize_legacy_transaction_without_fee_market_fields() {
+        let legacy: serde_json::Value = {
+            let mut value: serde_json::Value = serde_json::from_str(MINED_TRANSACTION).unwrap();
+            let fields = value.as_object_mut().unwrap();
+            for field in ["maxFeePerGas", "maxPriorityFeePerGas", "type", "accessList"] {
+                fields.remove(field);
+            }
+            value
+        };
+
+        let transaction: Transaction = serde_json::from_value(legacy).unwrap();
+
+        assert_eq!(
+            transaction,
+            Transaction {
+                transaction_type: None,
+                max_fee_per_gas: None,
+                max_priority_fee_per_gas: None,
+                ..expected_mined_transaction()
+            }
+        );
+    }
+}
+
 mod eth_get_transaction_count {
     use crate::eth_rpc::{BlockSpec, BlockTag};
     use crate::eth_rpc_client::requests::GetTransactionCountParams;
@@ -660,6 +1940,277 @@ mod eth_get_transaction_count {
     }
 }
 
+mod eth_block_number {
+    use crate::eth_rpc::JsonRpcResult;
+    use crate::eth_rpc_client::providers::{EthereumProvider, RpcNodeProvider};
+    use crate::eth_rpc_client::MultiCallResults;
+    use crate::numeric::BlockNumber;
+
+    #[test]
+    fn should_deserialize_block_number() {
+        let block_number: BlockNumber = serde_json::from_str("\"0x12d687\"").unwrap();
+        assert_eq!(block_number, BlockNumber::new(0x12d687));
+    }
+
+    #[test]
+    fn should_serialize_empty_params() {
+        assert_eq!(serde_json::to_string(&Vec::<()>::new()).unwrap(), "[]");
+    }
+
+    #[test]
+    fn should_return_lowest_block_number() {
+        let results: MultiCallResults<BlockNumber> = MultiCallResults::from_non_empty_iter(vec![
+            (
+                RpcNodeProvider::Ethereum(EthereumProvider::Ankr),
+                Ok(JsonRpcResult::Result(BlockNumber::new(0x12d688))),
+            ),
+            (
+                RpcNodeProvider::Ethereum(EthereumProvider::PublicNode),
+                Ok(JsonRpcResult::Result(BlockNumber::new(0x12d687))),
+            ),
+        ]);
+
+        let reduced = results.reduce_with_min_by_key(|block_number| *block_number);
+
+        assert_eq!(reduced, Ok(BlockNumber::new(0x12d687)));
+    }
+}
+
+mod eth_get_code {
+    use crate::eth_rpc::{BlockSpec, BlockTag, Data};
+    use crate::eth_rpc_client::requests::GetCodeParams;
+    use ic_ethereum_types::Address;
+    use std::str::FromStr;
+
+    #[test]
+    fn should_serialize_get_code_params_as_tuple() {
+        let params = GetCodeParams {
+            address: Address::from_str("0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34").unwrap(),
+            block: BlockSpec::Tag(BlockTag::Finalized),
+        };
+        let serialized_params = serde_json::to_string(&params).unwrap();
+        assert_eq!(
+            serialized_params,
+            r#"["0xb44b5e756a894775fc32eddf3314bb1b1944dc34","finalized"]"#
+        );
+    }
+
+    #[test]
+    fn should_deserialize_code() {
+        let code: Data = serde_json::from_str("\"0x6080604052\"").unwrap();
+        assert_eq!(code, Data(vec![0x60, 0x80, 0x60, 0x40, 0x52]));
+    }
+}
+
+mod debug_trace_transaction {
+    use crate::eth_rpc::Hash;
+    use crate::eth_rpc_client::requests::{DebugTraceTransactionParams, Tracer};
+    use crate::eth_rpc_client::responses::TransactionTrace;
+    use std::str::FromStr;
+
+    #[test]
+    fn should_serialize_debug_trace_transaction_params_as_tuple() {
+        let params = DebugTraceTransactionParams {
+            transaction_hash: Hash::from_str(
+                "0x0e59bd032b9b22aca5e2784e4cf114783512db00988c716cf17a1cc755a0a93d",
+            )
+            .unwrap(),
+            tracer: Tracer::CallTracer,
+        };
+        let serialized_params = serde_json::to_string(&params).unwrap();
+        assert_eq!(
+            serialized_params,
+            r#"["0x0e59bd032b9b22aca5e2784e4cf114783512db00988c716cf17a1cc755a0a93d",{"tracer":"callTracer"}]"#
+        );
+    }
+
+    #[test]
+    fn should_deserialize_arbitrary_trace() {
+        const TRACE: &str = r#"{"from":"0x1","gas":"0x5208","type":"CALL","calls":[]}"#;
+        let trace: TransactionTrace = serde_json::from_str(TRACE).unwrap();
+        assert_eq!(
+            trace,
+            TransactionTrace(serde_json::from_str(TRACE).unwrap())
+        );
+    }
+}
+
+mod eth_send_raw_transaction {
+    use crate::eth_rpc::{HttpOutcallError, JsonRpcResult, SendRawTransactionResult};
+    use crate::eth_rpc_client::providers::{EthereumProvider, RpcNodeProvider};
+    use crate::eth_rpc_client::reduce_send_raw_transaction_results;
+    use ic_cdk::api::call::RejectionCode;
+
+    const ANKR: RpcNodeProvider = RpcNodeProvider::Ethereum(EthereumProvider::Ankr);
+    const PUBLIC_NODE: RpcNodeProvider = RpcNodeProvider::Ethereum(EthereumProvider::PublicNode);
+    const LLAMA_NODES: RpcNodeProvider = RpcNodeProvider::Ethereum(EthereumProvider::LlamaNodes);
+
+    #[test]
+    fn should_be_ok_when_providers_agree_on_accepted_transaction() {
+        let reduced = reduce_send_raw_transaction_results(vec![
+            (
+                ANKR,
+                Ok(JsonRpcResult::Result(SendRawTransactionResult::NonceTooLow)),
+            ),
+            (
+                PUBLIC_NODE,
+                Ok(JsonRpcResult::Result(SendRawTransactionResult::Ok)),
+            ),
+            (
+                LLAMA_NODES,
+                Ok(JsonRpcResult::Result(SendRawTransactionResult::Ok)),
+            ),
+        ]);
+
+        assert_eq!(
+            reduced,
+            Ok(JsonRpcResult::Result(SendRawTransactionResult::Ok))
+        );
+    }
+
+    #[test]
+    fn should_be_ok_when_single_provider_accepted_transaction_and_others_saw_nonce_too_low() {
+        let reduced = reduce_send_raw_transaction_results(vec![
+            (
+                ANKR,
+                Ok(JsonRpcResult::Result(SendRawTransactionResult::NonceTooLow)),
+            ),
+            (
+                PUBLIC_NODE,
+                Ok(JsonRpcResult::Result(SendRawTransactionResult::Ok)),
+            ),
+            (
+                LLAMA_NODES,
+                Ok(JsonRpcResult::Result(SendRawTransactionResult::NonceTooLow)),
+            ),
+        ]);
+
+        assert_eq!(
+            reduced,
+            Ok(JsonRpcResult::Result(SendRawTransactionResult::Ok))
+        );
+    }
+
+    #[test]
+    fn should_be_ok_when_single_provider_accepted_transaction_and_others_failed() {
+        let reduced = reduce_send_raw_transaction_results(vec![
+            (
+                ANKR,
+                Ok(JsonRpcResult::Result(SendRawTransactionResult::NonceTooLow)),
+            ),
+            (
+                PUBLIC_NODE,
+                Ok(JsonRpcResult::Result(SendRawTransactionResult::Ok)),
+            ),
+            (
+                LLAMA_NODES,
+                Err(HttpOutcallError::IcError {
+                    code: RejectionCode::SysTransient,
+                    message: "transient".to_string(),
+                }),
+            ),
+        ]);
+
+        assert_eq!(
+            reduced,
+            Ok(JsonRpcResult::Result(SendRawTransactionResult::Ok))
+        );
+    }
+
+    #[test]
+    fn should_be_ok_when_single_queried_provider_accepted_transaction() {
+        let reduced = reduce_send_raw_transaction_results(vec![(
+            ANKR,
+            Ok(JsonRpcResult::Result(SendRawTransactionResult::Ok)),
+        )]);
+
+        assert_eq!(
+            reduced,
+            Ok(JsonRpcResult::Result(SendRawTransactionResult::Ok))
+        );
+    }
+
+    #[test]
+    fn should_count_unsanitized_already_known_error_as_accepted() {
+        let reduced = reduce_send_raw_transaction_results(vec![
+            (
+                ANKR,
+                Ok(JsonRpcResult::Result(SendRawTransactionResult::NonceTooLow)),
+            ),
+            (
+                PUBLIC_NODE,
+                Ok(JsonRpcResult::Error {
+                    code: -32000,
+                    message: "already known".to_string(),
+                }),
+            ),
+        ]);
+
+        assert_eq!(
+            reduced,
+            Ok(JsonRpcResult::Result(SendRawTransactionResult::Ok))
+        );
+    }
+
+    #[test]
+    fn should_return_first_ok_result_when_no_provider_accepted_transaction() {
+        let reduced = reduce_send_raw_transaction_results(vec![
+            (
+                ANKR,
+                Err(HttpOutcallError::IcError {
+                    code: RejectionCode::SysTransient,
+                    message: "transient".to_string(),
+                }),
+            ),
+            (
+                PUBLIC_NODE,
+                Ok(JsonRpcResult::Result(
+                    SendRawTransactionResult::InsufficientFunds,
+                )),
+            ),
+            (
+                LLAMA_NODES,
+                Ok(JsonRpcResult::Result(SendRawTransactionResult::NonceTooLow)),
+            ),
+        ]);
+
+        assert_eq!(
+            reduced,
+            Ok(JsonRpcResult::Result(
+                SendRawTransactionResult::InsufficientFunds
+            ))
+        );
+    }
+
+    #[test]
+    fn should_return_last_error_when_all_providers_failed() {
+        let reduced = reduce_send_raw_transaction_results(vec![
+            (
+                ANKR,
+                Ok(JsonRpcResult::Error {
+                    code: -32700,
+                    message: "parse error".to_string(),
+                }),
+            ),
+            (
+                PUBLIC_NODE,
+                Err(HttpOutcallError::IcError {
+                    code: RejectionCode::SysTransient,
+                    message: "transient".to_string(),
+                }),
+            ),
+        ]);
+
+        assert_eq!(
+            reduced,
+            Err(HttpOutcallError::IcError {
+                code: RejectionCode::SysTransient,
+                message: "transient".to_string(),
+            })
+        );
+    }
+}
+
 mod evm_rpc_conversion {
     use crate::eth_rpc_client::providers::RpcNodeProvider;
     use crate::eth_rpc_client::{Block, MultiCallError};
@@ -684,7 +2235,10 @@ mod evm_rpc_conversion {
             reduced_block,
             Ok(Block {
                 number: BlockNumber::try_from(block.number).unwrap(),
+                hash: block.hash.parse().unwrap(),
                 base_fee_per_gas: Wei::try_from(block.base_fee_per_gas).unwrap(),
+                blob_gas_used: None,
+                excess_blob_gas: None,
             })
         );
     }
@@ -720,7 +2274,10 @@ mod evm_rpc_conversion {
                         )),
                         Ok(Block {
                             number: BlockNumber::try_from(block.number).unwrap(),
+                            hash: block.hash.parse().unwrap(),
                             base_fee_per_gas: Wei::try_from(block.base_fee_per_gas).unwrap(),
+                            blob_gas_used: None,
+                            excess_blob_gas: None,
                         }),
                     ),
                     (
@@ -729,7 +2286,10 @@ mod evm_rpc_conversion {
                         )),
                         Ok(Block {
                             number: BlockNumber::try_from(next_block.number).unwrap(),
+                            hash: next_block.hash.parse().unwrap(),
                             base_fee_per_gas: Wei::try_from(next_block.base_fee_per_gas).unwrap(),
+                            blob_gas_used: None,
+                            excess_blob_gas: None,
                         }),
                     ),
                 ])
@@ -762,11 +2322,37 @@ mod evm_rpc_conversion {
             reduced_block,
             Ok(Block {
                 number: BlockNumber::try_from(block.number).unwrap(),
+                hash: block.hash.parse().unwrap(),
                 base_fee_per_gas: Wei::try_from(block.base_fee_per_gas).unwrap(),
+                blob_gas_used: None,
+                excess_blob_gas: None,
             })
         );
     }
 
+    #[test]
+    fn should_be_inconsistent_when_evm_block_hashes_differ() {
+        let block = evm_rpc_block();
+        let other_block = EvmBlock {
+            hash: "0xb3b20624f8f0f86eb50dd04688409e5cea4bd02d700bf6e79e9384d47d6a5a35".to_string(),
+            ..evm_rpc_block()
+        };
+        let evm_result = EvmMultiRpcResult::Inconsistent(vec![
+            (
+                EvmRpcService::EthMainnet(EvmEthMainnetService::Alchemy),
+                Ok(block),
+            ),
+            (
+                EvmRpcService::EthMainnet(EvmEthMainnetService::Ankr),
+                Ok(other_block),
+            ),
+        ]);
+
+        let reduced_block: Result<_, _> = ReducedResult::from(evm_result).into();
+
+        assert_matches!(reduced_block, Err(MultiCallError::InconsistentResults(_)));
+    }
+
     #[test]
     fn should_fail_on_invalid_u256_nat() {
         const U256_MAX: &[u8; 64] =
@@ -0,0 +1,183 @@
+use crate::eth_rpc::{metrics, url_host, HttpOutcallError, HttpOutcallResult, MAX_PAYLOAD_SIZE};
+use candid::{CandidType, Deserialize, Nat, Principal};
+use evm_rpc_client::types::candid::{
+    HttpOutcallError as EvmHttpOutcallError, ProviderError as EvmProviderError,
+    RpcApi as EvmRpcApi, RpcError as EvmRpcError,
+};
+use futures::future::LocalBoxFuture;
+use ic_cdk::api::call::{call_with_payment128, RejectionCode};
+use ic_cdk::api::management_canister::http_request::{CanisterHttpRequestArgument, HttpResponse};
+use std::fmt::Debug;
+
+/// Executes the HTTPS outcalls issued by the [`EthRpcClient`](super::EthRpcClient).
+///
+/// The trait is object-safe so that the client can hold an `Arc<dyn RpcTransport>`,
+/// which allows swapping the transport at runtime and injecting stateful mocks in tests.
+pub trait RpcTransport: Debug {
+    /// Sends the given HTTP request for the JSON-RPC method `eth_method`,
+    /// attaching the given amount of cycles.
+    fn http_request(
+        &self,
+        eth_method: String,
+        request: CanisterHttpRequestArgument,
+        cycles: u128,
+    ) -> LocalBoxFuture<'_, HttpOutcallResult<HttpResponse>>;
+}
+
+/// Sends HTTPS outcalls directly from the minter via the management canister.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HttpsOutcallTransport;
+
+impl RpcTransport for HttpsOutcallTransport {
+    fn http_request(
+        &self,
+        eth_method: String,
+        request: CanisterHttpRequestArgument,
+        cycles: u128,
+    ) -> LocalBoxFuture<'_, HttpOutcallResult<HttpResponse>> {
+        Box::pin(async move {
+            ensure_sufficient_balance(cycles)?;
+            let provider = url_host(&request.url).to_string();
+            let result: Result<(HttpResponse,), _> = call_with_payment128(
+                Principal::management_canister(),
+                "http_request",
+                (request,),
+                cycles,
+            )
+            .await;
+            metrics::observe_cycles(
+                provider,
+                eth_method,
+                cycles,
+                ic_cdk::api::call::msg_cycles_refunded128(),
+            );
+            result
+                .map(|(response,)| response)
+                .map_err(|(code, message)| HttpOutcallError::IcError { code, message })
+        })
+    }
+}
+
+/// Sends HTTPS outcalls via the `request` endpoint of the EVM RPC canister,
+/// so that the minter can run on a subnet with constrained HTTPS outcall capacity.
+///
+/// The EVM RPC canister charges a fee on top of the cost of the HTTPS outcall.
+/// When too few cycles were attached, the call fails with [`HttpOutcallError::TooFewCycles`],
+/// so that it can be retried with the amount of cycles expected by the EVM RPC canister
+/// according to the [`RetryPolicy`](crate::eth_rpc_client::RetryPolicy) of the client.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EvmRpcTransport {
+    evm_rpc_id: Principal,
+}
+
+impl EvmRpcTransport {
+    pub fn new(evm_rpc_id: Principal) -> Self {
+        Self { evm_rpc_id }
+    }
+}
+
+/// Subset of the `RpcService` type of the EVM RPC canister used by [`EvmRpcTransport`].
+#[derive(Clone, Debug, CandidType)]
+enum EvmRpcService {
+    Custom(EvmRpcApi),
+}
+
+/// Result of the `request` endpoint of the EVM RPC canister.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub(crate) enum EvmRpcRequestResult {
+    Ok(String),
+    Err(EvmRpcError),
+}
+
+impl RpcTransport for EvmRpcTransport {
+    fn http_request(
+        &self,
+        eth_method: String,
+        request: CanisterHttpRequestArgument,
+        cycles: u128,
+    ) -> LocalBoxFuture<'_, HttpOutcallResult<HttpResponse>> {
+        Box::pin(async move {
+            let provider = url_host(&request.url).to_string();
+            // The EVM RPC canister sets the `Content-Type` header itself.
+            let headers = request
+                .headers
+                .into_iter()
+                .filter(|header| !header.name.eq_ignore_ascii_case("Content-Type"))
+                .collect();
+            let service = EvmRpcService::Custom(EvmRpcApi {
+                url: request.url,
+                headers: Some(headers),
+            });
+            let json = String::from_utf8(request.body.unwrap_or_default())
+                .expect("BUG: JSON-RPC request is not valid UTF-8");
+            let max_response_bytes = request.max_response_bytes.unwrap_or(MAX_PAYLOAD_SIZE);
+
+            ensure_sufficient_balance(cycles)?;
+            let result: Result<(EvmRpcRequestResult,), _> = call_with_payment128(
+                self.evm_rpc_id,
+                "request",
+                (service, json, max_response_bytes),
+                cycles,
+            )
+            .await;
+            metrics::observe_cycles(
+                provider,
+                eth_method,
+                cycles,
+                ic_cdk::api::call::msg_cycles_refunded128(),
+            );
+            match result {
+                Ok((EvmRpcRequestResult::Err(EvmRpcError::ProviderError(
+                    EvmProviderError::TooFewCycles { expected, .. },
+                )),))
+                    if expected > cycles =>
+                {
+                    Err(HttpOutcallError::TooFewCycles {
+                        attached: cycles,
+                        expected,
+                    })
+                }
+                Ok((result,)) => into_http_response(result),
+                Err((code, message)) => Err(HttpOutcallError::IcError { code, message }),
+            }
+        })
+    }
+}
+
+/// Converts the result of the EVM RPC canister into the HTTP response the minter would have
+/// received from the provider, so that the response is processed as for any other transport.
+pub(crate) fn into_http_response(result: EvmRpcRequestResult) -> HttpOutcallResult<HttpResponse> {
+    match result {
+        EvmRpcRequestResult::Ok(body) => Ok(HttpResponse {
+            status: Nat::from(200_u16),
+            headers: vec![],
+            body: body.into_bytes(),
+        }),
+        EvmRpcRequestResult::Err(EvmRpcError::HttpOutcallError(EvmHttpOutcallError::IcError {
+            code,
+            message,
+        })) => Err(HttpOutcallError::IcError { code, message }),
+        EvmRpcRequestResult::Err(EvmRpcError::HttpOutcallError(
+            EvmHttpOutcallError::InvalidHttpJsonRpcResponse { status, body, .. },
+        )) => Ok(HttpResponse {
+            status: Nat::from(status),
+            headers: vec![],
+            body: body.into_bytes(),
+        }),
+        EvmRpcRequestResult::Err(error) => Err(HttpOutcallError::IcError {
+            code: RejectionCode::CanisterError,
+            message: format!("EVM RPC canister error: {error}"),
+        }),
+    }
+}
+
+fn ensure_sufficient_balance(cycles: u128) -> HttpOutcallResult<()> {
+    let balance = ic_cdk::api::canister_balance128();
+    if balance < cycles {
+        return Err(HttpOutcallError::InsufficientCycles {
+            required: cycles,
+            available: balance,
+        });
+    }
+    Ok(())
+}
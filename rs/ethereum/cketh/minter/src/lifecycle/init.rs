@@ -1,5 +1,6 @@
 use crate::endpoints::CandidBlockTag;
-use crate::eth_rpc::BlockTag;
+use crate::eth_rpc::{BlockTag, DEFAULT_SUBNET_SIZE};
+use crate::eth_rpc_client::DEFAULT_MAX_ATTEMPTS;
 use crate::lifecycle::EthereumNetwork;
 use crate::numeric::{BlockNumber, TransactionNonce, Wei};
 use crate::state::transactions::EthTransactions;
@@ -73,7 +74,11 @@ impl TryFrom<InitArg> for State {
             ethereum_network,
             ecdsa_key_name,
             eth_helper_contract_address,
+            eth_helper_contract_code_hash: None,
+            verified_eth_helper_contract_address: None,
             erc20_helper_contract_address: None,
+            erc20_helper_contract_code_hash: None,
+            verified_erc20_helper_contract_address: None,
             pending_withdrawal_principals: Default::default(),
             eth_transactions: EthTransactions::new(initial_nonce),
             cketh_ledger_id: ledger_id,
@@ -92,9 +97,19 @@ impl TryFrom<InitArg> for State {
             active_tasks: Default::default(),
             http_request_counter: 0,
             last_transaction_price_estimate: None,
+            max_resubmission_fee_per_gas: None,
             ledger_suite_orchestrator_id: None,
             evm_rpc_id: None,
+            chain_backend: Default::default(),
+            send_raw_transaction_strategy: Default::default(),
+            latency_sensitive_rpc_providers: Default::default(),
+            rpc_provider_http_options: Default::default(),
+            max_rpc_call_attempts: DEFAULT_MAX_ATTEMPTS,
+            subnet_size: DEFAULT_SUBNET_SIZE,
             ckerc20_tokens: Default::default(),
+            disabled_rpc_providers: Default::default(),
+            max_response_size_per_method: Default::default(),
+            response_bytes_caps: Default::default(),
             erc20_balances: Default::default(),
         };
         state.validate_config()?;
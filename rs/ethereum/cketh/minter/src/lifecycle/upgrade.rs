@@ -1,9 +1,10 @@
-use crate::endpoints::CandidBlockTag;
+use crate::endpoints::{CandidBlockTag, EthRpcProvider};
+use crate::eth_rpc_client::{ChainBackend, SendRawTransactionStrategy};
 use crate::logs::INFO;
 use crate::state::audit::{process_event, replay_events, EventType};
 use crate::state::mutate_state;
 use crate::state::STATE;
-use crate::storage::total_event_count;
+use crate::storage::{response_sizes, total_event_count};
 use candid::{CandidType, Deserialize, Nat, Principal};
 use ic_canister_log::log;
 use minicbor::{Decode, Encode};
@@ -26,6 +27,71 @@ pub struct UpgradeArg {
     pub last_erc20_scraped_block_number: Option<Nat>,
     #[cbor(n(7), with = "crate::cbor::principal::option")]
     pub evm_rpc_id: Option<Principal>,
+    #[n(8)]
+    pub eth_helper_contract_code_hash: Option<String>,
+    #[n(10)]
+    pub response_bytes_caps: Option<Vec<ResponseBytesCap>>,
+    #[n(11)]
+    pub chain_backend: Option<ChainBackend>,
+    #[cbor(n(12), with = "crate::cbor::nat::option")]
+    pub max_resubmission_fee_per_gas: Option<Nat>,
+    #[n(13)]
+    pub send_raw_transaction_strategy: Option<SendRawTransactionStrategy>,
+    #[n(14)]
+    pub latency_sensitive_rpc_providers: Option<Vec<EthRpcProvider>>,
+    #[n(15)]
+    pub rpc_provider_http_configs: Option<Vec<RpcProviderHttpConfig>>,
+    #[n(16)]
+    pub max_rpc_call_attempts: Option<u32>,
+    #[n(17)]
+    pub subnet_size: Option<u32>,
+    #[n(18)]
+    pub erc20_helper_contract_code_hash: Option<String>,
+}
+
+impl UpgradeArg {
+    /// Returns the arguments with the values of the configured HTTP headers redacted,
+    /// since they may contain credentials that should not be publicly exposed.
+    pub fn redact_http_headers(mut self) -> Self {
+        for config in self.rpc_provider_http_configs.iter_mut().flatten() {
+            for header in config.headers.iter_mut() {
+                header.value = REDACTED_HTTP_HEADER_VALUE.to_string();
+            }
+        }
+        self
+    }
+}
+
+pub const REDACTED_HTTP_HEADER_VALUE: &str = "<redacted>";
+
+/// Hard cap on the size of the responses to a JSON-RPC method.
+#[derive(CandidType, Deserialize, Clone, Debug, Encode, Decode, PartialEq, Eq)]
+pub struct ResponseBytesCap {
+    #[n(0)]
+    pub method: String,
+    #[n(1)]
+    pub max_response_bytes: u64,
+}
+
+/// Additional HTTP options for the requests sent to a JSON-RPC provider,
+/// e.g., for providers requiring header-based authentication.
+#[derive(CandidType, Deserialize, Clone, Debug, Encode, Decode, PartialEq, Eq)]
+pub struct RpcProviderHttpConfig {
+    #[n(0)]
+    pub provider: EthRpcProvider,
+    #[n(1)]
+    pub headers: Vec<RpcHttpHeader>,
+    /// Value of the `Host` header, when it should differ from the host in the URL.
+    #[n(2)]
+    pub host: Option<String>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Encode, Decode, PartialEq, Eq)]
+pub struct RpcHttpHeader {
+    #[n(0)]
+    pub name: String,
+    #[n(1)]
+    pub value: String,
 }
 
 pub fn post_upgrade(upgrade_args: Option<UpgradeArg>) {
@@ -34,6 +100,7 @@ pub fn post_upgrade(upgrade_args: Option<UpgradeArg>) {
     STATE.with(|cell| {
         *cell.borrow_mut() = Some(replay_events());
     });
+    mutate_state(|s| s.max_response_size_per_method = response_sizes());
     if let Some(args) = upgrade_args {
         mutate_state(|s| process_event(s, EventType::Upgrade(args)))
     }
@@ -12,8 +12,9 @@ use ic_cketh_minter::endpoints::events::{
 };
 use ic_cketh_minter::endpoints::{
     AddCkErc20Token, Eip1559TransactionPrice, Eip1559TransactionPriceArg, Erc20Balance,
-    GasFeeEstimate, MinterInfo, RetrieveEthRequest, RetrieveEthStatus, WithdrawalArg,
-    WithdrawalDetail, WithdrawalError, WithdrawalSearchParameter,
+    EstimateEthRpcCallCyclesArg, EthRpcCallReport, GasFeeEstimate, MinterInfo, RetrieveEthRequest,
+    RetrieveEthStatus, UpdateRpcProviderStatusArg, WithdrawalArg, WithdrawalDetail,
+    WithdrawalError, WithdrawalSearchParameter,
 };
 use ic_cketh_minter::erc20::CkTokenSymbol;
 use ic_cketh_minter::eth_logs::{EventSource, ReceivedErc20Event, ReceivedEthEvent};
@@ -503,6 +504,89 @@ fn is_address_blocked(address_string: String) -> bool {
     ic_cketh_minter::blocklist::is_blocked(&address)
 }
 
+/// Estimates the number of cycles required for a single HTTPS outcall to a JSON-RPC provider.
+#[query]
+fn estimate_eth_rpc_call_cycles(arg: EstimateEthRpcCallCyclesArg) -> Nat {
+    use ic_cketh_minter::eth_rpc::{
+        estimate_call_cycles_cost, ResponseSizeEstimate, MAX_PAYLOAD_SIZE,
+    };
+
+    if arg.response_size_estimate == 0 || arg.response_size_estimate > MAX_PAYLOAD_SIZE {
+        ic_cdk::trap(&format!(
+            "ERROR: response size estimate must be between 1 and {MAX_PAYLOAD_SIZE} bytes"
+        ));
+    }
+    Nat::from(estimate_call_cycles_cost(
+        &arg.method,
+        arg.params_size,
+        ResponseSizeEstimate::new(arg.response_size_estimate),
+        read_state(|s| s.subnet_size),
+    ))
+}
+
+/// Returns a summary of the last responses of each provider, by JSON-RPC method.
+#[query]
+fn get_eth_rpc_diagnostics() -> Vec<EthRpcCallReport> {
+    ic_cketh_minter::eth_rpc_client::diagnostics::last_reports()
+}
+
+/// Starts or stops recording the JSON-RPC calls made to the providers.
+/// This call is restricted to the controllers of the minter.
+#[update]
+fn set_eth_rpc_recording(enabled: bool) {
+    if !ic_cdk::api::is_controller(&ic_cdk::caller()) {
+        ic_cdk::trap("ERROR: only controllers can record JSON-RPC calls");
+    }
+    ic_cketh_minter::eth_rpc_client::recording::set_recording_enabled(enabled);
+    log!(
+        INFO,
+        "[set_eth_rpc_recording]: recording enabled: {enabled}"
+    );
+}
+
+/// Returns the recorded JSON-RPC calls as a JSON array, from oldest to newest,
+/// which can be replayed in tests.
+/// This call is restricted to the controllers of the minter.
+#[query]
+fn get_recorded_eth_rpc_calls() -> String {
+    if !ic_cdk::api::is_controller(&ic_cdk::caller()) {
+        ic_cdk::trap("ERROR: only controllers can retrieve recorded JSON-RPC calls");
+    }
+    serde_json::to_string(&ic_cketh_minter::eth_rpc_client::recording::recorded_calls())
+        .expect("BUG: failed to serialize recorded calls")
+}
+
+/// Disables or re-enables a JSON-RPC provider, e.g. during a provider outage.
+/// This call is restricted to the controllers of the minter.
+#[update]
+fn update_rpc_provider_status(arg: UpdateRpcProviderStatusArg) {
+    if !ic_cdk::api::is_controller(&ic_cdk::caller()) {
+        ic_cdk::trap("ERROR: only controllers can update the status of RPC providers");
+    }
+    read_state(|s| s.validate_rpc_provider_status_update(arg.provider, arg.enabled))
+        .unwrap_or_else(|e| {
+            ic_cdk::trap(&format!(
+                "ERROR: failed to update status of RPC provider {:?}: {e:?}",
+                arg.provider
+            ))
+        });
+    mutate_state(|s| {
+        process_event(
+            s,
+            EventType::UpdatedRpcProviderStatus {
+                provider: arg.provider,
+                enabled: arg.enabled,
+            },
+        )
+    });
+    log!(
+        INFO,
+        "[update_rpc_provider_status]: provider {:?} enabled: {}",
+        arg.provider,
+        arg.enabled
+    );
+}
+
 #[update]
 async fn add_ckerc20_token(erc20_token: AddCkErc20Token) {
     let orchestrator_id = read_state(|s| s.ledger_suite_orchestrator_id)
@@ -534,10 +618,15 @@ async fn get_canister_status() -> ic_cdk::api::management_canister::main::Canist
 fn get_events(arg: GetEventsArg) -> GetEventsResult {
     use ic_cketh_minter::endpoints::events::{
         AccessListItem, ReimbursementIndex as CandidReimbursementIndex,
+        ResubmissionOutcome as CandidResubmissionOutcome,
+        StuckTransactionReason as CandidStuckTransactionReason,
         TransactionReceipt as CandidTransactionReceipt,
         TransactionStatus as CandidTransactionStatus, UnsignedTransaction,
     };
     use ic_cketh_minter::eth_rpc_client::responses::TransactionReceipt;
+    use ic_cketh_minter::state::transactions::{
+        ResubmissionAttempt, ResubmissionOutcome, StuckTransactionReason,
+    };
     use ic_cketh_minter::tx::Eip1559TransactionRequest;
     use serde_bytes::ByteBuf;
 
@@ -572,6 +661,48 @@ fn get_events(arg: GetEventsArg) -> GetEventsResult {
         }
     }
 
+    fn map_stuck_transaction_reason(
+        reason: StuckTransactionReason,
+    ) -> CandidStuckTransactionReason {
+        match reason {
+            StuckTransactionReason::BelowBaseFee {
+                max_fee_per_gas,
+                base_fee_per_gas,
+            } => CandidStuckTransactionReason::BelowBaseFee {
+                max_fee_per_gas: max_fee_per_gas.into(),
+                base_fee_per_gas: base_fee_per_gas.into(),
+            },
+            StuckTransactionReason::NotFound => CandidStuckTransactionReason::NotFound,
+            StuckTransactionReason::Unknown => CandidStuckTransactionReason::Unknown,
+        }
+    }
+
+    fn map_resubmission_outcome(outcome: ResubmissionOutcome) -> CandidResubmissionOutcome {
+        match outcome {
+            ResubmissionOutcome::Replaced {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => CandidResubmissionOutcome::Replaced {
+                max_fee_per_gas: max_fee_per_gas.into(),
+                max_priority_fee_per_gas: max_priority_fee_per_gas.into(),
+            },
+            ResubmissionOutcome::FeeCapExceeded {
+                max_fee_per_gas_cap,
+                max_fee_per_gas,
+            } => CandidResubmissionOutcome::FeeCapExceeded {
+                max_fee_per_gas_cap: max_fee_per_gas_cap.into(),
+                max_fee_per_gas: max_fee_per_gas.into(),
+            },
+            ResubmissionOutcome::InsufficientTransactionFee {
+                allowed_max_transaction_fee,
+                max_transaction_fee,
+            } => CandidResubmissionOutcome::InsufficientTransactionFee {
+                allowed_max_transaction_fee: allowed_max_transaction_fee.into(),
+                max_transaction_fee: max_transaction_fee.into(),
+            },
+        }
+    }
+
     fn map_unsigned_transaction(tx: Eip1559TransactionRequest) -> UnsignedTransaction {
         UnsignedTransaction {
             chain_id: tx.chain_id.into(),
@@ -619,7 +750,7 @@ fn get_events(arg: GetEventsArg) -> GetEventsResult {
             timestamp,
             payload: match payload {
                 EventType::Init(args) => EP::Init(args),
-                EventType::Upgrade(args) => EP::Upgrade(args),
+                EventType::Upgrade(args) => EP::Upgrade(args.redact_http_headers()),
                 EventType::AcceptedDeposit(ReceivedEthEvent {
                     transaction_hash,
                     block_number,
@@ -803,6 +934,23 @@ fn get_events(arg: GetEventsArg) -> GetEventsResult {
                 EventType::QuarantinedReimbursement { index } => EP::QuarantinedReimbursement {
                     index: map_reimbursement_index(index),
                 },
+                EventType::AttemptedTransactionResubmission {
+                    withdrawal_id,
+                    attempt:
+                        ResubmissionAttempt {
+                            nonce,
+                            reason,
+                            outcome,
+                        },
+                } => EP::AttemptedTransactionResubmission {
+                    withdrawal_id: withdrawal_id.get().into(),
+                    nonce: nonce.into(),
+                    reason: map_stuck_transaction_reason(reason),
+                    outcome: map_resubmission_outcome(outcome),
+                },
+                EventType::UpdatedRpcProviderStatus { provider, enabled } => {
+                    EP::UpdatedRpcProviderStatus { provider, enabled }
+                }
             },
         }
     }
@@ -1026,6 +1174,27 @@ fn http_request(req: HttpRequest) -> HttpResponse {
     }
 }
 
+#[cfg(feature = "debug_checks")]
+#[update]
+async fn debug_trace_transaction(transaction_hash: String) {
+    use ic_cketh_minter::eth_rpc::Hash;
+    use ic_cketh_minter::eth_rpc_client::EthRpcClient;
+    use ic_cketh_minter::lifecycle::EthereumNetwork;
+
+    if read_state(State::ethereum_network) != EthereumNetwork::Sepolia {
+        ic_cdk::trap("ERROR: transactions can only be traced on Sepolia");
+    }
+    let tx_hash = Hash::from_str(&transaction_hash)
+        .unwrap_or_else(|e| ic_cdk::trap(&format!("invalid transaction hash: {}", e)));
+    let result = read_state(EthRpcClient::from_state)
+        .debug_trace_transaction(tx_hash)
+        .await;
+    log!(
+        INFO,
+        "[debug_trace_transaction]: trace of {tx_hash}: {result:?}"
+    );
+}
+
 #[cfg(feature = "debug_checks")]
 #[query]
 fn check_audit_log() {
@@ -1,20 +1,25 @@
 use crate::address::ecdsa_public_key_to_address;
+use crate::endpoints::EthRpcProvider;
 use crate::erc20::{CkErc20Token, CkTokenSymbol};
 use crate::eth_logs::{EventSource, ReceivedEvent};
-use crate::eth_rpc::BlockTag;
+use crate::eth_rpc::{BlockTag, Hash, MAX_PAYLOAD_SIZE};
 use crate::eth_rpc_client::responses::{TransactionReceipt, TransactionStatus};
+use crate::eth_rpc_client::{
+    ChainBackend, RpcNodeProvider, RpcProviderHttpOptions, SendRawTransactionStrategy,
+};
 use crate::lifecycle::upgrade::UpgradeArg;
 use crate::lifecycle::EthereumNetwork;
 use crate::logs::DEBUG;
 use crate::map::DedupMultiKeyMap;
 use crate::numeric::{
-    BlockNumber, Erc20Value, LedgerBurnIndex, LedgerMintIndex, TransactionNonce, Wei,
+    BlockNumber, Erc20Value, LedgerBurnIndex, LedgerMintIndex, TransactionNonce, Wei, WeiPerGas,
 };
 use crate::state::transactions::{Erc20WithdrawalRequest, TransactionCallData, WithdrawalRequest};
 use crate::tx::GasFeeEstimate;
 use candid::Principal;
 use ic_canister_log::log;
 use ic_cdk::api::management_canister::ecdsa::EcdsaPublicKeyResponse;
+use ic_cdk::api::management_canister::http_request::HttpHeader;
 use ic_crypto_ecdsa_secp256k1::PublicKey;
 use ic_ethereum_types::Address;
 use std::cell::RefCell;
@@ -30,6 +35,10 @@ pub mod transactions;
 #[cfg(test)]
 mod tests;
 
+/// Time after which a learned response size no longer serves as response size estimate,
+/// so that a single unusually large response does not inflate the cost of all future calls.
+pub(crate) const LEARNED_RESPONSE_SIZE_MAX_AGE_NS: u64 = 24 * 60 * 60 * 1_000_000_000; // 1 day
+
 thread_local! {
     pub static STATE: RefCell<Option<State>> = RefCell::default();
 }
@@ -54,7 +63,17 @@ pub struct State {
     pub ecdsa_key_name: String,
     pub cketh_ledger_id: Principal,
     pub eth_helper_contract_address: Option<Address>,
+    /// Expected Keccak-256 hash of the bytecode of the ETH helper smart contract.
+    pub eth_helper_contract_code_hash: Option<Hash>,
+    /// ETH helper smart contract address whose bytecode was checked against
+    /// `eth_helper_contract_code_hash` since the last upgrade.
+    pub verified_eth_helper_contract_address: Option<Address>,
     pub erc20_helper_contract_address: Option<Address>,
+    /// Expected Keccak-256 hash of the bytecode of the ERC-20 helper smart contract.
+    pub erc20_helper_contract_code_hash: Option<Hash>,
+    /// ERC-20 helper smart contract address whose bytecode was checked against
+    /// `erc20_helper_contract_code_hash` since the last upgrade.
+    pub verified_erc20_helper_contract_address: Option<Address>,
     pub ecdsa_public_key: Option<EcdsaPublicKeyResponse>,
     pub cketh_minimum_withdrawal_amount: Wei,
     pub ethereum_block_height: BlockTag,
@@ -88,6 +107,10 @@ pub struct State {
 
     pub last_transaction_price_estimate: Option<(u64, GasFeeEstimate)>,
 
+    /// Cap on the maximum fee per gas of transactions replacing stuck transactions.
+    /// A stuck transaction is not replaced if the replacement would exceed the cap.
+    pub max_resubmission_fee_per_gas: Option<WeiPerGas>,
+
     /// Canister ID of the ledger suite orchestrator that
     /// can add new ERC-20 token to the minter
     pub ledger_suite_orchestrator_id: Option<Principal>,
@@ -96,11 +119,45 @@ pub struct State {
     /// handles communication with Ethereum
     pub evm_rpc_id: Option<Principal>,
 
+    /// Backend used to read from and write to the Ethereum blockchain.
+    pub chain_backend: ChainBackend,
+
+    /// How signed transactions are submitted to the JSON-RPC providers.
+    pub send_raw_transaction_strategy: SendRawTransactionStrategy,
+
+    /// JSON-RPC providers queried for latency-sensitive methods, such as `eth_sendRawTransaction`.
+    /// All providers are queried if empty.
+    pub latency_sensitive_rpc_providers: BTreeSet<RpcNodeProvider>,
+
+    /// Number of nodes in the subnet where the minter is deployed,
+    /// which determines the cost in cycles of HTTPS outcalls.
+    pub subnet_size: u32,
+
+    /// Maximum number of attempts of a single call to a JSON-RPC provider,
+    /// see [`RetryPolicy`](crate::eth_rpc_client::RetryPolicy).
+    pub max_rpc_call_attempts: u32,
+
+    /// Additional HTTP headers sent to the JSON-RPC providers, e.g., for authentication.
+    pub(crate) rpc_provider_http_options: BTreeMap<RpcNodeProvider, RpcProviderHttpOptions>,
+
     /// ERC-20 tokens that the minter can mint:
     /// - primary key: ledger ID for the ckERC20 token
     /// - secondary key: ERC-20 contract address on Ethereum
     /// - value: ckERC20 token symbol
     pub ckerc20_tokens: DedupMultiKeyMap<Principal, Address, CkTokenSymbol>,
+
+    /// JSON-RPC providers that were disabled at runtime, e.g. during an outage.
+    pub(crate) disabled_rpc_providers: BTreeSet<RpcNodeProvider>,
+
+    /// Largest observed response size (in bytes) for each JSON-RPC method,
+    /// together with the time (in nanoseconds) at which it was observed.
+    /// Used as response size estimate for future calls of that method until it expires.
+    /// Not derived from events: the map is kept in stable memory and restored after upgrades.
+    pub max_response_size_per_method: BTreeMap<String, (u64, u64)>,
+
+    /// Hard cap on the response size (in bytes) for some JSON-RPC methods,
+    /// which takes precedence over any response size estimate.
+    pub response_bytes_caps: BTreeMap<String, u64>,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -109,10 +166,26 @@ pub enum InvalidStateError {
     InvalidEcdsaKeyName(String),
     InvalidLedgerId(String),
     InvalidEthereumContractAddress(String),
+    InvalidEthereumContractCodeHash(String),
     InvalidErc20HelperContractAddress(String),
+    InvalidErc20HelperContractCodeHash(String),
     InvalidMinimumWithdrawalAmount(String),
     InvalidLastScrapedBlockNumber(String),
     InvalidLastErc20ScrapedBlockNumber(String),
+    InvalidChainBackend(String),
+    InvalidResponseBytesCap(String),
+    InvalidMaxResubmissionFeePerGas(String),
+    InvalidRpcProvider(String),
+    InvalidMaxRpcCallAttempts(String),
+    InvalidSubnetSize(String),
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum UpdateRpcProviderStatusError {
+    /// The provider is not available on the Ethereum network of the minter.
+    UnsupportedProvider(EthRpcProvider),
+    /// Disabling the provider would leave no provider to query.
+    NoProviderLeft,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -179,6 +252,20 @@ impl State {
                     .to_string(),
             ));
         }
+        if self.chain_backend == ChainBackend::EvmRpc && self.evm_rpc_id.is_none() {
+            return Err(InvalidStateError::InvalidChainBackend(
+                "EvmRpc chain backend requires evm_rpc_id to be set".to_string(),
+            ));
+        }
+        if let Some((method, cap)) = self
+            .response_bytes_caps
+            .iter()
+            .find(|(_method, &cap)| cap == 0 || cap > MAX_PAYLOAD_SIZE)
+        {
+            return Err(InvalidStateError::InvalidResponseBytesCap(format!(
+                "response bytes cap {cap} for {method} must be positive and at most {MAX_PAYLOAD_SIZE}"
+            )));
+        }
         Ok(())
     }
 
@@ -248,6 +335,84 @@ impl State {
             })
     }
 
+    /// Checks whether the given JSON-RPC provider can be disabled or re-enabled.
+    pub fn validate_rpc_provider_status_update(
+        &self,
+        provider: EthRpcProvider,
+        enabled: bool,
+    ) -> Result<(), UpdateRpcProviderStatusError> {
+        self.rpc_node_provider_to_update(provider, enabled)
+            .map(|_| ())
+    }
+
+    /// Disables or re-enables the given JSON-RPC provider.
+    pub(crate) fn update_rpc_provider_status(
+        &mut self,
+        provider: EthRpcProvider,
+        enabled: bool,
+    ) -> Result<(), UpdateRpcProviderStatusError> {
+        let node_provider = self.rpc_node_provider_to_update(provider, enabled)?;
+        if enabled {
+            self.disabled_rpc_providers.remove(&node_provider);
+        } else {
+            self.disabled_rpc_providers.insert(node_provider);
+        }
+        Ok(())
+    }
+
+    fn rpc_node_provider_to_update(
+        &self,
+        provider: EthRpcProvider,
+        enabled: bool,
+    ) -> Result<RpcNodeProvider, UpdateRpcProviderStatusError> {
+        let node_provider = RpcNodeProvider::from_candid(self.ethereum_network, provider)
+            .ok_or(UpdateRpcProviderStatusError::UnsupportedProvider(provider))?;
+        if !enabled {
+            let has_other_enabled_provider = RpcNodeProvider::all(self.ethereum_network)
+                .iter()
+                .any(|p| p != &node_provider && !self.disabled_rpc_providers.contains(p));
+            if !has_other_enabled_provider {
+                return Err(UpdateRpcProviderStatusError::NoProviderLeft);
+            }
+        }
+        Ok(node_provider)
+    }
+
+    /// Returns the largest response size observed for the given JSON-RPC method
+    /// that has not expired at the given time, capped by the response bytes cap of that method.
+    pub fn learned_response_size(&self, method: &str, now_ns: u64) -> Option<u64> {
+        self.max_response_size_per_method
+            .get(method)
+            .filter(|(observed_at_ns, _size)| {
+                now_ns < observed_at_ns.saturating_add(LEARNED_RESPONSE_SIZE_MAX_AGE_NS)
+            })
+            .map(|(_observed_at_ns, size)| self.capped_response_size(method, *size))
+    }
+
+    pub fn response_bytes_cap(&self, method: &str) -> Option<u64> {
+        self.response_bytes_caps.get(method).copied()
+    }
+
+    fn capped_response_size(&self, method: &str, size: u64) -> u64 {
+        match self.response_bytes_cap(method) {
+            Some(cap) => size.min(cap),
+            None => size,
+        }
+    }
+
+    /// Remembers the response size observed for the given JSON-RPC method,
+    /// if it is larger than the one learned so far or if the latter expired.
+    pub fn record_response_size(&mut self, method: String, size: u64, observed_at_ns: u64) {
+        let learned_size = self.learned_response_size(&method, observed_at_ns);
+        let size = self.capped_response_size(&method, size);
+        if learned_size.is_some_and(|learned_size| learned_size >= size) {
+            return;
+        }
+        crate::storage::record_response_size(method.clone(), observed_at_ns, size);
+        self.max_response_size_per_method
+            .insert(method, (observed_at_ns, size));
+    }
+
     /// Quarantine the deposit event to prevent double minting.
     /// WARNING!: It's crucial that this method does not panic,
     /// since it's called inside the clean-up callback, when an unexpected panic did occur before.
@@ -478,7 +643,26 @@ impl State {
             erc20_helper_contract_address,
             last_erc20_scraped_block_number,
             evm_rpc_id,
+            eth_helper_contract_code_hash,
+            response_bytes_caps,
+            chain_backend,
+            max_resubmission_fee_per_gas,
+            send_raw_transaction_strategy,
+            latency_sensitive_rpc_providers,
+            rpc_provider_http_configs,
+            max_rpc_call_attempts,
+            subnet_size,
+            erc20_helper_contract_code_hash,
         } = upgrade_args;
+        let ethereum_network = self.ethereum_network;
+        let to_rpc_node_provider = |provider: EthRpcProvider| {
+            RpcNodeProvider::from_candid(ethereum_network, provider).ok_or_else(|| {
+                InvalidStateError::InvalidRpcProvider(format!(
+                    "ERROR: {:?} is not supported on {}",
+                    provider, ethereum_network
+                ))
+            })
+        };
         if let Some(nonce) = next_transaction_nonce {
             let nonce = TransactionNonce::try_from(nonce)
                 .map_err(|e| InvalidStateError::InvalidTransactionNonce(format!("ERROR: {}", e)))?;
@@ -496,12 +680,24 @@ impl State {
             })?;
             self.eth_helper_contract_address = Some(eth_helper_contract_address);
         }
+        if let Some(code_hash) = eth_helper_contract_code_hash {
+            let code_hash = Hash::from_str(&code_hash).map_err(|e| {
+                InvalidStateError::InvalidEthereumContractCodeHash(format!("ERROR: {}", e))
+            })?;
+            self.eth_helper_contract_code_hash = Some(code_hash);
+        }
         if let Some(address) = erc20_helper_contract_address {
             let erc20_helper_contract_address = Address::from_str(&address).map_err(|e| {
                 InvalidStateError::InvalidErc20HelperContractAddress(format!("ERROR: {}", e))
             })?;
             self.erc20_helper_contract_address = Some(erc20_helper_contract_address);
         }
+        if let Some(code_hash) = erc20_helper_contract_code_hash {
+            let code_hash = Hash::from_str(&code_hash).map_err(|e| {
+                InvalidStateError::InvalidErc20HelperContractCodeHash(format!("ERROR: {}", e))
+            })?;
+            self.erc20_helper_contract_code_hash = Some(code_hash);
+        }
         if let Some(block_number) = last_erc20_scraped_block_number {
             self.last_erc20_scraped_block_number =
                 BlockNumber::try_from(block_number).map_err(|e| {
@@ -517,6 +713,65 @@ impl State {
         if let Some(evm_id) = evm_rpc_id {
             self.evm_rpc_id = Some(evm_id);
         }
+        if let Some(chain_backend) = chain_backend {
+            self.chain_backend = chain_backend;
+        }
+        if let Some(strategy) = send_raw_transaction_strategy {
+            self.send_raw_transaction_strategy = strategy;
+        }
+        if let Some(providers) = latency_sensitive_rpc_providers {
+            self.latency_sensitive_rpc_providers = providers
+                .into_iter()
+                .map(to_rpc_node_provider)
+                .collect::<Result<_, _>>()?;
+        }
+        if let Some(subnet_size) = subnet_size {
+            if subnet_size == 0 {
+                return Err(InvalidStateError::InvalidSubnetSize(
+                    "ERROR: subnet must have at least one node".to_string(),
+                ));
+            }
+            self.subnet_size = subnet_size;
+        }
+        if let Some(max_attempts) = max_rpc_call_attempts {
+            if max_attempts == 0 {
+                return Err(InvalidStateError::InvalidMaxRpcCallAttempts(
+                    "ERROR: at least one attempt is required".to_string(),
+                ));
+            }
+            self.max_rpc_call_attempts = max_attempts;
+        }
+        if let Some(configs) = rpc_provider_http_configs {
+            self.rpc_provider_http_options = configs
+                .into_iter()
+                .map(|config| {
+                    let options = RpcProviderHttpOptions {
+                        headers: config
+                            .headers
+                            .into_iter()
+                            .map(|header| HttpHeader {
+                                name: header.name,
+                                value: header.value,
+                            })
+                            .collect(),
+                        host: config.host,
+                    };
+                    to_rpc_node_provider(config.provider).map(|provider| (provider, options))
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        if let Some(cap) = max_resubmission_fee_per_gas {
+            let cap = WeiPerGas::try_from(cap).map_err(|e| {
+                InvalidStateError::InvalidMaxResubmissionFeePerGas(format!("ERROR: {}", e))
+            })?;
+            self.max_resubmission_fee_per_gas = Some(cap);
+        }
+        if let Some(caps) = response_bytes_caps {
+            self.response_bytes_caps = caps
+                .into_iter()
+                .map(|cap| (cap.method, cap.max_response_bytes))
+                .collect();
+        }
         self.validate_config()
     }
 
@@ -538,6 +793,14 @@ impl State {
             self.eth_helper_contract_address,
             other.eth_helper_contract_address
         );
+        ensure_eq!(
+            self.eth_helper_contract_code_hash,
+            other.eth_helper_contract_code_hash
+        );
+        ensure_eq!(
+            self.erc20_helper_contract_code_hash,
+            other.erc20_helper_contract_code_hash
+        );
         ensure_eq!(
             self.cketh_minimum_withdrawal_amount,
             other.cketh_minimum_withdrawal_amount
@@ -559,6 +822,27 @@ impl State {
             other.ledger_suite_orchestrator_id
         );
         ensure_eq!(self.ckerc20_tokens, other.ckerc20_tokens);
+        ensure_eq!(self.chain_backend, other.chain_backend);
+        ensure_eq!(
+            self.send_raw_transaction_strategy,
+            other.send_raw_transaction_strategy
+        );
+        ensure_eq!(
+            self.latency_sensitive_rpc_providers,
+            other.latency_sensitive_rpc_providers
+        );
+        ensure_eq!(
+            self.rpc_provider_http_options,
+            other.rpc_provider_http_options
+        );
+        ensure_eq!(self.max_rpc_call_attempts, other.max_rpc_call_attempts);
+        ensure_eq!(self.disabled_rpc_providers, other.disabled_rpc_providers);
+        ensure_eq!(self.subnet_size, other.subnet_size);
+        ensure_eq!(
+            self.max_resubmission_fee_per_gas,
+            other.max_resubmission_fee_per_gas
+        );
+        ensure_eq!(self.response_bytes_caps, other.response_bytes_caps);
 
         self.eth_transactions
             .is_equivalent_to(&other.eth_transactions)
@@ -152,6 +152,21 @@ pub fn apply_state_transition(state: &mut State, payload: &EventType) {
                 .eth_transactions
                 .record_quarantined_reimbursement(index.clone());
         }
+        EventType::AttemptedTransactionResubmission {
+            withdrawal_id,
+            attempt,
+        } => {
+            state
+                .eth_transactions
+                .record_resubmission_attempt(*withdrawal_id, attempt.clone());
+        }
+        EventType::UpdatedRpcProviderStatus { provider, enabled } => {
+            state
+                .update_rpc_provider_status(*provider, *enabled)
+                .unwrap_or_else(|e| {
+                    panic!("BUG: failed to update status of RPC provider {provider:?}: {e:?}")
+                });
+        }
     }
 }
 
@@ -7,7 +7,8 @@ use crate::lifecycle::EthereumNetwork;
 use crate::numeric::Wei;
 use crate::state::audit::{replay_events_internal, Event};
 use crate::state::transactions::{
-    Erc20WithdrawalRequest, Reimbursed, ReimbursementIndex, ReimbursementRequest, Subaccount,
+    Erc20WithdrawalRequest, Reimbursed, ReimbursementIndex, ReimbursementRequest,
+    ResubmissionAttempt, ResubmissionOutcome, StuckTransactionReason, Subaccount,
 };
 use crate::tx::{
     AccessList, AccessListItem, Eip1559TransactionRequest, SignedEip1559TransactionRequest,
@@ -90,6 +91,8 @@ impl GetEventsFile {
         use crate::endpoints::events::{
             AccessListItem as CandidAccessListItem, EventSource as CandidEventSource,
             ReimbursementIndex as CandidReimbursementIndex,
+            ResubmissionOutcome as CandidResubmissionOutcome,
+            StuckTransactionReason as CandidStuckTransactionReason,
             TransactionStatus as CandidTransactionStatus,
         };
         use crate::eth_logs::EventSource;
@@ -449,6 +452,56 @@ impl GetEventsFile {
                 EventPayload::QuarantinedReimbursement { index } => ET::QuarantinedReimbursement {
                     index: map_reimbursement_index(index),
                 },
+                EventPayload::AttemptedTransactionResubmission {
+                    withdrawal_id,
+                    nonce,
+                    reason,
+                    outcome,
+                } => ET::AttemptedTransactionResubmission {
+                    withdrawal_id: map_nat(withdrawal_id),
+                    attempt: ResubmissionAttempt {
+                        nonce: nonce.try_into().unwrap(),
+                        reason: match reason {
+                            CandidStuckTransactionReason::BelowBaseFee {
+                                max_fee_per_gas,
+                                base_fee_per_gas,
+                            } => StuckTransactionReason::BelowBaseFee {
+                                max_fee_per_gas: max_fee_per_gas.try_into().unwrap(),
+                                base_fee_per_gas: base_fee_per_gas.try_into().unwrap(),
+                            },
+                            CandidStuckTransactionReason::NotFound => {
+                                StuckTransactionReason::NotFound
+                            }
+                        },
+                        outcome: match outcome {
+                            CandidResubmissionOutcome::Replaced {
+                                max_fee_per_gas,
+                                max_priority_fee_per_gas,
+                            } => ResubmissionOutcome::Replaced {
+                                max_fee_per_gas: max_fee_per_gas.try_into().unwrap(),
+                                max_priority_fee_per_gas: max_priority_fee_per_gas
+                                    .try_into()
+                                    .unwrap(),
+                            },
+                            CandidResubmissionOutcome::FeeCapExceeded {
+                                max_fee_per_gas_cap,
+                                max_fee_per_gas,
+                            } => ResubmissionOutcome::FeeCapExceeded {
+                                max_fee_per_gas_cap: max_fee_per_gas_cap.try_into().unwrap(),
+                                max_fee_per_gas: max_fee_per_gas.try_into().unwrap(),
+                            },
+                            CandidResubmissionOutcome::InsufficientTransactionFee {
+                                allowed_max_transaction_fee,
+                                max_transaction_fee,
+                            } => ResubmissionOutcome::InsufficientTransactionFee {
+                                allowed_max_transaction_fee: allowed_max_transaction_fee
+                                    .try_into()
+                                    .unwrap(),
+                                max_transaction_fee: max_transaction_fee.try_into().unwrap(),
+                            },
+                        },
+                    },
+                },
             },
         }
     }
@@ -1,3 +1,4 @@
+use crate::endpoints::EthRpcProvider;
 use crate::erc20::CkErc20Token;
 use crate::eth_logs::{EventSource, ReceivedErc20Event, ReceivedEthEvent, ReceivedEvent};
 use crate::eth_rpc_client::responses::TransactionReceipt;
@@ -5,7 +6,7 @@ use crate::lifecycle::{init::InitArg, upgrade::UpgradeArg};
 use crate::numeric::{BlockNumber, LedgerBurnIndex, LedgerMintIndex};
 use crate::state::transactions::{
     Erc20WithdrawalRequest, EthWithdrawalRequest, Reimbursed, ReimbursementIndex,
-    ReimbursementRequest,
+    ReimbursementRequest, ResubmissionAttempt,
 };
 use crate::tx::{Eip1559TransactionRequest, SignedEip1559TransactionRequest};
 use candid::Principal;
@@ -164,6 +165,24 @@ pub enum EventType {
         #[n(1)]
         block_number: BlockNumber,
     },
+    /// The minter attempted to replace a stuck transaction.
+    /// A successful attempt is followed by a [`EventType::ReplacedTransaction`] event.
+    #[n(25)]
+    AttemptedTransactionResubmission {
+        /// The withdrawal identifier.
+        #[cbor(n(0), with = "crate::cbor::id")]
+        withdrawal_id: LedgerBurnIndex,
+        #[n(1)]
+        attempt: ResubmissionAttempt,
+    },
+    /// A controller disabled or re-enabled a JSON-RPC provider.
+    #[n(26)]
+    UpdatedRpcProviderStatus {
+        #[n(0)]
+        provider: EthRpcProvider,
+        #[n(1)]
+        enabled: bool,
+    },
 }
 
 impl ReceivedEvent {
@@ -1,10 +1,13 @@
 use crate::checked_amount::CheckedAmountOf;
-use crate::endpoints::CandidBlockTag;
+use crate::endpoints::{CandidBlockTag, EthRpcProvider};
 use crate::eth_logs::{EventSource, ReceivedErc20Event, ReceivedEthEvent, ReceivedEvent};
 use crate::eth_rpc::{BlockTag, Hash};
 use crate::eth_rpc_client::responses::{TransactionReceipt, TransactionStatus};
+use crate::eth_rpc_client::{ChainBackend, SendRawTransactionStrategy};
 use crate::lifecycle::init::InitArg;
-use crate::lifecycle::upgrade::UpgradeArg;
+use crate::lifecycle::upgrade::{
+    ResponseBytesCap, RpcHttpHeader, RpcProviderHttpConfig, UpgradeArg,
+};
 use crate::lifecycle::EthereumNetwork;
 use crate::map::DedupMultiKeyMap;
 use crate::numeric::{
@@ -13,7 +16,10 @@ use crate::numeric::{
 };
 use crate::state::audit::apply_state_transition;
 use crate::state::event::{Event, EventType};
-use crate::state::transactions::{Erc20WithdrawalRequest, ReimbursementIndex};
+use crate::state::transactions::{
+    Erc20WithdrawalRequest, ReimbursementIndex, ResubmissionAttempt, ResubmissionOutcome,
+    StuckTransactionReason,
+};
 use crate::state::{Erc20Balances, State};
 use crate::tx::{
     AccessList, AccessListItem, Eip1559Signature, Eip1559TransactionRequest, GasFeeEstimate,
@@ -65,6 +71,178 @@ fn initial_state() -> State {
     .expect("init args should be valid")
 }
 
+mod rpc_provider_status {
+    use crate::endpoints::EthRpcProvider;
+    use crate::eth_rpc_client::RpcNodeProvider;
+    use crate::lifecycle::EthereumNetwork;
+    use crate::state::audit::apply_state_transition;
+    use crate::state::event::EventType;
+    use crate::state::tests::initial_state;
+    use crate::state::UpdateRpcProviderStatusError;
+
+    #[test]
+    fn should_disable_and_re_enable_provider() {
+        let mut state = initial_state();
+        state.ethereum_network = EthereumNetwork::Mainnet;
+
+        assert_eq!(
+            state.update_rpc_provider_status(EthRpcProvider::Ankr, false),
+            Ok(())
+        );
+        assert_eq!(
+            state.disabled_rpc_providers.iter().collect::<Vec<_>>(),
+            vec![&RpcNodeProvider::all(EthereumNetwork::Mainnet)[0]]
+        );
+
+        assert_eq!(
+            state.update_rpc_provider_status(EthRpcProvider::Ankr, true),
+            Ok(())
+        );
+        assert!(state.disabled_rpc_providers.is_empty());
+    }
+
+    #[test]
+    fn should_update_provider_status_from_event() {
+        let mut state = initial_state();
+        state.ethereum_network = EthereumNetwork::Mainnet;
+
+        apply_state_transition(
+            &mut state,
+            &EventType::UpdatedRpcProviderStatus {
+                provider: EthRpcProvider::Ankr,
+                enabled: false,
+            },
+        );
+
+        assert_eq!(
+            state.disabled_rpc_providers.iter().collect::<Vec<_>>(),
+            vec![&RpcNodeProvider::all(EthereumNetwork::Mainnet)[0]]
+        );
+    }
+
+    #[test]
+    fn should_not_disable_last_provider() {
+        let mut state = initial_state();
+        state.ethereum_network = EthereumNetwork::Mainnet;
+
+        assert_eq!(
+            state.update_rpc_provider_status(EthRpcProvider::Ankr, false),
+            Ok(())
+        );
+        assert_eq!(
+            state.update_rpc_provider_status(EthRpcProvider::PublicNode, false),
+            Ok(())
+        );
+        assert_eq!(
+            state.update_rpc_provider_status(EthRpcProvider::LlamaNodes, false),
+            Err(UpdateRpcProviderStatusError::NoProviderLeft)
+        );
+        assert_eq!(state.disabled_rpc_providers.len(), 2);
+    }
+
+    #[test]
+    fn should_reject_unsupported_provider() {
+        let mut state = initial_state();
+        state.ethereum_network = EthereumNetwork::Sepolia;
+
+        assert_eq!(
+            state.update_rpc_provider_status(EthRpcProvider::LlamaNodes, false),
+            Err(UpdateRpcProviderStatusError::UnsupportedProvider(
+                EthRpcProvider::LlamaNodes
+            ))
+        );
+    }
+}
+
+mod response_size {
+    use crate::state::tests::initial_state;
+    use crate::state::{State, LEARNED_RESPONSE_SIZE_MAX_AGE_NS};
+    use crate::storage;
+
+    const NOW_NS: u64 = 1_700_000_000_000_000_000;
+
+    fn observe_response_size(state: &mut State, size: u64, observed_at: u64) {
+        state.record_response_size("eth_getLogs".to_string(), size, observed_at);
+    }
+
+    #[test]
+    fn should_remember_largest_observed_response_size() {
+        let mut state = initial_state();
+        assert_eq!(state.learned_response_size("eth_getLogs", NOW_NS), None);
+
+        for size in [4096, 16384, 8192] {
+            observe_response_size(&mut state, size, NOW_NS);
+        }
+
+        assert_eq!(
+            state.learned_response_size("eth_getLogs", NOW_NS),
+            Some(16384)
+        );
+        assert_eq!(
+            state.learned_response_size("eth_getBlockByNumber", NOW_NS),
+            None
+        );
+    }
+
+    #[test]
+    fn should_keep_learned_response_size_in_stable_memory() {
+        let mut state = initial_state();
+        for size in [4096, 16384, 8192] {
+            observe_response_size(&mut state, size, NOW_NS);
+        }
+
+        assert_eq!(
+            storage::response_sizes(),
+            state.max_response_size_per_method
+        );
+        assert_eq!(
+            storage::response_sizes().get("eth_getLogs"),
+            Some(&(NOW_NS, 16384))
+        );
+    }
+
+    #[test]
+    fn should_forget_expired_response_size() {
+        let mut state = initial_state();
+        observe_response_size(&mut state, 16384, NOW_NS);
+
+        let expired_at = NOW_NS + LEARNED_RESPONSE_SIZE_MAX_AGE_NS;
+        assert_eq!(
+            state.learned_response_size("eth_getLogs", expired_at - 1),
+            Some(16384)
+        );
+        assert_eq!(state.learned_response_size("eth_getLogs", expired_at), None);
+
+        observe_response_size(&mut state, 8192, expired_at);
+        assert_eq!(
+            state.learned_response_size("eth_getLogs", expired_at),
+            Some(8192)
+        );
+    }
+
+    #[test]
+    fn should_cap_learned_response_size() {
+        let mut state = initial_state();
+        state
+            .response_bytes_caps
+            .insert("eth_getLogs".to_string(), 10_000);
+
+        observe_response_size(&mut state, 16384, NOW_NS);
+        assert_eq!(
+            state.learned_response_size("eth_getLogs", NOW_NS),
+            Some(10_000)
+        );
+
+        state
+            .response_bytes_caps
+            .insert("eth_getLogs".to_string(), 5_000);
+        assert_eq!(
+            state.learned_response_size("eth_getLogs", NOW_NS),
+            Some(5_000)
+        );
+    }
+}
+
 mod mint_transaction {
     use crate::eth_logs::{EventSourceError, ReceivedEthEvent};
     use crate::lifecycle::EthereumNetwork;
@@ -291,16 +469,26 @@ fn received_erc20_event() -> ReceivedErc20Event {
 }
 
 mod upgrade {
-    use crate::eth_rpc::BlockTag;
-    use crate::lifecycle::upgrade::UpgradeArg;
+    use crate::endpoints::EthRpcProvider;
+    use crate::eth_rpc::MAX_PAYLOAD_SIZE;
+    use crate::eth_rpc_client::{
+        ChainBackend, RpcNodeProvider, RpcProviderHttpOptions, DEFAULT_MAX_ATTEMPTS,
+    };
+    use crate::eth_rpc_client::{RpcNodeProvider, RpcProviderHttpOptions, DEFAULT_MAX_ATTEMPTS};
+    use crate::lifecycle::upgrade::{
+        ResponseBytesCap, RpcHttpHeader, RpcProviderHttpConfig, UpgradeArg,
+        REDACTED_HTTP_HEADER_VALUE,
+    };
     use crate::lifecycle::EthereumNetwork;
-    use crate::numeric::{TransactionNonce, Wei};
+    use crate::numeric::{TransactionNonce, Wei, WeiPerGas};
     use crate::state::tests::initial_state;
     use crate::state::InvalidStateError;
     use assert_matches::assert_matches;
-    use candid::Nat;
+    use candid::{Nat, Principal};
+    use ic_cdk::api::management_canister::http_request::HttpHeader;
     use ic_ethereum_types::Address;
     use num_bigint::BigUint;
+    use std::collections::{BTreeMap, BTreeSet};
     use std::str::FromStr;
 
     #[test]
@@ -364,6 +552,292 @@ mod upgrade {
             }),
             Err(InvalidStateError::InvalidEthereumContractAddress(_))
         );
+
+        let mut state = initial_state();
+        assert_matches!(
+            state.upgrade(UpgradeArg {
+                eth_helper_contract_code_hash: Some("0x1234".to_string()),
+                ..Default::default()
+            }),
+            Err(InvalidStateError::InvalidEthereumContractCodeHash(_))
+        );
+
+        let mut state = initial_state();
+        assert_matches!(
+            state.upgrade(UpgradeArg {
+                erc20_helper_contract_code_hash: Some("0x1234".to_string()),
+                ..Default::default()
+            }),
+            Err(InvalidStateError::InvalidErc20HelperContractCodeHash(_))
+        );
+
+        let mut state = initial_state();
+        assert_matches!(
+            state.upgrade(UpgradeArg {
+                chain_backend: Some(ChainBackend::EvmRpc),
+                ..Default::default()
+            }),
+            Err(InvalidStateError::InvalidChainBackend(_))
+        );
+
+        for invalid_cap in [0, MAX_PAYLOAD_SIZE + 1] {
+            let mut state = initial_state();
+            assert_matches!(
+                state.upgrade(UpgradeArg {
+                    response_bytes_caps: Some(vec![ResponseBytesCap {
+                        method: "eth_getLogs".to_string(),
+                        max_response_bytes: invalid_cap,
+                    }]),
+                    ..Default::default()
+                }),
+                Err(InvalidStateError::InvalidResponseBytesCap(_))
+            );
+        }
+    }
+
+    #[test]
+    fn should_replace_response_bytes_caps() {
+        let mut state = initial_state();
+        let cap = |method: &str, max_response_bytes: u64| ResponseBytesCap {
+            method: method.to_string(),
+            max_response_bytes,
+        };
+
+        assert_eq!(
+            state.upgrade(UpgradeArg {
+                response_bytes_caps: Some(vec![
+                    cap("eth_getLogs", 100_000),
+                    cap("eth_getBlockByNumber", 50_000)
+                ]),
+                ..Default::default()
+            }),
+            Ok(())
+        );
+        assert_eq!(state.response_bytes_cap("eth_getLogs"), Some(100_000));
+        assert_eq!(
+            state.response_bytes_cap("eth_getBlockByNumber"),
+            Some(50_000)
+        );
+        assert_eq!(state.response_bytes_cap("eth_feeHistory"), None);
+
+        assert_eq!(
+            state.upgrade(UpgradeArg {
+                response_bytes_caps: Some(vec![cap("eth_feeHistory", 1_000)]),
+                ..Default::default()
+            }),
+            Ok(())
+        );
+        assert_eq!(state.response_bytes_cap("eth_getLogs"), None);
+        assert_eq!(state.response_bytes_cap("eth_feeHistory"), Some(1_000));
+
+        assert_eq!(state.upgrade(UpgradeArg::default()), Ok(()));
+        assert_eq!(state.response_bytes_cap("eth_feeHistory"), Some(1_000));
+    }
+
+    #[test]
+    fn should_route_http_outcalls_through_evm_rpc_canister() {
+        let mut state = initial_state();
+        let evm_rpc_id = Principal::from_text("7hfb6-caaaa-aaaar-qadga-cai").unwrap();
+
+        assert_eq!(
+            state.upgrade(UpgradeArg {
+                evm_rpc_id: Some(evm_rpc_id),
+                chain_backend: Some(ChainBackend::EvmRpc),
+                ..Default::default()
+            }),
+            Ok(())
+        );
+        assert_eq!(state.chain_backend, ChainBackend::EvmRpc);
+
+        assert_eq!(
+            state.upgrade(UpgradeArg {
+                chain_backend: Some(ChainBackend::JsonRpc),
+                ..Default::default()
+            }),
+            Ok(())
+        );
+        assert_eq!(state.chain_backend, ChainBackend::JsonRpc);
+        assert_eq!(state.evm_rpc_id, Some(evm_rpc_id));
+    }
+
+    #[test]
+    fn should_set_max_resubmission_fee_per_gas() {
+        let mut state = initial_state();
+        assert_eq!(state.max_resubmission_fee_per_gas, None);
+
+        assert_eq!(
+            state.upgrade(UpgradeArg {
+                max_resubmission_fee_per_gas: Some(Nat::from(500_000_000_000_u64)),
+                ..Default::default()
+            }),
+            Ok(())
+        );
+        assert_eq!(
+            state.max_resubmission_fee_per_gas,
+            Some(WeiPerGas::new(500_000_000_000))
+        );
+
+        assert_eq!(state.upgrade(UpgradeArg::default()), Ok(()));
+        assert_eq!(
+            state.max_resubmission_fee_per_gas,
+            Some(WeiPerGas::new(500_000_000_000))
+        );
+
+        let mut state = initial_state();
+        assert_matches!(
+            state.upgrade(UpgradeArg {
+                max_resubmission_fee_per_gas: Some(Nat(BigUint::from_bytes_be(
+                    &ethnum::u256::MAX.to_be_bytes(),
+                ) + 1_u8)),
+                ..Default::default()
+            }),
+            Err(InvalidStateError::InvalidMaxResubmissionFeePerGas(_))
+        );
+    }
+
+    #[test]
+    fn should_set_latency_sensitive_rpc_providers() {
+        let mut state = initial_state();
+        state.ethereum_network = EthereumNetwork::Mainnet;
+        assert!(state.latency_sensitive_rpc_providers.is_empty());
+
+        assert_eq!(
+            state.upgrade(UpgradeArg {
+                latency_sensitive_rpc_providers: Some(vec![
+                    EthRpcProvider::LlamaNodes,
+                    EthRpcProvider::Ankr
+                ]),
+                ..Default::default()
+            }),
+            Ok(())
+        );
+        assert_eq!(
+            state.latency_sensitive_rpc_providers,
+            [EthRpcProvider::Ankr, EthRpcProvider::LlamaNodes]
+                .into_iter()
+                .map(|p| RpcNodeProvider::from_candid(EthereumNetwork::Mainnet, p).unwrap())
+                .collect::<BTreeSet<_>>()
+        );
+
+        assert_eq!(
+            state.upgrade(UpgradeArg {
+                latency_sensitive_rpc_providers: Some(vec![]),
+                ..Default::default()
+            }),
+            Ok(())
+        );
+        assert!(state.latency_sensitive_rpc_providers.is_empty());
+    }
+
+    #[test]
+    fn should_fail_when_latency_sensitive_rpc_provider_unsupported() {
+        let mut state = initial_state();
+        state.ethereum_network = EthereumNetwork::Sepolia;
+
+        assert_matches!(
+            state.upgrade(UpgradeArg {
+                latency_sensitive_rpc_providers: Some(vec![EthRpcProvider::LlamaNodes]),
+                ..Default::default()
+            }),
+            Err(InvalidStateError::InvalidRpcProvider(_))
+        );
+    }
+
+    #[test]
+    fn should_set_max_rpc_call_attempts() {
+        let mut state = initial_state();
+        assert_eq!(state.max_rpc_call_attempts, DEFAULT_MAX_ATTEMPTS);
+
+        assert_eq!(
+            state.upgrade(UpgradeArg {
+                max_rpc_call_attempts: Some(3),
+                ..Default::default()
+            }),
+            Ok(())
+        );
+        assert_eq!(state.max_rpc_call_attempts, 3);
+
+        assert_matches!(
+            state.upgrade(UpgradeArg {
+                max_rpc_call_attempts: Some(0),
+                ..Default::default()
+            }),
+            Err(InvalidStateError::InvalidMaxRpcCallAttempts(_))
+        );
+    }
+
+    #[test]
+    fn should_set_subnet_size() {
+        let mut state = initial_state();
+        assert_eq!(state.subnet_size, DEFAULT_SUBNET_SIZE);
+
+        assert_eq!(
+            state.upgrade(UpgradeArg {
+                subnet_size: Some(13),
+                ..Default::default()
+            }),
+            Ok(())
+        );
+        assert_eq!(state.subnet_size, 13);
+
+        assert_matches!(
+            state.upgrade(UpgradeArg {
+                subnet_size: Some(0),
+                ..Default::default()
+            }),
+            Err(InvalidStateError::InvalidSubnetSize(_))
+        );
+    }
+
+    #[test]
+    fn should_set_rpc_provider_http_options() {
+        let mut state = initial_state();
+        state.ethereum_network = EthereumNetwork::Mainnet;
+        let config = RpcProviderHttpConfig {
+            provider: EthRpcProvider::PublicNode,
+            headers: vec![RpcHttpHeader {
+                name: "Authorization".to_string(),
+                value: "Bearer token".to_string(),
+            }],
+            host: Some("eth.example.com".to_string()),
+        };
+
+        assert_eq!(
+            state.upgrade(UpgradeArg {
+                rpc_provider_http_configs: Some(vec![config.clone()]),
+                ..Default::default()
+            }),
+            Ok(())
+        );
+        let public_node =
+            RpcNodeProvider::from_candid(EthereumNetwork::Mainnet, EthRpcProvider::PublicNode)
+                .unwrap();
+        assert_eq!(
+            state.rpc_provider_http_options,
+            BTreeMap::from([(
+                public_node,
+                RpcProviderHttpOptions {
+                    headers: vec![HttpHeader {
+                        name: "Authorization".to_string(),
+                        value: "Bearer token".to_string(),
+                    }],
+                    host: Some("eth.example.com".to_string()),
+                }
+            )])
+        );
+
+        let redacted = UpgradeArg {
+            rpc_provider_http_configs: Some(vec![config]),
+            ..Default::default()
+        }
+        .redact_http_headers();
+        assert_eq!(
+            redacted.rpc_provider_http_configs.unwrap()[0].headers,
+            vec![RpcHttpHeader {
+                name: "Authorization".to_string(),
+                value: REDACTED_HTTP_HEADER_VALUE.to_string(),
+            }]
+        );
     }
 
     #[test]
@@ -377,6 +851,12 @@ mod upgrade {
                 "0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34".to_string(),
             ),
             ethereum_block_height: Some(CandidBlockTag::Safe),
+            eth_helper_contract_code_hash: Some(
+                "0x1a2b3c4d5e6f708192a3b4c5d6e7f8091a2b3c4d5e6f708192a3b4c5d6e7f809".to_string(),
+            ),
+            erc20_helper_contract_code_hash: Some(
+                "0x9f8e7d6c5b4a39281706f5e4d3c2b1a09f8e7d6c5b4a39281706f5e4d3c2b1a0".to_string(),
+            ),
             ..Default::default()
         };
 
@@ -395,6 +875,24 @@ mod upgrade {
             Some(Address::from_str("0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34").unwrap())
         );
         assert_eq!(state.ethereum_block_height, BlockTag::Safe);
+        assert_eq!(
+            state.eth_helper_contract_code_hash,
+            Some(
+                Hash::from_str(
+                    "0x1a2b3c4d5e6f708192a3b4c5d6e7f8091a2b3c4d5e6f708192a3b4c5d6e7f809"
+                )
+                .unwrap()
+            )
+        );
+        assert_eq!(
+            state.erc20_helper_contract_code_hash,
+            Some(
+                Hash::from_str(
+                    "0x9f8e7d6c5b4a39281706f5e4d3c2b1a09f8e7d6c5b4a39281706f5e4d3c2b1a0"
+                )
+                .unwrap()
+            )
+        );
     }
 }
 
@@ -566,6 +1064,13 @@ fn arb_nat() -> impl Strategy<Value = Nat> {
     any::<u128>().prop_map(Nat::from)
 }
 
+fn arb_response_bytes_cap() -> impl Strategy<Value = ResponseBytesCap> {
+    (".*", any::<u64>()).prop_map(|(method, max_response_bytes)| ResponseBytesCap {
+        method,
+        max_response_bytes,
+    })
+}
+
 fn arb_storage_key() -> impl Strategy<Value = StorageKey> {
     uniform32(any::<u8>()).prop_map(StorageKey)
 }
@@ -616,6 +1121,22 @@ prop_compose! {
         erc20_helper_contract_address in proptest::option::of(arb_address()),
         last_erc20_scraped_block_number in proptest::option::of(arb_nat()),
         evm_rpc_id in proptest::option::of(arb_principal()),
+        eth_helper_contract_code_hash in proptest::option::of(arb_hash()),
+        response_bytes_caps in proptest::option::of(pvec(arb_response_bytes_cap(), 0..10)),
+        chain_backend in proptest::option::of(prop_oneof![
+            Just(ChainBackend::JsonRpc),
+            Just(ChainBackend::EvmRpc),
+        ]),
+        max_resubmission_fee_per_gas in proptest::option::of(arb_nat()),
+        send_raw_transaction_strategy in proptest::option::of(prop_oneof![
+            Just(SendRawTransactionStrategy::SequentialUntilOk),
+            Just(SendRawTransactionStrategy::Parallel),
+        ]),
+        latency_sensitive_rpc_providers in proptest::option::of(pvec(arb_eth_rpc_provider(), 0..3)),
+        rpc_provider_http_configs in proptest::option::of(pvec(arb_rpc_provider_http_config(), 0..3)),
+        max_rpc_call_attempts in proptest::option::of(any::<u32>()),
+        subnet_size in proptest::option::of(any::<u32>()),
+        erc20_helper_contract_code_hash in proptest::option::of(arb_hash()),
     ) -> UpgradeArg {
         UpgradeArg {
             ethereum_contract_address: contract_address.map(|addr| addr.to_string()),
@@ -625,7 +1146,42 @@ prop_compose! {
             ledger_suite_orchestrator_id,
             erc20_helper_contract_address: erc20_helper_contract_address.map(|addr| addr.to_string()),
             last_erc20_scraped_block_number,
-            evm_rpc_id
+            evm_rpc_id,
+            eth_helper_contract_code_hash: eth_helper_contract_code_hash.map(|hash| hash.to_string()),
+            response_bytes_caps,
+            chain_backend,
+            max_resubmission_fee_per_gas,
+            send_raw_transaction_strategy,
+            latency_sensitive_rpc_providers,
+            rpc_provider_http_configs,
+            max_rpc_call_attempts,
+            subnet_size,
+            erc20_helper_contract_code_hash: erc20_helper_contract_code_hash.map(|hash| hash.to_string()),
+        }
+    }
+}
+
+fn arb_eth_rpc_provider() -> impl Strategy<Value = EthRpcProvider> {
+    prop_oneof![
+        Just(EthRpcProvider::Ankr),
+        Just(EthRpcProvider::PublicNode),
+        Just(EthRpcProvider::LlamaNodes),
+    ]
+}
+
+prop_compose! {
+    fn arb_rpc_provider_http_config()(
+        provider in arb_eth_rpc_provider(),
+        headers in pvec(("[a-zA-Z-]{1,20}", "[a-zA-Z0-9 ]{0,40}"), 0..3),
+        host in proptest::option::of("[a-z]{1,10}\\.com"),
+    ) -> RpcProviderHttpConfig {
+        RpcProviderHttpConfig {
+            provider,
+            headers: headers
+                .into_iter()
+                .map(|(name, value)| RpcHttpHeader { name, value })
+                .collect(),
+            host,
         }
     }
 }
@@ -786,9 +1342,60 @@ fn arb_event_type() -> impl Strategy<Value = EventType> {
                 transaction_receipt,
             }
         }),
+        (any::<u64>(), arb_resubmission_attempt()).prop_map(|(withdrawal_id, attempt)| {
+            EventType::AttemptedTransactionResubmission {
+                withdrawal_id: withdrawal_id.into(),
+                attempt,
+            }
+        }),
+        (arb_eth_rpc_provider(), any::<bool>()).prop_map(|(provider, enabled)| {
+            EventType::UpdatedRpcProviderStatus { provider, enabled }
+        }),
     ]
 }
 
+fn arb_resubmission_attempt() -> impl Strategy<Value = ResubmissionAttempt> {
+    let arb_reason = prop_oneof![
+        (arb_checked_amount_of(), arb_checked_amount_of()).prop_map(
+            |(max_fee_per_gas, base_fee_per_gas)| StuckTransactionReason::BelowBaseFee {
+                max_fee_per_gas,
+                base_fee_per_gas,
+            }
+        ),
+        Just(StuckTransactionReason::NotFound),
+        Just(StuckTransactionReason::Unknown),
+    ];
+    let arb_outcome = prop_oneof![
+        (arb_checked_amount_of(), arb_checked_amount_of()).prop_map(
+            |(max_fee_per_gas, max_priority_fee_per_gas)| ResubmissionOutcome::Replaced {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            }
+        ),
+        (arb_checked_amount_of(), arb_checked_amount_of()).prop_map(
+            |(max_fee_per_gas_cap, max_fee_per_gas)| ResubmissionOutcome::FeeCapExceeded {
+                max_fee_per_gas_cap,
+                max_fee_per_gas,
+            }
+        ),
+        (arb_checked_amount_of(), arb_checked_amount_of()).prop_map(
+            |(allowed_max_transaction_fee, max_transaction_fee)| {
+                ResubmissionOutcome::InsufficientTransactionFee {
+                    allowed_max_transaction_fee,
+                    max_transaction_fee,
+                }
+            }
+        ),
+    ];
+    (arb_checked_amount_of(), arb_reason, arb_outcome).prop_map(|(nonce, reason, outcome)| {
+        ResubmissionAttempt {
+            nonce,
+            reason,
+            outcome,
+        }
+    })
+}
+
 fn arb_event() -> impl Strategy<Value = Event> {
     (any::<u64>(), arb_event_type()).prop_map(|(timestamp, payload)| Event { timestamp, payload })
 }
@@ -982,6 +1589,16 @@ fn state_equivalence() {
                 burn_in_block: LedgerBurnIndex::new(6),
             }),
         },
+        resubmission_attempts: btreemap! {
+            LedgerBurnIndex::new(4) => vec![ResubmissionAttempt {
+                nonce: TransactionNonce::new(1),
+                reason: StuckTransactionReason::NotFound,
+                outcome: ResubmissionOutcome::Replaced {
+                    max_fee_per_gas: WeiPerGas::new(110_000_000),
+                    max_priority_fee_per_gas: WeiPerGas::new(110_000_000),
+                },
+            }],
+        },
     };
     let mut ckerc20_tokens = DedupMultiKeyMap::default();
     ckerc20_tokens
@@ -1002,11 +1619,15 @@ fn state_equivalence() {
                 .parse()
                 .unwrap(),
         ),
+        eth_helper_contract_code_hash: None,
+        verified_eth_helper_contract_address: None,
         erc20_helper_contract_address: Some(
             "0xe1788e4834c896f1932188645cc36c54d1b80ac1"
                 .parse()
                 .unwrap(),
         ),
+        erc20_helper_contract_code_hash: None,
+        verified_erc20_helper_contract_address: None,
         ecdsa_public_key: Some(EcdsaPublicKeyResponse {
             public_key: vec![1; 32],
             chain_code: vec![2; 32],
@@ -1053,9 +1674,23 @@ fn state_equivalence() {
         erc20_balances: Default::default(),
         skipped_blocks: Default::default(),
         last_transaction_price_estimate: None,
+        max_resubmission_fee_per_gas: Some(WeiPerGas::new(500_000_000_000)),
         ledger_suite_orchestrator_id: Some("2s5qh-7aaaa-aaaar-qadya-cai".parse().unwrap()),
         evm_rpc_id: Some("7hfb6-caaaa-aaaar-qadga-cai".parse().unwrap()),
+        chain_backend: ChainBackend::JsonRpc,
+        send_raw_transaction_strategy: SendRawTransactionStrategy::Parallel,
+        latency_sensitive_rpc_providers: Default::default(),
+        rpc_provider_http_options: Default::default(),
+        max_rpc_call_attempts: 20,
+        subnet_size: 34,
+        response_bytes_caps: btreemap! {
+            "eth_getLogs".to_string() => 1_000_000,
+        },
         ckerc20_tokens,
+        disabled_rpc_providers: Default::default(),
+        max_response_size_per_method: btreemap! {
+            "eth_getLogs".to_string() => (1_700_000_000_000_000_000, 8192),
+        },
     };
 
     assert_eq!(
@@ -1064,11 +1699,25 @@ fn state_equivalence() {
             ecdsa_public_key: None,
             last_observed_block_number: None,
             http_request_counter: 0,
+            verified_eth_helper_contract_address: state.eth_helper_contract_address,
+            verified_erc20_helper_contract_address: state.erc20_helper_contract_address,
+            max_response_size_per_method: Default::default(),
             ..state.clone()
         }),
         "changing only computed/transient fields should result in an equivalent state",
     );
 
+    assert_ne!(
+        Ok(()),
+        state.is_equivalent_to(&State {
+            disabled_rpc_providers: btreeset! {
+                crate::eth_rpc_client::RpcNodeProvider::all(EthereumNetwork::Mainnet)[0]
+            },
+            ..state.clone()
+        }),
+        "changing essential fields should break equivalence",
+    );
+
     assert_ne!(
         Ok(()),
         state.is_equivalent_to(&State {
@@ -1191,6 +1840,18 @@ fn state_equivalence() {
         "changing the transactions should break equivalence"
     );
 
+    assert_ne!(
+        Ok(()),
+        state.is_equivalent_to(&State {
+            eth_transactions: EthTransactions {
+                resubmission_attempts: Default::default(),
+                ..eth_transactions.clone()
+            },
+            ..state.clone()
+        }),
+        "changing the resubmission attempts should break equivalence"
+    );
+
     assert_ne!(
         Ok(()),
         state.is_equivalent_to(&State {
@@ -1295,6 +1956,33 @@ fn state_equivalence() {
         }),
         "changing essential fields should break equivalence",
     );
+
+    assert_ne!(
+        Ok(()),
+        state.is_equivalent_to(&State {
+            chain_backend: ChainBackend::EvmRpc,
+            ..state.clone()
+        }),
+        "changing essential fields should break equivalence",
+    );
+
+    assert_ne!(
+        Ok(()),
+        state.is_equivalent_to(&State {
+            response_bytes_caps: Default::default(),
+            ..state.clone()
+        }),
+        "changing essential fields should break equivalence",
+    );
+
+    assert_ne!(
+        Ok(()),
+        state.is_equivalent_to(&State {
+            max_resubmission_fee_per_gas: None,
+            ..state.clone()
+        }),
+        "changing essential fields should break equivalence",
+    );
 }
 
 mod eth_balance {
@@ -3,13 +3,14 @@ mod tests;
 
 use crate::endpoints::{EthTransaction, RetrieveEthStatus, TxFinalizedStatus, WithdrawalStatus};
 use crate::eth_rpc::Hash;
+use crate::eth_rpc_client::responses::Transaction;
 use crate::eth_rpc_client::responses::TransactionReceipt;
 use crate::eth_rpc_client::responses::TransactionStatus;
 use crate::lifecycle::EthereumNetwork;
 use crate::map::MultiKeyMap;
 use crate::numeric::{
     CkTokenAmount, Erc20Value, GasAmount, LedgerBurnIndex, LedgerMintIndex, TransactionCount,
-    TransactionNonce, Wei,
+    TransactionNonce, Wei, WeiPerGas,
 };
 use crate::state::event::EventType;
 use crate::tx::{
@@ -341,6 +342,123 @@ impl fmt::Debug for Erc20WithdrawalRequest {
     }
 }
 
+/// Why a sent transaction is considered stuck and needs to be replaced.
+#[derive(Clone, Debug, Eq, PartialEq, Encode, Decode)]
+pub enum StuckTransactionReason {
+    /// The transaction is pending, but its maximum fee per gas
+    /// does not cover the current base fee per gas.
+    #[n(0)]
+    BelowBaseFee {
+        #[n(0)]
+        max_fee_per_gas: WeiPerGas,
+        #[n(1)]
+        base_fee_per_gas: WeiPerGas,
+    },
+    /// The transaction is unknown to the JSON-RPC providers,
+    /// e.g., because it was dropped from the mempool.
+    #[n(1)]
+    NotFound,
+    /// The transaction was not mined in time, but the JSON-RPC providers
+    /// could not tell why, e.g., because the lookup failed.
+    #[n(2)]
+    Unknown,
+}
+
+impl StuckTransactionReason {
+    /// Returns why the last sent transaction `sent_tx` is stuck, given the transaction with the
+    /// same hash returned by `eth_getTransactionByHash`, or `None` if it is not stuck.
+    pub fn of(
+        sent_tx: &Eip1559TransactionRequest,
+        observed_tx: Option<&Transaction>,
+        current_gas_fee: &GasFeeEstimate,
+    ) -> Option<Self> {
+        match observed_tx {
+            None => Some(StuckTransactionReason::NotFound),
+            Some(tx) if tx.is_pending() => (sent_tx.max_fee_per_gas
+                < current_gas_fee.base_fee_per_gas)
+                .then_some(StuckTransactionReason::BelowBaseFee {
+                    max_fee_per_gas: sent_tx.max_fee_per_gas,
+                    base_fee_per_gas: current_gas_fee.base_fee_per_gas,
+                }),
+            // The transaction was mined and will be finalized eventually.
+            Some(_) => None,
+        }
+    }
+}
+
+/// Outcome of an attempt to replace a stuck transaction.
+#[derive(Clone, Debug, Eq, PartialEq, Encode, Decode)]
+pub enum ResubmissionOutcome {
+    /// The stuck transaction was replaced by a transaction with the given fees.
+    #[n(0)]
+    Replaced {
+        #[n(0)]
+        max_fee_per_gas: WeiPerGas,
+        #[n(1)]
+        max_priority_fee_per_gas: WeiPerGas,
+    },
+    /// The replacement transaction would exceed the configured cap on the maximum fee per gas.
+    #[n(1)]
+    FeeCapExceeded {
+        #[n(0)]
+        max_fee_per_gas_cap: WeiPerGas,
+        #[n(1)]
+        max_fee_per_gas: WeiPerGas,
+    },
+    /// The withdrawal does not cover the transaction fee of the replacement transaction.
+    #[n(2)]
+    InsufficientTransactionFee {
+        #[n(0)]
+        allowed_max_transaction_fee: Wei,
+        #[n(1)]
+        max_transaction_fee: Wei,
+    },
+}
+
+impl ResubmissionOutcome {
+    /// Returns the outcome of replacing a stuck transaction by `new_tx`,
+    /// subject to the cap `max_fee_per_gas_cap` if any.
+    pub fn of(new_tx: &Eip1559TransactionRequest, max_fee_per_gas_cap: Option<WeiPerGas>) -> Self {
+        match max_fee_per_gas_cap {
+            Some(cap) if new_tx.max_fee_per_gas > cap => ResubmissionOutcome::FeeCapExceeded {
+                max_fee_per_gas_cap: cap,
+                max_fee_per_gas: new_tx.max_fee_per_gas,
+            },
+            _ => ResubmissionOutcome::Replaced {
+                max_fee_per_gas: new_tx.max_fee_per_gas,
+                max_priority_fee_per_gas: new_tx.max_priority_fee_per_gas,
+            },
+        }
+    }
+
+    pub fn is_replaced(&self) -> bool {
+        matches!(self, ResubmissionOutcome::Replaced { .. })
+    }
+}
+
+/// An attempt to replace a stuck transaction, kept for auditability.
+/// The time of the attempt is the timestamp of the corresponding event.
+#[derive(Clone, Debug, Eq, PartialEq, Encode, Decode)]
+pub struct ResubmissionAttempt {
+    /// The nonce of the stuck transaction.
+    #[n(0)]
+    pub nonce: TransactionNonce,
+    #[n(1)]
+    pub reason: StuckTransactionReason,
+    #[n(2)]
+    pub outcome: ResubmissionOutcome,
+}
+
+impl ResubmissionAttempt {
+    /// Returns true if both attempts failed for the same nonce in the same way,
+    /// regardless of the fees involved, which change with every block.
+    pub fn is_same_failure_as(&self, other: &Self) -> bool {
+        !self.outcome.is_replaced()
+            && self.nonce == other.nonce
+            && std::mem::discriminant(&self.outcome) == std::mem::discriminant(&other.outcome)
+    }
+}
+
 /// State machine holding Ethereum transactions issued by the minter.
 /// Overall the transaction lifecycle is as follows:
 /// 1. The user's withdrawal request is enqueued and processed in a FIFO order.
@@ -372,6 +490,8 @@ pub struct EthTransactions {
     pub(in crate::state) maybe_reimburse: BTreeSet<LedgerBurnIndex>,
     pub(in crate::state) reimbursement_requests: BTreeMap<ReimbursementIndex, ReimbursementRequest>,
     pub(in crate::state) reimbursed: BTreeMap<ReimbursementIndex, ReimbursedResult>,
+    // Attempts to replace stuck transactions, per withdrawal.
+    pub(in crate::state) resubmission_attempts: BTreeMap<LedgerBurnIndex, Vec<ResubmissionAttempt>>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -405,6 +525,7 @@ impl EthTransactions {
             maybe_reimburse: Default::default(),
             reimbursement_requests: Default::default(),
             reimbursed: Default::default(),
+            resubmission_attempts: Default::default(),
         }
     }
 
@@ -646,6 +767,37 @@ impl EthTransactions {
         );
     }
 
+    pub fn record_resubmission_attempt(
+        &mut self,
+        withdrawal_id: LedgerBurnIndex,
+        attempt: ResubmissionAttempt,
+    ) {
+        self.resubmission_attempts
+            .entry(withdrawal_id)
+            .or_default()
+            .push(attempt);
+    }
+
+    /// Returns the attempts to replace the stuck transactions of the given withdrawal,
+    /// from the oldest to the most recent.
+    pub fn resubmission_attempts(&self, withdrawal_id: &LedgerBurnIndex) -> &[ResubmissionAttempt] {
+        self.resubmission_attempts
+            .get(withdrawal_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Returns the last sent transaction with the given nonce, if any.
+    pub fn last_sent_transaction(
+        &self,
+        nonce: &TransactionNonce,
+    ) -> Option<&SignedEip1559TransactionRequest> {
+        self.sent_tx
+            .get(nonce)
+            .and_then(|sent_txs| sent_txs.last())
+            .map(|tx| tx.as_ref())
+    }
+
     pub fn sent_transactions_to_finalize(
         &self,
         finalized_transaction_count: &TransactionCount,
@@ -1077,6 +1229,7 @@ impl EthTransactions {
         ensure_eq!(self.maybe_reimburse, other.maybe_reimburse);
         ensure_eq!(self.reimbursement_requests, other.reimbursement_requests);
         ensure_eq!(self.reimbursed, other.reimbursed);
+        ensure_eq!(self.resubmission_attempts, other.resubmission_attempts);
 
         Ok(())
     }
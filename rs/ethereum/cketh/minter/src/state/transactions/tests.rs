@@ -2116,6 +2116,171 @@ mod eth_transactions {
     }
 }
 
+mod resubmission {
+    use crate::eth_rpc::{Data, Hash};
+    use crate::eth_rpc_client::responses::Transaction;
+    use crate::numeric::{BlockNumber, TransactionNonce, Wei, WeiPerGas};
+    use crate::state::transactions::tests::{
+        eip_1559_transaction_request_with_nonce, gas_fee_estimate,
+    };
+    use crate::state::transactions::{
+        ResubmissionAttempt, ResubmissionOutcome, StuckTransactionReason,
+    };
+    use crate::tx::{Eip1559TransactionRequest, GasFeeEstimate};
+    use ic_ethereum_types::Address;
+
+    #[test]
+    fn should_be_stuck_when_transaction_is_not_found() {
+        let sent_tx = eip_1559_transaction_request_with_nonce(TransactionNonce::ZERO);
+
+        assert_eq!(
+            StuckTransactionReason::of(&sent_tx, None, &gas_fee_estimate()),
+            Some(StuckTransactionReason::NotFound)
+        );
+    }
+
+    #[test]
+    fn should_be_stuck_when_pending_below_base_fee() {
+        let sent_tx = eip_1559_transaction_request_with_nonce(TransactionNonce::ZERO);
+        let current_gas_fee = GasFeeEstimate {
+            base_fee_per_gas: sent_tx.max_fee_per_gas.checked_increment().unwrap(),
+            ..gas_fee_estimate()
+        };
+
+        assert_eq!(
+            StuckTransactionReason::of(
+                &sent_tx,
+                Some(&observed_transaction(&sent_tx, None)),
+                &current_gas_fee
+            ),
+            Some(StuckTransactionReason::BelowBaseFee {
+                max_fee_per_gas: sent_tx.max_fee_per_gas,
+                base_fee_per_gas: current_gas_fee.base_fee_per_gas,
+            })
+        );
+    }
+
+    #[test]
+    fn should_not_be_stuck_when_pending_above_base_fee() {
+        let sent_tx = eip_1559_transaction_request_with_nonce(TransactionNonce::ZERO);
+        let current_gas_fee = GasFeeEstimate {
+            base_fee_per_gas: sent_tx.max_fee_per_gas,
+            ..gas_fee_estimate()
+        };
+
+        assert_eq!(
+            StuckTransactionReason::of(
+                &sent_tx,
+                Some(&observed_transaction(&sent_tx, None)),
+                &current_gas_fee
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn should_not_be_stuck_when_mined() {
+        let sent_tx = eip_1559_transaction_request_with_nonce(TransactionNonce::ZERO);
+        let current_gas_fee = GasFeeEstimate {
+            base_fee_per_gas: sent_tx.max_fee_per_gas.checked_increment().unwrap(),
+            ..gas_fee_estimate()
+        };
+
+        assert_eq!(
+            StuckTransactionReason::of(
+                &sent_tx,
+                Some(&observed_transaction(
+                    &sent_tx,
+                    Some(BlockNumber::new(0x4132ec))
+                )),
+                &current_gas_fee
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn should_replace_transaction_when_fee_is_within_cap() {
+        let new_tx = eip_1559_transaction_request_with_nonce(TransactionNonce::ZERO);
+        let expected_outcome = ResubmissionOutcome::Replaced {
+            max_fee_per_gas: new_tx.max_fee_per_gas,
+            max_priority_fee_per_gas: new_tx.max_priority_fee_per_gas,
+        };
+
+        assert_eq!(ResubmissionOutcome::of(&new_tx, None), expected_outcome);
+        assert_eq!(
+            ResubmissionOutcome::of(&new_tx, Some(new_tx.max_fee_per_gas)),
+            expected_outcome
+        );
+    }
+
+    #[test]
+    fn should_not_replace_transaction_when_fee_exceeds_cap() {
+        let new_tx = eip_1559_transaction_request_with_nonce(TransactionNonce::ZERO);
+        let cap = new_tx.max_fee_per_gas.checked_decrement().unwrap();
+
+        assert_eq!(
+            ResubmissionOutcome::of(&new_tx, Some(cap)),
+            ResubmissionOutcome::FeeCapExceeded {
+                max_fee_per_gas_cap: cap,
+                max_fee_per_gas: new_tx.max_fee_per_gas,
+            }
+        );
+    }
+
+    #[test]
+    fn should_identify_same_failure_regardless_of_fees() {
+        let failure = |nonce: u64, max_fee_per_gas: u128| ResubmissionAttempt {
+            nonce: TransactionNonce::from(nonce),
+            reason: StuckTransactionReason::NotFound,
+            outcome: ResubmissionOutcome::FeeCapExceeded {
+                max_fee_per_gas_cap: WeiPerGas::new(100),
+                max_fee_per_gas: WeiPerGas::new(max_fee_per_gas),
+            },
+        };
+        let insufficient_fee = ResubmissionAttempt {
+            outcome: ResubmissionOutcome::InsufficientTransactionFee {
+                allowed_max_transaction_fee: Wei::new(100),
+                max_transaction_fee: Wei::new(200),
+            },
+            ..failure(0, 200)
+        };
+        let replaced = ResubmissionAttempt {
+            outcome: ResubmissionOutcome::Replaced {
+                max_fee_per_gas: WeiPerGas::new(200),
+                max_priority_fee_per_gas: WeiPerGas::new(1),
+            },
+            ..failure(0, 200)
+        };
+
+        assert!(failure(0, 200).is_same_failure_as(&failure(0, 300)));
+        assert!(!failure(0, 200).is_same_failure_as(&failure(1, 200)));
+        assert!(!failure(0, 200).is_same_failure_as(&insufficient_fee));
+        assert!(!replaced.is_same_failure_as(&replaced));
+    }
+
+    fn observed_transaction(
+        sent_tx: &Eip1559TransactionRequest,
+        block_number: Option<BlockNumber>,
+    ) -> Transaction {
+        Transaction {
+            hash: Hash([0x2e; 32]),
+            transaction_type: None,
+            nonce: sent_tx.nonce,
+            from: Address::new([0x11; 20]),
+            to: Some(sent_tx.destination),
+            value: sent_tx.amount,
+            gas: sent_tx.gas_limit,
+            gas_price: Some(sent_tx.max_fee_per_gas),
+            max_fee_per_gas: Some(sent_tx.max_fee_per_gas),
+            max_priority_fee_per_gas: Some(sent_tx.max_priority_fee_per_gas),
+            input: Data(sent_tx.data.clone()),
+            block_hash: block_number.map(|_| Hash([0x4e; 32])),
+            block_number,
+        }
+    }
+}
+
 mod oldest_incomplete_withdrawal_timestamp {
     use super::*;
     use ic_crypto_test_utils_reproducible_rng::reproducible_rng;
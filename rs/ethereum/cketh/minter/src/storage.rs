@@ -3,16 +3,19 @@ use ic_stable_structures::{
     log::Log as StableLog,
     memory_manager::{MemoryId, MemoryManager, VirtualMemory},
     storable::{Bound, Storable},
-    DefaultMemoryImpl,
+    DefaultMemoryImpl, StableBTreeMap,
 };
 use std::borrow::Cow;
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 
 const LOG_INDEX_MEMORY_ID: MemoryId = MemoryId::new(0);
 const LOG_DATA_MEMORY_ID: MemoryId = MemoryId::new(1);
+const RESPONSE_SIZE_MEMORY_ID: MemoryId = MemoryId::new(2);
 
 type VMem = VirtualMemory<DefaultMemoryImpl>;
 type EventLog = StableLog<Event, VMem, VMem>;
+type ResponseSizeMap = StableBTreeMap<String, (u64, u64), VMem>;
 
 impl Storable for Event {
     fn to_bytes(&self) -> Cow<[u8]> {
@@ -44,6 +47,11 @@ thread_local! {
                   ).expect("failed to initialize stable log")
               )
         );
+
+    /// The largest observed response size per JSON-RPC method,
+    /// see [`State::max_response_size_per_method`](crate::state::State::max_response_size_per_method).
+    static RESPONSE_SIZES: RefCell<ResponseSizeMap> = MEMORY_MANAGER
+        .with(|m| RefCell::new(StableBTreeMap::init(m.borrow().get(RESPONSE_SIZE_MEMORY_ID))));
 }
 
 /// Appends the event to the event log.
@@ -69,3 +77,14 @@ where
 {
     EVENTS.with(|events| f(Box::new(events.borrow().iter())))
 }
+
+/// Stores the largest observed response size of the given JSON-RPC method,
+/// together with the time (in nanoseconds) at which it was observed.
+pub fn record_response_size(method: String, observed_at_ns: u64, size: u64) {
+    RESPONSE_SIZES.with(|sizes| sizes.borrow_mut().insert(method, (observed_at_ns, size)));
+}
+
+/// Returns the largest observed response sizes stored by [`record_response_size`].
+pub fn response_sizes() -> BTreeMap<String, (u64, u64)> {
+    RESPONSE_SIZES.with(|sizes| sizes.borrow().iter().collect())
+}
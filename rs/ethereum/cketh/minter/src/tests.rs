@@ -382,7 +382,7 @@ mod rlp_encoding {
 
 mod eth_get_block_by_number {
     use crate::eth_rpc::{into_nat, Block, BlockSpec, BlockTag, GetBlockByNumberParams, Quantity};
-    use crate::numeric::{BlockNumber, Wei};
+    use crate::numeric::{BlockNumber, GasAmount, Wei};
 
     #[test]
     fn should_serialize_get_block_by_number_params_as_tuple() {
@@ -720,11 +720,67 @@ mod eth_get_block_by_number {
             block,
             Block {
                 number: BlockNumber::new(0x10eb3c6),
+                hash: "0x85db6d6ad071d127795df4c5f1b04863629d7c2832c89550aa2771bf81c40c85"
+                    .parse()
+                    .unwrap(),
                 base_fee_per_gas: Wei::new(0x4b85a0fcd),
+                blob_gas_used: None,
+                excess_blob_gas: None,
             }
         )
     }
 
+    #[test]
+    fn should_deserialize_blob_gas_fields_of_post_dencun_block() {
+        const ETHEREUM_BLOCK: &str = r#"{
+        "number": "0x12884e1",
+        "hash": "0x8f1e1a6a1bd34b48a55b5e5b5be2b0d2b49cde0f2a3c6b3b9a8c5f94e2d7c1a0",
+        "baseFeePerGas": "0x2f3a6b7c1",
+        "blobGasUsed": "0x60000",
+        "excessBlobGas": "0x4b80000",
+        "gasLimit": "0x1c9c380",
+        "gasUsed": "0xdb3f2a"
+    }"#;
+
+        let block: Block = serde_json::from_str(ETHEREUM_BLOCK).unwrap();
+
+        assert_eq!(
+            block,
+            Block {
+                number: BlockNumber::new(0x12884e1),
+                hash: "0x8f1e1a6a1bd34b48a55b5e5b5be2b0d2b49cde0f2a3c6b3b9a8c5f94e2d7c1a0"
+                    .parse()
+                    .unwrap(),
+                base_fee_per_gas: Wei::new(0x2f3a6b7c1),
+                blob_gas_used: Some(GasAmount::new(0x60000)),
+                excess_blob_gas: Some(GasAmount::new(0x4b80000)),
+            }
+        );
+        let serialized_block = serde_json::to_string(&block).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Block>(&serialized_block).unwrap(),
+            block
+        );
+    }
+
+    #[test]
+    fn should_not_serialize_missing_blob_gas_fields() {
+        let block = Block {
+            number: BlockNumber::new(0x10eb3c6),
+            hash: "0x85db6d6ad071d127795df4c5f1b04863629d7c2832c89550aa2771bf81c40c85"
+                .parse()
+                .unwrap(),
+            base_fee_per_gas: Wei::new(0x4b85a0fcd),
+            blob_gas_used: None,
+            excess_blob_gas: None,
+        };
+
+        let serialized_block = serde_json::to_string(&block).unwrap();
+
+        assert!(!serialized_block.contains("blobGasUsed"));
+        assert!(!serialized_block.contains("excessBlobGas"));
+    }
+
     #[test]
     fn should_convert_quantity_to_nat() {
         let quantity = Quantity::new(0x4b85a0fcd); //20_272_779_213 wei
@@ -837,6 +893,31 @@ mod eth_fee_history {
                         WeiPerGas::new(0x180789e0)
                     ]
                 ],
+                base_fee_per_blob_gas: vec![],
+            }
+        )
+    }
+
+    #[test]
+    fn should_deserialize_base_fee_per_blob_gas() {
+        const ETH_FEE_HISTORY: &str = r#"{
+        "baseFeePerGas": ["0x2f3a6b7c1", "0x2e1c7b6a2"],
+        "baseFeePerBlobGas": ["0x1", "0x3"],
+        "blobGasUsedRatio": [0.5],
+        "gasUsedRatio": [0.45],
+        "oldestBlock": "0x12884e1",
+        "reward": [["0x5f5e100"]]
+    }"#;
+
+        let fee_history: FeeHistory = serde_json::from_str(ETH_FEE_HISTORY).unwrap();
+
+        assert_eq!(
+            fee_history,
+            FeeHistory {
+                oldest_block: BlockNumber::new(0x12884e1),
+                base_fee_per_gas: vec![WeiPerGas::new(0x2f3a6b7c1), WeiPerGas::new(0x2e1c7b6a2)],
+                reward: vec![vec![WeiPerGas::new(0x5f5e100)]],
+                base_fee_per_blob_gas: vec![WeiPerGas::new(0x1), WeiPerGas::new(0x3)],
             }
         )
     }
@@ -78,6 +78,7 @@ mod estimate_transaction_price {
             oldest_block: BlockNumber::new(0x10f73fc),
             base_fee_per_gas: base_fee_per_gas.into_iter().map(|x| x.into()).collect(),
             reward: reward.into_iter().map(|x| vec![x.into()]).collect(),
+            base_fee_per_blob_gas: vec![],
         }
     }
 }
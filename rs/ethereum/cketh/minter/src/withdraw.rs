@@ -2,15 +2,16 @@ use crate::eth_rpc::JsonRpcResult;
 use crate::eth_rpc::{BlockSpec, BlockTag, SendRawTransactionResult};
 use crate::eth_rpc_client::requests::GetTransactionCountParams;
 use crate::eth_rpc_client::responses::TransactionReceipt;
-use crate::eth_rpc_client::EthRpcClient;
 use crate::eth_rpc_client::MultiCallError;
+use crate::eth_rpc_client::{ChainBackend, EthRpcClient};
 use crate::guard::TimerGuard;
 use crate::logs::{DEBUG, INFO};
 use crate::numeric::{GasAmount, LedgerBurnIndex, LedgerMintIndex, TransactionCount};
 use crate::state::audit::{process_event, EventType};
 use crate::state::transactions::{
     create_transaction, CreateTransactionError, Reimbursed, ReimbursementIndex,
-    ReimbursementRequest, WithdrawalRequest,
+    ReimbursementRequest, ResubmissionAttempt, ResubmissionOutcome, ResubmitTransactionError,
+    StuckTransactionReason, WithdrawalRequest,
 };
 use crate::state::{mutate_state, read_state, State, TaskType};
 use crate::tx::{lazy_refresh_gas_fee_estimate, GasFeeEstimate};
@@ -210,9 +211,67 @@ async fn resubmit_transactions_batch(
         s.eth_transactions
             .create_resubmit_transactions(latest_transaction_count, gas_fee_estimate.clone())
     });
+    if transactions_to_resubmit.is_empty() {
+        return;
+    }
+    let max_fee_per_gas_cap = read_state(|s| s.max_resubmission_fee_per_gas);
+    let chain_reader = read_state(ChainBackend::reader);
     for result in transactions_to_resubmit {
+        let (withdrawal_id, nonce) = match &result {
+            Ok((withdrawal_id, transaction)) => (*withdrawal_id, transaction.nonce),
+            Err(ResubmitTransactionError::InsufficientTransactionFee {
+                ledger_burn_index,
+                transaction_nonce,
+                ..
+            }) => (*ledger_burn_index, *transaction_nonce),
+        };
+        let last_sent_tx = read_state(|s| {
+            s.eth_transactions
+                .last_sent_transaction(&nonce)
+                .cloned()
+                .expect("BUG: transaction to resubmit was not sent")
+        });
+        // The transaction is resubmitted since it was not mined at the latest block.
+        // Looking it up only tells why, for the record.
+        let reason = match chain_reader.get_transaction(last_sent_tx.hash()).await {
+            Ok(observed_tx) => StuckTransactionReason::of(
+                last_sent_tx.transaction(),
+                observed_tx.as_ref(),
+                gas_fee_estimate,
+            )
+            .unwrap_or(StuckTransactionReason::Unknown),
+            Err(e) => {
+                log!(
+                    INFO,
+                    "Failed to get transaction {} with nonce {nonce}: {e:?}",
+                    last_sent_tx.hash()
+                );
+                StuckTransactionReason::Unknown
+            }
+        };
+        let outcome = match &result {
+            Ok((_withdrawal_id, transaction)) => {
+                ResubmissionOutcome::of(transaction, max_fee_per_gas_cap)
+            }
+            Err(ResubmitTransactionError::InsufficientTransactionFee {
+                allowed_max_transaction_fee,
+                max_transaction_fee,
+                ..
+            }) => ResubmissionOutcome::InsufficientTransactionFee {
+                allowed_max_transaction_fee: *allowed_max_transaction_fee,
+                max_transaction_fee: *max_transaction_fee,
+            },
+        };
+        record_resubmission_attempt(
+            withdrawal_id,
+            ResubmissionAttempt {
+                nonce,
+                reason,
+                outcome: outcome.clone(),
+            },
+        );
         match result {
-            Ok((withdrawal_id, transaction)) => {
+            Ok((withdrawal_id, transaction)) if outcome.is_replaced() => {
                 log!(
                     INFO,
                     "[resubmit_transactions_batch]: transactions to resubmit {transaction:?}"
@@ -227,6 +286,15 @@ async fn resubmit_transactions_batch(
                     )
                 });
             }
+            Ok(_) => {
+                // Transactions with higher nonces cannot be mined before this one,
+                // so that there is no point in replacing them.
+                log!(
+                    INFO,
+                    "Not resubmitting transaction with nonce {nonce}: {outcome:?}"
+                );
+                return;
+            }
             Err(e) => {
                 log!(INFO, "Failed to resubmit transaction: {e:?}");
             }
@@ -234,6 +302,30 @@ async fn resubmit_transactions_batch(
     }
 }
 
+/// Records the attempt to replace a stuck transaction, unless the last attempt
+/// for that withdrawal already failed in the same way, to avoid recording
+/// an event every time withdrawals are processed.
+fn record_resubmission_attempt(withdrawal_id: LedgerBurnIndex, attempt: ResubmissionAttempt) {
+    mutate_state(|s| {
+        let is_repeated_failure = s
+            .eth_transactions
+            .resubmission_attempts(&withdrawal_id)
+            .last()
+            .map_or(false, |last_attempt| {
+                attempt.is_same_failure_as(last_attempt)
+            });
+        if !is_repeated_failure {
+            process_event(
+                s,
+                EventType::AttemptedTransactionResubmission {
+                    withdrawal_id,
+                    attempt,
+                },
+            );
+        }
+    });
+}
+
 fn create_transactions_batch(gas_fee_estimate: GasFeeEstimate) {
     for request in read_state(|s| {
         s.eth_transactions
@@ -337,11 +429,11 @@ async fn send_transactions_batch(latest_transaction_count: Option<TransactionCou
             .transactions_to_send_batch(latest_transaction_count, TRANSACTIONS_TO_SEND_BATCH_SIZE)
     });
 
-    let rpc_client = read_state(EthRpcClient::from_state);
+    let chain_writer = read_state(ChainBackend::writer);
     let results = join_all(
         transactions_to_send
             .iter()
-            .map(|tx| rpc_client.eth_send_raw_transaction(tx.raw_transaction_hex())),
+            .map(|tx| chain_writer.submit_transaction(tx.raw_transaction_hex())),
     )
     .await;
 
@@ -380,14 +472,14 @@ async fn finalize_transactions_batch() {
             let expected_finalized_withdrawal_ids: BTreeSet<_> =
                 txs_to_finalize.values().cloned().collect();
             let rpc_client = read_state(EthRpcClient::from_state);
-            let results = join_all(
-                txs_to_finalize
-                    .keys()
-                    .map(|hash| rpc_client.eth_get_transaction_receipt(*hash)),
-            )
-            .await;
+            let mut results = rpc_client
+                .eth_get_transaction_receipts(txs_to_finalize.keys().copied().collect())
+                .await;
             let mut receipts: BTreeMap<LedgerBurnIndex, TransactionReceipt> = BTreeMap::new();
-            for ((hash, withdrawal_id), result) in zip(txs_to_finalize, results) {
+            for (hash, withdrawal_id) in txs_to_finalize {
+                let result = results
+                    .remove(&hash)
+                    .expect("BUG: missing transaction receipt result");
                 match result {
                     Ok(Some(receipt)) => {
                         log!(DEBUG, "Received transaction receipt {receipt:?} for transaction {hash} and withdrawal ID {withdrawal_id}");
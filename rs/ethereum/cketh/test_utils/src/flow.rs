@@ -556,6 +556,10 @@ impl<T: AsRef<CkEthSetup>, Req: HasWithdrawalId> ProcessWithdrawal<T, Req> {
                     *count = transaction_count_response(0)
                 })
             })
+            .retrieve_transaction_by_hash(|mock| {
+                mock.with_request_params(json!([first_tx_hash]))
+                    .respond_for_all_with(serde_json::Value::Null)
+            })
             .expect_status(RetrieveEthStatus::TxCreated)
             .send_raw_transaction_expecting(&resubmitted_sent_tx)
             .expect_status_sent()
@@ -651,6 +655,23 @@ impl<T: AsRef<CkEthSetup>, Req: HasWithdrawalId> LatestTransactionCountProcessWi
         self
     }
 
+    /// Mocks the lookup of the last sent transaction, which happens when that transaction
+    /// is a candidate for resubmission. By default, the transaction is unknown and hence stuck.
+    pub fn retrieve_transaction_by_hash<
+        F: FnMut(MockJsonRpcProvidersBuilder) -> MockJsonRpcProvidersBuilder,
+    >(
+        self,
+        mut override_mock: F,
+    ) -> Self {
+        let default_eth_get_transaction_by_hash =
+            MockJsonRpcProviders::when(JsonRpcMethod::EthGetTransactionByHash)
+                .respond_for_all_with(serde_json::Value::Null);
+        (override_mock)(default_eth_get_transaction_by_hash)
+            .build()
+            .expect_rpc_calls(&self.setup);
+        self
+    }
+
     pub fn expect_status(
         self,
         status: RetrieveEthStatus,
@@ -36,6 +36,9 @@ pub enum JsonRpcMethod {
     #[strum(serialize = "eth_getTransactionCount")]
     EthGetTransactionCount,
 
+    #[strum(serialize = "eth_getTransactionByHash")]
+    EthGetTransactionByHash,
+
     #[strum(serialize = "eth_getTransactionReceipt")]
     EthGetTransactionReceipt,
 
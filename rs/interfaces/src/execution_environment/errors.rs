@@ -159,6 +159,13 @@ pub enum HypervisorError {
         bytes: NumBytes,
         limit: NumBytes,
     },
+    /// The sandbox process running the non-replicated execution exited
+    /// unexpectedly or was terminated for exceeding its resource limits.
+    SandboxCrashed,
+    /// The sandbox process running the execution was killed by the replica
+    /// because the execution did not complete within the given wall-clock
+    /// time.
+    SandboxExecutionTimedOut(std::time::Duration),
 }
 
 impl From<WasmInstrumentationError> for HypervisorError {
@@ -331,6 +338,15 @@ impl std::fmt::Display for HypervisorError {
                         limit.get(), bytes.get()
                 )
             }
+            Self::SandboxCrashed => write!(
+                f,
+                "Canister execution failed because its sandbox process exited unexpectedly."
+            ),
+            Self::SandboxExecutionTimedOut(limit) => write!(
+                f,
+                "Canister execution was terminated because it did not complete within {:?}.",
+                limit
+            ),
         }
     }
 }
@@ -442,6 +458,8 @@ impl AsErrorHelp for HypervisorError {
                     .to_string(),
                 doc_link: doc_ref("wasm-memory-limit-exceeded"),
             },
+            Self::SandboxCrashed => ErrorHelp::InternalError,
+            Self::SandboxExecutionTimedOut(_) => ErrorHelp::InternalError,
             Self::UserContractViolation {
                 suggestion,
                 doc_link,
@@ -501,6 +519,8 @@ impl HypervisorError {
                 E::InsufficientCyclesInMessageMemoryGrow
             }
             Self::WasmMemoryLimitExceeded { .. } => E::CanisterWasmMemoryLimitExceeded,
+            Self::SandboxCrashed => E::CanisterWasmEngineError,
+            Self::SandboxExecutionTimedOut(_) => E::CanisterWasmEngineError,
         };
         UserError::new(code, description)
     }
@@ -539,6 +559,8 @@ impl HypervisorError {
                 "InsufficientCyclesInMessageMemoryGrow"
             }
             HypervisorError::WasmMemoryLimitExceeded { .. } => "WasmMemoryLimitExceeded",
+            HypervisorError::SandboxCrashed => "SandboxCrashed",
+            HypervisorError::SandboxExecutionTimedOut(_) => "SandboxExecutionTimedOut",
         }
     }
 }
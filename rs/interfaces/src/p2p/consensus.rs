@@ -1,5 +1,7 @@
 //! The artifact pool public interface that defines the Consensus-P2P API.
 //! Consensus clients must implement the traits in this file in order to use the IC P2P protocol.
+use std::time::Instant;
+
 use ic_types::{artifact::IdentifiableArtifact, NodeId, Time};
 
 /// Produces mutations to be applied on the artifact pool.
@@ -58,6 +60,16 @@ pub trait MutablePool<T: IdentifiableArtifact> {
 }
 
 /// Priority of artifact.
+///
+/// The priority of an advert is re-evaluated whenever the priority function is refreshed.
+/// To keep adverts close to a priority boundary from oscillating between being queued
+/// and being dropped, P2P applies the following hysteresis rules:
+/// - An advert that is evaluated to [`Priority::Drop`] before it was queued is dropped
+///   immediately.
+/// - An advert that is queued, i.e. stashed, scheduled or being fetched, is only dropped
+///   after the priority function evaluated it to [`Priority::Drop`] on
+///   [`PRIORITY_DROP_HYSTERESIS`] consecutive refreshes. Any other priority resets the count.
+/// - A [`Priority::FetchAfter`] deadline that has passed is equivalent to [`Priority::FetchNow`].
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Priority {
     /// Drop the advert, the local replica doesn't need the corresponding artifact for
@@ -65,10 +77,17 @@ pub enum Priority {
     Drop,
     /// Stash the advert. It may be requested at a later point in time.
     Stash,
+    /// Fetch the artifact once the deadline has passed, unless the priority changes
+    /// before. Unlike stashed adverts, pushed artifacts are kept until then.
+    FetchAfter(Instant),
     /// High priority adverts, fetch the artifact immediately.
     FetchNow,
 }
 
+/// Number of consecutive refreshes of the priority function that must evaluate a queued
+/// advert to [`Priority::Drop`] before it is dropped.
+pub const PRIORITY_DROP_HYSTERESIS: usize = 2;
+
 /// Priority function used by `ArtifactClient`.
 pub type PriorityFn<Id, Attribute> =
     Box<dyn Fn(&Id, &Attribute) -> Priority + Send + Sync + 'static>;
@@ -0,0 +1,74 @@
+use std::{num::NonZeroUsize, time::Duration};
+
+use backoff::{ExponentialBackoff, ExponentialBackoffBuilder};
+
+use crate::SendRateLimit;
+
+/// Tunables of the consensus manager. Every client added to the
+/// [`ConsensusManagerBuilder`](crate::ConsensusManagerBuilder) uses the same configuration,
+/// but gets its own channels, download limit and send rate limit.
+///
+/// The [`Default`] matches the behavior of the consensus manager before it was configurable.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConsensusManagerConfig {
+    /// Capacity of the channel between the handler of slot updates pushed by peers and the
+    /// receiver. Handlers wait while the channel is full.
+    pub adverts_from_peers_channel_capacity: NonZeroUsize,
+    /// Maximum number of artifact download requests in flight at the same time.
+    /// Unlimited if `None`.
+    pub max_concurrent_downloads: Option<NonZeroUsize>,
+    /// Maximum number of locally produced adverts the sender takes from its channel at once.
+    pub advert_batch_size: NonZeroUsize,
+    /// Waiting time between attempts to push a slot update to a peer.
+    pub push_retry_backoff: RetryBackoff,
+    /// Timeouts of consecutive attempts to download an artifact.
+    pub download_retry_backoff: RetryBackoff,
+    /// Number of consecutive failed attempts to download an artifact from the peers advertising
+    /// it, after which the artifact is also requested from the peers that committed the same
+    /// slot to a different artifact. Such peers respond with the artifact if it is in their
+    /// validated pool. Disabled if `None`.
+    pub download_fallback_after_failures: Option<NonZeroUsize>,
+    /// Limits the rate at which slot updates are pushed to peers. Unlimited if `None`.
+    pub send_rate_limit: Option<SendRateLimit>,
+}
+
+impl Default for ConsensusManagerConfig {
+    fn default() -> Self {
+        Self {
+            adverts_from_peers_channel_capacity: NonZeroUsize::new(100).unwrap(),
+            max_concurrent_downloads: None,
+            advert_batch_size: NonZeroUsize::new(1).unwrap(),
+            push_retry_backoff: RetryBackoff {
+                initial_interval: Duration::from_millis(250),
+                max_interval: Duration::from_secs(60),
+                multiplier: 2.0,
+            },
+            download_retry_backoff: RetryBackoff {
+                initial_interval: Duration::from_secs(5),
+                max_interval: Duration::from_secs(120),
+                multiplier: backoff::default::MULTIPLIER,
+            },
+            download_fallback_after_failures: None,
+            send_rate_limit: None,
+        }
+    }
+}
+
+/// Exponential backoff without a limit on the total time spent retrying.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryBackoff {
+    pub initial_interval: Duration,
+    pub max_interval: Duration,
+    pub multiplier: f64,
+}
+
+impl RetryBackoff {
+    pub(crate) fn build(&self) -> ExponentialBackoff {
+        ExponentialBackoffBuilder::new()
+            .with_initial_interval(self.initial_interval)
+            .with_max_interval(self.max_interval)
+            .with_multiplier(self.multiplier)
+            .with_max_elapsed_time(None)
+            .build()
+    }
+}
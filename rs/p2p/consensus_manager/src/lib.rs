@@ -7,13 +7,15 @@ use crate::{
 };
 use axum::Router;
 use ic_base_types::NodeId;
+use ic_crypto_sha2::Sha256;
 use ic_interfaces::p2p::{
     artifact_manager::ArtifactProcessorEvent,
     consensus::{PriorityFnFactory, ValidatedPoolReader},
 };
 use ic_logger::ReplicaLogger;
 use ic_metrics::MetricsRegistry;
-use ic_quic_transport::{ConnId, Shutdown, SubnetTopology, Transport};
+use ic_protobuf::{p2p::v1 as pb, proxy::ProtoProxy};
+use ic_quic_transport::{ConnId, PeerCapabilities, Shutdown, SubnetTopology, Transport};
 use ic_types::artifact::{PbArtifact, UnvalidatedArtifactMutation};
 use phantom_newtype::AmountOf;
 use tokio::{
@@ -24,10 +26,18 @@ use tokio::{
     },
 };
 
+mod config;
 mod metrics;
+mod rate_limit;
 mod receiver;
 mod sender;
 
+pub use config::{ConsensusManagerConfig, RetryBackoff};
+pub use rate_limit::SendRateLimit;
+
+/// The capabilities the consensus manager of this version supports as a receiver.
+pub const CAPABILITIES: PeerCapabilities = PeerCapabilities::COMPACT_ADVERTS;
+
 type StartConsensusManagerFn =
     Box<dyn FnOnce(Arc<dyn Transport>, watch::Receiver<SubnetTopology>) -> Shutdown>;
 
@@ -37,16 +47,23 @@ pub struct ConsensusManagerBuilder {
     rt_handle: Handle,
     clients: Vec<StartConsensusManagerFn>,
     router: Option<Router>,
+    config: ConsensusManagerConfig,
 }
 
 impl ConsensusManagerBuilder {
-    pub fn new(log: ReplicaLogger, rt_handle: Handle, metrics_registry: MetricsRegistry) -> Self {
+    pub fn new(
+        log: ReplicaLogger,
+        rt_handle: Handle,
+        metrics_registry: MetricsRegistry,
+        config: ConsensusManagerConfig,
+    ) -> Self {
         Self {
             log,
             metrics_registry,
             rt_handle,
             clients: Vec::new(),
             router: None,
+            config,
         }
     }
 
@@ -61,15 +78,21 @@ impl ConsensusManagerBuilder {
         Artifact: PbArtifact,
     {
         assert!(uri_prefix::<Artifact>().chars().all(char::is_alphabetic));
-        let (router, adverts_from_peers_rx) = build_axum_router(self.log.clone(), pool.clone());
+        let (router, adverts_from_peers_rx) = build_axum_router(
+            self.log.clone(),
+            pool.clone(),
+            self.config.adverts_from_peers_channel_capacity,
+        );
 
         let log = self.log.clone();
         let rt_handle = self.rt_handle.clone();
         let metrics_registry = self.metrics_registry.clone();
+        let config = self.config.clone();
 
         let builder = move |transport: Arc<dyn Transport>, topology_watcher| {
             start_consensus_manager(
                 log,
+                &config,
                 &metrics_registry,
                 rt_handle,
                 outbound_artifacts_rx,
@@ -106,6 +129,7 @@ impl ConsensusManagerBuilder {
 
 fn start_consensus_manager<Artifact, Pool>(
     log: ReplicaLogger,
+    config: &ConsensusManagerConfig,
     metrics_registry: &MetricsRegistry,
     rt_handle: Handle,
     // Locally produced adverts to send to the node's peers.
@@ -130,10 +154,12 @@ where
         rt_handle.clone(),
         transport.clone(),
         adverts_to_send,
+        config,
     );
 
     ConsensusManagerReceiver::run(
         log,
+        config,
         metrics,
         rt_handle,
         adverts_received,
@@ -155,6 +181,40 @@ pub(crate) struct SlotUpdate<Artifact: PbArtifact> {
 pub(crate) enum Update<Artifact: PbArtifact> {
     Artifact(Artifact),
     Advert((Artifact::Id, Artifact::Attribute)),
+    /// Advert that carries the digest of the attribute instead of the attribute.
+    CompactAdvert((Artifact::Id, AttributeDigest)),
+}
+
+/// SHA-256 digest of an encoded attribute.
+pub(crate) type AttributeDigest = [u8; 32];
+
+/// Encodes the attribute for an advert. If `compact` is set, attributes above the compaction
+/// threshold of the artifact type are replaced by their digest. Only peers with the
+/// [`PeerCapabilities::COMPACT_ADVERTS`] capability may receive compacted adverts.
+pub(crate) fn encode_advert<Artifact: PbArtifact>(
+    id: Artifact::Id,
+    attribute: Artifact::Attribute,
+    compact: bool,
+) -> pb::Advert {
+    let attribute = Artifact::PbAttribute::proxy_encode(attribute);
+    let (attribute, attribute_digest) = match Artifact::COMPACT_ATTRIBUTE_THRESHOLD {
+        Some(threshold) if compact && attribute.len() > threshold => {
+            (Vec::new(), Sha256::hash(&attribute).to_vec())
+        }
+        _ => (attribute, Vec::new()),
+    };
+    pb::Advert {
+        id: Artifact::PbId::proxy_encode(id),
+        attribute,
+        attribute_digest,
+    }
+}
+
+/// Returns true if the peer reported that it accepts compacted adverts.
+pub(crate) fn supports_compact_adverts(transport: &dyn Transport, peer: &NodeId) -> bool {
+    transport
+        .peer_metadata(peer)
+        .is_some_and(|metadata| metadata.supports(PeerCapabilities::COMPACT_ADVERTS))
 }
 
 pub(crate) fn uri_prefix<Artifact: PbArtifact>() -> String {
@@ -1,9 +1,11 @@
 use ic_metrics::{buckets::decimal_buckets, MetricsRegistry};
 use ic_types::artifact::PbArtifact;
-use prometheus::{histogram_opts, labels, opts, Histogram, IntCounter, IntCounterVec, IntGauge};
+use prometheus::{Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, Opts};
 
 use crate::uri_prefix;
 
+const METRICS_NAMESPACE: &str = "ic_consensus_manager";
+pub(crate) const CLIENT_LABEL: &str = "client";
 pub(crate) const PEER_LABEL: &str = "peer_id";
 pub(crate) const DOWNLOAD_TASK_RESULT_LABEL: &str = "result";
 pub(crate) const DOWNLOAD_TASK_RESULT_COMPLETED: &str = "completed";
@@ -18,14 +20,20 @@ pub(crate) struct ConsensusManagerMetrics {
     pub download_task_duration: Histogram,
     pub download_task_result_total: IntCounterVec,
     pub download_task_stashed_total: IntCounter,
+    pub download_task_drop_deferred_total: IntCounter,
     pub download_task_artifact_download_duration: Histogram,
     pub download_task_restart_after_join_total: IntCounter,
     pub download_task_artifact_download_errors_total: IntCounter,
+    pub download_task_fallback_total: IntCounter,
+    pub attribute_fetches_total: IntCounter,
+    pub attribute_fetch_errors_total: IntCounter,
+    pub attribute_fetches_superseded_total: IntCounter,
 
     // Slot table
     pub slot_table_updates_total: IntCounter,
     pub slot_table_updates_with_artifact_total: IntCounter,
     pub slot_table_overwrite_total: IntCounter,
+    pub slot_table_overwrite_before_download_total: IntCounter,
     pub slot_table_stale_total: IntCounter,
     pub slot_table_new_entry_total: IntCounterVec,
     pub slot_table_seen_id_total: IntCounter,
@@ -43,6 +51,8 @@ pub(crate) struct ConsensusManagerMetrics {
     pub send_view_send_to_peer_delivered_total: IntCounter,
     pub send_view_send_to_peer_cancelled_total: IntCounter,
     pub send_view_resend_reconnect_total: IntCounter,
+    pub send_view_rate_limited_total: IntCounter,
+    pub send_view_rate_limit_delay_duration: Histogram,
 
     // Available slot set
     pub slot_set_in_use_slots: IntGauge,
@@ -51,230 +61,256 @@ pub(crate) struct ConsensusManagerMetrics {
 
 impl ConsensusManagerMetrics {
     pub fn new<Artifact: PbArtifact>(metrics_registry: &MetricsRegistry) -> Self {
-        let prefix = uri_prefix::<Artifact>();
-        let const_labels_string = labels! {"client".to_string() => prefix.clone()};
-        let const_labels = labels! {"client" => prefix.as_str()};
+        let namespace = ClientMetricsNamespace::new::<Artifact>(metrics_registry);
         Self {
-            download_task_started_total: metrics_registry.register(
-                IntCounter::with_opts(opts!(
-                    "ic_consensus_manager_download_task_started_total",
-                    "Artifact download tasks started.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
-            ),
-            download_task_finished_total: metrics_registry.register(
-                IntCounter::with_opts(opts!(
-                    "ic_consensus_manager_download_task_finished_total",
-                    "Artifact download tasks finished.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
-            ),
-            download_task_duration: metrics_registry.register(
-                Histogram::with_opts(histogram_opts!(
-                    "ic_consensus_manager_download_task_duration",
-                    "Duration for which the download task was alive. This includes downloading and waiting for close.",
-                    decimal_buckets(0, 2),
-                    const_labels_string.clone(),
-                ))
-                .unwrap(),
-            ),
-            download_task_result_total: metrics_registry.register(
-                IntCounterVec::new(
-                    opts!(
-                        "ic_consensus_manager_download_task_result_total",
-                        "Download task result.",
-                        const_labels.clone(),
-                    ),
-                    &[DOWNLOAD_TASK_RESULT_LABEL],
-                )
-                .unwrap(),
-            ),
-            download_task_stashed_total: metrics_registry.register(
-                IntCounter::with_opts(opts!(
-                    "ic_consensus_manager_download_task_stashed_total",
-                    "Adverts stashed at least once.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
-            ),
-            download_task_artifact_download_duration: metrics_registry.register(
-                Histogram::with_opts(histogram_opts!(
-                    "ic_consensus_manager_download_task_artifact_download_duration",
-                    "Download time for artifact.",
-                    decimal_buckets(-2, 1),
-                    const_labels_string.clone(),
-                ))
-                .unwrap(),
-            ),
-            download_task_restart_after_join_total: metrics_registry.register(
-                IntCounter::with_opts(opts!(
-                    "ic_consensus_manager_download_task_restart_after_join_total",
-                    "Download task immediately restarted due to advert appearing when closing.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
-            ),
-            download_task_artifact_download_errors_total: metrics_registry.register(
-                IntCounter::with_opts(opts!(
-                    "ic_consensus_manager_download_task_artifact_download_errors_total",
-                    "Error occurred when downloading artifact.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
+            download_task_started_total: namespace.int_counter(
+                "download_task_started_total",
+                "Artifact download tasks started.",
+            ),
+            download_task_finished_total: namespace.int_counter(
+                "download_task_finished_total",
+                "Artifact download tasks finished.",
+            ),
+            download_task_duration: namespace.histogram(
+                "download_task_duration",
+                "Duration for which the download task was alive. This includes downloading and waiting for close.",
+                task_duration_buckets(),
+            ),
+            download_task_result_total: namespace.int_counter_vec(
+                "download_task_result_total",
+                "Download task result.",
+                &[DOWNLOAD_TASK_RESULT_LABEL],
+            ),
+            download_task_stashed_total: namespace.int_counter(
+                "download_task_stashed_total",
+                "Adverts stashed at least once.",
+            ),
+            download_task_drop_deferred_total: namespace.int_counter(
+                "download_task_drop_deferred_total",
+                "Adverts whose drop was deferred at least once because of hysteresis.",
+            ),
+            download_task_artifact_download_duration: namespace.histogram(
+                "download_task_artifact_download_duration",
+                "Download time for artifact.",
+                request_duration_buckets(),
+            ),
+            download_task_restart_after_join_total: namespace.int_counter(
+                "download_task_restart_after_join_total",
+                "Download task immediately restarted due to advert appearing when closing.",
+            ),
+            download_task_artifact_download_errors_total: namespace.int_counter(
+                "download_task_artifact_download_errors_total",
+                "Error occurred when downloading artifact.",
+            ),
+            download_task_fallback_total: namespace.int_counter(
+                "download_task_fallback_total",
+                "Downloads that fell back to peers that did not advertise the artifact.",
+            ),
+            attribute_fetches_total: namespace.int_counter(
+                "attribute_fetches_total",
+                "Attributes fetched for compact adverts.",
+            ),
+            attribute_fetch_errors_total: namespace.int_counter(
+                "attribute_fetch_errors_total",
+                "Compact adverts dropped because their attribute could not be fetched.",
+            ),
+            attribute_fetches_superseded_total: namespace.int_counter(
+                "attribute_fetches_superseded_total",
+                "Attribute fetches aborted or skipped because of another advert for the same slot.",
             ),
 
-            slot_table_updates_total: metrics_registry.register(
-                IntCounter::with_opts(opts!(
-                    "ic_consensus_manager_slot_table_updates_total",
-                    "Slot table updates.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
-            ),
-            slot_table_updates_with_artifact_total: metrics_registry.register(
-                IntCounter::with_opts(opts!(
-                    "ic_consensus_manager_slot_table_updates_with_artifact_total",
-                    "Slot table updates that contained artifact itself.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
-            ),
-            slot_table_overwrite_total: metrics_registry.register(
-                IntCounter::with_opts(opts!(
-                    "ic_consensus_manager_slot_table_overwrite_total",
-                    "Existing slot updated.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
-            ),
-            slot_table_stale_total: metrics_registry.register(
-                IntCounter::with_opts(opts!(
-                    "ic_consensus_manager_slot_table_stale_total",
-                    "Slot not updated because it referred to an older version.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
-            ),
-            slot_table_new_entry_total: metrics_registry.register(
-                IntCounterVec::new(
-                    opts!(
-                        "ic_consensus_manager_slot_table_new_entry_total",
-                        "Slot updates for new slot.",
-                        const_labels.clone(),
-                    ),
-                    &[PEER_LABEL],
-                )
-                .unwrap(),
-            ),
-            slot_table_seen_id_total: metrics_registry.register(
-                IntCounter::with_opts(opts!(
-                    "ic_consensus_manager_slot_table_seen_id_total",
-                    "Added peer to existing download.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
-            ),
-            slot_table_removals_total: metrics_registry.register(
-                IntCounter::with_opts(opts!(
-                    "ic_consensus_manager_slot_table_removals_total",
-                    "Peer removed from active download task.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
+            slot_table_updates_total: namespace.int_counter(
+                "slot_table_updates_total",
+                "Slot table updates.",
+            ),
+            slot_table_updates_with_artifact_total: namespace.int_counter(
+                "slot_table_updates_with_artifact_total",
+                "Slot table updates that contained artifact itself.",
+            ),
+            slot_table_overwrite_total: namespace.int_counter(
+                "slot_table_overwrite_total",
+                "Existing slot updated.",
+            ),
+            slot_table_overwrite_before_download_total: namespace.int_counter(
+                "slot_table_overwrite_before_download_total",
+                "Existing slot updated before the artifact it referred to was downloaded.",
+            ),
+            slot_table_stale_total: namespace.int_counter(
+                "slot_table_stale_total",
+                "Slot not updated because it referred to an older version.",
+            ),
+            slot_table_new_entry_total: namespace.int_counter_vec(
+                "slot_table_new_entry_total",
+                "Slot updates for new slot.",
+                &[PEER_LABEL],
+            ),
+            slot_table_seen_id_total: namespace.int_counter(
+                "slot_table_seen_id_total",
+                "Added peer to existing download.",
+            ),
+            slot_table_removals_total: namespace.int_counter(
+                "slot_table_removals_total",
+                "Peer removed from active download task.",
             ),
 
-            topology_updates_total: metrics_registry.register(
-                IntCounter::with_opts(opts!(
-                    "ic_consensus_manager_topology_updates_total",
-                    "Slot table pruning due to topology update.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
+            topology_updates_total: namespace.int_counter(
+                "topology_updates_total",
+                "Slot table pruning due to topology update.",
             ),
 
-            send_view_consensus_new_adverts_total: metrics_registry.register(
-                IntCounter::with_opts(opts!(
-                    "ic_consensus_manager_send_view_consensus_new_adverts_total",
-                    "New adverts received from consensus.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
-            ),
-            send_view_consensus_dup_adverts_total: metrics_registry.register(
-                IntCounter::with_opts(opts!(
-                    "ic_consensus_manager_send_view_consnsus_dup_adverts_total",
-                    "Adverts received from consensus that are already in the send view.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
-            ),
-            send_view_consensus_purge_active_total: metrics_registry.register(
-                IntCounter::with_opts(opts!(
-                    "ic_consensus_manager_send_view_consensus_purge_active_total",
-                    "Purges to currently active downloads.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
-            ),
-            send_view_consensus_dup_purge_total: metrics_registry.register(
-                IntCounter::with_opts(opts!(
-                    "ic_consensus_manager_send_view_consensus_dup_purge_total",
-                    "Purges for adverts with no existing download task.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
-            ),
-            send_view_send_to_peer_total: metrics_registry.register(
-                IntCounter::with_opts(opts!(
-                    "ic_consensus_manager_send_view_send_to_peer_total",
-                    "Slot updates sent to peers.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
-            ),
-            send_view_send_to_peer_delivered_total: metrics_registry.register(
-                IntCounter::with_opts(opts!(
-                    "ic_consensus_manager_send_view_send_to_peer_delivered_total",
-                    "Slot updates delivered to peers.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
-            ),
-            send_view_send_to_peer_cancelled_total: metrics_registry.register(
-                IntCounter::with_opts(opts!(
-                    "ic_consensus_manager_send_view_send_to_peer_cancelled_total",
-                    "Cancelled slot updates to peers.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
-            ),
-            send_view_resend_reconnect_total: metrics_registry.register(
-                IntCounter::with_opts(opts!(
-                    "ic_consensus_manager_send_view_resend_reconnect_total",
-                    "Artifact was sent again due to reconnection.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
+            send_view_consensus_new_adverts_total: namespace.int_counter(
+                "send_view_consensus_new_adverts_total",
+                "New adverts received from consensus.",
+            ),
+            send_view_consensus_dup_adverts_total: namespace.int_counter(
+                "send_view_consnsus_dup_adverts_total",
+                "Adverts received from consensus that are already in the send view.",
+            ),
+            send_view_consensus_purge_active_total: namespace.int_counter(
+                "send_view_consensus_purge_active_total",
+                "Purges to currently active downloads.",
+            ),
+            send_view_consensus_dup_purge_total: namespace.int_counter(
+                "send_view_consensus_dup_purge_total",
+                "Purges for adverts with no existing download task.",
+            ),
+            send_view_send_to_peer_total: namespace.int_counter(
+                "send_view_send_to_peer_total",
+                "Slot updates sent to peers.",
+            ),
+            send_view_send_to_peer_delivered_total: namespace.int_counter(
+                "send_view_send_to_peer_delivered_total",
+                "Slot updates delivered to peers.",
+            ),
+            send_view_send_to_peer_cancelled_total: namespace.int_counter(
+                "send_view_send_to_peer_cancelled_total",
+                "Cancelled slot updates to peers.",
+            ),
+            send_view_resend_reconnect_total: namespace.int_counter(
+                "send_view_resend_reconnect_total",
+                "Artifact was sent again due to reconnection.",
+            ),
+            send_view_rate_limited_total: namespace.int_counter(
+                "send_view_rate_limited_total",
+                "Slot updates to peers delayed by the send rate limit.",
+            ),
+            send_view_rate_limit_delay_duration: namespace.histogram(
+                "send_view_rate_limit_delay_duration",
+                "Time slot updates to peers were delayed by the send rate limit.",
+                request_duration_buckets(),
             ),
 
-            slot_set_in_use_slots: metrics_registry.register(
-                IntGauge::with_opts(opts!(
-                    "ic_consensus_manager_slot_set_in_use_slots",
-                    "Active slots in use.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
-            ),
-            slot_set_allocated_slots_total: metrics_registry.register(
-                IntCounter::with_opts(opts!(
-                    "ic_consensus_manager_slot_set_allocated_slots_total",
-                    "Maximum of slots simultaneously used.",
-                    const_labels.clone(),
-                ))
-                .unwrap(),
+            slot_set_in_use_slots: namespace.int_gauge(
+                "slot_set_in_use_slots",
+                "Active slots in use.",
+            ),
+            slot_set_allocated_slots_total: namespace.int_counter(
+                "slot_set_allocated_slots_total",
+                "Maximum of slots simultaneously used.",
             ),
         }
     }
 }
+
+/// Buckets for tasks that live as long as an artifact is advertised. From 1s to 500s.
+pub(crate) fn task_duration_buckets() -> Vec<f64> {
+    decimal_buckets(0, 2)
+}
+
+/// Buckets for single requests to peers. From 10ms to 50s.
+pub(crate) fn request_duration_buckets() -> Vec<f64> {
+    decimal_buckets(-2, 1)
+}
+
+/// Registers the metrics of a consensus manager client.
+///
+/// Metric names are prefixed with `ic_consensus_manager` and every metric carries the
+/// `client` label of the artifact type. Adding a client therefore yields a complete metric
+/// family that only differs from the other clients in the label.
+pub(crate) struct ClientMetricsNamespace<'a> {
+    metrics_registry: &'a MetricsRegistry,
+    client: String,
+}
+
+impl<'a> ClientMetricsNamespace<'a> {
+    pub fn new<Artifact: PbArtifact>(metrics_registry: &'a MetricsRegistry) -> Self {
+        Self {
+            metrics_registry,
+            client: uri_prefix::<Artifact>(),
+        }
+    }
+
+    /// Returns the full name of the metric in the namespace.
+    pub fn name(&self, name: &str) -> String {
+        format!("{}_{}", METRICS_NAMESPACE, name)
+    }
+
+    fn opts(&self, name: &str, help: &str) -> Opts {
+        Opts::new(self.name(name), help).const_label(CLIENT_LABEL, &self.client)
+    }
+
+    pub fn int_counter(&self, name: &str, help: &str) -> IntCounter {
+        self.metrics_registry
+            .register(IntCounter::with_opts(self.opts(name, help)).unwrap())
+    }
+
+    pub fn int_counter_vec(&self, name: &str, help: &str, label_names: &[&str]) -> IntCounterVec {
+        self.metrics_registry
+            .register(IntCounterVec::new(self.opts(name, help), label_names).unwrap())
+    }
+
+    pub fn int_gauge(&self, name: &str, help: &str) -> IntGauge {
+        self.metrics_registry
+            .register(IntGauge::with_opts(self.opts(name, help)).unwrap())
+    }
+
+    pub fn histogram(&self, name: &str, help: &str, buckets: Vec<f64>) -> Histogram {
+        let opts = HistogramOpts::from(self.opts(name, help)).buckets(buckets);
+        self.metrics_registry
+            .register(Histogram::with_opts(opts).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ic_p2p_test_utils::{consensus::U64Artifact, synthetic_artifact::SyntheticArtifact};
+
+    use super::*;
+
+    /// Check that every client registers the same metric families that only differ in the
+    /// client label.
+    #[test]
+    fn clients_share_metric_families() {
+        let metrics_registry = MetricsRegistry::new();
+        ConsensusManagerMetrics::new::<U64Artifact>(&metrics_registry);
+        ConsensusManagerMetrics::new::<SyntheticArtifact>(&metrics_registry);
+
+        let families: Vec<_> = metrics_registry
+            .prometheus_registry()
+            .gather()
+            .into_iter()
+            .filter(|family| family.get_name().starts_with(METRICS_NAMESPACE))
+            .collect();
+        assert!(!families.is_empty());
+        for family in families {
+            let mut clients: Vec<_> = family
+                .get_metric()
+                .iter()
+                .flat_map(|metric| metric.get_label())
+                .filter(|label| label.get_name() == CLIENT_LABEL)
+                .map(|label| label.get_value().to_string())
+                .collect();
+            clients.sort();
+            clients.dedup();
+            assert_eq!(
+                clients,
+                vec![
+                    uri_prefix::<U64Artifact>(),
+                    uri_prefix::<SyntheticArtifact>()
+                ],
+                "{}",
+                family.get_name()
+            );
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::{collections::HashMap, num::NonZeroU64, sync::Mutex, time::Duration};
+
+use ic_base_types::NodeId;
+use tokio::time::{self, Instant};
+
+use crate::metrics::ConsensusManagerMetrics;
+
+/// Caps the rate at which a client pushes slot updates, i.e. adverts and artifacts, to its peers.
+///
+/// Each limit is enforced with a token bucket that holds up to `burst` worth of bytes at the
+/// respective rate. An update is pushed as soon as neither bucket is in debt, so that updates
+/// larger than a bucket are delayed instead of rejected while the average rate still holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SendRateLimit {
+    /// Bytes per second pushed to a single peer.
+    pub per_peer_bytes_per_second: NonZeroU64,
+    /// Bytes per second pushed to all peers together.
+    pub aggregate_bytes_per_second: NonZeroU64,
+    /// Traffic at the full rate that can be pushed at once after being idle.
+    pub burst: Duration,
+}
+
+/// Enforces a [`SendRateLimit`] for the pushes of a single client.
+pub(crate) struct SendRateLimiter {
+    limit: SendRateLimit,
+    metrics: ConsensusManagerMetrics,
+    buckets: Mutex<Buckets>,
+}
+
+struct Buckets {
+    aggregate: TokenBucket,
+    per_peer: HashMap<NodeId, TokenBucket>,
+}
+
+impl SendRateLimiter {
+    pub(crate) fn new(limit: SendRateLimit, metrics: ConsensusManagerMetrics) -> Self {
+        Self {
+            limit,
+            metrics,
+            buckets: Mutex::new(Buckets {
+                aggregate: TokenBucket::new(
+                    limit.aggregate_bytes_per_second,
+                    limit.burst,
+                    Instant::now(),
+                ),
+                per_peer: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Waits until `bytes` can be pushed to `peer` and takes them from the buckets.
+    pub(crate) async fn acquire(&self, peer: NodeId, bytes: usize) {
+        let start = Instant::now();
+        loop {
+            let wait = self.try_acquire(peer, bytes, Instant::now());
+            if wait.is_zero() {
+                break;
+            }
+            time::sleep(wait).await;
+        }
+        let delay = start.elapsed();
+        if !delay.is_zero() {
+            self.metrics.send_view_rate_limited_total.inc();
+            self.metrics
+                .send_view_rate_limit_delay_duration
+                .observe(delay.as_secs_f64());
+        }
+    }
+
+    /// Takes `bytes` from the buckets if neither of them is in debt. Otherwise, returns how
+    /// long to wait before trying again.
+    fn try_acquire(&self, peer: NodeId, bytes: usize, now: Instant) -> Duration {
+        let mut buckets = self.buckets.lock().unwrap();
+        let Buckets {
+            aggregate,
+            per_peer,
+        } = &mut *buckets;
+        let peer_bucket = per_peer.entry(peer).or_insert_with(|| {
+            TokenBucket::new(self.limit.per_peer_bytes_per_second, self.limit.burst, now)
+        });
+        aggregate.refill(now);
+        peer_bucket.refill(now);
+
+        let wait = aggregate
+            .time_until_available()
+            .max(peer_bucket.time_until_available());
+        if wait.is_zero() {
+            aggregate.take(bytes);
+            peer_bucket.take(bytes);
+        }
+        wait
+    }
+}
+
+struct TokenBucket {
+    bytes_per_second: f64,
+    capacity: f64,
+    /// Negative if more bytes were taken than the bucket held.
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(bytes_per_second: NonZeroU64, burst: Duration, now: Instant) -> Self {
+        let bytes_per_second = bytes_per_second.get() as f64;
+        let capacity = bytes_per_second * burst.as_secs_f64();
+        Self {
+            bytes_per_second,
+            capacity,
+            tokens: capacity,
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * self.bytes_per_second).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    fn time_until_available(&self) -> Duration {
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.bytes_per_second)
+        }
+    }
+
+    fn take(&mut self, bytes: usize) {
+        self.tokens -= bytes as f64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ic_metrics::MetricsRegistry;
+    use ic_p2p_test_utils::consensus::U64Artifact;
+    use ic_types_test_utils::ids::{NODE_1, NODE_2};
+
+    use super::*;
+
+    fn limiter(per_peer: u64, aggregate: u64, burst: Duration) -> SendRateLimiter {
+        SendRateLimiter::new(
+            SendRateLimit {
+                per_peer_bytes_per_second: NonZeroU64::new(per_peer).unwrap(),
+                aggregate_bytes_per_second: NonZeroU64::new(aggregate).unwrap(),
+                burst,
+            },
+            ConsensusManagerMetrics::new::<U64Artifact>(&MetricsRegistry::default()),
+        )
+    }
+
+    #[test]
+    fn per_peer_limit_delays_only_that_peer() {
+        let limiter = limiter(1_000, 1_000_000, Duration::from_secs(1));
+        let now = Instant::now();
+
+        assert_eq!(limiter.try_acquire(NODE_1, 1_500, now), Duration::ZERO);
+        assert_eq!(
+            limiter.try_acquire(NODE_1, 100, now),
+            Duration::from_millis(500)
+        );
+        assert_eq!(limiter.try_acquire(NODE_2, 100, now), Duration::ZERO);
+        assert_eq!(
+            limiter.try_acquire(NODE_1, 100, now + Duration::from_millis(500)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn aggregate_limit_delays_all_peers() {
+        let limiter = limiter(1_000_000, 1_000, Duration::from_secs(1));
+        let now = Instant::now();
+
+        assert_eq!(limiter.try_acquire(NODE_1, 2_000, now), Duration::ZERO);
+        assert_eq!(
+            limiter.try_acquire(NODE_2, 100, now),
+            Duration::from_secs(1)
+        );
+        assert_eq!(
+            limiter.try_acquire(NODE_2, 100, now + Duration::from_secs(1)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn idle_bucket_does_not_exceed_burst() {
+        let limiter = limiter(1_000, 1_000_000, Duration::from_secs(1));
+        let start = Instant::now();
+        assert_eq!(limiter.try_acquire(NODE_1, 500, start), Duration::ZERO);
+
+        let now = start + Duration::from_secs(60);
+        assert_eq!(limiter.try_acquire(NODE_1, 2_000, now), Duration::ZERO);
+        assert_eq!(
+            limiter.try_acquire(NODE_1, 100, now),
+            Duration::from_secs(1)
+        );
+    }
+}
@@ -2,7 +2,11 @@
 
 use std::{
     collections::{hash_map::Entry, HashMap, HashSet},
-    sync::{Arc, RwLock},
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
     time::Duration,
 };
 
@@ -11,7 +15,8 @@ use crate::{
         ConsensusManagerMetrics, DOWNLOAD_TASK_RESULT_ALL_PEERS_DELETED,
         DOWNLOAD_TASK_RESULT_COMPLETED, DOWNLOAD_TASK_RESULT_DROP,
     },
-    uri_prefix, CommitId, SlotNumber, SlotUpdate, Update,
+    uri_prefix, AttributeDigest, CommitId, ConsensusManagerConfig, RetryBackoff, SlotNumber,
+    SlotUpdate, Update,
 };
 use axum::{
     extract::{DefaultBodyLimit, State},
@@ -19,31 +24,44 @@ use axum::{
     routing::any,
     Extension, Router,
 };
-use backoff::{backoff::Backoff, ExponentialBackoffBuilder};
+use backoff::backoff::Backoff;
 use bytes::Bytes;
 use ic_base_types::NodeId;
-use ic_interfaces::p2p::consensus::{Priority, PriorityFn, PriorityFnFactory, ValidatedPoolReader};
-use ic_logger::{error, warn, ReplicaLogger};
+use ic_crypto_sha2::Sha256;
+use ic_interfaces::p2p::consensus::{
+    Priority, PriorityFn, PriorityFnFactory, ValidatedPoolReader, PRIORITY_DROP_HYSTERESIS,
+};
+use ic_logger::{error, info, warn, ReplicaLogger};
 use ic_protobuf::{p2p::v1 as pb, proxy::ProtoProxy};
 use ic_quic_transport::{ConnId, SubnetTopology, Transport};
 use ic_types::artifact::{PbArtifact, UnvalidatedArtifactMutation};
 use prost::Message;
-use rand::{rngs::SmallRng, seq::IteratorRandom, SeedableRng};
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    rngs::SmallRng,
+    Rng, SeedableRng,
+};
 use tokio::{
     runtime::Handle,
     select,
     sync::{
         mpsc::{Receiver, Sender, UnboundedSender},
-        watch,
+        watch, Semaphore,
     },
-    task::JoinSet,
-    time::{self, sleep_until, timeout_at, Instant, MissedTickBehavior},
+    task::{AbortHandle, JoinSet},
+    time::{self, sleep_until, Instant, MissedTickBehavior},
 };
 use tracing::instrument;
 
-const MIN_ARTIFACT_RPC_TIMEOUT: Duration = Duration::from_secs(5);
-const MAX_ARTIFACT_RPC_TIMEOUT: Duration = Duration::from_secs(120);
 const PRIORITY_FUNCTION_UPDATE_INTERVAL: Duration = Duration::from_secs(3);
+const ATTRIBUTE_FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+const ATTRIBUTE_FETCH_ATTEMPTS: usize = 3;
+// Lower bound of the round-trip time used to weight peers, so that a single peer with a
+// tiny round-trip time does not get all downloads.
+const MIN_PEER_SELECTION_RTT: Duration = Duration::from_millis(1);
+/// Minimum interval between two logged samples of slots that were overwritten before
+/// the artifact was downloaded.
+const SLOT_CHURN_LOG_INTERVAL_SECONDS: u64 = 30;
 
 type ValidatedPoolReaderRef<T> = Arc<RwLock<dyn ValidatedPoolReader<T> + Send + Sync>>;
 type ReceivedAdvertSender<A> = Sender<(SlotUpdate<A>, NodeId, ConnId)>;
@@ -52,13 +70,18 @@ type ReceivedAdvertSender<A> = Sender<(SlotUpdate<A>, NodeId, ConnId)>;
 pub fn build_axum_router<Artifact: PbArtifact>(
     log: ReplicaLogger,
     pool: ValidatedPoolReaderRef<Artifact>,
+    channel_capacity: NonZeroUsize,
 ) -> (Router, Receiver<(SlotUpdate<Artifact>, NodeId, ConnId)>) {
-    let (update_tx, update_rx) = tokio::sync::mpsc::channel(100);
+    let (update_tx, update_rx) = tokio::sync::mpsc::channel(channel_capacity.get());
     let router = Router::new()
         .route(
             &format!("/{}/rpc", uri_prefix::<Artifact>()),
             any(rpc_handler),
         )
+        .route(
+            &format!("/{}/attribute", uri_prefix::<Artifact>()),
+            any(attribute_handler),
+        )
         .with_state(pool)
         .route(
             &format!("/{}/update", uri_prefix::<Artifact>()),
@@ -90,6 +113,27 @@ async fn rpc_handler<Artifact: PbArtifact>(
     Ok(bytes)
 }
 
+async fn attribute_handler<Artifact: PbArtifact>(
+    State(pool): State<ValidatedPoolReaderRef<Artifact>>,
+    payload: Bytes,
+) -> Result<Bytes, StatusCode> {
+    let jh = tokio::task::spawn_blocking(move || {
+        let id: Artifact::Id =
+            Artifact::PbId::proxy_decode(&payload).map_err(|_| StatusCode::BAD_REQUEST)?;
+        let artifact = pool
+            .read()
+            .unwrap()
+            .get(&id)
+            .ok_or(StatusCode::NO_CONTENT)?;
+        Ok::<_, StatusCode>(Bytes::from(Artifact::PbAttribute::proxy_encode(
+            artifact.attribute(),
+        )))
+    });
+    let bytes = jh.await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)??;
+
+    Ok(bytes)
+}
+
 async fn update_handler<Artifact: PbArtifact>(
     State((log, sender)): State<(ReplicaLogger, ReceivedAdvertSender<Artifact>)>,
     Extension(peer): Extension<NodeId>,
@@ -106,11 +150,19 @@ async fn update_handler<Artifact: PbArtifact>(
                 let id: Artifact::Id = Artifact::PbId::decode(advert.id.as_slice())
                     .map(|pb_id| pb_id.try_into().map_err(|_| StatusCode::BAD_REQUEST))
                     .map_err(|_| StatusCode::BAD_REQUEST)??;
-                let attr: Artifact::Attribute =
-                    Artifact::PbAttribute::decode(advert.attribute.as_slice())
-                        .map(|pb_attr| pb_attr.try_into().map_err(|_| StatusCode::BAD_REQUEST))
-                        .map_err(|_| StatusCode::BAD_REQUEST)??;
-                Update::Advert((id, attr))
+                if advert.attribute_digest.is_empty() {
+                    let attr: Artifact::Attribute =
+                        Artifact::PbAttribute::decode(advert.attribute.as_slice())
+                            .map(|pb_attr| pb_attr.try_into().map_err(|_| StatusCode::BAD_REQUEST))
+                            .map_err(|_| StatusCode::BAD_REQUEST)??;
+                    Update::Advert((id, attr))
+                } else {
+                    let digest: AttributeDigest = advert
+                        .attribute_digest
+                        .try_into()
+                        .map_err(|_| StatusCode::BAD_REQUEST)?;
+                    Update::CompactAdvert((id, digest))
+                }
             }
             Some(pb::slot_update::Update::Artifact(artifact)) => {
                 let message: Artifact = Artifact::PbMessage::decode(artifact.as_slice())
@@ -133,24 +185,49 @@ async fn update_handler<Artifact: PbArtifact>(
 }
 
 #[derive(Debug)]
-pub struct PeerCounter(HashMap<NodeId, u32>);
+pub struct PeerCounter {
+    peers: HashMap<NodeId, u32>,
+    // Peers that committed a slot in which the artifact was advertised to a different artifact.
+    // Only tracked if the download fallback is enabled.
+    slot_peers: HashSet<NodeId>,
+    // Set by the download task once the artifact was downloaded.
+    downloaded: AtomicBool,
+}
 
 impl PeerCounter {
     pub fn new() -> Self {
-        Self(HashMap::new())
+        Self {
+            peers: HashMap::new(),
+            slot_peers: HashSet::new(),
+            downloaded: AtomicBool::new(false),
+        }
     }
 
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.peers.is_empty()
     }
 
     pub fn peers(&self) -> impl Iterator<Item = &NodeId> {
-        self.0.keys()
+        self.peers.keys()
+    }
+
+    /// Returns the peers that committed a slot in which the artifact was advertised.
+    pub fn slot_peers(&self) -> impl Iterator<Item = &NodeId> {
+        self.slot_peers.iter()
+    }
+
+    /// Returns true if the artifact was downloaded by the current download task.
+    pub fn is_downloaded(&self) -> bool {
+        self.downloaded.load(Ordering::Relaxed)
+    }
+
+    fn set_downloaded(&self, downloaded: bool) {
+        self.downloaded.store(downloaded, Ordering::Relaxed);
     }
 
     /// Returns true if value is newly inserted
     pub fn insert(&mut self, node: NodeId) -> bool {
-        match self.0.entry(node) {
+        match self.peers.entry(node) {
             Entry::Occupied(mut entry) => {
                 *entry.get_mut() += 1;
                 false
@@ -162,9 +239,19 @@ impl PeerCounter {
         }
     }
 
+    /// Returns true if value is newly inserted
+    fn insert_slot_peer(&mut self, node: NodeId) -> bool {
+        self.slot_peers.insert(node)
+    }
+
+    /// Returns true if value was present
+    fn remove_slot_peer(&mut self, node: NodeId) -> bool {
+        self.slot_peers.remove(&node)
+    }
+
     /// Returns true if removed key was present and counter got to zero
     pub fn remove(&mut self, node: NodeId) -> bool {
-        match self.0.entry(node) {
+        match self.peers.entry(node) {
             Entry::Occupied(mut entry) => {
                 assert!(*entry.get() != 0);
 
@@ -205,6 +292,17 @@ pub(crate) struct ConsensusManagerReceiver<Artifact: PbArtifact, Pool, ReceivedA
         Artifact::Id,
         Artifact::Attribute,
     )>,
+    // Fetches of attributes of compact adverts. Resolve to the peer and slot of the fetch,
+    // and to the full advert if successful.
+    #[allow(clippy::type_complexity)]
+    attribute_fetch_tasks: JoinSet<(NodeId, SlotNumber, ConnId, CommitId, Option<ReceivedAdvert>)>,
+    // The attribute fetch in flight for each peer and slot. Bounds the fetches like the slot
+    // table bounds the adverts.
+    pending_attribute_fetches: HashMap<NodeId, HashMap<SlotNumber, PendingAttributeFetch>>,
+    // Shared by all download tasks to bound the artifact requests in flight, if configured.
+    download_permits: Option<Arc<Semaphore>>,
+    download_retry_backoff: RetryBackoff,
+    download_fallback_after_failures: Option<NonZeroUsize>,
 
     topology_watcher: watch::Receiver<SubnetTopology>,
 }
@@ -218,6 +316,7 @@ where
 {
     pub(crate) fn run(
         log: ReplicaLogger,
+        config: &ConsensusManagerConfig,
         metrics: ConsensusManagerMetrics,
         rt_handle: Handle,
         adverts_received: Receiver<(SlotUpdate<Artifact>, NodeId, ConnId)>,
@@ -244,6 +343,13 @@ where
             active_downloads: HashMap::new(),
             slot_table: HashMap::new(),
             artifact_processor_tasks: JoinSet::new(),
+            attribute_fetch_tasks: JoinSet::new(),
+            pending_attribute_fetches: HashMap::new(),
+            download_permits: config
+                .max_concurrent_downloads
+                .map(|max| Arc::new(Semaphore::new(max.get()))),
+            download_retry_backoff: config.download_retry_backoff,
+            download_fallback_after_failures: config.download_fallback_after_failures,
             topology_watcher,
         };
 
@@ -263,6 +369,22 @@ where
                 Some((advert_update, peer_id, conn_id)) = self.adverts_received.recv() => {
                     self.handle_advert_receive(advert_update, peer_id, conn_id);
                 }
+                Some(result) = self.attribute_fetch_tasks.join_next() => {
+                    match result {
+                        Ok((peer_id, slot_number, conn_id, commit_id, advert)) => {
+                            self.handle_attribute_fetch_joined(peer_id, slot_number, conn_id, commit_id);
+                            if let Some((advert_update, peer_id, conn_id)) = advert {
+                                self.handle_advert_receive(advert_update, peer_id, conn_id);
+                            }
+                        }
+                        // Fetches are aborted when a newer advert for their slot arrives.
+                        Err(err) => {
+                            if err.is_panic() {
+                                std::panic::resume_unwind(err.into_panic());
+                            }
+                        }
+                    }
+                }
                 Some(result) = self.artifact_processor_tasks.join_next() => {
                     match result {
                         Ok((receiver, id, attr)) => {
@@ -327,6 +449,8 @@ where
         if !peer_rx.borrow().is_empty() {
             self.metrics.download_task_restart_after_join_total.inc();
             self.metrics.download_task_started_total.inc();
+            // The artifact was purged from the unvalidated pool, the new task downloads it again.
+            peer_rx.borrow().set_downloaded(false);
             self.artifact_processor_tasks.spawn_on(
                 Self::process_advert(
                     self.log.clone(),
@@ -337,6 +461,9 @@ where
                     self.current_priority_fn.subscribe(),
                     self.sender.clone(),
                     self.transport.clone(),
+                    self.download_permits.clone(),
+                    self.download_retry_backoff,
+                    self.download_fallback_after_failures,
                     self.metrics.clone(),
                 ),
                 &self.rt_handle,
@@ -360,7 +487,6 @@ where
         peer_id: NodeId,
         connection_id: ConnId,
     ) {
-        self.metrics.slot_table_updates_total.inc();
         let SlotUpdate {
             slot_number,
             commit_id,
@@ -370,7 +496,20 @@ where
         let (id, attribute, artifact) = match update {
             Update::Artifact(artifact) => (artifact.id(), artifact.attribute(), Some(artifact)),
             Update::Advert((id, attribute)) => (id, attribute, None),
+            // The advert is processed once the attribute is available.
+            Update::CompactAdvert((id, digest)) => {
+                self.spawn_attribute_fetch(
+                    slot_number,
+                    commit_id,
+                    id,
+                    digest,
+                    peer_id,
+                    connection_id,
+                );
+                return;
+            }
         };
+        self.metrics.slot_table_updates_total.inc();
 
         if artifact.is_some() {
             self.metrics.slot_table_updates_with_artifact_total.inc();
@@ -432,17 +571,38 @@ where
                             self.current_priority_fn.subscribe(),
                             self.sender.clone(),
                             self.transport.clone(),
+                            self.download_permits.clone(),
+                            self.download_retry_backoff,
+                            self.download_fallback_after_failures,
                             self.metrics.clone(),
                         ),
                         &self.rt_handle,
                     );
                 }
             }
+
+            if self.download_fallback_after_failures.is_some() {
+                self.record_slot_peers(peer_id, slot_number, &id);
+            }
         }
 
         if let Some(to_remove) = to_remove {
             match self.active_downloads.get_mut(&to_remove) {
                 Some(sender) => {
+                    if to_remove != id && !sender.borrow().is_downloaded() {
+                        self.metrics
+                            .slot_table_overwrite_before_download_total
+                            .inc();
+                        info!(
+                            every_n_seconds => SLOT_CHURN_LOG_INTERVAL_SECONDS,
+                            self.log,
+                            "Slot {} of peer {} for {} overwritten before artifact {:?} was downloaded",
+                            slot_number,
+                            peer_id,
+                            uri_prefix::<Artifact>(),
+                            Artifact::PbId::from(to_remove.clone())
+                        );
+                    }
                     sender.send_if_modified(|h| h.remove(peer_id));
                     self.metrics.slot_table_removals_total.inc();
                 }
@@ -459,7 +619,158 @@ where
         }
     }
 
+    /// Records the peers that committed `slot_number` to a different artifact than `peer_id`
+    /// as fallback peers of each other's downloads. Download tasks are not notified because
+    /// the fallback peers are only read once the download from the advertising peers stalled.
+    fn record_slot_peers(&mut self, peer_id: NodeId, slot_number: SlotNumber, id: &Artifact::Id) {
+        for (node_id, slots) in &self.slot_table {
+            let Some(entry) = slots.get(&slot_number) else {
+                continue;
+            };
+            if *node_id == peer_id || &entry.id == id {
+                continue;
+            }
+            if let Some(sender) = self.active_downloads.get(id) {
+                sender.send_if_modified(|h| {
+                    h.insert_slot_peer(*node_id);
+                    false
+                });
+            }
+            if let Some(sender) = self.active_downloads.get(&entry.id) {
+                sender.send_if_modified(|h| {
+                    h.insert_slot_peer(peer_id);
+                    false
+                });
+            }
+        }
+    }
+
+    /// Starts fetching the attribute of a compact advert. At most one fetch is in flight per
+    /// peer and slot: the fetch for a newer advert of the slot replaces the pending one, and
+    /// an advert that is not newer than the pending one is dropped.
+    fn spawn_attribute_fetch(
+        &mut self,
+        slot_number: SlotNumber,
+        commit_id: CommitId,
+        id: Artifact::Id,
+        digest: AttributeDigest,
+        peer_id: NodeId,
+        conn_id: ConnId,
+    ) {
+        let pending_fetches = self.pending_attribute_fetches.entry(peer_id).or_default();
+        if let Some(pending_fetch) = pending_fetches.get(&slot_number) {
+            if (conn_id, commit_id) <= (pending_fetch.conn_id, pending_fetch.commit_id) {
+                self.metrics.attribute_fetches_superseded_total.inc();
+                return;
+            }
+            pending_fetch.abort_handle.abort();
+            self.metrics.attribute_fetches_superseded_total.inc();
+        }
+        self.metrics.attribute_fetches_total.inc();
+        let fetch = Self::fetch_attribute(
+            self.log.clone(),
+            slot_number,
+            commit_id,
+            id,
+            digest,
+            peer_id,
+            conn_id,
+            self.transport.clone(),
+            self.metrics.clone(),
+        );
+        let abort_handle = self.attribute_fetch_tasks.spawn_on(
+            async move { (peer_id, slot_number, conn_id, commit_id, fetch.await) },
+            &self.rt_handle,
+        );
+        pending_fetches.insert(
+            slot_number,
+            PendingAttributeFetch {
+                conn_id,
+                commit_id,
+                abort_handle,
+            },
+        );
+    }
+
+    /// Forgets the attribute fetch for the given advert, unless it was replaced in the meantime.
+    fn handle_attribute_fetch_joined(
+        &mut self,
+        peer_id: NodeId,
+        slot_number: SlotNumber,
+        conn_id: ConnId,
+        commit_id: CommitId,
+    ) {
+        let Some(pending_fetches) = self.pending_attribute_fetches.get_mut(&peer_id) else {
+            return;
+        };
+        if let Entry::Occupied(pending_fetch) = pending_fetches.entry(slot_number) {
+            if pending_fetch.get().conn_id == conn_id && pending_fetch.get().commit_id == commit_id
+            {
+                pending_fetch.remove();
+            }
+        }
+        if pending_fetches.is_empty() {
+            self.pending_attribute_fetches.remove(&peer_id);
+        }
+    }
+
+    /// Fetches the attribute of a compact advert from the peer that sent it and verifies it
+    /// against the advertised digest. Returns the advert with the attribute if successful.
+    #[instrument(skip_all)]
+    async fn fetch_attribute(
+        log: ReplicaLogger,
+        slot_number: SlotNumber,
+        commit_id: CommitId,
+        id: Artifact::Id,
+        digest: AttributeDigest,
+        peer_id: NodeId,
+        conn_id: ConnId,
+        transport: Arc<dyn Transport>,
+        metrics: ConsensusManagerMetrics,
+    ) -> Option<(SlotUpdate<Artifact>, NodeId, ConnId)> {
+        for _ in 0..ATTRIBUTE_FETCH_ATTEMPTS {
+            let request = Request::builder()
+                .uri(format!("/{}/attribute", uri_prefix::<Artifact>()))
+                .body(Bytes::from(Artifact::PbId::proxy_encode(id.clone())))
+                .unwrap();
+            let deadline = Instant::now() + ATTRIBUTE_FETCH_TIMEOUT;
+            match transport
+                .rpc_with_deadline(&peer_id, request, deadline)
+                .await
+            {
+                Ok(response) if response.status() == StatusCode::OK => {
+                    let body = response.into_body();
+                    if Sha256::hash(&body) != digest {
+                        warn!(
+                            log,
+                            "Peer {} responded with attribute that does not match the advert",
+                            peer_id
+                        );
+                        break;
+                    }
+                    if let Ok(attribute) = Artifact::PbAttribute::proxy_decode(&body) {
+                        let advert_update = SlotUpdate {
+                            slot_number,
+                            commit_id,
+                            update: Update::Advert((id, attribute)),
+                        };
+                        return Some((advert_update, peer_id, conn_id));
+                    }
+                    break;
+                }
+                // The peer no longer has the artifact.
+                Ok(response) if response.status() == StatusCode::NO_CONTENT => break,
+                _ => sleep_until(deadline).await,
+            }
+        }
+        metrics.attribute_fetch_errors_total.inc();
+        None
+    }
+
     /// Waits until advert resolves to fetch. If all peers are removed or priority becomes drop `DownloadStopped` is returned.
+    ///
+    /// Adverts that are already `queued` are only dropped after [`PRIORITY_DROP_HYSTERESIS`] consecutive
+    /// priority function refreshes evaluated them to drop.
     #[instrument(skip_all)]
     async fn wait_fetch(
         id: &Artifact::Id,
@@ -470,38 +781,70 @@ where
         mut priority_fn_watcher: &mut watch::Receiver<
             PriorityFn<Artifact::Id, Artifact::Attribute>,
         >,
+        mut queued: bool,
     ) -> Result<(), DownloadStopped> {
         let mut priority = priority_fn_watcher.borrow_and_update()(id, attr);
+        let mut consecutive_drops = usize::from(priority == Priority::Drop);
+        let (mut stashed, mut drop_deferred) = (false, false);
 
-        // Clear the artifact from memory if it was pushed.
-        if let Priority::Stash = priority {
-            artifact.take();
-            metrics.download_task_stashed_total.inc();
-        }
-
-        while let Priority::Stash = priority {
-            select! {
-                Ok(_) = priority_fn_watcher.changed() => {
-                    priority = priority_fn_watcher.borrow_and_update()(id, attr);
+        loop {
+            let fetch_at = match priority {
+                Priority::FetchNow => return Ok(()),
+                Priority::Drop if !queued || consecutive_drops >= PRIORITY_DROP_HYSTERESIS => {
+                    return Err(DownloadStopped::PriorityIsDrop);
+                }
+                Priority::Drop => {
+                    if !drop_deferred {
+                        drop_deferred = true;
+                        metrics.download_task_drop_deferred_total.inc();
+                    }
+                    None
                 }
+                Priority::Stash => {
+                    // Clear the artifact from memory if it was pushed.
+                    artifact.take();
+                    if !stashed {
+                        stashed = true;
+                        metrics.download_task_stashed_total.inc();
+                    }
+                    None
+                }
+                Priority::FetchAfter(deadline) => {
+                    let deadline = Instant::from_std(deadline);
+                    if deadline <= Instant::now() {
+                        return Ok(());
+                    }
+                    Some(deadline)
+                }
+            };
+            queued = true;
+
+            let refreshed = select! {
+                Ok(_) = priority_fn_watcher.changed() => true,
+                () = sleep_until(fetch_at.unwrap_or_else(Instant::now)), if fetch_at.is_some() => false,
                 res = peer_rx.changed() => {
                     match res {
                         Ok(()) if peer_rx.borrow().is_empty() => {
                             return Err(DownloadStopped::AllPeersDeletedTheArtifact);
                         },
-                        Ok(()) => {},
+                        Ok(()) => false,
                         Err(_) => {
                             return Err(DownloadStopped::AllPeersDeletedTheArtifact);
                         }
                     }
                 }
-            }
-        }
+            };
 
-        if let Priority::Drop = priority {
-            return Err(DownloadStopped::PriorityIsDrop);
+            // Re-evaluate after every wake-up, so that a deadline or peer update never acts on
+            // a stale priority. Only refreshes of the priority function count as drops.
+            let refreshed = refreshed || priority_fn_watcher.has_changed().unwrap_or(false);
+            priority = priority_fn_watcher.borrow_and_update()(id, attr);
+            consecutive_drops = match priority {
+                Priority::Drop if refreshed => consecutive_drops + 1,
+                Priority::Drop => consecutive_drops,
+                _ => 0,
+            };
         }
-        Ok(())
     }
 
     /// Downloads a given artifact.
@@ -512,6 +855,9 @@ where
     /// - The priority function evaluates the advert to [`Priority::Drop`] -> [`DownloadStopped::PriorityIsDrop`]
     /// - The set of peers advertising the artifact, `peer_rx`, becomes empty -> [`DownloadStopped::AllPeersDeletedTheArtifact`]
     /// and the failure condition is reported in the error variant of the returned result.
+    ///
+    /// After `fallback_after_failures` consecutive failed attempts, the artifact is also requested
+    /// from peers that committed a slot in which it was advertised to a different artifact.
     #[instrument(skip_all)]
     async fn download_artifact(
         log: ReplicaLogger,
@@ -522,6 +868,9 @@ where
         mut peer_rx: &mut watch::Receiver<PeerCounter>,
         mut priority_fn_watcher: watch::Receiver<PriorityFn<Artifact::Id, Artifact::Attribute>>,
         transport: Arc<dyn Transport>,
+        download_permits: Option<Arc<Semaphore>>,
+        retry_backoff: RetryBackoff,
+        fallback_after_failures: Option<NonZeroUsize>,
         metrics: ConsensusManagerMetrics,
     ) -> Result<(Artifact, NodeId), DownloadStopped> {
         // Evaluate priority and wait until we should fetch.
@@ -532,14 +881,11 @@ where
             &metrics,
             peer_rx,
             &mut priority_fn_watcher,
+            false,
         )
         .await?;
 
-        let mut artifact_download_timeout = ExponentialBackoffBuilder::new()
-            .with_initial_interval(MIN_ARTIFACT_RPC_TIMEOUT)
-            .with_max_interval(MAX_ARTIFACT_RPC_TIMEOUT)
-            .with_max_elapsed_time(None)
-            .build();
+        let mut artifact_download_timeout = retry_backoff.build();
 
         match artifact {
             // Artifact was pushed by peer. In this case we don't need check that the artifact ID corresponds
@@ -554,8 +900,24 @@ where
                     .download_task_artifact_download_duration
                     .start_timer();
                 let mut rng = SmallRng::from_entropy();
+                let mut tried_peers = HashSet::new();
+                let (mut consecutive_failures, mut fallback) = (0, false);
                 while let Some(peer) = {
-                    let peer = peer_rx.borrow().peers().choose(&mut rng).copied();
+                    let peer = if fallback {
+                        choose_peer(
+                            &fallback_peers(&peer_rx.borrow()),
+                            &mut tried_peers,
+                            transport.as_ref(),
+                            &mut rng,
+                        )
+                    } else {
+                        choose_peer(
+                            &peer_rx.borrow(),
+                            &mut tried_peers,
+                            transport.as_ref(),
+                            &mut rng,
+                        )
+                    };
                     peer
                 } {
                     let bytes = Bytes::from(Artifact::PbId::proxy_encode(id.clone()));
@@ -568,12 +930,26 @@ where
                         artifact_download_timeout.reset();
                     }
 
+                    // Held until the request completes or times out.
+                    let permit = match &download_permits {
+                        Some(permits) => Some(
+                            permits
+                                .clone()
+                                .acquire_owned()
+                                .await
+                                .expect("The semaphore is never closed"),
+                        ),
+                        None => None,
+                    };
                     let next_request_at = Instant::now()
                         + artifact_download_timeout
                             .next_backoff()
-                            .unwrap_or(MAX_ARTIFACT_RPC_TIMEOUT);
-                    match timeout_at(next_request_at, transport.rpc(&peer, request)).await {
-                        Ok(Ok(response)) if response.status() == StatusCode::OK => {
+                            .unwrap_or(retry_backoff.max_interval);
+                    match transport
+                        .rpc_with_deadline(&peer, request, next_request_at)
+                        .await
+                    {
+                        Ok(response) if response.status() == StatusCode::OK => {
                             let body = response.into_body();
                             let decoded: Result<Artifact, _> =
                                 Artifact::PbMessage::proxy_decode(&body);
@@ -593,6 +969,20 @@ where
                             metrics.download_task_artifact_download_errors_total.inc();
                         }
                     }
+                    drop(permit);
+
+                    consecutive_failures += 1;
+                    if !fallback
+                        && fallback_after_failures.is_some_and(|n| consecutive_failures >= n.get())
+                    {
+                        fallback = true;
+                        metrics.download_task_fallback_total.inc();
+                        info!(
+                            log,
+                            "Requesting artifact from peers that committed the same slot after {} failed attempts",
+                            consecutive_failures
+                        );
+                    }
 
                     // Wait before checking the priority so we might be able to avoid an unnecessary download.
                     sleep_until(next_request_at).await;
@@ -603,6 +993,7 @@ where
                         &metrics,
                         peer_rx,
                         &mut priority_fn_watcher,
+                        true,
                     )
                     .await?;
                 }
@@ -629,6 +1020,9 @@ where
         mut priority_fn_watcher: watch::Receiver<PriorityFn<Artifact::Id, Artifact::Attribute>>,
         sender: UnboundedSender<UnvalidatedArtifactMutation<Artifact>>,
         transport: Arc<dyn Transport>,
+        download_permits: Option<Arc<Semaphore>>,
+        download_retry_backoff: RetryBackoff,
+        download_fallback_after_failures: Option<NonZeroUsize>,
         metrics: ConsensusManagerMetrics,
     ) -> (
         watch::Receiver<PeerCounter>,
@@ -644,12 +1038,16 @@ where
             &mut peer_rx,
             priority_fn_watcher,
             transport,
+            download_permits,
+            download_retry_backoff,
+            download_fallback_after_failures,
             metrics.clone(),
         )
         .await;
 
         match download_result {
             Ok((artifact, peer_id)) => {
+                peer_rx.borrow().set_downloaded(true);
                 // Send artifact to pool
                 sender.send(UnvalidatedArtifactMutation::Insert((artifact, peer_id)));
 
@@ -700,8 +1098,22 @@ where
             }
         });
 
+        self.pending_attribute_fetches
+            .retain(|node_id, pending_fetches| {
+                if new_topology.is_member(node_id) {
+                    return true;
+                }
+                for pending_fetch in pending_fetches.values() {
+                    pending_fetch.abort_handle.abort();
+                }
+                false
+            });
+
         for peers_sender in self.active_downloads.values() {
             peers_sender.send_if_modified(|set| {
+                for n in &nodes_leaving_topology {
+                    set.remove_slot_peer(*n);
+                }
                 nodes_leaving_topology
                     .iter()
                     .map(|n| set.remove(*n))
@@ -715,12 +1127,73 @@ where
     }
 }
 
+/// Picks the peer to fetch an artifact from among the advertising peers that
+/// were not tried yet, at random with a probability inversely proportional to
+/// the round-trip time, so that faster peers get more downloads without taking
+/// all of them. Peers without connection statistics are weighted like the
+/// slowest peer. Once all peers were tried, the next round starts.
+fn choose_peer<R: Rng>(
+    peers: &PeerCounter,
+    tried_peers: &mut HashSet<NodeId>,
+    transport: &dyn Transport,
+    rng: &mut R,
+) -> Option<NodeId> {
+    if peers.peers().all(|peer| tried_peers.contains(peer)) {
+        tried_peers.clear();
+    }
+    let candidates: Vec<_> = peers
+        .peers()
+        .filter(|peer| !tried_peers.contains(peer))
+        .map(|peer| {
+            let weight = transport
+                .connection_stats(peer)
+                .map(|stats| 1.0 / stats.rtt.max(MIN_PEER_SELECTION_RTT).as_secs_f64());
+            (*peer, weight)
+        })
+        .collect();
+    let slowest_weight = candidates
+        .iter()
+        .filter_map(|(_, weight)| *weight)
+        .reduce(f64::min)
+        .unwrap_or(1.0);
+    let index = WeightedIndex::new(
+        candidates
+            .iter()
+            .map(|(_, weight)| weight.unwrap_or(slowest_weight)),
+    )
+    .ok()?
+    .sample(rng);
+    let peer = candidates[index].0;
+    tried_peers.insert(peer);
+    Some(peer)
+}
+
+/// Returns the peers to fetch an artifact from once the download from the advertising
+/// peers stalled, i.e. the advertising peers and the peers that committed the same slot.
+/// Empty if no peer advertises the artifact anymore.
+fn fallback_peers(advertising: &PeerCounter) -> PeerCounter {
+    let mut peers = PeerCounter::new();
+    if !advertising.is_empty() {
+        for peer in advertising.peers().chain(advertising.slot_peers()).copied() {
+            peers.insert(peer);
+        }
+    }
+    peers
+}
+
 #[derive(Debug, PartialEq, Eq)]
 enum DownloadStopped {
     AllPeersDeletedTheArtifact,
     PriorityIsDrop,
 }
 
+/// The attribute fetch in flight for a compact advert.
+struct PendingAttributeFetch {
+    conn_id: ConnId,
+    commit_id: CommitId,
+    abort_handle: AbortHandle,
+}
+
 #[derive(PartialEq, Eq, Debug)]
 struct SlotEntry<T> {
     conn_id: ConnId,
@@ -744,15 +1217,19 @@ mod tests {
 
     use axum::{body::Body, http::Response};
     use ic_logger::replica_logger::no_op_logger;
+    use ic_memory_transport::TransportRouter;
     use ic_metrics::MetricsRegistry;
     use ic_p2p_test_utils::{
-        consensus::U64Artifact,
+        consensus::{FakeValidatedPool, SlowPoolReader, U64Artifact},
+        golden::{assert_golden_round_trip, parse_golden},
         mocks::{MockPriorityFnFactory, MockTransport, MockValidatedPoolReader},
+        strategies::{arb_interleaved_peer_events, PeerEvent},
     };
     use ic_test_utilities_logger::with_test_replica_logger;
     use ic_types::{artifact::IdentifiableArtifact, RegistryVersion};
-    use ic_types_test_utils::ids::{NODE_1, NODE_2};
+    use ic_types_test_utils::ids::{node_test_id, NODE_1, NODE_2, NODE_3, NODE_4};
     use mockall::Sequence;
+    use proptest::{prelude::ProptestConfig, proptest};
     use tokio::{sync::mpsc::UnboundedReceiver, time::timeout};
     use tower::util::ServiceExt;
 
@@ -856,6 +1333,13 @@ mod tests {
                     active_downloads: HashMap::new(),
                     slot_table: HashMap::new(),
                     artifact_processor_tasks: JoinSet::new(),
+                    attribute_fetch_tasks: JoinSet::new(),
+                    pending_attribute_fetches: HashMap::new(),
+                    download_permits: None,
+                    download_retry_backoff: ConsensusManagerConfig::default()
+                        .download_retry_backoff,
+                    download_fallback_after_failures: ConsensusManagerConfig::default()
+                        .download_fallback_after_failures,
                 }
             });
 
@@ -1063,6 +1547,12 @@ mod tests {
         );
         assert_eq!(mgr.slot_table.len(), 1);
         assert_eq!(mgr.slot_table.get(&NODE_1).unwrap().len(), 1);
+        // First advert was overwritten before it was downloaded.
+        assert_eq!(mgr.metrics.slot_table_overwrite_total.get(), 1);
+        assert_eq!(
+            mgr.metrics.slot_table_overwrite_before_download_total.get(),
+            1
+        );
 
         let joined_artifact_processor = mgr.artifact_processor_tasks.join_next().await;
 
@@ -1113,6 +1603,39 @@ mod tests {
         assert_eq!(mgr.active_downloads.len(), 1);
     }
 
+    /// Verify that peers committing the same slot to different adverts become fallback peers
+    /// of each other's downloads.
+    #[tokio::test]
+    async fn peers_committing_same_slot_become_slot_peers() {
+        let (mut mgr, _channels) = ReceiverManagerBuilder::new().build();
+        mgr.download_fallback_after_failures = NonZeroUsize::new(1);
+
+        for (node_id, id, slot_number) in [(NODE_1, 0, 1), (NODE_2, 1, 1), (NODE_3, 2, 2)] {
+            mgr.handle_advert_receive(
+                SlotUpdate {
+                    slot_number: SlotNumber::from(slot_number),
+                    commit_id: CommitId::from(1),
+                    update: Update::Advert((id, ())),
+                },
+                node_id,
+                ConnId::from(1),
+            );
+        }
+
+        let slot_peers = |id| {
+            mgr.active_downloads
+                .get(&id)
+                .unwrap()
+                .borrow()
+                .slot_peers()
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(slot_peers(0), vec![NODE_2]);
+        assert_eq!(slot_peers(1), vec![NODE_1]);
+        assert!(slot_peers(2).is_empty());
+    }
+
     /// Verify that a new download task is started if we receive a new update for an already finished download.
     #[tokio::test]
     async fn new_advert_while_download_finished() {
@@ -1294,6 +1817,114 @@ mod tests {
         );
     }
 
+    /// Check that a single drop evaluation of a stashed advert is deferred by the hysteresis
+    /// and the advert is downloaded if the priority becomes fetch afterwards.
+    #[tokio::test]
+    async fn priority_drop_is_deferred_by_hysteresis() {
+        // Abort process if a thread panics. This catches detached tokio tasks that panic.
+        // https://github.com/tokio-rs/tokio/issues/4516
+        std::panic::set_hook(Box::new(|info| {
+            let stacktrace = Backtrace::force_capture();
+            println!("Got panic. @info:{}\n@stackTrace:{}", info, stacktrace);
+            std::process::abort();
+        }));
+
+        let mut mock_pfn = MockPriorityFnFactory::new();
+        let mut seq = Sequence::new();
+        for priority in [Priority::Stash, Priority::Drop, Priority::FetchNow] {
+            mock_pfn
+                .expect_get_priority_function()
+                .times(1)
+                .returning(move |_| Box::new(move |_, _| priority))
+                .in_sequence(&mut seq);
+        }
+
+        let mut mock_transport = MockTransport::new();
+        mock_transport.expect_rpc().returning(|_, _| {
+            Ok(Response::builder()
+                .body(Bytes::from(
+                    <<U64Artifact as PbArtifact>::PbMessage>::proxy_encode(U64Artifact::id_to_msg(
+                        0, 1024,
+                    )),
+                ))
+                .unwrap())
+        });
+
+        let (mut mgr, mut channels) = ReceiverManagerBuilder::new()
+            .with_priority_fn_producer(Arc::new(mock_pfn))
+            .with_transport(Arc::new(mock_transport))
+            .build();
+
+        mgr.handle_advert_receive(
+            SlotUpdate {
+                slot_number: SlotNumber::from(1),
+                commit_id: CommitId::from(1),
+                update: Update::Advert((0, ())),
+            },
+            NODE_1,
+            ConnId::from(1),
+        );
+        // Update priority fn to drop and then to fetch.
+        mgr.handle_pfn_timer_tick();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        mgr.handle_pfn_timer_tick();
+        // Check that we received downloaded artifact.
+        assert_eq!(
+            channels.unvalidated_artifact_receiver.recv().await.unwrap(),
+            UnvalidatedArtifactMutation::Insert((U64Artifact::id_to_msg(0, 1024), NODE_1))
+        );
+    }
+
+    /// Check that an advert with a fetch deadline is downloaded once the deadline passed.
+    #[tokio::test]
+    async fn priority_fetch_after_deadline() {
+        // Abort process if a thread panics. This catches detached tokio tasks that panic.
+        // https://github.com/tokio-rs/tokio/issues/4516
+        std::panic::set_hook(Box::new(|info| {
+            let stacktrace = Backtrace::force_capture();
+            println!("Got panic. @info:{}\n@stackTrace:{}", info, stacktrace);
+            std::process::abort();
+        }));
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(1);
+        let mut mock_pfn = MockPriorityFnFactory::new();
+        mock_pfn
+            .expect_get_priority_function()
+            .times(1)
+            .returning(move |_| Box::new(move |_, _| Priority::FetchAfter(deadline)));
+
+        let mut mock_transport = MockTransport::new();
+        mock_transport.expect_rpc().returning(|_, _| {
+            Ok(Response::builder()
+                .body(Bytes::from(
+                    <<U64Artifact as PbArtifact>::PbMessage>::proxy_encode(U64Artifact::id_to_msg(
+                        0, 1024,
+                    )),
+                ))
+                .unwrap())
+        });
+
+        let (mut mgr, mut channels) = ReceiverManagerBuilder::new()
+            .with_priority_fn_producer(Arc::new(mock_pfn))
+            .with_transport(Arc::new(mock_transport))
+            .build();
+
+        mgr.handle_advert_receive(
+            SlotUpdate {
+                slot_number: SlotNumber::from(1),
+                commit_id: CommitId::from(1),
+                update: Update::Advert((0, ())),
+            },
+            NODE_1,
+            ConnId::from(1),
+        );
+        assert_eq!(
+            channels.unvalidated_artifact_receiver.recv().await.unwrap(),
+            UnvalidatedArtifactMutation::Insert((U64Artifact::id_to_msg(0, 1024), NODE_1))
+        );
+        assert!(std::time::Instant::now() >= deadline);
+    }
+
     /// Verify that slot table is pruned if node leaves subnet.
     #[tokio::test]
     async fn topology_update() {
@@ -1789,6 +2420,9 @@ mod tests {
                     &mut peer_rx,
                     pfn_rx,
                     Arc::new(mock_transport),
+                    None,
+                    ConsensusManagerConfig::default().download_retry_backoff,
+                    ConsensusManagerConfig::default().download_fallback_after_failures,
                     ConsensusManagerMetrics::new::<U64Artifact>(&MetricsRegistry::default()),
                 )
                 .await,
@@ -1797,6 +2431,180 @@ mod tests {
         });
     }
 
+    /// Verify that artifacts are only requested while a download permit is available.
+    #[tokio::test]
+    async fn download_waits_for_permit() {
+        let (rpc_tx, mut rpc_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut mock_transport = MockTransport::new();
+        mock_transport.expect_rpc().once().returning(move |_, _| {
+            rpc_tx.send(()).unwrap();
+            Ok(Response::builder()
+                .body(Bytes::from(
+                    <<U64Artifact as PbArtifact>::PbMessage>::proxy_encode(U64Artifact::id_to_msg(
+                        0, 1024,
+                    )),
+                ))
+                .unwrap())
+        });
+
+        let mut pc = PeerCounter::new();
+        pc.insert(NODE_1);
+        let (_peer_tx, mut peer_rx) = watch::channel(pc);
+        let pfn = |_: &_, _: &_| Priority::FetchNow;
+        let (_pfn_tx, pfn_rx) = watch::channel(Box::new(pfn) as Box<_>);
+        let download_permits = Arc::new(Semaphore::new(1));
+        let permit = download_permits.clone().acquire_owned().await.unwrap();
+
+        let download = tokio::spawn(async move {
+            ConsensusManagerReceiver::<
+                U64Artifact,
+                MockValidatedPoolReader<U64Artifact>,
+                (SlotUpdate<U64Artifact>, NodeId, ConnId),
+            >::download_artifact(
+                no_op_logger(),
+                &0,
+                &(),
+                None,
+                &mut peer_rx,
+                pfn_rx,
+                Arc::new(mock_transport),
+                Some(download_permits),
+                ConsensusManagerConfig::default().download_retry_backoff,
+                ConsensusManagerConfig::default().download_fallback_after_failures,
+                ConsensusManagerMetrics::new::<U64Artifact>(&MetricsRegistry::default()),
+            )
+            .await
+        });
+
+        assert!(timeout(Duration::from_millis(100), rpc_rx.recv())
+            .await
+            .is_err());
+        drop(permit);
+        assert_eq!(
+            download.await.unwrap(),
+            Ok((U64Artifact::id_to_msg(0, 1024), NODE_1))
+        );
+        assert_eq!(rpc_rx.recv().await, Some(()));
+    }
+
+    /// Verify that the priority is re-evaluated once a `FetchAfter` deadline passed, so that
+    /// the artifact is not downloaded if it became `Drop` in the meantime. The download is
+    /// only stopped once enough refreshes of the priority function evaluated it to `Drop`.
+    #[tokio::test]
+    async fn priority_is_reevaluated_after_fetch_deadline() {
+        let priority = Arc::new(Mutex::new(Priority::FetchAfter(
+            std::time::Instant::now() + Duration::from_millis(200),
+        )));
+        let priority_clone = priority.clone();
+        let pfn = move |_: &_, _: &_| *priority_clone.lock().unwrap();
+        let (pfn_tx, pfn_rx) = watch::channel(Box::new(pfn) as Box<_>);
+        let mut pc = PeerCounter::new();
+        pc.insert(NODE_1);
+        let (_peer_tx, mut peer_rx) = watch::channel(pc);
+
+        let download = tokio::spawn(async move {
+            ConsensusManagerReceiver::<
+                U64Artifact,
+                MockValidatedPoolReader<U64Artifact>,
+                (SlotUpdate<U64Artifact>, NodeId, ConnId),
+            >::download_artifact(
+                no_op_logger(),
+                &0,
+                &(),
+                None,
+                &mut peer_rx,
+                pfn_rx,
+                // Any request panics.
+                Arc::new(MockTransport::new()),
+                None,
+                ConsensusManagerConfig::default().download_retry_backoff,
+                ConsensusManagerConfig::default().download_fallback_after_failures,
+                ConsensusManagerMetrics::new::<U64Artifact>(&MetricsRegistry::default()),
+            )
+            .await
+        });
+        // Let the download wait for the deadline first.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        // The priority function changes its result without being refreshed.
+        *priority.lock().unwrap() = Priority::Drop;
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(!download.is_finished());
+
+        for _ in 0..PRIORITY_DROP_HYSTERESIS {
+            pfn_tx.send_modify(|_| {});
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(
+            download.await.unwrap(),
+            Err(DownloadStopped::PriorityIsDrop)
+        );
+    }
+
+    /// Verify that the artifact is requested from peers that committed the same slot once
+    /// the downloads from the advertising peer failed repeatedly.
+    #[tokio::test]
+    async fn download_falls_back_to_slot_peers() {
+        let mut mock_transport = MockTransport::new();
+        mock_transport
+            .expect_rpc()
+            .withf(|peer, _| *peer == NODE_1)
+            .times(2)
+            .returning(|_, _| {
+                Ok(Response::builder()
+                    .status(StatusCode::NO_CONTENT)
+                    .body(Bytes::new())
+                    .unwrap())
+            });
+        mock_transport
+            .expect_rpc()
+            .withf(|peer, _| *peer == NODE_2)
+            .once()
+            .returning(|_, _| {
+                Ok(Response::builder()
+                    .body(Bytes::from(
+                        <<U64Artifact as PbArtifact>::PbMessage>::proxy_encode(
+                            U64Artifact::id_to_msg(0, 1024),
+                        ),
+                    ))
+                    .unwrap())
+            });
+
+        let mut pc = PeerCounter::new();
+        pc.insert(NODE_1);
+        pc.insert_slot_peer(NODE_2);
+        let (_peer_tx, mut peer_rx) = watch::channel(pc);
+        let pfn = |_: &_, _: &_| Priority::FetchNow;
+        let (_pfn_tx, pfn_rx) = watch::channel(Box::new(pfn) as Box<_>);
+        let metrics = ConsensusManagerMetrics::new::<U64Artifact>(&MetricsRegistry::default());
+
+        assert_eq!(
+            ConsensusManagerReceiver::<
+                U64Artifact,
+                MockValidatedPoolReader<U64Artifact>,
+                (SlotUpdate<U64Artifact>, NodeId, ConnId),
+            >::download_artifact(
+                no_op_logger(),
+                &0,
+                &(),
+                None,
+                &mut peer_rx,
+                pfn_rx,
+                Arc::new(mock_transport),
+                None,
+                RetryBackoff {
+                    initial_interval: Duration::from_millis(10),
+                    max_interval: Duration::from_millis(10),
+                    multiplier: 1.0,
+                },
+                NonZeroUsize::new(2),
+                metrics.clone(),
+            )
+            .await,
+            Ok((U64Artifact::id_to_msg(0, 1024), NODE_2))
+        );
+        assert_eq!(metrics.download_task_fallback_total.get(), 1);
+    }
+
     #[tokio::test]
     async fn large_artifact() {
         use ic_protobuf::p2p::v1 as pb;
@@ -1833,6 +2641,7 @@ mod tests {
         let (router, mut update_rx) = build_axum_router::<BigArtifact>(
             no_op_logger(),
             Arc::new(RwLock::new(MockValidatedPoolReader::default())),
+            ConsensusManagerConfig::default().adverts_from_peers_channel_capacity,
         );
 
         let req_pb = pb::SlotUpdate {
@@ -1859,4 +2668,358 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::OK);
         update_rx.recv().await.unwrap();
     }
+
+    /// Check that a slow pool read in an artifact RPC does not delay the
+    /// handling of slot updates.
+    #[tokio::test]
+    async fn slow_pool_does_not_block_updates() {
+        let pool = FakeValidatedPool::new();
+        pool.insert(U64Artifact::id_to_msg(0, 1024));
+        let slow_pool = SlowPoolReader::new(pool, Duration::from_secs(2));
+        let (router, mut update_rx) = build_axum_router::<U64Artifact>(
+            no_op_logger(),
+            Arc::new(RwLock::new(slow_pool.clone())),
+            ConsensusManagerConfig::default().adverts_from_peers_channel_capacity,
+        );
+
+        let rpc = tokio::spawn(
+            router.clone().oneshot(
+                Request::builder()
+                    .uri(format!("/{}/rpc", uri_prefix::<U64Artifact>()))
+                    .body(Body::from(0_u64.encode_to_vec()))
+                    .unwrap(),
+            ),
+        );
+        while slow_pool.reads() == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let req_pb = pb::SlotUpdate {
+            commit_id: 0,
+            slot_id: 0,
+            update: Some(pb::slot_update::Update::Advert(pb::Advert {
+                id: 1_u64.encode_to_vec(),
+                attribute: ().encode_to_vec(),
+                attribute_digest: Vec::new(),
+            })),
+        }
+        .encode_to_vec();
+        let resp = router
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/{}/update", uri_prefix::<U64Artifact>()))
+                    .extension(NODE_1)
+                    .extension(ConnId::from(1))
+                    .body(Body::from(req_pb))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        timeout(Duration::from_millis(500), update_rx.recv())
+            .await
+            .expect("The update should be handled while the pool is read.")
+            .unwrap();
+
+        let resp = rpc.await.unwrap().unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(slow_pool.reads(), 1);
+    }
+
+    /// Check that downloads try every advertising peer once per round, and
+    /// start rounds more often with peers that have a lower round-trip time.
+    #[tokio::test]
+    async fn peers_with_lower_rtt_are_preferred() {
+        let mut transport_router = TransportRouter::new();
+        let transport =
+            transport_router.add_peer(NODE_1, Router::new(), Duration::from_millis(10), 1_000_000);
+        transport_router.add_peer(NODE_2, Router::new(), Duration::from_millis(50), 1_000_000);
+        transport_router.add_peer(NODE_3, Router::new(), Duration::from_millis(20), 1_000_000);
+
+        let mut peers = PeerCounter::new();
+        // NODE_4 is not connected, so there are no statistics for it.
+        for peer in [NODE_2, NODE_3, NODE_4] {
+            peers.insert(peer);
+        }
+        let mut tried_peers = HashSet::new();
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut first_choices = HashMap::new();
+        for _ in 0..1000 {
+            let round: HashSet<_> = (0..3)
+                .map(|_| choose_peer(&peers, &mut tried_peers, &transport, &mut rng).unwrap())
+                .collect();
+            assert_eq!(round, HashSet::from([NODE_2, NODE_3, NODE_4]));
+            tried_peers.clear();
+            let first = choose_peer(&peers, &mut tried_peers, &transport, &mut rng).unwrap();
+            *first_choices.entry(first).or_insert(0) += 1;
+            tried_peers.clear();
+        }
+        // NODE_3 has a round-trip time of 60ms, NODE_2 of 120ms and NODE_4 is weighted like
+        // the slowest peer.
+        assert!(first_choices[&NODE_3] > first_choices[&NODE_2]);
+        assert!(first_choices[&NODE_2] > 0);
+        assert!(first_choices[&NODE_4] > 0);
+        assert_eq!(
+            choose_peer(
+                &PeerCounter::new(),
+                &mut HashSet::new(),
+                &transport,
+                &mut rng
+            ),
+            None
+        );
+    }
+
+    /// Check that the attribute of a compact advert is fetched from the peer and only
+    /// accepted if it matches the advertised digest.
+    #[tokio::test]
+    async fn compact_advert_attribute_is_fetched() {
+        let pool = FakeValidatedPool::new();
+        pool.insert(U64Artifact::id_to_msg(0, 1024));
+        let (router, _update_rx) = build_axum_router::<U64Artifact>(
+            no_op_logger(),
+            Arc::new(RwLock::new(pool)),
+            ConsensusManagerConfig::default().adverts_from_peers_channel_capacity,
+        );
+        let mut transport_router = TransportRouter::new();
+        let transport: Arc<dyn Transport> = Arc::new(transport_router.add_peer(
+            NODE_1,
+            Router::new(),
+            Duration::from_millis(10),
+            1_000_000,
+        ));
+        transport_router.add_peer(NODE_2, router, Duration::from_millis(10), 1_000_000);
+
+        let digest = Sha256::hash(&().encode_to_vec());
+        let metrics = ConsensusManagerMetrics::new::<U64Artifact>(&MetricsRegistry::default());
+        let fetch = |id, digest| {
+            ConsensusManagerReceiverForTest::fetch_attribute(
+                no_op_logger(),
+                SlotNumber::from(1),
+                CommitId::from(1),
+                id,
+                digest,
+                NODE_2,
+                ConnId::from(1),
+                transport.clone(),
+                metrics.clone(),
+            )
+        };
+
+        let (advert_update, peer_id, conn_id) = fetch(0, digest).await.unwrap();
+        assert!(matches!(advert_update.update, Update::Advert((0, ()))));
+        assert_eq!(advert_update.commit_id, CommitId::from(1));
+        assert_eq!(peer_id, NODE_2);
+        assert_eq!(conn_id, ConnId::from(1));
+        // Digest does not match the attribute.
+        assert!(fetch(0, [0; 32]).await.is_none());
+        // Peer does not have the artifact.
+        assert!(fetch(1, digest).await.is_none());
+        assert_eq!(metrics.attribute_fetch_errors_total.get(), 2);
+    }
+
+    /// Check that at most one attribute fetch is in flight per peer and slot, and that a
+    /// newer compact advert replaces the pending fetch while a stale one is dropped.
+    #[tokio::test]
+    async fn compact_adverts_share_one_attribute_fetch_per_slot() {
+        let mut transport_router = TransportRouter::new();
+        // NODE_2 is not connected, so the fetches stay pending until their deadline.
+        let transport =
+            transport_router.add_peer(NODE_1, Router::new(), Duration::from_millis(10), 1_000_000);
+        let (mut mgr, _channels) = ReceiverManagerBuilder::new()
+            .with_transport(Arc::new(transport))
+            .build();
+        let compact_advert = |slot_number: u64, commit_id: u64| SlotUpdate {
+            slot_number: SlotNumber::from(slot_number),
+            commit_id: CommitId::from(commit_id),
+            update: Update::CompactAdvert((0, [0; 32])),
+        };
+        let pending_commit_id = |mgr: &ConsensusManagerReceiverForTest, slot_number: u64| {
+            mgr.pending_attribute_fetches[&NODE_2][&SlotNumber::from(slot_number)].commit_id
+        };
+
+        for _ in 0..10 {
+            mgr.handle_advert_receive(compact_advert(1, 2), NODE_2, ConnId::from(1));
+        }
+        // Stale advert.
+        mgr.handle_advert_receive(compact_advert(1, 1), NODE_2, ConnId::from(1));
+        assert_eq!(mgr.attribute_fetch_tasks.len(), 1);
+        assert_eq!(pending_commit_id(&mgr, 1), CommitId::from(2));
+
+        mgr.handle_advert_receive(compact_advert(1, 3), NODE_2, ConnId::from(1));
+        assert_eq!(pending_commit_id(&mgr, 1), CommitId::from(3));
+        // The replaced fetch is aborted.
+        assert!(mgr
+            .attribute_fetch_tasks
+            .join_next()
+            .await
+            .unwrap()
+            .is_err());
+        assert_eq!(mgr.attribute_fetch_tasks.len(), 1);
+
+        mgr.handle_advert_receive(compact_advert(2, 1), NODE_2, ConnId::from(1));
+        assert_eq!(mgr.attribute_fetch_tasks.len(), 2);
+        assert_eq!(mgr.pending_attribute_fetches[&NODE_2].len(), 2);
+        assert_eq!(mgr.metrics.attribute_fetches_total.get(), 3);
+        assert_eq!(mgr.metrics.attribute_fetches_superseded_total.get(), 11);
+    }
+
+    /// Check that the golden slot updates are still decoded to the same
+    /// updates.
+    #[tokio::test]
+    async fn golden_slot_updates_are_decoded() {
+        let advert = pb::SlotUpdate {
+            commit_id: 1,
+            slot_id: 1,
+            update: Some(pb::slot_update::Update::Advert(pb::Advert {
+                id: 2_u64.encode_to_vec(),
+                attribute: ().encode_to_vec(),
+                attribute_digest: Vec::new(),
+            })),
+        };
+        let artifact = pb::SlotUpdate {
+            commit_id: 2,
+            slot_id: 2,
+            update: Some(pb::slot_update::Update::Artifact(
+                Vec::<u8>::from(U64Artifact::id_to_msg(3, 16)).encode_to_vec(),
+            )),
+        };
+        let golden_advert = include_str!("../golden/slot_update_advert.hex");
+        let golden_artifact = include_str!("../golden/slot_update_artifact.hex");
+        assert_golden_round_trip(&advert, golden_advert);
+        assert_golden_round_trip(&artifact, golden_artifact);
+
+        let (router, mut update_rx) = build_axum_router::<U64Artifact>(
+            no_op_logger(),
+            Arc::new(RwLock::new(MockValidatedPoolReader::default())),
+            ConsensusManagerConfig::default().adverts_from_peers_channel_capacity,
+        );
+        for golden in [golden_advert, golden_artifact] {
+            let resp = router
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri(format!("/{}/update", uri_prefix::<U64Artifact>()))
+                        .extension(NODE_1)
+                        .extension(ConnId::from(1))
+                        .body(Body::from(parse_golden(golden)))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+
+        let (update, _, _) = update_rx.recv().await.unwrap();
+        assert_eq!(update.commit_id, CommitId::from(1));
+        assert_eq!(update.slot_number, SlotNumber::from(1));
+        assert!(matches!(update.update, Update::Advert((2, ()))));
+        let (update, _, _) = update_rx.recv().await.unwrap();
+        assert_eq!(update.commit_id, CommitId::from(2));
+        assert_eq!(update.slot_number, SlotNumber::from(2));
+        assert!(
+            matches!(update.update, Update::Artifact(artifact) if artifact == U64Artifact::id_to_msg(3, 16))
+        );
+    }
+
+    const PROPTEST_PEERS: u64 = 3;
+    const PROPTEST_SLOTS: u64 = 4;
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        /// Check that the slot table stays bounded by the number of peers
+        /// times the number of slots they use, and that no artifact is
+        /// delivered again before it was removed, for arbitrary interleavings
+        /// of the updates of several peers.
+        #[test]
+        fn receiver_invariants_hold_for_interleaved_updates(
+            events in arb_interleaved_peer_events(
+                (1..=PROPTEST_PEERS).map(node_test_id).collect(),
+                20,
+                PROPTEST_SLOTS,
+                8,
+            )
+        ) {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async move {
+                let mut mock_pfn = MockPriorityFnFactory::new();
+                mock_pfn
+                    .expect_get_priority_function()
+                    .returning(|_| Box::new(|_, _| Priority::FetchNow));
+                let mut mock_transport = MockTransport::new();
+                mock_transport.expect_rpc().returning(|_, request| {
+                    let id = <<U64Artifact as PbArtifact>::PbId>::proxy_decode(request.body())
+                        .unwrap();
+                    Ok(Response::builder()
+                        .body(Bytes::from(
+                            <<U64Artifact as PbArtifact>::PbMessage>::proxy_encode(
+                                U64Artifact::id_to_msg(id, 1024),
+                            ),
+                        ))
+                        .unwrap())
+                });
+                let (mut mgr, mut channels) = ReceiverManagerBuilder::new()
+                    .with_priority_fn_producer(Arc::new(mock_pfn))
+                    .with_transport(Arc::new(mock_transport))
+                    .build();
+
+                let mut conn_ids = HashMap::new();
+                for event in events {
+                    match event {
+                        PeerEvent::Reconnect(peer_id) => {
+                            *conn_ids.entry(peer_id).or_insert(0) += 1;
+                        }
+                        PeerEvent::Update(peer_id, update) => {
+                            let conn_id = ConnId::from(*conn_ids.entry(peer_id).or_insert(0));
+                            mgr.handle_advert_receive(
+                                SlotUpdate {
+                                    slot_number: SlotNumber::from(update.slot),
+                                    commit_id: CommitId::from(update.commit_id),
+                                    update: if update.push {
+                                        Update::Artifact(update.artifact(1024))
+                                    } else {
+                                        Update::Advert((update.id, ()))
+                                    },
+                                },
+                                peer_id,
+                                conn_id,
+                            );
+                        }
+                    }
+                    // Let the download tasks progress and restart the finished
+                    // ones, like the event loop does.
+                    tokio::task::yield_now().await;
+                    while let Some(result) = mgr.artifact_processor_tasks.try_join_next() {
+                        let (peer_rx, id, attr) = result.unwrap();
+                        mgr.handle_artifact_processor_joined(peer_rx, id, attr);
+                    }
+
+                    assert!(mgr
+                        .slot_table
+                        .values()
+                        .all(|slots| slots.len() as u64 <= PROPTEST_SLOTS));
+                    let entries: usize = mgr.slot_table.values().map(HashMap::len).sum();
+                    assert!(entries as u64 <= PROPTEST_PEERS * PROPTEST_SLOTS);
+                }
+
+                let mut delivered = HashSet::new();
+                while let Ok(mutation) = channels.unvalidated_artifact_receiver.try_recv() {
+                    match mutation {
+                        UnvalidatedArtifactMutation::Insert((artifact, _)) => {
+                            let id = artifact.id();
+                            assert!(delivered.insert(id), "Artifact {} was delivered twice.", id);
+                        }
+                        UnvalidatedArtifactMutation::Remove(id) => {
+                            assert!(
+                                delivered.remove(&id),
+                                "Artifact {} was removed but not delivered.",
+                                id
+                            );
+                        }
+                    }
+                }
+            });
+        }
+    }
 }
@@ -8,12 +8,12 @@ use std::{
 };
 
 use axum::http::Request;
-use backoff::{backoff::Backoff, ExponentialBackoffBuilder};
+use backoff::backoff::Backoff;
 use bytes::Bytes;
 use ic_base_types::NodeId;
 use ic_interfaces::p2p::{artifact_manager::ArtifactProcessorEvent, consensus::ArtifactWithOpt};
 use ic_logger::{error, warn, ReplicaLogger};
-use ic_protobuf::{p2p::v1 as pb, proxy::ProtoProxy};
+use ic_protobuf::p2p::v1 as pb;
 use ic_quic_transport::{ConnId, Shutdown, Transport};
 use ic_types::artifact::PbArtifact;
 use prost::Message;
@@ -27,7 +27,11 @@ use tokio::{
 use tokio_util::sync::CancellationToken;
 use tracing::instrument;
 
-use crate::{metrics::ConsensusManagerMetrics, uri_prefix, CommitId, SlotNumber};
+use crate::{
+    encode_advert, metrics::ConsensusManagerMetrics, rate_limit::SendRateLimiter,
+    supports_compact_adverts, uri_prefix, CommitId, ConsensusManagerConfig, RetryBackoff,
+    SlotNumber,
+};
 
 use self::available_slot_set::{AvailableSlot, AvailableSlotSet};
 
@@ -35,9 +39,9 @@ use self::available_slot_set::{AvailableSlot, AvailableSlotSet};
 /// in size are pushed.
 pub(crate) const ARTIFACT_PUSH_THRESHOLD_BYTES: usize = 1024; // 1KB
 
-const MIN_BACKOFF_INTERVAL: Duration = Duration::from_millis(250);
-const MAX_BACKOFF_INTERVAL: Duration = Duration::from_secs(60);
-const BACKOFF_MULTIPLIER: f64 = 2.0;
+// Deadline for the initial broadcast of an update to all peers. Peers that did not receive it in
+// time are retried individually.
+const BROADCAST_TIMEOUT: Duration = Duration::from_secs(5);
 
 // Used to log warnings if the slot table grows beyond the threshold.
 const SLOT_TABLE_THRESHOLD: u64 = 30_000;
@@ -66,6 +70,9 @@ pub(crate) struct ConsensusManagerSender<Artifact: PbArtifact> {
     current_commit_id: CommitId,
     active_adverts: HashMap<Artifact::Id, (CancellationToken, AvailableSlot)>,
     join_set: JoinSet<()>,
+    advert_batch_size: usize,
+    push_retry_backoff: RetryBackoff,
+    rate_limiter: Option<Arc<SendRateLimiter>>,
 }
 
 impl<Artifact: PbArtifact> ConsensusManagerSender<Artifact> {
@@ -75,8 +82,12 @@ impl<Artifact: PbArtifact> ConsensusManagerSender<Artifact> {
         rt_handle: Handle,
         transport: Arc<dyn Transport>,
         adverts_to_send: Receiver<ArtifactProcessorEvent<Artifact>>,
+        config: &ConsensusManagerConfig,
     ) -> Shutdown {
         let slot_manager = AvailableSlotSet::new(log.clone(), metrics.clone(), Artifact::NAME);
+        let rate_limiter = config
+            .send_rate_limit
+            .map(|limit| Arc::new(SendRateLimiter::new(limit, metrics.clone())));
 
         let manager = Self {
             log,
@@ -88,6 +99,9 @@ impl<Artifact: PbArtifact> ConsensusManagerSender<Artifact> {
             current_commit_id: CommitId::from(0),
             active_adverts: HashMap::new(),
             join_set: JoinSet::new(),
+            advert_batch_size: config.advert_batch_size.get(),
+            push_retry_backoff: config.push_retry_backoff,
+            rate_limiter,
         };
 
         Shutdown::spawn_on_with_cancellation(
@@ -97,6 +111,7 @@ impl<Artifact: PbArtifact> ConsensusManagerSender<Artifact> {
     }
 
     async fn start_event_loop(mut self, cancellation_token: CancellationToken) {
+        let mut adverts = Vec::with_capacity(self.advert_batch_size);
         loop {
             select! {
                 _ = cancellation_token.cancelled() => {
@@ -107,13 +122,15 @@ impl<Artifact: PbArtifact> ConsensusManagerSender<Artifact> {
                     );
                     break;
                 }
-                Some(advert) = self.adverts_to_send.recv() => {
-                    match advert {
-                        ArtifactProcessorEvent::Artifact(new_artifact) => self.handle_send_advert(new_artifact, cancellation_token.clone()),
-                        ArtifactProcessorEvent::Purge(id) => self.handle_purge_advert(&id),
-                    }
+                1.. = self.adverts_to_send.recv_many(&mut adverts, self.advert_batch_size) => {
+                    for advert in adverts.drain(..) {
+                        match advert {
+                            ArtifactProcessorEvent::Artifact(new_artifact) => self.handle_send_advert(new_artifact, cancellation_token.clone()),
+                            ArtifactProcessorEvent::Purge(id) => self.handle_purge_advert(&id),
+                        }
 
-                    self.current_commit_id.inc_assign();
+                        self.current_commit_id.inc_assign();
+                    }
                 }
 
                 Some(result) = self.join_set.join_next() => {
@@ -186,6 +203,8 @@ impl<Artifact: PbArtifact> ConsensusManagerSender<Artifact> {
                 id,
                 attribute,
                 child_token_clone,
+                self.push_retry_backoff,
+                self.rate_limiter.clone(),
             );
 
             self.join_set.spawn_on(send_future, &self.rt_handle);
@@ -210,31 +229,85 @@ impl<Artifact: PbArtifact> ConsensusManagerSender<Artifact> {
         id: Artifact::Id,
         attribute: Artifact::Attribute,
         cancellation_token: CancellationToken,
+        push_retry_backoff: RetryBackoff,
+        rate_limiter: Option<Arc<SendRateLimiter>>,
     ) {
-        let pb_slot_update = pb::SlotUpdate {
-            commit_id: commit_id.get(),
-            slot_id: slot_number.get(),
-            update: Some({
-                let pb_artifact: Artifact::PbMessage = artifact.into();
-                // Try to push artifact if size below threshold or it is latency sensitive.
-                if pb_artifact.encoded_len() < ARTIFACT_PUSH_THRESHOLD_BYTES || is_latency_sensitive
-                {
-                    pb::slot_update::Update::Artifact(pb_artifact.encode_to_vec())
-                } else {
-                    pb::slot_update::Update::Advert(pb::Advert {
-                        id: Artifact::PbId::proxy_encode(id),
-                        attribute: Artifact::PbAttribute::proxy_encode(attribute),
-                    })
+        let encode_slot_update = |update| {
+            Bytes::from(
+                pb::SlotUpdate {
+                    commit_id: commit_id.get(),
+                    slot_id: slot_number.get(),
+                    update: Some(update),
                 }
-            }),
+                .encode_to_vec(),
+            )
+        };
+        let pb_artifact: Artifact::PbMessage = artifact.into();
+        // Try to push artifact if size below threshold or it is latency sensitive.
+        let (body, compact_body) =
+            if pb_artifact.encoded_len() < ARTIFACT_PUSH_THRESHOLD_BYTES || is_latency_sensitive {
+                let body = encode_slot_update(pb::slot_update::Update::Artifact(
+                    pb_artifact.encode_to_vec(),
+                ));
+                (body.clone(), body)
+            } else {
+                (
+                    encode_slot_update(pb::slot_update::Update::Advert(encode_advert::<Artifact>(
+                        id.clone(),
+                        attribute.clone(),
+                        false,
+                    ))),
+                    encode_slot_update(pb::slot_update::Update::Advert(encode_advert::<Artifact>(
+                        id, attribute, true,
+                    ))),
+                )
+            };
+        // Peers that did not report the compact adverts capability get the full advert.
+        let body_for_peer = |peer: &NodeId| {
+            if supports_compact_adverts(transport.as_ref(), peer) {
+                compact_body.clone()
+            } else {
+                body.clone()
+            }
         };
-
-        let body = Bytes::from(pb_slot_update.encode_to_vec());
 
         let mut in_progress_transmissions = JoinSet::new();
         // Stores the connection ID and the [`CancellationToken`] of the last successful transmission task to a peer.
         let mut initiated_transmissions: HashMap<NodeId, (ConnId, CancellationToken)> =
             HashMap::new();
+
+        // Broadcast the update to all connected peers first. Peers that did not receive it and
+        // peers that connect later are handled individually by the periodic check below.
+        // Rate limited updates skip the broadcast, which cannot hold back pushes to single
+        // peers, and are sent to every peer individually right away.
+        if rate_limiter.is_none() {
+            // The broadcast pushes the same update to all peers, so it is only compacted if
+            // every connected peer accepts compacted adverts.
+            let broadcast_body = if transport
+                .peers()
+                .iter()
+                .all(|(peer, _)| supports_compact_adverts(transport.as_ref(), peer))
+            {
+                compact_body.clone()
+            } else {
+                body.clone()
+            };
+            let request = update_request::<Artifact>(broadcast_body);
+            let deadline = time::Instant::now() + BROADCAST_TIMEOUT;
+            let broadcast = select! {
+                outcome = transport.broadcast(request, deadline) => outcome,
+                _ = cancellation_token.cancelled() => return,
+            };
+            for (peer, connection_id, result) in broadcast {
+                metrics.send_view_send_to_peer_total.inc();
+                if result.is_ok() {
+                    metrics.send_view_send_to_peer_delivered_total.inc();
+                    initiated_transmissions
+                        .insert(peer, (connection_id, cancellation_token.child_token()));
+                }
+            }
+        }
+
         let mut periodic_check_interval = time::interval(Duration::from_secs(5));
         loop {
             select! {
@@ -259,12 +332,13 @@ impl<Artifact: PbArtifact> ConsensusManagerSender<Artifact> {
                             let child_token_clone = child_token.clone();
                             metrics.send_view_send_to_peer_total.inc();
 
+                            let body = body_for_peer(&peer);
                             let transport = transport.clone();
-                            let body = body.clone();
+                            let rate_limiter = rate_limiter.clone();
 
                             let send_future = async move {
                                 select! {
-                                    _ = send_advert_to_peer(transport, body, peer, uri_prefix::<Artifact>()) => {},
+                                    _ = send_advert_to_peer::<Artifact>(transport, body, peer, push_retry_backoff, rate_limiter) => {},
                                     _ = child_token.cancelled() => {},
                                 }
                             };
@@ -292,35 +366,39 @@ impl<Artifact: PbArtifact> ConsensusManagerSender<Artifact> {
 
 /// Sends a serialized advert or artifact message to a peer.
 /// If the peer is not reachable, it will retry with an exponential backoff.
-#[instrument(skip(transport, message))]
-async fn send_advert_to_peer(
+/// Every attempt is subject to the rate limit, if any.
+#[instrument(skip(transport, message, retry_backoff, rate_limiter))]
+async fn send_advert_to_peer<Artifact: PbArtifact>(
     transport: Arc<dyn Transport>,
     message: Bytes,
     peer: NodeId,
-    uri_prefix: String,
+    retry_backoff: RetryBackoff,
+    rate_limiter: Option<Arc<SendRateLimiter>>,
 ) {
-    let mut backoff = ExponentialBackoffBuilder::new()
-        .with_initial_interval(MIN_BACKOFF_INTERVAL)
-        .with_max_interval(MAX_BACKOFF_INTERVAL)
-        .with_multiplier(BACKOFF_MULTIPLIER)
-        .with_max_elapsed_time(None)
-        .build();
+    let mut backoff = retry_backoff.build();
 
     loop {
-        let request = Request::builder()
-            .uri(format!("/{}/update", uri_prefix))
-            .body(message.clone())
-            .expect("Building from typed values");
+        if let Some(rate_limiter) = &rate_limiter {
+            rate_limiter.acquire(peer, message.len()).await;
+        }
+        let request = update_request::<Artifact>(message.clone());
 
         if let Ok(()) = transport.push(&peer, request).await {
             return;
         }
 
-        let backoff_duration = backoff.next_backoff().unwrap_or(MAX_BACKOFF_INTERVAL);
+        let backoff_duration = backoff.next_backoff().unwrap_or(retry_backoff.max_interval);
         time::sleep(backoff_duration).await;
     }
 }
 
+fn update_request<Artifact: PbArtifact>(message: Bytes) -> Request<Bytes> {
+    Request::builder()
+        .uri(format!("/{}/update", uri_prefix::<Artifact>()))
+        .body(message)
+        .expect("Building from typed values")
+}
+
 mod available_slot_set {
     use super::*;
 
@@ -392,12 +470,16 @@ mod tests {
     use anyhow::anyhow;
     use ic_logger::replica_logger::no_op_logger;
     use ic_metrics::MetricsRegistry;
-    use ic_p2p_test_utils::{consensus::U64Artifact, mocks::MockTransport};
+    use ic_p2p_test_utils::{
+        consensus::U64Artifact, golden::assert_matches_golden, mocks::MockTransport,
+    };
     use ic_test_utilities_logger::with_test_replica_logger;
     use ic_types_test_utils::ids::{NODE_1, NODE_2};
     use mockall::Sequence;
     use tokio::{runtime::Handle, time::timeout};
 
+    use crate::SendRateLimit;
+
     use super::*;
 
     /// Verify that advert is sent to multiple peers.
@@ -425,6 +507,7 @@ mod tests {
                 Handle::current(),
                 Arc::new(mock_transport),
                 rx,
+                &ConsensusManagerConfig::default(),
             );
 
             tx.send(ArtifactProcessorEvent::Artifact(ArtifactWithOpt {
@@ -451,6 +534,61 @@ mod tests {
         .await
     }
 
+    /// Verify that rate limited adverts are pushed to every peer and delayed by the limit.
+    #[tokio::test]
+    async fn send_rate_limited_advert_to_all_peers() {
+        with_test_replica_logger(|log| async {
+            let (push_tx, mut push_rx) = tokio::sync::mpsc::unbounded_channel();
+            let (tx, rx) = tokio::sync::mpsc::channel(100);
+
+            let mut mock_transport = MockTransport::new();
+            mock_transport
+                .expect_peers()
+                .return_const(vec![(NODE_1, ConnId::from(1)), (NODE_2, ConnId::from(2))]);
+            mock_transport
+                .expect_push()
+                .times(2)
+                .returning(move |n, _| {
+                    push_tx.send(*n).unwrap();
+                    Ok(())
+                });
+
+            let metrics = ConsensusManagerMetrics::new::<U64Artifact>(&MetricsRegistry::default());
+            let shutdown = ConsensusManagerSender::<U64Artifact>::run(
+                log,
+                metrics.clone(),
+                Handle::current(),
+                Arc::new(mock_transport),
+                rx,
+                &ConsensusManagerConfig {
+                    // Without burst, the push to the second peer waits for the first one.
+                    send_rate_limit: Some(SendRateLimit {
+                        per_peer_bytes_per_second: 1_000_000.try_into().unwrap(),
+                        aggregate_bytes_per_second: 1_000.try_into().unwrap(),
+                        burst: Duration::ZERO,
+                    }),
+                    ..Default::default()
+                },
+            );
+
+            tx.send(ArtifactProcessorEvent::Artifact(ArtifactWithOpt {
+                artifact: U64Artifact::id_to_msg(1, 1024),
+                is_latency_sensitive: false,
+            }))
+            .await
+            .unwrap();
+
+            let pushes = [push_rx.recv().await.unwrap(), push_rx.recv().await.unwrap()];
+            assert!(pushes.contains(&NODE_1) && pushes.contains(&NODE_2));
+            assert_eq!(metrics.send_view_rate_limited_total.get(), 1);
+
+            timeout(Duration::from_secs(5), shutdown.shutdown())
+                .await
+                .expect("ConsensusManagerSender did not terminate in time.")
+        })
+        .await
+    }
+
     /// Verify that increasing connection id causes advert to be resent.
     #[tokio::test]
     async fn resend_advert_to_reconnected_peer() {
@@ -486,6 +624,7 @@ mod tests {
                 Handle::current(),
                 Arc::new(mock_transport),
                 rx,
+                &ConsensusManagerConfig::default(),
             );
 
             tx.send(ArtifactProcessorEvent::Artifact(ArtifactWithOpt {
@@ -544,6 +683,7 @@ mod tests {
                 Handle::current(),
                 Arc::new(mock_transport),
                 rx,
+                &ConsensusManagerConfig::default(),
             );
 
             tx.send(ArtifactProcessorEvent::Artifact(ArtifactWithOpt {
@@ -562,6 +702,65 @@ mod tests {
         .await
     }
 
+    /// Verify that the encoding of slot updates matches the golden files.
+    #[tokio::test]
+    async fn slot_update_encoding_matches_golden_files() {
+        with_test_replica_logger(|log| async {
+            let (push_tx, mut push_rx) = tokio::sync::mpsc::unbounded_channel();
+            let (tx, rx) = tokio::sync::mpsc::channel(100);
+
+            let mut mock_transport = MockTransport::new();
+            mock_transport
+                .expect_peers()
+                .return_const(vec![(NODE_1, ConnId::from(1))]);
+            mock_transport
+                .expect_push()
+                .times(3)
+                .returning(move |_, r| {
+                    push_tx.send(r.into_body()).unwrap();
+                    Ok(())
+                });
+
+            let shutdown = ConsensusManagerSender::<U64Artifact>::run(
+                log,
+                ConsensusManagerMetrics::new::<U64Artifact>(&MetricsRegistry::default()),
+                Handle::current(),
+                Arc::new(mock_transport),
+                rx,
+                &ConsensusManagerConfig::default(),
+            );
+            // The first update only moves the commit id and the slot away from
+            // their default values, which are not encoded.
+            for (id, size) in [(1, 1024), (2, 1024), (3, 16)] {
+                tx.send(ArtifactProcessorEvent::Artifact(ArtifactWithOpt {
+                    artifact: U64Artifact::id_to_msg(id, size),
+                    is_latency_sensitive: false,
+                }))
+                .await
+                .unwrap();
+            }
+            let mut pushes = HashMap::new();
+            for _ in 0..3 {
+                let body = push_rx.recv().await.unwrap();
+                let commit_id = pb::SlotUpdate::decode(body.clone()).unwrap().commit_id;
+                pushes.insert(commit_id, body);
+            }
+            assert_matches_golden(
+                &pushes[&1],
+                include_str!("../golden/slot_update_advert.hex"),
+            );
+            assert_matches_golden(
+                &pushes[&2],
+                include_str!("../golden/slot_update_artifact.hex"),
+            );
+
+            timeout(Duration::from_secs(5), shutdown.shutdown())
+                .await
+                .expect("ConsensusManagerSender did not terminate in time.")
+        })
+        .await
+    }
+
     /// Verify commit id increases with new adverts/purge events.
     #[tokio::test]
     async fn increasing_commit_id() {
@@ -589,6 +788,7 @@ mod tests {
                 Handle::current(),
                 Arc::new(mock_transport),
                 rx,
+                &ConsensusManagerConfig::default(),
             );
             // Send advert and verify commit it.
             tx.send(ArtifactProcessorEvent::Artifact(ArtifactWithOpt {
@@ -651,6 +851,7 @@ mod tests {
                 Handle::current(),
                 Arc::new(mock_transport),
                 rx,
+                &ConsensusManagerConfig::default(),
             );
 
             // Send advert and verify commit id.
@@ -717,6 +918,7 @@ mod tests {
                 Handle::current(),
                 Arc::new(mock_transport),
                 rx,
+                &ConsensusManagerConfig::default(),
             );
 
         tx.send(ArtifactProcessorEvent::Artifact(ArtifactWithOpt {
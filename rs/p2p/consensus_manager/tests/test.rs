@@ -9,13 +9,19 @@ use std::{
 use futures::StreamExt;
 use ic_interfaces::p2p::artifact_manager::JoinGuard;
 use ic_logger::{replica_logger::no_op_logger, ReplicaLogger};
+use ic_memory_transport::TransportRouter;
 use ic_metrics::MetricsRegistry;
 use ic_p2p_test_utils::{
+    byzantine::{ByzantinePeer, ServeBehavior},
     consensus::{TestConsensus, U64Artifact},
     fully_connected_localhost_subnet,
+    load::LoadGenerator,
+    metrics::assert_counter_eq,
+    partition::NetworkPartitioner,
     turmoil::{
-        add_peer_manager_to_sim, add_transport_to_sim, run_simulation_for, start_test_processor,
-        wait_for, wait_for_timeout, waiter_fut, PeerManagerAction,
+        add_peer_manager_to_sim, add_transport_to_sim, check_pools_equal, run_simulation_for,
+        start_test_processor, wait_for, wait_for_timeout, waiter_fut, ConsensusManagerSim,
+        PeerManagerAction,
     },
 };
 use ic_test_utilities_logger::with_test_replica_logger;
@@ -241,10 +247,37 @@ fn test_flapping_connection_does_not_cause_duplicate_artifact_downloads() {
     });
 }
 
+#[test]
+fn test_pools_converge_after_partition_is_repaired() {
+    with_test_replica_logger(|log| {
+        let mut sim = ConsensusManagerSim::new(log);
+        let pool_1 = sim.add_node(NODE_1, 1024, false);
+        let pool_2 = sim.add_node(NODE_2, 1024, false);
+        let pool_3 = sim.add_node(NODE_3, 1024, false);
+
+        pool_1.push_advert(1);
+        sim.run_until_converged().unwrap();
+
+        sim.partition(NODE_1, NODE_3);
+        pool_1.push_advert(2);
+        pool_3.push_advert(3);
+        sim.run_until(|| pool_2.received_advert_once(2) && pool_2.received_advert_once(3))
+            .unwrap();
+        sim.run_for(Duration::from_secs(5)).unwrap();
+        assert!(!sim.pools_converged());
+
+        sim.repair(NODE_1, NODE_3);
+        sim.run_until_converged().unwrap();
+
+        sim.finish().unwrap();
+    });
+}
+
 fn start_consensus_manager(
     log: ReplicaLogger,
     rt_handle: Handle,
     processor: TestConsensus<U64Artifact>,
+    metrics_registry: MetricsRegistry,
 ) -> (
     Box<dyn JoinGuard>,
     ic_consensus_manager::ConsensusManagerBuilder,
@@ -257,7 +290,8 @@ fn start_consensus_manager(
     let mut cm1 = ic_consensus_manager::ConsensusManagerBuilder::new(
         log,
         rt_handle.clone(),
-        MetricsRegistry::default(),
+        metrics_registry,
+        ic_consensus_manager::ConsensusManagerConfig::default(),
     );
     cm1.add_client(
         artifact_manager_event_rx,
@@ -309,34 +343,6 @@ async fn generate_consensus_events(
     }
 }
 
-/// Verifies that all active adverts sent by Node A are present in the pool of every peer.
-fn check_pools_equal(node_pool_map: &HashMap<NodeId, TestConsensus<U64Artifact>>) -> bool {
-    for (node1, pool1) in node_pool_map {
-        for (node2, pool2) in node_pool_map {
-            // Check that all adverts produced by 1 were received by 2.
-            if node2 != node1 {
-                // If other pool subset everything is fine
-                if !pool1.my_pool().is_subset(&pool2.peer_pool(node1)) {
-                    // It can be case that multiple peers advertised same id and it only got downloaded from a different peer.
-                    // In that case check that the id is contained in some other pool.
-                    for diff in pool1.my_pool().difference(&pool2.peer_pool(node1)) {
-                        let mut found = false;
-                        for n in node_pool_map.keys() {
-                            if n != node1 && pool2.peer_pool(n).contains(diff) {
-                                found |= true;
-                            }
-                        }
-                        if !found {
-                            return false;
-                        }
-                    }
-                }
-            }
-        }
-    }
-    true
-}
-
 struct LoadParameters {
     num_peers: u64,
     num_events: u64,
@@ -380,8 +386,12 @@ fn load_test(
     for i in 0..num_peers {
         let node = node_test_id(i);
         let processor = TestConsensus::new(log.clone(), node, 256 * (i as usize + 1), i % 2 == 0);
-        let (jh, mut cm) =
-            start_consensus_manager(no_op_logger(), rt.handle().clone(), processor.clone());
+        let (jh, mut cm) = start_consensus_manager(
+            no_op_logger(),
+            rt.handle().clone(),
+            processor.clone(),
+            MetricsRegistry::default(),
+        );
         jhs.push(jh);
         nodes.push((node, cm.router()));
         cms.push((node, cm));
@@ -625,6 +635,190 @@ fn test_adverts_are_retransmitted_on_reconnection() {
     });
 }
 
+/// Test that adverts are retransmitted when the connection id of a peer changes,
+/// without the peer disconnecting first.
+#[test]
+fn test_adverts_are_retransmitted_on_conn_id_change() {
+    with_test_replica_logger(|log| {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let _enter = rt.enter();
+        let mut transport_router = TransportRouter::new();
+        let partitioner = NetworkPartitioner::with_placeholder_addresses([NODE_1, NODE_2]);
+        let processor_1 = TestConsensus::new(log.clone(), NODE_1, 1024, false);
+        let processor_2 = TestConsensus::new(log.clone(), NODE_2, 1024, false);
+
+        let metrics_registry_1 = MetricsRegistry::default();
+
+        let mut jhs = vec![];
+        for (node, processor, metrics_registry) in [
+            (NODE_1, &processor_1, metrics_registry_1.clone()),
+            (NODE_2, &processor_2, MetricsRegistry::default()),
+        ] {
+            let (jh, mut cm) = start_consensus_manager(
+                log.clone(),
+                rt.handle().clone(),
+                processor.clone(),
+                metrics_registry,
+            );
+            let transport =
+                transport_router.add_peer(node, cm.router(), Duration::from_millis(10), 1_000_000);
+            cm.run(Arc::new(transport), partitioner.topology_watcher(&node));
+            jhs.push(jh);
+        }
+
+        rt.block_on(async move {
+            processor_1.push_advert(1);
+            while !processor_2.received_advert_once(1) {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+
+            transport_router.reconnect(NODE_1, NODE_2);
+            let retransmitted = async {
+                while processor_2.received_advert_count(1) != 2 {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+            };
+            tokio::time::timeout(Duration::from_secs(30), retransmitted)
+                .await
+                .expect("NODE_2 should receive `advert 1` again after the reconnect.");
+            assert_counter_eq(
+                &metrics_registry_1,
+                "ic_consensus_manager_send_view_resend_reconnect_total",
+                &[],
+                1,
+            );
+        });
+    });
+}
+
+/// Test that a receiver keeps trying to download an advertised artifact that
+/// the advertising peer withholds or corrupts, and accepts it once the peer
+/// serves it correctly.
+#[test]
+fn test_withheld_and_corrupted_artifacts_are_refetched() {
+    with_test_replica_logger(|log| {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let _enter = rt.enter();
+        let mut transport_router = TransportRouter::new();
+        let partitioner = NetworkPartitioner::with_placeholder_addresses([NODE_1, NODE_2]);
+        let processor_1 = TestConsensus::new(log.clone(), NODE_1, 1024, false);
+
+        let (_jh, mut cm) = start_consensus_manager(
+            log.clone(),
+            rt.handle().clone(),
+            processor_1.clone(),
+            MetricsRegistry::default(),
+        );
+        let transport_1 =
+            transport_router.add_peer(NODE_1, cm.router(), Duration::from_millis(10), 1_000_000);
+        cm.run(Arc::new(transport_1), partitioner.topology_watcher(&NODE_1));
+
+        let mut byzantine = ByzantinePeer::<U64Artifact>::new();
+        let transport_2 = transport_router.add_peer(
+            NODE_2,
+            byzantine.router(),
+            Duration::from_millis(10),
+            1_000_000,
+        );
+        byzantine.set_transport(Arc::new(transport_2));
+
+        rt.block_on(async move {
+            byzantine.set_serve_behavior(ServeBehavior::Withhold);
+            byzantine
+                .advertise_artifact(&NODE_1, 0, U64Artifact::id_to_msg(1, 1024))
+                .await
+                .unwrap();
+            while byzantine.served_rpcs() < 2 {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            byzantine.set_serve_behavior(ServeBehavior::Corrupt);
+            let served = byzantine.served_rpcs();
+            while byzantine.served_rpcs() < served + 2 {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            assert!(processor_1.peer_pool(&NODE_2).is_empty());
+
+            byzantine.set_serve_behavior(ServeBehavior::Honest);
+            let downloaded = async {
+                while !processor_1.peer_pool(&NODE_2).contains(&1) {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+            };
+            tokio::time::timeout(Duration::from_secs(60), downloaded)
+                .await
+                .expect("NODE_1 should download `artifact 1` once NODE_2 serves it.");
+        });
+    });
+}
+
+/// Test that a receiver ignores a slot update with a lower commit id than the
+/// last update it received for the same slot.
+#[test]
+fn test_stale_slot_updates_are_ignored() {
+    with_test_replica_logger(|log| {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let _enter = rt.enter();
+        let mut transport_router = TransportRouter::new();
+        let partitioner = NetworkPartitioner::with_placeholder_addresses([NODE_1, NODE_2]);
+        let processor_1 = TestConsensus::new(log.clone(), NODE_1, 1024, false);
+
+        let (_jh, mut cm) = start_consensus_manager(
+            log.clone(),
+            rt.handle().clone(),
+            processor_1.clone(),
+            MetricsRegistry::default(),
+        );
+        let transport_1 =
+            transport_router.add_peer(NODE_1, cm.router(), Duration::from_millis(10), 1_000_000);
+        cm.run(Arc::new(transport_1), partitioner.topology_watcher(&NODE_1));
+
+        let mut byzantine = ByzantinePeer::<U64Artifact>::new();
+        let transport_2 = transport_router.add_peer(
+            NODE_2,
+            byzantine.router(),
+            Duration::from_millis(10),
+            1_000_000,
+        );
+        byzantine.set_transport(Arc::new(transport_2));
+
+        rt.block_on(async move {
+            byzantine.serve(U64Artifact::id_to_msg(1, 1024));
+            byzantine.serve(U64Artifact::id_to_msg(2, 1024));
+            byzantine.send_advert(&NODE_1, 2, 0, 1, ()).await.unwrap();
+            byzantine.send_advert(&NODE_1, 1, 0, 2, ()).await.unwrap();
+
+            let downloaded = async {
+                while !processor_1.peer_pool(&NODE_2).contains(&1) {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+            };
+            tokio::time::timeout(Duration::from_secs(30), downloaded)
+                .await
+                .expect("NODE_1 should download `artifact 1`.");
+            tokio::time::sleep(Duration::from_secs(2)).await;
+            assert!(!processor_1.peer_pool(&NODE_2).contains(&2));
+        });
+    });
+}
+
+/// Test that a node under load from several peers delivers all artifacts
+/// the peers advertise.
+#[test]
+fn test_all_artifacts_are_delivered_under_load() {
+    with_test_replica_logger(|log| {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let report = rt.block_on(
+            LoadGenerator::new(5)
+                .with_rate(20.0)
+                .with_payload_sizes(0..=100_000)
+                .with_duration(Duration::from_secs(5))
+                .run(log),
+        );
+        assert!(report.sent > 0);
+        assert_eq!(report.delivered, report.sent, "{}", report);
+    });
+}
+
 /// Test that a node transmit adverts to its peers for adverts that were produced at a time
 /// its peer was disconnected, but that eventually reconnect.
 /// Scenario:
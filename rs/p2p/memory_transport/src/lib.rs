@@ -41,11 +41,14 @@ use axum::{
     Router,
 };
 use bytes::Bytes;
-use ic_quic_transport::{ConnId, Transport};
+use ic_quic_transport::{ConnId, ConnectionStats, Transport};
 use ic_types::NodeId;
 use std::{
     collections::HashMap,
-    sync::{Arc, RwLock},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
     time::Duration,
 };
 use tokio::{
@@ -86,9 +89,42 @@ impl Default for TransportRouter {
     }
 }
 
+/// Connection ids of the links between pairs of peers. A link that was never
+/// reconnected has connection id zero.
+#[derive(Default)]
+struct Connections {
+    conn_ids: RwLock<HashMap<(NodeId, NodeId), ConnId>>,
+    last_conn_id: AtomicU64,
+}
+
+impl Connections {
+    fn key(a: NodeId, b: NodeId) -> (NodeId, NodeId) {
+        (a.min(b), a.max(b))
+    }
+
+    fn conn_id(&self, a: NodeId, b: NodeId) -> ConnId {
+        self.conn_ids
+            .read()
+            .unwrap()
+            .get(&Self::key(a, b))
+            .copied()
+            .unwrap_or(ConnId::from(0))
+    }
+
+    fn reconnect(&self, a: NodeId, b: NodeId) -> ConnId {
+        let conn_id = ConnId::from(self.last_conn_id.fetch_add(1, Ordering::SeqCst) + 1);
+        self.conn_ids
+            .write()
+            .unwrap()
+            .insert(Self::key(a, b), conn_id);
+        conn_id
+    }
+}
+
 #[derive(Clone)]
 pub struct TransportRouter {
     peers: Arc<RwLock<HashMap<NodeId, PeerHandle>>>,
+    connections: Arc<Connections>,
     router_req_tx: UnboundedSender<(Request<Bytes>, NodeId, oneshot::Sender<Response<Bytes>>)>,
     router_resp_tx: UnboundedSender<(Response<Bytes>, NodeId, oneshot::Sender<Response<Bytes>>)>,
 }
@@ -119,11 +155,33 @@ impl TransportRouter {
 
         Self {
             peers,
+            connections: Arc::default(),
             router_req_tx,
             router_resp_tx,
         }
     }
 
+    /// Simulates a reconnect of the two peers by giving the link between them
+    /// a new, higher connection id. Both peers see the new id from then on,
+    /// in `peers()` and in the extensions of the requests they receive from
+    /// each other. Requests that are in flight are not affected.
+    pub fn reconnect(&self, a: NodeId, b: NodeId) -> ConnId {
+        self.connections.reconnect(a, b)
+    }
+
+    /// Reconnects the peer to all other peers, as if it restarted.
+    pub fn reconnect_all(&self, node_id: NodeId) {
+        let peers: Vec<_> = self.peers.read().unwrap().keys().copied().collect();
+        for peer_id in peers.into_iter().filter(|peer_id| peer_id != &node_id) {
+            self.connections.reconnect(node_id, peer_id);
+        }
+    }
+
+    /// Returns the connection id of the link between the two peers.
+    pub fn conn_id(&self, a: NodeId, b: NodeId) -> ConnId {
+        self.connections.conn_id(a, b)
+    }
+
     /// Adds peer to the memory transport.
     /// This involves starting an event loop that listens for requests.
     pub fn add_peer(
@@ -144,6 +202,7 @@ impl TransportRouter {
             .insert(node_id, PeerHandle::new(rpc_tx, latency, capacity));
         let this_node_id = node_id;
         let router_resp_tx = self.router_resp_tx.clone();
+        let connections = self.connections.clone();
 
         // Spawn request handler for this added node
         tokio::spawn(async move {
@@ -151,7 +210,9 @@ impl TransportRouter {
                 // Get origin NodeId and change request body type
                 let (mut parts, body) = msg.into_parts();
                 let origin_id = *parts.extensions.get::<NodeId>().unwrap();
-                parts.extensions.insert(ConnId::from(u64::MAX));
+                parts
+                    .extensions
+                    .insert(connections.conn_id(origin_id, this_node_id));
                 let req = Request::from_parts(parts, Body::from(body));
 
                 // Call request handler
@@ -312,7 +373,21 @@ impl Transport for PeerTransport {
             .unwrap()
             .iter()
             .filter(|(&n, _)| n != self.node_id)
-            .map(|(k, _)| (*k, ConnId::from(u64::MAX)))
+            .map(|(k, _)| (*k, self.global.conn_id(self.node_id, *k)))
             .collect()
     }
+
+    /// Reports the round-trip time of the links to the router, without any
+    /// queuing for capacity. No packets are lost.
+    fn connection_stats(&self, peer_id: &NodeId) -> Option<ConnectionStats> {
+        if peer_id == &self.node_id {
+            return None;
+        }
+        let peers = self.global.peers.read().unwrap();
+        let latency = peers.get(&self.node_id)?.latency + peers.get(peer_id)?.latency;
+        Some(ConnectionStats {
+            rtt: 2 * latency,
+            ..Default::default()
+        })
+    }
 }
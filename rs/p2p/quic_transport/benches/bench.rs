@@ -18,7 +18,7 @@ use ic_metrics::MetricsRegistry;
 use ic_p2p_test_utils::{
     create_registry_handle, temp_crypto_component_with_tls_keys, RegistryConsensusHandle,
 };
-use ic_quic_transport::{DummyUdpSocket, QuicTransport, SubnetTopology, Transport};
+use ic_quic_transport::{DummyUdpSocket, PeerMetadata, QuicTransport, SubnetTopology, Transport};
 use ic_types_test_utils::ids::node_test_id;
 use tokio::{
     runtime::{Handle, Runtime},
@@ -57,6 +57,7 @@ fn spawn_transport(
         tls,
         registry_handle.registry_client.clone(),
         node_id,
+        PeerMetadata::default(),
         watch_rx,
         Either::<_, DummyUdpSocket>::Left(node_addr),
         Router::new().route("/", any(pong)),
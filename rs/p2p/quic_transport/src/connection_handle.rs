@@ -1,21 +1,29 @@
 //! Quic Transport connection handle.
 //!
 //! Contains a wrapper, called `ConnectionHandle`, around quinn's Connection.
-//! The `ConnectionHandle` implements `rpc` and `push` methods for the given
-//! connection.
+//! The `ConnectionHandle` implements `rpc`, `rpc_stream` and `push` methods for
+//! the given connection.
 //!
-use axum::http::{Request, Response};
+use std::sync::{Arc, OnceLock};
+
+use axum::http::{HeaderValue, Request, Response};
 use bytes::Bytes;
+use futures::StreamExt;
 use ic_base_types::NodeId;
-use quinn::Connection;
+use quinn::{Connection, RecvStream};
+use tokio::time::{timeout_at, Instant};
 
 use crate::{
     metrics::{
         QuicTransportMetrics, ERROR_TYPE_FINISH, ERROR_TYPE_OPEN, ERROR_TYPE_READ,
-        ERROR_TYPE_STOPPED, ERROR_TYPE_WRITE, REQUEST_TYPE_PUSH, REQUEST_TYPE_RPC,
+        ERROR_TYPE_STOPPED, ERROR_TYPE_TIMEOUT, ERROR_TYPE_WRITE, REQUEST_TYPE_PUSH,
+        REQUEST_TYPE_RPC,
+    },
+    utils::{
+        encode_request, read_response, read_streamed_response, write_request,
+        STREAMED_RESPONSE_HEADER,
     },
-    utils::{read_response, write_request},
-    ConnId, MessagePriority,
+    BodyStream, ConnId, ConnectionStats, MessagePriority, PeerMetadata,
 };
 
 #[derive(Clone, Debug)]
@@ -24,6 +32,8 @@ pub(crate) struct ConnectionHandle {
     pub connection: Connection,
     pub metrics: QuicTransportMetrics,
     conn_id: ConnId,
+    // Set once the peer reported its metadata on this connection.
+    metadata: Arc<OnceLock<PeerMetadata>>,
 }
 
 impl ConnectionHandle {
@@ -38,6 +48,7 @@ impl ConnectionHandle {
             connection,
             metrics,
             conn_id,
+            metadata: Arc::new(OnceLock::new()),
         }
     }
 
@@ -45,6 +56,25 @@ impl ConnectionHandle {
         self.conn_id
     }
 
+    pub(crate) fn metadata(&self) -> Option<PeerMetadata> {
+        self.metadata.get().cloned()
+    }
+
+    pub(crate) fn set_metadata(&self, metadata: PeerMetadata) {
+        let _ = self.metadata.set(metadata);
+    }
+
+    pub(crate) fn connection_stats(&self) -> ConnectionStats {
+        let path = self.connection.stats().path;
+        ConnectionStats {
+            rtt: path.rtt,
+            congestion_window: path.cwnd,
+            sent_packets: path.sent_packets,
+            lost_packets: path.lost_packets,
+            congestion_events: path.congestion_events,
+        }
+    }
+
     pub(crate) async fn rpc(
         &self,
         request: Request<Bytes>,
@@ -54,15 +84,82 @@ impl ConnectionHandle {
             .connection_handle_duration_seconds
             .with_label_values(&[request.uri().path()])
             .start_timer();
-        self.metrics
-            .connection_handle_bytes_sent_total
+        let in_counter = self
+            .metrics
+            .connection_handle_bytes_received_total
+            .with_label_values(&[request.uri().path()]);
+
+        let recv_stream = self.send_request(request).await?;
+
+        let mut response = read_response(recv_stream).await.map_err(|err| {
+            self.metrics
+                .connection_handle_errors_total
+                .with_label_values(&[REQUEST_TYPE_RPC, ERROR_TYPE_READ])
+                .inc();
+            err
+        })?;
+
+        // Propagate PeerId from this request to upper layers.
+        response.extensions_mut().insert(self.peer_id);
+
+        in_counter.inc_by(response.body().len() as u64);
+        Ok(response)
+    }
+
+    /// Like [`ConnectionHandle::rpc`], but the body of the response is read
+    /// from the stream chunk by chunk while the returned body is polled. The
+    /// duration metric only covers the time until the response header arrived.
+    pub(crate) async fn rpc_stream(
+        &self,
+        mut request: Request<Bytes>,
+    ) -> Result<Response<BodyStream>, anyhow::Error> {
+        let _timer = self
+            .metrics
+            .connection_handle_duration_seconds
             .with_label_values(&[request.uri().path()])
-            .inc_by(request.body().len() as u64);
+            .start_timer();
         let in_counter = self
             .metrics
             .connection_handle_bytes_received_total
             .with_label_values(&[request.uri().path()]);
 
+        request
+            .headers_mut()
+            .insert(STREAMED_RESPONSE_HEADER, HeaderValue::from_static("1"));
+        let recv_stream = self.send_request(request).await?;
+
+        let mut response = read_streamed_response(recv_stream).await.map_err(|err| {
+            self.metrics
+                .connection_handle_errors_total
+                .with_label_values(&[REQUEST_TYPE_RPC, ERROR_TYPE_READ])
+                .inc();
+            err
+        })?;
+
+        // Propagate PeerId from this request to upper layers.
+        response.extensions_mut().insert(self.peer_id);
+
+        let metrics = self.metrics.clone();
+        Ok(response.map(|body| {
+            body.inspect(move |chunk| match chunk {
+                Ok(chunk) => in_counter.inc_by(chunk.len() as u64),
+                Err(_) => metrics
+                    .connection_handle_errors_total
+                    .with_label_values(&[REQUEST_TYPE_RPC, ERROR_TYPE_READ])
+                    .inc(),
+            })
+            .boxed()
+        }))
+    }
+
+    // Opens a bidirectional stream for the RPC and writes the request to it.
+    // Returns the stream on which the response arrives.
+    async fn send_request(&self, request: Request<Bytes>) -> Result<RecvStream, anyhow::Error> {
+        self.metrics
+            .connection_handle_bytes_sent_total
+            .with_label_values(&[request.uri().path()])
+            .inc_by(request.body().len() as u64);
+
         let (mut send_stream, recv_stream) = self.connection.open_bi().await.map_err(|err| {
             self.metrics
                 .connection_handle_errors_total
@@ -103,31 +200,86 @@ impl ConnectionHandle {
             err
         })?;
 
-        let mut response = read_response(recv_stream).await.map_err(|err| {
+        Ok(recv_stream)
+    }
+
+    /// Like [`ConnectionHandle::rpc`], but gives up once the deadline passed.
+    /// The streams of the request are dropped, which resets them.
+    pub(crate) async fn rpc_with_deadline(
+        &self,
+        request: Request<Bytes>,
+        deadline: Instant,
+    ) -> Result<Response<Bytes>, anyhow::Error> {
+        let path = request.uri().path().to_string();
+        timeout_at(deadline, self.rpc(request)).await.map_err(|_| {
             self.metrics
                 .connection_handle_errors_total
-                .with_label_values(&[REQUEST_TYPE_RPC, ERROR_TYPE_READ])
+                .with_label_values(&[REQUEST_TYPE_RPC, ERROR_TYPE_TIMEOUT])
+                .inc();
+            anyhow::anyhow!(
+                "RPC {} to {} did not complete before the deadline",
+                path,
+                self.peer_id
+            )
+        })?
+    }
+
+    pub(crate) async fn push(&self, request: Request<Bytes>) -> Result<(), anyhow::Error> {
+        let path = request.uri().path().to_string();
+        let priority = request
+            .extensions()
+            .get::<MessagePriority>()
+            .copied()
+            .unwrap_or_default();
+        let message = encode_request(request).map_err(|err| {
+            self.metrics
+                .connection_handle_errors_total
+                .with_label_values(&[REQUEST_TYPE_PUSH, ERROR_TYPE_WRITE])
                 .inc();
             err
         })?;
+        self.push_encoded(&path, priority, message).await
+    }
 
-        // Propagate PeerId from this request to upper layers.
-        response.extensions_mut().insert(self.peer_id);
-
-        in_counter.inc_by(response.body().len() as u64);
-        Ok(response)
+    /// Pushes an already encoded request, so that a request sent to many
+    /// peers is only encoded once. Gives up once the deadline passed.
+    pub(crate) async fn push_encoded_with_deadline(
+        &self,
+        path: &str,
+        priority: MessagePriority,
+        message: Bytes,
+        deadline: Instant,
+    ) -> Result<(), anyhow::Error> {
+        timeout_at(deadline, self.push_encoded(path, priority, message))
+            .await
+            .map_err(|_| {
+                self.metrics
+                    .connection_handle_errors_total
+                    .with_label_values(&[REQUEST_TYPE_PUSH, ERROR_TYPE_TIMEOUT])
+                    .inc();
+                anyhow::anyhow!(
+                    "Push {} to {} did not complete before the deadline",
+                    path,
+                    self.peer_id
+                )
+            })?
     }
 
-    pub(crate) async fn push(&self, request: Request<Bytes>) -> Result<(), anyhow::Error> {
+    async fn push_encoded(
+        &self,
+        path: &str,
+        priority: MessagePriority,
+        message: Bytes,
+    ) -> Result<(), anyhow::Error> {
         let _timer = self
             .metrics
             .connection_handle_duration_seconds
-            .with_label_values(&[request.uri().path()])
+            .with_label_values(&[path])
             .start_timer();
         self.metrics
             .connection_handle_bytes_sent_total
-            .with_label_values(&[request.uri().path()])
-            .inc_by(request.body().len() as u64);
+            .with_label_values(&[path])
+            .inc_by(message.len() as u64);
 
         let mut send_stream = self.connection.open_uni().await.map_err(|err| {
             self.metrics
@@ -136,22 +288,15 @@ impl ConnectionHandle {
             err
         })?;
 
-        let priority = request
-            .extensions()
-            .get::<MessagePriority>()
-            .copied()
-            .unwrap_or_default();
         let _ = send_stream.set_priority(priority.into());
 
-        write_request(&mut send_stream, request)
-            .await
-            .map_err(|err| {
-                self.metrics
-                    .connection_handle_errors_total
-                    .with_label_values(&[REQUEST_TYPE_PUSH, ERROR_TYPE_WRITE])
-                    .inc();
-                err
-            })?;
+        send_stream.write_all(&message).await.map_err(|err| {
+            self.metrics
+                .connection_handle_errors_total
+                .with_label_values(&[REQUEST_TYPE_PUSH, ERROR_TYPE_WRITE])
+                .inc();
+            err
+        })?;
 
         send_stream.finish().map_err(|err| {
             self.metrics
@@ -33,33 +33,39 @@ use std::{
     time::Duration,
 };
 
-use axum::{middleware::from_fn_with_state, Router};
+use axum::{
+    http::{Request, StatusCode},
+    middleware::from_fn_with_state,
+    routing::any,
+    Router,
+};
+use bytes::Bytes;
 use either::Either;
 use futures::StreamExt;
 use ic_async_utils::JoinMap;
 use ic_base_types::NodeId;
+use ic_crypto_internal_tls::mutual_tls::authenticated_peer_node_id;
 use ic_crypto_tls_interfaces::{
     MalformedPeerCertificateError, SomeOrAllNodes, TlsConfig, TlsConfigError,
 };
-use ic_crypto_utils_tls::node_id_from_certificate_der;
 use ic_interfaces_registry::RegistryClient;
 use ic_logger::{error, info, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
 use quinn::{
-    crypto::rustls::{QuicClientConfig, QuicServerConfig},
     AsyncUdpSocket, ConnectError, Connection, ConnectionError, Endpoint, EndpointConfig, Incoming,
     VarInt,
 };
 use rustls::pki_types::CertificateDer;
 use socket2::{Domain, Protocol, SockAddr, Socket, Type};
-use tokio::{runtime::Handle, select, task::JoinSet};
+use tokio::{runtime::Handle, select, task::JoinSet, time::Instant};
 use tokio_util::{sync::CancellationToken, time::DelayQueue};
 
 use crate::{
     connection_handle::ConnectionHandle,
     metrics::{CONNECTION_RESULT_FAILED_LABEL, CONNECTION_RESULT_SUCCESS_LABEL},
+    tls::{quinn_client_config, quinn_server_config},
     utils::collect_metrics,
-    ConnId, Shutdown, SubnetTopology,
+    ConnId, PeerMetadata, Shutdown, SubnetTopology,
 };
 use crate::{metrics::QuicTransportMetrics, request_handler::run_stream_acceptor};
 
@@ -71,6 +77,9 @@ const IDLE_TIMEOUT: Duration = Duration::from_secs(5);
 const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
 const CONNECT_RETRY_BACKOFF: Duration = Duration::from_secs(3);
 const GRUEZI_HANDSHAKE: &str = "gruezi";
+/// Path on which each node serves its metadata to its peers.
+const PEER_METADATA_PATH: &str = "/transport/peer_metadata";
+const PEER_METADATA_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum Direction {
@@ -183,6 +192,7 @@ pub(crate) fn start_connection_manager(
     tls_config: Arc<dyn TlsConfig + Send + Sync>,
     registry_client: Arc<dyn RegistryClient>,
     node_id: NodeId,
+    local_metadata: PeerMetadata,
     peer_map: Arc<RwLock<HashMap<NodeId, ConnectionHandle>>>,
     watcher: tokio::sync::watch::Receiver<SubnetTopology>,
     socket: Either<SocketAddr, impl AsyncUdpSocket>,
@@ -192,7 +202,16 @@ pub(crate) fn start_connection_manager(
 
     let metrics = QuicTransportMetrics::new(metrics_registry);
 
-    let router = router.route_layer(from_fn_with_state(metrics.clone(), collect_metrics));
+    let encoded_metadata = local_metadata.encode();
+    let router = router
+        .route(
+            PEER_METADATA_PATH,
+            any(move || {
+                let encoded_metadata = encoded_metadata.clone();
+                async move { encoded_metadata }
+            }),
+        )
+        .route_layer(from_fn_with_state(metrics.clone(), collect_metrics));
 
     // We use a random reset key here. The downside of this is that
     // during a crash and restart the peer will not recognize our
@@ -225,10 +244,7 @@ pub(crate) fn start_connection_manager(
     transport_config.max_concurrent_bidi_streams(VarInt::from_u32(1_000));
     transport_config.max_concurrent_uni_streams(VarInt::from_u32(1_000));
     let transport_config = Arc::new(transport_config);
-    let mut server_config = quinn::ServerConfig::with_crypto(Arc::new(
-        QuicServerConfig::try_from(rustls_server_config).unwrap(),
-    ));
-    server_config.transport_config(transport_config.clone());
+    let server_config = quinn_server_config(rustls_server_config, transport_config.clone());
 
     // Start endpoint
     let endpoint = match socket {
@@ -416,10 +432,8 @@ impl ConnectionManager {
             .server_config(subnet_nodes, self.topology.latest_registry_version())
         {
             Ok(rustls_server_config) => {
-                let quic_server_config = QuicServerConfig::try_from(rustls_server_config).unwrap();
-                let mut server_config =
-                    quinn::ServerConfig::with_crypto(Arc::new(quic_server_config));
-                server_config.transport_config(self.transport_config.clone());
+                let server_config =
+                    quinn_server_config(rustls_server_config, self.transport_config.clone());
                 self.endpoint.set_server_config(Some(server_config));
             }
             Err(e) => {
@@ -501,9 +515,7 @@ impl ConnectionManager {
             .unwrap();
         let transport_config = self.transport_config.clone();
         let conn_fut = async move {
-            let quinn_client_config = QuicClientConfig::try_from(rustls_client_config).unwrap();
-            let mut client_config = quinn::ClientConfig::new(Arc::new(quinn_client_config));
-            client_config.transport_config(transport_config);
+            let client_config = quinn_client_config(rustls_client_config, transport_config);
             let connecting = endpoint.connect_with(client_config, addr, "irrelevant");
             let established = connecting
                 .map_err(|cause| ConnectionEstablishError::ConnectError { peer_id, cause })?
@@ -562,6 +574,10 @@ impl ConnectionManager {
                 let connection_handle =
                     ConnectionHandle::new(peer_id, connection, self.metrics.clone(), conn_id);
                 let req_handler_connection_handle = connection_handle.clone();
+                self.rt.spawn(fetch_peer_metadata(
+                    self.log.clone(),
+                    connection_handle.clone(),
+                ));
 
                 // dropping the old connection will result in closing it
                 if let Some(old_conn) = peer_map_mut.insert(peer_id, connection_handle) {
@@ -624,19 +640,12 @@ impl ConnectionManager {
                 .ok_or(ConnectionEstablishError::MissingPeerIdentity)?
                 .downcast::<Vec<CertificateDer>>()
                 .unwrap();
-            let rustls_cert =
-                rustls_certs
-                    .first()
-                    .ok_or(ConnectionEstablishError::MalformedPeerIdentity(
-                        MalformedPeerCertificateError {
-                            internal_error: "a single cert must be present".to_string(),
-                        },
-                    ))?;
-            let peer_id = node_id_from_certificate_der(rustls_cert.as_ref()).map_err(|err| {
-                ConnectionEstablishError::MalformedPeerIdentity(MalformedPeerCertificateError {
-                    internal_error: format!("{:?}", err),
-                })
-            })?;
+            let peer_id =
+                authenticated_peer_node_id(Some(rustls_certs.as_slice())).map_err(|err| {
+                    ConnectionEstablishError::MalformedPeerIdentity(MalformedPeerCertificateError {
+                        internal_error: err.to_string(),
+                    })
+                })?;
 
             // Lower ID is dialer. So we reject if this nodes id is higher.
             if peer_id > node_id {
@@ -725,3 +734,24 @@ impl ConnectionManager {
         Ok(conn)
     }
 }
+
+/// Requests the metadata of the peer on a new connection. Peers that do not serve
+/// it, e.g. because they run an older version, get the default metadata. The metadata
+/// stays unknown if the request fails.
+async fn fetch_peer_metadata(log: ReplicaLogger, conn_handle: ConnectionHandle) {
+    let request = Request::builder()
+        .uri(PEER_METADATA_PATH)
+        .body(Bytes::new())
+        .unwrap();
+    let deadline = Instant::now() + PEER_METADATA_TIMEOUT;
+    match conn_handle.rpc_with_deadline(request, deadline).await {
+        Ok(response) if response.status() == StatusCode::OK => {
+            conn_handle.set_metadata(PeerMetadata::decode(response.body()).unwrap_or_default())
+        }
+        Ok(_) => conn_handle.set_metadata(PeerMetadata::default()),
+        Err(err) => info!(
+            log,
+            "Failed to fetch metadata of peer {}: {}", conn_handle.peer_id, err
+        ),
+    }
+}
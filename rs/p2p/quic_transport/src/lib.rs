@@ -9,7 +9,10 @@
 //!  - Connection Manager (connection_manager.rs): Keeps peers connected.
 //!  - Request Handler (request_handler.rs): Accepts streams on an active connection.
 //!    Spawned by the connection manager for each connection.
-//!  - Connection Handle (connection_handle.rs): Provides rpc and push interfaces to a peer.
+//!  - Connection Handle (connection_handle.rs): Provides rpc, rpc_stream and push interfaces to a peer.
+//!    Broadcasts push the same encoded request through the handles of all peers.
+//!  - TLS (tls.rs): Builds the quinn endpoint configs from rustls configs, and rustls configs
+//!    from TLS key material.
 //!
 //! API:
 //!  - Constructor takes a topology watcher. The topology defines the
@@ -20,6 +23,8 @@
 //!     The connection handle is small wrapper around the actual quic connection
 //!     with an rpc/push interface. Passed in requests need to specify an URI to get
 //!     routed to the correct handler.
+//!  - Constructor also takes the metadata of this node. Peers request it on every new
+//!    connection, see `Transport::peer_metadata`.
 //!
 //! GUARANTEES:
 //!  - If a peer is reachable, part of the topology and well-behaving transport will eventually
@@ -34,9 +39,14 @@ use std::{
     future::Future,
     io::IoSliceMut,
     net::SocketAddr,
+    panic::AssertUnwindSafe,
     pin::Pin,
-    sync::{Arc, RwLock},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
     task::{Context, Poll},
+    time::Duration,
 };
 
 use anyhow::anyhow;
@@ -47,6 +57,7 @@ use axum::{
 };
 use bytes::Bytes;
 use either::Either;
+use futures::{future, stream, stream::BoxStream, FutureExt, StreamExt};
 use ic_base_types::{NodeId, RegistryVersion};
 use ic_crypto_tls_interfaces::TlsConfig;
 use ic_interfaces_registry::RegistryClient;
@@ -55,38 +66,136 @@ use ic_metrics::MetricsRegistry;
 use phantom_newtype::AmountOf;
 use quinn::{AsyncUdpSocket, UdpPoller};
 use quinn_udp::{RecvMeta, Transmit};
-use tokio::sync::watch;
+use tokio::{
+    sync::watch,
+    time::{timeout_at, Instant},
+};
 use tokio_util::{sync::CancellationToken, task::task_tracker::TaskTracker};
 use tracing::instrument;
 
 use crate::connection_handle::ConnectionHandle;
 use crate::connection_manager::start_connection_manager;
+use crate::utils::encode_request;
 
 mod connection_handle;
 mod connection_manager;
 mod metrics;
 mod request_handler;
+mod tls;
 mod utils;
 
+pub use tls::{KeyMaterialError, KeyMaterialTlsConfig, ALPN_QUIC_TRANSPORT};
+
+/// Handle to stop one or more background tasks of P2P components.
+///
+/// Handles of several components can be combined with [`Shutdown::join_all`],
+/// so that they are stopped together. The tasks of a combined handle are
+/// indexed in the order in which the handles were combined.
 #[derive(Clone)]
 pub struct Shutdown {
+    tasks: Vec<ShutdownTask>,
+}
+
+#[derive(Clone)]
+struct ShutdownTask {
     cancellation: CancellationToken,
     task_tracker: TaskTracker,
+    // Set if the task panicked.
+    failed: Arc<AtomicBool>,
 }
 
+impl ShutdownTask {
+    fn completed(&self) -> bool {
+        self.task_tracker.is_closed() && self.task_tracker.is_empty()
+    }
+}
+
+/// The state of the tasks of a [`Shutdown`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShutdownStatus {
+    /// Some tasks are still running, and none failed.
+    Running,
+    /// All tasks completed without a panic.
+    Completed,
+    /// At least one task panicked.
+    Failed,
+}
+
+/// Returned by [`Shutdown::shutdown_with_timeout`] if some tasks did not
+/// complete in time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShutdownTimeout {
+    /// Indices of the tasks that were still running.
+    pub pending: Vec<usize>,
+}
+
+impl std::fmt::Display for ShutdownTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Tasks {:?} did not shut down in time", self.pending)
+    }
+}
+
+impl std::error::Error for ShutdownTimeout {}
+
 impl Shutdown {
     pub async fn shutdown(&self) {
         // If an error is returned it means the conn manager is already stopped.
-        self.cancellation.cancel();
-        self.task_tracker.wait().await;
+        self.cancel();
+        future::join_all(self.tasks.iter().map(|task| task.task_tracker.wait())).await;
+    }
+
+    /// Like [`Shutdown::shutdown`], but gives up waiting after the timeout and
+    /// reports the tasks that are still running.
+    pub async fn shutdown_with_timeout(&self, timeout: Duration) -> Result<(), ShutdownTimeout> {
+        if tokio::time::timeout(timeout, self.shutdown()).await.is_ok() {
+            return Ok(());
+        }
+        Err(ShutdownTimeout {
+            pending: self
+                .tasks
+                .iter()
+                .enumerate()
+                .filter(|(_, task)| !task.completed())
+                .map(|(index, _)| index)
+                .collect(),
+        })
     }
 
     pub fn cancel(&self) {
-        self.cancellation.cancel()
+        for task in &self.tasks {
+            task.cancellation.cancel();
+        }
     }
 
     pub fn completed(&self) -> bool {
-        self.task_tracker.is_closed() && self.task_tracker.is_empty()
+        self.tasks.iter().all(ShutdownTask::completed)
+    }
+
+    /// Returns true if any of the tasks panicked.
+    pub fn failed(&self) -> bool {
+        self.tasks
+            .iter()
+            .any(|task| task.failed.load(Ordering::SeqCst))
+    }
+
+    pub fn status(&self) -> ShutdownStatus {
+        if self.failed() {
+            ShutdownStatus::Failed
+        } else if self.completed() {
+            ShutdownStatus::Completed
+        } else {
+            ShutdownStatus::Running
+        }
+    }
+
+    /// Combines the handles into one that stops all of their tasks.
+    pub fn join_all(shutdowns: Vec<Shutdown>) -> Self {
+        Self {
+            tasks: shutdowns
+                .into_iter()
+                .flat_map(|shutdown| shutdown.tasks)
+                .collect(),
+        }
     }
 
     pub fn spawn_on_with_cancellation<F>(
@@ -99,11 +208,28 @@ impl Shutdown {
     {
         let task_tracker = TaskTracker::new();
         let cancellation = CancellationToken::new();
-        task_tracker.spawn_on(run(cancellation.clone()), rt_handle);
+        let failed = Arc::new(AtomicBool::new(false));
+        let task = AssertUnwindSafe(run(cancellation.clone())).catch_unwind();
+        let failed_clone = failed.clone();
+        task_tracker.spawn_on(
+            async move {
+                match task.await {
+                    Ok(output) => output,
+                    Err(panic) => {
+                        failed_clone.store(true, Ordering::SeqCst);
+                        std::panic::resume_unwind(panic)
+                    }
+                }
+            },
+            rt_handle,
+        );
         let _ = task_tracker.close();
         Self {
-            cancellation,
-            task_tracker,
+            tasks: vec![ShutdownTask {
+                cancellation,
+                task_tracker,
+                failed,
+            }],
         }
     }
 }
@@ -136,6 +262,8 @@ impl QuicTransport {
         tls_config: Arc<dyn TlsConfig + Send + Sync>,
         registry_client: Arc<dyn RegistryClient>,
         node_id: NodeId,
+        // Served to peers, so that they can enable protocol features supported by this node.
+        local_metadata: PeerMetadata,
         // The receiver is passed here mainly to be consistent with other managers that also
         // require receivers on construction.
         topology_watcher: watch::Receiver<SubnetTopology>,
@@ -154,6 +282,7 @@ impl QuicTransport {
             tls_config.clone(),
             registry_client,
             node_id,
+            local_metadata,
             conn_handles.clone(),
             topology_watcher,
             udp_socket,
@@ -198,12 +327,65 @@ impl Transport for QuicTransport {
         peer.rpc(request).await
     }
 
+    #[instrument(skip(self, request))]
+    async fn rpc_with_deadline(
+        &self,
+        peer_id: &NodeId,
+        request: Request<Bytes>,
+        deadline: Instant,
+    ) -> Result<Response<Bytes>, anyhow::Error> {
+        let peer = self.get_conn_handle(peer_id)?;
+        peer.rpc_with_deadline(request, deadline).await
+    }
+
+    #[instrument(skip(self, request))]
+    async fn rpc_stream(
+        &self,
+        peer_id: &NodeId,
+        request: Request<Bytes>,
+    ) -> Result<Response<BodyStream>, anyhow::Error> {
+        let peer = self.get_conn_handle(peer_id)?;
+        peer.rpc_stream(request).await
+    }
+
     #[instrument(skip(self, request))]
     async fn push(&self, peer_id: &NodeId, request: Request<Bytes>) -> Result<(), anyhow::Error> {
         let peer = self.get_conn_handle(peer_id)?;
         peer.push(request).await
     }
 
+    #[instrument(skip(self, request))]
+    async fn broadcast(&self, request: Request<Bytes>, deadline: Instant) -> BroadcastOutcome {
+        let peers: Vec<_> = self
+            .conn_handles
+            .read()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect();
+        let path = request.uri().path().to_string();
+        let priority = request
+            .extensions()
+            .get::<MessagePriority>()
+            .copied()
+            .unwrap_or_default();
+        // The request is encoded once and the same bytes are pushed to all peers.
+        let message = match encode_request(request) {
+            Ok(message) => message,
+            Err(err) => {
+                return peers
+                    .iter()
+                    .map(|peer| (peer.peer_id, peer.conn_id(), Err(anyhow!("{:#}", err))))
+                    .collect()
+            }
+        };
+        let pushes = peers.iter().map(|peer| {
+            let push = peer.push_encoded_with_deadline(&path, priority, message.clone(), deadline);
+            async move { (peer.peer_id, peer.conn_id(), push.await) }
+        });
+        future::join_all(pushes).await
+    }
+
     fn peers(&self) -> Vec<(NodeId, ConnId)> {
         self.conn_handles
             .read()
@@ -212,6 +394,22 @@ impl Transport for QuicTransport {
             .map(|(n, c)| (*n, c.conn_id()))
             .collect()
     }
+
+    fn connection_stats(&self, peer_id: &NodeId) -> Option<ConnectionStats> {
+        self.conn_handles
+            .read()
+            .unwrap()
+            .get(peer_id)
+            .map(ConnectionHandle::connection_stats)
+    }
+
+    fn peer_metadata(&self, peer_id: &NodeId) -> Option<PeerMetadata> {
+        self.conn_handles
+            .read()
+            .unwrap()
+            .get(peer_id)
+            .and_then(ConnectionHandle::metadata)
+    }
 }
 
 /// Low-level transport interface for exchanging messages between nodes.
@@ -226,9 +424,103 @@ pub trait Transport: Send + Sync {
         request: Request<Bytes>,
     ) -> Result<Response<Bytes>, anyhow::Error>;
 
+    /// Like [`Transport::rpc`], but fails if the response did not arrive by
+    /// the deadline. Lets callers pick a timeout per request instead of
+    /// relying on the connection timeouts.
+    async fn rpc_with_deadline(
+        &self,
+        peer_id: &NodeId,
+        request: Request<Bytes>,
+        deadline: Instant,
+    ) -> Result<Response<Bytes>, anyhow::Error> {
+        timeout_at(deadline, self.rpc(peer_id, request))
+            .await
+            .map_err(|_| anyhow!("RPC to {} did not complete before the deadline", peer_id))?
+    }
+
+    /// Like [`Transport::rpc`], but returns the response body as a stream of
+    /// chunks, so that large responses do not need to be buffered as a whole.
+    /// The default implementation buffers the response and yields it as a
+    /// single chunk.
+    async fn rpc_stream(
+        &self,
+        peer_id: &NodeId,
+        request: Request<Bytes>,
+    ) -> Result<Response<BodyStream>, anyhow::Error> {
+        let response = self.rpc(peer_id, request).await?;
+        Ok(response.map(|body| stream::once(future::ready(Ok(body))).boxed()))
+    }
+
     async fn push(&self, peer_id: &NodeId, request: Request<Bytes>) -> Result<(), anyhow::Error>;
 
+    /// Pushes the request to all connected peers concurrently. Every push
+    /// fails that did not complete by the deadline, without holding up the
+    /// pushes to other peers. Returns the outcome for every peer together with
+    /// the connection the request was pushed on.
+    async fn broadcast(&self, request: Request<Bytes>, deadline: Instant) -> BroadcastOutcome {
+        let (parts, body) = request.into_parts();
+        let pushes = self.peers().into_iter().map(|(peer_id, conn_id)| {
+            let request = Request::from_parts(parts.clone(), body.clone());
+            async move {
+                let result = timeout_at(deadline, self.push(&peer_id, request))
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(anyhow!(
+                            "Push to {} did not complete before the deadline",
+                            peer_id
+                        ))
+                    });
+                (peer_id, conn_id, result)
+            }
+        });
+        future::join_all(pushes).await
+    }
+
     fn peers(&self) -> Vec<(NodeId, ConnId)>;
+
+    /// Returns the statistics of the current connection to the peer, or
+    /// `None` if the peer is not connected or the transport does not track
+    /// connection quality.
+    fn connection_stats(&self, _peer_id: &NodeId) -> Option<ConnectionStats> {
+        None
+    }
+
+    /// Returns the metadata the peer reported on the current connection, or
+    /// `None` if the peer is not connected, did not report it yet, or the
+    /// transport does not exchange metadata.
+    fn peer_metadata(&self, _peer_id: &NodeId) -> Option<PeerMetadata> {
+        None
+    }
+}
+
+/// The outcome of a [`Transport::broadcast`] per peer and connection.
+pub type BroadcastOutcome = Vec<(NodeId, ConnId, Result<(), anyhow::Error>)>;
+
+/// The body of a response returned by [`Transport::rpc_stream`].
+pub type BodyStream = BoxStream<'static, Result<Bytes, anyhow::Error>>;
+
+/// Quality statistics of a connection, as estimated by its congestion
+/// controller.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConnectionStats {
+    /// Smoothed round-trip time.
+    pub rtt: Duration,
+    /// Congestion window in bytes.
+    pub congestion_window: u64,
+    pub sent_packets: u64,
+    pub lost_packets: u64,
+    /// Number of times the congestion window was reduced.
+    pub congestion_events: u64,
+}
+
+impl ConnectionStats {
+    /// The fraction of the sent packets that were lost.
+    pub fn loss_rate(&self) -> f64 {
+        if self.sent_packets == 0 {
+            return 0.0;
+        }
+        self.lost_packets as f64 / self.sent_packets as f64
+    }
 }
 
 pub struct ConnIdTag {}
@@ -280,6 +572,88 @@ impl AsyncUdpSocket for DummyUdpSocket {
     }
 }
 
+/// Protocol features a peer supports. Lets P2P protocols enable new wire
+/// features per peer instead of on all nodes at once.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct PeerCapabilities(u64);
+
+impl PeerCapabilities {
+    /// The peer accepts compressed artifacts.
+    pub const COMPRESSION: Self = Self(1);
+    /// The peer serves artifacts in chunks.
+    pub const CHUNKED_DOWNLOADS: Self = Self(1 << 1);
+    /// The peer accepts adverts that carry the digest of the attribute instead of the
+    /// attribute.
+    pub const COMPACT_ADVERTS: Self = Self(1 << 2);
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn bits(&self) -> u64 {
+        self.0
+    }
+
+    /// Keeps unknown bits, so that capabilities of newer peers survive.
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    /// Returns true if all of the given capabilities are supported.
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
+impl std::ops::BitOr for PeerCapabilities {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Information a peer reports about itself once connected. Peers that do not
+/// report it, e.g. because they run an older version, support no capabilities.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PeerMetadata {
+    /// The replica version the peer runs, if known.
+    pub replica_version: Option<String>,
+    pub capabilities: PeerCapabilities,
+}
+
+impl PeerMetadata {
+    /// Returns true if the peer supports all of the given capabilities.
+    pub fn supports(&self, capabilities: PeerCapabilities) -> bool {
+        self.capabilities.contains(capabilities)
+    }
+
+    /// Encodes the capabilities as little endian bits, followed by the replica version.
+    pub(crate) fn encode(&self) -> Bytes {
+        let mut encoded = self.capabilities.bits().to_le_bytes().to_vec();
+        encoded.extend_from_slice(
+            self.replica_version
+                .as_deref()
+                .unwrap_or_default()
+                .as_bytes(),
+        );
+        Bytes::from(encoded)
+    }
+
+    pub(crate) fn decode(encoded: &[u8]) -> Option<Self> {
+        let (bits, replica_version) = encoded.split_first_chunk::<8>()?;
+        let replica_version = std::str::from_utf8(replica_version).ok()?;
+        Some(Self {
+            replica_version: (!replica_version.is_empty()).then(|| replica_version.to_string()),
+            capabilities: PeerCapabilities::from_bits(u64::from_le_bytes(*bits)),
+        })
+    }
+}
+
 /// Holds socket addresses of all peers in a subnet.
 #[derive(Clone, Debug, Eq, PartialEq, Default)]
 pub struct SubnetTopology {
@@ -22,6 +22,7 @@ pub(crate) const ERROR_TYPE_FINISH: &str = "finish";
 pub(crate) const ERROR_TYPE_STOPPED: &str = "stopped";
 pub(crate) const ERROR_TYPE_READ: &str = "read";
 pub(crate) const ERROR_TYPE_WRITE: &str = "write";
+pub(crate) const ERROR_TYPE_TIMEOUT: &str = "timeout";
 pub(crate) const STREAM_TYPE_BIDI: &str = "bidi";
 pub(crate) const STREAM_TYPE_UNI: &str = "uni";
 pub(crate) const REQUEST_TYPE_PUSH: &str = "push";
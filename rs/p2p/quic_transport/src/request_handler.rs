@@ -6,7 +6,7 @@
 //!     - Adds metadata to the request based on the underlying connection.
 //!       E.g. adds the NodeId of the peer as an extension.
 //!     - Calls the router.
-//!     - Writes the response to the wire.
+//!     - Writes the response to the wire, streaming its body if the request asks for it.
 //!
 //! Please note that the connection manager is responsible for closing connections.
 //!
@@ -15,7 +15,7 @@ use std::time::Duration;
 use axum::Router;
 use ic_base_types::NodeId;
 use ic_logger::{info, ReplicaLogger};
-use quinn::{Connection, RecvStream, SendStream};
+use quinn::{Connection, RecvStream, SendStream, VarInt};
 use tower::ServiceExt;
 use tracing::instrument;
 
@@ -24,7 +24,7 @@ use crate::{
         QuicTransportMetrics, ERROR_TYPE_ACCEPT, ERROR_TYPE_APP, ERROR_TYPE_FINISH,
         ERROR_TYPE_READ, ERROR_TYPE_STOPPED, ERROR_TYPE_WRITE, STREAM_TYPE_BIDI, STREAM_TYPE_UNI,
     },
-    utils::{read_request, write_response},
+    utils::{read_request, write_response, write_streamed_response, STREAMED_RESPONSE_HEADER},
     ConnId,
 };
 
@@ -149,6 +149,7 @@ async fn handle_bi_stream(
 
     request.extensions_mut().insert::<NodeId>(peer_id);
     request.extensions_mut().insert::<ConnId>(conn_id);
+    let streamed = request.headers().contains_key(STREAMED_RESPONSE_HEADER);
 
     let svc = router.oneshot(request);
     let stopped = bi_tx.stopped();
@@ -170,12 +171,22 @@ async fn handle_bi_stream(
     // We can ignore the errors because if both peers follow the protocol an errors will only occur
     // if the other peer has closed the connection. In this case `accept_bi` in the peer event
     // loop will close this connection.
-    if let Err(e) = write_response(&mut bi_tx, response).await {
+    let written = if streamed {
+        write_streamed_response(&mut bi_tx, response).await
+    } else {
+        write_response(&mut bi_tx, response).await
+    };
+    if let Err(e) = written {
         info!(every_n_seconds => 60, log, "Failed to write response to stream: {}", e);
         metrics
             .request_handle_errors_total
             .with_label_values(&[STREAM_TYPE_BIDI, ERROR_TYPE_WRITE])
             .inc();
+        // A finished stream would pass a partially written body as complete.
+        if streamed {
+            let _ = bi_tx.reset(VarInt::from_u32(0));
+            return;
+        }
     }
     if let Err(e) = bi_tx.finish() {
         info!(every_n_seconds => 60, log, "Failed to finish stream: {}", e.to_string());
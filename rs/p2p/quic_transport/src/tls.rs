@@ -0,0 +1,188 @@
+//! Quic Transport TLS configuration.
+//!
+//! The rustls configurations returned by a [`TlsConfig`] are turned into quinn endpoint
+//! configurations by [`quinn_server_config`] and [`quinn_client_config`].
+//!
+//! [`KeyMaterialTlsConfig`] is a [`TlsConfig`] that is created in one call from a node's
+//! TLS key material, e.g. the output of `ic_crypto_internal_tls::generate_tls_key_pair_der`,
+//! and the certificates of the trusted peers. Peers are authenticated the same way as by the
+//! crypto component: The node ID is taken from the subject CN of the presented certificate,
+//! and the certificate must be equal to the trusted certificate of that node. Unlike the
+//! crypto component it does not need a secret key store or a registry, which makes it
+//! convenient for tests and tools. The verification itself is shared with other users of
+//! pinned node certificates via `ic_crypto_internal_tls::mutual_tls`.
+use std::{collections::BTreeMap, sync::Arc};
+
+use ic_base_types::{NodeId, RegistryVersion};
+use ic_crypto_internal_tls::{
+    mutual_tls::{MutualTlsError, NodeTlsIdentity, PinnedNodeCertVerifier},
+    TlsEd25519CertificateDerBytes, TlsEd25519SecretKeyDerBytes,
+};
+use ic_crypto_tls_interfaces::{SomeOrAllNodes, TlsConfig, TlsConfigError};
+use ic_crypto_utils_tls::node_id_from_certificate_der;
+use quinn::crypto::rustls::{QuicClientConfig, QuicServerConfig};
+use rustls::{pki_types::CertificateDer, ClientConfig, ServerConfig};
+
+/// Application protocol negotiated by endpoints configured with a [`KeyMaterialTlsConfig`].
+pub const ALPN_QUIC_TRANSPORT: &[u8] = b"ic-quic-transport";
+
+/// Creates the quinn server config of an endpoint from a rustls server config.
+pub(crate) fn quinn_server_config(
+    rustls_server_config: ServerConfig,
+    transport_config: Arc<quinn::TransportConfig>,
+) -> quinn::ServerConfig {
+    let quic_server_config = QuicServerConfig::try_from(rustls_server_config)
+        .expect("rustls server config supports TLS 1.3");
+    let mut server_config = quinn::ServerConfig::with_crypto(Arc::new(quic_server_config));
+    server_config.transport_config(transport_config);
+    server_config
+}
+
+/// Creates the quinn client config of a connection from a rustls client config.
+pub(crate) fn quinn_client_config(
+    rustls_client_config: ClientConfig,
+    transport_config: Arc<quinn::TransportConfig>,
+) -> quinn::ClientConfig {
+    let quic_client_config = QuicClientConfig::try_from(rustls_client_config)
+        .expect("rustls client config supports TLS 1.3");
+    let mut client_config = quinn::ClientConfig::new(Arc::new(quic_client_config));
+    client_config.transport_config(transport_config);
+    client_config
+}
+
+/// Returned by [`KeyMaterialTlsConfig::new`] if the key material can't be used.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeyMaterialError {
+    /// The certificate can't be parsed or its subject CN is not a node ID.
+    MalformedCertificate(String),
+    /// The secret key is not a DER-encoded Ed25519 key in PKCS#8 format.
+    MalformedSecretKey(String),
+}
+
+impl std::fmt::Display for KeyMaterialError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MalformedCertificate(e) => write!(f, "Malformed certificate. {e}"),
+            Self::MalformedSecretKey(e) => write!(f, "Malformed secret key. {e}"),
+        }
+    }
+}
+
+impl std::error::Error for KeyMaterialError {}
+
+impl From<MutualTlsError> for KeyMaterialError {
+    fn from(error: MutualTlsError) -> Self {
+        match error {
+            MutualTlsError::MalformedSecretKey(e) => Self::MalformedSecretKey(e),
+            other => Self::MalformedCertificate(other.to_string()),
+        }
+    }
+}
+
+/// [`TlsConfig`] backed by TLS key material instead of the crypto component.
+///
+/// The rustls configurations are created by `ic_crypto_internal_tls::mutual_tls`, which
+/// uses the same settings as the crypto component, and negotiate [`ALPN_QUIC_TRANSPORT`].
+/// The registry version passed to the [`TlsConfig`] methods is ignored.
+pub struct KeyMaterialTlsConfig {
+    identity: NodeTlsIdentity,
+    trusted_certificates: BTreeMap<NodeId, CertificateDer<'static>>,
+}
+
+impl KeyMaterialTlsConfig {
+    /// Creates the config from the node's DER-encoded certificate and PKCS#8 secret key,
+    /// and the DER-encoded certificates of the peers the node trusts.
+    pub fn new(
+        certificate_der: Vec<u8>,
+        secret_key_der: Vec<u8>,
+        trusted_certificates_der: impl IntoIterator<Item = Vec<u8>>,
+    ) -> Result<Self, KeyMaterialError> {
+        let identity = NodeTlsIdentity::new(
+            &TlsEd25519CertificateDerBytes {
+                bytes: certificate_der,
+            },
+            &TlsEd25519SecretKeyDerBytes::new(secret_key_der),
+        )
+        .map_err(KeyMaterialError::from)?;
+        let trusted_certificates = trusted_certificates_der
+            .into_iter()
+            .map(|der| {
+                let node_id = node_id_from_certificate_der(&der)
+                    .map_err(|e| KeyMaterialError::MalformedCertificate(e.to_string()))?;
+                Ok((node_id, CertificateDer::from(der)))
+            })
+            .collect::<Result<_, KeyMaterialError>>()?;
+
+        Ok(Self {
+            identity,
+            trusted_certificates,
+        })
+    }
+
+    /// The node ID from the subject CN of the node's certificate.
+    pub fn node_id(&self) -> NodeId {
+        self.identity.node_id()
+    }
+
+    fn verifier(&self, allowed_nodes: SomeOrAllNodes) -> Arc<PinnedNodeCertVerifier> {
+        Arc::new(PinnedNodeCertVerifier::new(
+            self.trusted_certificates
+                .iter()
+                .filter(|(node_id, _)| allowed_nodes.contains(**node_id))
+                .map(|(node_id, certificate)| (*node_id, certificate.clone()))
+                .collect(),
+        ))
+    }
+}
+
+impl TlsConfig for KeyMaterialTlsConfig {
+    fn server_config(
+        &self,
+        allowed_clients: SomeOrAllNodes,
+        _registry_version: RegistryVersion,
+    ) -> Result<ServerConfig, TlsConfigError> {
+        let mut server_config = self
+            .identity
+            .server_config(self.verifier(allowed_clients))
+            .map_err(malformed_self_certificate)?;
+        server_config.alpn_protocols = vec![ALPN_QUIC_TRANSPORT.to_vec()];
+        Ok(server_config)
+    }
+
+    fn server_config_without_client_auth(
+        &self,
+        _registry_version: RegistryVersion,
+    ) -> Result<ServerConfig, TlsConfigError> {
+        let mut server_config = self
+            .identity
+            .server_config_without_client_auth()
+            .map_err(malformed_self_certificate)?;
+        server_config.alpn_protocols = vec![ALPN_QUIC_TRANSPORT.to_vec()];
+        Ok(server_config)
+    }
+
+    fn client_config(
+        &self,
+        server: NodeId,
+        registry_version: RegistryVersion,
+    ) -> Result<ClientConfig, TlsConfigError> {
+        if !self.trusted_certificates.contains_key(&server) {
+            return Err(TlsConfigError::CertificateNotInRegistry {
+                node_id: server,
+                registry_version,
+            });
+        }
+        let mut client_config = self
+            .identity
+            .client_config(self.verifier(SomeOrAllNodes::new_with_single_node(server)))
+            .map_err(malformed_self_certificate)?;
+        client_config.alpn_protocols = vec![ALPN_QUIC_TRANSPORT.to_vec()];
+        Ok(client_config)
+    }
+}
+
+fn malformed_self_certificate(error: MutualTlsError) -> TlsConfigError {
+    TlsConfigError::MalformedSelfCertificate {
+        internal_error: error.to_string(),
+    }
+}
@@ -3,19 +3,25 @@
 //! Contains the actual wire format used for messages.
 //! Request encoding of Request<Bytes> into HttpRequest protobuf.
 //! Response encoding Response<Bytes> into HttpResponse protobuf.
+//! Streamed responses send the HttpResponse protobuf without body first, followed by the raw body.
 use anyhow::{anyhow, Context};
 use axum::{
     body::{Body, HttpBody},
     extract::State,
-    http::{Method, Request, Response, Version},
+    http::{response::Parts, Method, Request, Response, Version},
     middleware::Next,
 };
 use bytes::Bytes;
+use futures::{stream, StreamExt};
 use ic_protobuf::transport::v1 as pb;
 use prost::Message;
 use quinn::{RecvStream, SendStream};
 
-use crate::metrics::QuicTransportMetrics;
+use crate::{metrics::QuicTransportMetrics, BodyStream};
+
+/// Request header asking the peer to stream the response, see
+/// [`write_streamed_response`].
+pub(crate) const STREAMED_RESPONSE_HEADER: &str = "x-ic-streamed-response";
 
 /// On purpose the value is big, otherwise there is risk of not processing important consensus messages.
 /// E.g. summary blocks generated by the consensus protocol for 40 node subnet can be bigger than 5MB.
@@ -71,9 +77,55 @@ pub(crate) async fn read_response(
         .await
         .with_context(|| "Failed to read response from the stream.")?;
 
-    let response_proto = pb::HttpResponse::decode(raw_msg.as_slice())
+    let mut response_proto = pb::HttpResponse::decode(raw_msg.as_slice())
         .with_context(|| "Failed to decode response header.")?;
 
+    // This consumes the body without requiring allocation or cloning the whole content.
+    let body_bytes = Bytes::from(std::mem::take(&mut response_proto.body));
+    response_from_proto(response_proto, body_bytes)
+}
+
+/// Reads a response written with [`write_streamed_response`]. The returned
+/// body yields the chunks as they arrive on the stream.
+pub(crate) async fn read_streamed_response(
+    mut recv_stream: RecvStream,
+) -> Result<Response<BodyStream>, anyhow::Error> {
+    let mut head_len = [0; 4];
+    recv_stream
+        .read_exact(&mut head_len)
+        .await
+        .with_context(|| "Failed to read response header length from the stream.")?;
+    let head_len = u32::from_be_bytes(head_len) as usize;
+    if head_len > MAX_MESSAGE_SIZE_BYTES {
+        return Err(anyhow!(
+            "Response header of {} bytes is too large",
+            head_len
+        ));
+    }
+    let mut head = vec![0; head_len];
+    recv_stream
+        .read_exact(&mut head)
+        .await
+        .with_context(|| "Failed to read response header from the stream.")?;
+
+    let response_proto = pb::HttpResponse::decode(head.as_slice())
+        .with_context(|| "Failed to decode response header.")?;
+
+    let body = stream::try_unfold(recv_stream, |mut recv_stream| async move {
+        let chunk = recv_stream
+            .read_chunk(usize::MAX, true)
+            .await
+            .with_context(|| "Failed to read response body from the stream.")?;
+        Ok(chunk.map(|chunk| (chunk.bytes, recv_stream)))
+    })
+    .boxed();
+    response_from_proto(response_proto, body)
+}
+
+fn response_from_proto<B>(
+    response_proto: pb::HttpResponse,
+    body: B,
+) -> Result<Response<B>, anyhow::Error> {
     let status: u16 = match response_proto.status_code.try_into() {
         Ok(status) => status,
         Err(e) => {
@@ -90,10 +142,8 @@ pub(crate) async fn read_response(
         let pb::HttpHeader { key, value } = h;
         response = response.header(key, value);
     }
-    // This consumes the body without requiring allocation or cloning the whole content.
-    let body_bytes = Bytes::from(response_proto.body);
     response
-        .body(body_bytes)
+        .body(body)
         .with_context(|| "Failed to build response.")
 }
 
@@ -101,6 +151,16 @@ pub(crate) async fn write_request(
     send_stream: &mut SendStream,
     request: Request<Bytes>,
 ) -> Result<(), anyhow::Error> {
+    let request_bytes = encode_request(request)?;
+    send_stream
+        .write_all(&request_bytes)
+        .await
+        .with_context(|| "Failed to write request to stream.")?;
+    Ok(())
+}
+
+/// Encodes the request as it is written to the stream.
+pub(crate) fn encode_request(request: Request<Bytes>) -> Result<Bytes, anyhow::Error> {
     let (parts, body) = request.into_parts();
 
     let request_proto = pb::HttpRequest {
@@ -130,12 +190,7 @@ pub(crate) async fn write_request(
         body: body.into(),
     };
 
-    let request_bytes = request_proto.encode_to_vec();
-    send_stream
-        .write_all(&request_bytes)
-        .await
-        .with_context(|| "Failed to write request to stream.")?;
-    Ok(())
+    Ok(Bytes::from(request_proto.encode_to_vec()))
 }
 
 pub(crate) async fn write_response(
@@ -148,7 +203,47 @@ pub(crate) async fn write_response(
     let body = axum::body::to_bytes(body, MAX_MESSAGE_SIZE_BYTES)
         .await
         .with_context(|| "Failed to read response from body.")?;
-    let response_proto = pb::HttpResponse {
+    let response_proto = response_to_proto(parts, body);
+
+    let response_bytes = response_proto.encode_to_vec();
+    send_stream
+        .write_all(&response_bytes)
+        .await
+        .with_context(|| "Failed to write request to stream.")?;
+    Ok(())
+}
+
+/// Writes the response without its body, prefixed with its length as a
+/// big-endian `u32`, followed by the raw body chunks as the handler produces
+/// them. The body is never buffered as a whole.
+pub(crate) async fn write_streamed_response(
+    send_stream: &mut SendStream,
+    response: Response<Body>,
+) -> Result<(), anyhow::Error> {
+    let (parts, body) = response.into_parts();
+    let head = response_to_proto(parts, Bytes::new()).encode_to_vec();
+    send_stream
+        .write_all(&(head.len() as u32).to_be_bytes())
+        .await
+        .with_context(|| "Failed to write response header length to stream.")?;
+    send_stream
+        .write_all(&head)
+        .await
+        .with_context(|| "Failed to write response header to stream.")?;
+
+    let mut body = body.into_data_stream();
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk.with_context(|| "Failed to read response from body.")?;
+        send_stream
+            .write_all(&chunk)
+            .await
+            .with_context(|| "Failed to write response body to stream.")?;
+    }
+    Ok(())
+}
+
+fn response_to_proto(parts: Parts, body: Bytes) -> pb::HttpResponse {
+    pb::HttpResponse {
         status_code: parts.status.as_u16().into(),
         headers: parts
             .headers
@@ -161,14 +256,7 @@ pub(crate) async fn write_response(
             })
             .collect(),
         body: body.into(),
-    };
-
-    let response_bytes = response_proto.encode_to_vec();
-    send_stream
-        .write_all(&response_bytes)
-        .await
-        .with_context(|| "Failed to write request to stream.")?;
-    Ok(())
+    }
 }
 
 /// Axum middleware to collect metrics
@@ -1,27 +1,32 @@
-use std::{net::SocketAddr, sync::Arc, time::Duration};
+use std::{convert::Infallible, net::SocketAddr, sync::Arc, time::Duration};
 
 use crate::common::PeerRestrictedTlsConfig;
-use axum::{http::Request, Router};
+use axum::{body::Body, http::Request, Router};
 use bytes::Bytes;
 use either::Either;
-use futures::FutureExt;
+use futures::{stream, FutureExt, TryStreamExt};
 use ic_base_types::{NodeId, RegistryVersion};
 use ic_logger::info;
 use ic_metrics::MetricsRegistry;
 use ic_p2p_test_utils::{
-    create_peer_manager_and_registry_handle, temp_crypto_component_with_tls_keys,
+    create_peer_manager_and_registry_handle, key_material_tls_configs,
+    temp_crypto_component_with_tls_keys,
     turmoil::{
-        add_peer_manager_to_sim, add_transport_to_sim, wait_for, wait_for_timeout,
+        add_peer_manager_to_sim, add_transport_to_sim, wait_for, wait_for_timeout, waiter_fut,
         PeerManagerAction,
     },
     ConnectivityChecker,
 };
-use ic_quic_transport::{DummyUdpSocket, QuicTransport, Transport};
+use ic_quic_transport::{
+    DummyUdpSocket, PeerCapabilities, PeerMetadata, QuicTransport, Shutdown, ShutdownStatus,
+    ShutdownTimeout, Transport,
+};
 use ic_test_utilities_logger::with_test_replica_logger;
 use ic_types_test_utils::ids::{NODE_1, NODE_2, NODE_3, NODE_4, NODE_5};
 use tokio::{
+    runtime::Handle,
     sync::{mpsc, Notify},
-    time::timeout,
+    time::{timeout, Instant},
 };
 use turmoil::Builder;
 
@@ -86,6 +91,69 @@ fn test_ping_pong() {
     })
 }
 
+/// Transports configured from generated TLS key material connect without
+/// any certificates in the registry.
+#[test]
+fn test_ping_pong_with_key_material_tls() {
+    with_test_replica_logger(|log| {
+        info!(log, "Starting test");
+
+        let mut sim = Builder::new()
+            .tick_duration(Duration::from_millis(100))
+            .simulation_duration(Duration::from_secs(10))
+            .build();
+
+        let exit_notify = Arc::new(Notify::new());
+
+        let (peer_manager_cmd_sender, topology_watcher, registry_handle) =
+            add_peer_manager_to_sim(&mut sim, exit_notify.clone(), log.clone());
+
+        let conn_checker = ConnectivityChecker::new(&[NODE_1, NODE_2]);
+        let tls_configs = key_material_tls_configs(&[NODE_1, NODE_2]);
+
+        add_transport_to_sim(
+            &mut sim,
+            log.clone(),
+            NODE_1,
+            registry_handle.clone(),
+            topology_watcher.clone(),
+            Some(ConnectivityChecker::router()),
+            Some(tls_configs[&NODE_1].clone()),
+            None,
+            None,
+            conn_checker.check_fut(),
+        );
+
+        add_transport_to_sim(
+            &mut sim,
+            log,
+            NODE_2,
+            registry_handle.clone(),
+            topology_watcher,
+            Some(ConnectivityChecker::router()),
+            Some(tls_configs[&NODE_2].clone()),
+            None,
+            None,
+            conn_checker.check_fut(),
+        );
+
+        peer_manager_cmd_sender
+            .send(PeerManagerAction::Add((NODE_1, RegistryVersion::from(2))))
+            .unwrap();
+        peer_manager_cmd_sender
+            .send(PeerManagerAction::Add((NODE_2, RegistryVersion::from(3))))
+            .unwrap();
+        registry_handle.registry_client.reload();
+        registry_handle.registry_client.update_to_latest_version();
+
+        wait_for(&mut sim, || conn_checker.fully_connected())
+            .expect("The network did not reach a fully connected state after startup");
+
+        exit_notify.notify_waiters();
+        sim.run().unwrap();
+    })
+}
+
 #[test]
 fn test_graceful_shutdown() {
     with_test_replica_logger(|log| {
@@ -109,6 +177,7 @@ fn test_graceful_shutdown() {
             node_crypto_1,
             registry_handler.registry_client.clone(),
             NODE_1,
+            PeerMetadata::default(),
             topology_watcher.clone(),
             Either::Left::<_, DummyUdpSocket>(socket_1),
             ConnectivityChecker::router(),
@@ -121,6 +190,7 @@ fn test_graceful_shutdown() {
             node_crypto_2,
             registry_handler.registry_client.clone(),
             NODE_2,
+            PeerMetadata::default(),
             topology_watcher,
             Either::Left::<_, DummyUdpSocket>(socket_2),
             ConnectivityChecker::router(),
@@ -163,6 +233,85 @@ fn test_graceful_shutdown() {
     })
 }
 
+#[test]
+fn test_peer_metadata() {
+    with_test_replica_logger(|log| {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+
+        let (_jh, topology_watcher, mut registry_handler) =
+            create_peer_manager_and_registry_handle(rt.handle(), log.clone());
+
+        let node_crypto_1 = temp_crypto_component_with_tls_keys(&registry_handler, NODE_1);
+        let node_crypto_2 = temp_crypto_component_with_tls_keys(&registry_handler, NODE_2);
+        registry_handler.registry_client.update_to_latest_version();
+
+        let socket_1: SocketAddr = "127.0.12.1:4100".parse().unwrap();
+        let socket_2: SocketAddr = "127.0.13.1:4100".parse().unwrap();
+        let metadata_1 = PeerMetadata {
+            replica_version: Some("0.9.0".to_string()),
+            capabilities: PeerCapabilities::COMPRESSION,
+        };
+
+        let transport_1 = Arc::new(QuicTransport::start(
+            &log,
+            &MetricsRegistry::default(),
+            rt.handle(),
+            node_crypto_1,
+            registry_handler.registry_client.clone(),
+            NODE_1,
+            metadata_1.clone(),
+            topology_watcher.clone(),
+            Either::Left::<_, DummyUdpSocket>(socket_1),
+            ConnectivityChecker::router(),
+        ));
+
+        let transport_2 = Arc::new(QuicTransport::start(
+            &log,
+            &MetricsRegistry::default(),
+            rt.handle(),
+            node_crypto_2,
+            registry_handler.registry_client.clone(),
+            NODE_2,
+            PeerMetadata::default(),
+            topology_watcher,
+            Either::Left::<_, DummyUdpSocket>(socket_2),
+            ConnectivityChecker::router(),
+        ));
+
+        registry_handler.add_node(
+            RegistryVersion::from(2),
+            NODE_1,
+            Some(&socket_1.ip().to_string()),
+        );
+        registry_handler.add_node(
+            RegistryVersion::from(3),
+            NODE_2,
+            Some(&socket_2.ip().to_string()),
+        );
+        registry_handler.registry_client.reload();
+        registry_handler.registry_client.update_to_latest_version();
+
+        let metadata_exchanged_fut = async {
+            while transport_1.peer_metadata(&NODE_2).is_none()
+                || transport_2.peer_metadata(&NODE_1).is_none()
+            {
+                tokio::time::sleep(Duration::from_millis(250)).await;
+            }
+        };
+        rt.block_on(async move { timeout(Duration::from_secs(10), metadata_exchanged_fut).await })
+            .unwrap();
+
+        assert_eq!(transport_2.peer_metadata(&NODE_1), Some(metadata_1));
+        assert_eq!(
+            transport_1.peer_metadata(&NODE_2),
+            Some(PeerMetadata::default())
+        );
+        assert!(transport_2
+            .peer_metadata(&NODE_1)
+            .is_some_and(|metadata| metadata.supports(PeerCapabilities::COMPRESSION)));
+    })
+}
+
 #[test]
 fn test_real_socket() {
     with_test_replica_logger(|log| {
@@ -186,6 +335,7 @@ fn test_real_socket() {
             node_crypto_1,
             registry_handler.registry_client.clone(),
             NODE_1,
+            PeerMetadata::default(),
             topology_watcher.clone(),
             Either::Left::<_, DummyUdpSocket>(socket_1),
             ConnectivityChecker::router(),
@@ -198,6 +348,7 @@ fn test_real_socket() {
             node_crypto_2,
             registry_handler.registry_client.clone(),
             NODE_2,
+            PeerMetadata::default(),
             topology_watcher,
             Either::Left::<_, DummyUdpSocket>(socket_2),
             ConnectivityChecker::router(),
@@ -255,6 +406,7 @@ fn test_real_socket_large_msg() {
             node_crypto_1,
             registry_handler.registry_client.clone(),
             NODE_1,
+            PeerMetadata::default(),
             topology_watcher.clone(),
             Either::Left::<_, DummyUdpSocket>(socket_1),
             ConnectivityChecker::router(),
@@ -267,6 +419,7 @@ fn test_real_socket_large_msg() {
             node_crypto_2,
             registry_handler.registry_client.clone(),
             NODE_2,
+            PeerMetadata::default(),
             topology_watcher,
             Either::Left::<_, DummyUdpSocket>(socket_2),
             ConnectivityChecker::router(),
@@ -406,6 +559,357 @@ fn test_sending_large_message() {
     })
 }
 
+/// Test that an RPC with a deadline fails once the deadline passed, while RPCs
+/// that complete in time succeed.
+#[test]
+fn test_rpc_with_deadline() {
+    with_test_replica_logger(|log| {
+        info!(log, "Starting test");
+
+        let mut sim = Builder::new()
+            .tick_duration(Duration::from_millis(100))
+            .simulation_duration(Duration::from_secs(30))
+            .build();
+
+        let exit_notify = Arc::new(Notify::new());
+
+        let (peer_manager_cmd_sender, topology_watcher, registry_handle) =
+            add_peer_manager_to_sim(&mut sim, exit_notify.clone(), log.clone());
+
+        let router_2: Router<()> = Router::new()
+            .route("/fast", axum::routing::any(|| async {}))
+            .route(
+                "/slow",
+                axum::routing::any(|| async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }),
+            );
+
+        let (outcome_tx, mut outcome_rx) = mpsc::channel(1);
+        let call_node_2 = move |_node_id: NodeId, transport: Arc<dyn Transport>| {
+            let outcome_tx = outcome_tx.clone();
+            async move {
+                // Wait until the nodes are connected.
+                while transport
+                    .rpc_with_deadline(
+                        &NODE_2,
+                        Request::builder().uri("/fast").body(Bytes::new()).unwrap(),
+                        Instant::now() + Duration::from_secs(1),
+                    )
+                    .await
+                    .is_err()
+                {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+                let slow = transport
+                    .rpc_with_deadline(
+                        &NODE_2,
+                        Request::builder().uri("/slow").body(Bytes::new()).unwrap(),
+                        Instant::now() + Duration::from_secs(1),
+                    )
+                    .await;
+                outcome_tx.send(slow.is_err()).await.unwrap();
+                waiter_fut()(NODE_1, transport).await;
+            }
+            .boxed()
+        };
+
+        add_transport_to_sim(
+            &mut sim,
+            log.clone(),
+            NODE_1,
+            registry_handle.clone(),
+            topology_watcher.clone(),
+            None,
+            None,
+            None,
+            None,
+            call_node_2,
+        );
+
+        add_transport_to_sim(
+            &mut sim,
+            log,
+            NODE_2,
+            registry_handle.clone(),
+            topology_watcher,
+            Some(router_2),
+            None,
+            None,
+            None,
+            waiter_fut(),
+        );
+
+        peer_manager_cmd_sender
+            .send(PeerManagerAction::Add((NODE_1, RegistryVersion::from(2))))
+            .unwrap();
+        peer_manager_cmd_sender
+            .send(PeerManagerAction::Add((NODE_2, RegistryVersion::from(3))))
+            .unwrap();
+        registry_handle.registry_client.reload();
+        registry_handle.registry_client.update_to_latest_version();
+
+        let mut slow_failed = None;
+        wait_for(&mut sim, || {
+            slow_failed = outcome_rx.try_recv().ok();
+            slow_failed.is_some()
+        })
+        .expect("Node 1 did not complete its RPCs to node 2.");
+        assert_eq!(
+            slow_failed,
+            Some(true),
+            "The RPC to the slow handler completed despite its deadline."
+        );
+
+        exit_notify.notify_waiters();
+        sim.run().unwrap();
+    })
+}
+
+/// Test that a streamed RPC delivers the complete body of a large response
+/// produced by a streaming handler.
+#[test]
+fn test_rpc_stream() {
+    with_test_replica_logger(|log| {
+        info!(log, "Starting test");
+
+        let mut sim = Builder::new()
+            .tick_duration(Duration::from_millis(100))
+            .simulation_duration(Duration::from_secs(30))
+            .build();
+
+        let exit_notify = Arc::new(Notify::new());
+
+        let (peer_manager_cmd_sender, topology_watcher, registry_handle) =
+            add_peer_manager_to_sim(&mut sim, exit_notify.clone(), log.clone());
+
+        const CHUNKS: u8 = 4;
+        const CHUNK_SIZE: usize = 1_000_000;
+        let router_2: Router<()> = Router::new().route(
+            "/stream",
+            axum::routing::any(|| async {
+                Body::from_stream(stream::iter(
+                    (0..CHUNKS).map(|i| Ok::<_, Infallible>(vec![i; CHUNK_SIZE])),
+                ))
+            }),
+        );
+
+        let (body_tx, mut body_rx) = mpsc::channel(1);
+        let call_node_2 = move |_node_id: NodeId, transport: Arc<dyn Transport>| {
+            let body_tx = body_tx.clone();
+            async move {
+                // Wait until the nodes are connected.
+                let response = loop {
+                    match transport
+                        .rpc_stream(
+                            &NODE_2,
+                            Request::builder()
+                                .uri("/stream")
+                                .body(Bytes::new())
+                                .unwrap(),
+                        )
+                        .await
+                    {
+                        Ok(response) => break response,
+                        Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+                    }
+                };
+                let body = response
+                    .into_body()
+                    .try_fold(Vec::new(), |mut body, chunk| async move {
+                        body.extend_from_slice(&chunk);
+                        Ok(body)
+                    })
+                    .await;
+                body_tx.send(body.ok()).await.unwrap();
+                waiter_fut()(NODE_1, transport).await;
+            }
+            .boxed()
+        };
+
+        add_transport_to_sim(
+            &mut sim,
+            log.clone(),
+            NODE_1,
+            registry_handle.clone(),
+            topology_watcher.clone(),
+            None,
+            None,
+            None,
+            None,
+            call_node_2,
+        );
+
+        add_transport_to_sim(
+            &mut sim,
+            log,
+            NODE_2,
+            registry_handle.clone(),
+            topology_watcher,
+            Some(router_2),
+            None,
+            None,
+            None,
+            waiter_fut(),
+        );
+
+        peer_manager_cmd_sender
+            .send(PeerManagerAction::Add((NODE_1, RegistryVersion::from(2))))
+            .unwrap();
+        peer_manager_cmd_sender
+            .send(PeerManagerAction::Add((NODE_2, RegistryVersion::from(3))))
+            .unwrap();
+        registry_handle.registry_client.reload();
+        registry_handle.registry_client.update_to_latest_version();
+
+        let mut body = None;
+        wait_for(&mut sim, || {
+            body = body_rx.try_recv().ok();
+            body.is_some()
+        })
+        .expect("Node 1 did not complete its streamed RPC to node 2.");
+        let expected: Vec<u8> = (0..CHUNKS)
+            .flat_map(|i| std::iter::repeat(i).take(CHUNK_SIZE))
+            .collect();
+        assert_eq!(
+            body,
+            Some(Some(expected)),
+            "The streamed body differs from the body of the handler."
+        );
+
+        exit_notify.notify_waiters();
+        sim.run().unwrap();
+    })
+}
+
+/// Test that a broadcast reaches all connected peers and reports the outcome
+/// for each of them.
+#[test]
+fn test_broadcast() {
+    with_test_replica_logger(|log| {
+        info!(log, "Starting test");
+
+        let mut sim = Builder::new()
+            .tick_duration(Duration::from_millis(100))
+            .simulation_duration(Duration::from_secs(30))
+            .build();
+
+        let exit_notify = Arc::new(Notify::new());
+
+        let (peer_manager_cmd_sender, topology_watcher, registry_handle) =
+            add_peer_manager_to_sim(&mut sim, exit_notify.clone(), log.clone());
+
+        let (received_tx, mut received_rx) = mpsc::unbounded_channel();
+        let receiving_router = |node_id: NodeId| {
+            let received_tx = received_tx.clone();
+            Router::new().route(
+                "/broadcast",
+                axum::routing::any(move |body: Bytes| async move {
+                    received_tx.send((node_id, body)).unwrap();
+                }),
+            )
+        };
+
+        let (outcome_tx, mut outcome_rx) = mpsc::channel(1);
+        let broadcast_fut = move |_node_id: NodeId, transport: Arc<dyn Transport>| {
+            let outcome_tx = outcome_tx.clone();
+            async move {
+                // Wait until both peers are connected.
+                while transport.peers().len() < 2 {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+                let mut outcome: Vec<_> = transport
+                    .broadcast(
+                        Request::builder()
+                            .uri("/broadcast")
+                            .body(Bytes::from_static(b"hello"))
+                            .unwrap(),
+                        Instant::now() + Duration::from_secs(5),
+                    )
+                    .await
+                    .into_iter()
+                    .map(|(peer_id, _, result)| (peer_id, result.is_ok()))
+                    .collect();
+                outcome.sort();
+                outcome_tx.send(outcome).await.unwrap();
+                waiter_fut()(NODE_1, transport).await;
+            }
+            .boxed()
+        };
+
+        add_transport_to_sim(
+            &mut sim,
+            log.clone(),
+            NODE_1,
+            registry_handle.clone(),
+            topology_watcher.clone(),
+            None,
+            None,
+            None,
+            None,
+            broadcast_fut,
+        );
+
+        for node_id in [NODE_2, NODE_3] {
+            add_transport_to_sim(
+                &mut sim,
+                log.clone(),
+                node_id,
+                registry_handle.clone(),
+                topology_watcher.clone(),
+                Some(receiving_router(node_id)),
+                None,
+                None,
+                None,
+                waiter_fut(),
+            );
+        }
+
+        for (version, node_id) in [(2, NODE_1), (3, NODE_2), (4, NODE_3)] {
+            peer_manager_cmd_sender
+                .send(PeerManagerAction::Add((
+                    node_id,
+                    RegistryVersion::from(version),
+                )))
+                .unwrap();
+        }
+        registry_handle.registry_client.reload();
+        registry_handle.registry_client.update_to_latest_version();
+
+        let mut outcome = None;
+        wait_for(&mut sim, || {
+            outcome = outcome_rx.try_recv().ok();
+            outcome.is_some()
+        })
+        .expect("Node 1 did not complete its broadcast.");
+        let mut expected = vec![(NODE_2, true), (NODE_3, true)];
+        expected.sort();
+        assert_eq!(outcome, Some(expected));
+
+        // The handlers may run after the pushes completed.
+        let mut received = Vec::new();
+        wait_for(&mut sim, || {
+            received.extend(received_rx.try_recv().ok());
+            received.len() == 2
+        })
+        .expect("Not all peers handled the broadcast.");
+        received.sort();
+        let mut expected = vec![
+            (NODE_2, Bytes::from_static(b"hello")),
+            (NODE_3, Bytes::from_static(b"hello")),
+        ];
+        expected.sort();
+        assert_eq!(received, expected);
+        assert!(
+            received_rx.try_recv().is_err(),
+            "A peer received the broadcast twice."
+        );
+
+        exit_notify.notify_waiters();
+        sim.run().unwrap();
+    })
+}
+
 /// Test abrupt peer crashes and verify that dead connections are detected and repaired.
 #[test]
 fn test_peer_restart() {
@@ -1105,3 +1609,42 @@ fn test_bad_network_and_membership_change() {
         sim.run().unwrap();
     })
 }
+
+/// Test that a combined shutdown stops all tasks and reports the tasks that
+/// ignore the cancellation.
+#[tokio::test]
+async fn test_shutdown_join_all() {
+    let rt = Handle::current();
+    let cooperative = || Shutdown::spawn_on_with_cancellation(|c| c.cancelled_owned(), &rt);
+    let hanging = Shutdown::spawn_on_with_cancellation(|_| std::future::pending::<()>(), &rt);
+    let shutdown = Shutdown::join_all(vec![cooperative(), hanging.clone(), cooperative()]);
+    assert_eq!(shutdown.status(), ShutdownStatus::Running);
+
+    assert_eq!(
+        shutdown
+            .shutdown_with_timeout(Duration::from_millis(100))
+            .await,
+        Err(ShutdownTimeout { pending: vec![1] })
+    );
+    assert!(!shutdown.completed());
+    assert_eq!(hanging.status(), ShutdownStatus::Running);
+
+    let shutdown = Shutdown::join_all(vec![cooperative(), cooperative()]);
+    assert_eq!(
+        shutdown.shutdown_with_timeout(Duration::from_secs(5)).await,
+        Ok(())
+    );
+    assert_eq!(shutdown.status(), ShutdownStatus::Completed);
+}
+
+/// Test that a panicking task marks the shutdown as failed.
+#[tokio::test]
+async fn test_shutdown_failed() {
+    let shutdown = Shutdown::spawn_on_with_cancellation(
+        |_| async { panic!("Task failed.") },
+        &Handle::current(),
+    );
+    shutdown.shutdown().await;
+    assert!(shutdown.failed());
+    assert_eq!(shutdown.status(), ShutdownStatus::Failed);
+}
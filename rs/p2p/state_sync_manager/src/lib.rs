@@ -39,6 +39,7 @@ use tokio_util::sync::CancellationToken;
 mod metrics;
 mod ongoing;
 mod routes;
+mod throttle;
 
 // Interval with which state is advertised to peers.
 const ADVERT_BROADCAST_INTERVAL: Duration = Duration::from_secs(5);
@@ -47,6 +48,30 @@ const ADVERT_BROADCAST_INTERVAL: Duration = Duration::from_secs(5);
 const ADVERT_BROADCAST_TIMEOUT: Duration =
     ADVERT_BROADCAST_INTERVAL.saturating_sub(Duration::from_secs(2));
 
+/// Limits on chunk downloads of state sync. They keep a state sync from starving the
+/// consensus traffic on constrained links.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateSyncConfig {
+    /// Number of chunks that are downloaded in parallel from a single peer.
+    pub parallel_chunk_downloads_per_peer: usize,
+    /// Upper bound on the number of chunks that are downloaded in parallel from all peers.
+    pub max_parallel_chunk_downloads: Option<usize>,
+    /// Bandwidth in bytes per second that chunk downloads may use. The rest of the link
+    /// is left to other traffic. Downloads are not throttled if this is `None`.
+    pub max_download_bandwidth: Option<u64>,
+}
+
+impl Default for StateSyncConfig {
+    fn default() -> Self {
+        Self {
+            // TODO: NET-1461 find appropriate value for the parallelism
+            parallel_chunk_downloads_per_peer: 10,
+            max_parallel_chunk_downloads: None,
+            max_download_bandwidth: None,
+        }
+    }
+}
+
 pub fn build_axum_router<T: 'static>(
     state_sync: Arc<dyn StateSyncClient<Message = T>>,
     log: ReplicaLogger,
@@ -84,12 +109,14 @@ pub fn start_state_sync_manager<T: Send + 'static>(
     transport: Arc<dyn Transport>,
     state_sync: Arc<dyn StateSyncClient<Message = T>>,
     advert_receiver: tokio::sync::mpsc::Receiver<(StateSyncArtifactId, NodeId)>,
+    config: StateSyncConfig,
 ) -> Shutdown {
     let state_sync_manager_metrics = StateSyncManagerMetrics::new(metrics);
     let manager = StateSyncManager {
         log: log.clone(),
         rt: rt.clone(),
         metrics: state_sync_manager_metrics,
+        config,
         transport,
         state_sync,
         advert_receiver,
@@ -105,6 +132,7 @@ struct StateSyncManager<T> {
     log: ReplicaLogger,
     rt: Handle,
     metrics: StateSyncManagerMetrics,
+    config: StateSyncConfig,
     transport: Arc<dyn Transport>,
     state_sync: Arc<dyn StateSyncClient<Message = T>>,
     advert_receiver: tokio::sync::mpsc::Receiver<(StateSyncArtifactId, NodeId)>,
@@ -185,6 +213,7 @@ impl<T: 'static + Send> StateSyncManager<T> {
                 Arc::new(Mutex::new(chunkable)),
                 artifact_id.clone(),
                 self.transport.clone(),
+                self.config.clone(),
             );
             // Add peer that initiated this state sync to ongoing state sync.
             ongoing
@@ -337,6 +366,7 @@ mod tests {
                 Arc::new(t) as Arc<_>,
                 Arc::new(s) as Arc<_>,
                 handler_rx,
+                StateSyncConfig::default(),
             );
             rt.block_on(async move {
                 handler_tx.send((id, NODE_1)).await.unwrap();
@@ -68,6 +68,8 @@ pub(crate) struct OngoingStateSyncMetrics {
     pub peers_serving_state: IntGauge,
     pub chunk_download_duration: Histogram,
     pub chunk_download_results_total: IntCounterVec,
+    pub download_rate: IntGauge,
+    pub download_throttled_total: IntCounter,
 }
 
 impl OngoingStateSyncMetrics {
@@ -113,6 +115,14 @@ impl OngoingStateSyncMetrics {
                 "Chunk download request results.",
                 &[CHUNK_DOWNLOAD_STATUS_LABEL],
             ),
+            download_rate: metrics_registry.int_gauge(
+                "state_sync_manager_download_rate_bytes",
+                "Rate in bytes per second at which chunks are received from transport.",
+            ),
+            download_throttled_total: metrics_registry.int_counter(
+                "state_sync_manager_download_throttled_total",
+                "Number of times chunk downloads were paused because the bandwidth limit was reached.",
+            ),
         }
     }
 
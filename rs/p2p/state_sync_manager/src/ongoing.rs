@@ -3,7 +3,8 @@
 //! Implements the logic that drives the chunk download for a particular state sync.
 //! Mechanism:
 //!  - Ask State sync for which chunks to download
-//!  - Download this batch of chunk in parallel with a concurrency limiter per peer.
+//!  - Download this batch of chunk in parallel with a concurrency limiter per peer
+//!    and an optional limit on the total concurrency and bandwidth.
 //!    Note: - We randomly chose a peer from the set of peers advertised this state.
 //!          - We don't retry failed downloads immediately. Failed downloads are retried
 //!            in the next batch download.
@@ -18,6 +19,8 @@ use std::{
 
 use crate::metrics::OngoingStateSyncMetrics;
 use crate::routes::{build_chunk_handler_request, parse_chunk_handler_response};
+use crate::throttle::{BandwidthThrottle, RateMeter};
+use crate::StateSyncConfig;
 
 use ic_async_utils::JoinMap;
 use ic_base_types::NodeId;
@@ -34,11 +37,10 @@ use tokio::{
     runtime::Handle,
     select,
     sync::mpsc::{Receiver, Sender},
+    time::{sleep_until, Instant},
 };
 use tokio_util::sync::CancellationToken;
 
-// TODO: NET-1461 find appropriate value for the parallelism
-const PARALLEL_CHUNK_DOWNLOADS: usize = 10;
 const ONGOING_STATE_SYNC_CHANNEL_SIZE: usize = 200;
 const CHUNK_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(10);
 
@@ -47,6 +49,7 @@ struct OngoingStateSync {
     rt: Handle,
     artifact_id: StateSyncArtifactId,
     metrics: OngoingStateSyncMetrics,
+    config: StateSyncConfig,
     transport: Arc<dyn Transport>,
     // Peer management
     new_peers_rx: Receiver<NodeId>,
@@ -57,6 +60,11 @@ struct OngoingStateSync {
     chunks_to_download: Box<dyn Iterator<Item = ChunkId> + Send>,
     // Event tasks
     downloading_chunks: JoinMap<ChunkId, DownloadResult>,
+    // Bandwidth management
+    throttle: Option<BandwidthThrottle>,
+    // Set while the bandwidth is exhausted. No downloads are started until then.
+    throttled_until: Option<Instant>,
+    download_rate: RateMeter,
 }
 
 pub(crate) struct OngoingStateSyncHandle {
@@ -67,6 +75,8 @@ pub(crate) struct OngoingStateSyncHandle {
 
 pub(crate) struct DownloadResult {
     peer_id: NodeId,
+    // Bytes received from the peer.
+    bytes: u64,
     result: Result<(), DownloadChunkError>,
 }
 
@@ -77,13 +87,21 @@ pub(crate) fn start_ongoing_state_sync<T: Send + 'static>(
     tracker: Arc<Mutex<Box<dyn Chunkable<T> + Send>>>,
     artifact_id: StateSyncArtifactId,
     transport: Arc<dyn Transport>,
+    config: StateSyncConfig,
 ) -> OngoingStateSyncHandle {
     let (new_peers_tx, new_peers_rx) = tokio::sync::mpsc::channel(ONGOING_STATE_SYNC_CHANNEL_SIZE);
+    let now = Instant::now();
     let ongoing = OngoingStateSync {
         log,
         rt: rt.clone(),
         artifact_id: artifact_id.clone(),
         metrics,
+        throttle: config
+            .max_download_bandwidth
+            .map(|rate| BandwidthThrottle::new(rate, now)),
+        throttled_until: None,
+        download_rate: RateMeter::new(now),
+        config,
         transport,
         new_peers_rx,
         active_downloads: HashMap::new(),
@@ -119,10 +137,14 @@ impl OngoingStateSync {
                     if let Entry::Vacant(e) = self.active_downloads.entry(new_peer) {
                         info!(self.log, "Adding peer {} to ongoing state sync of height {}.", new_peer, self.artifact_id.height);
                         e.insert(0);
-                        self.allowed_downloads += PARALLEL_CHUNK_DOWNLOADS;
+                        self.allowed_downloads += self.config.parallel_chunk_downloads_per_peer;
                         self.spawn_chunk_downloads(cancellation.clone(), tracker.clone());
                     }
                 }
+                () = sleep_until(self.throttled_until.unwrap_or_else(Instant::now)), if self.throttled_until.is_some() => {
+                    self.throttled_until = None;
+                    self.spawn_chunk_downloads(cancellation.clone(), tracker.clone());
+                }
                 Some(download_result) = self.downloading_chunks.join_next() => {
                     match download_result {
                         Ok((result, _)) => {
@@ -149,13 +171,14 @@ impl OngoingStateSync {
             }
 
             debug_assert!(
-                self.active_downloads.len() * PARALLEL_CHUNK_DOWNLOADS == self.allowed_downloads
+                self.active_downloads.len() * self.config.parallel_chunk_downloads_per_peer
+                    == self.allowed_downloads
             );
 
             // Collect metrics
             self.metrics
                 .allowed_parallel_downloads
-                .set(self.allowed_downloads as i64);
+                .set(self.download_capacity() as i64);
             self.metrics
                 .peers_serving_state
                 .set(self.active_downloads.len() as i64);
@@ -169,19 +192,60 @@ impl OngoingStateSync {
             self.handle_downloaded_chunk_result(finished);
         }
         self.new_peers_rx.close();
+        self.metrics.download_rate.set(0);
+    }
+
+    /// Number of parallel downloads allowed by the peers serving the state and the
+    /// total limit.
+    fn download_capacity(&self) -> usize {
+        match self.config.max_parallel_chunk_downloads {
+            Some(max) => self.allowed_downloads.min(max),
+            None => self.allowed_downloads,
+        }
+    }
+
+    /// Returns true if the bandwidth is exhausted and schedules the resumption of downloads.
+    fn throttled(&mut self) -> bool {
+        let Some(throttle) = &mut self.throttle else {
+            return false;
+        };
+        match throttle.throttled_until(Instant::now()) {
+            Some(until) => {
+                if self.throttled_until.is_none() {
+                    self.metrics.download_throttled_total.inc();
+                }
+                self.throttled_until = Some(until);
+                true
+            }
+            None => {
+                self.throttled_until = None;
+                false
+            }
+        }
     }
 
     fn handle_downloaded_chunk_result(
         &mut self,
-        DownloadResult { peer_id, result }: DownloadResult,
+        DownloadResult {
+            peer_id,
+            bytes,
+            result,
+        }: DownloadResult,
     ) {
         self.metrics.record_chunk_download_result(&result);
+        let now = Instant::now();
+        if let Some(throttle) = &mut self.throttle {
+            throttle.consume(bytes, now);
+        }
+        if let Some(rate) = self.download_rate.record(bytes, now) {
+            self.metrics.download_rate.set(rate as i64);
+        }
         match result {
             // Received chunk
             Ok(()) => {}
             Err(DownloadChunkError::NoContent) => {
                 if self.active_downloads.remove(&peer_id).is_some() {
-                    self.allowed_downloads -= PARALLEL_CHUNK_DOWNLOADS;
+                    self.allowed_downloads -= self.config.parallel_chunk_downloads_per_peer;
                 }
             }
             Err(DownloadChunkError::RequestError { chunk_id, err }) => {
@@ -190,7 +254,7 @@ impl OngoingStateSync {
                     "Failed to download chunk {} from {}: {} ", chunk_id, peer_id, err
                 );
                 if self.active_downloads.remove(&peer_id).is_some() {
-                    self.allowed_downloads -= PARALLEL_CHUNK_DOWNLOADS;
+                    self.allowed_downloads -= self.config.parallel_chunk_downloads_per_peer;
                 }
             }
             Err(DownloadChunkError::Overloaded) => {}
@@ -205,10 +269,10 @@ impl OngoingStateSync {
         tracker: Arc<Mutex<Box<dyn Chunkable<T> + Send>>>,
     ) {
         let available_download_capacity = self
-            .allowed_downloads
+            .download_capacity()
             .saturating_sub(self.downloading_chunks.len());
 
-        if self.active_downloads.is_empty() {
+        if self.active_downloads.is_empty() || self.throttled() {
             return;
         }
 
@@ -285,6 +349,7 @@ impl OngoingStateSync {
             () = download_cancel_token.cancelled() => {
                 return DownloadResult {
                     peer_id,
+                    bytes: 0,
                     result: Err(DownloadChunkError::Cancelled)
                 }
             }
@@ -298,6 +363,7 @@ impl OngoingStateSync {
             Ok(Err(e)) => {
                 return DownloadResult {
                     peer_id,
+                    bytes: 0,
                     result: Err(DownloadChunkError::RequestError {
                         chunk_id,
                         err: e.to_string(),
@@ -307,10 +373,12 @@ impl OngoingStateSync {
             Err(_) => {
                 return DownloadResult {
                     peer_id,
+                    bytes: 0,
                     result: Err(DownloadChunkError::Timeout),
                 }
             }
         };
+        let bytes = response.body().len() as u64;
 
         let result = tokio::task::spawn_blocking(move || {
             let chunk = parse_chunk_handler_response(response, chunk_id, metrics)?;
@@ -329,7 +397,11 @@ impl OngoingStateSync {
         })
         .and_then(std::convert::identity);
 
-        DownloadResult { peer_id, result }
+        DownloadResult {
+            peer_id,
+            bytes,
+            result,
+        }
     }
 }
 
@@ -406,6 +478,7 @@ mod tests {
                     hash: CryptoHash(vec![]),
                 },
                 Arc::new(t),
+                StateSyncConfig::default(),
             );
 
             rt.block_on(async move {
@@ -444,6 +517,7 @@ mod tests {
                     hash: CryptoHash(vec![]),
                 },
                 Arc::new(t),
+                StateSyncConfig::default(),
             );
 
             rt.block_on(async move {
@@ -484,6 +558,7 @@ mod tests {
                     hash: CryptoHash(vec![]),
                 },
                 Arc::new(t),
+                StateSyncConfig::default(),
             );
 
             rt.block_on(async move {
@@ -0,0 +1,131 @@
+//! Bandwidth accounting for chunk downloads.
+//!
+//! The [`BandwidthThrottle`] is a token bucket that refills at the configured rate and
+//! holds at most one second worth of bytes. Downloaded chunks are charged after they
+//! arrive, so the bucket can go into debt. While it is in debt no new downloads are
+//! started, which keeps the average download rate at the configured limit.
+//!
+//! The [`RateMeter`] measures the download rate that is actually achieved.
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+pub(crate) struct BandwidthThrottle {
+    // Bytes per second.
+    rate: f64,
+    // Bytes that can still be downloaded. Negative while in debt.
+    budget: f64,
+    last_refill: Instant,
+}
+
+impl BandwidthThrottle {
+    pub fn new(rate: u64, now: Instant) -> Self {
+        let rate = rate.max(1) as f64;
+        Self {
+            rate,
+            budget: rate,
+            last_refill: now,
+        }
+    }
+
+    /// Charges downloaded bytes against the budget.
+    pub fn consume(&mut self, bytes: u64, now: Instant) {
+        self.refill(now);
+        self.budget -= bytes as f64;
+    }
+
+    /// Returns the instant at which downloads may resume, or `None` if the budget
+    /// is not exhausted.
+    pub fn throttled_until(&mut self, now: Instant) -> Option<Instant> {
+        self.refill(now);
+        if self.budget > 0.0 {
+            return None;
+        }
+        // Wait until at least one byte is available again.
+        let missing = 1.0 - self.budget;
+        Some(now + Duration::from_secs_f64(missing / self.rate))
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.budget = (self.budget + elapsed.as_secs_f64() * self.rate).min(self.rate);
+        self.last_refill = now;
+    }
+}
+
+pub(crate) struct RateMeter {
+    window_start: Instant,
+    bytes: u64,
+}
+
+impl RateMeter {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            window_start: now,
+            bytes: 0,
+        }
+    }
+
+    /// Records downloaded bytes. Returns the rate in bytes per second once a
+    /// measurement window has passed.
+    pub fn record(&mut self, bytes: u64, now: Instant) -> Option<u64> {
+        self.bytes += bytes;
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed < RATE_WINDOW {
+            return None;
+        }
+        let rate = (self.bytes as f64 / elapsed.as_secs_f64()) as u64;
+        self.window_start = now;
+        self.bytes = 0;
+        Some(rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttle_allows_one_second_burst() {
+        let now = Instant::now();
+        let mut throttle = BandwidthThrottle::new(1000, now);
+        assert_eq!(throttle.throttled_until(now), None);
+        throttle.consume(999, now);
+        assert_eq!(throttle.throttled_until(now), None);
+        throttle.consume(1, now);
+        assert!(throttle.throttled_until(now).is_some());
+    }
+
+    #[test]
+    fn throttle_resumes_after_debt_is_paid() {
+        let now = Instant::now();
+        let mut throttle = BandwidthThrottle::new(1000, now);
+        // Two seconds worth of bytes leave one second of debt.
+        throttle.consume(2000, now);
+        let until = throttle.throttled_until(now).unwrap();
+        assert!(until >= now + Duration::from_secs(1));
+        assert!(until <= now + Duration::from_millis(1010));
+        assert_eq!(throttle.throttled_until(until), None);
+    }
+
+    #[test]
+    fn throttle_does_not_accumulate_more_than_rate() {
+        let now = Instant::now();
+        let mut throttle = BandwidthThrottle::new(1000, now);
+        let later = now + Duration::from_secs(60);
+        throttle.consume(1000, later);
+        assert!(throttle.throttled_until(later).is_some());
+    }
+
+    #[test]
+    fn rate_meter_reports_after_window() {
+        let now = Instant::now();
+        let mut meter = RateMeter::new(now);
+        assert_eq!(meter.record(500, now + Duration::from_millis(500)), None);
+        assert_eq!(meter.record(1500, now + Duration::from_secs(2)), Some(1000));
+        // The next window starts empty.
+        assert_eq!(meter.record(0, now + Duration::from_secs(3)), Some(0));
+    }
+}
@@ -14,8 +14,11 @@ use ic_interfaces::p2p::state_sync::{
 use ic_logger::ReplicaLogger;
 use ic_memory_transport::TransportRouter;
 use ic_metrics::MetricsRegistry;
-use ic_p2p_test_utils::mocks::{MockChunkable, MockStateSync};
-use ic_quic_transport::Shutdown;
+use ic_p2p_test_utils::{
+    mocks::{MockChunkable, MockStateSync},
+    partition::NetworkPartitioner,
+};
+use ic_quic_transport::{Shutdown, Transport};
 use ic_state_manager::state_sync::types::StateSyncMessage;
 use ic_types::{crypto::CryptoHash, Height, NodeId, PrincipalId};
 use tokio::runtime::Handle;
@@ -418,25 +421,50 @@ pub fn create_node(
         disconnected: Arc::new(AtomicBool::new(false)),
     });
 
+    let shutdown = start_node(
+        node_num,
+        log,
+        transport_router,
+        rt,
+        state_sync.clone(),
+        link,
+        None,
+    );
+
+    (state_sync, shutdown)
+}
+
+/// Starts a state sync manager with the given state sync client on a new node
+/// of the memory transport. If a partitioner is given, the node can only reach
+/// the nodes on its side of the partition.
+pub fn start_node(
+    node_num: u64,
+    log: ReplicaLogger,
+    transport_router: &mut TransportRouter,
+    rt: &Handle,
+    state_sync: Arc<dyn StateSyncClient<Message = StateSyncMessage>>,
+    link: (Duration, usize),
+    partitioner: Option<&NetworkPartitioner>,
+) -> Shutdown {
     let (router, rx) = ic_state_sync_manager::build_axum_router(
         state_sync.clone(),
         log.clone(),
         &MetricsRegistry::default(),
     );
-    let transport = transport_router.add_peer(
-        NodeId::from(PrincipalId::new_node_test_id(node_num)),
-        router,
-        link.0,
-        link.1,
-    );
-    let shutdown = ic_state_sync_manager::start_state_sync_manager(
+    let node_id = NodeId::from(PrincipalId::new_node_test_id(node_num));
+    let transport: Arc<dyn Transport> =
+        Arc::new(transport_router.add_peer(node_id, router, link.0, link.1));
+    let transport = match partitioner {
+        Some(partitioner) => partitioner.transport(node_id, transport),
+        None => transport,
+    };
+    ic_state_sync_manager::start_state_sync_manager(
         &log,
         &MetricsRegistry::default(),
         rt,
-        Arc::new(transport),
-        state_sync.clone(),
+        transport,
+        state_sync,
         rx,
-    );
-
-    (state_sync, shutdown)
+        ic_state_sync_manager::StateSyncConfig::default(),
+    )
 }
@@ -7,7 +7,7 @@ use std::{
 };
 
 use crate::common::{
-    create_node, latency_30ms_throughput_1000mbits, latency_50ms_throughput_300mbits,
+    create_node, latency_30ms_throughput_1000mbits, latency_50ms_throughput_300mbits, start_node,
     SharableMockChunkable, State,
 };
 use common::SharableMockStateSync;
@@ -16,6 +16,8 @@ use ic_logger::info;
 use ic_memory_transport::TransportRouter;
 use ic_p2p_test_utils::{
     mocks::MockStateSync,
+    partition::NetworkPartitioner,
+    state_sync::{FakeStateSync, SyntheticState},
     turmoil::{
         add_peer_manager_to_sim, add_transport_to_sim, wait_for, wait_for_timeout, waiter_fut,
         PeerManagerAction,
@@ -24,7 +26,7 @@ use ic_p2p_test_utils::{
 };
 use ic_test_utilities_logger::with_test_replica_logger;
 use ic_types::{crypto::CryptoHash, Height, RegistryVersion};
-use ic_types_test_utils::ids::{NODE_1, NODE_2, NODE_3};
+use ic_types_test_utils::ids::{node_test_id, NODE_1, NODE_2, NODE_3};
 use tokio::sync::Notify;
 use turmoil::Builder;
 
@@ -308,6 +310,113 @@ fn test_full_subnet_syncing_from_one_node() {
     });
 }
 
+/// Test a node syncing a state with chunks of different sizes from two nodes
+/// that are slow to serve chunks.
+#[test]
+fn test_sync_from_slow_nodes() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let rt_handle = runtime.handle().clone();
+    with_test_replica_logger(|log| {
+        runtime.block_on(async move {
+            let mut transport_router = TransportRouter::new();
+            let chunk_sizes = (1..=200).map(|i| i * 1_000).collect();
+            let state = SyntheticState::with_chunk_sizes(Height::from(10), chunk_sizes);
+
+            let mut join_handles = Vec::new();
+            for i in 0..2 {
+                let state_sync =
+                    FakeStateSync::new(state.clone()).with_chunk_delay(Duration::from_millis(20));
+                join_handles.push(start_node(
+                    i,
+                    log.clone(),
+                    &mut transport_router,
+                    &rt_handle,
+                    Arc::new(state_sync),
+                    latency_30ms_throughput_1000mbits(),
+                    None,
+                ));
+            }
+
+            let state_sync_empty = FakeStateSync::empty();
+            let _join_handle_empty = start_node(
+                2,
+                log,
+                &mut transport_router,
+                &rt_handle,
+                Arc::new(state_sync_empty.clone()),
+                latency_50ms_throughput_300mbits(),
+                None,
+            );
+
+            // Verify that the empty node has caught up.
+            let fut = async move {
+                while state_sync_empty.state().as_ref() != Some(&state) {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            };
+            tokio::time::timeout(TEST_STATE_SYNC_TIMEOUT, fut)
+                .await
+                .unwrap();
+        });
+    });
+}
+
+/// Test that a node cut off from the subnet does not sync the state until the
+/// partition is healed.
+#[test]
+fn test_sync_after_partition_is_healed() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let rt_handle = runtime.handle().clone();
+    with_test_replica_logger(|log| {
+        runtime.block_on(async move {
+            let mut transport_router = TransportRouter::new();
+            let node_ids: Vec<_> = (0..3).map(node_test_id).collect();
+            let mut partitioner =
+                NetworkPartitioner::with_placeholder_addresses(node_ids.iter().copied());
+            partitioner.isolate(node_ids[2]);
+            let state = SyntheticState::new(Height::from(10), 50, 100_000);
+
+            let mut join_handles = Vec::new();
+            for i in 0..2 {
+                join_handles.push(start_node(
+                    i,
+                    log.clone(),
+                    &mut transport_router,
+                    &rt_handle,
+                    Arc::new(FakeStateSync::new(state.clone())),
+                    latency_30ms_throughput_1000mbits(),
+                    Some(&partitioner),
+                ));
+            }
+
+            let state_sync_isolated = FakeStateSync::empty();
+            let _join_handle_isolated = start_node(
+                2,
+                log,
+                &mut transport_router,
+                &rt_handle,
+                Arc::new(state_sync_isolated.clone()),
+                latency_50ms_throughput_300mbits(),
+                Some(&partitioner),
+            );
+
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            assert_eq!(state_sync_isolated.state(), None);
+
+            // Verify that the isolated node catches up once it is reachable.
+            partitioner.heal();
+            let fut = async move {
+                while state_sync_isolated.state().as_ref() != Some(&state) {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            };
+            tokio::time::timeout(TEST_STATE_SYNC_TIMEOUT, fut)
+                .await
+                .unwrap();
+        });
+    });
+}
+
 /// Test state sync advert ping pong between two nodes over quic transport.
 #[test]
 fn test_single_advert_between_two_nodes() {
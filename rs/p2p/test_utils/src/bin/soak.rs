@@ -0,0 +1,457 @@
+//! Soak test of the consensus manager over the QUIC transport.
+//!
+//! Starts a subnet of nodes on localhost. Every node runs a consensus manager
+//! with a [`SyntheticArtifact`] client on top of a real QUIC transport and
+//! broadcasts artifacts at a fixed rate. The producer of an artifact purges it
+//! once all other nodes received it.
+//!
+//! The following delivery invariants are asserted, and the process aborts if
+//! one of them is violated:
+//!  - Artifacts are only delivered by the node that produced them.
+//!  - Every artifact reaches all other nodes within the delivery timeout.
+//!    This is checked periodically, so the soak test can run indefinitely.
+//!
+//! If a metrics address is given, the metrics of the soak test and of the
+//! consensus managers of all nodes, labeled by node, are served on `/metrics`.
+//!
+//! ```text
+//! bazel run //rs/p2p/test_utils:p2p-soak -- --nodes 7 --rate 20 --duration-secs 3600
+//! ```
+use std::{
+    backtrace::Backtrace,
+    collections::{btree_map::Entry, BTreeMap, HashMap, HashSet},
+    net::SocketAddr,
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+
+use axum::{routing::get, Router};
+use clap::Parser;
+use ic_consensus_manager::{ConsensusManagerBuilder, ConsensusManagerConfig};
+use ic_interfaces::p2p::{
+    artifact_manager::ArtifactProcessorEvent,
+    consensus::{ArtifactWithOpt, Priority},
+};
+use ic_logger::replica_logger::no_op_logger;
+use ic_metrics::{buckets::decimal_buckets, MetricsRegistry};
+use ic_p2p_test_utils::{
+    consensus::{FakeValidatedPool, SwitchablePriorityFnFactory},
+    fully_connected_localhost_subnet,
+    synthetic_artifact::{IdPattern, SyntheticArtifact, SyntheticArtifactGenerator},
+};
+use ic_quic_transport::Shutdown;
+use ic_types::{
+    artifact::{IdentifiableArtifact, UnvalidatedArtifactMutation},
+    NodeId,
+};
+use ic_types_test_utils::ids::node_test_id;
+use prometheus::{
+    proto::{LabelPair, MetricFamily},
+    Encoder, Histogram, IntCounter, IntGauge, TextEncoder,
+};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver},
+    task::JoinSet,
+    time::{interval, Instant, MissedTickBehavior},
+};
+
+const OUTBOUND_CHANNEL_CAPACITY: usize = 1_000;
+const NODE_LABEL: &str = "node";
+
+/// Soak test of the consensus manager over the QUIC transport on localhost.
+#[derive(Parser)]
+struct Args {
+    /// Number of nodes in the subnet.
+    #[clap(long, default_value_t = 4)]
+    nodes: u64,
+    /// Number of artifacts every node broadcasts per second.
+    #[clap(long, default_value_t = 10.0)]
+    rate: f64,
+    /// Smallest payload size of the artifacts, in bytes.
+    #[clap(long, default_value_t = 1_024)]
+    min_payload_size: usize,
+    /// Largest payload size of the artifacts, in bytes.
+    #[clap(long, default_value_t = 100_000)]
+    max_payload_size: usize,
+    /// For how long the nodes broadcast artifacts, in seconds. If not set,
+    /// the soak test runs until it is interrupted.
+    #[clap(long)]
+    duration_secs: Option<u64>,
+    /// Interval between two checks of the delivery invariants, in seconds.
+    #[clap(long, default_value_t = 10)]
+    check_interval_secs: u64,
+    /// Time within which every artifact must reach all nodes, in seconds.
+    #[clap(long, default_value_t = 60)]
+    delivery_timeout_secs: u64,
+    /// Address to serve the metrics on.
+    #[clap(long)]
+    metrics_addr: Option<SocketAddr>,
+    /// The nodes listen on 127.1.<subnet>.<node>, so concurrent soak tests
+    /// need different values. Must not be zero.
+    #[clap(long, default_value_t = 200)]
+    subnet: u8,
+}
+
+struct SoakMetrics {
+    sent: IntCounter,
+    delivered: IntCounter,
+    duplicates: IntCounter,
+    pending: IntGauge,
+    delivery_latency: Histogram,
+}
+
+impl SoakMetrics {
+    fn new(metrics_registry: &MetricsRegistry) -> Self {
+        Self {
+            sent: metrics_registry.int_counter(
+                "p2p_soak_artifacts_sent_total",
+                "Artifacts broadcast by all nodes.",
+            ),
+            delivered: metrics_registry.int_counter(
+                "p2p_soak_artifacts_delivered_total",
+                "Artifacts delivered to a node, counted once per receiving node.",
+            ),
+            duplicates: metrics_registry.int_counter(
+                "p2p_soak_artifacts_duplicates_total",
+                "Artifacts delivered to a node that already received them.",
+            ),
+            pending: metrics_registry.int_gauge(
+                "p2p_soak_artifacts_pending",
+                "Artifacts that did not reach all nodes yet.",
+            ),
+            delivery_latency: metrics_registry.histogram(
+                "p2p_soak_delivery_latency_seconds",
+                "Time from the broadcast of an artifact until a node received it.",
+                decimal_buckets(-3, 1),
+            ),
+        }
+    }
+}
+
+/// An artifact that did not reach all nodes yet.
+struct Pending {
+    producer: NodeId,
+    sent_at: Instant,
+    missing: HashSet<NodeId>,
+}
+
+/// Keeps track of the artifacts that did not reach all nodes yet.
+#[derive(Clone)]
+struct Tracker {
+    nodes: Vec<NodeId>,
+    pending: Arc<Mutex<HashMap<u64, Pending>>>,
+    metrics: Arc<SoakMetrics>,
+}
+
+impl Tracker {
+    fn sent(&self, producer: NodeId, id: u64) {
+        let missing = self
+            .nodes
+            .iter()
+            .copied()
+            .filter(|node_id| *node_id != producer)
+            .collect();
+        let mut pending = self.pending.lock().unwrap();
+        pending.insert(
+            id,
+            Pending {
+                producer,
+                sent_at: Instant::now(),
+                missing,
+            },
+        );
+        self.metrics.sent.inc();
+        self.metrics.pending.set(pending.len() as i64);
+    }
+
+    /// Records that `receiver` got the artifact from `peer_id`. Returns the
+    /// producer of the artifact if it reached all nodes now.
+    fn delivered(&self, receiver: NodeId, peer_id: NodeId, id: u64) -> Option<NodeId> {
+        let mut pending = self.pending.lock().unwrap();
+        let Some(artifact) = pending.get_mut(&id) else {
+            // The artifact already reached all nodes.
+            self.metrics.duplicates.inc();
+            return None;
+        };
+        assert_eq!(
+            peer_id, artifact.producer,
+            "Artifact {id} was delivered to {receiver} by {peer_id} instead of its producer."
+        );
+        if !artifact.missing.remove(&receiver) {
+            self.metrics.duplicates.inc();
+            return None;
+        }
+        self.metrics.delivered.inc();
+        self.metrics
+            .delivery_latency
+            .observe(artifact.sent_at.elapsed().as_secs_f64());
+        if !artifact.missing.is_empty() {
+            return None;
+        }
+        let producer = artifact.producer;
+        pending.remove(&id);
+        self.metrics.pending.set(pending.len() as i64);
+        Some(producer)
+    }
+
+    /// Panics if an artifact did not reach all nodes within the timeout.
+    fn check(&self, delivery_timeout: Duration) {
+        let pending = self.pending.lock().unwrap();
+        if let Some((id, artifact)) = pending
+            .iter()
+            .find(|(_, artifact)| artifact.sent_at.elapsed() > delivery_timeout)
+        {
+            panic!(
+                "Artifact {id} of {} did not reach {:?} within {delivery_timeout:?}.",
+                artifact.producer, artifact.missing
+            );
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pending.lock().unwrap().is_empty()
+    }
+}
+
+#[derive(Clone)]
+struct Node {
+    pool: FakeValidatedPool<SyntheticArtifact>,
+    outbound_tx: mpsc::Sender<ArtifactProcessorEvent<SyntheticArtifact>>,
+    metrics_registry: MetricsRegistry,
+}
+
+/// Broadcasts the generated artifacts from `node_id` until `end`.
+async fn produce(
+    node_id: NodeId,
+    node: Node,
+    generator: SyntheticArtifactGenerator,
+    period: Duration,
+    end: Option<Instant>,
+    tracker: Tracker,
+) {
+    let mut ticker = interval(period);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    for artifact in generator {
+        let now = ticker.tick().await;
+        if end.is_some_and(|end| now >= end) {
+            break;
+        }
+        tracker.sent(node_id, artifact.id());
+        node.pool.insert(artifact.clone());
+        let event = ArtifactProcessorEvent::Artifact(ArtifactWithOpt {
+            artifact,
+            is_latency_sensitive: false,
+        });
+        if node.outbound_tx.send(event).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Records the artifacts delivered to `node_id`, and purges artifacts at their
+/// producer once they reached all nodes.
+async fn consume(
+    node_id: NodeId,
+    mut inbound_rx: UnboundedReceiver<UnvalidatedArtifactMutation<SyntheticArtifact>>,
+    nodes: Arc<BTreeMap<NodeId, Node>>,
+    tracker: Tracker,
+) {
+    while let Some(mutation) = inbound_rx.recv().await {
+        let UnvalidatedArtifactMutation::Insert((artifact, peer_id)) = mutation else {
+            continue;
+        };
+        let id = artifact.id();
+        if let Some(producer) = tracker.delivered(node_id, peer_id, id) {
+            let producer = &nodes[&producer];
+            producer.pool.remove(&id);
+            let _ = producer
+                .outbound_tx
+                .send(ArtifactProcessorEvent::Purge(id))
+                .await;
+        }
+    }
+}
+
+/// Encodes the metrics of the soak test and of all nodes in the text format.
+/// The metrics of the nodes are labeled with the node id.
+fn encode_metrics(soak_registry: &MetricsRegistry, nodes: &BTreeMap<NodeId, Node>) -> String {
+    let mut families: BTreeMap<String, MetricFamily> = soak_registry
+        .prometheus_registry()
+        .gather()
+        .into_iter()
+        .map(|family| (family.get_name().to_string(), family))
+        .collect();
+    for (node_id, node) in nodes {
+        for mut family in node.metrics_registry.prometheus_registry().gather() {
+            for metric in family.mut_metric().iter_mut() {
+                let mut label = LabelPair::new();
+                label.set_name(NODE_LABEL.to_string());
+                label.set_value(node_id.to_string());
+                metric.mut_label().push(label);
+            }
+            match families.entry(family.get_name().to_string()) {
+                Entry::Vacant(entry) => {
+                    entry.insert(family);
+                }
+                Entry::Occupied(mut entry) => {
+                    entry.get_mut().mut_metric().extend(family.take_metric());
+                }
+            }
+        }
+    }
+    let families: Vec<_> = families.into_values().collect();
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&families, &mut buffer)
+        .expect("Failed to encode metrics.");
+    String::from_utf8(buffer).expect("Metrics are valid UTF-8.")
+}
+
+fn main() {
+    let args = Args::parse();
+    assert!(args.nodes > 1, "The soak test needs at least two nodes.");
+    assert!(args.rate > 0.0, "The rate must be positive.");
+    assert!(
+        args.min_payload_size <= args.max_payload_size,
+        "The smallest payload size must not exceed the largest."
+    );
+    // Abort if any thread panics, e.g. because an invariant is violated in a
+    // detached task.
+    std::panic::set_hook(Box::new(|info| {
+        let stacktrace = Backtrace::force_capture();
+        println!("Got panic. @info:{}\n@stackTrace:{}", info, stacktrace);
+        std::process::abort();
+    }));
+
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let log = no_op_logger();
+    let soak_registry = MetricsRegistry::new();
+    let node_ids: Vec<_> = (0..args.nodes).map(node_test_id).collect();
+    let tracker = Tracker {
+        nodes: node_ids.clone(),
+        pending: Arc::new(Mutex::new(HashMap::new())),
+        metrics: Arc::new(SoakMetrics::new(&soak_registry)),
+    };
+
+    let mut nodes = BTreeMap::new();
+    let mut routers = Vec::new();
+    let mut builders = Vec::new();
+    let mut inbound_rxs = Vec::new();
+    for node_id in &node_ids {
+        let metrics_registry = MetricsRegistry::new();
+        let pool = FakeValidatedPool::new();
+        let (outbound_tx, outbound_rx) = mpsc::channel(OUTBOUND_CHANNEL_CAPACITY);
+        let (inbound_tx, inbound_rx) = mpsc::unbounded_channel();
+        let (priority_fn_factory, _) = SwitchablePriorityFnFactory::new(Priority::FetchNow);
+        let mut cm = ConsensusManagerBuilder::new(
+            log.clone(),
+            rt.handle().clone(),
+            metrics_registry.clone(),
+            ConsensusManagerConfig::default(),
+        );
+        cm.add_client(
+            outbound_rx,
+            Arc::new(RwLock::new(pool.clone())),
+            Arc::new(priority_fn_factory),
+            inbound_tx,
+        );
+        routers.push((*node_id, cm.router()));
+        builders.push(cm);
+        inbound_rxs.push((*node_id, inbound_rx));
+        nodes.insert(
+            *node_id,
+            Node {
+                pool,
+                outbound_tx,
+                metrics_registry,
+            },
+        );
+    }
+    let nodes = Arc::new(nodes);
+
+    let (transports, topology_watcher) =
+        fully_connected_localhost_subnet(rt.handle(), log, args.subnet, routers);
+    let shutdowns: Vec<_> = transports
+        .into_iter()
+        .zip(builders)
+        .flat_map(|((_, transport), cm)| cm.run(transport, topology_watcher.clone()))
+        .collect();
+
+    rt.block_on(async move {
+        if let Some(metrics_addr) = args.metrics_addr {
+            let nodes = nodes.clone();
+            let app = Router::new().route(
+                "/metrics",
+                get(move || std::future::ready(encode_metrics(&soak_registry, &nodes))),
+            );
+            let listener = tokio::net::TcpListener::bind(metrics_addr)
+                .await
+                .expect("Failed to bind the metrics address.");
+            tokio::spawn(async move { axum::serve(listener, app).await });
+        }
+
+        let mut consumers = JoinSet::new();
+        for (node_id, inbound_rx) in inbound_rxs {
+            consumers.spawn(consume(node_id, inbound_rx, nodes.clone(), tracker.clone()));
+        }
+
+        let start = Instant::now();
+        let end = args
+            .duration_secs
+            .map(|duration| start + Duration::from_secs(duration));
+        let period = Duration::from_secs_f64(1.0 / args.rate);
+        let mut producers = JoinSet::new();
+        for (i, (node_id, node)) in nodes.iter().enumerate() {
+            let generator = SyntheticArtifactGenerator::new(i as u64)
+                .with_payload_sizes(args.min_payload_size..=args.max_payload_size)
+                // Every node gets its own id range, so ids never collide.
+                .with_ids(IdPattern::Sequential {
+                    start: (i as u64) << 32,
+                });
+            producers.spawn(produce(
+                *node_id,
+                node.clone(),
+                generator,
+                period,
+                end,
+                tracker.clone(),
+            ));
+        }
+
+        let delivery_timeout = Duration::from_secs(args.delivery_timeout_secs);
+        let mut check = interval(Duration::from_secs(args.check_interval_secs));
+        check.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = check.tick() => {
+                    tracker.check(delivery_timeout);
+                    let metrics = &tracker.metrics;
+                    println!(
+                        "{:?}: sent {}, delivered {}, duplicates {}, pending {}",
+                        start.elapsed(),
+                        metrics.sent.get(),
+                        metrics.delivered.get(),
+                        metrics.duplicates.get(),
+                        metrics.pending.get(),
+                    );
+                    if producers.is_empty() && tracker.is_empty() {
+                        break;
+                    }
+                }
+                Some(produced) = producers.join_next() => {
+                    produced.expect("Producer panicked.");
+                }
+            }
+        }
+
+        Shutdown::join_all(shutdowns).shutdown().await;
+        consumers.shutdown().await;
+        println!(
+            "All {} artifacts reached all nodes.",
+            tracker.metrics.sent.get()
+        );
+    });
+}
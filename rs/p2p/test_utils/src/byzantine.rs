@@ -0,0 +1,280 @@
+//! A peer that speaks the slot protocol of the consensus manager but
+//! misbehaves on demand.
+//!
+//! [`ByzantinePeer`] sends slot updates through its transport and serves
+//! artifact RPCs with its router, like the consensus manager does. Unlike the
+//! consensus manager it lets tests choose the commit ids and slots of the
+//! updates freely, advertise ids it never serves and serve corrupted
+//! artifacts, so that the hardening of the receiving side can be tested.
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::anyhow;
+use axum::{
+    extract::{DefaultBodyLimit, State},
+    http::{Request, StatusCode},
+    routing::any,
+    Router,
+};
+use bytes::Bytes;
+use ic_protobuf::{p2p::v1 as pb, proxy::ProtoProxy};
+use ic_quic_transport::Transport;
+use ic_types::{artifact::PbArtifact, NodeId};
+use prost::Message;
+
+/// How the peer answers artifact RPCs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ServeBehavior {
+    /// Serves the artifacts added with [`ByzantinePeer::serve`].
+    #[default]
+    Honest,
+    /// Answers every RPC with `NO_CONTENT`, as if the artifact was not in the
+    /// pool, even if it was advertised.
+    Withhold,
+    /// Serves the artifacts with every byte of their encoding inverted.
+    Corrupt,
+}
+
+struct PeerState<A: PbArtifact> {
+    artifacts: HashMap<A::Id, A>,
+    behavior: ServeBehavior,
+    // The commit id of the last update sent with an automatic commit id.
+    commit_id: u64,
+    served_rpcs: u64,
+    received_updates: u64,
+}
+
+/// Sends and serves the slot protocol of artifact type `A` with configurable
+/// misbehavior.
+pub struct ByzantinePeer<A: PbArtifact> {
+    transport: Option<Arc<dyn Transport>>,
+    state: Arc<Mutex<PeerState<A>>>,
+}
+
+impl<A: PbArtifact + Clone> ByzantinePeer<A> {
+    /// Creates a peer without a transport. Like for the consensus manager,
+    /// the transport is usually created with the peer's router, so it is set
+    /// with [`ByzantinePeer::set_transport`] afterwards.
+    pub fn new() -> Self {
+        Self {
+            transport: None,
+            state: Arc::new(Mutex::new(PeerState {
+                artifacts: HashMap::new(),
+                behavior: ServeBehavior::default(),
+                commit_id: 0,
+                served_rpcs: 0,
+                received_updates: 0,
+            })),
+        }
+    }
+
+    /// Returns the router that serves the artifact RPCs and accepts, but
+    /// ignores, the slot updates of other peers.
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route(
+                &format!("/{}/rpc", uri_prefix::<A>()),
+                any(rpc_handler::<A>),
+            )
+            .route(
+                &format!("/{}/update", uri_prefix::<A>()),
+                any(update_handler::<A>),
+            )
+            .with_state(self.state.clone())
+            .layer(DefaultBodyLimit::disable())
+    }
+
+    /// Sets the transport through which the updates are sent.
+    pub fn set_transport(&mut self, transport: Arc<dyn Transport>) {
+        self.transport = Some(transport);
+    }
+
+    pub fn set_serve_behavior(&self, behavior: ServeBehavior) {
+        self.state.lock().unwrap().behavior = behavior;
+    }
+
+    /// Makes the artifact available for RPCs.
+    pub fn serve(&self, artifact: A) {
+        self.state
+            .lock()
+            .unwrap()
+            .artifacts
+            .insert(artifact.id(), artifact);
+    }
+
+    /// Makes the artifact with the given id unavailable for RPCs.
+    pub fn stop_serving(&self, id: &A::Id) {
+        self.state.lock().unwrap().artifacts.remove(id);
+    }
+
+    /// Returns the number of artifact RPCs answered so far, whatever the
+    /// answer was.
+    pub fn served_rpcs(&self) -> u64 {
+        self.state.lock().unwrap().served_rpcs
+    }
+
+    /// Returns the number of slot updates received from other peers.
+    pub fn received_updates(&self) -> u64 {
+        self.state.lock().unwrap().received_updates
+    }
+
+    /// Advertises the artifact in the slot with the next commit id and serves
+    /// it, like an honest peer does.
+    pub async fn advertise_artifact(
+        &self,
+        peer_id: &NodeId,
+        slot: u64,
+        artifact: A,
+    ) -> Result<(), anyhow::Error> {
+        let (id, attribute) = (artifact.id(), artifact.attribute());
+        self.serve(artifact);
+        self.advertise(peer_id, slot, id, attribute).await
+    }
+
+    /// Advertises the id in the slot with the next commit id. The artifact is
+    /// only served if it was added with [`ByzantinePeer::serve`], so this can
+    /// advertise ids the peer never serves.
+    pub async fn advertise(
+        &self,
+        peer_id: &NodeId,
+        slot: u64,
+        id: A::Id,
+        attribute: A::Attribute,
+    ) -> Result<(), anyhow::Error> {
+        let commit_id = self.next_commit_id();
+        self.send_advert(peer_id, commit_id, slot, id, attribute)
+            .await
+    }
+
+    /// Pushes the artifact in the slot with the next commit id.
+    pub async fn push_artifact(
+        &self,
+        peer_id: &NodeId,
+        slot: u64,
+        artifact: A,
+    ) -> Result<(), anyhow::Error> {
+        let commit_id = self.next_commit_id();
+        self.send_artifact(peer_id, commit_id, slot, artifact).await
+    }
+
+    /// Sends an advert with the given commit id, which does not need to be
+    /// larger than the commit ids sent before. Does not change the next
+    /// automatic commit id.
+    pub async fn send_advert(
+        &self,
+        peer_id: &NodeId,
+        commit_id: u64,
+        slot: u64,
+        id: A::Id,
+        attribute: A::Attribute,
+    ) -> Result<(), anyhow::Error> {
+        let update = pb::slot_update::Update::Advert(pb::Advert {
+            id: A::PbId::from(id).encode_to_vec(),
+            attribute: A::PbAttribute::from(attribute).encode_to_vec(),
+            attribute_digest: Vec::new(),
+        });
+        self.send_update(peer_id, commit_id, slot, Some(update))
+            .await
+    }
+
+    /// Pushes the artifact with the given commit id, which does not need to be
+    /// larger than the commit ids sent before. Does not change the next
+    /// automatic commit id.
+    pub async fn send_artifact(
+        &self,
+        peer_id: &NodeId,
+        commit_id: u64,
+        slot: u64,
+        artifact: A,
+    ) -> Result<(), anyhow::Error> {
+        let update = pb::slot_update::Update::Artifact(A::PbMessage::proxy_encode(artifact));
+        self.send_update(peer_id, commit_id, slot, Some(update))
+            .await
+    }
+
+    /// Sends a slot update without any content, which receivers must reject.
+    pub async fn send_empty_update(
+        &self,
+        peer_id: &NodeId,
+        commit_id: u64,
+        slot: u64,
+    ) -> Result<(), anyhow::Error> {
+        self.send_update(peer_id, commit_id, slot, None).await
+    }
+
+    /// Sends arbitrary bytes as a slot update.
+    pub async fn send_raw_update(
+        &self,
+        peer_id: &NodeId,
+        body: Bytes,
+    ) -> Result<(), anyhow::Error> {
+        let request = Request::builder()
+            .uri(format!("/{}/update", uri_prefix::<A>()))
+            .body(body)?;
+        self.transport
+            .as_ref()
+            .ok_or_else(|| anyhow!("The byzantine peer has no transport"))?
+            .push(peer_id, request)
+            .await
+    }
+
+    async fn send_update(
+        &self,
+        peer_id: &NodeId,
+        commit_id: u64,
+        slot: u64,
+        update: Option<pb::slot_update::Update>,
+    ) -> Result<(), anyhow::Error> {
+        let slot_update = pb::SlotUpdate {
+            commit_id,
+            slot_id: slot,
+            update,
+        };
+        self.send_raw_update(peer_id, Bytes::from(slot_update.encode_to_vec()))
+            .await
+    }
+
+    fn next_commit_id(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        state.commit_id += 1;
+        state.commit_id
+    }
+}
+
+impl<A: PbArtifact + Clone> Default for ByzantinePeer<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn uri_prefix<A: PbArtifact>() -> String {
+    A::NAME.to_lowercase()
+}
+
+async fn rpc_handler<A: PbArtifact + Clone>(
+    State(state): State<Arc<Mutex<PeerState<A>>>>,
+    payload: Bytes,
+) -> Result<Bytes, StatusCode> {
+    let id: A::Id = A::PbId::proxy_decode(&payload).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let mut state = state.lock().unwrap();
+    state.served_rpcs += 1;
+    if state.behavior == ServeBehavior::Withhold {
+        return Err(StatusCode::NO_CONTENT);
+    }
+    let artifact = state
+        .artifacts
+        .get(&id)
+        .cloned()
+        .ok_or(StatusCode::NO_CONTENT)?;
+    let mut bytes = A::PbMessage::proxy_encode(artifact);
+    if state.behavior == ServeBehavior::Corrupt {
+        bytes.iter_mut().for_each(|byte| *byte = !*byte);
+    }
+    Ok(Bytes::from(bytes))
+}
+
+async fn update_handler<A: PbArtifact>(State(state): State<Arc<Mutex<PeerState<A>>>>) {
+    state.lock().unwrap().received_updates += 1;
+}
@@ -1,8 +1,11 @@
 use std::collections::HashMap;
-use std::convert::Infallible;
 use std::{
     collections::{HashSet, VecDeque},
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::Duration,
 };
 
 use ic_interfaces::p2p::consensus::{
@@ -10,23 +13,13 @@ use ic_interfaces::p2p::consensus::{
     UnvalidatedArtifact, ValidatedPoolReader,
 };
 use ic_logger::ReplicaLogger;
-use ic_types::artifact::{IdentifiableArtifact, PbArtifact};
+use ic_types::artifact::IdentifiableArtifact;
 use ic_types::NodeId;
 use serde::{Deserialize, Serialize};
 
 #[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
 pub struct U64Artifact(Vec<u8>);
 
-impl IdentifiableArtifact for U64Artifact {
-    const NAME: &'static str = "artifact";
-    type Id = u64;
-    type Attribute = ();
-    fn id(&self) -> Self::Id {
-        u64::from_le_bytes(self.0[..8].try_into().unwrap())
-    }
-    fn attribute(&self) -> Self::Attribute {}
-}
-
 impl From<U64Artifact> for Vec<u8> {
     fn from(value: U64Artifact) -> Self {
         value.0
@@ -38,14 +31,12 @@ impl From<Vec<u8>> for U64Artifact {
     }
 }
 
-impl PbArtifact for U64Artifact {
-    type PbMessage = Vec<u8>;
-    type PbIdError = Infallible;
-    type PbMessageError = Infallible;
-    type PbAttributeError = Infallible;
-    type PbId = u64;
-    type PbAttribute = ();
-}
+ic_types::impl_pb_artifact!(U64Artifact {
+    name: "artifact",
+    id: u64 = |artifact| u64::from_le_bytes(artifact.0[..8].try_into().unwrap()),
+    pb_id: u64,
+    pb_message: Vec<u8>,
+});
 
 impl U64Artifact {
     pub fn id_to_msg(id: u64, msg_size: usize) -> Self {
@@ -297,3 +288,183 @@ impl PriorityFnFactory<U64Artifact, TestConsensus<U64Artifact>> for TestConsensu
         Box::new(|_, _| Priority::FetchNow)
     }
 }
+
+/// Priority function factory whose priority can be switched while it is in
+/// use, e.g. to stash or drop adverts whose artifacts are being downloaded.
+///
+/// All returned priority functions assign the current priority of the factory
+/// to every advert. The consensus manager picks up a switch when it
+/// re-evaluates the priorities of its adverts, which happens at the latest
+/// when it fetches a new priority function.
+#[derive(Clone)]
+pub struct SwitchablePriorityFnFactory {
+    priority: Arc<RwLock<Priority>>,
+}
+
+impl SwitchablePriorityFnFactory {
+    /// Creates a factory with the given initial priority and a handle to
+    /// switch it.
+    pub fn new(priority: Priority) -> (Self, PriorityHandle) {
+        let priority = Arc::new(RwLock::new(priority));
+        (
+            Self {
+                priority: Arc::clone(&priority),
+            },
+            PriorityHandle { priority },
+        )
+    }
+}
+
+impl<A: IdentifiableArtifact, Pool> PriorityFnFactory<A, Pool> for SwitchablePriorityFnFactory {
+    fn get_priority_function(
+        &self,
+        _pool: &Pool,
+    ) -> ic_interfaces::p2p::consensus::PriorityFn<A::Id, A::Attribute> {
+        let priority = Arc::clone(&self.priority);
+        Box::new(move |_, _| *priority.read().unwrap())
+    }
+}
+
+/// Switches the priority of a [`SwitchablePriorityFnFactory`].
+#[derive(Clone)]
+pub struct PriorityHandle {
+    priority: Arc<RwLock<Priority>>,
+}
+
+impl PriorityHandle {
+    pub fn set(&self, priority: Priority) {
+        *self.priority.write().unwrap() = priority;
+    }
+
+    pub fn get(&self) -> Priority {
+        *self.priority.read().unwrap()
+    }
+}
+
+/// Validated pool that tests can insert artifacts into and remove them from
+/// while it is being read, e.g. by the consensus manager.
+///
+/// Clones share the same artifacts. Like the pools in production, readers and
+/// writers are synchronized with a read-write lock, but the lock is only held
+/// for the duration of each call. `get_all_validated()` returns a snapshot.
+pub struct FakeValidatedPool<A: IdentifiableArtifact> {
+    artifacts: Arc<RwLock<HashMap<A::Id, A>>>,
+}
+
+impl<A: IdentifiableArtifact> Clone for FakeValidatedPool<A> {
+    fn clone(&self) -> Self {
+        Self {
+            artifacts: self.artifacts.clone(),
+        }
+    }
+}
+
+impl<A: IdentifiableArtifact> Default for FakeValidatedPool<A> {
+    fn default() -> Self {
+        Self {
+            artifacts: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+}
+
+impl<A: IdentifiableArtifact> FakeValidatedPool<A> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts the artifact, replacing the artifact with the same id. Returns
+    /// true if there was no such artifact.
+    pub fn insert(&self, artifact: A) -> bool {
+        self.artifacts
+            .write()
+            .unwrap()
+            .insert(artifact.id(), artifact)
+            .is_none()
+    }
+
+    /// Removes the artifact with the given id and returns it.
+    pub fn remove(&self, id: &A::Id) -> Option<A> {
+        self.artifacts.write().unwrap().remove(id)
+    }
+
+    pub fn contains(&self, id: &A::Id) -> bool {
+        self.artifacts.read().unwrap().contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.artifacts.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<A: IdentifiableArtifact + Clone> ValidatedPoolReader<A> for FakeValidatedPool<A> {
+    fn get(&self, id: &A::Id) -> Option<A> {
+        self.artifacts.read().unwrap().get(id).cloned()
+    }
+
+    fn get_all_validated(&self) -> Box<dyn Iterator<Item = A> + '_> {
+        let artifacts: Vec<_> = self.artifacts.read().unwrap().values().cloned().collect();
+        Box::new(artifacts.into_iter())
+    }
+}
+
+/// Pool reader that blocks the calling thread for a configurable latency
+/// before every read of the inner pool.
+///
+/// The consensus manager reads the pool on blocking threads, so a slow pool
+/// must only slow down the reads and never stall the event loops. Tests can
+/// use this reader to check that, e.g. that adverts are still received while
+/// artifact RPCs wait for the pool.
+#[derive(Clone)]
+pub struct SlowPoolReader<P> {
+    inner: P,
+    latency: Arc<Mutex<Duration>>,
+    reads: Arc<AtomicU64>,
+}
+
+impl<P> SlowPoolReader<P> {
+    pub fn new(inner: P, latency: Duration) -> Self {
+        Self {
+            inner,
+            latency: Arc::new(Mutex::new(latency)),
+            reads: Arc::default(),
+        }
+    }
+
+    /// Changes the latency of subsequent reads, also of clones.
+    pub fn set_latency(&self, latency: Duration) {
+        *self.latency.lock().unwrap() = latency;
+    }
+
+    /// Returns the number of reads started so far.
+    pub fn reads(&self) -> u64 {
+        self.reads.load(Ordering::SeqCst)
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    fn wait(&self) {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        let latency = *self.latency.lock().unwrap();
+        std::thread::sleep(latency);
+    }
+}
+
+impl<A: IdentifiableArtifact, P: ValidatedPoolReader<A>> ValidatedPoolReader<A>
+    for SlowPoolReader<P>
+{
+    fn get(&self, id: &A::Id) -> Option<A> {
+        self.wait();
+        self.inner.get(id)
+    }
+
+    fn get_all_validated(&self) -> Box<dyn Iterator<Item = A> + '_> {
+        self.wait();
+        self.inner.get_all_validated()
+    }
+}
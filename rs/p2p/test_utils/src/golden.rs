@@ -0,0 +1,79 @@
+//! Golden files for wire formats.
+//!
+//! A golden file holds the hex encoding of a message, e.g. of a
+//! `pb::SlotUpdate`, as checked in at review time. Whitespace is ignored and
+//! lines starting with `#` are comments, so a golden file can describe the
+//! message it contains. Tests embed golden files with `include_str!` and
+//! check that the current code still decodes them to the same message and
+//! still encodes that message to the same bytes.
+use std::fmt::Debug;
+
+use prost::Message;
+
+// Bytes per line of a formatted golden file.
+const BYTES_PER_LINE: usize = 16;
+
+/// Parses the bytes of a golden file.
+///
+/// Panics if the file contains anything but hex bytes and comments.
+#[track_caller]
+pub fn parse_golden(golden: &str) -> Vec<u8> {
+    let digits: Vec<u8> = golden
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .flat_map(|line| line.bytes())
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+    assert!(
+        digits.len() % 2 == 0,
+        "Golden file has an odd number of hex digits."
+    );
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).expect("Golden file is not ASCII.");
+            u8::from_str_radix(pair, 16)
+                .unwrap_or_else(|_| panic!("Invalid hex byte `{}` in golden file.", pair))
+        })
+        .collect()
+}
+
+/// Formats bytes as the content of a golden file, without comments.
+pub fn format_golden(bytes: &[u8]) -> String {
+    bytes
+        .chunks(BYTES_PER_LINE)
+        .map(|line| {
+            line.iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Asserts that the bytes are the bytes of the golden file. On a mismatch the
+/// panic message contains the bytes formatted as a golden file, so that an
+/// intended wire format change can be checked in.
+#[track_caller]
+pub fn assert_matches_golden(bytes: &[u8], golden: &str) {
+    assert!(
+        bytes == parse_golden(golden).as_slice(),
+        "Encoding differs from the golden file. If the wire format change is \
+         intended and backwards compatible, update the golden file to:\n{}",
+        format_golden(bytes)
+    );
+}
+
+/// Asserts that the golden file decodes to the message and that the message
+/// encodes to the bytes of the golden file.
+#[track_caller]
+pub fn assert_golden_round_trip<M: Message + Default + PartialEq + Debug>(
+    message: &M,
+    golden: &str,
+) {
+    let decoded = M::decode(parse_golden(golden).as_slice())
+        .unwrap_or_else(|err| panic!("Failed to decode the golden file: {}", err));
+    assert_eq!(&decoded, message, "Golden file decodes to another message.");
+    assert_matches_golden(&message.encode_to_vec(), golden);
+}
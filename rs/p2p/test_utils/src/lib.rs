@@ -6,6 +6,7 @@ use futures::{
     FutureExt,
 };
 use ic_base_types::{NodeId, PrincipalId, RegistryVersion, SubnetId};
+use ic_crypto_internal_tls::generate_tls_key_pair_der;
 use ic_crypto_temp_crypto::{NodeKeysToGenerate, TempCryptoComponent};
 use ic_crypto_tls_interfaces::TlsConfig;
 use ic_interfaces_mocks::consensus_pool::MockConsensusPoolCache;
@@ -16,7 +17,10 @@ use ic_protobuf::registry::{
     node::v1::{ConnectionEndpoint, NodeRecord},
     subnet::v1::SubnetRecord,
 };
-use ic_quic_transport::{ConnId, DummyUdpSocket, QuicTransport, SubnetTopology, Transport};
+use ic_quic_transport::{
+    ConnId, DummyUdpSocket, KeyMaterialTlsConfig, PeerMetadata, QuicTransport, SubnetTopology,
+    Transport,
+};
 use ic_registry_client_fake::FakeRegistryClient;
 use ic_registry_keys::make_node_record_key;
 use ic_registry_local_registry::LocalRegistry;
@@ -41,8 +45,16 @@ use tokio::{
     task::JoinHandle,
 };
 
+pub mod byzantine;
 pub mod consensus;
+pub mod golden;
+pub mod load;
+pub mod metrics;
 pub mod mocks;
+pub mod partition;
+pub mod state_sync;
+pub mod strategies;
+pub mod synthetic_artifact;
 pub mod turmoil;
 
 /// Creates a temp crypto component with TLS key and specified node id.
@@ -61,6 +73,50 @@ pub fn temp_crypto_component_with_tls_keys(
         .build_arc()
 }
 
+/// The notAfter date of the generated certificates, 9999-12-31 23:59:59 UTC.
+const NO_WELL_DEFINED_CERTIFICATE_EXPIRATION_DATE: u64 = 253_402_300_799;
+
+/// Generates TLS key material for the specified node ids and creates a tls config
+/// for each of them. Every node trusts the certificates of all specified nodes.
+/// In contrast to `temp_crypto_component_with_tls_keys` nothing is added to the registry.
+pub fn key_material_tls_configs(
+    node_ids: &[NodeId],
+) -> HashMap<NodeId, Arc<dyn TlsConfig + Send + Sync>> {
+    let key_material: Vec<_> = node_ids
+        .iter()
+        .map(|node_id| {
+            let (certificate, secret_key) = generate_tls_key_pair_der(
+                &mut rand::thread_rng(),
+                &node_id.get().to_string(),
+                0,
+                NO_WELL_DEFINED_CERTIFICATE_EXPIRATION_DATE,
+            )
+            .expect("Failed to generate tls key pair");
+            (*node_id, certificate.bytes, secret_key)
+        })
+        .collect();
+    let certificates: Vec<_> = key_material
+        .iter()
+        .map(|(_, certificate, _)| certificate.clone())
+        .collect();
+
+    key_material
+        .into_iter()
+        .map(|(node_id, certificate, secret_key)| {
+            let tls_config = KeyMaterialTlsConfig::new(
+                certificate,
+                secret_key.bytes.expose_secret().to_vec(),
+                certificates.clone(),
+            )
+            .expect("Generated key material is valid");
+            (
+                node_id,
+                Arc::new(tls_config) as Arc<dyn TlsConfig + Send + Sync>,
+            )
+        })
+        .collect()
+}
+
 /// Handle that can be used to update anything relevant to the subnet topology.
 #[derive(Clone)]
 pub struct RegistryConsensusHandle {
@@ -206,6 +262,7 @@ pub fn fully_connected_localhost_subnet(
             node_crypto,
             registry_handler.registry_client.clone(),
             node,
+            PeerMetadata::default(),
             topology_watcher.clone(),
             Either::Left::<_, DummyUdpSocket>(socket),
             router,
@@ -0,0 +1,343 @@
+//! Load generation for P2P stress tests.
+//!
+//! A [`LoadGenerator`] starts a consensus manager as the node under test and a
+//! number of simulated peers that are connected to it with the memory
+//! transport. Every peer advertises [`SyntheticArtifact`]s to the node under
+//! test at a fixed rate and serves them until they are delivered. The
+//! resulting [`LoadReport`] contains the achieved throughput and the delivery
+//! latencies, i.e. the time from the advert until the consensus manager hands
+//! the artifact to the artifact processor.
+//!
+//! The same generator serves short CI checks and long soak tests or
+//! benchmarks, which only differ in their configuration, e.g.
+//!
+//! ```ignore
+//! let report = LoadGenerator::new(10)
+//!     .with_rate(100.0)
+//!     .with_payload_sizes(1_000..=100_000)
+//!     .with_duration(Duration::from_secs(60))
+//!     .run(log)
+//!     .await;
+//! println!("{}", report);
+//! ```
+use std::{
+    collections::HashMap,
+    fmt,
+    ops::RangeInclusive,
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+
+use ic_consensus_manager::{ConsensusManagerBuilder, ConsensusManagerConfig};
+use ic_interfaces::p2p::consensus::Priority;
+use ic_logger::ReplicaLogger;
+use ic_memory_transport::TransportRouter;
+use ic_metrics::MetricsRegistry;
+use ic_quic_transport::Shutdown;
+use ic_types::artifact::{IdentifiableArtifact, UnvalidatedArtifactMutation};
+use ic_types_test_utils::ids::node_test_id;
+use tokio::{
+    runtime::Handle,
+    sync::mpsc,
+    task::JoinSet,
+    time::{interval, timeout_at, Instant, MissedTickBehavior},
+};
+
+use crate::{
+    byzantine::ByzantinePeer,
+    consensus::{FakeValidatedPool, SwitchablePriorityFnFactory},
+    partition::NetworkPartitioner,
+    synthetic_artifact::{IdPattern, SyntheticArtifact, SyntheticArtifactGenerator},
+};
+
+/// The node that receives the load.
+pub const NODE_UNDER_TEST: u64 = 0;
+
+// Capacity of the memory transport links, in bytes.
+const LINK_CAPACITY: usize = 100_000_000;
+
+/// Generates artifacts at a configurable rate and size distribution from
+/// simulated peers against one consensus manager.
+#[derive(Clone, Debug)]
+pub struct LoadGenerator {
+    peers: u64,
+    rate: f64,
+    payload_sizes: RangeInclusive<usize>,
+    duration: Duration,
+    drain_timeout: Duration,
+    link_latency: Duration,
+    slots_per_peer: u64,
+    seed: u64,
+}
+
+impl LoadGenerator {
+    /// Creates a generator with the given number of peers, each sending one
+    /// artifact with a payload of 1KB per second for ten seconds.
+    ///
+    /// Panics if `peers` is zero.
+    pub fn new(peers: u64) -> Self {
+        assert!(peers > 0, "Cannot generate load without peers.");
+        Self {
+            peers,
+            rate: 1.0,
+            payload_sizes: 1024..=1024,
+            duration: Duration::from_secs(10),
+            drain_timeout: Duration::from_secs(30),
+            link_latency: Duration::from_millis(10),
+            slots_per_peer: 1_000,
+            seed: 0,
+        }
+    }
+
+    /// Sets the number of artifacts every peer sends per second.
+    ///
+    /// Panics if `rate` is not positive.
+    pub fn with_rate(mut self, rate: f64) -> Self {
+        assert!(rate > 0.0, "The rate must be positive.");
+        self.rate = rate;
+        self
+    }
+
+    /// Draws the payload sizes of the artifacts uniformly from the range.
+    pub fn with_payload_sizes(mut self, payload_sizes: RangeInclusive<usize>) -> Self {
+        self.payload_sizes = payload_sizes;
+        self
+    }
+
+    /// Sets for how long the peers send artifacts.
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Sets how long to wait for outstanding deliveries after the peers
+    /// stopped sending.
+    pub fn with_drain_timeout(mut self, drain_timeout: Duration) -> Self {
+        self.drain_timeout = drain_timeout;
+        self
+    }
+
+    pub fn with_link_latency(mut self, link_latency: Duration) -> Self {
+        self.link_latency = link_latency;
+        self
+    }
+
+    /// Sets the number of slots every peer cycles through. An artifact that
+    /// is not delivered before its slot is reused is lost.
+    ///
+    /// Panics if `slots_per_peer` is zero.
+    pub fn with_slots_per_peer(mut self, slots_per_peer: u64) -> Self {
+        assert!(slots_per_peer > 0, "Every peer needs at least one slot.");
+        self.slots_per_peer = slots_per_peer;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Runs the load against a fresh node under test on the current runtime
+    /// and returns once all artifacts are delivered or the drain timeout
+    /// expired.
+    pub async fn run(self, log: ReplicaLogger) -> LoadReport {
+        self.run_with_metrics(log, MetricsRegistry::default()).await
+    }
+
+    /// Like [`LoadGenerator::run`], but the node under test registers its
+    /// metrics in the given registry, so that they can be inspected.
+    pub async fn run_with_metrics(
+        self,
+        log: ReplicaLogger,
+        metrics_registry: MetricsRegistry,
+    ) -> LoadReport {
+        let node_under_test = node_test_id(NODE_UNDER_TEST);
+        let peer_ids: Vec<_> = (1..=self.peers).map(node_test_id).collect();
+        let partitioner = NetworkPartitioner::with_placeholder_addresses(
+            std::iter::once(node_under_test).chain(peer_ids.iter().copied()),
+        );
+        let mut transport_router = TransportRouter::new();
+
+        // The node under test does not produce artifacts itself.
+        let (_outbound_tx, outbound_rx) = mpsc::channel(1);
+        let (inbound_tx, mut inbound_rx) = mpsc::unbounded_channel();
+        let (priority_fn_factory, _) = SwitchablePriorityFnFactory::new(Priority::FetchNow);
+        let mut cm = ConsensusManagerBuilder::new(
+            log,
+            Handle::current(),
+            metrics_registry,
+            ConsensusManagerConfig::default(),
+        );
+        cm.add_client(
+            outbound_rx,
+            Arc::new(RwLock::new(FakeValidatedPool::<SyntheticArtifact>::new())),
+            Arc::new(priority_fn_factory),
+            inbound_tx,
+        );
+        let transport = transport_router.add_peer(
+            node_under_test,
+            cm.router(),
+            self.link_latency,
+            LINK_CAPACITY,
+        );
+        let shutdowns = cm.run(
+            Arc::new(transport),
+            partitioner.topology_watcher(&node_under_test),
+        );
+
+        let mut peers = HashMap::new();
+        for peer_id in &peer_ids {
+            let mut peer = ByzantinePeer::<SyntheticArtifact>::new();
+            let transport = transport_router.add_peer(
+                *peer_id,
+                peer.router(),
+                self.link_latency,
+                LINK_CAPACITY,
+            );
+            peer.set_transport(Arc::new(transport));
+            peers.insert(*peer_id, Arc::new(peer));
+        }
+
+        // Send times of the artifacts that were not delivered yet.
+        let pending = Arc::new(Mutex::new(HashMap::new()));
+        let start = Instant::now();
+        let mut senders = JoinSet::new();
+        for (i, peer_id) in peer_ids.iter().enumerate() {
+            let peer = peers[peer_id].clone();
+            let pending = pending.clone();
+            let generator = SyntheticArtifactGenerator::new(self.seed + i as u64)
+                .with_payload_sizes(self.payload_sizes.clone())
+                // Every peer gets its own id range, so ids never collide.
+                .with_ids(IdPattern::Sequential {
+                    start: (i as u64) << 32,
+                });
+            let period = Duration::from_secs_f64(1.0 / self.rate);
+            let (end, slots) = (start + self.duration, self.slots_per_peer);
+            senders.spawn(async move {
+                let mut ticker = interval(period);
+                ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                let mut sent = 0;
+                for (seq, artifact) in (0..).zip(generator) {
+                    if ticker.tick().await >= end {
+                        break;
+                    }
+                    let id = artifact.id();
+                    pending.lock().unwrap().insert(id, Instant::now());
+                    match peer
+                        .advertise_artifact(&node_under_test, seq % slots, artifact)
+                        .await
+                    {
+                        Ok(()) => sent += 1,
+                        Err(_) => {
+                            pending.lock().unwrap().remove(&id);
+                            peer.stop_serving(&id);
+                        }
+                    }
+                }
+                sent
+            });
+        }
+
+        let mut report = LoadReport::default();
+        let mut sending = true;
+        let deadline = start + self.duration + self.drain_timeout;
+        loop {
+            tokio::select! {
+                Some(sent) = senders.join_next() => {
+                    report.sent += sent.expect("Load sender panicked.");
+                    sending = !senders.is_empty();
+                }
+                mutation = timeout_at(deadline, inbound_rx.recv()) => {
+                    let Ok(Some(mutation)) = mutation else {
+                        break;
+                    };
+                    if let UnvalidatedArtifactMutation::Insert((artifact, peer_id)) = mutation {
+                        let id = artifact.id();
+                        if let Some(sent_at) = pending.lock().unwrap().remove(&id) {
+                            report.latencies.push(sent_at.elapsed());
+                            report.delivered += 1;
+                            report.delivered_bytes += artifact.size() as u64;
+                            report.elapsed = start.elapsed();
+                        }
+                        if let Some(peer) = peers.get(&peer_id) {
+                            peer.stop_serving(&id);
+                        }
+                    }
+                }
+            }
+            if !sending && pending.lock().unwrap().is_empty() {
+                break;
+            }
+        }
+        senders.shutdown().await;
+        Shutdown::join_all(shutdowns).shutdown().await;
+
+        report.latencies.sort();
+        report
+    }
+}
+
+/// The outcome of a run of a [`LoadGenerator`].
+#[derive(Clone, Debug, Default)]
+pub struct LoadReport {
+    /// The number of artifacts advertised to the node under test.
+    pub sent: u64,
+    /// The number of advertised artifacts the node under test delivered.
+    pub delivered: u64,
+    /// The total size of the delivered artifacts.
+    pub delivered_bytes: u64,
+    /// The time from the start of the run until the last delivery.
+    pub elapsed: Duration,
+    /// The delivery latencies, sorted in ascending order.
+    pub latencies: Vec<Duration>,
+}
+
+impl LoadReport {
+    /// Delivered artifacts per second.
+    pub fn throughput(&self) -> f64 {
+        self.delivered as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Delivered bytes per second.
+    pub fn bandwidth(&self) -> f64 {
+        self.delivered_bytes as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// The fraction of the sent artifacts that were delivered.
+    pub fn delivery_ratio(&self) -> f64 {
+        if self.sent == 0 {
+            return 1.0;
+        }
+        self.delivered as f64 / self.sent as f64
+    }
+
+    /// Returns the latency below which the given fraction of the deliveries
+    /// completed, or `None` if nothing was delivered.
+    ///
+    /// Panics if `quantile` is not between 0 and 1.
+    pub fn latency_quantile(&self, quantile: f64) -> Option<Duration> {
+        assert!((0.0..=1.0).contains(&quantile), "Invalid quantile.");
+        let index = ((self.latencies.len() as f64 * quantile).ceil() as usize).max(1) - 1;
+        self.latencies.get(index).copied()
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "delivered {}/{} artifacts in {:?} ({:.1} artifacts/s, {:.0} B/s)",
+            self.delivered,
+            self.sent,
+            self.elapsed,
+            self.throughput(),
+            self.bandwidth()
+        )?;
+        for quantile in [0.5, 0.9, 0.99] {
+            if let Some(latency) = self.latency_quantile(quantile) {
+                write!(f, ", p{} latency {:?}", quantile * 100.0, latency)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+//! Helpers to assert on the metrics of P2P components, e.g. the
+//! `ConsensusManagerMetrics`.
+//!
+//! Metrics are looked up by name and by a subset of their labels. All label
+//! value combinations of the metric that contain the given labels are summed
+//! up, so e.g. a counter of the consensus manager can be asserted on without
+//! knowing the `client` label of its artifact type.
+use std::time::Duration;
+
+use ic_metrics::MetricsRegistry;
+use ic_test_utilities_metrics::{fetch_counter_vec, fetch_gauge_vec, MetricVec};
+
+// How often `eventually_*` helpers check the metric.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+fn sum_matching(values: MetricVec<f64>, labels: &[(&str, &str)]) -> Option<f64> {
+    values
+        .into_iter()
+        .filter(|(metric_labels, _)| {
+            labels
+                .iter()
+                .all(|(name, value)| metric_labels.get(*name).map(String::as_str) == Some(*value))
+        })
+        .map(|(_, value)| value)
+        .reduce(|sum, value| sum + value)
+}
+
+/// Returns the sum of the values of the counter with the given name and
+/// labels, or `None` if no such counter exists.
+pub fn counter_value(
+    registry: &MetricsRegistry,
+    name: &str,
+    labels: &[(&str, &str)],
+) -> Option<u64> {
+    sum_matching(fetch_counter_vec(registry, name), labels).map(|value| value as u64)
+}
+
+/// Returns the sum of the values of the gauge with the given name and labels,
+/// or `None` if no such gauge exists.
+pub fn gauge_value(registry: &MetricsRegistry, name: &str, labels: &[(&str, &str)]) -> Option<i64> {
+    sum_matching(fetch_gauge_vec(registry, name), labels).map(|value| value as i64)
+}
+
+/// Asserts that the counter with the given name and labels has the expected
+/// value. A counter that does not exist is treated as zero.
+#[track_caller]
+pub fn assert_counter_eq(
+    registry: &MetricsRegistry,
+    name: &str,
+    labels: &[(&str, &str)],
+    expected: u64,
+) {
+    let value = counter_value(registry, name, labels).unwrap_or(0);
+    assert_eq!(
+        value, expected,
+        "Counter {} with labels {:?} is {}, expected {}",
+        name, labels, value, expected
+    );
+}
+
+/// Asserts that the gauge with the given name and labels has the expected
+/// value. A gauge that does not exist is treated as zero.
+#[track_caller]
+pub fn assert_gauge_eq(
+    registry: &MetricsRegistry,
+    name: &str,
+    labels: &[(&str, &str)],
+    expected: i64,
+) {
+    let value = gauge_value(registry, name, labels).unwrap_or(0);
+    assert_eq!(
+        value, expected,
+        "Gauge {} with labels {:?} is {}, expected {}",
+        name, labels, value, expected
+    );
+}
+
+/// Waits until the counter with the given name and labels has the expected
+/// value. Panics if it does not within the timeout.
+pub async fn eventually_counter_eq(
+    registry: &MetricsRegistry,
+    name: &str,
+    labels: &[(&str, &str)],
+    expected: u64,
+    timeout: Duration,
+) {
+    let poll = async {
+        while counter_value(registry, name, labels).unwrap_or(0) != expected {
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    };
+    if tokio::time::timeout(timeout, poll).await.is_err() {
+        assert_counter_eq(registry, name, labels, expected);
+    }
+}
+
+/// Waits until the gauge with the given name and labels has the expected
+/// value. Panics if it does not within the timeout.
+pub async fn eventually_gauge_eq(
+    registry: &MetricsRegistry,
+    name: &str,
+    labels: &[(&str, &str)],
+    expected: i64,
+    timeout: Duration,
+) {
+    let poll = async {
+        while gauge_value(registry, name, labels).unwrap_or(0) != expected {
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    };
+    if tokio::time::timeout(timeout, poll).await.is_err() {
+        assert_gauge_eq(registry, name, labels, expected);
+    }
+}
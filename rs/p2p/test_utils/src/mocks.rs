@@ -1,5 +1,11 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::anyhow;
 use async_trait::async_trait;
-use axum::http::{Request, Response};
+use axum::http::{Request, Response, StatusCode};
 use bytes::Bytes;
 use ic_interfaces::p2p::{
     consensus::{PriorityFn, PriorityFnFactory, ValidatedPoolReader},
@@ -77,3 +83,216 @@ mock! {
         fn get_priority_function(&self, pool: &MockValidatedPoolReader<A>) -> PriorityFn<A::Id, A::Attribute>;
     }
 }
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum CallKind {
+    Rpc,
+    Push,
+}
+
+// The calls a scripted step applies to. A step without a path applies to
+// all calls to the peer that no step with a matching path applies to.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Route {
+    kind: CallKind,
+    peer_id: NodeId,
+    path: Option<String>,
+}
+
+#[derive(Clone)]
+enum Outcome {
+    Fail,
+    Respond(StatusCode, Bytes),
+}
+
+struct Step {
+    outcome: Outcome,
+    remaining: usize,
+}
+
+#[derive(Default)]
+struct Script {
+    steps: HashMap<Route, VecDeque<Step>>,
+}
+
+impl Script {
+    fn next(&mut self, kind: CallKind, peer_id: &NodeId, path: &str) -> Outcome {
+        let routes = [
+            Route {
+                kind,
+                peer_id: *peer_id,
+                path: Some(path.to_string()),
+            },
+            Route {
+                kind,
+                peer_id: *peer_id,
+                path: None,
+            },
+        ];
+        for route in routes {
+            if let Some(steps) = self.steps.get_mut(&route) {
+                if let Some(step) = steps.front_mut() {
+                    let outcome = step.outcome.clone();
+                    step.remaining -= 1;
+                    if step.remaining == 0 {
+                        steps.pop_front();
+                    }
+                    return outcome;
+                }
+            }
+        }
+        panic!(
+            "Unscripted {:?} call to peer {} for {}",
+            kind, peer_id, path
+        );
+    }
+}
+
+/// Builds a [`MockTransport`] that answers the calls to each peer with a
+/// scripted sequence of outcomes, e.g.
+///
+/// ```ignore
+/// let transport = MockTransportBuilder::new()
+///     .rpc(NODE_1).fails()
+///     .rpc(NODE_1).times(2).responds_with(body)
+///     .rpc(NODE_2).uri("/chunk").responds_with(chunk)
+///     .build();
+/// ```
+///
+/// The steps of a peer are used up in the order they were declared. Steps
+/// restricted to a URI path take precedence over the other steps of the peer.
+/// A call that no step is left for panics, like an unexpected call to a mock.
+#[derive(Default)]
+pub struct MockTransportBuilder {
+    script: Script,
+    peers: Vec<(NodeId, ConnId)>,
+}
+
+impl MockTransportBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares the next outcome of the rpcs to the given peer.
+    pub fn rpc(self, peer_id: NodeId) -> ScriptedStep {
+        ScriptedStep::new(self, CallKind::Rpc, peer_id)
+    }
+
+    /// Declares the next outcome of the pushes to the given peer.
+    pub fn push(self, peer_id: NodeId) -> ScriptedStep {
+        ScriptedStep::new(self, CallKind::Push, peer_id)
+    }
+
+    /// Sets the peers returned by `peers()`.
+    pub fn with_peers(mut self, peers: Vec<(NodeId, ConnId)>) -> Self {
+        self.peers = peers;
+        self
+    }
+
+    pub fn build(self) -> MockTransport {
+        let script = Arc::new(Mutex::new(self.script));
+        let mut transport = MockTransport::new();
+        let rpc_script = script.clone();
+        transport.expect_rpc().returning(move |peer_id, request| {
+            let outcome =
+                rpc_script
+                    .lock()
+                    .unwrap()
+                    .next(CallKind::Rpc, peer_id, request.uri().path());
+            match outcome {
+                Outcome::Fail => Err(anyhow!("Scripted rpc failure")),
+                Outcome::Respond(status, body) => {
+                    Ok(Response::builder().status(status).body(body).unwrap())
+                }
+            }
+        });
+        transport.expect_push().returning(move |peer_id, request| {
+            let outcome =
+                script
+                    .lock()
+                    .unwrap()
+                    .next(CallKind::Push, peer_id, request.uri().path());
+            match outcome {
+                Outcome::Fail => Err(anyhow!("Scripted push failure")),
+                Outcome::Respond(..) => Ok(()),
+            }
+        });
+        let peers = self.peers;
+        transport.expect_peers().returning(move || peers.clone());
+        transport
+    }
+}
+
+/// A step of a [`MockTransportBuilder`] script that is being declared.
+pub struct ScriptedStep {
+    builder: MockTransportBuilder,
+    route: Route,
+    times: usize,
+}
+
+impl ScriptedStep {
+    fn new(builder: MockTransportBuilder, kind: CallKind, peer_id: NodeId) -> Self {
+        Self {
+            builder,
+            route: Route {
+                kind,
+                peer_id,
+                path: None,
+            },
+            times: 1,
+        }
+    }
+
+    /// Restricts the step to the calls for the given URI path.
+    pub fn uri(mut self, path: &str) -> Self {
+        self.route.path = Some(path.to_string());
+        self
+    }
+
+    /// Applies the step to the given number of consecutive calls instead of
+    /// one.
+    pub fn times(mut self, times: usize) -> Self {
+        self.times = times;
+        self
+    }
+
+    /// The calls fail with an error.
+    pub fn fails(self) -> MockTransportBuilder {
+        self.finish(Outcome::Fail)
+    }
+
+    /// The calls succeed. Rpcs get an empty response.
+    pub fn succeeds(self) -> MockTransportBuilder {
+        self.finish(Outcome::Respond(StatusCode::OK, Bytes::new()))
+    }
+
+    /// The calls succeed. Rpcs get a response with the given body.
+    pub fn responds_with(self, body: Bytes) -> MockTransportBuilder {
+        self.finish(Outcome::Respond(StatusCode::OK, body))
+    }
+
+    /// The calls succeed. Rpcs get a response with the given status and body.
+    pub fn responds_with_status(self, status: StatusCode, body: Bytes) -> MockTransportBuilder {
+        self.finish(Outcome::Respond(status, body))
+    }
+
+    fn finish(self, outcome: Outcome) -> MockTransportBuilder {
+        let Self {
+            mut builder,
+            route,
+            times,
+        } = self;
+        if times > 0 {
+            builder
+                .script
+                .steps
+                .entry(route)
+                .or_default()
+                .push_back(Step {
+                    outcome,
+                    remaining: times,
+                });
+        }
+        builder
+    }
+}
@@ -0,0 +1,222 @@
+//! Partitions a subnet of test nodes and heals it again.
+//!
+//! A partition has two effects, which together mirror what a node observes
+//! when the network between it and some of its peers goes down:
+//!  - Each node gets its own `SubnetTopology` watch that only lists the nodes
+//!    it can reach. Components that follow the topology, like the consensus
+//!    manager, stop working with the unreachable peers.
+//!  - Each node's transport is wrapped so that requests to unreachable peers
+//!    fail and those peers are not reported as connected.
+//!
+//! Healing the partition restores the full topology and reachability, so
+//! partition-recovery tests of the consensus manager and of state sync use
+//! the same mechanism.
+use std::{
+    collections::{BTreeSet, HashMap},
+    net::{Ipv4Addr, SocketAddr},
+    sync::{Arc, RwLock},
+};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use axum::http::{Request, Response};
+use bytes::Bytes;
+use ic_quic_transport::{ConnId, ConnectionStats, PeerMetadata, SubnetTopology, Transport};
+use ic_types::{NodeId, RegistryVersion};
+use tokio::sync::watch;
+
+// Assigns every node to a group. Nodes can only reach nodes of the same
+// group.
+#[derive(Default)]
+struct Reachability {
+    groups: HashMap<NodeId, usize>,
+}
+
+impl Reachability {
+    fn reachable(&self, a: &NodeId, b: &NodeId) -> bool {
+        self.groups.get(a) == self.groups.get(b)
+    }
+}
+
+/// Creates partitions between a fixed set of test nodes and heals them.
+pub struct NetworkPartitioner {
+    nodes: Vec<(NodeId, SocketAddr)>,
+    reachability: Arc<RwLock<Reachability>>,
+    topologies: HashMap<NodeId, watch::Sender<SubnetTopology>>,
+    registry_version: RegistryVersion,
+}
+
+impl NetworkPartitioner {
+    /// Creates a fully connected subnet of the given nodes and addresses.
+    pub fn new<T: IntoIterator<Item = (NodeId, SocketAddr)>>(nodes: T) -> Self {
+        let nodes: Vec<_> = nodes.into_iter().collect();
+        let registry_version = RegistryVersion::from(1);
+        let topologies = nodes
+            .iter()
+            .map(|(node_id, _)| {
+                let topology =
+                    SubnetTopology::new(nodes.clone(), registry_version, registry_version);
+                (*node_id, watch::channel(topology).0)
+            })
+            .collect();
+        Self {
+            nodes,
+            reachability: Arc::default(),
+            topologies,
+            registry_version,
+        }
+    }
+
+    /// Creates a fully connected subnet of the given nodes with placeholder
+    /// addresses, for nodes whose transport does not use the topology to
+    /// connect, e.g. the memory transport.
+    pub fn with_placeholder_addresses<T: IntoIterator<Item = NodeId>>(nodes: T) -> Self {
+        Self::new(nodes.into_iter().enumerate().map(|(i, node_id)| {
+            (
+                node_id,
+                SocketAddr::from((Ipv4Addr::LOCALHOST, 4100 + i as u16)),
+            )
+        }))
+    }
+
+    /// Returns the topology as seen by the given node.
+    pub fn topology_watcher(&self, node_id: &NodeId) -> watch::Receiver<SubnetTopology> {
+        self.topologies[node_id].subscribe()
+    }
+
+    /// Wraps the transport of the given node so that it can only reach the
+    /// nodes on its side of the partition.
+    pub fn transport(&self, node_id: NodeId, inner: Arc<dyn Transport>) -> Arc<dyn Transport> {
+        Arc::new(PartitionedTransport {
+            node_id,
+            inner,
+            reachability: Arc::clone(&self.reachability),
+        })
+    }
+
+    /// Splits the subnet into the given groups. Nodes can only reach nodes of
+    /// the same group. Nodes that are not part of any group form one more
+    /// group together.
+    pub fn partition(&mut self, groups: &[&[NodeId]]) {
+        {
+            let mut reachability = self.reachability.write().unwrap();
+            reachability.groups.clear();
+            for (i, group) in groups.iter().enumerate() {
+                for node_id in group.iter() {
+                    reachability.groups.insert(*node_id, i + 1);
+                }
+            }
+        }
+        self.update_topologies();
+    }
+
+    /// Cuts the given node off from all other nodes.
+    pub fn isolate(&mut self, node_id: NodeId) {
+        self.partition(&[&[node_id]]);
+    }
+
+    /// Makes all nodes reachable again.
+    pub fn heal(&mut self) {
+        self.reachability.write().unwrap().groups.clear();
+        self.update_topologies();
+    }
+
+    /// Returns true if the two nodes can reach each other.
+    pub fn reachable(&self, a: &NodeId, b: &NodeId) -> bool {
+        self.reachability.read().unwrap().reachable(a, b)
+    }
+
+    /// Returns the nodes that the given node can reach, including itself.
+    pub fn reachable_from(&self, node_id: &NodeId) -> BTreeSet<NodeId> {
+        let reachability = self.reachability.read().unwrap();
+        self.nodes
+            .iter()
+            .map(|(peer_id, _)| *peer_id)
+            .filter(|peer_id| reachability.reachable(node_id, peer_id))
+            .collect()
+    }
+
+    // Publishes a new topology to every node that only contains the nodes it
+    // can reach.
+    fn update_topologies(&mut self) {
+        self.registry_version = RegistryVersion::from(self.registry_version.get() + 1);
+        for (node_id, sender) in self.topologies.iter() {
+            let reachable = self.reachable_from(node_id);
+            let topology = SubnetTopology::new(
+                self.nodes
+                    .iter()
+                    .filter(|(peer_id, _)| reachable.contains(peer_id))
+                    .copied(),
+                RegistryVersion::from(1),
+                self.registry_version,
+            );
+            sender.send_replace(topology);
+        }
+    }
+}
+
+/// Transport of a node that fails requests to peers on the other side of a
+/// partition.
+struct PartitionedTransport {
+    node_id: NodeId,
+    inner: Arc<dyn Transport>,
+    reachability: Arc<RwLock<Reachability>>,
+}
+
+impl PartitionedTransport {
+    fn check_reachable(&self, peer_id: &NodeId) -> Result<(), anyhow::Error> {
+        if self
+            .reachability
+            .read()
+            .unwrap()
+            .reachable(&self.node_id, peer_id)
+        {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Peer {} is partitioned from {}",
+                peer_id,
+                self.node_id
+            ))
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for PartitionedTransport {
+    async fn rpc(
+        &self,
+        peer_id: &NodeId,
+        request: Request<Bytes>,
+    ) -> Result<Response<Bytes>, anyhow::Error> {
+        self.check_reachable(peer_id)?;
+        let response = self.inner.rpc(peer_id, request).await?;
+        // The partition may have been created while the request was in flight.
+        self.check_reachable(peer_id)?;
+        Ok(response)
+    }
+
+    async fn push(&self, peer_id: &NodeId, request: Request<Bytes>) -> Result<(), anyhow::Error> {
+        self.check_reachable(peer_id)?;
+        self.inner.push(peer_id, request).await
+    }
+
+    fn peers(&self) -> Vec<(NodeId, ConnId)> {
+        let reachability = self.reachability.read().unwrap();
+        self.inner
+            .peers()
+            .into_iter()
+            .filter(|(peer_id, _)| reachability.reachable(&self.node_id, peer_id))
+            .collect()
+    }
+
+    fn connection_stats(&self, peer_id: &NodeId) -> Option<ConnectionStats> {
+        self.check_reachable(peer_id).ok()?;
+        self.inner.connection_stats(peer_id)
+    }
+
+    fn peer_metadata(&self, peer_id: &NodeId) -> Option<PeerMetadata> {
+        self.check_reachable(peer_id).ok()?;
+        self.inner.peer_metadata(peer_id)
+    }
+}
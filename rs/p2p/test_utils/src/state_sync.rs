@@ -0,0 +1,410 @@
+//! In-memory state sync client that serves a synthetic state split into
+//! chunks.
+//!
+//! Unlike `MockStateSync`, the fake speaks the whole state sync protocol: a
+//! node that serves a state answers chunk requests for it, and a node that
+//! learns about a newer state downloads its manifest and then all of its
+//! chunks. Once the download completes the node serves the new state itself,
+//! so the fakes can be used to test state sync end to end.
+//!
+//! [`FakeChunkable`] on the other hand assembles an arbitrary set of chunks
+//! and can be made slow or to reject chunks, for tests that hand chunkables to
+//! the state sync manager directly.
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeSet},
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use ic_interfaces::p2p::state_sync::{
+    AddChunkError, Chunk, ChunkId, Chunkable, StateSyncArtifactId, StateSyncClient,
+};
+use ic_state_manager::state_sync::types::StateSyncMessage;
+use ic_types::{crypto::CryptoHash, Height};
+
+/// The id of the chunk that lists the sizes of the chunks of a state. It is
+/// downloaded before any other chunk.
+pub const MANIFEST_CHUNK_ID: u32 = u32::MAX - 1;
+
+/// A synthetic state at some height, split into chunks of the given sizes.
+/// The chunks are numbered from 1 and their content is derived from their id,
+/// so that a downloaded chunk can be verified.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SyntheticState {
+    height: Height,
+    chunk_sizes: Vec<usize>,
+}
+
+impl SyntheticState {
+    /// Creates a state with `num_chunks` chunks of `chunk_size` bytes each.
+    pub fn new(height: Height, num_chunks: u32, chunk_size: usize) -> Self {
+        Self::with_chunk_sizes(height, vec![chunk_size; num_chunks as usize])
+    }
+
+    /// Creates a state with one chunk of each of the given sizes.
+    pub fn with_chunk_sizes(height: Height, chunk_sizes: Vec<usize>) -> Self {
+        Self {
+            height,
+            chunk_sizes,
+        }
+    }
+
+    pub fn height(&self) -> Height {
+        self.height
+    }
+
+    pub fn num_chunks(&self) -> usize {
+        self.chunk_sizes.len()
+    }
+
+    /// The total size of the chunks of the state in bytes.
+    pub fn size(&self) -> usize {
+        self.chunk_sizes.iter().sum()
+    }
+
+    /// Calculates the artifact id of the state by hashing its height and chunk
+    /// sizes.
+    pub fn artifact_id(&self) -> StateSyncArtifactId {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        StateSyncArtifactId {
+            height: self.height,
+            hash: CryptoHash(hasher.finish().to_be_bytes().to_vec()),
+        }
+    }
+
+    /// Returns the content of the given chunk, or `None` if it is not part of
+    /// the state.
+    pub fn chunk(&self, chunk_id: ChunkId) -> Option<Vec<u8>> {
+        if chunk_id.get() == MANIFEST_CHUNK_ID {
+            return Some(self.encode_manifest());
+        }
+        let size = self
+            .chunk_sizes
+            .get((chunk_id.get() as usize).checked_sub(1)?)?;
+        Some(vec![chunk_id.get() as u8; *size])
+    }
+
+    fn encode_manifest(&self) -> Vec<u8> {
+        self.chunk_sizes
+            .iter()
+            .flat_map(|size| (*size as u64).to_le_bytes())
+            .collect()
+    }
+
+    fn decode_manifest(height: Height, manifest: &[u8]) -> Option<Self> {
+        if manifest.len() % 8 != 0 {
+            return None;
+        }
+        let chunk_sizes = manifest
+            .chunks_exact(8)
+            .map(|size| u64::from_le_bytes(size.try_into().unwrap()) as usize)
+            .collect();
+        Some(Self::with_chunk_sizes(height, chunk_sizes))
+    }
+}
+
+struct FakeStateSyncInner {
+    // The latest state of the node, served to its peers.
+    state: Option<SyntheticState>,
+    // Whether a state sync is running.
+    syncing: bool,
+}
+
+/// Fake state sync client that serves the latest state of a node and syncs to
+/// newer states advertised by its peers.
+#[derive(Clone)]
+pub struct FakeStateSync {
+    inner: Arc<Mutex<FakeStateSyncInner>>,
+    chunk_delay: Duration,
+}
+
+impl FakeStateSync {
+    /// Creates a client that serves the given state.
+    pub fn new(state: SyntheticState) -> Self {
+        Self::with_state(Some(state))
+    }
+
+    /// Creates a client without any state, which syncs to the first state it
+    /// learns about.
+    pub fn empty() -> Self {
+        Self::with_state(None)
+    }
+
+    fn with_state(state: Option<SyntheticState>) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(FakeStateSyncInner {
+                state,
+                syncing: false,
+            })),
+            chunk_delay: Duration::ZERO,
+        }
+    }
+
+    /// Delays every served chunk by the given duration, to simulate a node
+    /// that is slow to read its state.
+    pub fn with_chunk_delay(mut self, chunk_delay: Duration) -> Self {
+        self.chunk_delay = chunk_delay;
+        self
+    }
+
+    /// Replaces the state of the node, e.g. to simulate the subnet making
+    /// progress.
+    pub fn set_state(&self, state: SyntheticState) {
+        self.inner.lock().unwrap().state = Some(state);
+    }
+
+    /// The latest state of the node, either set or synced.
+    pub fn state(&self) -> Option<SyntheticState> {
+        self.inner.lock().unwrap().state.clone()
+    }
+
+    pub fn is_syncing(&self) -> bool {
+        self.inner.lock().unwrap().syncing
+    }
+
+    fn height(&self) -> Option<Height> {
+        self.inner
+            .lock()
+            .unwrap()
+            .state
+            .as_ref()
+            .map(SyntheticState::height)
+    }
+}
+
+impl StateSyncClient for FakeStateSync {
+    type Message = StateSyncMessage;
+
+    fn available_states(&self) -> Vec<StateSyncArtifactId> {
+        self.state()
+            .map(|state| state.artifact_id())
+            .into_iter()
+            .collect()
+    }
+
+    fn maybe_start_state_sync(
+        &self,
+        id: &StateSyncArtifactId,
+    ) -> Option<Box<dyn Chunkable<StateSyncMessage> + Send>> {
+        let mut inner = self.inner.lock().unwrap();
+        let newer = inner
+            .state
+            .as_ref()
+            .map_or(true, |state| id.height > state.height());
+        if inner.syncing || !newer {
+            return None;
+        }
+        inner.syncing = true;
+        Some(Box::new(SyntheticStateChunkable {
+            inner: Arc::clone(&self.inner),
+            syncing_state: id.clone(),
+            manifest: None,
+            missing: BTreeSet::from([ChunkId::from(MANIFEST_CHUNK_ID)]),
+        }))
+    }
+
+    fn cancel_if_running(&self, id: &StateSyncArtifactId) -> bool {
+        // A state sync is cancelled once the node has a state at least as
+        // recent, e.g. because it was set in the meantime.
+        self.height().map_or(false, |height| height >= id.height)
+    }
+
+    fn chunk(&self, id: &StateSyncArtifactId, chunk_id: ChunkId) -> Option<Chunk> {
+        let state = self.state().filter(|state| &state.artifact_id() == id)?;
+        // Chunks are served from a blocking task, so sleeping does not stall
+        // the runtime.
+        std::thread::sleep(self.chunk_delay);
+        state.chunk(chunk_id).map(Chunk::from)
+    }
+}
+
+/// Downloads a state by first fetching its manifest and then all chunks
+/// listed in it. Stops the state sync of the client when dropped.
+struct SyntheticStateChunkable {
+    inner: Arc<Mutex<FakeStateSyncInner>>,
+    syncing_state: StateSyncArtifactId,
+    manifest: Option<SyntheticState>,
+    missing: BTreeSet<ChunkId>,
+}
+
+impl Chunkable<StateSyncMessage> for SyntheticStateChunkable {
+    fn chunks_to_download(&self) -> Box<dyn Iterator<Item = ChunkId>> {
+        Box::new(self.missing.clone().into_iter())
+    }
+
+    fn add_chunk(&mut self, chunk_id: ChunkId, chunk: Chunk) -> Result<(), AddChunkError> {
+        if !self.missing.contains(&chunk_id) {
+            return Err(AddChunkError::Invalid);
+        }
+        match &self.manifest {
+            None => {
+                let manifest =
+                    SyntheticState::decode_manifest(self.syncing_state.height, chunk.as_bytes())
+                        .filter(|manifest| manifest.artifact_id() == self.syncing_state)
+                        .ok_or(AddChunkError::Invalid)?;
+                self.missing = (1..=manifest.num_chunks() as u32)
+                    .map(ChunkId::from)
+                    .collect();
+                self.manifest = Some(manifest);
+            }
+            Some(manifest) => {
+                if manifest.chunk(chunk_id).as_deref() != Some(chunk.as_bytes()) {
+                    return Err(AddChunkError::Invalid);
+                }
+                self.missing.remove(&chunk_id);
+            }
+        }
+
+        if self.missing.is_empty() {
+            let mut inner = self.inner.lock().unwrap();
+            let newer = inner
+                .state
+                .as_ref()
+                .map_or(true, |state| self.syncing_state.height > state.height());
+            if newer {
+                inner.state = self.manifest.clone();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SyntheticStateChunkable {
+    fn drop(&mut self) {
+        self.inner.lock().unwrap().syncing = false;
+    }
+}
+
+#[derive(Default)]
+struct FakeChunkableState {
+    missing: BTreeSet<ChunkId>,
+    rejected: BTreeSet<ChunkId>,
+    added: Vec<ChunkId>,
+    rejections: Vec<ChunkId>,
+    dropped: bool,
+}
+
+/// Chunkable that assembles a fixed set of chunks and records what happens to
+/// it, to test the cancellation and timeout paths of state sync.
+///
+/// Chunks can be configured to be rejected with `AddChunkError::Invalid`
+/// every time they are added, in which case the state sync never completes.
+/// Adding a chunk can be slowed down, and the chunk that completes the state
+/// sync is only accepted once a minimum time passed since the chunkable was
+/// created. Both delays block the caller, like a state manager that is slow to
+/// assemble the state.
+pub struct FakeChunkable {
+    state: Arc<Mutex<FakeChunkableState>>,
+    chunk_delay: Duration,
+    min_duration: Duration,
+    created_at: Instant,
+}
+
+impl FakeChunkable {
+    /// Creates a chunkable that completes once all the given chunks were
+    /// added.
+    pub fn new<T: IntoIterator<Item = ChunkId>>(chunks: T) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(FakeChunkableState {
+                missing: chunks.into_iter().collect(),
+                ..Default::default()
+            })),
+            chunk_delay: Duration::ZERO,
+            min_duration: Duration::ZERO,
+            created_at: Instant::now(),
+        }
+    }
+
+    /// Rejects the given chunks whenever they are added.
+    pub fn rejecting<T: IntoIterator<Item = ChunkId>>(self, chunk_ids: T) -> Self {
+        self.state.lock().unwrap().rejected.extend(chunk_ids);
+        self
+    }
+
+    /// Blocks every call to `add_chunk()` for the given duration.
+    pub fn with_chunk_delay(mut self, chunk_delay: Duration) -> Self {
+        self.chunk_delay = chunk_delay;
+        self
+    }
+
+    /// Completes the state sync no earlier than the given duration after the
+    /// chunkable was created.
+    pub fn with_min_duration(mut self, min_duration: Duration) -> Self {
+        self.min_duration = min_duration;
+        self
+    }
+
+    /// Returns a handle to inspect the chunkable after it was handed to the
+    /// state sync manager.
+    pub fn handle(&self) -> FakeChunkableHandle {
+        FakeChunkableHandle {
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl<T> Chunkable<T> for FakeChunkable {
+    fn chunks_to_download(&self) -> Box<dyn Iterator<Item = ChunkId>> {
+        Box::new(self.state.lock().unwrap().missing.clone().into_iter())
+    }
+
+    fn add_chunk(&mut self, chunk_id: ChunkId, _chunk: Chunk) -> Result<(), AddChunkError> {
+        std::thread::sleep(self.chunk_delay);
+        let mut state = self.state.lock().unwrap();
+        if state.rejected.contains(&chunk_id) {
+            state.rejections.push(chunk_id);
+            return Err(AddChunkError::Invalid);
+        }
+        if state.missing.len() == 1 && state.missing.contains(&chunk_id) {
+            // Release the lock while waiting, so that the handle can be used.
+            drop(state);
+            std::thread::sleep(self.min_duration.saturating_sub(self.created_at.elapsed()));
+            state = self.state.lock().unwrap();
+        }
+        if state.missing.remove(&chunk_id) {
+            state.added.push(chunk_id);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for FakeChunkable {
+    fn drop(&mut self) {
+        self.state.lock().unwrap().dropped = true;
+    }
+}
+
+/// Inspects a [`FakeChunkable`].
+#[derive(Clone)]
+pub struct FakeChunkableHandle {
+    state: Arc<Mutex<FakeChunkableState>>,
+}
+
+impl FakeChunkableHandle {
+    /// Returns the chunks that were added successfully, in order.
+    pub fn added_chunks(&self) -> Vec<ChunkId> {
+        self.state.lock().unwrap().added.clone()
+    }
+
+    /// Returns the chunks whose addition was rejected, in order.
+    pub fn rejected_chunks(&self) -> Vec<ChunkId> {
+        self.state.lock().unwrap().rejections.clone()
+    }
+
+    /// Returns the chunks that still need to be added.
+    pub fn missing_chunks(&self) -> BTreeSet<ChunkId> {
+        self.state.lock().unwrap().missing.clone()
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.state.lock().unwrap().missing.is_empty()
+    }
+
+    /// Returns true if the state sync manager dropped the chunkable, i.e. the
+    /// state sync completed or was cancelled.
+    pub fn is_dropped(&self) -> bool {
+        self.state.lock().unwrap().dropped
+    }
+}
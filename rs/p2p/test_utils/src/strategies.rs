@@ -0,0 +1,159 @@
+//! Proptest strategies for the messages of the consensus manager.
+//!
+//! The strategies generate slot updates for the [`U64Artifact`] and sequences
+//! of peer events as a receiver sees them: every peer sends its updates with
+//! increasing commit ids, as the sender of the consensus manager does, and the
+//! updates of different peers arrive in arbitrary interleavings.
+use ic_protobuf::p2p::v1 as pb;
+use ic_types::NodeId;
+use proptest::{collection::vec, prelude::*, sample::Index};
+use prost::Message;
+
+use crate::consensus::U64Artifact;
+
+/// A slot update of the [`U64Artifact`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArbitrarySlotUpdate {
+    pub commit_id: u64,
+    pub slot: u64,
+    pub id: u64,
+    /// Whether the artifact is pushed with the update instead of being
+    /// advertised.
+    pub push: bool,
+}
+
+impl ArbitrarySlotUpdate {
+    /// The artifact of the update, with the given size.
+    pub fn artifact(&self, msg_size: usize) -> U64Artifact {
+        U64Artifact::id_to_msg(self.id, msg_size)
+    }
+
+    /// Encodes the update as it is sent on the wire. Pushed artifacts have
+    /// the given size.
+    pub fn to_pb(&self, msg_size: usize) -> pb::SlotUpdate {
+        let update = if self.push {
+            pb::slot_update::Update::Artifact(
+                Vec::<u8>::from(self.artifact(msg_size)).encode_to_vec(),
+            )
+        } else {
+            pb::slot_update::Update::Advert(pb::Advert {
+                id: self.id.encode_to_vec(),
+                attribute: ().encode_to_vec(),
+                attribute_digest: Vec::new(),
+            })
+        };
+        pb::SlotUpdate {
+            commit_id: self.commit_id,
+            slot_id: self.slot,
+            update: Some(update),
+        }
+    }
+}
+
+/// An event a receiver observes from one of its peers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PeerEvent {
+    /// The peer sends the slot update.
+    Update(NodeId, ArbitrarySlotUpdate),
+    /// The peer reconnects, so its later updates arrive with a higher
+    /// connection id.
+    Reconnect(NodeId),
+}
+
+impl PeerEvent {
+    pub fn peer_id(&self) -> NodeId {
+        match self {
+            Self::Update(peer_id, _) | Self::Reconnect(peer_id) => *peer_id,
+        }
+    }
+}
+
+/// Generates slot updates with arbitrary commit ids, slots below `slots` and
+/// ids below `ids`.
+pub fn arb_slot_update(slots: u64, ids: u64) -> impl Strategy<Value = ArbitrarySlotUpdate> {
+    (any::<u64>(), 0..slots, 0..ids, any::<bool>()).prop_map(|(commit_id, slot, id, push)| {
+        ArbitrarySlotUpdate {
+            commit_id,
+            slot,
+            id,
+            push,
+        }
+    })
+}
+
+/// Generates `len` strictly increasing commit ids. Gaps between them stand
+/// for updates that were sent to other peers or purged before they were sent.
+pub fn arb_commit_ids(len: usize) -> impl Strategy<Value = Vec<u64>> {
+    vec(1..4_u64, len).prop_map(|gaps| {
+        gaps.into_iter()
+            .scan(0, |commit_id, gap| {
+                *commit_id += gap;
+                Some(*commit_id)
+            })
+            .collect()
+    })
+}
+
+/// Generates the events of one peer: `len` slot updates with increasing
+/// commit ids, some of which are preceded by a reconnect.
+pub fn arb_peer_events(
+    peer_id: NodeId,
+    len: usize,
+    slots: u64,
+    ids: u64,
+) -> impl Strategy<Value = Vec<PeerEvent>> {
+    (
+        vec(
+            (arb_slot_update(slots, ids), prop::bool::weighted(0.1)),
+            len,
+        ),
+        arb_commit_ids(len),
+    )
+        .prop_map(move |(updates, commit_ids)| {
+            updates
+                .into_iter()
+                .zip(commit_ids)
+                .flat_map(|((mut update, reconnect), commit_id)| {
+                    update.commit_id = commit_id;
+                    reconnect
+                        .then_some(PeerEvent::Reconnect(peer_id))
+                        .into_iter()
+                        .chain(std::iter::once(PeerEvent::Update(peer_id, update)))
+                })
+                .collect()
+        })
+}
+
+/// Generates an interleaving of the events of all peers, each sending `len`
+/// slot updates. The events of every peer keep their order.
+pub fn arb_interleaved_peer_events(
+    peers: Vec<NodeId>,
+    len: usize,
+    slots: u64,
+    ids: u64,
+) -> impl Strategy<Value = Vec<PeerEvent>> {
+    let per_peer: Vec<_> = peers
+        .into_iter()
+        .map(|peer_id| arb_peer_events(peer_id, len, slots, ids))
+        .collect();
+    per_peer.prop_flat_map(|per_peer| {
+        let total = per_peer.iter().map(Vec::len).sum();
+        vec(any::<Index>(), total).prop_map(move |choices| interleave(per_peer.clone(), choices))
+    })
+}
+
+// Merges the sequences by taking the next event of the sequence picked by
+// each choice among the non-empty ones.
+fn interleave(sequences: Vec<Vec<PeerEvent>>, choices: Vec<Index>) -> Vec<PeerEvent> {
+    let mut sequences: Vec<_> = sequences.into_iter().map(Vec::into_iter).collect();
+    let mut events = Vec::new();
+    for choice in choices {
+        sequences.retain(|sequence| !sequence.as_slice().is_empty());
+        if sequences.is_empty() {
+            break;
+        }
+        let index = choice.index(sequences.len());
+        events.extend(sequences[index].next());
+    }
+    events
+}
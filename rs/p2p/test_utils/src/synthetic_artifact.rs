@@ -0,0 +1,148 @@
+//! Synthetic artifacts for load and fuzz tests.
+//!
+//! A [`SyntheticArtifact`] carries its id and attribute in its first bytes,
+//! followed by a payload of arbitrary size, so that tests can control the size
+//! of the artifacts independently of their ids and attributes.
+//! [`SyntheticArtifactGenerator`] produces a reproducible stream of such
+//! artifacts.
+use std::ops::RangeInclusive;
+
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+// Bytes of the id and of the attribute.
+const HEADER_SIZE: usize = 16;
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct SyntheticArtifact(Vec<u8>);
+
+impl SyntheticArtifact {
+    /// Creates an artifact whose payload, i.e. the bytes after the id and the
+    /// attribute, has the given size.
+    pub fn new(id: u64, attribute: u64, payload_size: usize) -> Self {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + payload_size);
+        bytes.extend_from_slice(&id.to_le_bytes());
+        bytes.extend_from_slice(&attribute.to_le_bytes());
+        // The payload depends on the id so that artifacts with different ids
+        // differ beyond their header.
+        bytes.resize(HEADER_SIZE + payload_size, id as u8);
+        Self(bytes)
+    }
+
+    pub fn payload_size(&self) -> usize {
+        self.0.len() - HEADER_SIZE
+    }
+
+    /// The size of the encoded artifact.
+    pub fn size(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl From<SyntheticArtifact> for Vec<u8> {
+    fn from(value: SyntheticArtifact) -> Self {
+        value.0
+    }
+}
+
+impl From<Vec<u8>> for SyntheticArtifact {
+    fn from(mut value: Vec<u8>) -> Self {
+        // Decoding must not fail, so a truncated artifact is padded with
+        // zeros.
+        if value.len() < HEADER_SIZE {
+            value.resize(HEADER_SIZE, 0);
+        }
+        Self(value)
+    }
+}
+
+ic_types::impl_pb_artifact!(SyntheticArtifact {
+    name: "synthetic",
+    id: u64 = |artifact| u64::from_le_bytes(artifact.0[..8].try_into().unwrap()),
+    pb_id: u64,
+    pb_message: Vec<u8>,
+    attribute: u64 = |artifact| u64::from_le_bytes(artifact.0[8..HEADER_SIZE].try_into().unwrap()),
+    pb_attribute: u64,
+});
+
+/// How the ids of generated artifacts are chosen.
+#[derive(Clone, Debug)]
+pub enum IdPattern {
+    /// Consecutive ids starting at the given one, without collisions.
+    Sequential { start: u64 },
+    /// Consecutive ids that wrap around after `period` artifacts, so every
+    /// id is generated again after `period` artifacts.
+    Cyclic { period: u64 },
+    /// Ids drawn uniformly from the range, so collisions happen at random.
+    Random(RangeInclusive<u64>),
+}
+
+impl Default for IdPattern {
+    fn default() -> Self {
+        Self::Sequential { start: 0 }
+    }
+}
+
+/// Generates a reproducible stream of synthetic artifacts. The sizes,
+/// attributes and, depending on the [`IdPattern`], the ids are drawn from a
+/// generator seeded with the given seed.
+pub struct SyntheticArtifactGenerator {
+    rng: SmallRng,
+    payload_sizes: RangeInclusive<usize>,
+    attributes: Vec<u64>,
+    ids: IdPattern,
+    generated: u64,
+}
+
+impl SyntheticArtifactGenerator {
+    /// Creates a generator of artifacts with sequential ids, an empty payload
+    /// and attribute zero.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: SmallRng::seed_from_u64(seed),
+            payload_sizes: 0..=0,
+            attributes: vec![0],
+            ids: IdPattern::default(),
+            generated: 0,
+        }
+    }
+
+    /// Draws payload sizes uniformly from the given range.
+    pub fn with_payload_sizes(mut self, payload_sizes: RangeInclusive<usize>) -> Self {
+        self.payload_sizes = payload_sizes;
+        self
+    }
+
+    /// Draws attributes uniformly from the given values.
+    ///
+    /// Panics if `attributes` is empty.
+    pub fn with_attributes(mut self, attributes: Vec<u64>) -> Self {
+        assert!(!attributes.is_empty(), "No attributes to choose from.");
+        self.attributes = attributes;
+        self
+    }
+
+    pub fn with_ids(mut self, ids: IdPattern) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    fn next_id(&mut self) -> u64 {
+        match &self.ids {
+            IdPattern::Sequential { start } => start + self.generated,
+            IdPattern::Cyclic { period } => self.generated % (*period).max(1),
+            IdPattern::Random(range) => self.rng.gen_range(range.clone()),
+        }
+    }
+}
+
+impl Iterator for SyntheticArtifactGenerator {
+    type Item = SyntheticArtifact;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.next_id();
+        let attribute = self.attributes[self.rng.gen_range(0..self.attributes.len())];
+        let payload_size = self.rng.gen_range(self.payload_sizes.clone());
+        self.generated += 1;
+        Some(SyntheticArtifact::new(id, attribute, payload_size))
+    }
+}
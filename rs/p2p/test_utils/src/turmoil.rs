@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     fmt::Debug,
     future::Future,
     io::{self, IoSliceMut},
@@ -28,7 +29,7 @@ use ic_interfaces::{
 use ic_logger::ReplicaLogger;
 use ic_metrics::MetricsRegistry;
 use ic_quic_transport::SubnetTopology;
-use ic_quic_transport::{QuicTransport, Transport};
+use ic_quic_transport::{PeerMetadata, QuicTransport, Transport};
 use ic_state_manager::state_sync::types::StateSyncMessage;
 use ic_types::{artifact::UnvalidatedArtifactMutation, NodeId, RegistryVersion};
 use quinn::{self, udp::EcnCodepoint, AsyncUdpSocket, UdpPoller};
@@ -36,7 +37,7 @@ use tokio::{
     select,
     sync::{mpsc, oneshot, watch, Notify},
 };
-use turmoil::Sim;
+use turmoil::{Builder, Sim};
 
 struct CustomUdp {
     ip: IpAddr,
@@ -351,6 +352,7 @@ pub fn add_transport_to_sim<F>(
                 log.clone(),
                 tokio::runtime::Handle::current(),
                 metrics_registry,
+                ic_consensus_manager::ConsensusManagerConfig::default(),
             );
 
             let mut router = conn_checker_clone;
@@ -397,6 +399,7 @@ pub fn add_transport_to_sim<F>(
                 node_crypto_clone,
                 registry_client,
                 peer,
+                PeerMetadata::default(),
                 topology_watcher_clone.clone(),
                 Either::Right(custom_udp),
                 router.unwrap_or_default(),
@@ -412,6 +415,7 @@ pub fn add_transport_to_sim<F>(
                     transport.clone(),
                     state_sync_client_clone.unwrap().clone(),
                     state_sync_rx,
+                    ic_state_sync_manager::StateSyncConfig::default(),
                 );
             }
 
@@ -454,3 +458,172 @@ pub fn start_test_processor(
     );
     (jh, rx, sender)
 }
+
+/// Verifies that all active adverts sent by Node A are present in the pool of every peer.
+pub fn check_pools_equal(node_pool_map: &HashMap<NodeId, TestConsensus<U64Artifact>>) -> bool {
+    for (node1, pool1) in node_pool_map {
+        for (node2, pool2) in node_pool_map {
+            // Check that all adverts produced by 1 were received by 2.
+            if node2 != node1 {
+                // If other pool subset everything is fine
+                if !pool1.my_pool().is_subset(&pool2.peer_pool(node1)) {
+                    // It can be case that multiple peers advertised same id and it only got downloaded from a different peer.
+                    // In that case check that the id is contained in some other pool.
+                    for diff in pool1.my_pool().difference(&pool2.peer_pool(node1)) {
+                        let mut found = false;
+                        for n in node_pool_map.keys() {
+                            if n != node1 && pool2.peer_pool(n).contains(diff) {
+                                found |= true;
+                            }
+                        }
+                        if !found {
+                            return false;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    true
+}
+
+/// Runs the consensus managers of several nodes in a deterministic turmoil
+/// simulation. Time only advances while the simulation is stepped, so
+/// protocol-level properties can be asserted without real sockets or clocks.
+///
+/// Each node runs a [`TestConsensus`] pool behind a consensus manager and a
+/// QUIC transport over the simulated network. Nodes join and leave the subnet
+/// through the simulated registry, and links between nodes can be cut and
+/// repaired.
+pub struct ConsensusManagerSim {
+    sim: Sim<'static>,
+    log: ReplicaLogger,
+    exit_notify: Arc<Notify>,
+    peer_manager_cmd_sender: mpsc::UnboundedSender<PeerManagerAction>,
+    topology_watcher: watch::Receiver<SubnetTopology>,
+    registry_handle: RegistryConsensusHandle,
+    nodes: HashMap<NodeId, TestConsensus<U64Artifact>>,
+    // Registry version 1 is the initial one without nodes.
+    next_registry_version: u64,
+}
+
+impl ConsensusManagerSim {
+    pub fn new(log: ReplicaLogger) -> Self {
+        let mut sim = Builder::new()
+            .tick_duration(Duration::from_millis(100))
+            .simulation_duration(Duration::from_secs(60 * 60))
+            .build();
+        let exit_notify = Arc::new(Notify::new());
+        let (peer_manager_cmd_sender, topology_watcher, registry_handle) =
+            add_peer_manager_to_sim(&mut sim, exit_notify.clone(), log.clone());
+        Self {
+            sim,
+            log,
+            exit_notify,
+            peer_manager_cmd_sender,
+            topology_watcher,
+            registry_handle,
+            nodes: HashMap::new(),
+            next_registry_version: 2,
+        }
+    }
+
+    /// Starts a node whose artifacts have the given size and adds it to the
+    /// subnet. Returns its pool.
+    pub fn add_node(
+        &mut self,
+        node_id: NodeId,
+        msg_size: usize,
+        latency_sensitive: bool,
+    ) -> TestConsensus<U64Artifact> {
+        let pool = TestConsensus::new(self.log.clone(), node_id, msg_size, latency_sensitive);
+        add_transport_to_sim(
+            &mut self.sim,
+            self.log.clone(),
+            node_id,
+            self.registry_handle.clone(),
+            self.topology_watcher.clone(),
+            None,
+            None,
+            None,
+            Some(pool.clone()),
+            waiter_fut(),
+        );
+        self.nodes.insert(node_id, pool.clone());
+        self.update_topology(PeerManagerAction::Add((
+            node_id,
+            self.next_registry_version(),
+        )));
+        pool
+    }
+
+    /// Removes the node from the subnet. It keeps running, but its peers
+    /// disconnect from it.
+    pub fn remove_node(&mut self, node_id: NodeId) {
+        self.nodes.remove(&node_id);
+        self.update_topology(PeerManagerAction::Remove((
+            node_id,
+            self.next_registry_version(),
+        )));
+    }
+
+    /// Cuts the link between the two nodes.
+    pub fn partition(&mut self, a: NodeId, b: NodeId) {
+        self.sim.partition(a.to_string(), b.to_string());
+    }
+
+    /// Repairs the link between the two nodes.
+    pub fn repair(&mut self, a: NodeId, b: NodeId) {
+        self.sim.repair(a.to_string(), b.to_string());
+    }
+
+    /// Returns the pool of the given node.
+    pub fn pool(&self, node_id: &NodeId) -> &TestConsensus<U64Artifact> {
+        &self.nodes[node_id]
+    }
+
+    /// Returns true if every node received all artifacts of all the other
+    /// nodes in the subnet, see [`check_pools_equal`].
+    pub fn pools_converged(&self) -> bool {
+        check_pools_equal(&self.nodes)
+    }
+
+    /// Runs the simulation until the condition holds.
+    pub fn run_until<F>(&mut self, f: F) -> turmoil::Result
+    where
+        F: FnMut() -> bool,
+    {
+        wait_for(&mut self.sim, f)
+    }
+
+    /// Runs the simulation until the pools of all nodes converged.
+    pub fn run_until_converged(&mut self) -> turmoil::Result {
+        let nodes = self.nodes.clone();
+        wait_for(&mut self.sim, || check_pools_equal(&nodes))
+    }
+
+    /// Runs the simulation for the given duration of virtual time.
+    pub fn run_for(&mut self, duration: Duration) -> turmoil::Result {
+        run_simulation_for(&mut self.sim, duration)
+    }
+
+    /// Stops all nodes and runs the simulation to completion.
+    pub fn finish(mut self) -> turmoil::Result {
+        self.exit_notify.notify_waiters();
+        self.sim.run()
+    }
+
+    fn next_registry_version(&mut self) -> RegistryVersion {
+        let version = RegistryVersion::from(self.next_registry_version);
+        self.next_registry_version += 1;
+        version
+    }
+
+    fn update_topology(&mut self, action: PeerManagerAction) {
+        self.peer_manager_cmd_sender.send(action).unwrap();
+        self.registry_handle.registry_client.reload();
+        self.registry_handle
+            .registry_client
+            .update_to_latest_version();
+    }
+}
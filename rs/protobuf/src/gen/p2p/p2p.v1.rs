@@ -48,4 +48,8 @@ pub struct Advert {
     pub id: ::prost::alloc::vec::Vec<u8>,
     #[prost(bytes = "vec", tag = "2")]
     pub attribute: ::prost::alloc::vec::Vec<u8>,
+    /// SHA-256 digest of the encoded attribute. If set, the attribute is omitted and
+    /// must be fetched from the peer that sent the advert.
+    #[prost(bytes = "vec", tag = "3")]
+    pub attribute_digest: ::prost::alloc::vec::Vec<u8>,
 }
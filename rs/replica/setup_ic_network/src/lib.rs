@@ -13,13 +13,18 @@ use ic_artifact_pool::{
     idkg_pool::IDkgPoolImpl,
     ingress_pool::{IngressPoolImpl, IngressPrioritizer},
 };
-use ic_config::{artifact_pool::ArtifactPoolConfig, transport::TransportConfig};
+use ic_config::{
+    artifact_pool::ArtifactPoolConfig,
+    transport::{ConsensusManagerTunables, StateSyncTunables, TransportConfig},
+};
 use ic_consensus::{
     certification::{setup as certification_setup, CertificationCrypto},
     consensus::{dkg_key_manager::DkgKeyManager, setup as consensus_setup},
     dkg, ecdsa,
 };
-use ic_consensus_manager::ConsensusManagerBuilder;
+use ic_consensus_manager::{
+    ConsensusManagerBuilder, ConsensusManagerConfig, RetryBackoff, SendRateLimit,
+};
 use ic_consensus_utils::{
     crypto::ConsensusCrypto, membership::Membership, pool_reader::PoolReader,
 };
@@ -45,7 +50,7 @@ use ic_interfaces_registry::RegistryClient;
 use ic_interfaces_state_manager::{StateManager, StateReader};
 use ic_logger::{info, replica_logger::ReplicaLogger};
 use ic_metrics::MetricsRegistry;
-use ic_quic_transport::DummyUdpSocket;
+use ic_quic_transport::{DummyUdpSocket, PeerMetadata};
 use ic_registry_client_helpers::subnet::SubnetRegistry;
 use ic_replicated_state::ReplicatedState;
 use ic_state_manager::state_sync::types::StateSyncMessage;
@@ -56,17 +61,21 @@ use ic_types::{
     malicious_flags::MaliciousFlags,
     messages::SignedIngress,
     replica_config::ReplicaConfig,
-    Height, NodeId, SubnetId,
+    Height, NodeId, ReplicaVersion, SubnetId,
 };
 use std::{
     net::{IpAddr, SocketAddr},
+    num::{NonZeroU64, NonZeroUsize},
     str::FromStr,
     sync::{Arc, Mutex, RwLock},
+    time::Duration,
 };
 use tokio::sync::{mpsc::UnboundedSender, watch};
 use tower_http::trace::TraceLayer;
 
 pub const MAX_ADVERT_BUFFER: usize = 100_000;
+/// Burst of the consensus manager send rate limit if the replica config does not set one.
+const DEFAULT_SEND_RATE_LIMIT_BURST: Duration = Duration::from_secs(1);
 
 /// The collection of all artifact pools.
 struct ArtifactPools {
@@ -128,6 +137,7 @@ pub fn setup_consensus_and_p2p(
         log,
         metrics_registry,
         rt_handle,
+        consensus_manager_config(&transport_config.consensus_manager),
         node_id,
         subnet_id,
         artifact_pool_config,
@@ -184,6 +194,10 @@ pub fn setup_consensus_and_p2p(
         tls_config,
         registry_client.clone(),
         node_id,
+        PeerMetadata {
+            replica_version: Some(ReplicaVersion::default().to_string()),
+            capabilities: ic_consensus_manager::CAPABILITIES,
+        },
         topology_watcher.clone(),
         Either::<_, DummyUdpSocket>::Left(transport_addr),
         p2p_router,
@@ -196,6 +210,7 @@ pub fn setup_consensus_and_p2p(
         quic_transport.clone(),
         state_sync_client,
         state_sync_manager_rx,
+        state_sync_config(&transport_config.state_sync),
     );
 
     let _cancellation_token = p2p_consensus.run(quic_transport, topology_watcher);
@@ -203,6 +218,79 @@ pub fn setup_consensus_and_p2p(
     (ingress_pool, ingress_sender, join_handles)
 }
 
+/// Applies the tunables set in the replica config to the default consensus manager config.
+fn consensus_manager_config(tunables: &ConsensusManagerTunables) -> ConsensusManagerConfig {
+    let default = ConsensusManagerConfig::default();
+    let retry_backoff =
+        |default: RetryBackoff, initial_ms: Option<u64>, max_ms: Option<u64>| RetryBackoff {
+            initial_interval: initial_ms.map_or(default.initial_interval, Duration::from_millis),
+            max_interval: max_ms.map_or(default.max_interval, Duration::from_millis),
+            multiplier: default.multiplier,
+        };
+    let send_rate_limit = match (
+        tunables
+            .send_rate_limit_per_peer_bytes_per_second
+            .and_then(NonZeroU64::new),
+        tunables
+            .send_rate_limit_aggregate_bytes_per_second
+            .and_then(NonZeroU64::new),
+    ) {
+        (Some(per_peer_bytes_per_second), Some(aggregate_bytes_per_second)) => {
+            Some(SendRateLimit {
+                per_peer_bytes_per_second,
+                aggregate_bytes_per_second,
+                burst: tunables
+                    .send_rate_limit_burst_ms
+                    .map_or(DEFAULT_SEND_RATE_LIMIT_BURST, Duration::from_millis),
+            })
+        }
+        _ => default.send_rate_limit,
+    };
+    ConsensusManagerConfig {
+        adverts_from_peers_channel_capacity: tunables
+            .adverts_from_peers_channel_capacity
+            .and_then(NonZeroUsize::new)
+            .unwrap_or(default.adverts_from_peers_channel_capacity),
+        max_concurrent_downloads: tunables
+            .max_concurrent_downloads
+            .map_or(default.max_concurrent_downloads, NonZeroUsize::new),
+        advert_batch_size: tunables
+            .advert_batch_size
+            .and_then(NonZeroUsize::new)
+            .unwrap_or(default.advert_batch_size),
+        push_retry_backoff: retry_backoff(
+            default.push_retry_backoff,
+            tunables.push_retry_initial_interval_ms,
+            tunables.push_retry_max_interval_ms,
+        ),
+        download_retry_backoff: retry_backoff(
+            default.download_retry_backoff,
+            tunables.download_retry_initial_interval_ms,
+            tunables.download_retry_max_interval_ms,
+        ),
+        download_fallback_after_failures: tunables
+            .download_fallback_after_failures
+            .map_or(default.download_fallback_after_failures, NonZeroUsize::new),
+        send_rate_limit,
+    }
+}
+
+/// Applies the tunables set in the replica config to the default state sync config.
+fn state_sync_config(tunables: &StateSyncTunables) -> ic_state_sync_manager::StateSyncConfig {
+    let default = ic_state_sync_manager::StateSyncConfig::default();
+    ic_state_sync_manager::StateSyncConfig {
+        parallel_chunk_downloads_per_peer: tunables
+            .parallel_chunk_downloads_per_peer
+            .unwrap_or(default.parallel_chunk_downloads_per_peer),
+        max_parallel_chunk_downloads: tunables
+            .max_parallel_chunk_downloads
+            .or(default.max_parallel_chunk_downloads),
+        max_download_bandwidth: tunables
+            .max_download_bandwidth
+            .or(default.max_download_bandwidth),
+    }
+}
+
 /// The function creates the Consensus stack (including all Consensus clients)
 /// and starts the artifact manager event loop for each client.
 #[allow(clippy::too_many_arguments, clippy::type_complexity)]
@@ -210,6 +298,7 @@ fn start_consensus(
     log: &ReplicaLogger,
     metrics_registry: &MetricsRegistry,
     rt_handle: &tokio::runtime::Handle,
+    consensus_manager_config: ConsensusManagerConfig,
     node_id: NodeId,
     subnet_id: SubnetId,
     artifact_pool_config: ArtifactPoolConfig,
@@ -245,6 +334,7 @@ fn start_consensus(
             log.clone(),
             rt_handle.clone(),
             metrics_registry.clone(),
+            consensus_manager_config,
         );
 
     let artifact_pools = init_artifact_pools(
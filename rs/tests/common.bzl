@@ -21,9 +21,11 @@ DEPENDENCIES = [
     "//rs/certification",
     "//rs/config",
     "//rs/constants",
+    "//rs/crypto",
     "//rs/crypto/sha2",
     "//rs/crypto/test_utils/reproducible_rng",
     "//rs/crypto/tree_hash",
+    "//rs/crypto/utils/canister_threshold_sig",
     "//rs/crypto/utils/threshold_sig_der",
     "//rs/cup_explorer",
     "//rs/cycles_account_manager",
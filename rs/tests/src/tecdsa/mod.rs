@@ -2,9 +2,11 @@ use candid::{Encode, Principal};
 use canister_test::{Canister, Cycles};
 use ic_agent::AgentError;
 use ic_base_types::{NodeId, SubnetId};
-use ic_canister_client::Sender;
+use ic_canister_client::{Agent, Sender};
 use ic_config::subnet_config::ECDSA_SIGNATURE_FEE;
 use ic_constants::SMALL_APP_SUBNET_MAX_SIZE;
+use ic_crypto::get_master_public_key_from_transcript;
+use ic_crypto_utils_canister_threshold_sig::derive_threshold_public_key;
 use ic_management_canister_types::{
     DerivationPath, ECDSAPublicKeyArgs, ECDSAPublicKeyResponse, EcdsaCurve, EcdsaKeyId,
     MasterPublicKeyId, Payload, SchnorrAlgorithm, SchnorrKeyId, SchnorrPublicKeyArgs,
@@ -18,7 +20,11 @@ use ic_nns_test_utils::governance::submit_external_update_proposal;
 use ic_registry_subnet_features::DEFAULT_ECDSA_MAX_QUEUE_SIZE;
 use ic_registry_subnet_type::SubnetType;
 use ic_system_test_driver::{nns::vote_and_execute_proposal, util::MessageCanister};
-use ic_types::{PrincipalId, ReplicaVersion};
+use ic_types::{
+    consensus::CatchUpPackage,
+    crypto::canister_threshold_sig::{ExtendedDerivationPath, MasterPublicKey},
+    PrincipalId, ReplicaVersion,
+};
 use ic_types_test_utils::ids::subnet_test_id;
 use k256::ecdsa::{signature::hazmat::PrehashVerifier, Signature, VerifyingKey};
 use registry_canister::mutations::{
@@ -28,7 +34,8 @@ use registry_canister::mutations::{
     do_update_subnet::{ChainKeyConfig, KeyConfig as KeyConfigUpdate, UpdateSubnetPayload},
 };
 use slog::{debug, info, Logger};
-use std::time::Duration;
+use std::{collections::BTreeMap, time::Duration};
+use url::Url;
 
 pub mod tecdsa_add_nodes_test;
 pub mod tecdsa_complaint_test;
@@ -308,6 +315,101 @@ pub(crate) async fn get_public_key_with_logger(
     get_public_key_with_retries(key_id, msg_can, logger, /*retries=*/ 100).await
 }
 
+/// Returns the master public keys recorded in the latest CUP served by the node at `node_url`.
+pub(crate) async fn get_master_public_keys_from_cup(
+    node_url: &Url,
+) -> Result<BTreeMap<MasterPublicKeyId, MasterPublicKey>, String> {
+    let agent = Agent::new(node_url.clone(), Sender::Anonymous);
+    let Some(pb_cup) = agent.query_cup_endpoint(None).await? else {
+        return Ok(BTreeMap::new());
+    };
+    let cup = CatchUpPackage::try_from(&pb_cup)
+        .map_err(|err| format!("Failed to deserialize CUP: {:?}", err))?;
+    let Some(idkg) = cup.content.block.get_value().payload.as_ref().as_ecdsa() else {
+        return Ok(BTreeMap::new());
+    };
+
+    let mut public_keys = BTreeMap::new();
+    for (key_id, key_transcript) in &idkg.key_transcripts {
+        let Some(transcript) = key_transcript
+            .current
+            .as_ref()
+            .and_then(|transcript_ref| idkg.idkg_transcripts.get(&transcript_ref.transcript_id()))
+        else {
+            continue;
+        };
+        let public_key = get_master_public_key_from_transcript(transcript).map_err(|err| {
+            format!(
+                "Failed to get the master public key for {}: {:?}",
+                key_id, err
+            )
+        })?;
+        public_keys.insert(key_id.clone(), public_key);
+    }
+    Ok(public_keys)
+}
+
+/// Asserts that the public keys returned by `ecdsa_public_key`/`schnorr_public_key` to the
+/// message canister are the keys derived from the master public keys in the subnet's CUP.
+/// Waits until the CUP served by the node at `node_url` contains all of the given keys.
+pub(crate) async fn assert_public_keys_match_cup(
+    key_ids: &[MasterPublicKeyId],
+    node_url: &Url,
+    msg_can: &MessageCanister<'_>,
+    logger: &Logger,
+) {
+    let mut count = 0;
+    let master_public_keys = loop {
+        match get_master_public_keys_from_cup(node_url).await {
+            Ok(keys) if key_ids.iter().all(|key_id| keys.contains_key(key_id)) => break keys,
+            Ok(keys) => debug!(
+                logger,
+                "CUP contains master public keys for {:?}. Trying again in 2 seconds...",
+                keys.keys().collect::<Vec<_>>()
+            ),
+            Err(err) => debug!(
+                logger,
+                "Failed to read the master public keys from the CUP: {}. Trying again in 2 seconds...",
+                err
+            ),
+        }
+        count += 1;
+        assert!(
+            count < 100,
+            "CUP at {} does not contain all keys of {:?}",
+            node_url,
+            key_ids
+        );
+        tokio::time::sleep(Duration::from_secs(2)).await;
+    };
+
+    let caller = PrincipalId::from(msg_can.canister_id());
+    for key_id in key_ids {
+        let expected_public_key = derive_threshold_public_key(
+            &master_public_keys[key_id],
+            &ExtendedDerivationPath {
+                caller,
+                derivation_path: vec![],
+            },
+        )
+        .expect("Failed to derive the public key from the CUP master public key")
+        .public_key;
+
+        let public_key = get_public_key_with_logger(key_id, msg_can, logger)
+            .await
+            .expect("Failed to get the public key");
+        assert_eq!(
+            public_key, expected_public_key,
+            "Public key for {} differs from the key in the CUP",
+            key_id
+        );
+        info!(
+            logger,
+            "Public key for {} matches the key in the CUP", key_id
+        );
+    }
+}
+
 pub(crate) async fn execute_update_subnet_proposal(
     governance: &Canister<'_>,
     proposal_payload: UpdateSubnetPayload,
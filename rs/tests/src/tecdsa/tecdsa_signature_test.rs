@@ -18,9 +18,10 @@ use std::collections::{BTreeMap, HashSet};
 use std::time::Duration;
 
 use crate::tecdsa::{
-    create_new_subnet_with_keys, empty_subnet_update, enable_chain_key_signing,
-    execute_update_subnet_proposal, get_public_key_with_retries, make_bip340_key_id,
-    make_ecdsa_key_id, make_eddsa_key_id, scale_cycles, DKG_INTERVAL, NUMBER_OF_NODES,
+    assert_public_keys_match_cup, create_new_subnet_with_keys, empty_subnet_update,
+    enable_chain_key_signing, execute_update_subnet_proposal, get_public_key_with_retries,
+    make_bip340_key_id, make_ecdsa_key_id, make_eddsa_key_id, scale_cycles, DKG_INTERVAL,
+    NUMBER_OF_NODES,
 };
 use anyhow::bail;
 use canister_test::{Canister, Cycles};
@@ -167,6 +168,7 @@ pub fn test_threshold_ecdsa_signature_same_subnet(env: TestEnv) {
                 .await
                 .expect("Should successfully create and verify the signature");
         }
+        assert_public_keys_match_cup(&key_ids, &app_node.get_public_url(), &msg_can, &log).await;
     });
 }
 
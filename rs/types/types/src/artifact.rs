@@ -55,6 +55,101 @@ pub trait PbArtifact: IdentifiableArtifact + Send + Sized + 'static {
         + Default;
     /// Protobuf to rust conversion error
     type PbAttributeError: std::error::Error + Into<ProxyDecodeError>;
+
+    /// Encoded attributes larger than this number of bytes are advertised as a digest
+    /// and fetched on demand by the receivers. If `None`, attributes are always sent
+    /// with the advert.
+    const COMPACT_ATTRIBUTE_THRESHOLD: Option<usize> = None;
+}
+
+/// Returns true if `name` can be used as [`IdentifiableArtifact::NAME`], i.e. it is
+/// non-empty and consists of ASCII letters only. The name is used as URI prefix
+/// by the consensus manager.
+pub const fn is_valid_artifact_name(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    if bytes.is_empty() {
+        return false;
+    }
+    let mut i = 0;
+    while i < bytes.len() {
+        if !bytes[i].is_ascii_alphabetic() {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Implements [`IdentifiableArtifact`] and [`PbArtifact`] for an artifact type.
+///
+/// The id and the attribute are extracted with the given expressions, in which the
+/// given identifier is bound to `&self`. The conversion error types are the ones of the
+/// `TryFrom` implementations of the rust types from the protobuf types. If no attribute
+/// is given, the attribute is `()`. The name is checked at compile time with
+/// [`is_valid_artifact_name`].
+///
+/// ```ignore
+/// impl_pb_artifact!(U64Artifact {
+///     name: "artifact",
+///     id: u64 = |artifact| u64::from_le_bytes(artifact.0[..8].try_into().unwrap()),
+///     pb_id: u64,
+///     pb_message: Vec<u8>,
+/// });
+/// ```
+#[macro_export]
+macro_rules! impl_pb_artifact {
+    ($artifact:ty {
+        name: $name:literal,
+        id: $id:ty = |$id_self:ident| $id_expr:expr,
+        pb_id: $pb_id:ty,
+        pb_message: $pb_message:ty,
+        attribute: $attribute:ty = |$attribute_self:ident| $attribute_expr:expr,
+        pb_attribute: $pb_attribute:ty $(,)?
+    }) => {
+        const _: () = assert!(
+            $crate::artifact::is_valid_artifact_name($name),
+            "Artifact names must consist of ASCII letters only"
+        );
+
+        impl $crate::artifact::IdentifiableArtifact for $artifact {
+            const NAME: &'static str = $name;
+            type Id = $id;
+            type Attribute = $attribute;
+            fn id(&self) -> Self::Id {
+                let $id_self = self;
+                $id_expr
+            }
+            #[allow(clippy::unused_unit)]
+            fn attribute(&self) -> Self::Attribute {
+                let $attribute_self = self;
+                $attribute_expr
+            }
+        }
+
+        impl $crate::artifact::PbArtifact for $artifact {
+            type PbId = $pb_id;
+            type PbIdError = <$pb_id as ::std::convert::TryInto<$id>>::Error;
+            type PbMessage = $pb_message;
+            type PbMessageError = <$pb_message as ::std::convert::TryInto<$artifact>>::Error;
+            type PbAttribute = $pb_attribute;
+            type PbAttributeError = <$pb_attribute as ::std::convert::TryInto<$attribute>>::Error;
+        }
+    };
+    ($artifact:ty {
+        name: $name:literal,
+        id: $id:ty = |$id_self:ident| $id_expr:expr,
+        pb_id: $pb_id:ty,
+        pb_message: $pb_message:ty $(,)?
+    }) => {
+        $crate::impl_pb_artifact!($artifact {
+            name: $name,
+            id: $id = |$id_self| $id_expr,
+            pb_id: $pb_id,
+            pb_message: $pb_message,
+            attribute: () = |_artifact| (),
+            pb_attribute: (),
+        });
+    };
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -261,3 +356,49 @@ pub type IDkgMessageId = IDkgArtifactId;
 // CanisterHttp artifacts
 
 pub type CanisterHttpResponseId = CanisterHttpResponseShare;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct TestArtifact(Vec<u8>);
+
+    impl From<TestArtifact> for Vec<u8> {
+        fn from(value: TestArtifact) -> Self {
+            value.0
+        }
+    }
+
+    impl From<Vec<u8>> for TestArtifact {
+        fn from(value: Vec<u8>) -> Self {
+            Self(value)
+        }
+    }
+
+    impl_pb_artifact!(TestArtifact {
+        name: "test",
+        id: u64 = |artifact| artifact.0.len() as u64,
+        pb_id: u64,
+        pb_message: Vec<u8>,
+        attribute: u32 = |artifact| artifact.0[0].into(),
+        pb_attribute: u32,
+    });
+
+    #[test]
+    fn artifact_names_are_alphabetic() {
+        assert!(is_valid_artifact_name("consensus"));
+        assert!(is_valid_artifact_name("canisterhttp"));
+        assert!(!is_valid_artifact_name(""));
+        assert!(!is_valid_artifact_name("state_sync"));
+        assert!(!is_valid_artifact_name("dkg/rpc"));
+    }
+
+    #[test]
+    fn impl_pb_artifact_extracts_id_and_attribute() {
+        let artifact = TestArtifact(vec![7, 0, 0]);
+        assert_eq!(TestArtifact::NAME, "test");
+        assert_eq!(artifact.id(), 3);
+        assert_eq!(artifact.attribute(), 7);
+    }
+}